@@ -1,9 +1,12 @@
 use serde::{Deserialize, Serialize};
 
+use crate::decode::{check_chunk_position, check_len, Validate};
+use crate::relay;
+
 /// The version constant. Increased by 100 every minor client version, and by 10000 every major
 /// version. eg. 200 is 0.2.0, 10000 is 1.0.0, 10203 is 1.2.3.
 /// If two versions' hundreds places differ, the versions are incompatible.
-pub const PROTOCOL_VERSION: u32 = 400;
+pub const PROTOCOL_VERSION: u32 = 700;
 
 pub fn versions_compatible(v1: u32, v2: u32) -> bool {
    v1 / 100 == v2 / 100
@@ -13,6 +16,318 @@ pub fn compatible_with(v: u32) -> bool {
    versions_compatible(PROTOCOL_VERSION, v)
 }
 
+/// The maximum length of a nickname, room name, or tool name, in bytes.
+pub const MAX_NAME_LEN: usize = 256;
+
+/// The maximum length of a room description, in bytes.
+pub const MAX_DESCRIPTION_LEN: usize = 1024;
+
+/// The maximum number of chunk positions in a single packet.
+pub const MAX_CHUNK_POSITIONS: usize = 1 << 18;
+
+/// The maximum number of locked regions.
+pub const MAX_LOCKED_REGIONS: usize = 1024;
+
+/// The maximum number of colors in a room's palette.
+pub const MAX_PALETTE_LEN: usize = 256;
+
+/// The maximum number of sticky notes on a canvas.
+pub const MAX_NOTES: usize = 1024;
+
+/// The maximum length of a sticky note's text, in bytes.
+pub const MAX_NOTE_TEXT_LEN: usize = 512;
+
+/// The ID of a sticky note. The first element is the ID of the peer that created the note (or 0 if
+/// it was created offline or loaded from a file), and the second one counts up from there.
+pub type NoteId = (u64, u32);
+
+/// A sticky note. Notes are kept separately from the canvas's pixels, and are drawn above them.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct StickyNote {
+   /// The position of the note's top-left corner, in pixel coordinates.
+   pub position: (i32, i32),
+   pub color: (u8, u8, u8),
+   pub text: String,
+}
+
+/// The maximum number of floating images on a canvas.
+pub const MAX_FLOATING_IMAGES: usize = 64;
+
+/// The maximum width and height of a floating image, both as stored and as placed on the canvas.
+pub const MAX_FLOATING_IMAGE_SIZE: u32 = 4096;
+
+/// The maximum size of a floating image's encoded data, in bytes. This leaves room for the rest
+/// of the `AddImage` packet and the relay packet carrying it, so that it fits within
+/// [`relay::MAX_PACKET_SIZE`].
+pub const MAX_FLOATING_IMAGE_DATA_LEN: usize = relay::MAX_PACKET_SIZE as usize - 64 * 1024;
+
+/// The ID of a floating image, made up the same way as a [`NoteId`].
+pub type FloatingImageId = (u64, u32);
+
+/// Where a floating image is placed on the canvas. The image is stretched to fill the placement.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ImagePlacement {
+   /// The position of the image's top-left corner, in pixel coordinates.
+   pub position: (i32, i32),
+   pub size: (u32, u32),
+}
+
+/// A pattern drawn beneath the canvas's chunks. Backgrounds aren't part of the canvas's pixels.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Background {
+   #[default]
+   Blank,
+   /// Graph paper, with a darker line every few lines.
+   Graph,
+   Dots,
+   Lined,
+   /// A grid of equilateral triangles.
+   Isometric,
+}
+
+impl Background {
+   /// All the backgrounds, in the order they're cycled through.
+   pub const ALL: [Self; 5] = [
+      Self::Blank,
+      Self::Graph,
+      Self::Dots,
+      Self::Lined,
+      Self::Isometric,
+   ];
+
+   /// Returns the name of the background, used as its translation key.
+   pub fn name(self) -> &'static str {
+      match self {
+         Self::Blank => "blank",
+         Self::Graph => "graph",
+         Self::Dots => "dots",
+         Self::Lined => "lined",
+         Self::Isometric => "isometric",
+      }
+   }
+
+   /// Returns the background following this one, wrapping around to the first one.
+   pub fn next(self) -> Self {
+      let index = Self::ALL.iter().position(|&background| background == self).unwrap();
+      Self::ALL[(index + 1) % Self::ALL.len()]
+   }
+
+   pub fn is_blank(&self) -> bool {
+      *self == Self::Blank
+   }
+}
+
+/// The maximum number of vector strokes kept on a canvas.
+pub const MAX_VECTOR_STROKES: usize = 1 << 16;
+
+/// The maximum number of points in a single vector stroke.
+pub const MAX_VECTOR_STROKE_POINTS: usize = 4096;
+
+/// How many parts a pixel is divided into in vector stroke coordinates.
+pub const VECTOR_SUBPIXELS: i32 = 16;
+
+/// The maximum number of pages in a room.
+pub const MAX_PAGES: usize = 64;
+
+/// The maximum number of layers on a canvas.
+pub const MAX_LAYERS: usize = 32;
+
+/// The length of a chunk digest, in bytes. A digest holds the average color of each cell in an
+/// 8x8 grid laid over the chunk, as RGBA.
+pub const CHUNK_DIGEST_LEN: usize = 8 * 8 * 4;
+
+/// The maximum width and height of a bounded canvas, in pixels.
+pub const MAX_CANVAS_SIZE: u32 = 1 << 16;
+
+/// The maximum number of viewport bookmarks, one for each of the number keys 1 through 9.
+pub const MAX_BOOKMARKS: usize = 9;
+
+/// The zoom levels a bookmark can be saved with, and a shared viewport can be at.
+pub const BOOKMARK_ZOOM_LEVELS: std::ops::RangeInclusive<i32> = -8..=20;
+
+/// A named viewport position, saved under one of the number keys.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Bookmark {
+   /// The number key the bookmark is saved under, from 1 to [`MAX_BOOKMARKS`].
+   pub slot: u8,
+   pub name: String,
+   /// The point in the middle of the viewport, in pixel coordinates.
+   pub pan: (i32, i32),
+   /// The zoom level, rounded to a whole step of the mouse wheel.
+   pub zoom_level: i32,
+}
+
+/// The format chunk images are encoded in before they're sent over the network.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChunkFormat {
+   /// Lossless PNG for every chunk.
+   Png,
+   /// PNG, or lossy WebP for chunks whose PNG is too large.
+   #[default]
+   Auto,
+   /// Lossy WebP for every chunk.
+   WebP,
+   /// Lossless QOI for every chunk. Larger than PNG, but a lot quicker to encode and decode.
+   Qoi,
+}
+
+impl ChunkFormat {
+   /// All the formats, in the order they're cycled through.
+   pub const ALL: [Self; 4] = [Self::Png, Self::Qoi, Self::Auto, Self::WebP];
+
+   /// Returns the name of the format, used as its translation key.
+   pub fn name(self) -> &'static str {
+      match self {
+         Self::Png => "png",
+         Self::Auto => "auto",
+         Self::WebP => "webp",
+         Self::Qoi => "qoi",
+      }
+   }
+
+   /// Returns the format following this one, wrapping around to the first one.
+   pub fn next(self) -> Self {
+      let index = Self::ALL.iter().position(|&format| format == self).unwrap();
+      Self::ALL[(index + 1) % Self::ALL.len()]
+   }
+}
+
+/// The highest quality a lossy chunk image can be encoded at.
+pub const MAX_CHUNK_QUALITY: u8 = 100;
+
+/// The smallest number of bytes of chunk images a room can send in a single packet.
+pub const MIN_CHUNK_BATCH_SIZE: u32 = 16 * 1024;
+
+/// The largest number of bytes of chunk images a room can send in a single packet.
+pub const MAX_CHUNK_BATCH_SIZE: u32 = 1024 * 1024;
+
+/// How chunk images are encoded and batched before they're sent over the network. Set by the host
+/// for the whole room, such that rooms with little bandwidth can trade image quality for speed.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct ChunkEncoding {
+   pub format: ChunkFormat,
+   /// The quality of lossy images, from 0 to [`MAX_CHUNK_QUALITY`].
+   pub quality: u8,
+   /// How many bytes of chunk images can be sent in a single packet. A chunk that's larger than
+   /// this on its own is still sent, in a packet of its own.
+   pub max_batch_size: u32,
+}
+
+impl Default for ChunkEncoding {
+   fn default() -> Self {
+      Self {
+         format: ChunkFormat::Auto,
+         quality: 80,
+         max_batch_size: 128 * 1024,
+      }
+   }
+}
+
+/// Optional features of a client, which the others have to know about before relying on them.
+///
+/// Clients that never announce their capabilities are assumed to have none of them, and are sent
+/// PNG chunk images regardless of the room's chunk format.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, Default, PartialEq, Eq)]
+pub struct Capabilities {
+   /// Whether the client can decode WebP chunk images.
+   pub webp_chunks: bool,
+   /// Whether the client can decode QOI chunk images.
+   pub qoi_chunks: bool,
+}
+
+impl Capabilities {
+   /// The capabilities of this version of the client.
+   pub const CURRENT: Self = Self {
+      webp_chunks: true,
+      qoi_chunks: true,
+   };
+
+   /// Returns the capabilities shared by both sets.
+   pub fn intersection(self, other: Self) -> Self {
+      Self {
+         webp_chunks: self.webp_chunks && other.webp_chunks,
+         qoi_chunks: self.qoi_chunks && other.qoi_chunks,
+      }
+   }
+}
+
+/// The ID of a vector stroke, made up the same way as a [`NoteId`].
+pub type VectorStrokeId = (u64, u32);
+
+/// A brush stroke kept as a path, for rooms that have vector strokes turned on.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct VectorStroke {
+   /// The color of the stroke. Strokes with an alpha of zero erase.
+   pub color: (u8, u8, u8, u8),
+   pub thickness: u8,
+   /// The hardness of the brush, in percent.
+   pub hardness: u8,
+   /// The points the stroke passes through, in [`VECTOR_SUBPIXELS`] of a pixel.
+   pub points: Vec<(i32, i32)>,
+   /// The width of the stroke at each of its points, for strokes drawn with varying stylus
+   /// pressure. Empty if the stroke is `thickness` wide all along.
+   pub widths: Vec<u8>,
+}
+
+/// A layer of the canvas. Layers are stacked in order, starting with the bottom one, and every
+/// page has its own chunks for each layer.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct Layer {
+   pub name: String,
+   /// Whether the layer is shown. Hidden layers can still be drawn to.
+   pub visible: bool,
+   /// The opacity the layer is shown with, from 0 (transparent) to 255 (opaque).
+   pub opacity: u8,
+}
+
+/// The maximum length of a file path sent in an admin command or response, in bytes.
+pub const MAX_PATH_LEN: usize = 4096;
+
+/// A command a room admin sends to the host, to manage a room hosted on a server without having
+/// to log into it.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum AdminCommand {
+   /// Save the canvas to the host's configured path.
+   Save,
+   /// Replace the canvas with the one in the given file on the host's machine. Later saves go to
+   /// that file.
+   Load(String),
+   /// Report the state of the canvas.
+   Status,
+}
+
+/// The host's response to an [`AdminCommand`].
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub enum AdminResponse {
+   /// The password was wrong, or the host doesn't accept admin commands at all.
+   Denied,
+   /// The command could not be carried out, for the given reason.
+   Failed(String),
+   /// The canvas was saved to the given path.
+   Saved(String),
+   /// The canvas was loaded from the given path.
+   Loaded(String),
+   /// The state of the canvas, in response to [`AdminCommand::Status`].
+   Status(AdminStatus),
+}
+
+/// The state of the canvas, as reported to an admin.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct AdminStatus {
+   /// The path the canvas is saved to, if the host has one.
+   pub path: Option<String>,
+   /// The number of chunks on the host's current page.
+   pub chunks: u32,
+   /// The number of those chunks that changed since the canvas was last saved or loaded.
+   pub unsaved_chunks: u32,
+   /// The number of people in the room, not counting the host.
+   pub people: u32,
+   /// The number of pages in the room.
+   pub pages: u32,
+}
+
 /// A client communication packet.
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum Packet {
@@ -50,14 +365,16 @@ pub enum Packet {
    /// Version packet. This is sent as part of a response to Hello.
    Version(u32),
 
-   /// Sent by the host to a client upon connection.
-   ChunkPositions(Vec<(i32, i32)>),
+   /// Sent by the host to a client upon connection, once for every layer.
+   ///
+   /// Like every other chunk packet, this carries the index of the layer the chunks belong to.
+   ChunkPositions(u32, Vec<(i32, i32)>),
 
    /// Request from the client to download chunks.
-   GetChunks(Vec<(i32, i32)>),
+   GetChunks(u32, Vec<(i32, i32)>),
 
    /// Response from the other peer with the chunks encoded as PNG images.
-   Chunks(Vec<((i32, i32), Vec<u8>)>),
+   Chunks(u32, Vec<((i32, i32), Vec<u8>)>),
    /* ---
     * VERSION 0.3.0 (protocol 300)
     * ---
@@ -70,4 +387,421 @@ pub enum Packet {
     * Cursor and Stroke packets were removed in favor of the generic Tool packet.
     * Each tool is responsible for decoding its own packets now.
     */
+   // ---
+   // VERSION 0.5.0 (protocol 500)
+   // ---
+
+   //
+   // Moderation
+   //
+   /// Sent by the host to let clients know which regions of the canvas are locked.
+   ///
+   /// Each region is a rectangle of chunks, given as its top-left and bottom-right chunk
+   /// positions (both inclusive). The packet always carries the full list of locked regions.
+   LockedRegions(Vec<((i32, i32), (i32, i32))>),
+
+   /// Sent by the host to pause or resume all modifications to the canvas, room-wide.
+   Paused(bool),
+
+   //
+   // Room information
+   //
+   /// Sent by the host to a client upon connection, carrying the name and description of the
+   /// room. Either may be empty.
+   RoomInfo { name: String, description: String },
+
+   /// Sent by the host to share the room's color palette, which is shown to everyone alongside
+   /// their own palette. The packet always carries the full palette; an empty one means the room
+   /// has no palette.
+   RoomPalette(Vec<(u8, u8, u8)>),
+
+   //
+   // History
+   //
+   /// Sent to the host after finishing an operation, such as a brush stroke or a paste. Everything
+   /// the sender drew since their previous commit can then be undone as a whole.
+   CommitOperation,
+
+   /// Asks the host to undo the sender's most recent committed operation. The host sends the
+   /// affected chunks out to everyone once the operation is undone.
+   Undo,
+
+   //
+   // Collaboration
+   //
+   /// Points everyone's attention to a location on the canvas, given in pixel coordinates.
+   Ping((i32, i32)),
+
+   /// Adds a sticky note, or replaces the one with the same ID. Sent to everyone whenever a note is
+   /// created, edited, or moved.
+   SetNote(NoteId, StickyNote),
+
+   /// Removes the sticky note with the given ID.
+   RemoveNote(NoteId),
+
+   /// Sent by the host to a client upon connection, carrying all the sticky notes on the canvas.
+   Notes(Vec<(NoteId, StickyNote)>),
+
+   /// Adds a floating image: a pasted image that stays apart from the canvas's pixels until
+   /// someone flattens it. The data is a PNG image.
+   ///
+   /// Also sent by the host to a client upon connection, once for every floating image.
+   AddImage(FloatingImageId, ImagePlacement, Vec<u8>),
+
+   /// Moves or resizes a floating image.
+   PlaceImage(FloatingImageId, ImagePlacement),
+
+   /// Removes a floating image without drawing it onto the canvas.
+   RemoveImage(FloatingImageId),
+
+   /// Draws a floating image onto the canvas at its current placement, and removes it.
+   FlattenImage(FloatingImageId),
+
+   /// Sent by the host to turn vector strokes on or off, room-wide. While they're on, everyone
+   /// keeps brush strokes as paths in addition to drawing them into chunks.
+   VectorMode(bool),
+
+   /// Sent by the host to a client upon connection, carrying the vector strokes drawn so far, oldest
+   /// first.
+   VectorStrokes(Vec<(VectorStrokeId, VectorStroke)>),
+
+   /// Sent by the host after undoing an operation, to remove the vector strokes that were part of
+   /// it.
+   RemoveVectorStrokes(Vec<VectorStrokeId>),
+
+   /// Sent by the host to fix the canvas to the given width and height in pixels, centered on the
+   /// origin, or to make it infinite again. Nothing can be drawn outside of the bounds.
+   ///
+   /// Also sent by the host to a client upon connection, if the canvas is bounded.
+   CanvasBounds(Option<(u32, u32)>),
+
+   //
+   // Pages
+   //
+   /// Sent by the host to let everyone know the names of the room's pages. The packet always
+   /// carries the full list. Rooms start out with a single page, and each page has its own chunks.
+   Pages(Vec<String>),
+
+   /// Notifies that a different page was selected. Chunk packets exchanged with the sender
+   /// concern the page they selected last, so the host responds by sending them the new page's
+   /// chunk positions.
+   SelectPage(u32),
+
+   /// Sent by the host to change the pattern drawn beneath the canvas, room-wide.
+   ///
+   /// Also sent by the host to a client upon connection, if the canvas has a background.
+   Background(Background),
+
+   /// Sent by the host after committing an operation, with digests of the chunks it changed.
+   /// Everyone else applies operations by themselves, so chunk images only need to be downloaded
+   /// again if a digest doesn't match what they have.
+   ChunkDigests(u32, Vec<((i32, i32), Vec<u8>)>),
+
+   //
+   // Administration
+   //
+   /// Sent by an admin to the host. The host only carries out the command if it was started with
+   /// an admin password, and the password matches it.
+   Admin {
+      password: String,
+      command: AdminCommand,
+   },
+
+   /// Sent by the host in response to an admin command.
+   AdminResponse(AdminResponse),
+
+   //
+   // Network settings
+   //
+   /// Sent by the host to change how everyone encodes chunk images, room-wide.
+   ///
+   /// Also sent by the host to a client upon connection, if the room doesn't use the defaults.
+   ChunkEncoding(ChunkEncoding),
+
+   //
+   // History, continued
+   //
+   /// Asks the host to redo the sender's most recently undone operation. Like with [`Packet::Undo`],
+   /// the host sends the affected chunks out to everyone once the operation is redone.
+   ///
+   /// Operations can only be redone until their author commits a new one.
+   Redo,
+
+   //
+   // Layers
+   //
+   /// Sent by the host to let everyone know the room's layers, bottom one first. The packet always
+   /// carries the full list. Rooms start out with a single layer.
+   ///
+   /// Also sent by the host to a client upon connection, unless the room still only has the single
+   /// layer it started out with.
+   Layers(Vec<Layer>),
+
+   /// Notifies that a different layer was selected. Everything the sender draws from then on goes
+   /// onto that layer.
+   SelectLayer(u32),
+
+   //
+   // Network settings, continued
+   //
+   /// Lets the receiver know about the sender's capabilities. Sent along with [`Packet::Hello`],
+   /// and along with [`Packet::Version`] in response to it.
+   ///
+   /// This is a packet of its own rather than part of [`Packet::Version`], such that clients with
+   /// an incompatible version can still decode the version and tell the user what's wrong.
+   Capabilities(Capabilities),
+
+   //
+   // Collaboration, continued
+   //
+   /// Sent by the host to share their viewport bookmarks with the room, so that everyone can jump
+   /// to them. The packet always carries the full list; an empty one means nothing is shared.
+   ///
+   /// Also sent by the host to a client upon connection, if bookmarks are shared.
+   Bookmarks(Vec<Bookmark>),
+
+   //
+   // Network settings, continued
+   //
+   /// Sent periodically to everyone in the room, to measure the round-trip time to each of them.
+   /// The number identifies the probe, and is sent back unchanged in a [`Packet::LatencyReply`].
+   LatencyProbe(u32),
+
+   /// Sent in response to a [`Packet::LatencyProbe`], to its sender only.
+   LatencyReply(u32),
+
+   //
+   // Collaboration, continued
+   //
+   /// Lets everyone know where the sender's viewport is, so that they can follow it. Sent at a low
+   /// rate while the viewport moves, and to people who join the room.
+   Viewport {
+      /// The point in the middle of the viewport, in pixel coordinates.
+      pan: (i32, i32),
+      /// The zoom level, rounded to a whole step of the mouse wheel.
+      zoom_level: i32,
+   },
+}
+
+impl Validate for Packet {
+   fn validate(&self) -> Result<(), &'static str> {
+      match self {
+         Self::Hello(nickname) | Self::HiThere(nickname) => {
+            check_len(nickname.len(), MAX_NAME_LEN, "nickname is too long")
+         }
+         Self::Reserved1
+         | Self::Version(_)
+         | Self::Paused(_)
+         | Self::CommitOperation
+         | Self::Undo
+         | Self::Redo
+         | Self::Ping(_)
+         | Self::RemoveNote(_)
+         | Self::RemoveImage(_)
+         | Self::FlattenImage(_)
+         | Self::VectorMode(_)
+         | Self::Background(_)
+         | Self::Capabilities(_)
+         | Self::LatencyProbe(_)
+         | Self::LatencyReply(_) => Ok(()),
+         Self::Tool(name, _) | Self::SelectTool(name) => {
+            check_len(name.len(), MAX_NAME_LEN, "tool name is too long")
+         }
+         Self::ChunkPositions(layer, positions) | Self::GetChunks(layer, positions) => {
+            check_layer(*layer)?;
+            check_len(
+               positions.len(),
+               MAX_CHUNK_POSITIONS,
+               "too many chunk positions",
+            )?;
+            positions.iter().try_for_each(|&position| check_chunk_position(position))
+         }
+         Self::Chunks(layer, chunks) => {
+            check_layer(*layer)?;
+            check_len(chunks.len(), MAX_CHUNK_POSITIONS, "too many chunks")?;
+            chunks.iter().try_for_each(|&(position, _)| check_chunk_position(position))
+         }
+         Self::LockedRegions(regions) => {
+            check_len(regions.len(), MAX_LOCKED_REGIONS, "too many locked regions")?;
+            regions.iter().try_for_each(|&(top_left, bottom_right)| {
+               check_chunk_position(top_left)?;
+               check_chunk_position(bottom_right)
+            })
+         }
+         Self::RoomInfo { name, description } => {
+            check_len(name.len(), MAX_NAME_LEN, "room name is too long")?;
+            check_len(
+               description.len(),
+               MAX_DESCRIPTION_LEN,
+               "room description is too long",
+            )
+         }
+         Self::RoomPalette(colors) => {
+            check_len(colors.len(), MAX_PALETTE_LEN, "room palette is too long")
+         }
+         Self::Bookmarks(bookmarks) => {
+            check_len(bookmarks.len(), MAX_BOOKMARKS, "too many bookmarks")?;
+            bookmarks.iter().try_for_each(|bookmark| bookmark.validate())
+         }
+         Self::Viewport { zoom_level, .. } => {
+            if BOOKMARK_ZOOM_LEVELS.contains(zoom_level) {
+               Ok(())
+            } else {
+               Err("viewport zoom level is out of range")
+            }
+         }
+         Self::SetNote(_, note) => note.validate(),
+         Self::Notes(notes) => {
+            check_len(notes.len(), MAX_NOTES, "too many notes")?;
+            notes.iter().try_for_each(|(_, note)| note.validate())
+         }
+         Self::AddImage(_, placement, data) => {
+            check_len(
+               data.len(),
+               MAX_FLOATING_IMAGE_DATA_LEN,
+               "floating image data is too large",
+            )?;
+            placement.validate()
+         }
+         Self::PlaceImage(_, placement) => placement.validate(),
+         Self::VectorStrokes(strokes) => {
+            check_len(strokes.len(), MAX_VECTOR_STROKES, "too many vector strokes")?;
+            strokes.iter().try_for_each(|(_, stroke)| stroke.validate())
+         }
+         Self::RemoveVectorStrokes(ids) => {
+            check_len(ids.len(), MAX_VECTOR_STROKES, "too many vector strokes")
+         }
+         Self::CanvasBounds(None) => Ok(()),
+         Self::Pages(names) => {
+            check_len(names.len(), MAX_PAGES, "too many pages")?;
+            names
+               .iter()
+               .try_for_each(|name| check_len(name.len(), MAX_NAME_LEN, "page name is too long"))
+         }
+         Self::SelectPage(page) => {
+            if *page as usize >= MAX_PAGES {
+               return Err("page is out of range");
+            }
+            Ok(())
+         }
+         Self::CanvasBounds(Some((width, height))) => {
+            if *width == 0 || *height == 0 {
+               return Err("canvas bounds are empty");
+            }
+            if *width > MAX_CANVAS_SIZE || *height > MAX_CANVAS_SIZE {
+               return Err("canvas bounds are too large");
+            }
+            Ok(())
+         }
+         Self::Admin { password, command } => {
+            check_len(password.len(), MAX_NAME_LEN, "admin password is too long")?;
+            match command {
+               AdminCommand::Load(path) => check_len(path.len(), MAX_PATH_LEN, "path is too long"),
+               AdminCommand::Save | AdminCommand::Status => Ok(()),
+            }
+         }
+         Self::AdminResponse(response) => match response {
+            AdminResponse::Denied => Ok(()),
+            AdminResponse::Failed(reason) => {
+               check_len(reason.len(), MAX_DESCRIPTION_LEN, "reason is too long")
+            }
+            AdminResponse::Saved(path) | AdminResponse::Loaded(path) => {
+               check_len(path.len(), MAX_PATH_LEN, "path is too long")
+            }
+            AdminResponse::Status(status) => match &status.path {
+               Some(path) => check_len(path.len(), MAX_PATH_LEN, "path is too long"),
+               None => Ok(()),
+            },
+         },
+         Self::ChunkDigests(layer, digests) => {
+            check_layer(*layer)?;
+            check_len(digests.len(), MAX_CHUNK_POSITIONS, "too many chunk digests")?;
+            digests.iter().try_for_each(|(position, digest)| {
+               if digest.len() != CHUNK_DIGEST_LEN {
+                  return Err("chunk digest has the wrong length");
+               }
+               check_chunk_position(*position)
+            })
+         }
+         Self::ChunkEncoding(encoding) => encoding.validate(),
+         Self::Layers(layers) => {
+            if layers.is_empty() {
+               return Err("there must be at least one layer");
+            }
+            check_len(layers.len(), MAX_LAYERS, "too many layers")?;
+            layers.iter().try_for_each(|layer| {
+               check_len(layer.name.len(), MAX_NAME_LEN, "layer name is too long")
+            })
+         }
+         Self::SelectLayer(layer) => check_layer(*layer),
+      }
+   }
+}
+
+/// Checks that a layer index is below [`MAX_LAYERS`].
+fn check_layer(layer: u32) -> Result<(), &'static str> {
+   if layer as usize >= MAX_LAYERS {
+      return Err("layer is out of range");
+   }
+   Ok(())
+}
+
+impl Validate for StickyNote {
+   fn validate(&self) -> Result<(), &'static str> {
+      check_len(self.text.len(), MAX_NOTE_TEXT_LEN, "note text is too long")
+   }
+}
+
+impl Validate for Bookmark {
+   fn validate(&self) -> Result<(), &'static str> {
+      if !(1..=MAX_BOOKMARKS).contains(&(self.slot as usize)) {
+         return Err("bookmark slot is out of range");
+      }
+      if !BOOKMARK_ZOOM_LEVELS.contains(&self.zoom_level) {
+         return Err("bookmark zoom level is out of range");
+      }
+      check_len(self.name.len(), MAX_NAME_LEN, "bookmark name is too long")
+   }
+}
+
+impl Validate for ChunkEncoding {
+   fn validate(&self) -> Result<(), &'static str> {
+      if self.quality > MAX_CHUNK_QUALITY {
+         return Err("chunk quality is out of range");
+      }
+      if !(MIN_CHUNK_BATCH_SIZE..=MAX_CHUNK_BATCH_SIZE).contains(&self.max_batch_size) {
+         return Err("chunk batch size is out of range");
+      }
+      Ok(())
+   }
+}
+
+impl Validate for ImagePlacement {
+   fn validate(&self) -> Result<(), &'static str> {
+      let (width, height) = self.size;
+      if width == 0 || height == 0 {
+         return Err("floating image is empty");
+      }
+      if width > MAX_FLOATING_IMAGE_SIZE || height > MAX_FLOATING_IMAGE_SIZE {
+         return Err("floating image is too large");
+      }
+      Ok(())
+   }
+}
+
+impl Validate for VectorStroke {
+   fn validate(&self) -> Result<(), &'static str> {
+      check_len(
+         self.points.len(),
+         MAX_VECTOR_STROKE_POINTS,
+         "vector stroke has too many points",
+      )?;
+      if self.hardness > 100 {
+         return Err("vector stroke hardness is out of range");
+      }
+      if !self.widths.is_empty() && self.widths.len() != self.points.len() {
+         return Err("vector stroke widths don't match its points");
+      }
+      Ok(())
+   }
 }
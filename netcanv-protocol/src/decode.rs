@@ -0,0 +1,85 @@
+//! Defensive decoding of packets received over the network.
+//!
+//! Packets come from other peers and the relay, neither of which can be trusted to send well-formed
+//! data. Decoding is bounded by the maximum packet size, so that a crafted length prefix can't
+//! make bincode read (or allocate) more than a packet could possibly hold, and the decoded packet
+//! is then checked against the protocol's limits on vector lengths, string sizes, and chunk
+//! coordinates.
+
+use std::fmt::{self, Display, Formatter};
+
+use bincode::Options;
+use serde::de::DeserializeOwned;
+
+use crate::relay::MAX_PACKET_SIZE;
+
+/// The maximum absolute value of a chunk coordinate.
+///
+/// Chunks are 256 pixels wide, so this keeps pixel coordinates within the range of an `i32`.
+pub const MAX_CHUNK_COORDINATE: i32 = 1 << 22;
+
+/// A packet that can check whether it's within the protocol's limits.
+pub trait Validate {
+   /// Returns an error describing the first limit the packet exceeds, if any.
+   fn validate(&self) -> Result<(), &'static str>;
+}
+
+/// An error that occured while decoding a packet.
+#[derive(Debug)]
+pub enum DecodeError {
+   /// The packet could not be deserialized.
+   Malformed(bincode::Error),
+   /// The packet was deserialized, but it exceeds one of the protocol's limits.
+   Invalid(&'static str),
+}
+
+impl std::error::Error for DecodeError {}
+
+impl Display for DecodeError {
+   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+      match self {
+         Self::Malformed(error) => write!(f, "malformed packet: {}", error),
+         Self::Invalid(reason) => write!(f, "invalid packet: {}", reason),
+      }
+   }
+}
+
+/// Returns the bincode options packets are decoded with.
+///
+/// The encoding is the same as the one used by `bincode::serialize`, but reading is limited to
+/// [`MAX_PACKET_SIZE`] bytes and trailing bytes are rejected.
+fn options() -> impl Options {
+   bincode::DefaultOptions::new()
+      .with_fixint_encoding()
+      .with_limit(MAX_PACKET_SIZE as u64)
+      .reject_trailing_bytes()
+}
+
+/// Decodes a packet and checks that it's within the protocol's limits.
+pub fn decode<T>(bytes: &[u8]) -> Result<T, DecodeError>
+where
+   T: DeserializeOwned + Validate,
+{
+   let packet: T = options().deserialize(bytes).map_err(DecodeError::Malformed)?;
+   packet.validate().map_err(DecodeError::Invalid)?;
+   Ok(packet)
+}
+
+/// Checks that a vector or string is no longer than `max`.
+pub(crate) fn check_len(len: usize, max: usize, what: &'static str) -> Result<(), &'static str> {
+   if len > max {
+      Err(what)
+   } else {
+      Ok(())
+   }
+}
+
+/// Checks that a chunk position lies within [`MAX_CHUNK_COORDINATE`].
+pub(crate) fn check_chunk_position((x, y): (i32, i32)) -> Result<(), &'static str> {
+   let range = -MAX_CHUNK_COORDINATE..=MAX_CHUNK_COORDINATE;
+   if range.contains(&x) && range.contains(&y) {
+      Ok(())
+   } else {
+      Err("chunk position out of bounds")
+   }
+}
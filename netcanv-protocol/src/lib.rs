@@ -1,2 +1,6 @@
 pub mod client;
+pub mod decode;
 pub mod relay;
+
+#[cfg(test)]
+mod tests;
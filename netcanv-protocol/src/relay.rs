@@ -6,10 +6,13 @@ use std::{
 };
 
 use serde::{Deserialize, Serialize};
+use sha1::{Digest, Sha1};
 
 #[cfg(feature = "i18n")]
 use netcanv_i18n::Formatted;
 
+use crate::decode::{check_len, Validate};
+
 /// The default relay port.
 pub const DEFAULT_PORT: u16 = 62137;
 
@@ -17,7 +20,7 @@ pub const DEFAULT_PORT: u16 = 62137;
 ///
 /// This is sent by the server upon connecting, before any packets.
 // The version is incremented whenever breaking changes are introduced in the protocol.
-pub const PROTOCOL_VERSION: u32 = 1;
+pub const PROTOCOL_VERSION: u32 = 7;
 
 /// The maximum length of a serialized packet. If a packet is larger than this amount, the
 /// connection shall be closed.
@@ -25,6 +28,32 @@ pub const PROTOCOL_VERSION: u32 = 1;
 // images are downscaled to max 1024x1024. A 1024x1024 PNG of RGB noise is about 2 MiB.
 pub const MAX_PACKET_SIZE: u32 = 4 * 1024 * 1024;
 
+/// The maximum length of the name a room is publicly listed under, in bytes.
+pub const MAX_PUBLIC_NAME_LEN: usize = 256;
+
+/// The maximum length of the description a room is publicly listed with, in bytes.
+pub const MAX_PUBLIC_DESCRIPTION_LEN: usize = 1024;
+
+/// The maximum number of rooms in a room list.
+pub const MAX_LISTED_ROOMS: usize = 1024;
+
+/// The maximum length of an encoded voice frame, in bytes. This is the largest packet an Opus
+/// encoder can produce for a single frame.
+pub const MAX_VOICE_FRAME_LEN: usize = 1276;
+
+/// The length of a room creation challenge, in bytes.
+pub const HOST_CHALLENGE_LEN: usize = 16;
+
+/// The highest room creation difficulty a relay may ask for. Each step doubles the time it takes
+/// to solve a challenge, so this keeps a misbehaving relay from making clients spin forever.
+pub const MAX_HOST_DIFFICULTY: u8 = 24;
+
+/// The maximum length of a room creation token, in bytes.
+pub const MAX_HOST_TOKEN_LEN: usize = 256;
+
+/// The maximum length of the address a directly hosted room can be reached at, in bytes.
+pub const MAX_DIRECT_ADDRESS_LEN: usize = 256;
+
 #[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
 pub enum Packet {
    // ---
@@ -32,17 +61,26 @@ pub enum Packet {
    // ---
    /// Request from the host to the relay for a free room ID.
    Host,
+   /// Request from the host to the relay for a room with the given ID, rather than one picked at
+   /// random. The ID must be [valid for a custom room][RoomId::is_valid_custom].
+   HostCustom(RoomId),
    /// Response from the relay to the host containing the room ID, and the peer ID inside the
    /// room.
    RoomCreated(RoomId, PeerId),
    /// Request sent from a client, to join a room with the given ID.
    Join(RoomId),
-   /// Response from the relay to the client containing the client's peer ID and the host's
-   /// peer ID.
-   Joined { peer_id: PeerId, host_id: PeerId },
-   /// Message from the relay that the host has disconnected, and that the host role now
-   /// belongs to the peer with the given ID.
+   /// Response from the relay to the client containing the client's peer ID, the host's
+   /// peer ID, and the ID of the room that was joined.
+   Joined {
+      peer_id: PeerId,
+      host_id: PeerId,
+      room_id: RoomId,
+   },
+   /// Message from the relay that the host has disconnected (or handed the role over), and that
+   /// the host role now belongs to the peer with the given ID.
    HostTransfer(PeerId),
+   /// Request from the host to hand the host role over to another peer in its room.
+   TransferHost(PeerId),
 
    // ---
    // Packet relay
@@ -58,6 +96,93 @@ pub enum Packet {
    /// A peer has left the room.
    Disconnected(PeerId),
 
+   // ---
+   // Invites
+   // ---
+   /// Request from the host to create a new invite token for its room.
+   ///
+   /// A single-use token stops being valid once someone joins with it. If a lifetime (in seconds)
+   /// is given, the token also stops being valid once that much time passes.
+   CreateInvite {
+      single_use: bool,
+      lifetime_secs: Option<u64>,
+   },
+   /// Response from the relay to the host containing the newly created invite token.
+   InviteCreated(InviteToken),
+   /// Request from the host to revoke all invite tokens created for its room.
+   RevokeInvites,
+   /// Request from the host to only let people into its room if they have an invite token.
+   ///
+   /// Also sent by the relay to the new host of an invite-only room, once the host role is
+   /// transferred to them.
+   SetInviteOnly(bool),
+   /// Request sent from a client, to join a room using an invite token.
+   JoinWithInvite(InviteToken),
+
+   // ---
+   // Public rooms
+   // ---
+   /// Request from the host to list its room publicly, or to stop listing it if the listing is
+   /// `None`. Sending a new listing while the room is listed already replaces the old one.
+   SetPublic(Option<RoomListing>),
+   /// Request for the list of public rooms. Unlike most other requests, this can be sent without
+   /// hosting or joining a room first.
+   ListRooms,
+   /// Response from the relay containing the list of public rooms.
+   RoomList(Vec<PublicRoom>),
+
+   // ---
+   // Voice chat
+   // ---
+   /// An encoded voice frame, to be sent out to everyone else in the room.
+   ///
+   /// Unlike relayed payloads, voice frames may be dropped by the relay if a peer can't keep up
+   /// with them, since a late frame is of no use anyway.
+   Voice(Vec<u8>),
+   /// A voice frame sent by another peer.
+   VoiceRelayed(PeerId, Vec<u8>),
+
+   // ---
+   // Switching rooms
+   // ---
+   /// Request from a peer to leave its room, while staying connected to the relay. Afterwards,
+   /// the peer can host or join another room without having to reconnect.
+   ///
+   /// Hosting or joining a room while already in one leaves the previous room implicitly.
+   Leave,
+   /// Response from the relay confirming that the peer left its room. No more packets from the
+   /// room are sent after this one.
+   Left,
+
+   // ---
+   // Room creation gate
+   // ---
+   /// Response from the relay to [`Packet::Host`], when the relay only creates rooms for peers
+   /// that solve a proof-of-work challenge first. See [`HostChallenge::solve`].
+   HostChallenge(HostChallenge),
+   /// Request from the host to create a room, carrying the solution to the challenge the relay
+   /// sent last.
+   HostWithProof(u64),
+   /// Request from the host to create a room, carrying a token shared with the relay's operator
+   /// beforehand. A valid token lets the host skip the challenge.
+   ///
+   /// Like with [`Packet::HostCustom`], the host can ask for a specific room ID.
+   HostWithToken {
+      token: String,
+      room_id: Option<RoomId>,
+   },
+
+   // ---
+   // Direct hosting
+   // ---
+   /// Request from the host to send people joining its room over to the given address, where
+   /// the host runs a relay of its own. Sending `None` lets people join through this relay again.
+   SetDirectAddress(Option<String>),
+   /// Response from the relay to [`Packet::Join`], sent instead of [`Packet::Joined`] when the
+   /// room is hosted directly. The client is expected to connect to the given address and join
+   /// the room with the same ID there.
+   Redirect(String),
+
    // ---
    // Other
    // ---
@@ -65,6 +190,93 @@ pub enum Packet {
    Error(Error),
 }
 
+impl Validate for Packet {
+   fn validate(&self) -> Result<(), &'static str> {
+      match self {
+         Self::SetPublic(Some(listing)) => check_listing(&listing.name, &listing.description),
+         Self::RoomList(rooms) => {
+            check_len(rooms.len(), MAX_LISTED_ROOMS, "too many rooms")?;
+            rooms.iter().try_for_each(|room| check_listing(&room.name, &room.description))
+         }
+         Self::Voice(frame) | Self::VoiceRelayed(_, frame) => {
+            check_len(frame.len(), MAX_VOICE_FRAME_LEN, "voice frame is too long")
+         }
+         Self::HostChallenge(challenge) => check_len(
+            challenge.difficulty as usize,
+            MAX_HOST_DIFFICULTY as usize,
+            "room creation challenge is too difficult",
+         ),
+         Self::HostCustom(room_id) => check_custom_room_id(*room_id),
+         Self::SetDirectAddress(Some(address)) | Self::Redirect(address) => check_len(
+            address.len(),
+            MAX_DIRECT_ADDRESS_LEN,
+            "direct address is too long",
+         ),
+         Self::HostWithToken { token, room_id } => {
+            check_len(
+               token.len(),
+               MAX_HOST_TOKEN_LEN,
+               "room creation token is too long",
+            )?;
+            room_id.map_or(Ok(()), check_custom_room_id)
+         }
+         // Relayed payloads are validated by the client once they're decoded, and everything
+         // else is fixed-size.
+         _ => Ok(()),
+      }
+   }
+}
+
+fn check_listing(name: &str, description: &str) -> Result<(), &'static str> {
+   check_len(name.len(), MAX_PUBLIC_NAME_LEN, "room name is too long")?;
+   check_len(
+      description.len(),
+      MAX_PUBLIC_DESCRIPTION_LEN,
+      "room description is too long",
+   )
+}
+
+fn check_custom_room_id(room_id: RoomId) -> Result<(), &'static str> {
+   if room_id.is_valid_custom() {
+      Ok(())
+   } else {
+      Err("custom room ID must be made of uppercase letters and digits")
+   }
+}
+
+/// A proof-of-work challenge that has to be solved before the relay creates a room.
+///
+/// Solving a challenge takes a fraction of a second, which goes unnoticed by someone hosting a
+/// room, but adds up quickly for scripts trying to occupy every room ID on the relay.
+#[derive(Clone, Copy, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct HostChallenge {
+   /// Random bytes picked by the relay, so that solutions can't be computed in advance.
+   pub nonce: [u8; HOST_CHALLENGE_LEN],
+   /// The number of leading zero bits the hash of a solution must have.
+   pub difficulty: u8,
+}
+
+impl HostChallenge {
+   /// Returns whether the proof is a solution to the challenge, that is, whether the SHA-1 hash of
+   /// the nonce followed by the proof starts with enough zero bits.
+   pub fn is_solved_by(&self, proof: u64) -> bool {
+      let hash = Sha1::new().chain_update(self.nonce).chain_update(proof.to_le_bytes()).finalize();
+      let mut zeros = 0;
+      for byte in hash {
+         zeros += byte.leading_zeros();
+         if byte != 0 {
+            break;
+         }
+      }
+      zeros >= u32::from(self.difficulty)
+   }
+
+   /// Finds a solution to the challenge by trying proofs one by one.
+   pub fn solve(&self) -> u64 {
+      (0..=u64::MAX).find(|&proof| self.is_solved_by(proof)).unwrap()
+   }
+}
+
 /// The unique ID of a room.
 #[repr(transparent)]
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
@@ -73,6 +285,15 @@ pub struct RoomId(pub [u8; Self::LEN]);
 impl RoomId {
    /// The length of a room ID.
    pub const LEN: usize = 6;
+
+   /// Returns whether a host may ask for a room with this ID, that is, whether the ID is made of
+   /// uppercase ASCII letters and digits only.
+   ///
+   /// Randomly picked room IDs follow the same rules, so custom IDs can be typed in and shared in
+   /// the same way.
+   pub fn is_valid_custom(&self) -> bool {
+      self.0.iter().all(|byte| byte.is_ascii_uppercase() || byte.is_ascii_digit())
+   }
 }
 
 impl FromStr for RoomId {
@@ -106,6 +327,83 @@ impl fmt::Debug for RoomId {
    }
 }
 
+/// How the host wants its room to be listed publicly.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct RoomListing {
+   /// The name the room is listed under.
+   pub name: String,
+   /// What the room is about. Empty if the host didn't describe the room.
+   pub description: String,
+}
+
+/// A room listed publicly by its host.
+#[derive(Clone, Serialize, Deserialize, Debug, PartialEq, Eq)]
+pub struct PublicRoom {
+   pub room_id: RoomId,
+   /// The name the room is listed under.
+   pub name: String,
+   /// What the room is about. Empty if the host didn't describe the room.
+   pub description: String,
+   /// The number of people currently in the room.
+   pub players: u32,
+   /// Whether the room can only be joined with an invite token.
+   pub invite_only: bool,
+}
+
+/// A token that lets its bearer into a room. Created by the host of the room, and validated by the
+/// relay when joining.
+#[repr(transparent)]
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub struct InviteToken(pub [u8; Self::LEN]);
+
+impl InviteToken {
+   /// The length of an invite token.
+   pub const LEN: usize = 12;
+}
+
+impl FromStr for InviteToken {
+   type Err = InviteTokenError;
+
+   fn from_str(value: &str) -> Result<Self, Self::Err> {
+      if value.len() != Self::LEN {
+         Err(InviteTokenError(()))
+      } else {
+         let mut bytes = [0u8; Self::LEN];
+         for (i, byte) in value.bytes().enumerate() {
+            bytes[i] = byte;
+         }
+         Ok(InviteToken(bytes))
+      }
+   }
+}
+
+impl Display for InviteToken {
+   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+      match std::str::from_utf8(&self.0) {
+         Ok(s) => write!(f, "{}", s),
+         Err(_) => write!(f, "<invalid UTF-8>"),
+      }
+   }
+}
+
+impl fmt::Debug for InviteToken {
+   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+      write!(f, "i:{}", self)
+   }
+}
+
+/// An error returned in case the invite token is not made up of 12 characters.
+#[derive(Debug)]
+pub struct InviteTokenError(());
+
+impl std::error::Error for InviteTokenError {}
+
+impl Display for InviteTokenError {
+   fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+      write!(f, "invite token must be 12 characters long")
+   }
+}
+
 /// An error returned in case the room ID is not made up of 6 characters.
 #[derive(Debug)]
 pub struct RoomIdError(());
@@ -178,4 +476,20 @@ pub enum Error {
    RoomDoesNotExist,
    /// The peer with the given ID doesn't seem to be connected.
    NoSuchPeer { address: PeerId },
+   /// The invite token does not exist, has expired, or has already been used.
+   InvalidInvite,
+   /// The room can only be joined with an invite token.
+   InviteRequired,
+   /// Only the host of a room can do that.
+   NotTheHost,
+   /// The relay only creates rooms for peers with a room creation token.
+   HostTokenRequired,
+   /// The room creation token does not match the one the relay was set up with.
+   InvalidHostToken,
+   /// The solution to the room creation challenge is wrong, or no challenge was sent.
+   InvalidHostProof,
+   /// A room with the requested ID already exists.
+   RoomIdTaken,
+   /// The requested room ID is reserved by the relay's operator.
+   RoomIdReserved,
 }
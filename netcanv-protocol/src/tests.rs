@@ -0,0 +1,447 @@
+//! Fuzz tests for packet decoding.
+//!
+//! These feed random and mutated packets through [`decode`], making sure that malformed input is
+//! always rejected with an error instead of panicking or exhausting memory.
+
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+use crate::client as cl;
+use crate::decode::{decode, DecodeError, Validate, MAX_CHUNK_COORDINATE};
+use crate::relay::{self, HostChallenge, InviteToken, PeerId, PublicRoom, RoomId, RoomListing};
+
+/// The number of inputs generated by each fuzz test.
+const ITERATIONS: usize = 20_000;
+
+/// A small xorshift generator, so that failures are reproducible.
+struct Rng(u64);
+
+impl Rng {
+   fn next(&mut self) -> u64 {
+      self.0 ^= self.0 << 13;
+      self.0 ^= self.0 >> 7;
+      self.0 ^= self.0 << 17;
+      self.0
+   }
+
+   fn below(&mut self, max: usize) -> usize {
+      (self.next() % max as u64) as usize
+   }
+
+   fn bytes(&mut self, len: usize) -> Vec<u8> {
+      (0..len).map(|_| self.next() as u8).collect()
+   }
+}
+
+fn note(text: &str) -> cl::StickyNote {
+   cl::StickyNote {
+      position: (-16, 32),
+      color: (255, 235, 59),
+      text: text.to_owned(),
+   }
+}
+
+fn bookmark(slot: u8, zoom_level: i32) -> cl::Bookmark {
+   cl::Bookmark {
+      slot,
+      name: format!("Bookmark {slot}"),
+      pan: (1024, -512),
+      zoom_level,
+   }
+}
+
+fn placement(size: (u32, u32)) -> cl::ImagePlacement {
+   cl::ImagePlacement {
+      position: (-64, 32),
+      size,
+   }
+}
+
+fn vector_stroke(points: usize) -> cl::VectorStroke {
+   cl::VectorStroke {
+      color: (255, 0, 0, 255),
+      thickness: 4,
+      hardness: 100,
+      points: (0..points as i32).map(|i| (i * 16, -i * 8)).collect(),
+      widths: Vec::new(),
+   }
+}
+
+fn pressure_vector_stroke(widths: &[u8]) -> cl::VectorStroke {
+   cl::VectorStroke {
+      widths: widths.to_vec(),
+      ..vector_stroke(widths.len())
+   }
+}
+
+fn client_packets() -> Vec<cl::Packet> {
+   vec![
+      cl::Packet::Hello("nickname".into()),
+      cl::Packet::HiThere("someone".into()),
+      cl::Packet::Reserved1,
+      cl::Packet::Tool("brush".into(), vec![1, 2, 3, 4]),
+      cl::Packet::SelectTool("selection".into()),
+      cl::Packet::Version(cl::PROTOCOL_VERSION),
+      cl::Packet::ChunkPositions(0, vec![(0, 0), (-1, 2), (3, -4)]),
+      cl::Packet::GetChunks(1, vec![(5, 6)]),
+      cl::Packet::Chunks(
+         2,
+         vec![((0, 0), vec![0x89, b'P', b'N', b'G']), ((1, 1), vec![])],
+      ),
+      cl::Packet::LockedRegions(vec![((-2, -2), (2, 2))]),
+      cl::Packet::Paused(true),
+      cl::Packet::RoomInfo {
+         name: "room".into(),
+         description: "a room".into(),
+      },
+      cl::Packet::RoomPalette(vec![(255, 0, 0), (0, 255, 0)]),
+      cl::Packet::CommitOperation,
+      cl::Packet::Undo,
+      cl::Packet::Ping((-1234, 5678)),
+      cl::Packet::SetNote((3, 1), note("Hello")),
+      cl::Packet::RemoveNote((3, 1)),
+      cl::Packet::Notes(vec![((0, 0), note("First")), ((3, 2), note(""))]),
+      cl::Packet::AddImage((3, 0), placement((640, 480)), vec![0x89, b'P', b'N', b'G']),
+      cl::Packet::PlaceImage((3, 0), placement((320, 240))),
+      cl::Packet::RemoveImage((3, 0)),
+      cl::Packet::FlattenImage((3, 1)),
+      cl::Packet::VectorMode(true),
+      cl::Packet::VectorStrokes(vec![((0, 0), vector_stroke(3)), ((3, 0), vector_stroke(2))]),
+      cl::Packet::VectorStrokes(vec![((0, 1), pressure_vector_stroke(&[1, 3, 4]))]),
+      cl::Packet::RemoveVectorStrokes(vec![(3, 0)]),
+      cl::Packet::CanvasBounds(Some((4096, 2048))),
+      cl::Packet::CanvasBounds(None),
+      cl::Packet::Pages(vec!["Page 1".into(), "Page 2".into()]),
+      cl::Packet::SelectPage(1),
+      cl::Packet::Background(cl::Background::Isometric),
+      cl::Packet::ChunkDigests(0, vec![((0, -1), vec![0; cl::CHUNK_DIGEST_LEN])]),
+      cl::Packet::Admin {
+         password: "hunter2".into(),
+         command: cl::AdminCommand::Load("canvas.netcanv".into()),
+      },
+      cl::Packet::AdminResponse(cl::AdminResponse::Status(cl::AdminStatus {
+         path: Some("canvas.netcanv".into()),
+         chunks: 12,
+         unsaved_chunks: 3,
+         people: 2,
+         pages: 1,
+      })),
+      cl::Packet::ChunkEncoding(cl::ChunkEncoding {
+         format: cl::ChunkFormat::WebP,
+         quality: 50,
+         max_batch_size: cl::MIN_CHUNK_BATCH_SIZE,
+      }),
+      cl::Packet::Redo,
+      cl::Packet::Layers(vec![
+         cl::Layer {
+            name: "Background".into(),
+            visible: true,
+            opacity: 255,
+         },
+         cl::Layer {
+            name: "Sketch".into(),
+            visible: false,
+            opacity: 128,
+         },
+      ]),
+      cl::Packet::SelectLayer(1),
+      cl::Packet::Capabilities(cl::Capabilities::CURRENT),
+      cl::Packet::Bookmarks(vec![bookmark(1, 0), bookmark(9, -4)]),
+      cl::Packet::LatencyProbe(3),
+      cl::Packet::LatencyReply(3),
+      cl::Packet::Viewport {
+         pan: (-2048, 768),
+         zoom_level: -3,
+      },
+   ]
+}
+
+fn relay_packets() -> Vec<relay::Packet> {
+   let room_id = RoomId(*b"ABCDEF");
+   vec![
+      relay::Packet::Host,
+      relay::Packet::HostCustom(RoomId(*b"MYROOM")),
+      relay::Packet::RoomCreated(room_id, PeerId(1)),
+      relay::Packet::Join(room_id),
+      relay::Packet::Joined {
+         peer_id: PeerId(2),
+         host_id: PeerId(1),
+         room_id,
+      },
+      relay::Packet::HostTransfer(PeerId(2)),
+      relay::Packet::Relay(PeerId::BROADCAST, vec![1, 2, 3]),
+      relay::Packet::Relayed(
+         PeerId(1),
+         bincode::serialize(&cl::Packet::Paused(false)).unwrap(),
+      ),
+      relay::Packet::Disconnected(PeerId(2)),
+      relay::Packet::CreateInvite {
+         single_use: true,
+         lifetime_secs: Some(60),
+      },
+      relay::Packet::InviteCreated(InviteToken(*b"0123456789ab")),
+      relay::Packet::SetPublic(Some(RoomListing {
+         name: "public room".into(),
+         description: "come draw with us".into(),
+      })),
+      relay::Packet::ListRooms,
+      relay::Packet::RoomList(vec![PublicRoom {
+         room_id,
+         name: "public room".into(),
+         description: "come draw with us".into(),
+         players: 3,
+         invite_only: false,
+      }]),
+      relay::Packet::Voice(vec![0xf8, 0xff, 0xfe]),
+      relay::Packet::VoiceRelayed(PeerId(1), vec![0xf8, 0xff, 0xfe]),
+      relay::Packet::HostChallenge(HostChallenge {
+         nonce: [7; relay::HOST_CHALLENGE_LEN],
+         difficulty: 8,
+      }),
+      relay::Packet::HostWithProof(1234),
+      relay::Packet::HostWithToken {
+         token: "shared secret".into(),
+         room_id: Some(RoomId(*b"PR1VAT")),
+      },
+      relay::Packet::SetDirectAddress(Some("ws://203.0.113.7:51234".into())),
+      relay::Packet::Redirect("ws://203.0.113.7:51234".into()),
+      relay::Packet::Error(relay::Error::RoomDoesNotExist),
+   ]
+}
+
+/// Decodes the input, which must not panic. Successfully decoded packets must be within limits.
+fn decode_any<T>(input: &[u8])
+where
+   T: DeserializeOwned + Validate,
+{
+   if let Ok(packet) = decode::<T>(input) {
+      assert!(packet.validate().is_ok());
+   }
+}
+
+/// Mutates an encoded packet by flipping, overwriting, inserting, or removing bytes.
+fn mutate(rng: &mut Rng, mut data: Vec<u8>) -> Vec<u8> {
+   for _ in 0..1 + rng.below(4) {
+      let at = rng.below(data.len() + 1);
+      match rng.below(5) {
+         0 if at < data.len() => data[at] ^= 1 << rng.below(8),
+         1 if at < data.len() => data[at] = rng.next() as u8,
+         // Overwrite with a length prefix that's way too big.
+         2 => {
+            let end = (at + 8).min(data.len());
+            let huge = (u64::MAX - rng.below(1024) as u64).to_le_bytes();
+            data.splice(at..end, huge);
+         }
+         3 => {
+            let len = 1 + rng.below(16);
+            let bytes = rng.bytes(len);
+            data.splice(at..at, bytes);
+         }
+         _ => data.truncate(at),
+      }
+   }
+   data
+}
+
+fn fuzz<T>(seed: u64, packets: &[T])
+where
+   T: Serialize + DeserializeOwned + Validate,
+{
+   let encoded: Vec<_> = packets.iter().map(|packet| bincode::serialize(packet).unwrap()).collect();
+   let mut rng = Rng(seed);
+   for _ in 0..ITERATIONS {
+      let input = if rng.below(4) == 0 {
+         let len = rng.below(64);
+         rng.bytes(len)
+      } else {
+         let packet = encoded[rng.below(encoded.len())].clone();
+         mutate(&mut rng, packet)
+      };
+      decode_any::<T>(&input);
+   }
+}
+
+#[test]
+fn valid_packets_roundtrip() {
+   for packet in client_packets() {
+      let data = bincode::serialize(&packet).unwrap();
+      assert_eq!(decode::<cl::Packet>(&data).unwrap(), packet);
+   }
+   for packet in relay_packets() {
+      let data = bincode::serialize(&packet).unwrap();
+      assert_eq!(decode::<relay::Packet>(&data).unwrap(), packet);
+   }
+}
+
+#[test]
+fn fuzz_client_packets() {
+   fuzz(0x9e3779b97f4a7c15, &client_packets());
+}
+
+#[test]
+fn fuzz_relay_packets() {
+   fuzz(0xd1b54a32d192ed03, &relay_packets());
+}
+
+#[test]
+fn huge_length_prefix_is_rejected() {
+   // Variant index of `Chunks` and its layer index, followed by a length of 2^60 elements.
+   let mut data = 8u32.to_le_bytes().to_vec();
+   data.extend_from_slice(&0u32.to_le_bytes());
+   data.extend_from_slice(&(1u64 << 60).to_le_bytes());
+   assert!(matches!(
+      decode::<cl::Packet>(&data),
+      Err(DecodeError::Malformed(_))
+   ));
+}
+
+#[test]
+fn trailing_bytes_are_rejected() {
+   let mut data = bincode::serialize(&cl::Packet::Paused(true)).unwrap();
+   data.push(0);
+   assert!(decode::<cl::Packet>(&data).is_err());
+}
+
+#[test]
+fn limits_are_enforced() {
+   let out_of_bounds = MAX_CHUNK_COORDINATE + 1;
+   let invalid = [
+      cl::Packet::Hello("a".repeat(cl::MAX_NAME_LEN + 1)),
+      cl::Packet::Tool("a".repeat(cl::MAX_NAME_LEN + 1), vec![]),
+      cl::Packet::GetChunks(0, vec![(0, out_of_bounds)]),
+      cl::Packet::GetChunks(cl::MAX_LAYERS as u32, vec![(0, 0)]),
+      cl::Packet::ChunkPositions(0, vec![(0, 0); cl::MAX_CHUNK_POSITIONS + 1]),
+      cl::Packet::Chunks(0, vec![((i32::MIN, 0), vec![])]),
+      cl::Packet::LockedRegions(vec![((0, 0), (out_of_bounds, 0))]),
+      cl::Packet::RoomInfo {
+         name: String::new(),
+         description: "a".repeat(cl::MAX_DESCRIPTION_LEN + 1),
+      },
+      cl::Packet::RoomPalette(vec![(0, 0, 0); cl::MAX_PALETTE_LEN + 1]),
+      cl::Packet::SetNote((0, 0), note(&"a".repeat(cl::MAX_NOTE_TEXT_LEN + 1))),
+      cl::Packet::Notes(vec![((0, 0), note("")); cl::MAX_NOTES + 1]),
+      cl::Packet::PlaceImage((0, 0), placement((0, 16))),
+      cl::Packet::PlaceImage((0, 0), placement((16, cl::MAX_FLOATING_IMAGE_SIZE + 1))),
+      cl::Packet::VectorStrokes(vec![(
+         (0, 0),
+         vector_stroke(cl::MAX_VECTOR_STROKE_POINTS + 1),
+      )]),
+      cl::Packet::VectorStrokes(vec![(
+         (0, 0),
+         cl::VectorStroke {
+            widths: vec![4; 2],
+            ..vector_stroke(3)
+         },
+      )]),
+      cl::Packet::CanvasBounds(Some((0, 512))),
+      cl::Packet::CanvasBounds(Some((512, cl::MAX_CANVAS_SIZE + 1))),
+      cl::Packet::Pages(vec![String::new(); cl::MAX_PAGES + 1]),
+      cl::Packet::SelectPage(cl::MAX_PAGES as u32),
+      cl::Packet::ChunkDigests(0, vec![((0, 0), vec![0; cl::CHUNK_DIGEST_LEN - 1])]),
+      cl::Packet::ChunkDigests(0, vec![((0, out_of_bounds), vec![0; cl::CHUNK_DIGEST_LEN])]),
+      cl::Packet::Admin {
+         password: String::new(),
+         command: cl::AdminCommand::Load("a".repeat(cl::MAX_PATH_LEN + 1)),
+      },
+      cl::Packet::AdminResponse(cl::AdminResponse::Failed(
+         "a".repeat(cl::MAX_DESCRIPTION_LEN + 1),
+      )),
+      cl::Packet::ChunkEncoding(cl::ChunkEncoding {
+         quality: cl::MAX_CHUNK_QUALITY + 1,
+         ..Default::default()
+      }),
+      cl::Packet::ChunkEncoding(cl::ChunkEncoding {
+         max_batch_size: cl::MAX_CHUNK_BATCH_SIZE + 1,
+         ..Default::default()
+      }),
+      cl::Packet::Layers(Vec::new()),
+      cl::Packet::SelectLayer(cl::MAX_LAYERS as u32),
+      cl::Packet::Bookmarks(vec![bookmark(0, 0)]),
+      cl::Packet::Bookmarks(vec![bookmark(cl::MAX_BOOKMARKS as u8 + 1, 0)]),
+      cl::Packet::Bookmarks(vec![bookmark(1, 21)]),
+      cl::Packet::Bookmarks(vec![bookmark(1, 0); cl::MAX_BOOKMARKS + 1]),
+      cl::Packet::Viewport {
+         pan: (0, 0),
+         zoom_level: 21,
+      },
+   ];
+   for packet in invalid {
+      let data = bincode::serialize(&packet).unwrap();
+      assert!(matches!(
+         decode::<cl::Packet>(&data),
+         Err(DecodeError::Invalid(_))
+      ));
+   }
+
+   let invalid = [
+      relay::Packet::SetPublic(Some(RoomListing {
+         name: "a".repeat(relay::MAX_PUBLIC_NAME_LEN + 1),
+         description: String::new(),
+      })),
+      relay::Packet::SetPublic(Some(RoomListing {
+         name: "room".into(),
+         description: "a".repeat(relay::MAX_PUBLIC_DESCRIPTION_LEN + 1),
+      })),
+      relay::Packet::Voice(vec![0; relay::MAX_VOICE_FRAME_LEN + 1]),
+      relay::Packet::HostChallenge(HostChallenge {
+         nonce: [0; relay::HOST_CHALLENGE_LEN],
+         difficulty: relay::MAX_HOST_DIFFICULTY + 1,
+      }),
+      relay::Packet::HostWithToken {
+         token: "a".repeat(relay::MAX_HOST_TOKEN_LEN + 1),
+         room_id: None,
+      },
+      relay::Packet::HostCustom(RoomId(*b"myroom")),
+      relay::Packet::HostWithToken {
+         token: String::new(),
+         room_id: Some(RoomId(*b"ROOM!\0")),
+      },
+      relay::Packet::SetDirectAddress(Some("a".repeat(relay::MAX_DIRECT_ADDRESS_LEN + 1))),
+      relay::Packet::Redirect("a".repeat(relay::MAX_DIRECT_ADDRESS_LEN + 1)),
+   ];
+   for packet in invalid {
+      let data = bincode::serialize(&packet).unwrap();
+      assert!(matches!(
+         decode::<relay::Packet>(&data),
+         Err(DecodeError::Invalid(_))
+      ));
+   }
+}
+
+#[test]
+fn largest_floating_image_fits_in_a_relay_packet() {
+   let packet = cl::Packet::AddImage(
+      (0, 0),
+      placement((cl::MAX_FLOATING_IMAGE_SIZE, cl::MAX_FLOATING_IMAGE_SIZE)),
+      vec![0; cl::MAX_FLOATING_IMAGE_DATA_LEN],
+   );
+   let packet = relay::Packet::Relay(PeerId::BROADCAST, bincode::serialize(&packet).unwrap());
+   let data = bincode::serialize(&packet).unwrap();
+   assert!(data.len() <= relay::MAX_PACKET_SIZE as usize);
+   assert!(decode::<relay::Packet>(&data).is_ok());
+}
+
+#[test]
+fn clients_without_pressure_and_erase_strokes_are_incompatible() {
+   // 0.6 clients can't decode brush strokes using pressure or the erase blend mode.
+   for version in 600..=606 {
+      assert!(!cl::compatible_with(version));
+   }
+   assert!(cl::compatible_with(cl::PROTOCOL_VERSION));
+}
+
+#[test]
+fn host_challenge_solutions_are_checked() {
+   let challenge = HostChallenge {
+      nonce: *b"0123456789abcdef",
+      difficulty: 12,
+   };
+   let proof = challenge.solve();
+   assert!(challenge.is_solved_by(proof));
+   // A solution only counts for the nonce it was found for.
+   let other = HostChallenge {
+      nonce: *b"fedcba9876543210",
+      ..challenge
+   };
+   assert!(!other.is_solved_by(proof));
+}
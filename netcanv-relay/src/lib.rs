@@ -0,0 +1,1056 @@
+//! The NetCanv Relay server.
+//! Keeps track of open rooms and relays packets between peers.
+//!
+//! Besides running as a standalone server, the relay can be embedded into other programs, such as
+//! NetCanv itself when hosting a room on the local network.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use nanorand::Rng;
+use netcanv_protocol::decode::decode;
+use netcanv_protocol::relay::{
+   self, HostChallenge, InviteToken, Packet, PeerId, PublicRoom, RoomId, RoomListing,
+};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{accept_async, tungstenite, WebSocketStream};
+
+type Sink = SplitSink<WebSocketStream<TcpStream>, Message>;
+type Stream = SplitStream<WebSocketStream<TcpStream>>;
+
+#[cfg(test)]
+mod tests;
+
+/// An invite token created by the host of a room.
+struct Invite {
+   room_id: RoomId,
+   single_use: bool,
+   expires_at: Option<Instant>,
+}
+
+impl Invite {
+   /// Returns whether the invite has expired.
+   fn has_expired(&self) -> bool {
+      self.expires_at.map(|expires_at| Instant::now() >= expires_at).unwrap_or(false)
+   }
+}
+
+struct Rooms {
+   occupied_room_ids: HashSet<RoomId>,
+   reserved_room_ids: HashSet<RoomId>,
+   client_rooms: HashMap<PeerId, RoomId>,
+   room_clients: HashMap<RoomId, Vec<PeerId>>,
+   room_hosts: HashMap<RoomId, PeerId>,
+   invites: HashMap<InviteToken, Invite>,
+   invite_only_rooms: HashSet<RoomId>,
+   public_rooms: HashMap<RoomId, RoomListing>,
+   /// The addresses of relays run by hosts of directly hosted rooms.
+   direct_addresses: HashMap<RoomId, String>,
+}
+
+impl Rooms {
+   /// The room ID character set. Room IDs are composed of characters picked at random from
+   /// this string.
+   ///
+   /// This is _almost_ base32, with `I`, `0`, and `O` omitted to avoid confusion.
+   /// Some fonts render `0` and `O` in a very similar way, and people often confuse the capital
+   /// `I` for the lowercase `l`, even if it's not a part of a code.
+   ///
+   /// **Warning:** all characters in this string must be ASCII, as [`Self::generate_room_id`] does
+   /// not handle Unicode characters for performance reasons.
+   const ID_CHARSET: &'static [u8] = b"123456789ABCDEFGHJKLMNPQRSTUVWXYZ";
+
+   /// The maximum length of the name a room is publicly listed under, in characters.
+   const MAX_PUBLIC_NAME_LEN: usize = 32;
+
+   /// The maximum length of the description a room is publicly listed with, in characters.
+   const MAX_PUBLIC_DESCRIPTION_LEN: usize = 128;
+
+   /// The maximum number of rooms sent back in response to [`Packet::ListRooms`].
+   const MAX_LISTED_ROOMS: usize = 64;
+
+   fn new() -> Self {
+      Self {
+         occupied_room_ids: HashSet::new(),
+         reserved_room_ids: HashSet::new(),
+         client_rooms: HashMap::new(),
+         room_clients: HashMap::new(),
+         room_hosts: HashMap::new(),
+         invites: HashMap::new(),
+         invite_only_rooms: HashSet::new(),
+         public_rooms: HashMap::new(),
+         direct_addresses: HashMap::new(),
+      }
+   }
+
+   /// Generates a pseudo-random room ID.
+   fn generate_room_id(&self) -> RoomId {
+      let mut rng = nanorand::tls_rng();
+      RoomId([(); 6].map(|_| {
+         let index = rng.generate_range(0..Self::ID_CHARSET.len());
+         Self::ID_CHARSET[index]
+      }))
+   }
+
+   /// Creates a new invite token for the room with the given ID.
+   ///
+   /// Returns `None` if all attempts to find a free token have failed.
+   fn create_invite(
+      &mut self,
+      room_id: RoomId,
+      single_use: bool,
+      lifetime: Option<Duration>,
+   ) -> Option<InviteToken> {
+      // Expired invites are only cleaned up here, as they're harmless otherwise.
+      self.invites.retain(|_, invite| !invite.has_expired());
+
+      let mut rng = nanorand::tls_rng();
+      for _attempt in 0..50 {
+         let token = InviteToken([(); InviteToken::LEN].map(|_| {
+            let index = rng.generate_range(0..Self::ID_CHARSET.len());
+            Self::ID_CHARSET[index]
+         }));
+         if let Entry::Vacant(entry) = self.invites.entry(token) {
+            entry.insert(Invite {
+               room_id,
+               single_use,
+               expires_at: lifetime.map(|lifetime| Instant::now() + lifetime),
+            });
+            return Some(token);
+         }
+      }
+      None
+   }
+
+   /// Validates the invite token, and returns the ID of the room it leads to.
+   ///
+   /// Single-use tokens are consumed by this. Returns `None` if the token is not valid.
+   fn redeem_invite(&mut self, token: InviteToken) -> Option<RoomId> {
+      let invite = self.invites.get(&token)?;
+      if invite.has_expired() {
+         self.invites.remove(&token);
+         return None;
+      }
+      let room_id = invite.room_id;
+      if invite.single_use {
+         self.invites.remove(&token);
+      }
+      Some(room_id)
+   }
+
+   /// Returns the ID of the room the invite token leads to, without redeeming it. Returns `None`
+   /// if the token is not valid.
+   fn invite_room_id(&self, token: InviteToken) -> Option<RoomId> {
+      self.invites.get(&token).filter(|invite| !invite.has_expired()).map(|invite| invite.room_id)
+   }
+
+   /// Revokes all invite tokens created for the given room.
+   fn revoke_invites(&mut self, room_id: RoomId) {
+      self.invites.retain(|_, invite| invite.room_id != room_id);
+   }
+
+   /// Sets whether the room can only be joined with an invite token.
+   fn set_invite_only(&mut self, room_id: RoomId, invite_only: bool) {
+      if invite_only {
+         self.invite_only_rooms.insert(room_id);
+      } else {
+         self.invite_only_rooms.remove(&room_id);
+      }
+   }
+
+   /// Returns whether the room can only be joined with an invite token.
+   fn is_invite_only(&self, room_id: RoomId) -> bool {
+      self.invite_only_rooms.contains(&room_id)
+   }
+
+   /// Lists the room publicly, or stops listing it if the listing is `None`.
+   fn set_public(&mut self, room_id: RoomId, listing: Option<RoomListing>) {
+      match listing {
+         Some(listing) => {
+            let listing = RoomListing {
+               name: listing.name.chars().take(Self::MAX_PUBLIC_NAME_LEN).collect(),
+               description: listing
+                  .description
+                  .chars()
+                  .take(Self::MAX_PUBLIC_DESCRIPTION_LEN)
+                  .collect(),
+            };
+            self.public_rooms.insert(room_id, listing);
+         }
+         None => {
+            self.public_rooms.remove(&room_id);
+         }
+      }
+   }
+
+   /// Sends people joining the room over to the given address, or lets them join through this
+   /// relay again if the address is `None`.
+   fn set_direct_address(&mut self, room_id: RoomId, address: Option<String>) {
+      match address {
+         Some(address) => {
+            self.direct_addresses.insert(room_id, address);
+         }
+         None => {
+            self.direct_addresses.remove(&room_id);
+         }
+      }
+   }
+
+   /// Returns the address the room is hosted at directly, if it is.
+   fn direct_address(&self, room_id: RoomId) -> Option<&str> {
+      self.direct_addresses.get(&room_id).map(|address| address.as_str())
+   }
+
+   /// Returns the list of public rooms, with the most populated rooms first.
+   fn public_rooms(&self) -> Vec<PublicRoom> {
+      let mut rooms: Vec<PublicRoom> = self
+         .public_rooms
+         .iter()
+         .map(|(&room_id, listing)| PublicRoom {
+            room_id,
+            name: listing.name.clone(),
+            description: listing.description.clone(),
+            players: self.room_clients.get(&room_id).map(|clients| clients.len()).unwrap_or(0)
+               as u32,
+            invite_only: self.is_invite_only(room_id),
+         })
+         .collect();
+      rooms.sort_by(|a, b| b.players.cmp(&a.players).then_with(|| a.name.cmp(&b.name)));
+      rooms.truncate(Self::MAX_LISTED_ROOMS);
+      rooms
+   }
+
+   /// Allocates a new, free room ID.
+   ///
+   /// Returns `None` if all attempts to find a free ID have failed.
+   fn find_room_id(&mut self) -> Option<RoomId> {
+      for _attempt in 0..50 {
+         let id = self.generate_room_id();
+         if self.occupied_room_ids.insert(id) {
+            self.room_clients.insert(id, Vec::new());
+            return Some(id);
+         }
+      }
+      None
+   }
+
+   /// Occupies the room ID requested by a host.
+   fn claim_room_id(&mut self, id: RoomId) -> Result<(), relay::Error> {
+      if self.reserved_room_ids.contains(&id) {
+         return Err(relay::Error::RoomIdReserved);
+      }
+      if !self.occupied_room_ids.insert(id) {
+         return Err(relay::Error::RoomIdTaken);
+      }
+      self.room_clients.insert(id, Vec::new());
+      Ok(())
+   }
+
+   /// Makes the peer with the given ID the host of this room.
+   fn make_host(&mut self, room_id: RoomId, peer_id: PeerId) {
+      self.room_hosts.insert(room_id, peer_id);
+   }
+
+   /// Makes the peer join the room with the given ID.
+   fn join_room(&mut self, peer_id: PeerId, room_id: RoomId) {
+      if let Some(room_clients) = self.room_clients.get_mut(&room_id) {
+         self.client_rooms.insert(peer_id, room_id);
+         room_clients.push(peer_id);
+      }
+   }
+
+   /// Removes a room.
+   fn remove_room(&mut self, room_id: RoomId) {
+      self.occupied_room_ids.remove(&room_id);
+      self.room_clients.remove(&room_id);
+      self.room_hosts.remove(&room_id);
+      self.invite_only_rooms.remove(&room_id);
+      self.public_rooms.remove(&room_id);
+      self.direct_addresses.remove(&room_id);
+      self.revoke_invites(room_id);
+   }
+
+   /// Makes the peer quit the room with the given ID. Returns the peer's room ID.
+   fn quit_room(&mut self, peer_id: PeerId) {
+      if let Some(room_id) = self.client_rooms.remove(&peer_id) {
+         let n_connected = if let Some(room_clients) = self.room_clients.get_mut(&room_id) {
+            if let Some(index) = room_clients.iter().position(|&id| id == peer_id) {
+               // We use the order-preserving `remove`, such that peers are queued up for the host
+               // role in the order they joined into the room.
+               room_clients.remove(index);
+            }
+            room_clients.len()
+         } else {
+            0
+         };
+         if n_connected == 0 {
+            self.remove_room(room_id);
+         }
+      }
+   }
+
+   /// Returns the ID of the given room's host, or `None` if the room doesn't exist.
+   fn host_id(&self, room_id: RoomId) -> Option<PeerId> {
+      self.room_hosts.get(&room_id).cloned()
+   }
+
+   /// Returns the ID of the given peer's room, or `None` if they haven't joined a room yet.
+   fn room_id(&self, peer_id: PeerId) -> Option<RoomId> {
+      self.client_rooms.get(&peer_id).cloned()
+   }
+
+   /// Returns an iterator over all the peers in a given room.
+   fn peers_in_room(&self, room_id: RoomId) -> Option<impl Iterator<Item = PeerId> + '_> {
+      Some(self.room_clients.get(&room_id)?.iter().cloned())
+   }
+
+   /// Reserves the given room IDs, such that no room can ever be created with them.
+   fn reserve_room_ids(&mut self, ids: Vec<RoomId>) {
+      for id in ids {
+         // Occupying the ID keeps it from being picked at random.
+         self.occupied_room_ids.insert(id);
+         self.reserved_room_ids.insert(id);
+         log::info!("reserved room ID {}", id);
+      }
+   }
+}
+
+struct Peers {
+   occupied_peer_ids: HashSet<PeerId>,
+   peer_ids: HashMap<SocketAddr, PeerId>,
+   peer_sinks: HashMap<PeerId, Arc<Mutex<Sink>>>,
+}
+
+impl Peers {
+   fn new() -> Self {
+      Self {
+         occupied_peer_ids: HashSet::new(),
+         peer_ids: HashMap::new(),
+         peer_sinks: HashMap::new(),
+      }
+   }
+
+   /// Allocates a new peer ID for the given socket address.
+   fn allocate_peer_id(&mut self, sink: Arc<Mutex<Sink>>, address: SocketAddr) -> Option<PeerId> {
+      let mut rng = nanorand::tls_rng();
+      for _attempt in 0..50 {
+         let id = PeerId(rng.generate_range(PeerId::FIRST_PEER..=PeerId::LAST_PEER));
+         if self.occupied_peer_ids.insert(id) {
+            self.peer_ids.insert(address, id);
+            self.peer_sinks.insert(id, sink);
+            return Some(id);
+         }
+      }
+      None
+   }
+
+   /// Deallocates the peer with the given ID. New peers will be able to join with the same ID.
+   fn free_peer_id(&mut self, address: SocketAddr) {
+      if let Some(id) = self.peer_ids.remove(&address) {
+         self.occupied_peer_ids.remove(&id);
+         self.peer_sinks.remove(&id);
+      }
+   }
+
+   /// Returns the ID of the peer with the given socket address.
+   fn peer_id(&self, address: SocketAddr) -> Option<PeerId> {
+      self.peer_ids.get(&address).cloned()
+   }
+}
+
+/// What peers have to do before the relay creates a room for them. This keeps scripts from
+/// occupying every room ID on public relays.
+#[derive(Default)]
+pub struct HostGate {
+   /// The difficulty of the challenge hosts have to solve, or 0 if there's no challenge.
+   pub difficulty: u8,
+   /// The token that lets hosts skip the challenge.
+   pub token: Option<String>,
+}
+
+/// The state of the relay, shared between all connections.
+pub struct State {
+   rooms: Rooms,
+   peers: Peers,
+   host_gate: HostGate,
+   /// The challenge sent out to each peer that asked to host a room and hasn't answered yet,
+   /// along with the room ID they asked for.
+   host_challenges: HashMap<SocketAddr, (HostChallenge, Option<RoomId>)>,
+}
+
+impl State {
+   pub fn new(host_gate: HostGate) -> Self {
+      Self {
+         rooms: Rooms::new(),
+         peers: Peers::new(),
+         host_gate,
+         host_challenges: HashMap::new(),
+      }
+   }
+
+   /// Reserves the given room IDs, such that no room can ever be created with them.
+   pub fn reserve_room_ids(&mut self, ids: Vec<RoomId>) {
+      self.rooms.reserve_room_ids(ids);
+   }
+}
+
+async fn send_packet(sink: &Mutex<Sink>, packet: Packet) -> anyhow::Result<()> {
+   let encoded = bincode::serialize(&packet)?;
+   let mut sink = sink.lock().await;
+   u32::try_from(encoded.len()).context("packet is too big")?;
+
+   sink.send(Message::Binary(encoded)).await?;
+   Ok(())
+}
+
+/// Broadcasts a packet to all peers in the room.
+///
+/// If `sender` is not `PeerId::BROADCAST`, the packet is not sent to them.
+async fn broadcast_packet(
+   state: &mut State,
+   room_id: RoomId,
+   sender_id: PeerId,
+   packet: Packet,
+) -> anyhow::Result<()> {
+   let packet = bincode::serialize(&packet)?;
+   u32::try_from(packet.len()).context("packet is too big")?;
+
+   let peers_in_room = state.rooms.peers_in_room(room_id);
+   let mut result = Ok(());
+   if let Some(iter) = peers_in_room {
+      for peer_id in iter {
+         if peer_id != sender_id {
+            if let Some(stream) = state.peers.peer_sinks.get(&peer_id) {
+               match stream.lock().await.send(Message::Binary(packet.to_owned())).await {
+                  Ok(()) => (),
+                  Err(error) => result = Err(error),
+               }
+            }
+         }
+      }
+   }
+   Ok(result?)
+}
+
+/// Creates a room for the peer, under the requested ID or a random one.
+async fn host(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &mut State,
+   requested_room_id: Option<RoomId>,
+) -> anyhow::Result<()> {
+   leave_room(state, address).await?;
+   let room_id = match requested_room_id {
+      Some(id) => match state.rooms.claim_room_id(id) {
+         Ok(()) => id,
+         Err(error) => {
+            // The host can simply ask for another ID, so the connection is kept open.
+            send_packet(write, Packet::Error(error)).await?;
+            return Ok(());
+         }
+      },
+      None => {
+         if let Some(id) = state.rooms.find_room_id() {
+            id
+         } else {
+            send_packet(write, Packet::Error(relay::Error::NoFreeRooms)).await?;
+            anyhow::bail!("no more free room IDs");
+         }
+      }
+   };
+
+   let peer_id = if let Some(id) = state.peers.allocate_peer_id(Arc::clone(write), address) {
+      id
+   } else {
+      state.rooms.remove_room(room_id);
+      send_packet(write, Packet::Error(relay::Error::NoFreePeerIDs)).await?;
+      anyhow::bail!("no more free peer IDs");
+   };
+
+   state.rooms.make_host(room_id, peer_id);
+   state.rooms.join_room(peer_id, room_id);
+   send_packet(write, Packet::RoomCreated(room_id, peer_id)).await?;
+
+   Ok(())
+}
+
+/// Creates a room for the peer if the relay lets anyone do that. Otherwise, sends them a
+/// challenge to solve, or tells them that they need a token.
+async fn request_host(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &mut State,
+   requested_room_id: Option<RoomId>,
+) -> anyhow::Result<()> {
+   if state.host_gate.difficulty > 0 {
+      let challenge = HostChallenge {
+         nonce: {
+            let mut rng = nanorand::tls_rng();
+            [(); relay::HOST_CHALLENGE_LEN].map(|_| rng.generate())
+         },
+         difficulty: state.host_gate.difficulty,
+      };
+      state.host_challenges.insert(address, (challenge, requested_room_id));
+      send_packet(write, Packet::HostChallenge(challenge)).await?;
+   } else if state.host_gate.token.is_some() {
+      send_packet(write, Packet::Error(relay::Error::HostTokenRequired)).await?;
+      anyhow::bail!("rooms can only be created with a token");
+   } else {
+      host(write, address, state, requested_room_id).await?;
+   }
+   Ok(())
+}
+
+/// Creates a room for the peer if the proof solves the challenge they were sent.
+async fn host_with_proof(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &mut State,
+   proof: u64,
+) -> anyhow::Result<()> {
+   // Each challenge can only be answered once, so that a single solution can't be reused for
+   // creating many rooms.
+   match state.host_challenges.remove(&address) {
+      Some((challenge, requested_room_id)) if challenge.is_solved_by(proof) => {
+         host(write, address, state, requested_room_id).await
+      }
+      _ => {
+         send_packet(write, Packet::Error(relay::Error::InvalidHostProof)).await?;
+         anyhow::bail!("invalid room creation proof");
+      }
+   }
+}
+
+/// Creates a room for the peer if the token matches the relay's.
+async fn host_with_token(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &mut State,
+   token: String,
+   requested_room_id: Option<RoomId>,
+) -> anyhow::Result<()> {
+   match &state.host_gate.token {
+      Some(expected) if *expected == token => host(write, address, state, requested_room_id).await,
+      // The relay doesn't hand out tokens, so the peer goes through the usual procedure.
+      None => request_host(write, address, state, requested_room_id).await,
+      Some(_) => {
+         send_packet(write, Packet::Error(relay::Error::InvalidHostToken)).await?;
+         anyhow::bail!("invalid room creation token");
+      }
+   }
+}
+
+async fn join(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &mut State,
+   room_id: RoomId,
+) -> anyhow::Result<()> {
+   if state.rooms.is_invite_only(room_id) {
+      send_packet(write, Packet::Error(relay::Error::InviteRequired)).await?;
+      anyhow::bail!("the room can only be joined with an invite");
+   }
+   if let Some(direct_address) = state.rooms.direct_address(room_id) {
+      // The peer reconnects to the host, so it doesn't need an ID here.
+      send_packet(write, Packet::Redirect(direct_address.to_owned())).await?;
+      return Ok(());
+   }
+   join_room(write, address, state, room_id).await
+}
+
+async fn join_with_invite(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &mut State,
+   token: InviteToken,
+) -> anyhow::Result<()> {
+   // Like with regular joins, people are sent over to directly hosted rooms. The token is left for
+   // the host's relay to redeem, such that single-use tokens aren't used up before the peer
+   // actually gets into the room.
+   if let Some(direct_address) =
+      state.rooms.invite_room_id(token).and_then(|room_id| state.rooms.direct_address(room_id))
+   {
+      send_packet(write, Packet::Redirect(direct_address.to_owned())).await?;
+      return Ok(());
+   }
+   let room_id = if let Some(id) = state.rooms.redeem_invite(token) {
+      id
+   } else {
+      send_packet(write, Packet::Error(relay::Error::InvalidInvite)).await?;
+      anyhow::bail!("invalid invite token");
+   };
+   join_room(write, address, state, room_id).await
+}
+
+/// Makes the peer join the room, without checking whether they're allowed to.
+async fn join_room(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &mut State,
+   room_id: RoomId,
+) -> anyhow::Result<()> {
+   leave_room(state, address).await?;
+   let peer_id = if let Some(id) = state.peers.allocate_peer_id(Arc::clone(write), address) {
+      id
+   } else {
+      send_packet(write, Packet::Error(relay::Error::NoFreePeerIDs)).await?;
+      anyhow::bail!("no more free peer IDs");
+   };
+
+   let host_id = if let Some(id) = state.rooms.host_id(room_id) {
+      id
+   } else {
+      send_packet(write, Packet::Error(relay::Error::RoomDoesNotExist)).await?;
+      anyhow::bail!("no room with the given ID");
+   };
+
+   state.rooms.join_room(peer_id, room_id);
+   send_packet(
+      write,
+      Packet::Joined {
+         peer_id,
+         host_id,
+         room_id,
+      },
+   )
+   .await?;
+
+   Ok(())
+}
+
+/// Takes the peer with the given address out of its room, letting everyone else in the room know.
+/// The peer's ID is freed, and a new one is allocated if they host or join another room.
+async fn leave_room(state: &mut State, address: SocketAddr) -> anyhow::Result<()> {
+   // Peers that only browsed the list of public rooms never get an ID, so there's nothing to
+   // tear down for them.
+   let peer_id = match state.peers.peer_id(address) {
+      Some(peer_id) => peer_id,
+      None => return Ok(()),
+   };
+   let room_id = state.rooms.room_id(peer_id);
+   state.rooms.quit_room(peer_id);
+   if let Some(room_id) = room_id {
+      broadcast_packet(
+         state,
+         room_id,
+         PeerId::BROADCAST,
+         Packet::Disconnected(peer_id),
+      )
+      .await?;
+      if state.rooms.host_id(room_id) == Some(peer_id) {
+         transfer_host(state, room_id).await?;
+      }
+   }
+   state.peers.free_peer_id(address);
+   Ok(())
+}
+
+/// Leaves the peer's room at their request, and confirms it once they're out.
+async fn leave(write: &Mutex<Sink>, address: SocketAddr, state: &mut State) -> anyhow::Result<()> {
+   leave_room(state, address).await?;
+   send_packet(write, Packet::Left).await
+}
+
+/// Returns the ID of the room hosted by the peer with the given address. If the peer is not the
+/// host of a room, sends an error back to them and returns `None`.
+async fn hosted_room_id(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &State,
+) -> anyhow::Result<Option<RoomId>> {
+   let peer_id =
+      state.peers.peer_id(address).ok_or_else(|| anyhow::anyhow!("peer does not have an ID"))?;
+   match state.rooms.room_id(peer_id) {
+      Some(room_id) if state.rooms.host_id(room_id) == Some(peer_id) => Ok(Some(room_id)),
+      _ => {
+         send_packet(write, Packet::Error(relay::Error::NotTheHost)).await?;
+         Ok(None)
+      }
+   }
+}
+
+/// Hands the host role over to another peer in the room, at the current host's request.
+async fn hand_over_host(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &mut State,
+   new_host_id: PeerId,
+) -> anyhow::Result<()> {
+   if let Some(room_id) = hosted_room_id(write, address, state).await? {
+      let in_room = state
+         .rooms
+         .peers_in_room(room_id)
+         .map(|mut peers| peers.any(|peer_id| peer_id == new_host_id))
+         .unwrap_or(false);
+      if !in_room {
+         send_packet(
+            write,
+            Packet::Error(relay::Error::NoSuchPeer {
+               address: new_host_id,
+            }),
+         )
+         .await?;
+         return Ok(());
+      }
+      log::info!("room {:?} is now hosted by {:?}", room_id, new_host_id);
+      state.rooms.make_host(room_id, new_host_id);
+      broadcast_packet(
+         state,
+         room_id,
+         PeerId::BROADCAST,
+         Packet::HostTransfer(new_host_id),
+      )
+      .await?;
+      send_room_settings(state, room_id, new_host_id).await?;
+   }
+   Ok(())
+}
+
+async fn create_invite(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &mut State,
+   single_use: bool,
+   lifetime_secs: Option<u64>,
+) -> anyhow::Result<()> {
+   if let Some(room_id) = hosted_room_id(write, address, state).await? {
+      let lifetime = lifetime_secs.map(Duration::from_secs);
+      if let Some(token) = state.rooms.create_invite(room_id, single_use, lifetime) {
+         log::info!("created invite for room {:?}", room_id);
+         send_packet(write, Packet::InviteCreated(token)).await?;
+      } else {
+         send_packet(write, Packet::Error(relay::Error::InvalidInvite)).await?;
+      }
+   }
+   Ok(())
+}
+
+async fn revoke_invites(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &mut State,
+) -> anyhow::Result<()> {
+   if let Some(room_id) = hosted_room_id(write, address, state).await? {
+      log::info!("revoking all invites for room {:?}", room_id);
+      state.rooms.revoke_invites(room_id);
+   }
+   Ok(())
+}
+
+async fn set_invite_only(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &mut State,
+   invite_only: bool,
+) -> anyhow::Result<()> {
+   if let Some(room_id) = hosted_room_id(write, address, state).await? {
+      state.rooms.set_invite_only(room_id, invite_only);
+   }
+   Ok(())
+}
+
+async fn set_public(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &mut State,
+   listing: Option<RoomListing>,
+) -> anyhow::Result<()> {
+   if let Some(room_id) = hosted_room_id(write, address, state).await? {
+      log::info!("room {:?} is now public: {}", room_id, listing.is_some());
+      state.rooms.set_public(room_id, listing);
+   }
+   Ok(())
+}
+
+async fn set_direct_address(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &mut State,
+   direct_address: Option<String>,
+) -> anyhow::Result<()> {
+   if let Some(room_id) = hosted_room_id(write, address, state).await? {
+      log::info!(
+         "room {:?} is now hosted directly: {}",
+         room_id,
+         direct_address.is_some()
+      );
+      state.rooms.set_direct_address(room_id, direct_address);
+   }
+   Ok(())
+}
+
+/// Sends the list of public rooms back. This does not require the peer to be in a room.
+async fn list_rooms(write: &Mutex<Sink>, state: &State) -> anyhow::Result<()> {
+   send_packet(write, Packet::RoomList(state.rooms.public_rooms())).await
+}
+
+/// Relays a packet to the peer with the given ID.
+async fn relay(
+   write: &Mutex<Sink>,
+   address: SocketAddr,
+   state: &mut State,
+   target_id: PeerId,
+   data: Vec<u8>,
+) -> anyhow::Result<()> {
+   let sender_id =
+      state.peers.peer_id(address).ok_or_else(|| anyhow::anyhow!("peer does not have an ID"))?;
+   let room_id =
+      state.rooms.room_id(sender_id).ok_or_else(|| anyhow::anyhow!("peer is not in a room"))?;
+
+   let packet = Packet::Relayed(sender_id, data);
+   if target_id.is_broadcast() {
+      broadcast_packet(state, room_id, sender_id, packet).await?;
+   } else if let Some(sink) = state.peers.peer_sinks.get(&target_id) {
+      send_packet(sink, packet).await?;
+   } else {
+      send_packet(
+         write,
+         Packet::Error(relay::Error::NoSuchPeer { address: target_id }),
+      )
+      .await?;
+   }
+
+   Ok(())
+}
+
+/// Sends a voice frame out to everyone else in the sender's room.
+///
+/// Peers whose connection is busy sending something else are skipped rather than waited on, as
+/// voice frames are only useful if they arrive on time.
+async fn relay_voice(address: SocketAddr, state: &mut State, frame: Vec<u8>) -> anyhow::Result<()> {
+   let sender_id =
+      state.peers.peer_id(address).ok_or_else(|| anyhow::anyhow!("peer does not have an ID"))?;
+   let room_id =
+      state.rooms.room_id(sender_id).ok_or_else(|| anyhow::anyhow!("peer is not in a room"))?;
+
+   let packet = bincode::serialize(&Packet::VoiceRelayed(sender_id, frame))?;
+   if let Some(iter) = state.rooms.peers_in_room(room_id) {
+      for peer_id in iter {
+         if peer_id == sender_id {
+            continue;
+         }
+         if let Some(mut sink) =
+            state.peers.peer_sinks.get(&peer_id).and_then(|s| s.try_lock().ok())
+         {
+            // A peer that fails to receive its frame will find out about it when sending
+            // regular packets, so errors are not reported back to the sender.
+            let _ = sink.send(Message::Binary(packet.clone())).await;
+         }
+      }
+   }
+   Ok(())
+}
+
+async fn handle_packet(
+   write: &Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &Mutex<State>,
+   packet: Packet,
+) -> anyhow::Result<()> {
+   match packet {
+      Packet::Host => request_host(write, address, &mut *state.lock().await, None).await?,
+      Packet::HostCustom(room_id) => {
+         request_host(write, address, &mut *state.lock().await, Some(room_id)).await?
+      }
+      Packet::Join(room_id) => join(write, address, &mut *state.lock().await, room_id).await?,
+      Packet::Relay(target_id, data) => {
+         relay(write, address, &mut *state.lock().await, target_id, data).await?
+      }
+      Packet::CreateInvite {
+         single_use,
+         lifetime_secs,
+      } => {
+         create_invite(
+            write,
+            address,
+            &mut *state.lock().await,
+            single_use,
+            lifetime_secs,
+         )
+         .await?
+      }
+      Packet::TransferHost(new_host_id) => {
+         hand_over_host(write, address, &mut *state.lock().await, new_host_id).await?
+      }
+      Packet::RevokeInvites => revoke_invites(write, address, &mut *state.lock().await).await?,
+      Packet::SetInviteOnly(invite_only) => {
+         set_invite_only(write, address, &mut *state.lock().await, invite_only).await?
+      }
+      Packet::JoinWithInvite(token) => {
+         join_with_invite(write, address, &mut *state.lock().await, token).await?
+      }
+      Packet::SetPublic(listing) => {
+         set_public(write, address, &mut *state.lock().await, listing).await?
+      }
+      Packet::ListRooms => list_rooms(write, &*state.lock().await).await?,
+      Packet::Voice(frame) => relay_voice(address, &mut *state.lock().await, frame).await?,
+      Packet::Leave => leave(write, address, &mut *state.lock().await).await?,
+      Packet::HostWithProof(proof) => {
+         host_with_proof(write, address, &mut *state.lock().await, proof).await?
+      }
+      Packet::HostWithToken { token, room_id } => {
+         host_with_token(write, address, &mut *state.lock().await, token, room_id).await?
+      }
+      Packet::SetDirectAddress(direct_address) => {
+         set_direct_address(write, address, &mut *state.lock().await, direct_address).await?
+      }
+
+      // These ones shouldn't happen, ignore.
+      Packet::RoomCreated(_room_id, _peer_id) => (),
+      Packet::Joined { .. } => (),
+      Packet::HostTransfer(_host_id) => (),
+      Packet::Relayed(_peer_id, _data) => (),
+      Packet::Disconnected(_peer_id) => (),
+      Packet::InviteCreated(_token) => (),
+      Packet::RoomList(_rooms) => (),
+      Packet::VoiceRelayed(_peer_id, _frame) => (),
+      Packet::Left => (),
+      Packet::HostChallenge(_challenge) => (),
+      Packet::Redirect(_address) => (),
+      Packet::Error(_message) => (),
+   }
+   Ok(())
+}
+
+async fn read_packets(
+   mut read: Stream,
+   write: Arc<Mutex<Sink>>,
+   address: SocketAddr,
+   state: &Mutex<State>,
+) -> anyhow::Result<()> {
+   while let Some(message) = read.next().await {
+      match message {
+         Ok(Message::Binary(buffer)) => {
+            if buffer.len() > relay::MAX_PACKET_SIZE as usize {
+               anyhow::bail!("packet is too big");
+            }
+            let packet = decode(&buffer)?;
+            handle_packet(&write, address, state, packet).await?;
+         }
+         Ok(Message::Close(frame)) => {
+            if let Some(frame) = frame {
+               log::info!("client disconnected, reason: {}", frame.reason);
+               return Ok(());
+            }
+         }
+         Ok(Message::Pong(_)) => {}
+         Ok(_) => log::info!("got ignored message"),
+         Err(e) => {
+            use tungstenite::Error::*;
+            match e {
+               ConnectionClosed => break,
+               AlreadyClosed => {
+                  // According to the documentation this error is the fault of the programmer.
+                  // However, this error would crash the entire relay and *all* rooms,
+                  // so it's better to treat it as a simple error and end the connection.
+                  log::error!("cannot work with already closed connection");
+                  break;
+               }
+               _ => anyhow::bail!(e),
+            }
+         }
+      }
+   }
+
+   Ok(())
+}
+
+/// Performs the host transferrence procedure.
+///
+/// This transfers the host status to the next person that joined the room.
+async fn transfer_host(state: &mut State, room_id: RoomId) -> anyhow::Result<()> {
+   // If we get here, the room can't have been deleted, and because of that, there's at least
+   // one person still in the room.
+   let new_host_id = state.rooms.peers_in_room(room_id).unwrap().next().unwrap();
+   state.rooms.make_host(room_id, new_host_id);
+   broadcast_packet(
+      state,
+      room_id,
+      PeerId::BROADCAST,
+      Packet::HostTransfer(new_host_id),
+   )
+   .await?;
+   send_room_settings(state, room_id, new_host_id).await?;
+   Ok(())
+}
+
+/// Lets the new host of a room know about the room's settings kept by the relay. Hosts otherwise
+/// only know about settings they changed themselves.
+async fn send_room_settings(state: &State, room_id: RoomId, host_id: PeerId) -> anyhow::Result<()> {
+   if state.rooms.is_invite_only(room_id) {
+      if let Some(sink) = state.peers.peer_sinks.get(&host_id) {
+         send_packet(sink, Packet::SetInviteOnly(true)).await?;
+      }
+   }
+   Ok(())
+}
+
+/// Pings the sink periodically.
+async fn ping_loop(write: Arc<Mutex<Sink>>) -> anyhow::Result<()> {
+   // This loop is exited whenever the stream is closed.
+   const PING_MESSAGE: &str = concat!("PING NetCanv Relay ", env!("CARGO_PKG_VERSION"));
+   const PING_PERIOD: Duration = Duration::from_secs(5);
+   loop {
+      tokio::time::sleep(PING_PERIOD).await;
+      write.lock().await.send(Message::Ping(PING_MESSAGE.as_bytes().to_owned())).await?;
+   }
+}
+
+async fn handle_connection(
+   stream: TcpStream,
+   address: SocketAddr,
+   state: Arc<Mutex<State>>,
+) -> anyhow::Result<()> {
+   log::info!("{} has connected", address);
+   stream.set_nodelay(true)?;
+
+   let (mut write, read) = {
+      let stream = accept_async(stream).await?;
+      stream.split()
+   };
+
+   let version = relay::PROTOCOL_VERSION.to_le_bytes();
+   write.send(tungstenite::Message::binary(version)).await?;
+   let write = Arc::new(Mutex::new(write));
+
+   let pinger = {
+      let write: Arc<Mutex<SplitSink<WebSocketStream<TcpStream>, Message>>> = Arc::clone(&write);
+      tokio::spawn(async move {
+         if let Err(error) = ping_loop(write).await {
+            log::error!("[{}] ping loop: {}", address, error);
+         }
+      })
+   };
+
+   match read_packets(read, write, address, &state).await {
+      Ok(()) => (),
+      Err(error) => log::error!("[{}] connection error: {}", address, error),
+   }
+
+   // Abort the pinger if it hasn't already exited.
+   pinger.abort();
+
+   log::info!("tearing down {}'s connection", address);
+   let mut state = state.lock().await;
+   state.host_challenges.remove(&address);
+   leave_room(&mut state, address).await?;
+
+   Ok(())
+}
+
+/// Accepts connections from the listener, handling each one in its own task.
+pub async fn serve(listener: TcpListener, state: Arc<Mutex<State>>) -> anyhow::Result<()> {
+   loop {
+      let (socket, address) = listener.accept().await?;
+      let state = Arc::clone(&state);
+      tokio::spawn(async move { handle_connection(socket, address, state).await });
+   }
+}
@@ -0,0 +1,415 @@
+//! Load testing. Simulates many clients hosting rooms, joining them, and relaying traffic through
+//! a relay, so that server operators can find out how much their relay can handle.
+
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use anyhow::Context;
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use netcanv_protocol::relay::{self, Packet, PeerId, RoomId};
+use structopt::StructOpt;
+use tokio::net::TcpStream;
+use tokio::sync::watch;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+/// How long a client waits for the relay to respond before counting it as an error.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// The traffic pattern simulated clients send.
+#[derive(Clone, Copy, Debug)]
+pub enum Pattern {
+   /// Every client broadcasts its packets to the whole room, like strokes.
+   Broadcast,
+   /// Clients send their packets to the host only, and the host broadcasts, like chunk requests
+   /// and responses.
+   Direct,
+   /// Every client broadcasts a whole second's worth of packets at once, like when a canvas is
+   /// being downloaded.
+   Burst,
+}
+
+impl FromStr for Pattern {
+   type Err = String;
+
+   fn from_str(s: &str) -> Result<Self, Self::Err> {
+      match s {
+         "broadcast" => Ok(Self::Broadcast),
+         "direct" => Ok(Self::Direct),
+         "burst" => Ok(Self::Burst),
+         _ => Err(format!(
+            "unknown traffic pattern {:?}, expected broadcast, direct, or burst",
+            s
+         )),
+      }
+   }
+}
+
+#[derive(StructOpt)]
+pub struct StressOptions {
+   /// The number of clients to simulate when load testing.
+   #[structopt(long, default_value = "100")]
+   clients: usize,
+
+   /// The number of clients in each room, including the host.
+   #[structopt(long, default_value = "5")]
+   room_size: usize,
+
+   /// The traffic pattern to simulate: broadcast, direct, or burst.
+   #[structopt(long, default_value = "broadcast")]
+   pattern: Pattern,
+
+   /// The number of packets each client sends per second.
+   #[structopt(long, default_value = "20")]
+   packet_rate: u32,
+
+   /// The size of each relayed payload, in bytes.
+   #[structopt(long, default_value = "64")]
+   payload_size: usize,
+
+   /// How long to keep sending traffic for, in seconds.
+   #[structopt(long, default_value = "30")]
+   duration: u64,
+
+   /// How long to spread connecting all the clients over, in seconds.
+   #[structopt(long, default_value = "5")]
+   ramp_up: u64,
+}
+
+/// Counters shared between all the simulated clients.
+#[derive(Default)]
+struct Stats {
+   /// The number of clients currently in a room.
+   connected: AtomicU64,
+   /// The number of clients that managed to get into a room at some point.
+   joined: AtomicU64,
+   failed: AtomicU64,
+   packets_sent: AtomicU64,
+   bytes_sent: AtomicU64,
+   packets_received: AtomicU64,
+   bytes_received: AtomicU64,
+   errors: AtomicU64,
+   latency_total_micros: AtomicU64,
+   latency_max_micros: AtomicU64,
+}
+
+impl Stats {
+   fn add(counter: &AtomicU64, value: u64) {
+      counter.fetch_add(value, Ordering::Relaxed);
+   }
+
+   fn get(counter: &AtomicU64) -> u64 {
+      counter.load(Ordering::Relaxed)
+   }
+
+   fn record_latency(&self, latency: Duration) {
+      let micros = latency.as_micros() as u64;
+      Self::add(&self.latency_total_micros, micros);
+      self.latency_max_micros.fetch_max(micros, Ordering::Relaxed);
+   }
+}
+
+/// What a simulated client knows about the test.
+struct Client {
+   url: String,
+   pattern: Pattern,
+   packet_rate: u32,
+   payload_size: usize,
+   /// The time all latencies are measured relative to.
+   epoch: Instant,
+   stop_at: Instant,
+   stats: Arc<Stats>,
+}
+
+/// Runs a load test against the relay at the given URL, logging progress every second and a
+/// summary at the end.
+pub async fn run(url: String, options: StressOptions) -> anyhow::Result<()> {
+   anyhow::ensure!(options.clients > 0, "there must be at least one client");
+   anyhow::ensure!(options.room_size > 0, "rooms must have at least one client");
+   anyhow::ensure!(options.packet_rate > 0, "the packet rate must be positive");
+   // Payloads carry the time they were sent at, for measuring latency.
+   let payload_size = options.payload_size.max(8);
+   anyhow::ensure!(
+      payload_size + 64 <= relay::MAX_PACKET_SIZE as usize,
+      "payloads must be smaller than the maximum packet size"
+   );
+
+   let epoch = Instant::now();
+   let ramp_up = Duration::from_secs(options.ramp_up);
+   let stop_at = epoch + ramp_up + Duration::from_secs(options.duration);
+   let stats = Arc::new(Stats::default());
+   log::info!(
+      "load testing {} with {} clients in rooms of {}, {:?} pattern, {} packets/s of {} bytes each",
+      url,
+      options.clients,
+      options.room_size,
+      options.pattern,
+      options.packet_rate,
+      payload_size,
+   );
+
+   let mut tasks = Vec::with_capacity(options.clients);
+   let mut room_id = None;
+   for i in 0..options.clients {
+      let client = Client {
+         url: url.clone(),
+         pattern: options.pattern,
+         packet_rate: options.packet_rate,
+         payload_size,
+         epoch,
+         stop_at,
+         stats: Arc::clone(&stats),
+      };
+      let start_at = epoch + ramp_up.mul_f64(i as f64 / options.clients as f64);
+      // The first client of each room hosts it, and lets the others know its ID once it's created.
+      if i % options.room_size == 0 {
+         let (tx, rx) = watch::channel(None);
+         room_id = Some(rx);
+         tasks.push(tokio::spawn(async move {
+            tokio::time::sleep_until(start_at.into()).await;
+            client.run(Role::Host(tx)).await
+         }));
+      } else {
+         let mut room_id = room_id.clone().unwrap();
+         tasks.push(tokio::spawn(async move {
+            tokio::time::sleep_until(start_at.into()).await;
+            let room_id = match room_id.wait_for(Option::is_some).await {
+               Ok(room_id) => room_id.unwrap(),
+               Err(_) => anyhow::bail!("the host of the room failed to connect"),
+            };
+            client.run(Role::Guest(room_id)).await
+         }));
+      }
+   }
+
+   let progress = tokio::spawn(report_progress(Arc::clone(&stats), stop_at));
+   for task in tasks {
+      match task.await {
+         Ok(Ok(())) => (),
+         Ok(Err(error)) => {
+            log::debug!("client failed: {:#}", error);
+            Stats::add(&stats.failed, 1);
+         }
+         Err(error) => {
+            log::error!("client task panicked: {}", error);
+            Stats::add(&stats.failed, 1);
+         }
+      }
+   }
+   progress.abort();
+
+   report_summary(
+      &stats,
+      epoch.elapsed().saturating_sub(ramp_up),
+      options.clients,
+   );
+   Ok(())
+}
+
+/// Logs the traffic of the last second, every second.
+async fn report_progress(stats: Arc<Stats>, stop_at: Instant) {
+   let mut interval = tokio::time::interval(Duration::from_secs(1));
+   let (mut last_sent, mut last_received, mut last_errors) = (0, 0, 0);
+   while Instant::now() < stop_at {
+      interval.tick().await;
+      let sent = Stats::get(&stats.packets_sent);
+      let received = Stats::get(&stats.packets_received);
+      let errors = Stats::get(&stats.errors) + Stats::get(&stats.failed);
+      log::info!(
+         "{} clients connected, {} packets/s sent, {} packets/s received, {} new errors",
+         Stats::get(&stats.connected),
+         sent - last_sent,
+         received - last_received,
+         errors - last_errors,
+      );
+      (last_sent, last_received, last_errors) = (sent, received, errors);
+   }
+}
+
+fn report_summary(stats: &Stats, elapsed: Duration, clients: usize) {
+   let seconds = elapsed.as_secs_f64().max(0.001);
+   let sent = Stats::get(&stats.packets_sent);
+   let received = Stats::get(&stats.packets_received);
+   let errors = Stats::get(&stats.errors);
+   let failed = Stats::get(&stats.failed);
+   let average_latency = Stats::get(&stats.latency_total_micros) / received.max(1);
+
+   log::info!("load test finished");
+   log::info!(
+      "clients: {} joined a room, {} failed out of {}",
+      Stats::get(&stats.joined),
+      failed,
+      clients
+   );
+   log::info!(
+      "sent: {} packets ({:.1} packets/s, {:.1} KiB/s)",
+      sent,
+      sent as f64 / seconds,
+      Stats::get(&stats.bytes_sent) as f64 / seconds / 1024.0
+   );
+   log::info!(
+      "received: {} packets ({:.1} packets/s, {:.1} KiB/s)",
+      received,
+      received as f64 / seconds,
+      Stats::get(&stats.bytes_received) as f64 / seconds / 1024.0
+   );
+   log::info!(
+      "latency: {:.2} ms average, {:.2} ms max",
+      average_latency as f64 / 1000.0,
+      Stats::get(&stats.latency_max_micros) as f64 / 1000.0
+   );
+   log::info!(
+      "errors: {} ({:.3}% of packets sent)",
+      errors,
+      errors as f64 / sent.max(1) as f64 * 100.0
+   );
+}
+
+/// Whether a client hosts its room or joins one.
+enum Role {
+   /// The client hosts a room, and sends its ID to the other clients.
+   Host(watch::Sender<Option<RoomId>>),
+   /// The client joins the room with the given ID.
+   Guest(RoomId),
+}
+
+impl Client {
+   async fn run(self, role: Role) -> anyhow::Result<()> {
+      let (socket, _) = tokio::time::timeout(RESPONSE_TIMEOUT, connect_async(&self.url))
+         .await
+         .context("timed out while connecting")??;
+      let (mut sink, mut stream) = socket.split();
+      match next_message(&mut stream).await? {
+         Message::Binary(version) if version == relay::PROTOCOL_VERSION.to_le_bytes() => (),
+         other => anyhow::bail!("relay sent an unexpected version message: {:?}", other),
+      }
+
+      let (peer_id, host_id) = match role {
+         Role::Host(room_id_tx) => {
+            send(&mut sink, &Packet::Host).await?;
+            match next_packet(&mut stream).await? {
+               Packet::RoomCreated(room_id, peer_id) => {
+                  let _ = room_id_tx.send(Some(room_id));
+                  (peer_id, peer_id)
+               }
+               other => anyhow::bail!("expected RoomCreated, got {:?}", other),
+            }
+         }
+         Role::Guest(room_id) => {
+            send(&mut sink, &Packet::Join(room_id)).await?;
+            match next_packet(&mut stream).await? {
+               Packet::Joined {
+                  peer_id, host_id, ..
+               } => (peer_id, host_id),
+               other => anyhow::bail!("expected Joined, got {:?}", other),
+            }
+         }
+      };
+      Stats::add(&self.stats.connected, 1);
+      Stats::add(&self.stats.joined, 1);
+
+      let stats = Arc::clone(&self.stats);
+      let epoch = self.epoch;
+      let receiver = tokio::spawn(async move {
+         while let Some(Ok(message)) = stream.next().await {
+            receive(&stats, epoch, message);
+         }
+      });
+      let result = self.send_traffic(&mut sink, peer_id, host_id).await;
+      // Give the last packets some time to arrive before disconnecting.
+      tokio::time::sleep(Duration::from_millis(500)).await;
+      receiver.abort();
+      let _ = sink.close().await;
+      self.stats.connected.fetch_sub(1, Ordering::Relaxed);
+      result
+   }
+
+   /// Sends packets until the test is over.
+   async fn send_traffic(
+      &self,
+      sink: &mut SplitSink<Socket, Message>,
+      peer_id: PeerId,
+      host_id: PeerId,
+   ) -> anyhow::Result<()> {
+      let (period, packets_per_tick) = match self.pattern {
+         Pattern::Burst => (Duration::from_secs(1), self.packet_rate),
+         _ => (Duration::from_secs(1) / self.packet_rate, 1),
+      };
+      let to = match self.pattern {
+         Pattern::Direct if peer_id != host_id => host_id,
+         _ => PeerId::BROADCAST,
+      };
+      let mut interval = tokio::time::interval(period);
+      interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Skip);
+      while Instant::now() < self.stop_at {
+         interval.tick().await;
+         for _ in 0..packets_per_tick {
+            let mut payload = vec![0; self.payload_size];
+            let sent_at = self.epoch.elapsed().as_micros() as u64;
+            payload[..8].copy_from_slice(&sent_at.to_le_bytes());
+            let bytes = send(sink, &Packet::Relay(to, payload)).await?;
+            Stats::add(&self.stats.packets_sent, 1);
+            Stats::add(&self.stats.bytes_sent, bytes as u64);
+         }
+      }
+      Ok(())
+   }
+}
+
+/// Counts a message received during the test.
+fn receive(stats: &Stats, epoch: Instant, message: Message) {
+   let Message::Binary(data) = message else {
+      return;
+   };
+   match bincode::deserialize(&data) {
+      Ok(Packet::Relayed(_, payload)) if payload.len() >= 8 => {
+         let sent_at = u64::from_le_bytes(payload[..8].try_into().unwrap());
+         let now = epoch.elapsed().as_micros() as u64;
+         stats.record_latency(Duration::from_micros(now.saturating_sub(sent_at)));
+         Stats::add(&stats.packets_received, 1);
+         Stats::add(&stats.bytes_received, data.len() as u64);
+      }
+      Ok(Packet::Error(error)) => {
+         log::debug!("relay error: {:?}", error);
+         Stats::add(&stats.errors, 1);
+      }
+      // Other clients joining and leaving the room.
+      Ok(_) => (),
+      Err(error) => {
+         log::debug!("malformed packet from relay: {}", error);
+         Stats::add(&stats.errors, 1);
+      }
+   }
+}
+
+async fn send(sink: &mut SplitSink<Socket, Message>, packet: &Packet) -> anyhow::Result<usize> {
+   let data = bincode::serialize(packet)?;
+   let len = data.len();
+   sink.send(Message::Binary(data)).await?;
+   Ok(len)
+}
+
+/// Returns the next message from the relay, skipping pings.
+async fn next_message(stream: &mut SplitStream<Socket>) -> anyhow::Result<Message> {
+   loop {
+      let message = tokio::time::timeout(RESPONSE_TIMEOUT, stream.next())
+         .await
+         .context("timed out waiting for the relay")?
+         .context("the relay closed the connection")??;
+      if !matches!(message, Message::Ping(_) | Message::Pong(_)) {
+         return Ok(message);
+      }
+   }
+}
+
+async fn next_packet(stream: &mut SplitStream<Socket>) -> anyhow::Result<Packet> {
+   match next_message(stream).await? {
+      Message::Binary(data) => Ok(bincode::deserialize(&data)?),
+      other => anyhow::bail!("expected a packet, got {:?}", other),
+   }
+}
@@ -0,0 +1,758 @@
+//! Integration tests, running the relay in-process together with scripted fake peers.
+//!
+//! The fake peers speak the same protocol as the NetCanv client, but keep a much simpler canvas:
+//! a map of pixels split into chunks, where each stroke sets a single pixel. This is enough to
+//! check that strokes reach everyone in the room, and that peers joining late download a canvas
+//! identical to everyone else's.
+
+use std::collections::{BTreeMap, HashMap};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+use futures_util::{SinkExt, StreamExt};
+use netcanv_protocol::client as cl;
+use netcanv_protocol::relay::{self, Packet, PeerId, PublicRoom, RoomId, RoomListing};
+use serde::{Deserialize, Serialize};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Mutex;
+use tokio_tungstenite::tungstenite::Message;
+use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+use crate::{serve, HostGate, State};
+
+/// The name of the fake peers' tool.
+const TOOL: &str = "test-brush";
+
+/// The size of a chunk, in pixels.
+const CHUNK_SIZE: i32 = 256;
+
+/// How long a peer waits for more packets before deciding that the room has settled.
+const SETTLE_TIMEOUT: Duration = Duration::from_millis(200);
+
+/// How long a peer waits for a response it expects before failing the test.
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Starts a relay listening on a free local port, and returns its address.
+async fn start_relay() -> SocketAddr {
+   start_gated_relay(HostGate::default()).await
+}
+
+/// Starts a relay that only creates rooms for peers passing the gate.
+async fn start_gated_relay(host_gate: HostGate) -> SocketAddr {
+   let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+   let address = listener.local_addr().unwrap();
+   let state = Arc::new(Mutex::new(State::new(host_gate)));
+   tokio::spawn(serve(listener, state));
+   address
+}
+
+/// A stroke of the fake peers' tool, setting a single pixel.
+#[derive(Serialize, Deserialize)]
+struct Stroke {
+   position: (i32, i32),
+   color: [u8; 4],
+}
+
+/// The pixels of a single chunk, keyed by their position within the chunk.
+type ChunkPixels = BTreeMap<(i32, i32), [u8; 4]>;
+
+/// A canvas made up of chunks of pixels. Ordered maps are used so that canvases can be compared,
+/// and chunks encoded, deterministically.
+#[derive(Default, PartialEq, Eq, Debug)]
+struct Canvas {
+   chunks: BTreeMap<(i32, i32), ChunkPixels>,
+}
+
+impl Canvas {
+   fn draw(&mut self, stroke: &Stroke) {
+      let (x, y) = stroke.position;
+      let chunk = (x.div_euclid(CHUNK_SIZE), y.div_euclid(CHUNK_SIZE));
+      let pixel = (x.rem_euclid(CHUNK_SIZE), y.rem_euclid(CHUNK_SIZE));
+      self.chunks.entry(chunk).or_default().insert(pixel, stroke.color);
+   }
+
+   fn encode_chunk(&self, position: (i32, i32)) -> Vec<u8> {
+      bincode::serialize(&self.chunks[&position]).unwrap()
+   }
+
+   fn decode_chunk(&mut self, position: (i32, i32), data: &[u8]) {
+      self.chunks.insert(position, bincode::deserialize(data).unwrap());
+   }
+
+   fn pixel_count(&self) -> usize {
+      self.chunks.values().map(|chunk| chunk.len()).sum()
+   }
+}
+
+/// A scripted peer, connected to the relay.
+struct FakePeer {
+   socket: WebSocketStream<MaybeTlsStream<TcpStream>>,
+   nickname: String,
+   peer_id: PeerId,
+   host_id: PeerId,
+   room_id: RoomId,
+   canvas: Canvas,
+   /// The nicknames of the other peers in the room.
+   mates: HashMap<PeerId, String>,
+}
+
+impl FakePeer {
+   /// Connects to the relay, checking its version.
+   async fn connect(relay: SocketAddr) -> WebSocketStream<MaybeTlsStream<TcpStream>> {
+      let (mut socket, _) = connect_async(format!("ws://{}", relay)).await.unwrap();
+      match socket.next().await {
+         Some(Ok(Message::Binary(version))) => {
+            assert_eq!(version, relay::PROTOCOL_VERSION.to_le_bytes());
+         }
+         other => panic!("expected a version message, got {:?}", other),
+      }
+      socket
+   }
+
+   /// Connects to the relay and hosts a new room, solving the relay's challenge if it sends one.
+   async fn host(relay: SocketAddr, nickname: &str) -> Self {
+      let mut socket = Self::connect(relay).await;
+      send(&mut socket, Packet::Host).await;
+      let mut response = recv(&mut socket, RESPONSE_TIMEOUT).await;
+      if let Some(Packet::HostChallenge(challenge)) = response {
+         send(&mut socket, Packet::HostWithProof(challenge.solve())).await;
+         response = recv(&mut socket, RESPONSE_TIMEOUT).await;
+      }
+      match response {
+         Some(Packet::RoomCreated(room_id, peer_id)) => Self {
+            socket,
+            nickname: nickname.to_owned(),
+            peer_id,
+            host_id: peer_id,
+            room_id,
+            canvas: Canvas::default(),
+            mates: HashMap::new(),
+         },
+         other => panic!("expected RoomCreated, got {:?}", other),
+      }
+   }
+
+   /// Connects to the relay, joins the room, and says hello to everyone in it.
+   async fn join(relay: SocketAddr, room_id: RoomId, nickname: &str) -> Self {
+      let mut socket = Self::connect(relay).await;
+      send(&mut socket, Packet::Join(room_id)).await;
+      let mut peer = match recv(&mut socket, RESPONSE_TIMEOUT).await {
+         Some(Packet::Joined {
+            peer_id,
+            host_id,
+            room_id,
+         }) => Self {
+            socket,
+            nickname: nickname.to_owned(),
+            peer_id,
+            host_id,
+            room_id,
+            canvas: Canvas::default(),
+            mates: HashMap::new(),
+         },
+         other => panic!("expected Joined, got {:?}", other),
+      };
+      peer.send_client(PeerId::BROADCAST, cl::Packet::Hello(nickname.to_owned())).await;
+      peer
+   }
+
+   fn is_host(&self) -> bool {
+      self.peer_id == self.host_id
+   }
+
+   async fn send_client(&mut self, to: PeerId, packet: cl::Packet) {
+      let payload = bincode::serialize(&packet).unwrap();
+      send(&mut self.socket, Packet::Relay(to, payload)).await;
+   }
+
+   /// Draws a stroke onto our own canvas, and sends it to everyone else.
+   async fn draw(&mut self, stroke: Stroke) {
+      self.canvas.draw(&stroke);
+      let payload = bincode::serialize(&stroke).unwrap();
+      self
+         .send_client(
+            PeerId::BROADCAST,
+            cl::Packet::Tool(TOOL.to_owned(), payload),
+         )
+         .await;
+   }
+
+   /// Handles packets until none arrive for a while. Returns whether any packets were handled.
+   async fn handle_incoming(&mut self) -> bool {
+      let mut handled_any = false;
+      while let Some(packet) = recv(&mut self.socket, SETTLE_TIMEOUT).await {
+         self.handle_packet(packet).await;
+         handled_any = true;
+      }
+      handled_any
+   }
+
+   async fn handle_packet(&mut self, packet: Packet) {
+      match packet {
+         Packet::Relayed(author, payload) => {
+            let packet: cl::Packet = bincode::deserialize(&payload).unwrap();
+            self.handle_client_packet(author, packet).await;
+         }
+         Packet::HostTransfer(host_id) => self.host_id = host_id,
+         Packet::Disconnected(peer_id) => {
+            self.mates.remove(&peer_id);
+         }
+         other => panic!("{}: unexpected relay packet {:?}", self.nickname, other),
+      }
+   }
+
+   /// Responds to client packets the same way the NetCanv client does.
+   async fn handle_client_packet(&mut self, author: PeerId, packet: cl::Packet) {
+      match packet {
+         cl::Packet::Hello(nickname) => {
+            self.mates.insert(author, nickname);
+            self.send_client(author, cl::Packet::HiThere(self.nickname.clone())).await;
+            self.send_client(author, cl::Packet::Version(cl::PROTOCOL_VERSION)).await;
+            if self.is_host() {
+               // Fake peers only ever draw on the bottom layer.
+               let positions = self.canvas.chunks.keys().copied().collect();
+               self.send_client(author, cl::Packet::ChunkPositions(0, positions)).await;
+            }
+         }
+         cl::Packet::HiThere(nickname) => {
+            self.mates.insert(author, nickname);
+         }
+         cl::Packet::Version(version) => assert!(cl::compatible_with(version)),
+         cl::Packet::ChunkPositions(layer, positions) => {
+            assert_eq!(layer, 0);
+            assert_eq!(
+               author, self.host_id,
+               "only the host may send chunk positions"
+            );
+            if !positions.is_empty() {
+               self.send_client(author, cl::Packet::GetChunks(layer, positions)).await;
+            }
+         }
+         cl::Packet::GetChunks(layer, positions) => {
+            let chunks = positions
+               .into_iter()
+               .map(|position| (position, self.canvas.encode_chunk(position)))
+               .collect();
+            self.send_client(author, cl::Packet::Chunks(layer, chunks)).await;
+         }
+         cl::Packet::Chunks(layer, chunks) => {
+            assert_eq!(layer, 0);
+            for (position, data) in chunks {
+               self.canvas.decode_chunk(position, &data);
+            }
+         }
+         cl::Packet::Tool(tool, payload) => {
+            assert_eq!(tool, TOOL);
+            let stroke: Stroke = bincode::deserialize(&payload).unwrap();
+            self.canvas.draw(&stroke);
+         }
+         other => panic!("{}: unexpected client packet {:?}", self.nickname, other),
+      }
+   }
+}
+
+async fn send(socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>, packet: Packet) {
+   let data = bincode::serialize(&packet).unwrap();
+   socket.send(Message::Binary(data)).await.unwrap();
+}
+
+/// Receives the next relay packet, skipping pings. Returns `None` if no packet arrives in time.
+async fn recv(
+   socket: &mut WebSocketStream<MaybeTlsStream<TcpStream>>,
+   timeout: Duration,
+) -> Option<Packet> {
+   loop {
+      match tokio::time::timeout(timeout, socket.next()).await {
+         Ok(Some(Ok(Message::Binary(data)))) => return Some(bincode::deserialize(&data).unwrap()),
+         Ok(Some(Ok(Message::Ping(_) | Message::Pong(_)))) => continue,
+         Ok(other) => panic!("unexpected message from relay: {:?}", other),
+         Err(_) => return None,
+      }
+   }
+}
+
+/// Lets all the peers handle their incoming packets, until none of them receive any more.
+async fn settle(peers: &mut [FakePeer]) {
+   loop {
+      let mut handled_any = false;
+      for peer in peers.iter_mut() {
+         handled_any |= peer.handle_incoming().await;
+      }
+      if !handled_any {
+         break;
+      }
+   }
+}
+
+/// Asserts that all the peers see the same canvas.
+fn assert_converged(peers: &[FakePeer]) {
+   for peer in &peers[1..] {
+      assert_eq!(
+         peer.canvas, peers[0].canvas,
+         "{}'s canvas differs from {}'s",
+         peer.nickname, peers[0].nickname
+      );
+   }
+}
+
+/// A stroke unique to the given peer and step, so that strokes never overwrite each other and
+/// the order in which they arrive doesn't matter. Steps spread the strokes over several chunks.
+fn stroke(peer: usize, step: usize) -> Stroke {
+   let x = step as i32 * 37 - 300;
+   let y = peer as i32 * 101 - 150;
+   Stroke {
+      position: (x, y),
+      color: [peer as u8, step as u8, 0, 255],
+   }
+}
+
+#[tokio::test]
+async fn peers_discover_each_other() {
+   let relay = start_relay().await;
+   let host = FakePeer::host(relay, "host").await;
+   let room_id = host.room_id;
+   let mut peers = vec![host];
+   for i in 1..4 {
+      peers.push(FakePeer::join(relay, room_id, &format!("peer {}", i)).await);
+      settle(&mut peers).await;
+   }
+
+   for peer in &peers {
+      assert_eq!(
+         peer.mates.len(),
+         peers.len() - 1,
+         "{} is missing mates",
+         peer.nickname
+      );
+      assert_eq!(peer.host_id, peers[0].peer_id);
+   }
+}
+
+#[tokio::test]
+async fn strokes_converge() {
+   let relay = start_relay().await;
+   let host = FakePeer::host(relay, "host").await;
+   let room_id = host.room_id;
+   let mut peers = vec![host];
+   for i in 1..4 {
+      peers.push(FakePeer::join(relay, room_id, &format!("peer {}", i)).await);
+   }
+   settle(&mut peers).await;
+
+   // Everyone draws at the same time, interleaving their strokes.
+   for step in 0..20 {
+      for (i, peer) in peers.iter_mut().enumerate() {
+         peer.draw(stroke(i, step)).await;
+      }
+   }
+   settle(&mut peers).await;
+
+   assert_eq!(peers[0].canvas.pixel_count(), 4 * 20);
+   assert_converged(&peers);
+}
+
+#[tokio::test]
+async fn late_joiner_downloads_canvas() {
+   let relay = start_relay().await;
+   let host = FakePeer::host(relay, "host").await;
+   let room_id = host.room_id;
+   let mut peers = vec![host, FakePeer::join(relay, room_id, "early").await];
+   settle(&mut peers).await;
+
+   for step in 0..30 {
+      peers[0].draw(stroke(0, step)).await;
+      peers[1].draw(stroke(1, step)).await;
+   }
+   settle(&mut peers).await;
+
+   peers.push(FakePeer::join(relay, room_id, "late").await);
+   settle(&mut peers).await;
+
+   assert!(
+      peers[0].canvas.chunks.len() > 1,
+      "the strokes should span several chunks"
+   );
+   assert_converged(&peers);
+
+   // Strokes drawn after joining reach the late joiner too.
+   peers[2].draw(stroke(2, 0)).await;
+   peers[0].draw(stroke(0, 30)).await;
+   settle(&mut peers).await;
+   assert_converged(&peers);
+}
+
+#[tokio::test]
+async fn host_role_is_transferred_when_host_leaves() {
+   let relay = start_relay().await;
+   let host = FakePeer::host(relay, "host").await;
+   let room_id = host.room_id;
+   let mut peers = vec![host];
+   for i in 1..3 {
+      peers.push(FakePeer::join(relay, room_id, &format!("peer {}", i)).await);
+   }
+   settle(&mut peers).await;
+   for step in 0..10 {
+      peers[0].draw(stroke(0, step)).await;
+   }
+   settle(&mut peers).await;
+
+   let old_host = peers.remove(0);
+   let old_host_id = old_host.peer_id;
+   drop(old_host);
+   settle(&mut peers).await;
+
+   // The role goes to whoever has been in the room the longest.
+   let new_host_id = peers[0].host_id;
+   assert_ne!(new_host_id, old_host_id);
+   assert_eq!(new_host_id, peers[0].peer_id);
+   for peer in &peers {
+      assert_eq!(
+         peer.host_id, new_host_id,
+         "{} disagrees on the host",
+         peer.nickname
+      );
+      assert!(!peer.mates.contains_key(&old_host_id));
+   }
+
+   // The new host serves the canvas to people joining afterwards.
+   peers.push(FakePeer::join(relay, room_id, "late").await);
+   settle(&mut peers).await;
+   assert_eq!(peers[0].canvas.pixel_count(), 10);
+   assert_converged(&peers);
+}
+
+#[tokio::test]
+async fn new_host_learns_that_the_room_is_invite_only() {
+   let relay = start_relay().await;
+   let host = FakePeer::host(relay, "host").await;
+   let room_id = host.room_id;
+   let mut peers = vec![host, FakePeer::join(relay, room_id, "guest").await];
+   settle(&mut peers).await;
+
+   send(&mut peers[0].socket, Packet::SetInviteOnly(true)).await;
+   tokio::time::sleep(SETTLE_TIMEOUT).await;
+   drop(peers.remove(0));
+
+   let mut guest = peers.remove(0);
+   let mut packets = Vec::new();
+   while let Some(packet) = recv(&mut guest.socket, SETTLE_TIMEOUT).await {
+      packets.push(packet);
+   }
+   assert!(packets.contains(&Packet::HostTransfer(guest.peer_id)));
+   assert_eq!(packets.last(), Some(&Packet::SetInviteOnly(true)));
+}
+
+#[tokio::test]
+async fn voice_frames_reach_everyone_else() {
+   let relay = start_relay().await;
+   let host = FakePeer::host(relay, "host").await;
+   let room_id = host.room_id;
+   let mut peers = vec![host];
+   for i in 1..3 {
+      peers.push(FakePeer::join(relay, room_id, &format!("peer {}", i)).await);
+   }
+   settle(&mut peers).await;
+
+   let frame = vec![0xf8, 0xff, 0xfe];
+   let speaker_id = peers[1].peer_id;
+   send(&mut peers[1].socket, Packet::Voice(frame.clone())).await;
+   for (i, peer) in peers.iter_mut().enumerate() {
+      let received = recv(&mut peer.socket, SETTLE_TIMEOUT).await;
+      if i == 1 {
+         assert_eq!(received, None, "the speaker shouldn't hear themselves");
+      } else {
+         assert_eq!(
+            received,
+            Some(Packet::VoiceRelayed(speaker_id, frame.clone()))
+         );
+      }
+   }
+}
+
+#[tokio::test]
+async fn peer_switches_rooms_without_reconnecting() {
+   let relay = start_relay().await;
+   let first_host = FakePeer::host(relay, "first host").await;
+   let first_room_id = first_host.room_id;
+   let mut first_room = vec![
+      first_host,
+      FakePeer::join(relay, first_room_id, "switcher").await,
+   ];
+   settle(&mut first_room).await;
+   let second_host = FakePeer::host(relay, "second host").await;
+   let second_room_id = second_host.room_id;
+
+   let mut switcher = first_room.pop().unwrap();
+   let old_peer_id = switcher.peer_id;
+   send(&mut switcher.socket, Packet::Leave).await;
+   assert_eq!(
+      recv(&mut switcher.socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Left)
+   );
+   settle(&mut first_room).await;
+   assert!(!first_room[0].mates.contains_key(&old_peer_id));
+
+   send(&mut switcher.socket, Packet::Join(second_room_id)).await;
+   match recv(&mut switcher.socket, RESPONSE_TIMEOUT).await {
+      Some(Packet::Joined {
+         peer_id,
+         host_id,
+         room_id,
+      }) => {
+         assert_eq!(room_id, second_room_id);
+         switcher.peer_id = peer_id;
+         switcher.host_id = host_id;
+         switcher.room_id = room_id;
+      }
+      other => panic!("expected Joined, got {:?}", other),
+   }
+   switcher.mates.clear();
+   switcher.send_client(PeerId::BROADCAST, cl::Packet::Hello("switcher".to_owned())).await;
+   let mut second_room = vec![second_host, switcher];
+   settle(&mut second_room).await;
+   assert_eq!(second_room[0].mates.len(), 1);
+   assert_eq!(second_room[1].mates.len(), 1);
+
+   // Strokes drawn in the old room no longer reach the peer that left it.
+   first_room[0].draw(stroke(0, 0)).await;
+   second_room[0].draw(stroke(1, 0)).await;
+   settle(&mut first_room).await;
+   settle(&mut second_room).await;
+   assert_eq!(second_room[1].canvas.pixel_count(), 1);
+   assert_converged(&second_room);
+}
+
+#[tokio::test]
+async fn room_creation_is_gated() {
+   let relay = start_gated_relay(HostGate {
+      difficulty: 8,
+      token: Some("shared secret".to_owned()),
+   })
+   .await;
+
+   // Solving the challenge is enough to host, and the room works like any other.
+   let host = FakePeer::host(relay, "host").await;
+   let room_id = host.room_id;
+   let mut peers = vec![host, FakePeer::join(relay, room_id, "guest").await];
+   settle(&mut peers).await;
+   assert_eq!(peers[0].mates.len(), 1);
+
+   // So is the token.
+   let mut socket = FakePeer::connect(relay).await;
+   send(
+      &mut socket,
+      Packet::HostWithToken {
+         token: "shared secret".to_owned(),
+         room_id: None,
+      },
+   )
+   .await;
+   assert!(matches!(
+      recv(&mut socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::RoomCreated(..))
+   ));
+
+   let mut socket = FakePeer::connect(relay).await;
+   send(
+      &mut socket,
+      Packet::HostWithToken {
+         token: "wrong secret".to_owned(),
+         room_id: None,
+      },
+   )
+   .await;
+   assert_eq!(
+      recv(&mut socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Error(relay::Error::InvalidHostToken))
+   );
+
+   // Answering without a challenge, or with a wrong solution, gets the peer nowhere.
+   let mut socket = FakePeer::connect(relay).await;
+   send(&mut socket, Packet::HostWithProof(0)).await;
+   assert_eq!(
+      recv(&mut socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Error(relay::Error::InvalidHostProof))
+   );
+
+   let mut socket = FakePeer::connect(relay).await;
+   send(&mut socket, Packet::Host).await;
+   let challenge = match recv(&mut socket, RESPONSE_TIMEOUT).await {
+      Some(Packet::HostChallenge(challenge)) => challenge,
+      other => panic!("expected HostChallenge, got {:?}", other),
+   };
+   let wrong = (0..).find(|&proof| !challenge.is_solved_by(proof)).unwrap();
+   send(&mut socket, Packet::HostWithProof(wrong)).await;
+   assert_eq!(
+      recv(&mut socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Error(relay::Error::InvalidHostProof))
+   );
+}
+
+#[tokio::test]
+async fn room_creation_requires_token_without_challenge() {
+   let relay = start_gated_relay(HostGate {
+      difficulty: 0,
+      token: Some("shared secret".to_owned()),
+   })
+   .await;
+   let mut socket = FakePeer::connect(relay).await;
+   send(&mut socket, Packet::Host).await;
+   assert_eq!(
+      recv(&mut socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Error(relay::Error::HostTokenRequired))
+   );
+}
+
+#[tokio::test]
+async fn hosts_can_pick_room_ids() {
+   let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+   let relay = listener.local_addr().unwrap();
+   let mut state = State::new(HostGate::default());
+   state.rooms.reserve_room_ids(vec![RoomId(*b"ADMIN1")]);
+   tokio::spawn(serve(listener, Arc::new(Mutex::new(state))));
+
+   let mut socket = FakePeer::connect(relay).await;
+   send(&mut socket, Packet::HostCustom(RoomId(*b"MYROOM"))).await;
+   match recv(&mut socket, RESPONSE_TIMEOUT).await {
+      Some(Packet::RoomCreated(room_id, _)) => assert_eq!(room_id, RoomId(*b"MYROOM")),
+      other => panic!("expected RoomCreated, got {:?}", other),
+   }
+   let mut guest = FakePeer::join(relay, RoomId(*b"MYROOM"), "guest").await;
+   assert_eq!(guest.room_id, RoomId(*b"MYROOM"));
+
+   // Taken and reserved IDs are refused, but the host can try again with another one.
+   send(&mut guest.socket, Packet::HostCustom(RoomId(*b"MYROOM"))).await;
+   assert_eq!(
+      recv(&mut guest.socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Error(relay::Error::RoomIdTaken))
+   );
+   send(&mut guest.socket, Packet::HostCustom(RoomId(*b"ADMIN1"))).await;
+   assert_eq!(
+      recv(&mut guest.socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Error(relay::Error::RoomIdReserved))
+   );
+   send(&mut guest.socket, Packet::HostCustom(RoomId(*b"OTHER1"))).await;
+   assert!(matches!(
+      recv(&mut guest.socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::RoomCreated(..))
+   ));
+
+   // The ID is free again once everyone leaves the room.
+   drop(socket);
+   let mut socket = FakePeer::connect(relay).await;
+   let mut response = None;
+   for _attempt in 0..20 {
+      send(&mut socket, Packet::HostCustom(RoomId(*b"MYROOM"))).await;
+      response = recv(&mut socket, RESPONSE_TIMEOUT).await;
+      if response != Some(Packet::Error(relay::Error::RoomIdTaken)) {
+         break;
+      }
+      tokio::time::sleep(SETTLE_TIMEOUT).await;
+   }
+   assert!(matches!(response, Some(Packet::RoomCreated(..))));
+}
+
+#[tokio::test]
+async fn joining_directly_hosted_rooms_redirects() {
+   let relay = start_relay().await;
+   let mut host = FakePeer::host(relay, "host").await;
+   send(
+      &mut host.socket,
+      Packet::CreateInvite {
+         single_use: true,
+         lifetime_secs: None,
+      },
+   )
+   .await;
+   let invite = match recv(&mut host.socket, RESPONSE_TIMEOUT).await {
+      Some(Packet::InviteCreated(invite)) => invite,
+      other => panic!("expected InviteCreated, got {:?}", other),
+   };
+   let mut guest = FakePeer::join(relay, host.room_id, "guest").await;
+
+   let direct_address = "ws://203.0.113.7:51234".to_owned();
+   send(
+      &mut host.socket,
+      Packet::SetDirectAddress(Some(direct_address.clone())),
+   )
+   .await;
+   tokio::time::sleep(SETTLE_TIMEOUT).await;
+   let mut socket = FakePeer::connect(relay).await;
+   send(&mut socket, Packet::Join(host.room_id)).await;
+   assert_eq!(
+      recv(&mut socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Redirect(direct_address.clone()))
+   );
+   // Invites lead to the same place.
+   let mut socket = FakePeer::connect(relay).await;
+   send(&mut socket, Packet::JoinWithInvite(invite)).await;
+   assert_eq!(
+      recv(&mut socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Redirect(direct_address))
+   );
+
+   // Only the host can decide where the room is hosted.
+   send(&mut guest.socket, Packet::SetDirectAddress(None)).await;
+   assert_eq!(
+      recv(&mut guest.socket, RESPONSE_TIMEOUT).await,
+      Some(Packet::Error(relay::Error::NotTheHost))
+   );
+
+   send(&mut host.socket, Packet::SetDirectAddress(None)).await;
+   tokio::time::sleep(SETTLE_TIMEOUT).await;
+   let late = FakePeer::join(relay, host.room_id, "late").await;
+   assert_eq!(late.host_id, host.peer_id);
+}
+
+#[tokio::test]
+async fn public_room_listings_can_be_updated() {
+   let relay = start_relay().await;
+   let mut host = FakePeer::host(relay, "host").await;
+
+   let listing = |name: &str, description: &str| RoomListing {
+      name: name.to_owned(),
+      description: description.to_owned(),
+   };
+   let list_rooms = || async {
+      let mut socket = FakePeer::connect(relay).await;
+      send(&mut socket, Packet::ListRooms).await;
+      match recv(&mut socket, RESPONSE_TIMEOUT).await {
+         Some(Packet::RoomList(rooms)) => rooms,
+         other => panic!("expected RoomList, got {:?}", other),
+      }
+   };
+
+   send(
+      &mut host.socket,
+      Packet::SetPublic(Some(listing("sketches", "anything goes"))),
+   )
+   .await;
+   tokio::time::sleep(SETTLE_TIMEOUT).await;
+   assert_eq!(
+      list_rooms().await,
+      vec![PublicRoom {
+         room_id: host.room_id,
+         name: "sketches".into(),
+         description: "anything goes".into(),
+         players: 1,
+         invite_only: false,
+      }]
+   );
+
+   // Sending a new listing replaces the old one, rather than listing the room twice.
+   send(
+      &mut host.socket,
+      Packet::SetPublic(Some(listing("pixel art", "16 colors max"))),
+   )
+   .await;
+   tokio::time::sleep(SETTLE_TIMEOUT).await;
+   let rooms = list_rooms().await;
+   assert_eq!(rooms.len(), 1);
+   assert_eq!(rooms[0].name, "pixel art");
+   assert_eq!(rooms[0].description, "16 colors max");
+
+   send(&mut host.socket, Packet::SetPublic(None)).await;
+   tokio::time::sleep(SETTLE_TIMEOUT).await;
+   assert!(list_rooms().await.is_empty());
+}
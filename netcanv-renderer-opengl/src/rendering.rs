@@ -405,6 +405,10 @@ impl RenderState {
             );
             premultiply_alpha = true;
          },
+         BlendMode::Max => unsafe {
+            self.gl.blend_equation(glow::MAX);
+            self.gl.blend_func(glow::ONE, glow::ONE);
+         },
       }
       unsafe {
          self.gl.uniform_1_f32(
@@ -862,14 +866,19 @@ impl RenderBackend for OpenGlBackend {
    }
 
    fn framebuffer(&mut self, rect: Rect, framebuffer: &Framebuffer) {
+      self.translucent_framebuffer(rect, framebuffer, 1.0);
+   }
+
+   fn translucent_framebuffer(&mut self, rect: Rect, framebuffer: &Framebuffer, opacity: f32) {
       assert!(
          self.state.gl_state.borrow().framebuffer != Some(framebuffer.framebuffer()),
          "cannot render a framebuffer to itself"
       );
+      let color = Color::WHITE.with_alpha((opacity.clamp(0.0, 1.0) * 255.0).round() as u8);
       self.start();
       self.shape().rect(
-         Vertex::textured(rect.top_left(), point(0.0, 1.0)),
-         Vertex::textured(rect.bottom_right(), point(1.0, 0.0)),
+         Vertex::textured_colored(rect.top_left(), point(0.0, 1.0), color),
+         Vertex::textured_colored(rect.bottom_right(), point(1.0, 0.0), color),
       );
       let texture = framebuffer.texture();
       unsafe {
@@ -903,6 +912,10 @@ impl RenderBackend for OpenGlBackend {
       self.state.transform_mut().matrix *= Mat3A::from_scale(to_vec2(scale));
    }
 
+   fn rotate(&mut self, angle: f32) {
+      self.state.transform_mut().matrix *= Mat3A::from_angle(angle);
+   }
+
    fn set_blend_mode(&mut self, new_blend_mode: BlendMode) {
       self.state.transform_mut().blend_mode = new_blend_mode;
       self.state.apply_transform();
@@ -146,6 +146,18 @@ impl Gpu {
                   dst_factor: wgpu::BlendFactor::One,
                },
             },
+            BlendMode::Max => wgpu::BlendState {
+               color: wgpu::BlendComponent {
+                  src_factor: wgpu::BlendFactor::One,
+                  operation: wgpu::BlendOperation::Max,
+                  dst_factor: wgpu::BlendFactor::One,
+               },
+               alpha: wgpu::BlendComponent {
+                  src_factor: wgpu::BlendFactor::One,
+                  operation: wgpu::BlendOperation::Max,
+                  dst_factor: wgpu::BlendFactor::One,
+               },
+            },
          }),
          write_mask: wgpu::ColorWrites::ALL,
       }
@@ -105,7 +105,14 @@ impl Images {
       }
    }
 
-   pub fn add(&mut self, rect: Rect, color: Option<Color>, binding: u32, filter: ScalingFilter) {
+   pub fn add(
+      &mut self,
+      rect: Rect,
+      color: Option<Color>,
+      opacity: f32,
+      binding: u32,
+      filter: ScalingFilter,
+   ) {
       assert!(
          self.image_rect_data.len() < self.image_rect_data.capacity(),
          "too many images without flushing"
@@ -119,6 +126,7 @@ impl Images {
          rect: vec4(rect.x(), rect.y(), rect.width(), rect.height()),
          color: color.unwrap_or(Color::TRANSPARENT),
          rendition,
+         opacity,
       });
       self.image_bindings.push(binding);
    }
@@ -177,6 +185,7 @@ struct ImageRectData {
    rect: Vec4,
    color: Color,
    rendition: Rendition,
+   opacity: f32,
 }
 
 bitflags! {
@@ -11,6 +11,7 @@ struct ImageRect {
    rect: vec4f,
    color: u32,
    rendition: u32,
+   opacity: f32,
 }
 
 const rendition_colorize = 0x00000001u;
@@ -52,5 +53,6 @@ fn main_fs(vertex: Vertex) -> @location(0) vec4f {
       let tint_color = unpack4x8unorm(data.color);
       color = vec4f(tint_color.r, tint_color.g, tint_color.b, tint_color.a * color.a);
    }
+   color.a *= data.opacity;
    return color;
 }
@@ -377,7 +377,13 @@ impl RenderBackend for WgpuBackend {
       {
          let rect = self.current_transform().transform.translate_rect(rect);
          self.switch_pass(Pass::Images);
-         self.images.add(rect, image.color, image.index, ScalingFilter::default());
+         self.images.add(
+            rect,
+            image.color,
+            1.0,
+            image.index,
+            ScalingFilter::default(),
+         );
          if self.images.needs_flush() {
             self.flush("image");
          }
@@ -385,11 +391,21 @@ impl RenderBackend for WgpuBackend {
    }
 
    fn framebuffer(&mut self, rect: Rect, framebuffer: &Self::Framebuffer) {
+      self.translucent_framebuffer(rect, framebuffer, 1.0);
+   }
+
+   fn translucent_framebuffer(
+      &mut self,
+      rect: Rect,
+      framebuffer: &Self::Framebuffer,
+      opacity: f32,
+   ) {
       let rect = self.current_transform().transform.translate_rect(rect);
       self.switch_pass(Pass::Images);
       self.images.add(
          rect,
          None,
+         opacity.clamp(0.0, 1.0),
          framebuffer.image_storage_index,
          framebuffer.scaling_filter,
       );
@@ -426,6 +442,13 @@ impl RenderBackend for WgpuBackend {
       self.current_transform_mut().transform = state.transform.scale(vector_to_vec2(scale));
    }
 
+   fn rotate(&mut self, angle: f32) {
+      // Same as with scaling, rotation always results in a matrix.
+      self.flush("rotate");
+      let state = self.current_transform();
+      self.current_transform_mut().transform = state.transform.rotate(angle);
+   }
+
    fn set_blend_mode(&mut self, new_blend_mode: BlendMode) {
       if new_blend_mode != self.current_transform().blend_mode {
          self.flush("set_blend_mode");
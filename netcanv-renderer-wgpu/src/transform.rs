@@ -56,6 +56,15 @@ impl Transform {
       }
    }
 
+   pub fn rotate(&self, angle: f32) -> Self {
+      match *self {
+         Transform::Translation(t) => {
+            Transform::Matrix(Mat3A::from_translation(t) * Mat3A::from_angle(angle))
+         }
+         Transform::Matrix(m) => Transform::Matrix(m * Mat3A::from_angle(angle)),
+      }
+   }
+
    pub fn is_matrix(&self) -> bool {
       matches!(self, Self::Matrix(..))
    }
@@ -101,15 +101,18 @@ pub enum BlendMode {
    Add = 2,
    /// Inverts colors.
    Invert = 3,
+   /// Takes the maximum of the source and destination colors, component-wise.
+   Max = 4,
 }
 
 impl BlendMode {
    // NOTE: Indices here must match those of the enum.
-   pub const VARIANTS: [BlendMode; 4] = [
+   pub const VARIANTS: [BlendMode; 5] = [
       BlendMode::Replace,
       BlendMode::Alpha,
       BlendMode::Add,
       BlendMode::Invert,
+      BlendMode::Max,
    ];
 }
 
@@ -144,6 +147,10 @@ pub trait RenderBackend: Renderer {
    /// Drawing the framebuffer that is currently being rendered to is undefined behavior.
    fn framebuffer(&mut self, rect: Rect, framebuffer: &Self::Framebuffer);
 
+   /// Draws a framebuffer like [`RenderBackend::framebuffer`], with its alpha channel multiplied by
+   /// the given opacity, from 0 to 1.
+   fn translucent_framebuffer(&mut self, rect: Rect, framebuffer: &Self::Framebuffer, opacity: f32);
+
    /// Uploads RGBA pixels to the framebuffer.
    ///
    /// `pixels`'s length must be equal to `width * height * 4`.
@@ -167,6 +174,9 @@ pub trait RenderBackend: Renderer {
    /// Scales the transform matrix by the given factor.
    fn scale(&mut self, scale: Vector);
 
+   /// Rotates the transform matrix clockwise by the given angle, in radians.
+   fn rotate(&mut self, angle: f32);
+
    /// Sets the current blend mode.
    ///
    /// Blend modes are part of the transformation stack. If used inside `push()` and `pop()`,
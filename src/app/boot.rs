@@ -1,4 +1,4 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
 use nysa::global as bus;
@@ -10,6 +10,7 @@ use crate::cli;
 use crate::common::{Error, Fatal};
 use crate::config::config;
 use crate::net::peer::{self, Peer};
+use crate::net::recording::Replay;
 use crate::net::socket::SocketSystem;
 
 pub struct State {
@@ -25,17 +26,37 @@ impl State {
       assets: Box<Assets>,
       socket_system: Arc<SocketSystem>,
    ) -> Box<dyn AppState> {
+      if let Some(link) = cli.link {
+         let mut peer = link.join(Arc::clone(&socket_system), &config().lobby.nickname);
+         Self::start_recording(&mut peer, cli.record.as_deref());
+         let peer = Some(peer);
+         return Box::new(Self {
+            assets,
+            socket_system,
+            peer,
+            canvas: None,
+         });
+      }
+
       match cli.command {
          Some(cli::Commands::HostRoom {
             nickname,
             load_canvas,
+            room_id,
             relay_address,
+            admin_password,
+            host_token,
          }) => {
-            let peer = Some(Peer::host(
+            let mut peer = Peer::host(
                Arc::clone(&socket_system),
                nickname.unwrap_or(config().lobby.nickname.to_owned()).as_str(),
                relay_address.unwrap_or(config().lobby.relay.to_owned()).as_str(),
-            ));
+               room_id,
+            );
+            peer.set_admin_password(admin_password);
+            peer.set_host_token(host_token);
+            Self::start_recording(&mut peer, cli.record.as_deref());
+            let peer = Some(peer);
 
             Box::new(Self {
                assets,
@@ -50,12 +71,14 @@ impl State {
             nickname,
             relay_address,
          }) => {
-            let peer = Some(Peer::join(
+            let mut peer = Peer::join(
                Arc::clone(&socket_system),
                nickname.unwrap_or(config().lobby.nickname.to_owned()).as_str(),
                relay_address.unwrap_or(config().lobby.relay.to_owned()).as_str(),
                room_id,
-            ));
+            );
+            Self::start_recording(&mut peer, cli.record.as_deref());
+            let peer = Some(peer);
 
             Box::new(Self {
                assets,
@@ -64,9 +87,33 @@ impl State {
                canvas: save_canvas
             })
          }
+         Some(cli::Commands::Replay { file, speed }) => match Replay::load(&file, speed) {
+            Ok(replay) => {
+               let peer = Some(Peer::replay(&config().lobby.nickname, replay));
+               Box::new(Self {
+                  assets,
+                  socket_system,
+                  peer,
+                  canvas: None,
+               })
+            }
+            Err(error) => {
+               tracing::error!("cannot replay {:?}: {:?}", file, error);
+               Box::new(lobby::State::new(assets, Arc::clone(&socket_system)))
+            }
+         },
          _ => Box::new(lobby::State::new(assets, Arc::clone(&socket_system))),
       }
    }
+
+   /// Starts recording the peer's session to the given file, if there is one.
+   fn start_recording(peer: &mut Peer, path: Option<&Path>) {
+      if let Some(path) = path {
+         if let Err(error) = peer.start_recording(path) {
+            tracing::error!("cannot record to {:?}: {:?}", path, error);
+         }
+      }
+   }
 }
 
 impl AppState for State {
@@ -4,22 +4,30 @@ use std::fmt::Display;
 use std::path::PathBuf;
 use std::sync::Arc;
 
-use rfd::FileDialog;
+use rfd::AsyncFileDialog;
 use netcanv_i18n::translate_enum::TranslateEnum;
-use netcanv_protocol::relay::RoomId;
-use netcanv_renderer::paws::{vector, AlignH, AlignV, Color, Layout, LineCap, Padding, Rect, Renderer};
+use netcanv_protocol::relay::{InviteToken, PublicRoom, RoomId};
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Layout, LineCap, Padding, Rect, Renderer};
 use netcanv_renderer::{Font, Image as ImageTrait, RenderBackend};
 use nysa::global as bus;
+use web_time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use whd_common::{WALLHACKD_SLOGAN, WALLHACKD_VERSION, WALLHACKD_YEAR};
 
 use crate::app::{paint, AppState, StateArgs};
 use crate::assets::{self, Assets, ColorScheme};
+use crate::autosave;
 use crate::backend::Backend;
-use crate::common::{Error, Fatal, StrExt};
-use crate::config::{self, config};
+use crate::common::{truncate_text, Error, Fatal, StrExt};
+use crate::config::{self, config, RecentRoom, RelayProfile};
+use crate::file_dialog;
+use crate::net::lan::{LanBrowser, LanRoom};
 use crate::net::peer::{self, Peer};
-use crate::net::socket::SocketSystem;
+use crate::net::relay_ping::RelayPing;
+use crate::net::room_link::RoomLink;
+use crate::net::room_list::RoomListRequest;
+use crate::net::socket::{ConnectionStage, SocketSystem};
 use crate::strings::Strings;
+use crate::telemetry;
 use crate::ui::view::View;
 use crate::ui::*;
 
@@ -27,6 +35,7 @@ use crate::ui::*;
 #[derive(Clone)]
 pub struct LobbyColors {
    pub background: Color,
+   pub relay_reachable: Color,
 }
 
 /// A status returned from some other part of the app.
@@ -43,6 +52,61 @@ impl<T: Display> From<T> for Status {
    }
 }
 
+/// The tab shown in the rooms Expand.
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum RoomsTab {
+   Recent,
+   Public,
+   /// Rooms hosted on the local network.
+   Lan,
+}
+
+/// A problem with what was typed into one of the lobby's text fields.
+#[derive(Clone, Copy)]
+enum FieldError {
+   Empty,
+   TooLong { max: usize },
+   InvalidRoomIdLength,
+   WrongLength { length: usize },
+   InvalidCharacters,
+}
+
+impl FieldError {
+   /// Returns the short hint shown under the field.
+   fn hint(self, tr: &Strings) -> String {
+      match self {
+         FieldError::Empty => tr.lobby_field_required.clone(),
+         FieldError::TooLong { max } => {
+            tr.lobby_field_too_long.format().with("max-length", max).done()
+         }
+         FieldError::InvalidRoomIdLength => tr
+            .lobby_field_room_id_length
+            .format()
+            .with("length", RoomId::LEN)
+            .with("invite-length", InviteToken::LEN)
+            .done(),
+         FieldError::WrongLength { length } => {
+            tr.lobby_field_length.format().with("length", length).done()
+         }
+         FieldError::InvalidCharacters => tr.lobby_field_letters_and_digits.clone(),
+      }
+   }
+}
+
+/// Whether the relay in the relay server field can be reached.
+enum RelayHealth {
+   /// The relay hasn't been checked yet.
+   Unknown,
+   Checking,
+   /// The relay responded, with the given round-trip time.
+   Reachable(Duration),
+   /// The relay could not be reached, for the given (translated) reason.
+   Unreachable(String),
+}
+
+/// A bus message carrying the file the user picked to host a room from.
+struct HostFromFile(PathBuf);
+
 /// The lobby app state.
 pub struct State {
    assets: Box<Assets>,
@@ -54,29 +118,76 @@ pub struct State {
    nickname_field: TextField,
    relay_field: TextField,
    room_id_field: TextField,
+   room_name_field: TextField,
+   room_description_field: TextField,
+   custom_room_id_field: TextField,
 
    join_expand: Expand,
    host_expand: Expand,
+   rooms_expand: Expand,
+   rooms_tab: RoomsTab,
+   public_room: bool,
+   /// Whether rooms are hosted on the local network rather than on the relay.
+   lan_room: bool,
+   /// Whether rooms are hosted on a relay run by NetCanv itself, reachable from the Internet
+   /// through a port opened on the router.
+   direct_room: bool,
+   public_rooms: Vec<PublicRoom>,
+   public_rooms_scroll: usize,
+   /// The room that was last clicked in the public or LAN room list, and when. Used for detecting
+   /// double clicks.
+   last_public_room_click: Option<(RoomId, Instant)>,
 
    main_view: View,
    panel_view: View,
    language_menu: ContextMenu,
+   relay_profiles_menu: ContextMenu,
+   relay_profile_name_field: TextField,
+   usage_statistics_menu: ContextMenu,
+   usage_statistics_endpoint_field: TextField,
+   /// The report shown in the usage statistics menu. Refreshed every frame while it's open.
+   usage_statistics_preview: String,
 
    // net
    status: Status,
    peer: Option<Peer>,
+   /// The peer of the room we've just left, still connected to the relay. It's reused for the next
+   /// room hosted or joined on the same relay, instead of connecting all over again.
+   relay_peer: Option<Peer>,
+   room_list: Option<RoomListRequest>,
+   /// Looks for rooms on the local network while the LAN tab is open.
+   lan_browser: Option<LanBrowser>,
+   relay_ping: Option<RelayPing>,
+   relay_health: RelayHealth,
+   /// The relay address `relay_health` refers to.
+   relay_health_address: String,
+   /// When the relay address was last changed, or when the relay was last checked.
+   relay_health_time: Instant,
    image_file: Option<PathBuf>, // when this is Some, the canvas is loaded from a file
+   /// The autosave left behind by a session that didn't end cleanly, offered for recovery.
+   recovery: Option<autosave::Recovery>,
 }
 
 impl State {
    const BANNER_HEIGHT: f32 = 128.0;
-   const MENU_HEIGHT: f32 = 294.0;
+   const MENU_HEIGHT: f32 = 334.0;
    const STATUS_HEIGHT: f32 = 8.0 + 48.0;
 
    const VIEW_BOX_PADDING: f32 = 16.0;
    const VIEW_BOX_WIDTH: f32 = 388.0 + Self::VIEW_BOX_PADDING * 2.0;
    const VIEW_BOX_HEIGHT: f32 = Self::MENU_HEIGHT + Self::VIEW_BOX_PADDING * 2.0 + 32.0;
 
+   /// The number of public rooms visible at once. The rest can be scrolled to.
+   const VISIBLE_PUBLIC_ROOMS: usize = 3;
+   /// The maximum amount of time between two clicks for them to count as a double click.
+   const DOUBLE_CLICK_TIME: Duration = Duration::from_millis(400);
+   /// How long to wait after the relay address is changed before checking the relay, so that it's
+   /// not checked on every keystroke.
+   const RELAY_CHECK_DELAY: Duration = Duration::from_millis(500);
+   /// How often the relay is checked again.
+   const RELAY_CHECK_INTERVAL: Duration = Duration::from_secs(30);
+   const USAGE_STATISTICS_MENU_WIDTH: f32 = 360.0;
+
    /// Creates and initializes the lobby state.
    pub fn new(assets: Box<Assets>, socket_system: Arc<SocketSystem>) -> Self {
       let nickname_field = TextField::new(Some(&config().lobby.nickname));
@@ -87,28 +198,66 @@ impl State {
          nickname_field,
          relay_field,
          room_id_field: TextField::new(None),
+         room_name_field: TextField::new(Some(&config().lobby.room_name)),
+         room_description_field: TextField::new(Some(&config().lobby.room_description)),
+         custom_room_id_field: TextField::new(None),
 
          join_expand: Expand::new(true),
          host_expand: Expand::new(false),
+         rooms_expand: Expand::new(false),
+         rooms_tab: RoomsTab::Recent,
+         public_room: config().lobby.public_room,
+         lan_room: false,
+         direct_room: false,
+         public_rooms: Vec::new(),
+         public_rooms_scroll: 0,
+         last_public_room_click: None,
 
          main_view: View::new((
             Self::VIEW_BOX_WIDTH,
             Self::BANNER_HEIGHT + Self::VIEW_BOX_HEIGHT + Self::STATUS_HEIGHT,
          )),
-         panel_view: View::new((40.0, 4.0 + 3.0 * 36.0)),
+         panel_view: View::new((40.0, 4.0 + 5.0 * 36.0)),
          // The size of the language menu is computed later.
          language_menu: ContextMenu::new((0.0, 0.0)),
+         // Same here.
+         relay_profiles_menu: ContextMenu::new((0.0, 0.0)),
+         relay_profile_name_field: TextField::new(None),
+         // And here.
+         usage_statistics_menu: ContextMenu::new((0.0, 0.0)),
+         usage_statistics_endpoint_field: TextField::new(Some(&config().telemetry.endpoint)),
+         usage_statistics_preview: String::new(),
 
          assets,
 
          status: Status::None,
          peer: None,
+         relay_peer: None,
+         room_list: None,
+         lan_browser: None,
+         relay_ping: None,
+         relay_health: RelayHealth::Unknown,
+         relay_health_address: String::new(),
+         relay_health_time: Instant::now(),
          image_file: None,
+         recovery: autosave::recoverable(),
       };
       this.room_id_field.set_focus(true);
       this
    }
 
+   /// Creates the lobby state after leaving a room. The peer's relay connection is kept for
+   /// entering the next room.
+   pub fn after_leaving_room(
+      assets: Box<Assets>,
+      socket_system: Arc<SocketSystem>,
+      peer: Peer,
+   ) -> Self {
+      let mut this = Self::new(assets, socket_system);
+      this.relay_peer = Some(peer);
+      this
+   }
+
    /// Processes the logo banner.
    fn process_banner(&mut self, ui: &mut Ui, input: &Input, root_view: &View) {
       ui.push((ui.width(), Self::BANNER_HEIGHT), Layout::Freeform);
@@ -219,17 +368,25 @@ impl State {
          (ui.width(), TextField::labelled_height(textfield.font)),
          Layout::Horizontal,
       );
-      self.nickname_field.with_label(
+      let nickname_error = Self::check_nickname(self.nickname_field.text().strip_whitespace());
+      Self::field_with_error(
+         &mut self.nickname_field,
          ui,
          input,
-         &self.assets.sans,
+         &self.assets,
          &self.assets.tr.lobby_nickname.label,
          TextFieldArgs {
             hint: Some(&self.assets.tr.lobby_nickname.hint),
             ..textfield
          },
+         nickname_error.err(),
       );
       ui.space(16.0);
+      ui.push(
+         (textfield.width, TextField::labelled_height(textfield.font)),
+         Layout::Freeform,
+      );
+      let relay_field_rect = ui.rect();
       self.relay_field.with_label(
          ui,
          input,
@@ -241,6 +398,10 @@ impl State {
          },
       );
       ui.pop();
+      self.process_relay_health(ui, input, relay_field_rect);
+      ui.space(8.0);
+      self.process_relay_profiles_button(ui, input);
+      ui.pop();
       ui.space(24.0);
 
       // join room
@@ -255,6 +416,7 @@ impl State {
             },
          )
          .mutually_exclude(&mut self.host_expand)
+         .mutually_exclude(&mut self.rooms_expand)
          .expanded()
       {
          ui.push(ui.remaining_size(), Layout::Vertical);
@@ -272,16 +434,21 @@ impl State {
             (0.0, TextField::labelled_height(textfield.font)),
             Layout::Horizontal,
          );
-         let room_id_field = self.room_id_field.with_label(
+         // An empty room ID is not an error until the user tries to join.
+         let room_id = self.room_id_field.text().strip_whitespace();
+         let room_id_error = Self::check_room_id(room_id).err().filter(|_| !room_id.is_empty());
+         let room_id_field = Self::field_with_error(
+            &mut self.room_id_field,
             ui,
             input,
-            &self.assets.sans,
+            &self.assets,
             &self.assets.tr.lobby_room_id.label,
             TextFieldArgs {
                hint: Some(&self.assets.tr.lobby_room_id.hint),
                font: &self.assets.monospace,
                ..textfield
             },
+            room_id_error,
          );
          ui.offset(vector(8.0, 16.0));
          if Button::with_text(
@@ -294,8 +461,14 @@ impl State {
          .clicked()
             || room_id_field.done()
          {
+            // Links carry the relay address with them, so fill it in for the user.
+            if let Ok(link) = self.room_id_field.text().strip_whitespace().parse::<RoomLink>() {
+               self.relay_field.set_text(link.relay.clone());
+               self.room_id_field.set_text(link.target_string());
+            }
             match Self::join_room(
                Arc::clone(&self.socket_system),
+               &mut self.relay_peer,
                &self.assets.tr,
                self.nickname_field.text().strip_whitespace(),
                self.relay_field.text().strip_whitespace(),
@@ -303,7 +476,7 @@ impl State {
             ) {
                Ok(peer) => {
                   self.peer = Some(peer);
-                  self.status = Status::Info(self.assets.tr.connecting.clone());
+                  self.status = Status::None;
                }
                Err(status) => self.status = status,
             }
@@ -327,28 +500,85 @@ impl State {
             },
          )
          .mutually_exclude(&mut self.join_expand)
+         .mutually_exclude(&mut self.rooms_expand)
          .expanded()
       {
          ui.push(ui.remaining_size(), Layout::Vertical);
          ui.offset(vector(32.0, 8.0));
 
-         ui.paragraph(
-            &self.assets.sans,
-            self.assets.tr.lobby_host_a_new_room.description.split('\n'),
-            self.assets.colors.text,
-            AlignH::Left,
-            None,
+         ui.push(
+            (ui.width(), TextField::labelled_height(textfield.font)),
+            Layout::Horizontal,
          );
+         let room_name_error = Self::check_length(
+            self.room_name_field.text().strip_whitespace(),
+            Peer::MAX_ROOM_NAME_LEN,
+         );
+         Self::field_with_error(
+            &mut self.room_name_field,
+            ui,
+            input,
+            &self.assets,
+            &self.assets.tr.lobby_room_name.label,
+            TextFieldArgs {
+               hint: Some(&self.assets.tr.lobby_room_name.hint),
+               width: 112.0,
+               ..textfield
+            },
+            room_name_error.err(),
+         );
+         ui.space(16.0);
+         let room_description_error = Self::check_length(
+            self.room_description_field.text().strip_whitespace(),
+            Peer::MAX_ROOM_DESCRIPTION_LEN,
+         );
+         Self::field_with_error(
+            &mut self.room_description_field,
+            ui,
+            input,
+            &self.assets,
+            &self.assets.tr.lobby_room_description.label,
+            TextFieldArgs {
+               hint: Some(&self.assets.tr.lobby_room_description.hint),
+               width: 112.0,
+               ..textfield
+            },
+            room_description_error.err(),
+         );
+         ui.space(16.0);
+         let custom_room_id_error =
+            Self::check_custom_room_id(self.custom_room_id_field.text().strip_whitespace());
+         Self::field_with_error(
+            &mut self.custom_room_id_field,
+            ui,
+            input,
+            &self.assets,
+            &self.assets.tr.lobby_custom_room_id.label,
+            TextFieldArgs {
+               hint: Some(&self.assets.tr.lobby_custom_room_id.hint),
+               width: 100.0,
+               ..textfield
+            },
+            custom_room_id_error.err(),
+         );
+         ui.pop();
          ui.space(16.0);
 
          macro_rules! host_room {
             () => {
-               self.status = Status::Info(self.assets.tr.connecting.clone());
+               self.status = Status::None;
                match Self::host_room(
                   Arc::clone(&self.socket_system),
+                  &mut self.relay_peer,
                   &self.assets.tr,
                   self.nickname_field.text().strip_whitespace(),
                   self.relay_field.text().strip_whitespace(),
+                  self.room_name_field.text().strip_whitespace(),
+                  self.room_description_field.text().strip_whitespace(),
+                  self.custom_room_id_field.text().strip_whitespace(),
+                  self.public_room,
+                  self.lan_room,
+                  self.direct_room,
                ) {
                   Ok(peer) => self.peer = Some(peer),
                   Err(status) => self.status = status,
@@ -378,160 +608,1197 @@ impl State {
          )
          .clicked()
          {
-            match FileDialog::new()
+            self.status = Status::None;
+            let dialog = AsyncFileDialog::new()
                .set_file_name("canvas.png")
                .add_filter(
                   &self.assets.tr.fd_supported_image_files,
                   &["png", "jpg", "jpeg", "jfif"],
                )
-               .add_filter(&self.assets.tr.fd_netcanv_canvas, &["toml"])
-               .pick_file()
-            {
-               Some(path) => {
-                  self.image_file = Some(path);
-                  host_room!();
-               },
-               None => self.status = Status::None
-            }
+               .add_filter(&self.assets.tr.fd_netcanv_canvas, &["toml"]);
+            file_dialog::pick_file(dialog, HostFromFile);
+         }
+         for message in &bus::retrieve_all::<HostFromFile>() {
+            let HostFromFile(path) = message.consume();
+            self.image_file = Some(path);
+            host_room!();
+         }
+         ui.space(8.0);
+         if Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(
+               ui,
+               ButtonColors::toggle(
+                  self.public_room,
+                  &self.assets.colors.radio_button.normal,
+                  &self.assets.colors.radio_button.selected,
+               ),
+            )
+            .height(32.0)
+            .pill(),
+            &self.assets.sans,
+            &self.assets.tr.lobby_public,
+         )
+         .clicked()
+         {
+            self.public_room = !self.public_room;
+         }
+         ui.space(8.0);
+         if Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(
+               ui,
+               ButtonColors::toggle(
+                  self.lan_room,
+                  &self.assets.colors.radio_button.normal,
+                  &self.assets.colors.radio_button.selected,
+               ),
+            )
+            .height(32.0)
+            .pill(),
+            &self.assets.sans,
+            &self.assets.tr.lobby_lan,
+         )
+         .clicked()
+         {
+            self.lan_room = !self.lan_room;
+            self.direct_room = false;
+         }
+         ui.space(8.0);
+         if Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(
+               ui,
+               ButtonColors::toggle(
+                  self.direct_room,
+                  &self.assets.colors.radio_button.normal,
+                  &self.assets.colors.radio_button.selected,
+               ),
+            )
+            .height(32.0)
+            .pill(),
+            &self.assets.sans,
+            &self.assets.tr.lobby_direct,
+         )
+         .clicked()
+         {
+            self.direct_room = !self.direct_room;
+            self.lan_room = false;
          }
          ui.pop();
 
          ui.fit();
          ui.pop();
       }
+      ui.space(16.0);
 
+      // recent and public rooms
+      // The tabs are laid out on top of the Expand's header, so that they don't take up any
+      // extra space.
+      ui.push(
+         (ui.width(), self.assets.icons.expand.expand.height() as f32),
+         Layout::Freeform,
+      );
+      let rooms_expanded = self
+         .rooms_expand
+         .process(
+            ui,
+            input,
+            ExpandArgs {
+               label: &self.assets.tr.lobby_rooms,
+               ..expand
+            },
+         )
+         .mutually_exclude(&mut self.join_expand)
+         .mutually_exclude(&mut self.host_expand)
+         .expanded();
+      if rooms_expanded {
+         self.process_rooms_tabs(ui, input);
+      }
       ui.pop();
+      if rooms_expanded {
+         ui.push(ui.remaining_size(), Layout::Vertical);
+         ui.offset(vector(32.0, 8.0));
+         match self.rooms_tab {
+            RoomsTab::Recent => self.process_recent_rooms(ui, input),
+            RoomsTab::Public => self.process_public_rooms(ui, input),
+            RoomsTab::Lan => self.process_lan_rooms(ui, input),
+         }
+         ui.fit();
+         ui.pop();
+      }
 
-      chain_focus(
-         input,
-         &mut [
-            &mut self.nickname_field,
-            &mut self.relay_field,
-            &mut self.room_id_field,
-         ],
-      );
+      ui.pop();
 
       None
    }
 
-   /// Processes the status report box.
-   fn process_status(&mut self, ui: &mut Ui, input: &mut Input) {
-      if !matches!(self.status, Status::None) {
-         let (icon, color, text) = match &self.status {
-            Status::None => unreachable!(),
-            Status::Info(text) => (
-               &self.assets.icons.status.info,
-               self.assets.colors.text,
-               text,
-            ),
-            Status::Error(text) => (
-               &self.assets.icons.status.error,
-               self.assets.colors.error,
-               text,
-            ),
-         };
-         let width = 56.0 + self.assets.sans.text_width(text);
-         let width = width.max(ui.width());
-         let width = (width / 2.0).ceil() * 2.0;
-         let mut status_view = View::new((width, 48.0));
-         view::layout::align(
-            &self.main_view,
-            &mut status_view,
-            (AlignH::Center, AlignV::Bottom),
-         );
-         status_view.begin(ui, input, Layout::Horizontal);
-         ui.fill_rounded(self.assets.colors.panel, 8.0);
-         ui.pad(16.0);
-         ui.icon(icon, color, Some(vector(ui.height(), ui.height())));
-         ui.space(8.0);
-         ui.push((ui.remaining_width(), ui.height()), Layout::Freeform);
-         ui.text(
-            &self.assets.sans,
-            text,
-            color,
-            (AlignH::Left, AlignV::Middle),
-         );
-         ui.pop();
-         status_view.end(ui);
+   /// Processes the relay health indicator, shown in the top right corner of the relay server
+   /// field.
+   fn process_relay_health(&mut self, ui: &mut Ui, input: &Input, field_rect: Rect) {
+      let (color, text, tooltip) = match &self.relay_health {
+         RelayHealth::Unknown => return,
+         RelayHealth::Checking => (
+            self.assets.colors.text.with_alpha(96),
+            self.assets.tr.lobby_relay_checking.clone(),
+            None,
+         ),
+         RelayHealth::Reachable(latency) => (
+            self.assets.colors.lobby.relay_reachable,
+            self
+               .assets
+               .tr
+               .lobby_relay_latency
+               .format()
+               .with("milliseconds", latency.as_millis() as u64)
+               .done(),
+            Some(self.assets.tr.lobby_relay_reachable.as_str()),
+         ),
+         RelayHealth::Unreachable(error) => (
+            self.assets.colors.error,
+            self.assets.tr.lobby_relay_unreachable.clone(),
+            Some(error.as_str()),
+         ),
+      };
+      let font = self.assets.sans.with_size(12.0);
+      let dot_size = 8.0;
+      let width = dot_size + 4.0 + font.text_width(&text);
+
+      ui.push((0.0, 0.0), Layout::Freeform);
+      ui.set_position(point(field_rect.right() - width, field_rect.top()));
+      ui.push((width, 16.0), Layout::Horizontal);
+      ui.push((dot_size, ui.height()), Layout::Freeform);
+      let center = ui.rect().center();
+      ui.render().fill_circle(center, dot_size / 2.0, color);
+      ui.pop();
+      ui.space(4.0);
+      ui.horizontal_label(&font, &text, color, None);
+      if let Some(tooltip) = tooltip {
+         Tooltip::top(tooltip).process(ui, input, &self.assets.sans);
       }
+      ui.pop();
+      ui.pop();
    }
 
-   /// Processes the panel on the right that contains action buttons.
-   fn process_icon_panel(&mut self, ui: &mut Ui, input: &mut Input) {
-      if Button::with_icon(
-         ui,
-         input,
-         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(32.0).pill().tooltip(
-            &self.assets.sans,
-            Tooltip::left(match config().ui.color_scheme {
-               config::ColorScheme::Light => &self.assets.tr.switch_to_dark_mode,
-               config::ColorScheme::Dark => &self.assets.tr.switch_to_light_mode,
-            }),
-         ),
-         match config().ui.color_scheme {
-            config::ColorScheme::Dark => &self.assets.icons.lobby.light_mode,
-            config::ColorScheme::Light => &self.assets.icons.lobby.dark_mode,
-         },
-      )
-      .clicked()
-      {
-         config::write(|config| {
-            config.ui.color_scheme = match config.ui.color_scheme {
-               config::ColorScheme::Light => config::ColorScheme::Dark,
-               config::ColorScheme::Dark => config::ColorScheme::Light,
-            };
-         });
-         self.save_config();
-         self.assets.colors = ColorScheme::from(config().ui.color_scheme);
+   /// Checks the relay in the relay server field, once the user stops typing in it, and then
+   /// periodically.
+   fn update_relay_health(&mut self) {
+      let address = self.relay_field.text().strip_whitespace();
+      if address != self.relay_health_address {
+         self.relay_health_address = address.to_owned();
+         self.relay_health = RelayHealth::Unknown;
+         self.relay_ping = None;
+         self.relay_health_time = Instant::now();
       }
 
-      ui.space(4.0);
-
-      let language_button = Button::with_icon(
-         ui,
-         input,
-         &ButtonArgs::new(ui, &self.assets.colors.action_button)
-            .height(32.0)
-            .pill()
-            .tooltip(&self.assets.sans, Tooltip::left(&self.assets.tr.language)),
-         &self.assets.icons.lobby.translate,
-      );
-      let n_languages = self.assets.languages.len() as f32;
-      let language_menu_rect = TooltipPosition::Left.compute_rect(
-         ui,
-         language_button.group(),
-         vector(128.0, 16.0 + n_languages * 24.0 + (n_languages - 1.0) * 4.0),
-         TooltipLayout {
-            spacing: 24.0,
-            root_padding: 8.0,
-         },
-      );
-      view::layout::absolute(&mut self.language_menu.view, language_menu_rect);
-      if language_button.clicked() {
-         self.language_menu.toggle();
+      let check_after = match self.relay_health {
+         RelayHealth::Unknown => Some(Self::RELAY_CHECK_DELAY),
+         RelayHealth::Checking => None,
+         RelayHealth::Reachable(_) | RelayHealth::Unreachable(_) => {
+            Some(Self::RELAY_CHECK_INTERVAL)
+         }
+      };
+      if let Some(check_after) = check_after {
+         if !self.relay_health_address.is_empty() && self.relay_health_time.elapsed() > check_after
+         {
+            self.relay_ping = Some(RelayPing::new(
+               Arc::clone(&self.socket_system),
+               &self.relay_health_address,
+            ));
+            // The previous result is kept while rechecking, so that the indicator doesn't blink.
+            if let RelayHealth::Unknown = self.relay_health {
+               self.relay_health = RelayHealth::Checking;
+            }
+            self.relay_health_time = Instant::now();
+         }
       }
 
-      ui.space(4.0);
+      if let Some(ping) = &mut self.relay_ping {
+         match ping.poll() {
+            Ok(Some(latency)) => {
+               self.relay_health = RelayHealth::Reachable(latency);
+               self.relay_ping = None;
+            }
+            Ok(None) => (),
+            Err(error) => {
+               self.relay_health = RelayHealth::Unreachable(error.translate(&self.assets.language));
+               self.relay_ping = None;
+            }
+         }
+      }
+   }
 
-      if assets::has_license_page()
-         && Button::with_icon(
+   /// Processes the tabs switching between recent and public rooms.
+   fn process_rooms_tabs(&mut self, ui: &mut Ui, input: &mut Input) {
+      ui.push(ui.size(), Layout::HorizontalRev);
+      let tabs = [
+         (RoomsTab::Lan, &self.assets.tr.lobby_lan_rooms),
+         (RoomsTab::Public, &self.assets.tr.lobby_public_rooms),
+         (RoomsTab::Recent, &self.assets.tr.lobby_recent_rooms),
+      ];
+      let mut refresh = false;
+      for (tab, label) in tabs {
+         let height = 24.0;
+         let width = self.assets.sans.text_width(label) + height;
+         ui.push((width, ui.height()), Layout::Freeform);
+         ui.push((width, height), Layout::Freeform);
+         ui.align((AlignH::Left, AlignV::Middle));
+         let colors = ButtonColors::toggle(
+            self.rooms_tab == tab,
+            &self.assets.colors.radio_button.normal,
+            &self.assets.colors.radio_button.selected,
+         );
+         if Button::with_text(
             ui,
             input,
-            &ButtonArgs::new(ui, &self.assets.colors.action_button).height(32.0).pill().tooltip(
-               &self.assets.sans,
-               Tooltip::left(&self.assets.tr.open_source_licenses),
-            ),
-            &self.assets.icons.lobby.legal,
+            &ButtonArgs::new(ui, colors).height(height).pill(),
+            &self.assets.sans,
+            label,
          )
          .clicked()
-      {
-         catch!(assets::open_license_page());
+            && self.rooms_tab != tab
+         {
+            self.rooms_tab = tab;
+            refresh = tab == RoomsTab::Public;
+         }
+         ui.pop();
+         ui.pop();
+         ui.space(4.0);
       }
-   }
+      ui.pop();
 
-   fn process_language_menu(&mut self, ui: &mut Ui, input: &mut Input) {
-      if self
-         .language_menu
+      if refresh {
+         self.refresh_public_rooms();
+      }
+      // Rooms on the local network are only looked for while they're shown.
+      match self.rooms_tab {
+         RoomsTab::Lan if self.lan_browser.is_none() => match LanBrowser::start() {
+            Ok(browser) => self.lan_browser = Some(browser),
+            Err(error) => {
+               self.rooms_tab = RoomsTab::Recent;
+               bus::push(Error(error));
+            }
+         },
+         RoomsTab::Lan => (),
+         _ => self.lan_browser = None,
+      }
+   }
+
+   /// Processes the Join button shown at the right end of a hovered room list row. Returns whether
+   /// it was clicked.
+   fn process_join_button(&self, ui: &mut Ui, input: &Input) -> bool {
+      let clicked = Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(ui.height()).pill(),
+         &self.assets.sans,
+         &self.assets.tr.lobby_join,
+      )
+      .clicked();
+      ui.space(8.0);
+      clicked
+   }
+
+   /// Processes the list of public rooms on the relay. Clicking a room's Join button or
+   /// double-clicking the room joins it.
+   fn process_public_rooms(&mut self, ui: &mut Ui, input: &mut Input) {
+      let row_height = 24.0;
+      ui.push(
+         (ui.width(), row_height * Self::VISIBLE_PUBLIC_ROOMS as f32),
+         Layout::Vertical,
+      );
+      if ui.hover(input) {
+         let scroll = input.mouse_scroll().y;
+         let max_scroll = self.public_rooms.len().saturating_sub(Self::VISIBLE_PUBLIC_ROOMS);
+         if scroll > 0.0 {
+            self.public_rooms_scroll = self.public_rooms_scroll.saturating_sub(1);
+         } else if scroll < 0.0 {
+            self.public_rooms_scroll = (self.public_rooms_scroll + 1).min(max_scroll);
+         }
+      }
+
+      let placeholder = if self.room_list.as_ref().map(|list| list.is_pending()).unwrap_or(false) {
+         Some(&self.assets.tr.lobby_fetching_public_rooms)
+      } else if self.public_rooms.is_empty() {
+         Some(&self.assets.tr.lobby_no_public_rooms)
+      } else {
+         None
+      };
+      let mut joined = None;
+      if let Some(placeholder) = placeholder {
+         ui.paragraph(
+            &self.assets.sans,
+            placeholder.split('\n'),
+            self.assets.colors.text,
+            AlignH::Left,
+            None,
+         );
+      } else {
+         let visible_rooms = self
+            .public_rooms
+            .iter()
+            .skip(self.public_rooms_scroll)
+            .take(Self::VISIBLE_PUBLIC_ROOMS);
+         for room in visible_rooms {
+            ui.push((ui.width(), row_height), Layout::Horizontal);
+            let hovered = ui.hover(input);
+            if hovered {
+               ui.fill_rounded(self.assets.colors.action_button.hover, 4.0);
+            }
+            if ui.clicked(input, MouseButton::Left) {
+               match self.last_public_room_click {
+                  Some((room_id, clicked_at))
+                     if room_id == room.room_id
+                        && clicked_at.elapsed() < Self::DOUBLE_CLICK_TIME =>
+                  {
+                     self.last_public_room_click = None;
+                     joined = Some(room.room_id);
+                  }
+                  _ => self.last_public_room_click = Some((room.room_id, Instant::now())),
+               }
+            }
+            ui.pad((8.0, 0.0));
+
+            ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+            if hovered && self.process_join_button(ui, input) {
+               joined = Some(room.room_id);
+            }
+            let players = self
+               .assets
+               .tr
+               .lobby_public_room_players
+               .format()
+               .with("players", room.players)
+               .done();
+            ui.horizontal_label(&self.assets.sans, &players, self.assets.colors.text, None);
+            if room.invite_only {
+               ui.space(8.0);
+               ui.horizontal_label(
+                  &self.assets.sans,
+                  &self.assets.tr.lobby_public_room_invite_only,
+                  self.assets.colors.error,
+                  None,
+               );
+            }
+            ui.space(8.0);
+            ui.push((ui.remaining_width(), ui.height()), Layout::Horizontal);
+            ui.horizontal_label(
+               &self.assets.sans_bold,
+               &truncate_text(&self.assets.sans_bold, ui.width(), &room.name),
+               self.assets.colors.text,
+               None,
+            );
+            if !room.description.is_empty() && ui.remaining_width() > 16.0 {
+               ui.space(8.0);
+               ui.horizontal_label(
+                  &self.assets.sans,
+                  &truncate_text(&self.assets.sans, ui.remaining_width(), &room.description),
+                  self.assets.colors.text.with_alpha(160),
+                  None,
+               );
+            }
+            ui.pop();
+            ui.pop();
+
+            ui.pop();
+         }
+      }
+      ui.pop();
+      ui.space(8.0);
+
+      ui.push((ui.width(), row_height), Layout::HorizontalRev);
+      if Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(row_height).pill(),
+         &self.assets.sans,
+         &self.assets.tr.lobby_refresh,
+      )
+      .clicked()
+      {
+         self.refresh_public_rooms();
+      }
+      ui.space(8.0);
+      ui.horizontal_label(
+         &self.assets.sans,
+         &self.assets.tr.lobby_public_rooms_hint,
+         self.assets.colors.text,
+         Some((ui.remaining_width(), AlignH::Left)),
+      );
+      ui.pop();
+
+      if let Some(room_id) = joined {
+         self.room_id_field.set_text(room_id.to_string());
+         match Self::join_room(
+            Arc::clone(&self.socket_system),
+            &mut self.relay_peer,
+            &self.assets.tr,
+            self.nickname_field.text().strip_whitespace(),
+            self.relay_field.text().strip_whitespace(),
+            &room_id.to_string(),
+         ) {
+            Ok(peer) => {
+               self.peer = Some(peer);
+               self.status = Status::None;
+            }
+            Err(status) => self.status = status,
+         }
+      }
+   }
+
+   /// Processes the list of rooms found on the local network. Clicking a room's Join button or
+   /// double-clicking the room joins it.
+   fn process_lan_rooms(&mut self, ui: &mut Ui, input: &mut Input) {
+      let row_height = 24.0;
+      let rooms = self.lan_browser.as_ref().map(|browser| browser.rooms()).unwrap_or_default();
+      ui.push(
+         (ui.width(), row_height * Self::VISIBLE_PUBLIC_ROOMS as f32),
+         Layout::Vertical,
+      );
+      let mut joined: Option<LanRoom> = None;
+      if rooms.is_empty() {
+         ui.paragraph(
+            &self.assets.sans,
+            self.assets.tr.lobby_no_lan_rooms.split('\n'),
+            self.assets.colors.text,
+            AlignH::Left,
+            None,
+         );
+      } else {
+         for room in rooms.iter().take(Self::VISIBLE_PUBLIC_ROOMS) {
+            ui.push((ui.width(), row_height), Layout::Horizontal);
+            let hovered = ui.hover(input);
+            if hovered {
+               ui.fill_rounded(self.assets.colors.action_button.hover, 4.0);
+            }
+            if ui.clicked(input, MouseButton::Left) {
+               match self.last_public_room_click {
+                  Some((room_id, clicked_at))
+                     if room_id == room.room_id
+                        && clicked_at.elapsed() < Self::DOUBLE_CLICK_TIME =>
+                  {
+                     self.last_public_room_click = None;
+                     joined = Some(room.clone());
+                  }
+                  _ => self.last_public_room_click = Some((room.room_id, Instant::now())),
+               }
+            }
+            ui.pad((8.0, 0.0));
+
+            ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+            if hovered && self.process_join_button(ui, input) {
+               joined = Some(room.clone());
+            }
+            ui.horizontal_label(
+               &self.assets.sans,
+               &room.room_id.to_string(),
+               self.assets.colors.text,
+               None,
+            );
+            ui.space(8.0);
+            ui.horizontal_label(
+               &self.assets.sans_bold,
+               &truncate_text(&self.assets.sans_bold, ui.remaining_width(), &room.name),
+               self.assets.colors.text,
+               Some((ui.remaining_width(), AlignH::Left)),
+            );
+            ui.pop();
+
+            ui.pop();
+         }
+      }
+      ui.pop();
+      ui.space(8.0);
+
+      ui.push((ui.width(), row_height), Layout::Horizontal);
+      ui.horizontal_label(
+         &self.assets.sans,
+         &self.assets.tr.lobby_public_rooms_hint,
+         self.assets.colors.text,
+         Some((ui.remaining_width(), AlignH::Left)),
+      );
+      ui.pop();
+
+      if let Some(room) = joined {
+         self.room_id_field.set_text(room.room_id.to_string());
+         match Self::join_room(
+            Arc::clone(&self.socket_system),
+            &mut self.relay_peer,
+            &self.assets.tr,
+            self.nickname_field.text().strip_whitespace(),
+            &room.relay_address,
+            &room.room_id.to_string(),
+         ) {
+            Ok(peer) => {
+               self.peer = Some(peer);
+               self.status = Status::None;
+            }
+            Err(status) => self.status = status,
+         }
+      }
+   }
+
+   /// Asks the relay for a fresh list of public rooms.
+   fn refresh_public_rooms(&mut self) {
+      self.public_rooms.clear();
+      self.public_rooms_scroll = 0;
+      self.room_list = Some(RoomListRequest::new(
+         Arc::clone(&self.socket_system),
+         self.relay_field.text().strip_whitespace(),
+      ));
+   }
+
+   /// Processes the list of recent rooms, with buttons for joining or hosting them again.
+   fn process_recent_rooms(&mut self, ui: &mut Ui, input: &mut Input) {
+      let recent_rooms = config().lobby.recent_rooms.clone();
+      if recent_rooms.is_empty() {
+         ui.paragraph(
+            &self.assets.sans,
+            self.assets.tr.lobby_no_recent_rooms.split('\n'),
+            self.assets.colors.text,
+            AlignH::Left,
+            None,
+         );
+         return;
+      }
+
+      let button = ButtonArgs::new(ui, &self.assets.colors.button).height(32.0).pill();
+      let mut revisited = None;
+      for room in &recent_rooms {
+         ui.push((ui.width(), 32.0), Layout::Horizontal);
+         ui.horizontal_label(
+            &self.assets.monospace,
+            &room.room_id,
+            self.assets.colors.text,
+            Some((64.0, AlignH::Left)),
+         );
+         ui.space(8.0);
+
+         ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+         let label = if room.hosted {
+            &self.assets.tr.lobby_rehost
+         } else {
+            &self.assets.tr.lobby_rejoin
+         };
+         if Button::with_text(ui, input, &button, &self.assets.sans, label).clicked() {
+            revisited = Some(room.clone());
+         }
+         ui.space(8.0);
+
+         ui.push((ui.remaining_width(), ui.height()), Layout::Freeform);
+         ui.push(
+            (ui.width(), self.assets.sans.height() * 2.0 + 4.0),
+            Layout::Vertical,
+         );
+         ui.align((AlignH::Left, AlignV::Middle));
+         let last_seen = Self::format_last_seen(&self.assets.tr, room.last_seen);
+         let details = match room.file.as_ref().and_then(|file| file.file_name()) {
+            Some(file_name) => self
+               .assets
+               .tr
+               .lobby_recent_room_from_file
+               .format()
+               .with("file", file_name.to_string_lossy().as_ref())
+               .with("time", last_seen.as_str())
+               .done(),
+            None => self
+               .assets
+               .tr
+               .lobby_recent_room_as
+               .format()
+               .with("nickname", room.nickname.as_str())
+               .with("time", last_seen.as_str())
+               .done(),
+         };
+         for line in [&room.relay, &details] {
+            ui.vertical_label(
+               &self.assets.sans,
+               &truncate_text(&self.assets.sans, ui.width(), line),
+               self.assets.colors.text,
+               AlignH::Left,
+            );
+            ui.space(4.0);
+         }
+         ui.pop();
+         ui.pop();
+
+         ui.pop();
+         ui.pop();
+         ui.space(4.0);
+      }
+
+      if let Some(room) = revisited {
+         self.revisit_room(room);
+      }
+   }
+
+   /// Formats how long ago a recent room was last entered.
+   fn format_last_seen(tr: &Strings, last_seen: u64) -> String {
+      let now = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+      let minutes = now.saturating_sub(last_seen) / 60;
+      let hours = minutes / 60;
+      let days = hours / 24;
+      if days > 0 {
+         tr.lobby_recent_days_ago.format().with("days", days).done()
+      } else if hours > 0 {
+         tr.lobby_recent_hours_ago.format().with("hours", hours).done()
+      } else if minutes > 0 {
+         tr.lobby_recent_minutes_ago.format().with("minutes", minutes).done()
+      } else {
+         tr.lobby_recent_just_now.clone()
+      }
+   }
+
+   /// Fills in the lobby fields with the details of a recent room, and joins or hosts it again.
+   fn revisit_room(&mut self, room: RecentRoom) {
+      self.nickname_field.set_text(room.nickname.clone());
+      self.relay_field.set_text(room.relay.clone());
+      let result = if room.hosted {
+         self.image_file = room.file;
+         Self::host_room(
+            Arc::clone(&self.socket_system),
+            &mut self.relay_peer,
+            &self.assets.tr,
+            &room.nickname,
+            &room.relay,
+            self.room_name_field.text().strip_whitespace(),
+            self.room_description_field.text().strip_whitespace(),
+            self.custom_room_id_field.text().strip_whitespace(),
+            self.public_room,
+            false,
+            false,
+         )
+      } else {
+         self.room_id_field.set_text(room.room_id.clone());
+         Self::join_room(
+            Arc::clone(&self.socket_system),
+            &mut self.relay_peer,
+            &self.assets.tr,
+            &room.nickname,
+            &room.relay,
+            &room.room_id,
+         )
+      };
+      match result {
+         Ok(peer) => {
+            self.peer = Some(peer);
+            self.status = Status::None;
+         }
+         Err(status) => self.status = status,
+      }
+   }
+
+   /// Adds the room the peer is connected to to the top of the recent rooms list.
+   fn remember_room(&self, peer: &Peer) {
+      let room_id = match peer.room_id() {
+         Some(room_id) => room_id.to_string(),
+         None => return,
+      };
+      let room = RecentRoom {
+         room_id,
+         relay: self.relay_field.text().strip_whitespace().to_owned(),
+         nickname: self.nickname_field.text().strip_whitespace().to_owned(),
+         hosted: peer.is_host(),
+         file: self.image_file.clone(),
+         last_seen: SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_secs(),
+      };
+      config::write(|config| config.lobby.remember_room(room));
+   }
+
+   /// Processes the status report box.
+   fn process_status(&mut self, ui: &mut Ui, input: &mut Input) {
+      if let Some(stage) = self.peer.as_ref().and_then(|peer| peer.connection_stage()) {
+         self.process_connection_progress(ui, input, stage);
+      } else if !matches!(self.status, Status::None) {
+         let (icon, color, text) = match &self.status {
+            Status::None => unreachable!(),
+            Status::Info(text) => (
+               &self.assets.icons.status.info,
+               self.assets.colors.text,
+               text,
+            ),
+            Status::Error(text) => (
+               &self.assets.icons.status.error,
+               self.assets.colors.error,
+               text,
+            ),
+         };
+         let width = 56.0 + self.assets.sans.text_width(text);
+         let width = width.max(ui.width());
+         let width = (width / 2.0).ceil() * 2.0;
+         let mut status_view = View::new((width, 48.0));
+         view::layout::align(
+            &self.main_view,
+            &mut status_view,
+            (AlignH::Center, AlignV::Bottom),
+         );
+         status_view.begin(ui, input, Layout::Horizontal);
+         ui.fill_rounded(self.assets.colors.panel, 8.0);
+         ui.pad(16.0);
+         ui.icon(icon, color, Some(vector(ui.height(), ui.height())));
+         ui.space(8.0);
+         ui.push((ui.remaining_width(), ui.height()), Layout::Freeform);
+         ui.text(
+            &self.assets.sans,
+            text,
+            color,
+            (AlignH::Left, AlignV::Middle),
+         );
+         ui.pop();
+         status_view.end(ui);
+      } else if self.recovery.is_some() {
+         self.process_recovery_prompt(ui, input);
+      }
+   }
+
+   /// Processes the box offering to recover the autosave of a session that didn't end cleanly.
+   fn process_recovery_prompt(&mut self, ui: &mut Ui, input: &mut Input) {
+      let text = &self.assets.tr.lobby_recover_session;
+      let button_height = 32.0;
+      let buttons_width = self.assets.sans.text_width(&self.assets.tr.lobby_recover)
+         + self.assets.sans.text_width(&self.assets.tr.lobby_dismiss)
+         + button_height * 2.0
+         + 8.0;
+      let width = 64.0 + self.assets.sans.text_width(text) + buttons_width;
+      let width = width.max(ui.width());
+      let width = (width / 2.0).ceil() * 2.0;
+      let mut status_view = View::new((width, 48.0));
+      view::layout::align(
+         &self.main_view,
+         &mut status_view,
+         (AlignH::Center, AlignV::Bottom),
+      );
+      status_view.begin(ui, input, Layout::Horizontal);
+      ui.fill_rounded(self.assets.colors.panel, 8.0);
+      ui.pad((16.0, 8.0));
+
+      ui.icon(
+         &self.assets.icons.status.info,
+         self.assets.colors.text,
+         Some(vector(ui.height(), ui.height())),
+      );
+      ui.space(8.0);
+
+      ui.push(ui.remaining_size(), Layout::HorizontalRev);
+      let button =
+         ButtonArgs::new(ui, &self.assets.colors.action_button).height(button_height).pill();
+      if Button::with_text(
+         ui,
+         input,
+         &button,
+         &self.assets.sans,
+         &self.assets.tr.lobby_recover,
+      )
+      .clicked()
+      {
+         // The autosave is opened offline, from where it can be saved somewhere safe or hosted.
+         if let Some(recovery) = self.recovery.take() {
+            self.peer = Some(Peer::offline(self.nickname_field.text().strip_whitespace()));
+            self.image_file = Some(recovery.path.clone());
+            recovery.dismiss();
+            self.status = Status::None;
+         }
+      }
+      ui.space(8.0);
+      if Button::with_text(
+         ui,
+         input,
+         &button,
+         &self.assets.sans,
+         &self.assets.tr.lobby_dismiss,
+      )
+      .clicked()
+      {
+         if let Some(recovery) = self.recovery.take() {
+            recovery.dismiss();
+         }
+      }
+      ui.push(ui.remaining_size(), Layout::Freeform);
+      ui.text(
+         &self.assets.sans,
+         text,
+         self.assets.colors.text,
+         (AlignH::Left, AlignV::Middle),
+      );
+      ui.pop();
+      ui.pop();
+
+      status_view.end(ui);
+   }
+
+   /// Processes the status report box shown while connecting to the relay, with a button that
+   /// cancels the connection.
+   fn process_connection_progress(
+      &mut self,
+      ui: &mut Ui,
+      input: &mut Input,
+      stage: ConnectionStage,
+   ) {
+      let text = match stage {
+         ConnectionStage::MappingPort => &self.assets.tr.mapping_port,
+         ConnectionStage::Resolving => &self.assets.tr.resolving,
+         ConnectionStage::Connecting => &self.assets.tr.connecting,
+         ConnectionStage::WaitingForRelay => &self.assets.tr.waiting_for_relay,
+      };
+      let button_height = 32.0;
+      let button_width = self.assets.sans.text_width(&self.assets.tr.cancel) + button_height;
+      let width = 64.0 + self.assets.sans.text_width(text) + button_width;
+      let width = width.max(ui.width());
+      let width = (width / 2.0).ceil() * 2.0;
+      let mut status_view = View::new((width, 48.0));
+      view::layout::align(
+         &self.main_view,
+         &mut status_view,
+         (AlignH::Center, AlignV::Bottom),
+      );
+      status_view.begin(ui, input, Layout::Horizontal);
+      ui.fill_rounded(self.assets.colors.panel, 8.0);
+      ui.pad((16.0, 8.0));
+
+      ui.push((16.0, ui.height()), Layout::Freeform);
+      Self::spinner(ui, input, self.assets.colors.text);
+      ui.pop();
+      ui.space(8.0);
+
+      ui.push(ui.remaining_size(), Layout::HorizontalRev);
+      if Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(button_height).pill(),
+         &self.assets.sans,
+         &self.assets.tr.cancel,
+      )
+      .clicked()
+      {
+         // Dropping the peer aborts the connection.
+         self.peer = None;
+         self.status = Status::None;
+      }
+      ui.push(ui.remaining_size(), Layout::Freeform);
+      ui.text(
+         &self.assets.sans,
+         text,
+         self.assets.colors.text,
+         (AlignH::Left, AlignV::Middle),
+      );
+      ui.pop();
+      ui.pop();
+
+      status_view.end(ui);
+   }
+
+   /// Draws a spinning arc in the middle of the current group.
+   fn spinner(ui: &mut Ui, input: &Input, color: Color) {
+      const SEGMENTS: usize = 12;
+      const SWEEP: f32 = 1.5 * std::f32::consts::PI;
+      const THICKNESS: f32 = 2.0;
+      let center = ui.rect().center();
+      let radius = ui.width().min(ui.height()) / 2.0 - THICKNESS;
+      let start = input.time_in_seconds() * 2.0 * std::f32::consts::PI;
+      let point_at = |i: usize| {
+         let angle = start + SWEEP * i as f32 / SEGMENTS as f32;
+         center + vector(angle.cos(), angle.sin()) * radius
+      };
+      for i in 0..SEGMENTS {
+         ui.render().line(
+            point_at(i),
+            point_at(i + 1),
+            color,
+            LineCap::Round,
+            THICKNESS,
+         );
+      }
+   }
+
+   /// Processes the panel on the right that contains action buttons.
+   fn process_icon_panel(&mut self, ui: &mut Ui, input: &mut Input) {
+      if Button::with_icon(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(32.0).pill().tooltip(
+            &self.assets.sans,
+            Tooltip::left(&self.assets.tr.draw_offline),
+         ),
+         &self.assets.icons.lobby.draw_offline,
+      )
+      .clicked()
+      {
+         // The nickname isn't shown to anyone, so there's no need to validate it.
+         self.peer = Some(Peer::offline(self.nickname_field.text().strip_whitespace()));
+         self.image_file = None;
+         self.status = Status::None;
+      }
+
+      ui.space(4.0);
+
+      if Button::with_icon(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(32.0).pill().tooltip(
+            &self.assets.sans,
+            Tooltip::left(match config().ui.color_scheme {
+               config::ColorScheme::Light => &self.assets.tr.switch_to_dark_mode,
+               config::ColorScheme::Dark => &self.assets.tr.switch_to_light_mode,
+            }),
+         ),
+         match config().ui.color_scheme {
+            config::ColorScheme::Dark => &self.assets.icons.lobby.light_mode,
+            config::ColorScheme::Light => &self.assets.icons.lobby.dark_mode,
+         },
+      )
+      .clicked()
+      {
+         config::write(|config| {
+            config.ui.color_scheme = match config.ui.color_scheme {
+               config::ColorScheme::Light => config::ColorScheme::Dark,
+               config::ColorScheme::Dark => config::ColorScheme::Light,
+            };
+         });
+         self.save_config();
+         self.assets.colors = ColorScheme::from(config().ui.color_scheme);
+      }
+
+      ui.space(4.0);
+
+      let language_button = Button::with_icon(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button)
+            .height(32.0)
+            .pill()
+            .tooltip(&self.assets.sans, Tooltip::left(&self.assets.tr.language)),
+         &self.assets.icons.lobby.translate,
+      );
+      let n_languages = self.assets.languages.len() as f32;
+      let language_menu_rect = TooltipPosition::Left.compute_rect(
+         ui,
+         language_button.group(),
+         vector(128.0, 16.0 + n_languages * 24.0 + (n_languages - 1.0) * 4.0),
+         TooltipLayout {
+            spacing: 24.0,
+            root_padding: 8.0,
+         },
+      );
+      view::layout::absolute(&mut self.language_menu.view, language_menu_rect);
+      if language_button.clicked() {
+         self.language_menu.toggle();
+      }
+
+      ui.space(4.0);
+
+      let usage_statistics_button = Button::with_icon(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(32.0).pill().tooltip(
+            &self.assets.sans,
+            Tooltip::left(&self.assets.tr.usage_statistics),
+         ),
+         &self.assets.icons.lobby.usage_statistics,
+      );
+      if usage_statistics_button.clicked() {
+         self.usage_statistics_menu.toggle();
+      }
+      // The preview is refreshed before the menu is laid out, as its size depends on the number
+      // of lines in the report.
+      if self.usage_statistics_menu.is_open() {
+         self.usage_statistics_preview = telemetry::preview();
+      }
+      let usage_statistics_menu_rect = TooltipPosition::Left.compute_rect(
+         ui,
+         usage_statistics_button.group(),
+         vector(
+            Self::USAGE_STATISTICS_MENU_WIDTH,
+            self.usage_statistics_menu_height(),
+         ),
+         TooltipLayout {
+            spacing: 24.0,
+            root_padding: 8.0,
+         },
+      );
+      view::layout::absolute(
+         &mut self.usage_statistics_menu.view,
+         usage_statistics_menu_rect,
+      );
+
+      ui.space(4.0);
+
+      if assets::has_license_page()
+         && Button::with_icon(
+            ui,
+            input,
+            &ButtonArgs::new(ui, &self.assets.colors.action_button).height(32.0).pill().tooltip(
+               &self.assets.sans,
+               Tooltip::left(&self.assets.tr.open_source_licenses),
+            ),
+            &self.assets.icons.lobby.legal,
+         )
+         .clicked()
+      {
+         catch!(assets::open_license_page());
+      }
+   }
+
+   /// Processes the button that opens the menu with saved relay servers.
+   fn process_relay_profiles_button(&mut self, ui: &mut Ui, input: &mut Input) {
+      // Align the button with the text field rather than its label.
+      ui.push((32.0, ui.height()), Layout::Vertical);
+      ui.space(16.0);
+      let button = Button::with_icon(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).height(32.0).pill().tooltip(
+            &self.assets.sans,
+            Tooltip::top(&self.assets.tr.lobby_relay_profiles),
+         ),
+         &self.assets.icons.expand.shrink,
+      );
+      let n_profiles = config().lobby.relay_profiles.len().max(1) as f32;
+      let menu_rect = TooltipPosition::Right.compute_rect(
+         ui,
+         button.group(),
+         vector(
+            256.0,
+            16.0 + n_profiles * 28.0 + 8.0 + TextField::height(&self.assets.sans),
+         ),
+         TooltipLayout {
+            spacing: 24.0,
+            root_padding: 8.0,
+         },
+      );
+      view::layout::absolute(&mut self.relay_profiles_menu.view, menu_rect);
+      if button.clicked() {
+         self.relay_profiles_menu.toggle();
+      }
+      ui.pop();
+   }
+
+   /// Processes the menu with saved relay servers. Picking a server fills in its address, along
+   /// with the settings saved with it.
+   fn process_relay_profiles_menu(&mut self, ui: &mut Ui, input: &mut Input) {
+      if !self
+         .relay_profiles_menu
+         .begin(
+            ui,
+            input,
+            ContextMenuArgs {
+               colors: &self.assets.colors.context_menu,
+            },
+         )
+         .is_open()
+      {
+         return;
+      }
+      ui.pad(8.0);
+
+      let relay = self.relay_field.text().strip_whitespace().to_owned();
+      let mut picked = None;
+      let mut removed = None;
+      let profiles = config().lobby.relay_profiles.clone();
+      if profiles.is_empty() {
+         ui.push((ui.width(), 24.0), Layout::Horizontal);
+         ui.horizontal_label(
+            &self.assets.sans,
+            &self.assets.tr.lobby_no_relay_profiles,
+            self.assets.colors.text,
+            Some((ui.width(), AlignH::Center)),
+         );
+         ui.pop();
+         ui.space(4.0);
+      }
+      for (index, profile) in profiles.iter().enumerate() {
+         ui.push((ui.width(), 24.0), Layout::Horizontal);
+         let name_button = ButtonArgs::new(ui, &self.assets.colors.action_button).pill();
+         if Button::with_text_width(
+            ui,
+            input,
+            &name_button,
+            if profile.address == relay {
+               &self.assets.sans_bold
+            } else {
+               &self.assets.sans
+            },
+            &truncate_text(&self.assets.sans_bold, ui.width() - 56.0, &profile.name),
+            ui.width() - 28.0,
+         )
+         .clicked()
+         {
+            picked = Some(profile.clone());
+         }
+         ui.space(4.0);
+         if Button::with_icon(
+            ui,
+            input,
+            &ButtonArgs::new(ui, &self.assets.colors.action_button).pill(),
+            &self.assets.icons.window.close,
+         )
+         .clicked()
+         {
+            removed = Some(index);
+         }
+         ui.pop();
+         ui.space(4.0);
+      }
+      ui.space(4.0);
+
+      ui.push(
+         (ui.width(), TextField::height(&self.assets.sans)),
+         Layout::HorizontalRev,
+      );
+      let save_clicked = Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.button).pill(),
+         &self.assets.sans,
+         &self.assets.tr.lobby_save_relay_profile,
+      )
+      .clicked();
+      ui.space(8.0);
+      let name_field = self.relay_profile_name_field.process(
+         ui,
+         input,
+         TextFieldArgs {
+            font: &self.assets.sans,
+            width: ui.remaining_width(),
+            colors: &self.assets.colors.text_field,
+            hint: Some(&self.assets.tr.lobby_relay_profile_name),
+         },
+      );
+      ui.pop();
+
+      let name = self.relay_profile_name_field.text().strip_whitespace().to_owned();
+      if (save_clicked || name_field.done()) && !name.is_empty() && !relay.is_empty() {
+         let profile = RelayProfile {
+            name,
+            address: relay,
+            nickname: self.nickname_field.text().strip_whitespace().to_owned(),
+            public_room: self.public_room,
+         };
+         config::write(|config| config.lobby.save_relay_profile(profile));
+         self.relay_profile_name_field.set_text(String::new());
+      }
+      if let Some(profile) = picked {
+         self.relay_field.set_text(profile.address);
+         if !profile.nickname.is_empty() {
+            self.nickname_field.set_text(profile.nickname);
+         }
+         self.public_room = profile.public_room;
+         self.relay_profiles_menu.close();
+      }
+      if let Some(index) = removed {
+         config::write(|config| {
+            config.lobby.relay_profiles.remove(index);
+         });
+      }
+
+      self.relay_profiles_menu.end(ui);
+   }
+
+   fn process_language_menu(&mut self, ui: &mut Ui, input: &mut Input) {
+      if self
+         .language_menu
          .begin(
             ui,
             input,
@@ -572,15 +1839,225 @@ impl State {
       }
    }
 
+   /// Returns the height of a line of text in a paragraph.
+   fn line_height(font: &impl Font) -> f32 {
+      (font.size() * 1.2).ceil()
+   }
+
+   /// Computes the height of the usage statistics menu, which depends on the length of the report.
+   fn usage_statistics_menu_height(&self) -> f32 {
+      let description_lines = self.assets.tr.usage_statistics_description.lines().count() as f32;
+      let preview_lines = self.usage_statistics_preview.lines().count() as f32;
+      16.0
+         + description_lines * Self::line_height(&self.assets.sans)
+         + 8.0
+         + 32.0
+         + 8.0
+         + TextField::height(&self.assets.sans)
+         + 12.0
+         + self.assets.sans_bold.height()
+         + 4.0
+         + preview_lines * Self::line_height(&self.assets.monospace)
+   }
+
+   /// Processes the menu for opting in to sending usage statistics. The menu also shows the
+   /// report that would be sent, so that the user can see what they're agreeing to.
+   fn process_usage_statistics_menu(&mut self, ui: &mut Ui, input: &mut Input) {
+      if !self
+         .usage_statistics_menu
+         .begin(
+            ui,
+            input,
+            ContextMenuArgs {
+               colors: &self.assets.colors.context_menu,
+            },
+         )
+         .is_open()
+      {
+         return;
+      }
+      ui.pad(8.0);
+
+      ui.paragraph(
+         &self.assets.sans,
+         self.assets.tr.usage_statistics_description.split('\n'),
+         self.assets.colors.text,
+         AlignH::Left,
+         None,
+      );
+      ui.space(8.0);
+
+      let enabled = config().telemetry.enabled;
+      ui.push((ui.width(), 32.0), Layout::Horizontal);
+      let toggled = Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(
+            ui,
+            ButtonColors::toggle(
+               enabled,
+               &self.assets.colors.radio_button.normal,
+               &self.assets.colors.radio_button.selected,
+            ),
+         )
+         .height(32.0)
+         .pill(),
+         &self.assets.sans,
+         &self.assets.tr.usage_statistics_enabled,
+      )
+      .clicked();
+      ui.pop();
+      ui.space(8.0);
+
+      let endpoint_field = self.usage_statistics_endpoint_field.process(
+         ui,
+         input,
+         TextFieldArgs {
+            font: &self.assets.sans,
+            width: ui.width(),
+            colors: &self.assets.colors.text_field,
+            hint: Some(&self.assets.tr.usage_statistics_endpoint),
+         },
+      );
+      ui.space(12.0);
+
+      if toggled || endpoint_field.done() || endpoint_field.unfocused() {
+         let endpoint = self.usage_statistics_endpoint_field.text().strip_whitespace().to_owned();
+         config::write(|config| {
+            if toggled {
+               config.telemetry.enabled = !config.telemetry.enabled;
+            }
+            config.telemetry.endpoint = endpoint;
+         });
+      }
+
+      ui.vertical_label(
+         &self.assets.sans_bold,
+         &self.assets.tr.usage_statistics_preview,
+         self.assets.colors.text,
+         AlignH::Left,
+      );
+      ui.space(4.0);
+      ui.paragraph(
+         &self.assets.monospace,
+         self.usage_statistics_preview.lines(),
+         self.assets.colors.text,
+         AlignH::Left,
+         None,
+      );
+
+      self.usage_statistics_menu.end(ui);
+   }
+
+   /// Processes a labelled text field with a hint below it, explaining what's wrong with its
+   /// contents. The hint doesn't take up any space in the layout, so nothing moves around while
+   /// the user is typing.
+   fn field_with_error(
+      field: &mut TextField,
+      ui: &mut Ui,
+      input: &mut Input,
+      assets: &Assets,
+      label: &str,
+      args: TextFieldArgs,
+      error: Option<FieldError>,
+   ) -> TextFieldProcessResult {
+      ui.push(
+         (args.width, TextField::labelled_height(args.font)),
+         Layout::Freeform,
+      );
+      let rect = ui.rect();
+      let result = field.with_label(ui, input, &assets.sans, label, args);
+      if let Some(error) = error {
+         ui.push((0.0, 0.0), Layout::Freeform);
+         ui.set_position(point(rect.left(), rect.bottom() + 2.0));
+         ui.push((rect.width(), 14.0), Layout::Freeform);
+         ui.text(
+            &assets.sans.with_size(12.0),
+            &error.hint(&assets.tr),
+            assets.colors.error,
+            (AlignH::Left, AlignV::Top),
+         );
+         ui.pop();
+         ui.pop();
+      }
+      ui.pop();
+      result
+   }
+
    /// Checks whether a nickname is valid.
-   fn validate_nickname(tr: &Strings, nickname: &str) -> Result<(), Status> {
+   fn check_nickname(nickname: &str) -> Result<(), FieldError> {
       const MAX_LEN: usize = 16;
       if nickname.is_empty() {
-         return Err(Status::Error(tr.error_nickname_must_not_be_empty.clone()));
+         return Err(FieldError::Empty);
+      }
+      if nickname.len() > MAX_LEN {
+         return Err(FieldError::TooLong { max: MAX_LEN });
+      }
+      Ok(())
+   }
+
+   /// Checks whether a room ID or invite token is valid. Links to rooms are also accepted.
+   fn check_room_id(room_id: &str) -> Result<(), FieldError> {
+      if room_id.parse::<RoomLink>().is_ok() {
+         return Ok(());
+      }
+      if room_id.len() != RoomId::LEN && room_id.len() != InviteToken::LEN {
+         return Err(FieldError::InvalidRoomIdLength);
+      }
+      if !room_id.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+         return Err(FieldError::InvalidCharacters);
+      }
+      Ok(())
+   }
+
+   /// Checks whether a room ID the host asked for is valid. Lowercase letters are accepted, as
+   /// they're made uppercase before hosting. Without an ID, the relay picks one at random.
+   fn check_custom_room_id(room_id: &str) -> Result<(), FieldError> {
+      if room_id.is_empty() {
+         return Ok(());
+      }
+      if room_id.len() != RoomId::LEN {
+         return Err(FieldError::WrongLength {
+            length: RoomId::LEN,
+         });
+      }
+      if !room_id.bytes().all(|byte| byte.is_ascii_alphanumeric()) {
+         return Err(FieldError::InvalidCharacters);
+      }
+      Ok(())
+   }
+
+   /// Checks whether a text is no longer than the given number of characters.
+   fn check_length(text: &str, max: usize) -> Result<(), FieldError> {
+      if text.chars().count() > max {
+         return Err(FieldError::TooLong { max });
+      }
+      Ok(())
+   }
+
+   /// Checks whether a nickname is valid, and reports why it's not.
+   fn validate_nickname(tr: &Strings, nickname: &str) -> Result<(), Status> {
+      Self::check_nickname(nickname).map_err(|error| match error {
+         FieldError::TooLong { max } => {
+            Status::Error(tr.error_nickname_too_long.format().with("max-length", max).done())
+         }
+         _ => Status::Error(tr.error_nickname_must_not_be_empty.clone()),
+      })
+   }
+
+   /// Checks whether a room name and description are valid.
+   fn validate_room_info(tr: &Strings, name: &str, description: &str) -> Result<(), Status> {
+      if Self::check_length(name, Peer::MAX_ROOM_NAME_LEN).is_err() {
+         return Err(Status::Error(
+            tr.error_room_name_too_long.format().with("max-length", Peer::MAX_ROOM_NAME_LEN).done(),
+         ));
       }
-      if nickname.len() > 16 {
+      if Self::check_length(description, Peer::MAX_ROOM_DESCRIPTION_LEN).is_err() {
          return Err(Status::Error(
-            tr.error_nickname_too_long.format().with("max-length", MAX_LEN).done(),
+            tr.error_room_description_too_long
+               .format()
+               .with("max-length", Peer::MAX_ROOM_DESCRIPTION_LEN)
+               .done(),
          ));
       }
       Ok(())
@@ -589,30 +2066,94 @@ impl State {
    /// Establishes a connection to the relay and hosts a new room.
    fn host_room(
       socket_system: Arc<SocketSystem>,
+      relay_peer: &mut Option<Peer>,
       tr: &Strings,
       nickname: &str,
       relay_addr_str: &str,
+      room_name: &str,
+      room_description: &str,
+      custom_room_id: &str,
+      public: bool,
+      lan: bool,
+      direct: bool,
    ) -> Result<Peer, Status> {
       Self::validate_nickname(tr, nickname)?;
-      Ok(Peer::host(socket_system, nickname, relay_addr_str))
+      Self::validate_room_info(tr, room_name, room_description)?;
+      if Self::check_custom_room_id(custom_room_id).is_err() {
+         return Err(Status::Error(
+            tr.error_invalid_custom_room_id.format().with("length", RoomId::LEN).done(),
+         ));
+      }
+      let room_id = match custom_room_id {
+         "" => None,
+         room_id => Some(room_id.to_ascii_uppercase().parse()?),
+      };
+      let mut peer = if lan {
+         // Rooms on the local network get a relay of their own, so there's nobody else to pick
+         // the room ID for.
+         Peer::host_on_lan(socket_system, nickname)?
+      } else if direct {
+         Peer::host_directly(socket_system, nickname, relay_addr_str, room_id)?
+      } else {
+         Self::take_relay_peer(relay_peer, relay_addr_str)
+            .and_then(|mut peer| peer.host_another_room(nickname, room_id).ok().map(|()| peer))
+            .unwrap_or_else(|| Peer::host(socket_system, nickname, relay_addr_str, room_id))
+      };
+      peer.set_room_info(room_name, room_description);
+      peer.set_public(public);
+      Ok(peer)
    }
 
    /// Establishes a connection to the relay and joins an existing room.
+   ///
+   /// `room_id_str` may either be a room ID or an invite token; the two are told apart by their
+   /// length.
    fn join_room(
       socket_system: Arc<SocketSystem>,
+      relay_peer: &mut Option<Peer>,
       tr: &Strings,
       nickname: &str,
       relay_addr_str: &str,
       room_id_str: &str,
    ) -> Result<Peer, Status> {
-      if room_id_str.len() != RoomId::LEN {
-         return Err(Status::Error(
-            tr.error_invalid_room_id_length.format().with("length", RoomId::LEN).done(),
-         ));
+      match Self::check_room_id(room_id_str) {
+         Ok(()) => (),
+         Err(FieldError::InvalidCharacters) => {
+            return Err(Status::Error(tr.error_invalid_room_id_characters.clone()))
+         }
+         Err(_) => {
+            return Err(Status::Error(
+               tr.error_invalid_room_id_length
+                  .format()
+                  .with("length", RoomId::LEN)
+                  .with("invite-length", InviteToken::LEN)
+                  .done(),
+            ))
+         }
       }
       Self::validate_nickname(tr, nickname)?;
-      let room_id = room_id_str.parse()?;
-      Ok(Peer::join(socket_system, nickname, relay_addr_str, room_id))
+      let relay_peer = Self::take_relay_peer(relay_peer, relay_addr_str);
+      if room_id_str.len() == InviteToken::LEN {
+         let invite = room_id_str.parse()?;
+         Ok(relay_peer
+            .and_then(|mut peer| {
+               peer.join_another_room_with_invite(nickname, invite).ok().map(|()| peer)
+            })
+            .unwrap_or_else(|| {
+               Peer::join_with_invite(socket_system, nickname, relay_addr_str, invite)
+            }))
+      } else {
+         let room_id = room_id_str.parse()?;
+         Ok(relay_peer
+            .and_then(|mut peer| peer.join_another_room(nickname, room_id).ok().map(|()| peer))
+            .unwrap_or_else(|| Peer::join(socket_system, nickname, relay_addr_str, room_id)))
+      }
+   }
+
+   /// Takes the peer kept after leaving the previous room, if it's connected to the given relay.
+   /// A peer connected to another relay is of no use anymore, so it's dropped.
+   fn take_relay_peer(relay_peer: &mut Option<Peer>, relay_address: &str) -> Option<Peer> {
+      relay_peer.take().filter(|peer| peer.relay_address() == relay_address)
    }
 
    /// Saves the user configuration.
@@ -620,6 +2161,18 @@ impl State {
       config::write(|config| {
          self.nickname_field.text().strip_whitespace().clone_into(&mut config.lobby.nickname);
          self.relay_field.text().strip_whitespace().clone_into(&mut config.lobby.relay);
+         self.room_name_field.text().strip_whitespace().clone_into(&mut config.lobby.room_name);
+         self
+            .room_description_field
+            .text()
+            .strip_whitespace()
+            .clone_into(&mut config.lobby.room_description);
+         config.lobby.public_room = self.public_room;
+         self
+            .usage_statistics_endpoint_field
+            .text()
+            .strip_whitespace()
+            .clone_into(&mut config.telemetry.endpoint);
       });
    }
 }
@@ -639,7 +2192,30 @@ impl AppState for State {
       input.set_mouse_area(0, true);
 
       if let Some(peer) = &mut self.peer {
-         catch!(peer.communicate());
+         if let Err(error) = peer.communicate() {
+            // There's no recovering from errors before getting into the room, so the attempt is
+            // abandoned.
+            self.peer = None;
+            bus::push(Error(error));
+         }
+      }
+      // The connection kept after leaving a room has to keep up with the relay, so that it's
+      // ready for the next room. If it fails, the next room gets a new connection.
+      if let Some(relay_peer) = &mut self.relay_peer {
+         if relay_peer.communicate().is_err() {
+            self.relay_peer = None;
+         }
+      }
+      self.update_relay_health();
+      if let Some(room_list) = &mut self.room_list {
+         match room_list.poll() {
+            Ok(Some(rooms)) => self.public_rooms = rooms,
+            Ok(None) => (),
+            Err(error) => bus::push(Error(error)),
+         }
+      }
+      if let Some(lan_browser) = &mut self.lan_browser {
+         lan_browser.poll();
       }
 
       let padded_root_view = view::layout::padded(&root_view, 8.0);
@@ -690,6 +2266,8 @@ impl AppState for State {
       // Language menu
 
       self.process_language_menu(ui, input);
+      self.process_relay_profiles_menu(ui, input);
+      self.process_usage_statistics_menu(ui, input);
 
       for message in &bus::retrieve_all::<Error>() {
          let error = message.consume().0;
@@ -699,6 +2277,7 @@ impl AppState for State {
       for message in &bus::retrieve_all::<Fatal>() {
          let fatal = message.consume().0;
          tracing::error!("fatal: {:?}", fatal);
+         self.peer = None;
          self.status = Status::Error(
             self
                .assets
@@ -714,6 +2293,8 @@ impl AppState for State {
    fn next_state(self: Box<Self>, renderer: &mut Backend) -> Box<dyn AppState> {
       let mut connected = false;
       if let Some(peer) = &self.peer {
+         // Offline peers have nothing to connect to.
+         connected = peer.is_offline();
          for message in &bus::retrieve_all::<peer::Connected>() {
             tracing::info!("connection established");
             if message.peer == peer.token() {
@@ -727,6 +2308,9 @@ impl AppState for State {
          let mut this = *self;
          let socket_system = Arc::clone(&this.socket_system);
          this.save_config();
+         if let Some(peer) = &this.peer {
+            this.remember_room(peer);
+         }
          match paint::State::new(
             this.assets,
             this.socket_system,
@@ -1,6 +1,49 @@
 mod state;
 pub use state::*;
 
+use whd_common::{WALLHACKD_VERSION, WALLHACKD_YEAR};
+
 pub mod boot;
 pub mod lobby;
 pub mod paint;
+
+/// What the main window's title tells about the canvas that's open.
+pub struct TitleInfo<'a> {
+   /// The ID of the room, shown so that it can be read off the taskbar when inviting people.
+   pub room_id: Option<String>,
+   /// The name of the room. Empty if the room is unnamed.
+   pub room_name: &'a str,
+   /// The name of the file the canvas was loaded from or saved to.
+   pub file_name: Option<&'a str>,
+   /// Whether the canvas has changes that haven't been saved to a file.
+   pub unsaved: bool,
+}
+
+/// Returns the title of the main window. If a canvas is open, what's known about it is shown in
+/// front of the app name.
+pub fn window_title(info: Option<&TitleInfo>) -> String {
+   let app_name = format!(
+      "NetCanv WallhackD ({}) ({})",
+      WALLHACKD_VERSION, WALLHACKD_YEAR
+   );
+   let Some(info) = info else {
+      return app_name;
+   };
+   let mut parts = Vec::new();
+   match (info.room_name, &info.room_id) {
+      ("", Some(room_id)) => parts.push(room_id.clone()),
+      (room_name, Some(room_id)) => parts.push(format!("{} ({})", room_name, room_id)),
+      ("", None) => (),
+      (room_name, None) => parts.push(room_name.to_owned()),
+   }
+   if let Some(file_name) = info.file_name {
+      parts.push(file_name.to_owned());
+   }
+   parts.push(app_name);
+   let title = parts.join(" – ");
+   if info.unsaved {
+      format!("• {}", title)
+   } else {
+      title
+   }
+}
@@ -0,0 +1,51 @@
+//! The `Import document` action.
+
+use std::path::PathBuf;
+
+use rfd::AsyncFileDialog;
+
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+use crate::file_dialog;
+
+use super::{Action, ActionArgs};
+
+/// A bus message requesting that the pages of the document at the given path be imported onto the
+/// canvas.
+pub struct ImportDocument(pub PathBuf);
+
+pub struct ImportDocumentAction {
+   icon: Image,
+}
+
+impl ImportDocumentAction {
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(
+            renderer,
+            include_bytes!("../../../assets/icons/document.svg"),
+         ),
+      }
+   }
+}
+
+impl Action for ImportDocumentAction {
+   fn name(&self) -> &str {
+      "import-document"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn perform(&mut self, ActionArgs { assets, .. }: ActionArgs) -> netcanv::Result<()> {
+      let dialog = AsyncFileDialog::new()
+         .add_filter(&assets.tr.fd_supported_documents, &["pdf", "tif", "tiff"])
+         .add_filter(&assets.tr.fd_pdf_document, &["pdf"])
+         .add_filter(&assets.tr.fd_tiff_document, &["tif", "tiff"]);
+      // The paint state does the importing, since the imported chunks have to be sent out to
+      // everyone in the room.
+      file_dialog::pick_file(dialog, ImportDocument);
+      Ok(())
+   }
+}
@@ -0,0 +1,42 @@
+//! The `Keyboard shortcuts` action.
+
+use nysa::global as bus;
+
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+
+use super::{Action, ActionArgs};
+
+/// Message asking the paint state to open the Keyboard shortcuts window.
+pub struct OpenKeyboardShortcuts;
+
+pub struct KeyboardShortcutsAction {
+   icon: Image,
+}
+
+impl KeyboardShortcutsAction {
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(
+            renderer,
+            include_bytes!("../../../assets/icons/keyboard.svg"),
+         ),
+      }
+   }
+}
+
+impl Action for KeyboardShortcutsAction {
+   fn name(&self) -> &str {
+      "keyboard-shortcuts"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn perform(&mut self, _: ActionArgs) -> netcanv::Result<()> {
+      // The paint state owns the window manager, so it's the one that has to open the window.
+      bus::push(OpenKeyboardShortcuts);
+      Ok(())
+   }
+}
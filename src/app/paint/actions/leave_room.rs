@@ -0,0 +1,39 @@
+//! The `Leave room` action.
+
+use nysa::global as bus;
+
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+
+use super::{Action, ActionArgs};
+
+/// Message asking the paint state to leave the room and go back to the lobby.
+pub struct LeaveRoom;
+
+pub struct LeaveRoomAction {
+   icon: Image,
+}
+
+impl LeaveRoomAction {
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/leave.svg")),
+      }
+   }
+}
+
+impl Action for LeaveRoomAction {
+   fn name(&self) -> &str {
+      "leave-room"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn perform(&mut self, _: ActionArgs) -> netcanv::Result<()> {
+      // The paint state owns the peer, so it's the one that has to leave.
+      bus::push(LeaveRoom);
+      Ok(())
+   }
+}
@@ -1,13 +1,22 @@
 //! Overflow menu actions.
 
+mod import_document;
+mod keyboard_shortcuts;
+mod leave_room;
+mod reference_image;
 mod save_to_file;
 
+pub use import_document::*;
+pub use keyboard_shortcuts::*;
+pub use leave_room::*;
+pub use reference_image::*;
 pub use save_to_file::*;
 
 use crate::assets::Assets;
 use crate::backend::{Backend, Image};
+use crate::keymap::KeyBinding;
 use crate::paint_canvas::PaintCanvas;
-use crate::project_file::ProjectFile;
+use crate::project_file::{ProjectFile, ProjectPage};
 
 pub trait Action {
    /// Returns the name of the action.
@@ -16,6 +25,11 @@ pub trait Action {
    /// Returns the icon of the action.
    fn icon(&self) -> &Image;
 
+   /// Returns the key binding that performs the action, if it has one.
+   fn key_shortcut(&self) -> Option<KeyBinding> {
+      None
+   }
+
    /// Performs the action.
    fn perform(&mut self, args: ActionArgs) -> netcanv::Result<()>;
 
@@ -30,6 +44,8 @@ pub struct ActionArgs<'a> {
    pub assets: &'a Assets,
    pub paint_canvas: &'a mut PaintCanvas,
    pub project_file: &'a mut ProjectFile,
+   /// The pages saved along with the canvas.
+   pub pages: Vec<ProjectPage<'a>>,
    pub renderer: &'a mut Backend,
 }
 
@@ -0,0 +1,42 @@
+//! The `Load reference image` action.
+
+use rfd::AsyncFileDialog;
+
+use crate::app::paint::reference::LoadReferenceImage;
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+use crate::file_dialog;
+
+use super::{Action, ActionArgs};
+
+pub struct ReferenceImageAction {
+   icon: Image,
+}
+
+impl ReferenceImageAction {
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/image.svg")),
+      }
+   }
+}
+
+impl Action for ReferenceImageAction {
+   fn name(&self) -> &str {
+      "reference-image"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn perform(&mut self, ActionArgs { assets, .. }: ActionArgs) -> netcanv::Result<()> {
+      let dialog = AsyncFileDialog::new().add_filter(
+         &assets.tr.fd_supported_image_files,
+         &["png", "jpg", "jpeg", "jfif"],
+      );
+      // The paint state owns the reference image, since it's drawn along with the canvas.
+      file_dialog::pick_file(dialog, LoadReferenceImage);
+      Ok(())
+   }
+}
@@ -1,26 +1,29 @@
 //! The `Save to file` action.
 
-use web_time::{Duration, Instant};
+use std::path::PathBuf;
 
-use rfd::FileDialog;
+use nysa::global as bus;
+use rfd::AsyncFileDialog;
 
 use crate::assets::Assets;
 use crate::backend::{Backend, Image};
+use crate::config::config;
+use crate::file_dialog;
+use crate::keymap::KeyBinding;
 
 use super::{Action, ActionArgs};
 
+/// Message sent once the user picks where to save the canvas.
+struct SaveTo(PathBuf);
+
 pub struct SaveToFileAction {
    icon: Image,
-   last_autosave: Instant,
 }
 
 impl SaveToFileAction {
-   const AUTOSAVE_INTERVAL: Duration = Duration::from_secs(60);
-
    pub fn new(renderer: &mut Backend) -> Self {
       Self {
          icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/save.svg")),
-         last_autosave: Instant::now(),
       }
    }
 }
@@ -34,23 +37,16 @@ impl Action for SaveToFileAction {
       &self.icon
    }
 
-   fn perform(
-      &mut self,
-      ActionArgs {
-         assets,
-         paint_canvas,
-         project_file,
-         renderer,
-         ..
-      }: ActionArgs,
-   ) -> netcanv::Result<()> {
-      if let Some(path) = FileDialog::new()
+   fn key_shortcut(&self) -> Option<KeyBinding> {
+      Some(config().keymap.actions.save_to_file)
+   }
+
+   fn perform(&mut self, ActionArgs { assets, .. }: ActionArgs) -> netcanv::Result<()> {
+      let dialog = AsyncFileDialog::new()
          .add_filter(&assets.tr.fd_png_file, &["png"])
-         .add_filter(&assets.tr.fd_netcanv_canvas, &["netcanv", "toml"])
-         .save_file()
-      {
-         project_file.save(renderer, Some(&path), paint_canvas)?
-      }
+         .add_filter(&assets.tr.fd_svg_file, &["svg"])
+         .add_filter(&assets.tr.fd_netcanv_canvas, &["netcanv", "toml"]);
+      file_dialog::save_file(dialog, SaveTo);
       Ok(())
    }
 
@@ -59,16 +55,14 @@ impl Action for SaveToFileAction {
       ActionArgs {
          paint_canvas,
          project_file,
+         mut pages,
          renderer,
          ..
       }: ActionArgs,
    ) -> netcanv::Result<()> {
-      if project_file.filename().is_some() && self.last_autosave.elapsed() > Self::AUTOSAVE_INTERVAL
-      {
-         tracing::info!("autosaving chunks");
-         project_file.save(renderer, None, paint_canvas)?;
-         tracing::info!("autosave complete");
-         self.last_autosave = Instant::now();
+      for message in &bus::retrieve_all::<SaveTo>() {
+         let SaveTo(path) = message.consume();
+         project_file.save(renderer, Some(&path), paint_canvas, &mut pages)?;
       }
       Ok(())
    }
@@ -0,0 +1,346 @@
+//! The audit log. The host keeps a record of what happens in the room, to make moderation easier.
+
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{AlignH, Layout, Padding};
+use nysa::global as bus;
+use web_time::{Instant, SystemTime, UNIX_EPOCH};
+
+use crate::assets::Assets;
+use crate::common::truncate_text;
+use crate::paint_canvas::ChunkRegion;
+use crate::ui::view::Dimensions;
+use crate::ui::wm::{HitTest, WindowContent, WindowContentArgs};
+use crate::ui::{UiElements, UiInput};
+
+use super::people::PeopleWindow;
+
+/// A bus message reporting something that should be recorded in the audit log.
+pub struct AuditEvent {
+   /// The peer responsible for the event, or `None` if it's ourselves.
+   pub author: Option<PeerId>,
+   pub kind: AuditEventKind,
+}
+
+impl AuditEvent {
+   /// Pushes an event caused by ourselves onto the bus.
+   pub fn push(kind: AuditEventKind) {
+      bus::push(Self { author: None, kind });
+   }
+
+   /// Pushes an event caused by the given peer onto the bus.
+   pub fn push_from(author: PeerId, kind: AuditEventKind) {
+      bus::push(Self {
+         author: Some(author),
+         kind,
+      });
+   }
+}
+
+/// The kind of event recorded in the audit log.
+#[derive(Clone)]
+pub enum AuditEventKind {
+   Joined,
+   Left,
+   /// An image was pasted onto the canvas. The position is in pixels, and is `None` if it's not
+   /// known at the time of pasting.
+   Pasted {
+      size: (u32, u32),
+      position: Option<(i32, i32)>,
+   },
+   LockedRegion(ChunkRegion),
+   /// All locked regions containing the given chunk were unlocked.
+   UnlockedRegions((i32, i32)),
+   /// The captured contents of a selection spanning the given chunks were deleted.
+   Cleared(ChunkRegion),
+   /// Modifications to the canvas were paused (`true`) or resumed (`false`).
+   Paused(bool),
+   /// Changes made by someone else were reverted.
+   Reverted {
+      nickname: String,
+      chunks: usize,
+   },
+}
+
+impl AuditEventKind {
+   /// Returns the name of the event kind, used when exporting and translating the log.
+   pub fn name(&self) -> &'static str {
+      match self {
+         AuditEventKind::Joined => "joined",
+         AuditEventKind::Left => "left",
+         AuditEventKind::Pasted { .. } => "pasted",
+         AuditEventKind::LockedRegion(_) => "locked-region",
+         AuditEventKind::UnlockedRegions(_) => "unlocked-regions",
+         AuditEventKind::Cleared(_) => "cleared",
+         AuditEventKind::Paused(true) => "paused",
+         AuditEventKind::Paused(false) => "resumed",
+         AuditEventKind::Reverted { .. } => "reverted",
+      }
+   }
+
+   /// Returns the details of the event in a machine-readable form, used when exporting the log.
+   fn details(&self) -> String {
+      match self {
+         AuditEventKind::Joined | AuditEventKind::Left | AuditEventKind::Paused(_) => String::new(),
+         AuditEventKind::Pasted {
+            size: (width, height),
+            position,
+         } => match position {
+            Some((x, y)) => format!("{}x{} at {} {}", width, height, x, y),
+            None => format!("{}x{}", width, height),
+         },
+         AuditEventKind::LockedRegion(region) | AuditEventKind::Cleared(region) => format!(
+            "chunks {} {} to {} {}",
+            region.top_left.0, region.top_left.1, region.bottom_right.0, region.bottom_right.1
+         ),
+         AuditEventKind::UnlockedRegions((x, y)) => format!("chunk {} {}", x, y),
+         AuditEventKind::Reverted { nickname, chunks } => {
+            format!("{} chunks by {}", chunks, nickname)
+         }
+      }
+   }
+
+   /// Returns a translated description of the event.
+   fn describe(&self, assets: &Assets) -> String {
+      let message = assets.tr.audit_event.get(self.name());
+      let formatter = message.format();
+      match self {
+         AuditEventKind::Joined | AuditEventKind::Left | AuditEventKind::Paused(_) => {
+            formatter.done()
+         }
+         AuditEventKind::Pasted {
+            size: (width, height),
+            ..
+         } => formatter.with("width", *width).with("height", *height).done(),
+         AuditEventKind::LockedRegion(region) | AuditEventKind::Cleared(region) => formatter
+            .with("left", region.top_left.0)
+            .with("top", region.top_left.1)
+            .with("right", region.bottom_right.0)
+            .with("bottom", region.bottom_right.1)
+            .done(),
+         AuditEventKind::UnlockedRegions((x, y)) => formatter.with("x", *x).with("y", *y).done(),
+         AuditEventKind::Reverted { nickname, chunks } => {
+            formatter.with("nickname", nickname.as_str()).with("count", *chunks).done()
+         }
+      }
+   }
+}
+
+/// A single entry in the audit log.
+#[derive(Clone)]
+pub struct AuditLogEntry {
+   created: Instant,
+   timestamp: SystemTime,
+   peer_id: Option<PeerId>,
+   nickname: String,
+   kind: AuditEventKind,
+}
+
+/// The log of events that happened in a room.
+pub struct AuditLog {
+   entries: VecDeque<AuditLogEntry>,
+}
+
+impl AuditLog {
+   /// The maximum number of entries kept in the log. Older entries are dropped.
+   const MAX_ENTRIES: usize = 4096;
+
+   /// The minimum area (in pixels) of a paste for it to be recorded in the log.
+   pub const LARGE_PASTE_AREA: u32 = 256 * 256;
+
+   /// Creates a new, empty audit log.
+   pub fn new() -> Self {
+      Self {
+         entries: VecDeque::new(),
+      }
+   }
+
+   /// Records an event caused by the peer with the given ID and nickname.
+   pub fn record(&mut self, peer_id: Option<PeerId>, nickname: &str, kind: AuditEventKind) {
+      if let AuditEventKind::Pasted {
+         size: (width, height),
+         ..
+      } = &kind
+      {
+         if width.saturating_mul(*height) < Self::LARGE_PASTE_AREA {
+            return;
+         }
+      }
+      if self.entries.len() >= Self::MAX_ENTRIES {
+         self.entries.pop_front();
+      }
+      self.entries.push_back(AuditLogEntry {
+         created: Instant::now(),
+         timestamp: SystemTime::now(),
+         peer_id,
+         nickname: nickname.to_owned(),
+         kind,
+      });
+   }
+
+   /// Returns the `count` most recent entries, newest first.
+   pub fn latest(&self, count: usize) -> impl Iterator<Item = &AuditLogEntry> {
+      self.entries.iter().rev().take(count)
+   }
+
+   /// Exports the log to a CSV file at the given path.
+   pub fn export(&self, path: &Path) -> netcanv::Result<()> {
+      let mut csv = String::from("timestamp,peer_id,nickname,event,details\n");
+      for entry in &self.entries {
+         let timestamp = entry.timestamp.duration_since(UNIX_EPOCH).unwrap_or_default().as_secs();
+         let peer_id = entry.peer_id.map(|peer_id| peer_id.to_string()).unwrap_or_default();
+         csv.push_str(&format!(
+            "{},{},{},{},{}\n",
+            timestamp,
+            peer_id,
+            csv_quote(&entry.nickname),
+            entry.kind.name(),
+            csv_quote(&entry.kind.details()),
+         ));
+      }
+      std::fs::write(path, csv)?;
+      Ok(())
+   }
+}
+
+/// Quotes a CSV field, escaping any quotes inside of it.
+fn csv_quote(field: &str) -> String {
+   format!("\"{}\"", field.replace('"', "\"\""))
+}
+
+/// A bus message requesting that the audit log be exported to the given CSV file.
+pub struct ExportAuditLog(pub PathBuf);
+
+/// Data shared between the audit log window and the paint state.
+pub struct AuditLogWindowData {
+   /// The most recent entries in the log, newest first.
+   pub entries: Vec<AuditLogEntry>,
+   /// Set when the user asks to export the log to a file.
+   pub export_requested: bool,
+}
+
+impl AuditLogWindowData {
+   pub fn new() -> Self {
+      Self {
+         entries: Vec::new(),
+         export_requested: false,
+      }
+   }
+}
+
+pub struct AuditLogWindow;
+
+impl AuditLogWindow {
+   const WIDTH: f32 = 448.0;
+   const PADDING: f32 = 12.0;
+   const HEADER_HEIGHT: f32 = 48.0;
+   const ROW_HEIGHT: f32 = 20.0;
+
+   /// The number of entries displayed in the window at once.
+   pub const VISIBLE_ENTRIES: usize = 16;
+
+   /// Returns the dimensions of the window.
+   pub fn dimensions() -> Dimensions {
+      Dimensions::from((
+         Self::WIDTH,
+         Self::HEADER_HEIGHT + Self::VISIBLE_ENTRIES as f32 * Self::ROW_HEIGHT + Self::PADDING,
+      ))
+   }
+
+   /// Formats the time elapsed since the entry was created.
+   fn format_age(entry: &AuditLogEntry, assets: &Assets) -> String {
+      let minutes = entry.created.elapsed().as_secs() / 60;
+      if minutes == 0 {
+         assets.tr.audit_log_just_now.clone()
+      } else {
+         assets.tr.audit_log_minutes_ago.format().with("minutes", minutes).done()
+      }
+   }
+}
+
+impl WindowContent for AuditLogWindow {
+   type Data = AuditLogWindowData;
+
+   fn process(
+      &mut self,
+      WindowContentArgs {
+         ui,
+         input,
+         assets,
+         hit_test,
+         ..
+      }: &mut WindowContentArgs,
+      data: &mut Self::Data,
+   ) {
+      ui.push(ui.size(), Layout::Vertical);
+
+      // The title bar.
+      ui.push((ui.width(), Self::HEADER_HEIGHT), Layout::HorizontalRev);
+      ui.pad((Self::PADDING, 0.0));
+      ui.space(PeopleWindow::WINDOW_BUTTONS_WIDTH);
+      if PeopleWindow::small_button(ui, input, assets, &assets.tr.audit_log_export) {
+         data.export_requested = true;
+      }
+      ui.space(8.0);
+      ui.push((ui.remaining_width(), ui.height()), Layout::Horizontal);
+      if ui.hover(input) {
+         **hit_test = HitTest::Draggable;
+      }
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.audit_log,
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+      ui.pop();
+
+      // The entries.
+      ui.push(ui.remaining_size(), Layout::Vertical);
+      ui.pad(Padding {
+         top: 0.0,
+         ..Padding::even(Self::PADDING)
+      });
+      if data.entries.is_empty() {
+         ui.vertical_label(
+            &assets.sans,
+            &assets.tr.audit_log_empty,
+            assets.colors.text,
+            AlignH::Left,
+         );
+      }
+      let age_width = 80.0;
+      let nickname_width = 112.0;
+      for entry in &data.entries {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         ui.horizontal_label(
+            &assets.sans,
+            &Self::format_age(entry, assets),
+            assets.colors.text,
+            Some((age_width, AlignH::Left)),
+         );
+         ui.horizontal_label(
+            &assets.sans_bold,
+            &truncate_text(&assets.sans_bold, nickname_width - 8.0, &entry.nickname),
+            assets.colors.text,
+            Some((nickname_width, AlignH::Left)),
+         );
+         ui.horizontal_label(
+            &assets.sans,
+            &truncate_text(
+               &assets.sans,
+               ui.remaining_width(),
+               &entry.kind.describe(assets),
+            ),
+            assets.colors.text,
+            None,
+         );
+         ui.pop();
+      }
+      ui.pop();
+
+      ui.pop();
+   }
+}
@@ -0,0 +1,156 @@
+//! Background patterns drawn beneath the canvas's chunks. Backgrounds aren't part of the canvas's
+//! pixels, so they don't end up in saved images.
+
+use netcanv_protocol::client::Background;
+use netcanv_renderer::paws::{point, vector, Color, LineCap, Point, Rect, Renderer, Vector};
+
+use crate::backend::Backend;
+use crate::viewport::Viewport;
+
+/// The distance between lines, in pixels on the canvas.
+const SPACING: f32 = 32.0;
+
+/// How often a darker line is drawn on graph paper.
+const MAJOR_LINE_EVERY: i64 = 5;
+
+/// The smallest distance between lines on the screen. When zoomed out further, every other line is
+/// skipped, so that the pattern doesn't turn into a solid color.
+pub(super) const MIN_SCREEN_SPACING: f32 = 8.0;
+
+/// Returns the positions of the lines between `from` and `to`.
+pub(super) fn steps(from: f32, to: f32, spacing: f32) -> impl Iterator<Item = f32> {
+   let first = (from / spacing).ceil() as i64;
+   let last = (to / spacing).floor() as i64;
+   (first..=last).map(move |index| index as f32 * spacing)
+}
+
+/// Draws a line given in canvas coordinates.
+pub(super) fn line(
+   renderer: &mut Backend,
+   viewport: &Viewport,
+   canvas_size: Vector,
+   (a, b): (Point, Point),
+   color: Color,
+) {
+   let a = viewport.to_screen_space(a, canvas_size).floor();
+   let b = viewport.to_screen_space(b, canvas_size).floor();
+   renderer.line(a, b, color, LineCap::Butt, 1.0);
+}
+
+/// Draws lines of the form `y = slope * x + intercept` across the area, with the intercepts
+/// spaced `spacing` apart.
+fn sloped_lines(
+   renderer: &mut Backend,
+   viewport: &Viewport,
+   canvas_size: Vector,
+   area: Rect,
+   slope: f32,
+   spacing: f32,
+   color: Color,
+) {
+   let corners = [
+      area.top_left(),
+      point(area.right(), area.top()),
+      point(area.left(), area.bottom()),
+      area.bottom_right(),
+   ];
+   let intercepts = corners.map(|corner| corner.y - slope * corner.x);
+   let min = intercepts.iter().copied().fold(f32::INFINITY, f32::min);
+   let max = intercepts.iter().copied().fold(f32::NEG_INFINITY, f32::max);
+   for intercept in steps(min, max, spacing) {
+      // Clip the line to the area, so that it doesn't stick out of the canvas's bounds.
+      let at_top = (area.top() - intercept) / slope;
+      let at_bottom = (area.bottom() - intercept) / slope;
+      let left = at_top.min(at_bottom).max(area.left());
+      let right = at_top.max(at_bottom).min(area.right());
+      if left < right {
+         let a = point(left, slope * left + intercept);
+         let b = point(right, slope * right + intercept);
+         line(renderer, viewport, canvas_size, (a, b), color);
+      }
+   }
+}
+
+/// Draws the background over the part of the canvas that's visible in the viewport. If the canvas
+/// is bounded, the background only covers its bounds.
+pub fn draw(
+   renderer: &mut Backend,
+   background: Background,
+   viewport: &Viewport,
+   canvas_size: Vector,
+   bounds: Option<Rect>,
+) {
+   let visible = viewport.visible_rect(canvas_size);
+   let area = match bounds {
+      Some(bounds) => {
+         let left = visible.left().max(bounds.left());
+         let top = visible.top().max(bounds.top());
+         let right = visible.right().min(bounds.right());
+         let bottom = visible.bottom().min(bounds.bottom());
+         if left >= right || top >= bottom {
+            return;
+         }
+         Rect::new(point(left, top), vector(right - left, bottom - top))
+      }
+      None => visible,
+   };
+   let mut spacing = SPACING;
+   while spacing * viewport.zoom() < MIN_SCREEN_SPACING {
+      spacing *= 2.0;
+   }
+
+   let minor = Color::BLACK.with_alpha(24);
+   let major = Color::BLACK.with_alpha(56);
+   match background {
+      Background::Blank => (),
+      Background::Graph => {
+         let color = |position: f32| {
+            if (position / SPACING).round() as i64 % MAJOR_LINE_EVERY == 0 {
+               major
+            } else {
+               minor
+            }
+         };
+         for x in steps(area.left(), area.right(), spacing) {
+            let ends = (point(x, area.top()), point(x, area.bottom()));
+            line(renderer, viewport, canvas_size, ends, color(x));
+         }
+         for y in steps(area.top(), area.bottom(), spacing) {
+            let ends = (point(area.left(), y), point(area.right(), y));
+            line(renderer, viewport, canvas_size, ends, color(y));
+         }
+      }
+      Background::Dots => {
+         for y in steps(area.top(), area.bottom(), spacing) {
+            for x in steps(area.left(), area.right(), spacing) {
+               let center = viewport.to_screen_space(point(x, y), canvas_size).floor();
+               renderer.fill(
+                  Rect::new(center - vector(1.0, 1.0), vector(2.0, 2.0)),
+                  major,
+                  0.0,
+               );
+            }
+         }
+      }
+      Background::Lined => {
+         let color = Color::new(0x42, 0x7b, 0xd4, 64);
+         for y in steps(area.top(), area.bottom(), spacing) {
+            let ends = (point(area.left(), y), point(area.right(), y));
+            line(renderer, viewport, canvas_size, ends, color);
+         }
+      }
+      Background::Isometric => {
+         // The triangles' sides are `spacing` long, so the vertical lines are as far apart as the
+         // triangles are tall.
+         let height = spacing * 3f32.sqrt() / 2.0;
+         for x in steps(area.left(), area.right(), height) {
+            let ends = (point(x, area.top()), point(x, area.bottom()));
+            line(renderer, viewport, canvas_size, ends, minor);
+         }
+         let slope = 1.0 / 3f32.sqrt();
+         for slope in [slope, -slope] {
+            sloped_lines(renderer, viewport, canvas_size, area, slope, spacing, minor);
+         }
+      }
+   }
+}
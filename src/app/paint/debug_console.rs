@@ -0,0 +1,229 @@
+//! The debug console. Shows the log stream and accepts commands, so that multiplayer issues can be
+//! debugged without having to attach to stderr.
+
+use netcanv_protocol::client::AdminCommand;
+use netcanv_renderer::paws::{AlignH, Layout, Padding};
+use tracing::Level;
+
+use crate::common::truncate_text;
+use crate::console_log::LogRecord;
+use crate::ui::view::Dimensions;
+use crate::ui::wm::{HitTest, WindowContent, WindowContentArgs};
+use crate::ui::{Focus, TextField, TextFieldArgs, UiElements, UiInput};
+
+use super::people::PeopleWindow;
+
+/// A command entered into the debug console.
+pub enum ConsoleCommand {
+   /// Lists the available commands.
+   Help,
+   /// Clears the log.
+   Clear,
+   /// Logs the state of the room and the canvas.
+   Dump,
+   /// Downloads all chunks from the host again.
+   Resync,
+   /// Changes the log filter.
+   LogLevel(String),
+   /// Drops the given fraction of client packets, both incoming and outgoing.
+   PacketLoss(f32),
+   /// Asks the host to run an admin command.
+   Admin {
+      password: String,
+      command: AdminCommand,
+   },
+}
+
+impl ConsoleCommand {
+   /// The usage of each command, listed by `help`.
+   pub const USAGE: &'static [&'static str] = &[
+      "help - list available commands",
+      "clear - clear the log",
+      "dump - log the state of the room and the canvas",
+      "resync - download all chunks from the host again",
+      "level <filter> - set the log filter, eg. `debug` or `netcanv::net=trace`",
+      "loss <percent> - simulate loss of client packets",
+      "admin <password> save|status|load <path> - manage the host's canvas",
+   ];
+
+   /// Parses a command line. Returns a description of the problem if the command is not valid.
+   pub fn parse(line: &str) -> Result<Self, String> {
+      let mut words = line.split_whitespace();
+      let command = words.next().unwrap_or("");
+      let argument = words.next();
+      let command = match (command, argument) {
+         ("help", None) => Self::Help,
+         ("clear", None) => Self::Clear,
+         ("dump", None) => Self::Dump,
+         ("resync", None) => Self::Resync,
+         ("level", Some(filter)) => Self::LogLevel(filter.to_owned()),
+         ("loss", Some(percent)) => {
+            let percent: f32 = percent
+               .trim_end_matches('%')
+               .parse()
+               .map_err(|_| format!("`{}` is not a percentage", percent))?;
+            if !(0.0..=100.0).contains(&percent) {
+               return Err("packet loss must be between 0% and 100%".to_owned());
+            }
+            Self::PacketLoss(percent / 100.0)
+         }
+         ("admin", Some(password)) => {
+            let command = match words.next() {
+               Some("save") => AdminCommand::Save,
+               Some("status") => AdminCommand::Status,
+               Some("load") => {
+                  let path = words.by_ref().collect::<Vec<_>>().join(" ");
+                  if path.is_empty() {
+                     return Err("`admin load` expects a path".to_owned());
+                  }
+                  AdminCommand::Load(path)
+               }
+               _ => return Err("`admin` expects `save`, `status`, or `load <path>`".to_owned()),
+            };
+            Self::Admin {
+               password: password.to_owned(),
+               command,
+            }
+         }
+         ("admin", None) => return Err("`admin` expects a password".to_owned()),
+         ("level" | "loss", None) => return Err(format!("`{}` expects an argument", command)),
+         _ => return Err(format!("unknown command `{}`, try `help`", line.trim())),
+      };
+      if words.next().is_some() {
+         return Err(format!("too many arguments to `{}`", line.trim()));
+      }
+      Ok(command)
+   }
+}
+
+/// Data shared between the debug console window and the paint state.
+pub struct DebugConsoleWindowData {
+   /// The most recent log records, oldest first.
+   pub records: Vec<LogRecord>,
+   /// Command lines entered since the paint state last took them.
+   pub commands: Vec<String>,
+   command_field: TextField,
+}
+
+impl DebugConsoleWindowData {
+   pub fn new() -> Self {
+      Self {
+         records: Vec::new(),
+         commands: Vec::new(),
+         command_field: TextField::new(None),
+      }
+   }
+}
+
+pub struct DebugConsoleWindow;
+
+impl DebugConsoleWindow {
+   const WIDTH: f32 = 640.0;
+   const PADDING: f32 = 12.0;
+   const HEADER_HEIGHT: f32 = 48.0;
+   const LINE_HEIGHT: f32 = 16.0;
+   const COMMAND_HEIGHT: f32 = 40.0;
+
+   /// The number of log records displayed in the window at once.
+   pub const VISIBLE_RECORDS: usize = 24;
+
+   /// Returns the dimensions of the window.
+   pub fn dimensions() -> Dimensions {
+      Dimensions::from((
+         Self::WIDTH,
+         Self::HEADER_HEIGHT
+            + Self::VISIBLE_RECORDS as f32 * Self::LINE_HEIGHT
+            + Self::COMMAND_HEIGHT
+            + Self::PADDING,
+      ))
+   }
+}
+
+impl WindowContent for DebugConsoleWindow {
+   type Data = DebugConsoleWindowData;
+
+   fn process(
+      &mut self,
+      WindowContentArgs {
+         ui,
+         input,
+         assets,
+         hit_test,
+         ..
+      }: &mut WindowContentArgs,
+      data: &mut Self::Data,
+   ) {
+      ui.push(ui.size(), Layout::Vertical);
+
+      // The title bar.
+      ui.push((ui.width(), Self::HEADER_HEIGHT), Layout::Horizontal);
+      ui.pad((Self::PADDING, 0.0));
+      ui.push(
+         (ui.width() - PeopleWindow::WINDOW_BUTTONS_WIDTH, ui.height()),
+         Layout::Horizontal,
+      );
+      if ui.hover(input) {
+         **hit_test = HitTest::Draggable;
+      }
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.debug_console,
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+      ui.pop();
+
+      ui.push(ui.remaining_size(), Layout::Vertical);
+      ui.pad(Padding {
+         top: 0.0,
+         ..Padding::even(Self::PADDING)
+      });
+
+      // The log.
+      ui.push(
+         (ui.width(), Self::VISIBLE_RECORDS as f32 * Self::LINE_HEIGHT),
+         Layout::Vertical,
+      );
+      for record in &data.records {
+         let color = match record.level {
+            Level::ERROR | Level::WARN => assets.colors.error,
+            _ => assets.colors.text,
+         };
+         let line = format!("{:5} {}: {}", record.level, record.target, record.message);
+         ui.push((ui.width(), Self::LINE_HEIGHT), Layout::Horizontal);
+         ui.horizontal_label(
+            &assets.monospace,
+            &truncate_text(&assets.monospace, ui.width(), &line),
+            color,
+            None,
+         );
+         ui.pop();
+      }
+      ui.pop();
+
+      // The command line.
+      ui.space(Self::COMMAND_HEIGHT - TextField::height(&assets.monospace));
+      let command = data.command_field.process(
+         ui,
+         input,
+         TextFieldArgs {
+            width: ui.width(),
+            colors: &assets.colors.text_field,
+            hint: Some(&assets.tr.debug_console_hint),
+            font: &assets.monospace,
+         },
+      );
+      if command.done() {
+         let line = data.command_field.text().trim().to_owned();
+         if !line.is_empty() {
+            data.commands.push(line);
+         }
+         data.command_field.set_text(String::new());
+         data.command_field.set_focus(true);
+      }
+      ui.pop();
+
+      ui.pop();
+   }
+}
@@ -0,0 +1,80 @@
+//! The grid overlay. Unlike backgrounds, the grid is drawn over the canvas's chunks and is only
+//! shown locally, as a drawing aid.
+
+use netcanv_renderer::paws::{point, Color, Point, Vector};
+
+use crate::backend::Backend;
+use crate::config::GridConfig;
+use crate::paint_canvas::chunk::Chunk;
+use crate::viewport::Viewport;
+
+use super::background::{line, steps, MIN_SCREEN_SPACING};
+
+/// The zoom factor from which individual pixels are outlined.
+const PIXEL_GRID_MIN_ZOOM: f32 = 8.0;
+
+/// Returns the spacing of the grid, in pixels on the canvas.
+fn spacing(config: &GridConfig) -> f32 {
+   config.spacing.max(1) as f32
+}
+
+/// Snaps a point on the canvas to the nearest grid intersection.
+pub fn snap(config: &GridConfig, position: Point) -> Point {
+   let spacing = spacing(config);
+   point(
+      (position.x / spacing).round() * spacing,
+      (position.y / spacing).round() * spacing,
+   )
+}
+
+/// Returns whether points should be snapped to the grid. Snapping only applies while the grid is
+/// shown, so that it's never a surprise.
+pub fn snapping_enabled(config: &GridConfig) -> bool {
+   config.enabled && config.snap
+}
+
+/// Draws vertical and horizontal lines across the visible part of the canvas, `spacing` apart.
+fn lines(
+   renderer: &mut Backend,
+   viewport: &Viewport,
+   canvas_size: Vector,
+   spacing: f32,
+   color: Color,
+) {
+   let visible = viewport.visible_rect(canvas_size);
+   for x in steps(visible.left(), visible.right(), spacing) {
+      let ends = (point(x, visible.top()), point(x, visible.bottom()));
+      line(renderer, viewport, canvas_size, ends, color);
+   }
+   for y in steps(visible.top(), visible.bottom(), spacing) {
+      let ends = (point(visible.left(), y), point(visible.right(), y));
+      line(renderer, viewport, canvas_size, ends, color);
+   }
+}
+
+/// Draws the grid over the part of the canvas that's visible in the viewport.
+pub fn draw(renderer: &mut Backend, config: &GridConfig, viewport: &Viewport, canvas_size: Vector) {
+   if !config.enabled {
+      return;
+   }
+   let pixel = Color::BLACK.with_alpha(16);
+   let grid = Color::BLACK.with_alpha(48);
+   let chunk = Color::new(0x42, 0x7b, 0xd4, 128);
+
+   if config.pixel_grid && viewport.zoom() >= PIXEL_GRID_MIN_ZOOM {
+      lines(renderer, viewport, canvas_size, 1.0, pixel);
+   }
+
+   let mut spacing = spacing(config);
+   while spacing * viewport.zoom() < MIN_SCREEN_SPACING {
+      spacing *= 2.0;
+   }
+   lines(renderer, viewport, canvas_size, spacing, grid);
+
+   if config.highlight_chunks {
+      let chunk_size = Chunk::SIZE.0 as f32;
+      if chunk_size * viewport.zoom() >= MIN_SCREEN_SPACING {
+         lines(renderer, viewport, canvas_size, chunk_size, chunk);
+      }
+   }
+}
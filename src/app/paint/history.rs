@@ -0,0 +1,166 @@
+//! The History window. Lists our own recent operations along with thumbnails of what they drew,
+//! and lets the user jump back to any of them.
+
+use std::rc::Rc;
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_renderer::paws::{point, AlignH, Color, Layout, Padding, Renderer};
+use netcanv_renderer::{Framebuffer as FramebufferTrait, RenderBackend};
+
+use crate::backend::Framebuffer;
+use crate::ui::view::Dimensions;
+use crate::ui::wm::{HitTest, WindowContent, WindowContentArgs};
+use crate::ui::{UiElements, UiInput};
+
+use super::people::PeopleWindow;
+
+/// One of our own operations, as listed in the window.
+#[derive(Clone)]
+pub struct HistoryEntry {
+   /// The name of the tool the operation was made with.
+   pub tool: String,
+   /// The layer the operation was made on.
+   pub layer: usize,
+   /// A preview of the chunks the operation changed, as they were right after it.
+   pub thumbnail: Rc<Framebuffer>,
+   /// Whether the operation is currently undone.
+   pub undone: bool,
+}
+
+/// An action requested by the user through the window.
+pub enum HistoryRequest {
+   /// Undo or redo operations until the entry with the given index is the latest one done.
+   JumpTo(usize),
+}
+
+/// Data shared between the History window and the paint state.
+pub struct HistoryWindowData {
+   /// Our recent operations, oldest first.
+   pub entries: Vec<HistoryEntry>,
+   /// Actions requested through the window since the last time they were handled.
+   pub requests: Vec<HistoryRequest>,
+}
+
+impl HistoryWindowData {
+   pub fn new() -> Self {
+      Self {
+         entries: Vec::new(),
+         requests: Vec::new(),
+      }
+   }
+}
+
+pub struct HistoryWindow;
+
+impl HistoryWindow {
+   const WIDTH: f32 = 280.0;
+   const PADDING: f32 = 12.0;
+   const HEADER_HEIGHT: f32 = 48.0;
+   const ROW_HEIGHT: f32 = 40.0;
+   /// The size of the thumbnails, in pixels.
+   pub const THUMBNAIL_SIZE: u32 = 32;
+   /// The maximum number of operations kept in the history.
+   pub const MAX_ENTRIES: usize = 12;
+
+   /// Returns the dimensions of the window, given its data.
+   pub fn dimensions(data: &HistoryWindowData) -> Dimensions {
+      Dimensions::from((
+         Self::WIDTH,
+         Self::HEADER_HEIGHT + data.entries.len().max(1) as f32 * Self::ROW_HEIGHT + Self::PADDING,
+      ))
+   }
+}
+
+impl WindowContent for HistoryWindow {
+   type Data = HistoryWindowData;
+
+   fn process(
+      &mut self,
+      WindowContentArgs {
+         ui,
+         input,
+         assets,
+         hit_test,
+         ..
+      }: &mut WindowContentArgs,
+      data: &mut Self::Data,
+   ) {
+      ui.push(ui.size(), Layout::Vertical);
+
+      // The title bar.
+      ui.push((ui.width(), Self::HEADER_HEIGHT), Layout::HorizontalRev);
+      ui.pad((Self::PADDING, 0.0));
+      // Leave room for the window buttons.
+      ui.space(PeopleWindow::WINDOW_BUTTONS_WIDTH);
+      ui.push((ui.remaining_width(), ui.height()), Layout::Horizontal);
+      if ui.hover(input) {
+         **hit_test = HitTest::Draggable;
+      }
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.history,
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+      ui.pop();
+
+      ui.push(ui.remaining_size(), Layout::Vertical);
+      ui.pad(Padding {
+         top: 0.0,
+         ..Padding::even(Self::PADDING)
+      });
+
+      if data.entries.is_empty() {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.nothing_to_undo,
+            assets.colors.text.with_alpha(128),
+            None,
+         );
+         ui.pop();
+      }
+
+      // The most recent operation is listed first, just like the topmost layer is in the Layers
+      // window.
+      for (index, entry) in data.entries.iter().enumerate().rev() {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         if ui.hover(input) {
+            ui.fill(assets.colors.text.with_alpha(16));
+         }
+         if ui.clicked(input, MouseButton::Left) {
+            data.requests.push(HistoryRequest::JumpTo(index));
+         }
+         // Undone operations are dimmed, so that it's clear where the history currently stands.
+         let alpha = if entry.undone { 96 } else { 255 };
+
+         let size = Self::THUMBNAIL_SIZE as f32;
+         ui.push((size + 8.0, ui.height()), Layout::Freeform);
+         ui.draw(|ui| {
+            let position = point(4.0, (ui.height() - size) / 2.0);
+            let rect = entry.thumbnail.rect(position);
+            let renderer = ui.render();
+            renderer.fill(rect, Color::WHITE, 0.0);
+            renderer.framebuffer(rect, &entry.thumbnail);
+            if entry.undone {
+               renderer.fill(rect, Color::WHITE.with_alpha(160), 0.0);
+            }
+            renderer.outline(rect, assets.colors.text.with_alpha(alpha), 0.0, 1.0);
+         });
+         ui.pop();
+         ui.space(8.0);
+
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.tool.get(&entry.tool),
+            assets.colors.text.with_alpha(alpha),
+            Some((ui.remaining_width(), AlignH::Left)),
+         );
+         ui.pop();
+      }
+
+      ui.pop();
+      ui.pop();
+   }
+}
@@ -0,0 +1,194 @@
+//! The Keyboard shortcuts window. Lists the key bindings one section of the key map at a time,
+//! and lets the user rebind them by clicking on one and pressing the new key combination.
+
+use crate::backend::winit::event::{MouseButton, VirtualKeyCode};
+use netcanv_renderer::paws::{AlignH, Layout, Padding};
+
+use crate::config::{self, config};
+use crate::keymap::{self, Keymap};
+use crate::ui::view::Dimensions;
+use crate::ui::wm::{HitTest, WindowContent, WindowContentArgs};
+use crate::ui::{Modifier, UiElements, UiInput};
+
+use super::people::PeopleWindow;
+
+/// Data shared between the Keyboard shortcuts window and the paint state.
+pub struct KeyboardShortcutsWindowData {
+   /// The index of the section of [`Keymap::SECTIONS`] that's shown.
+   pub section: usize,
+   /// The name of the binding waiting for a new key combination, if any.
+   capturing: Option<&'static str>,
+}
+
+impl KeyboardShortcutsWindowData {
+   pub fn new() -> Self {
+      Self {
+         section: 0,
+         capturing: None,
+      }
+   }
+
+   /// Returns the name of the section that's shown.
+   fn section(&self) -> &'static str {
+      Keymap::SECTIONS[self.section]
+   }
+
+   /// Shows the section `offset` sections away from the current one, wrapping around at the ends.
+   fn switch_section(&mut self, offset: isize) {
+      let count = Keymap::SECTIONS.len() as isize;
+      self.section = (self.section as isize + offset).rem_euclid(count) as usize;
+      self.capturing = None;
+   }
+}
+
+pub struct KeyboardShortcutsWindow;
+
+impl KeyboardShortcutsWindow {
+   const WIDTH: f32 = 360.0;
+   const PADDING: f32 = 12.0;
+   const HEADER_HEIGHT: f32 = 48.0;
+   const ROW_HEIGHT: f32 = 28.0;
+
+   /// Returns the dimensions of the window, given its data.
+   pub fn dimensions(data: &KeyboardShortcutsWindowData) -> Dimensions {
+      let binding_count = config().keymap.clone().section_mut(data.section()).len();
+      Dimensions::from((
+         Self::WIDTH,
+         Self::HEADER_HEIGHT + (binding_count + 1) as f32 * Self::ROW_HEIGHT + Self::PADDING,
+      ))
+   }
+
+   /// Returns whether the key is a modifier key. These are never bound on their own, and are
+   /// instead picked up along with the next key that's pressed.
+   fn is_modifier(key: VirtualKeyCode) -> bool {
+      matches!(
+         key,
+         VirtualKeyCode::LShift
+            | VirtualKeyCode::RShift
+            | VirtualKeyCode::LControl
+            | VirtualKeyCode::RControl
+            | VirtualKeyCode::LAlt
+            | VirtualKeyCode::RAlt
+            | VirtualKeyCode::LWin
+            | VirtualKeyCode::RWin
+      )
+   }
+}
+
+impl WindowContent for KeyboardShortcutsWindow {
+   type Data = KeyboardShortcutsWindowData;
+
+   fn process(
+      &mut self,
+      WindowContentArgs {
+         ui,
+         input,
+         assets,
+         hit_test,
+         ..
+      }: &mut WindowContentArgs,
+      data: &mut Self::Data,
+   ) {
+      ui.push(ui.size(), Layout::Vertical);
+
+      // The title bar.
+      ui.push((ui.width(), Self::HEADER_HEIGHT), Layout::HorizontalRev);
+      ui.pad((Self::PADDING, 0.0));
+      // Leave room for the window buttons.
+      ui.space(PeopleWindow::WINDOW_BUTTONS_WIDTH);
+      if PeopleWindow::small_button(ui, input, assets, &assets.tr.keyboard_shortcuts_reset) {
+         config::write(|config| config.keymap = Keymap::default());
+         data.capturing = None;
+      }
+      ui.space(8.0);
+      ui.push((ui.remaining_width(), ui.height()), Layout::Horizontal);
+      if ui.hover(input) {
+         **hit_test = HitTest::Draggable;
+      }
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.keyboard_shortcuts,
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+      ui.pop();
+
+      ui.push(ui.remaining_size(), Layout::Vertical);
+      ui.pad(Padding {
+         top: 0.0,
+         ..Padding::even(Self::PADDING)
+      });
+
+      let section = data.section();
+      ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+      if PeopleWindow::small_button(ui, input, assets, "<") {
+         data.switch_section(-1);
+      }
+      ui.space(4.0);
+      if PeopleWindow::small_button(ui, input, assets, ">") {
+         data.switch_section(1);
+      }
+      ui.space(8.0);
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.keymap_section.get(section),
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+
+      let mut keymap = config().keymap.clone();
+      for (name, binding) in keymap.section_mut(section) {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         let capturing = data.capturing == Some(name);
+         if capturing {
+            ui.fill(assets.colors.text.with_alpha(32));
+         } else if ui.hover(input) {
+            ui.fill(assets.colors.text.with_alpha(16));
+         }
+         if ui.clicked(input, MouseButton::Left) {
+            data.capturing = Some(name);
+         }
+         ui.pad((8.0, 0.0));
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.shortcut.get(&format!("{}-{}", section, name)),
+            assets.colors.text,
+            Some((ui.width() * 0.6, AlignH::Left)),
+         );
+         let (description, alpha) = if capturing {
+            (assets.tr.keyboard_shortcuts_press_a_key.clone(), 128)
+         } else {
+            (keymap::describe(*binding), 255)
+         };
+         ui.horizontal_label(
+            &assets.sans,
+            &description,
+            assets.colors.text.with_alpha(alpha),
+            Some((ui.remaining_width(), AlignH::Right)),
+         );
+         ui.pop();
+      }
+
+      if let Some(name) = data.capturing {
+         match input.last_key_typed() {
+            Some(VirtualKeyCode::Escape) => data.capturing = None,
+            Some(key) if !Self::is_modifier(key) => {
+               let modifier = Modifier::from_input(input);
+               config::write(|config| {
+                  let bindings = config.keymap.section_mut(section);
+                  if let Some((_, binding)) = bindings.into_iter().find(|(n, _)| *n == name) {
+                     *binding = (modifier, key);
+                  }
+               });
+               data.capturing = None;
+            }
+            _ => (),
+         }
+      }
+
+      ui.pop();
+      ui.pop();
+   }
+}
@@ -0,0 +1,181 @@
+//! The Layers window. Lists the canvas's layers, and lets the host add them and change how they
+//! are shown.
+
+use netcanv_protocol::client::{Layer, MAX_LAYERS};
+use netcanv_renderer::paws::{AlignH, Layout, Padding};
+
+use crate::assets::Assets;
+use crate::ui::view::Dimensions;
+use crate::ui::wm::{HitTest, WindowContent, WindowContentArgs};
+use crate::ui::{UiElements, UiInput};
+
+use super::people::PeopleWindow;
+
+/// An action requested by the user through the window.
+pub enum LayersRequest {
+   /// Draw on the layer with the given index from now on.
+   Select(usize),
+   /// Add a new layer on top of all the others.
+   Add,
+   /// Show or hide the layer with the given index.
+   SetVisible(usize, bool),
+   /// Change the opacity of the layer with the given index.
+   SetOpacity(usize, u8),
+}
+
+/// Data shared between the Layers window and the paint state.
+pub struct LayersWindowData {
+   /// Whether we are the host. Only the host can add layers and change how they're shown.
+   pub is_host: bool,
+   /// The canvas's layers, bottom first.
+   pub layers: Vec<Layer>,
+   /// The layer we draw on.
+   pub current_layer: usize,
+   /// Actions requested through the window since the last time they were handled.
+   pub requests: Vec<LayersRequest>,
+}
+
+impl LayersWindowData {
+   pub fn new() -> Self {
+      Self {
+         is_host: false,
+         layers: Vec::new(),
+         current_layer: 0,
+         requests: Vec::new(),
+      }
+   }
+}
+
+pub struct LayersWindow;
+
+impl LayersWindow {
+   const WIDTH: f32 = 360.0;
+   const PADDING: f32 = 12.0;
+   const HEADER_HEIGHT: f32 = 48.0;
+   const ROW_HEIGHT: f32 = 32.0;
+   /// The opacities the host can pick from, in percent.
+   const OPACITIES: [u8; 4] = [25, 50, 75, 100];
+
+   /// Returns the name shown for the layer with the given index. Layers without a name are
+   /// numbered.
+   pub fn layer_name(assets: &Assets, layers: &[Layer], index: usize) -> String {
+      match layers.get(index) {
+         Some(layer) if !layer.name.is_empty() => layer.name.clone(),
+         _ => assets.tr.layer_name.format().with("number", index + 1).done(),
+      }
+   }
+
+   /// Returns the opacity that comes after the given one, wrapping around to the lowest.
+   pub fn next_opacity(opacity: u8) -> u8 {
+      let percent = (opacity as u32 * 100 / 255) as u8;
+      let next = Self::OPACITIES
+         .iter()
+         .copied()
+         .find(|&preset| preset > percent)
+         .unwrap_or(Self::OPACITIES[0]);
+      (next as u32 * 255 / 100) as u8
+   }
+
+   /// Returns the dimensions of the window, given its data.
+   pub fn dimensions(data: &LayersWindowData) -> Dimensions {
+      Dimensions::from((
+         Self::WIDTH,
+         Self::HEADER_HEIGHT + data.layers.len().max(1) as f32 * Self::ROW_HEIGHT + Self::PADDING,
+      ))
+   }
+}
+
+impl WindowContent for LayersWindow {
+   type Data = LayersWindowData;
+
+   fn process(
+      &mut self,
+      WindowContentArgs {
+         ui,
+         input,
+         assets,
+         hit_test,
+         ..
+      }: &mut WindowContentArgs,
+      data: &mut Self::Data,
+   ) {
+      ui.push(ui.size(), Layout::Vertical);
+
+      // The title bar.
+      ui.push((ui.width(), Self::HEADER_HEIGHT), Layout::HorizontalRev);
+      ui.pad((Self::PADDING, 0.0));
+      // Leave room for the window buttons.
+      ui.space(PeopleWindow::WINDOW_BUTTONS_WIDTH);
+      if data.is_host && data.layers.len() < MAX_LAYERS {
+         if PeopleWindow::small_button(ui, input, assets, &assets.tr.add_layer) {
+            data.requests.push(LayersRequest::Add);
+         }
+         ui.space(8.0);
+      }
+      ui.push((ui.remaining_width(), ui.height()), Layout::Horizontal);
+      if ui.hover(input) {
+         **hit_test = HitTest::Draggable;
+      }
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.layers,
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+      ui.pop();
+
+      ui.push(ui.remaining_size(), Layout::Vertical);
+      ui.pad(Padding {
+         top: 0.0,
+         ..Padding::even(Self::PADDING)
+      });
+
+      // The topmost layer is listed first, as that's how it's stacked on the canvas.
+      for (index, layer) in data.layers.iter().enumerate().rev() {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::HorizontalRev);
+         if data.is_host {
+            let opacity = layer.opacity as u32 * 100 / 255;
+            let label = assets.tr.layer_opacity.format().with("opacity", opacity).done();
+            if PeopleWindow::small_button(ui, input, assets, &label) {
+               let opacity = Self::next_opacity(layer.opacity);
+               data.requests.push(LayersRequest::SetOpacity(index, opacity));
+            }
+            ui.space(8.0);
+            let visibility = if layer.visible {
+               &assets.tr.hide_layer
+            } else {
+               &assets.tr.show_layer
+            };
+            if PeopleWindow::small_button(ui, input, assets, visibility) {
+               data.requests.push(LayersRequest::SetVisible(index, !layer.visible));
+            }
+            ui.space(8.0);
+         }
+         if index != data.current_layer {
+            if PeopleWindow::small_button(ui, input, assets, &assets.tr.draw_on_layer) {
+               data.requests.push(LayersRequest::Select(index));
+            }
+            ui.space(8.0);
+         }
+         let name = Self::layer_name(assets, &data.layers, index);
+         let (font, color) = if index == data.current_layer {
+            (&assets.sans_bold, assets.colors.text)
+         } else if layer.visible {
+            (&assets.sans, assets.colors.text)
+         } else {
+            (&assets.sans, assets.colors.text.with_alpha(128))
+         };
+         ui.horizontal_label(
+            font,
+            &name,
+            color,
+            Some((ui.remaining_width(), AlignH::Left)),
+         );
+         ui.pop();
+      }
+
+      ui.pop();
+      ui.pop();
+   }
+}
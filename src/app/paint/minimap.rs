@@ -0,0 +1,137 @@
+//! The minimap. Shows a zoomed out overview of where on the canvas there's anything drawn, along
+//! with the part of the canvas that's currently visible and where everyone's cursors are, and lets
+//! the user jump around the canvas by clicking on it.
+
+use std::collections::HashSet;
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, vector, Color, Layout, Point, Rect, Renderer};
+use netcanv_renderer::RenderBackend;
+
+use crate::assets::Assets;
+use crate::color::{Hsv, Srgb};
+use crate::paint_canvas::chunk::Chunk;
+use crate::ui::view::View;
+use crate::ui::{ButtonState, Input, Ui, UiInput};
+
+/// What's shown on the minimap.
+pub struct MinimapArgs<'a> {
+   /// The positions of all chunks known to have something drawn on them, whether they were
+   /// downloaded already or not.
+   pub chunks: &'a HashSet<(i32, i32)>,
+   /// The part of the canvas visible in the viewport.
+   pub visible_rect: Rect,
+   /// The positions of peers' cursors on the canvas.
+   pub cursors: &'a [(PeerId, Point)],
+}
+
+pub struct Minimap {
+   pub view: View,
+   /// Whether the mouse button was pressed over the minimap and is still held down, in which case
+   /// the viewport follows the mouse around.
+   dragging: bool,
+}
+
+impl Minimap {
+   const SIZE: f32 = 192.0;
+   const PADDING: f32 = 8.0;
+   const CURSOR_RADIUS: f32 = 3.0;
+
+   pub fn new() -> Self {
+      Self {
+         view: View::new((Self::SIZE, Self::SIZE)),
+         dragging: false,
+      }
+   }
+
+   /// Returns the color a peer's cursor is shown with. Each peer gets a different hue, such that
+   /// peers that joined one after another can still be told apart.
+   pub fn peer_color(peer_id: PeerId) -> Color {
+      // Stepping by the golden ratio spreads the hues out evenly, no matter how many peers there
+      // are.
+      let hue = (peer_id.0 as f32 * 0.618_034).fract() * 6.0;
+      Srgb::from(Hsv {
+         h: hue,
+         s: 0.8,
+         v: 0.9,
+      })
+      .to_color(1.0)
+   }
+
+   /// Returns the area of the canvas shown on the minimap. This covers all chunks, the visible
+   /// part of the canvas, and all cursors, and is made square so that the minimap isn't skewed.
+   fn shown_area(args: &MinimapArgs) -> Rect {
+      let (mut left, mut top) = (args.visible_rect.left(), args.visible_rect.top());
+      let (mut right, mut bottom) = (args.visible_rect.right(), args.visible_rect.bottom());
+      let chunk_size = vector(Chunk::SIZE.0 as f32, Chunk::SIZE.1 as f32);
+      let chunk_corners = args.chunks.iter().flat_map(|&position| {
+         let top_left = Chunk::screen_position(position);
+         [top_left, top_left + chunk_size]
+      });
+      let cursors = args.cursors.iter().map(|&(_, position)| position);
+      for corner in chunk_corners.chain(cursors) {
+         left = left.min(corner.x);
+         top = top.min(corner.y);
+         right = right.max(corner.x);
+         bottom = bottom.max(corner.y);
+      }
+      let side = (right - left).max(bottom - top);
+      let center = point((left + right) / 2.0, (top + bottom) / 2.0);
+      Rect::new(center - vector(side, side) / 2.0, vector(side, side))
+   }
+
+   /// Processes the minimap. Returns the point on the canvas the viewport should be centered on,
+   /// if the minimap was clicked.
+   pub fn process(
+      &mut self,
+      ui: &mut Ui,
+      input: &mut Input,
+      assets: &Assets,
+      args: MinimapArgs,
+   ) -> Option<Point> {
+      self.view.begin(ui, input, Layout::Freeform);
+      ui.fill_rounded(Color::BLACK.with_alpha(192), 8.0);
+      ui.pad(Self::PADDING);
+
+      let area = Self::shown_area(&args);
+      let scale = ui.width() / area.width().max(1.0);
+      let to_minimap = |position: Point| (position - area.position) * scale;
+
+      ui.draw(|ui| {
+         let renderer = ui.render();
+         let chunk_size = vector(Chunk::SIZE.0 as f32, Chunk::SIZE.1 as f32) * scale;
+         for &position in args.chunks {
+            let top_left = to_minimap(Chunk::screen_position(position));
+            // Chunks are never drawn smaller than a pixel, so that they don't disappear on very
+            // large canvases.
+            let size = vector(chunk_size.x.max(1.0), chunk_size.y.max(1.0));
+            renderer.fill(Rect::new(top_left, size), Color::WHITE.with_alpha(96), 0.0);
+         }
+
+         let visible = Rect::new(
+            to_minimap(args.visible_rect.position),
+            args.visible_rect.size * scale,
+         );
+         renderer.outline(visible, assets.colors.focus_ring, 0.0, 1.0);
+
+         for &(peer_id, position) in args.cursors {
+            renderer.fill_circle(
+               to_minimap(position),
+               Self::CURSOR_RADIUS,
+               Self::peer_color(peer_id),
+            );
+         }
+      });
+
+      match input.action(MouseButton::Left) {
+         (true, ButtonState::Pressed) if ui.hover(input) => self.dragging = true,
+         (_, ButtonState::Released) => self.dragging = false,
+         _ => (),
+      }
+      let jump_to = self.dragging.then(|| area.position + ui.mouse_position(input) * scale.recip());
+
+      self.view.end(ui);
+      jump_to
+   }
+}
@@ -1,23 +1,47 @@
 //! The paint state. This is the screen where you paint on the canvas with other people.
 
 mod actions;
+mod audit_log;
+mod background;
+mod debug_console;
+mod grid;
+mod history;
+mod keyboard_shortcuts;
+mod layers;
+mod minimap;
+mod network_stats;
+mod notes;
+mod people;
+mod perf_hud;
+mod pings;
+mod qr_code;
+mod reference;
+mod room_info;
 pub mod tool_bar;
 mod tools;
 
 use image::RgbaImage;
-use std::collections::HashMap;
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::{Path, PathBuf};
 use std::process::exit;
+use std::rc::Rc;
 use std::sync::Arc;
 use web_time::{Duration, Instant};
 
 use netcanv_i18n::translate_enum::TranslateEnum;
-use netcanv_protocol::relay::PeerId;
+use netcanv_protocol::client::{
+   AdminCommand, AdminResponse, AdminStatus, Background, Bookmark, ChunkEncoding, Layer,
+   BOOKMARK_ZOOM_LEVELS, CHUNK_DIGEST_LEN, MAX_BOOKMARKS, MAX_DESCRIPTION_LEN, MAX_LAYERS,
+   MAX_PAGES,
+};
+use netcanv_protocol::decode::Validate;
+use netcanv_protocol::relay::{InviteToken, PeerId};
 use netcanv_renderer::paws::{
-   point, vector, AlignH, AlignV, Color, Layout, Rect, Renderer, Vector,
+   point, vector, AlignH, AlignV, Color, Layout, Padding, Point, Rect, Renderer, Vector,
 };
 use netcanv_renderer::{BlendMode, Font, RenderBackend};
 use nysa::global as bus;
+use rfd::{AsyncFileDialog, MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
 use tokio::sync::mpsc;
 
 use crate::app::paint::actions::ActionArgs;
@@ -25,42 +49,75 @@ use crate::app::paint::tool_bar::ToolbarArgs;
 use crate::app::paint::tools::KeyShortcutAction;
 use crate::app::*;
 use crate::assets::*;
+use crate::autosave;
+use crate::backend::winit::window::CursorIcon;
 use crate::backend::Backend;
 use crate::clipboard;
+use crate::color::{Hsv, Srgb};
 use crate::common;
 use crate::common::*;
+use crate::config::{self, config};
+use crate::console_log;
+use crate::file_dialog;
 use crate::image_coder::ImageCoder;
 use crate::net::peer::{self, Peer};
+use crate::net::room_link::{LinkTarget, RoomLink};
 use crate::net::socket::SocketSystem;
 use crate::net::timer::Timer;
 use crate::paint_canvas::cache_layer::{CacheLayer, CachedChunk};
 use crate::paint_canvas::chunk::Chunk;
+use crate::paint_canvas::encode_pool::{EncodeJob, EncodePool};
 use crate::paint_canvas::*;
-use crate::project_file::ProjectFile;
+use crate::perf::{self, Metric};
+use crate::project_file::{
+   LoadedPage, ProjectFile, ProjectMetadata, ProjectPage, RoomMetadata, SavedViewport,
+};
+use crate::telemetry::{self, Session};
 use crate::ui::view::layout::DirectionV;
 use crate::ui::view::{Dimension, View};
-use crate::ui::wm::WindowManager;
+use crate::ui::wm::windows::WindowButtonStyle;
+use crate::ui::wm::{WindowContentWrappers, WindowId, WindowManager};
 use crate::ui::*;
 use crate::viewport::Viewport;
+use crate::voice_chat::VoiceChat;
 
-use self::actions::SaveToFileAction;
+use self::actions::{
+   ImportDocument, ImportDocumentAction, KeyboardShortcutsAction, LeaveRoom, LeaveRoomAction,
+   OpenKeyboardShortcuts, ReferenceImageAction, SaveToFileAction,
+};
+use self::audit_log::{
+   AuditEvent, AuditEventKind, AuditLog, AuditLogWindow, AuditLogWindowData, ExportAuditLog,
+};
+use self::debug_console::{ConsoleCommand, DebugConsoleWindow, DebugConsoleWindowData};
+use self::history::{HistoryEntry, HistoryRequest, HistoryWindow, HistoryWindowData};
+use self::keyboard_shortcuts::{KeyboardShortcutsWindow, KeyboardShortcutsWindowData};
+use self::layers::{LayersRequest, LayersWindow, LayersWindowData};
+use self::minimap::{Minimap, MinimapArgs};
+use self::network_stats::NetworkStatsOverlay;
+use self::people::{PeopleRequest, PeopleWindow, PeopleWindowData, Person};
+use self::perf_hud::PerfHud;
+use self::pings::Pings;
+use self::qr_code::QrCodeOverlay;
+use self::reference::{LoadReferenceImage, ReferenceImage};
+use self::room_info::{RoomInfoWindow, RoomInfoWindowData};
 use self::tool_bar::{ToolId, Toolbar};
-use self::tools::{BrushTool, EyedropperTool, Net, SelectionTool, ToolArgs};
+use self::tools::{
+   AirbrushTool, BrushTool, EyedropperTool, ImageTool, LaserTool, LockTool, Net, NoteTool,
+   SelectionTool, ShapesTool, StampTool, TextTool, ToolArgs,
+};
 
 /// A log message in the lower left corner.
 ///
 /// These are used for displaying errors and joined/left messages.
-type Log = Vec<(String, Instant)>;
-
-/// A small tip in the upper left corner.
-///
-/// These are used for displaying the panning and zoom level.
-struct Tip {
-   text: String,
+struct LogEntry {
+   message: String,
    created: Instant,
-   visible_duration: Duration,
+   /// The point on the canvas the viewport jumps to when the message is clicked.
+   jump_to: Option<Point>,
 }
 
+type Log = Vec<LogEntry>;
+
 /// The state of a chunk download.
 #[derive(Clone, Copy, Debug, PartialEq, Eq)]
 enum ChunkDownload {
@@ -71,7 +128,79 @@ enum ChunkDownload {
 }
 
 /// A bus message requesting a chunk download.
-struct RequestChunkDownload((i32, i32));
+struct RequestChunkDownload(LayerChunk);
+
+/// Groups chunks by the layer they're on, as chunk packets only ever carry a single layer's
+/// chunks.
+fn chunks_by_layer(
+   chunks: impl IntoIterator<Item = LayerChunk>,
+) -> BTreeMap<usize, Vec<(i32, i32)>> {
+   let mut by_layer: BTreeMap<usize, Vec<(i32, i32)>> = BTreeMap::new();
+   for (layer, chunk_position) in chunks {
+      by_layer.entry(layer).or_default().push(chunk_position);
+   }
+   by_layer
+}
+
+/// A page of the room. Each page has its own chunks, but everything else about the room, such as
+/// notes, images, and locked regions, is shared between all pages.
+struct Page {
+   name: String,
+   /// The page's contents while it isn't the current page, or `None` if we don't have them.
+   ///
+   /// Only the host keeps the contents of pages other than the current one, as nobody else
+   /// receives the changes made to them. Everyone else downloads a page anew when switching to it.
+   contents: Option<PageContents>,
+}
+
+impl Page {
+   fn new(name: String) -> Self {
+      Self {
+         name,
+         contents: None,
+      }
+   }
+}
+
+/// The parts of the paint state that belong to a single page.
+struct PageContents {
+   canvas: CanvasPage,
+   cache_layer: CacheLayer,
+   chunk_downloads: HashMap<LayerChunk, ChunkDownload>,
+}
+
+impl PageContents {
+   fn new() -> Self {
+      Self {
+         canvas: CanvasPage::default(),
+         cache_layer: CacheLayer::new(),
+         chunk_downloads: HashMap::new(),
+      }
+   }
+}
+
+/// Returns the pages to save along with the canvas. The host has every page, but everyone else
+/// only has the one they're on, so that's the only one saved for them.
+fn project_pages(pages: &mut [Page], current_page: usize, is_host: bool) -> Vec<ProjectPage<'_>> {
+   pages
+      .iter_mut()
+      .enumerate()
+      .filter_map(|(index, page)| {
+         let canvas = if index == current_page {
+            None
+         } else if is_host {
+            // Pages the host never switched to are empty.
+            Some(&mut page.contents.get_or_insert_with(PageContents::new).canvas)
+         } else {
+            Some(&mut page.contents.as_mut()?.canvas)
+         };
+         Some(ProjectPage {
+            name: &page.name,
+            canvas,
+         })
+      })
+      .collect()
+}
 
 /// Controls shared between tools, such as the color palette.
 pub struct GlobalControls {
@@ -79,13 +208,27 @@ pub struct GlobalControls {
 }
 
 struct EncodeChannels {
-   tx: mpsc::UnboundedSender<((i32, i32), CachedChunk)>,
-   rx: mpsc::UnboundedReceiver<((i32, i32), CachedChunk)>,
+   tx: mpsc::UnboundedSender<(LayerChunk, CachedChunk)>,
+   rx: mpsc::UnboundedReceiver<(LayerChunk, CachedChunk)>,
+}
+
+impl EncodeChannels {
+   fn new() -> Self {
+      let (tx, rx) = mpsc::unbounded_channel();
+      Self { tx, rx }
+   }
 }
 
 struct DecodeChannels {
-   tx: mpsc::UnboundedSender<((i32, i32), RgbaImage)>,
-   rx: mpsc::UnboundedReceiver<((i32, i32), RgbaImage)>,
+   tx: mpsc::UnboundedSender<(LayerChunk, RgbaImage)>,
+   rx: mpsc::UnboundedReceiver<(LayerChunk, RgbaImage)>,
+}
+
+impl DecodeChannels {
+   fn new() -> Self {
+      let (tx, rx) = mpsc::unbounded_channel();
+      Self { tx, rx }
+   }
 }
 
 /// The paint app state.
@@ -94,30 +237,107 @@ pub struct State {
    socket_system: Arc<SocketSystem>,
    project_file: ProjectFile,
    save_path: Option<PathBuf>,
+   autosave_session: autosave::Session,
+   /// When the canvas was last autosaved, or when autosaving was last considered.
+   last_autosave: Instant,
+   /// The revision of the canvas when it was last autosaved.
+   autosave_revision: u64,
 
    paint_canvas: PaintCanvas,
    cache_layer: CacheLayer,
+   audit_log: AuditLog,
 
    actions: Vec<Box<dyn actions::Action>>,
 
    peer: Peer,
+   /// The most recently created invite token for the room.
+   invite: Option<InviteToken>,
+   /// Whether the room can only be joined with an invite token.
+   invite_only: bool,
    update_timer: Timer,
-   chunk_downloads: HashMap<(i32, i32), ChunkDownload>,
+   chunk_downloads: HashMap<LayerChunk, ChunkDownload>,
    encoded_chunks: HashMap<PeerId, EncodeChannels>,
    encode_channels: EncodeChannels,
+   encode_pool: EncodePool,
    decode_channels: DecodeChannels,
+   /// How chunk images are encoded before they're sent out, as set by the host.
+   chunk_encoding: ChunkEncoding,
+   pages: Vec<Page>,
+   current_page: usize,
+   /// Whether we switched pages and are waiting for the host to tell us what's on the new page.
+   /// Chunks arriving in the meantime belong to the previous page.
+   page_sync_pending: bool,
+   /// The layer we draw on.
+   current_layer: usize,
 
+   /// The title last set on the window, so that it's only changed when it needs to be.
+   window_title: String,
    fatal_error: bool,
+   /// Whether we're leaving the room, to go back to the lobby.
+   leaving: bool,
+   /// Whether the host told us about the room's name and description already. Any info sent after
+   /// that means the host changed it.
+   room_info_received: bool,
    log: Log,
-   tip: Tip,
+   /// Covers the log messages that can be clicked, so that clicking them doesn't draw on the
+   /// canvas.
+   log_links_view: View,
+   pings: Pings,
 
    panning: bool,
    viewport: Viewport,
+   /// The position of the mouse cursor on the canvas, as last seen while hovering over it.
+   cursor_position: Point,
+   /// Our viewport bookmarks, sorted by the number keys they're saved under.
+   bookmarks: Vec<Bookmark>,
+   /// The bookmarks shared by the host. These are jumped to with the number keys we didn't save
+   /// bookmarks of our own under.
+   room_bookmarks: Vec<Bookmark>,
+   /// Whether our bookmarks are shared with the room. Only the host can share them.
+   sharing_bookmarks: bool,
+   /// The peer whose viewport ours follows, if any.
+   following: Option<PeerId>,
+   /// The viewport we last told everyone about, and when we did that.
+   sent_viewport: Option<((i32, i32), i32)>,
+   last_viewport_sent: Instant,
+   /// The image overlaid on the canvas for our eyes only, if any.
+   reference_image: Option<ReferenceImage>,
+   reference_opacity_slider: Slider,
+   /// Whether the reference image is drawn beneath the canvas's chunks rather than over them.
+   reference_below_canvas: bool,
+   /// Whether the reference image is being dragged around.
+   moving_reference_image: bool,
+   /// Whether the canvas is tinted by who drew to it.
+   show_attribution: bool,
+   /// Our voice chat session, if we've joined voice chat.
+   voice_chat: Option<VoiceChat>,
 
    canvas_view: View,
+   page_bar_view: View,
    bottom_bar_view: View,
+   status_bar_view: View,
 
    overflow_menu: ContextMenu,
+   qr_code_overlay: Option<QrCodeOverlay>,
+   network_stats_overlay: Option<NetworkStatsOverlay>,
+   perf_hud: Option<PerfHud>,
+   minimap: Option<Minimap>,
+   people_window: Option<WindowId<PeopleWindowData>>,
+   people_window_view: View,
+   layers_window: Option<WindowId<LayersWindowData>>,
+   layers_window_view: View,
+   /// Our own recent operations, oldest first.
+   history: Vec<HistoryEntry>,
+   history_window: Option<WindowId<HistoryWindowData>>,
+   history_window_view: View,
+   audit_log_window: Option<WindowId<AuditLogWindowData>>,
+   audit_log_window_view: View,
+   debug_console_window: Option<WindowId<DebugConsoleWindowData>>,
+   debug_console_window_view: View,
+   keyboard_shortcuts_window: Option<WindowId<KeyboardShortcutsWindowData>>,
+   keyboard_shortcuts_window_view: View,
+   room_info_window: Option<WindowId<RoomInfoWindowData>>,
+   room_info_window_view: View,
    toolbar: Toolbar,
    wm: WindowManager,
    global_controls: GlobalControls,
@@ -125,7 +345,11 @@ pub struct State {
 
 macro_rules! log {
    ($log:expr, $($arg:tt)*) => {
-      $log.push((format!($($arg)*), Instant::now()))
+      $log.push(LogEntry {
+         message: format!($($arg)*),
+         created: Instant::now(),
+         jump_to: None,
+      })
    };
 }
 
@@ -147,12 +371,27 @@ impl State {
    /// The network communication tick interval.
    pub const TIME_PER_UPDATE: Duration = Duration::from_millis(50);
 
+   /// How often our viewport is sent out at most, for others to follow it.
+   const VIEWPORT_SEND_INTERVAL: Duration = Duration::from_millis(250);
+
    /// The height of the bottom bar.
    const BOTTOM_BAR_SIZE: f32 = 32.0;
 
+   /// The height of the status bar below the bottom bar.
+   const STATUS_BAR_SIZE: f32 = 24.0;
+
+   /// The height of the page tabs above the bottom bar.
+   const PAGE_BAR_SIZE: f32 = 28.0;
+
    /// The amount of padding applied around the canvas area, when laying out elements on top of it.
    const CANVAS_INNER_PADDING: f32 = 8.0;
 
+   /// The height of a single message in the log.
+   const LOG_LINE_HEIGHT: f32 = 16.0;
+
+   /// The height of the room's name, or the "Room ID" caption, shown above the room ID.
+   const ROOM_CAPTION_HEIGHT: f32 = 24.0;
+
    /// Creates a new paint state.
    pub fn new(
       assets: Box<Assets>,
@@ -161,50 +400,89 @@ impl State {
       image_path: Option<PathBuf>,
       renderer: &mut Backend,
    ) -> Result<Self, (netcanv::Error, Box<Assets>)> {
-      let (encoded_tx, encoded_rx) = mpsc::unbounded_channel();
-      let (decoded_tx, decoded_rx) = mpsc::unbounded_channel();
-
       let mut wm = WindowManager::new();
       let mut this = Self {
          assets,
          socket_system,
 
          save_path: image_path.clone(),
+         autosave_session: autosave::Session::new(),
+         last_autosave: Instant::now(),
+         autosave_revision: 0,
 
          paint_canvas: PaintCanvas::new(),
          cache_layer: CacheLayer::new(),
+         audit_log: AuditLog::new(),
          project_file: ProjectFile::new(),
 
          actions: Vec::new(),
 
          peer,
+         invite: None,
+         invite_only: false,
          update_timer: Timer::new(Self::TIME_PER_UPDATE),
          chunk_downloads: HashMap::new(),
          encoded_chunks: HashMap::new(),
-         encode_channels: EncodeChannels {
-            tx: encoded_tx,
-            rx: encoded_rx,
-         },
-         decode_channels: DecodeChannels {
-            tx: decoded_tx,
-            rx: decoded_rx,
-         },
+         encode_channels: EncodeChannels::new(),
+         encode_pool: EncodePool::new(),
+         decode_channels: DecodeChannels::new(),
+         chunk_encoding: ChunkEncoding::default(),
+         pages: Vec::new(),
+         current_page: 0,
+         page_sync_pending: false,
+         current_layer: 0,
 
+         window_title: String::new(),
          fatal_error: false,
+         leaving: false,
+         room_info_received: false,
          log: Log::new(),
-         tip: Tip {
-            text: "".into(),
-            created: Instant::now(),
-            visible_duration: Default::default(),
-         },
+         log_links_view: View::new((0.0, 0.0)),
+         pings: Pings::new(),
 
          panning: false,
          viewport: Viewport::new(),
+         cursor_position: point(0.0, 0.0),
+         bookmarks: Vec::new(),
+         room_bookmarks: Vec::new(),
+         sharing_bookmarks: false,
+         following: None,
+         sent_viewport: None,
+         last_viewport_sent: Instant::now(),
+         reference_image: None,
+         reference_opacity_slider: Slider::new(50.0, 5.0, 100.0, SliderStep::Discrete(1.0)),
+         reference_below_canvas: false,
+         moving_reference_image: false,
+         show_attribution: false,
+         voice_chat: None,
 
          canvas_view: View::new((Dimension::Percentage(1.0), Dimension::Rest(1.0))),
+         page_bar_view: View::new((Dimension::Percentage(1.0), Self::PAGE_BAR_SIZE)),
          bottom_bar_view: View::new((Dimension::Percentage(1.0), Self::BOTTOM_BAR_SIZE)),
+         status_bar_view: View::new((Dimension::Percentage(1.0), Self::STATUS_BAR_SIZE)),
 
          overflow_menu: ContextMenu::new((256.0, 0.0)), // Vertical is filled in later
+         qr_code_overlay: None,
+         network_stats_overlay: None,
+         perf_hud: None,
+         minimap: config().ui.show_minimap.then(Minimap::new),
+         people_window: None,
+         people_window_view: View::new(PeopleWindow::dimensions(&PeopleWindowData::new())),
+         layers_window: None,
+         layers_window_view: View::new(LayersWindow::dimensions(&LayersWindowData::new())),
+         history: Vec::new(),
+         history_window: None,
+         history_window_view: View::new(HistoryWindow::dimensions(&HistoryWindowData::new())),
+         audit_log_window: None,
+         audit_log_window_view: View::new(AuditLogWindow::dimensions()),
+         debug_console_window: None,
+         debug_console_window_view: View::new(DebugConsoleWindow::dimensions()),
+         keyboard_shortcuts_window: None,
+         keyboard_shortcuts_window_view: View::new(KeyboardShortcutsWindow::dimensions(
+            &KeyboardShortcutsWindowData::new(),
+         )),
+         room_info_window: None,
+         room_info_window_view: View::new(RoomInfoWindow::dimensions()),
          toolbar: Toolbar::new(&mut wm),
          wm,
 
@@ -214,20 +492,45 @@ impl State {
       };
       this.register_tools(renderer);
       this.register_actions(renderer);
+      let first_page = this.page_name(0);
+      this.pages.push(Page::new(first_page));
 
       if let Some(path) = image_path {
          if !this.peer.is_host() {
          } else {
-            if let Err(error) = this.project_file.load(renderer, &path, &mut this.paint_canvas) {
-               return Err((error, this.assets));
+            match this.project_file.load(renderer, &path, &mut this.paint_canvas) {
+               Ok(pages) => this.set_loaded_pages(pages),
+               Err(error) => return Err((error, this.assets)),
             }
+            this.apply_project_metadata();
          }
       }
+      // The loaded canvas is already saved, so there's nothing new to autosave until it changes.
+      this.autosave_revision = this.canvas_revision();
 
-      if this.peer.is_host() {
+      this.paint_canvas.set_track_provenance(this.peer.is_host());
+      this.paint_canvas.set_track_operations(this.peer.is_host());
+      telemetry::session_started(if this.peer.is_offline() {
+         Session::Offline
+      } else if this.peer.is_host() {
+         Session::Host
+      } else {
+         Session::Join
+      });
+      if this.peer.is_offline() {
+         for line in this.assets.tr.paint_welcome_offline.split('\n') {
+            log!(this.log, "{}", line);
+         }
+      } else if this.peer.is_host() {
          for line in this.assets.tr.paint_welcome_host.split('\n') {
             log!(this.log, "{}", line);
          }
+         // Unlike rooms on a relay, a room on the local network doesn't outlive its host.
+         if this.peer.is_hosting_on_lan() {
+            for line in this.assets.tr.paint_welcome_lan_host.split('\n') {
+               log!(this.log, "{}", line);
+            }
+         }
          this.overflow_menu.open();
       }
 
@@ -239,24 +542,52 @@ impl State {
       let _selection = self.toolbar.add_tool(SelectionTool::new(renderer));
       let brush = self.toolbar.add_tool(BrushTool::new(renderer));
       let _eyedropper = self.toolbar.add_tool(EyedropperTool::new(renderer));
+      let _lock = self.toolbar.add_tool(LockTool::new(renderer));
+      let _laser = self.toolbar.add_tool(LaserTool::new(renderer));
+      let _note = self.toolbar.add_tool(NoteTool::new(renderer));
+      let _image = self.toolbar.add_tool(ImageTool::new(renderer));
+      let _text = self.toolbar.add_tool(TextTool::new(renderer));
+      let _shapes = self.toolbar.add_tool(ShapesTool::new(renderer));
+      let _airbrush = self.toolbar.add_tool(AirbrushTool::new(renderer));
+      let _stamp = self.toolbar.add_tool(StampTool::new(renderer));
 
       // Set the default tool to the brush.
       self.toolbar.set_current_tool(brush);
    }
 
-   /// Registers all the actions and calculates the layout height of the overflow menu.
+   /// Registers all the actions shown in the overflow menu.
    fn register_actions(&mut self, renderer: &mut Backend) {
       self.actions.push(Box::new(SaveToFileAction::new(renderer)));
+      self.actions.push(Box::new(ReferenceImageAction::new(renderer)));
+      if self.peer.is_host() {
+         self.actions.push(Box::new(ImportDocumentAction::new(renderer)));
+      }
+      self.actions.push(Box::new(KeyboardShortcutsAction::new(renderer)));
+      self.actions.push(Box::new(LeaveRoomAction::new(renderer)));
+   }
 
-      let room_id_height = 108.0;
+   /// Returns the layout height of the overflow menu. This changes along with the room's info.
+   fn overflow_menu_height(&self) -> f32 {
+      let room_id_height = if self.peer.is_offline() {
+         84.0
+      } else {
+         // The caption above the room ID is tall enough to fit the host's Edit button, and the
+         // room's description takes up another line below it.
+         let caption_height = Self::ROOM_CAPTION_HEIGHT - self.assets.sans.height();
+         let description_height = if self.peer.room_description().is_empty() {
+            0.0
+         } else {
+            self.assets.sans.height() + 4.0
+         };
+         108.0 + caption_height + description_height
+      };
       let separator_height = 8.0 * 2.0;
       let action_height = 32.0;
       let action_margin = 4.0;
       let actions_height = action_height * self.actions.len() as f32
          + action_margin * (self.actions.len() - 1) as f32
          + 4.0;
-      self.overflow_menu.view.dimensions.vertical =
-         Dimension::Constant(room_id_height + separator_height + actions_height);
+      room_id_height + separator_height + actions_height
    }
 
    fn tool_switch_events(
@@ -266,10 +597,13 @@ impl State {
       current_tool: ToolId,
    ) {
       if previous_tool != current_tool {
+         self.set_canvas_author(None);
          self.toolbar.with_tool(previous_tool, |tool| {
             tool.deactivate(renderer, &mut self.paint_canvas);
          });
-         catch!(self.peer.send_select_tool(self.toolbar.clone_tool_name(current_tool)));
+         let tool_name = self.toolbar.clone_tool_name(current_tool);
+         telemetry::feature_used(&format!("tool-{tool_name}"));
+         catch!(self.peer.send_select_tool(tool_name));
          self.toolbar.with_tool(current_tool, |tool| tool.activate());
       }
    }
@@ -281,28 +615,36 @@ impl State {
       self.tool_switch_events(renderer, previous_tool, tool);
    }
 
-   /// Requests a chunk download from the host.
-   fn queue_chunk_download(chunk_position: (i32, i32)) {
-      bus::push(RequestChunkDownload(chunk_position));
+   /// Sets up the paint canvas for edits made by the given author, where `None` stands for
+   /// ourselves. The edits go onto the layer the author has selected, are attributed to the
+   /// author, and locked regions are respected unless the author is the host.
+   fn set_canvas_author(&mut self, author: Option<PeerId>) {
+      let author_is_host = match author {
+         Some(peer_id) => self.peer.host_id() == Some(peer_id),
+         None => self.peer.is_host(),
+      };
+      let layer = match author {
+         Some(peer_id) => self.peer.mates().get(&peer_id).map_or(0, |mate| mate.layer),
+         None => self.current_layer,
+      };
+      self.paint_canvas.set_enforce_locks(!author_is_host);
+      self.paint_canvas.set_author(author);
+      self.paint_canvas.set_current_layer(layer);
    }
 
-   /// Shows a tip in the upper left corner.
-   fn show_tip(&mut self, text: &str, duration: Duration) {
-      self.tip = Tip {
-         text: text.into(),
-         created: Instant::now(),
-         visible_duration: duration,
-      };
+   /// Requests a chunk download from the host.
+   fn queue_chunk_download(chunk: LayerChunk) {
+      bus::push(RequestChunkDownload(chunk));
    }
 
    /// Decodes canvas data to the given chunk.
-   fn decode_canvas_data(&mut self, chunk_position: (i32, i32), image_data: Vec<u8>) {
+   fn decode_canvas_data(&mut self, chunk: LayerChunk, image_data: Vec<u8>) {
       let tx = self.decode_channels.tx.clone();
       tokio::task::spawn_blocking(move || {
          match ImageCoder::decode_network_data(&image_data) {
             Ok(image) => {
                // Doesn't matter if the receiving half is closed.
-               tx.send((chunk_position, image)).expect("Unbounded send failed");
+               tx.send((chunk, image)).expect("Unbounded send failed");
             }
             Err(error) => tracing::error!("image decoding failed: {:?}", error),
          }
@@ -311,31 +653,124 @@ impl State {
 
    /// Processes the message log.
    fn process_log(&mut self, ui: &mut Ui) {
-      self.log.retain(|(_, time_created)| time_created.elapsed() < Duration::from_secs(5));
+      self.log.retain(|entry| entry.created.elapsed() < Duration::from_secs(5));
       ui.draw(|ui| {
-         let mut y = ui.height() - (self.log.len() as f32 - 1.0) * 16.0 - 8.0;
+         let mut y = ui.height() - (self.log.len() as f32 - 1.0) * Self::LOG_LINE_HEIGHT - 8.0;
          let renderer = ui.render();
          renderer.push();
          renderer.set_blend_mode(BlendMode::Invert);
-         for (entry, _) in &self.log {
+         for entry in &self.log {
             renderer.text(
                Rect::new(point(8.0, y), vector(0.0, 0.0)),
                &self.assets.sans,
-               entry,
+               &entry.message,
                Color::WHITE.with_alpha(240),
                (AlignH::Left, AlignV::Bottom),
             );
-            y += 16.0;
+            y += Self::LOG_LINE_HEIGHT;
          }
          renderer.pop();
       });
    }
 
+   /// Returns the rectangle the log message with the given index occupies on the screen.
+   fn log_entry_rect(&self, index: usize) -> Rect {
+      let canvas = self.canvas_view.rect();
+      let lines_below = (self.log.len() - 1 - index) as f32;
+      let bottom = canvas.bottom() - lines_below * Self::LOG_LINE_HEIGHT - 8.0;
+      Rect::new(
+         point(canvas.left() + 8.0, bottom - Self::LOG_LINE_HEIGHT),
+         vector(
+            self.assets.sans.text_width(&self.log[index].message),
+            Self::LOG_LINE_HEIGHT,
+         ),
+      )
+   }
+
+   /// Processes clicks on log messages that lead to a location on the canvas.
+   fn process_log_links(&mut self, ui: &mut Ui, input: &mut Input) {
+      let links: Vec<_> = (0..self.log.len())
+         .filter_map(|i| Some((self.log_entry_rect(i), self.log[i].jump_to?)))
+         .collect();
+      let Some(first) = links.first().map(|&(rect, _)| rect) else {
+         return;
+      };
+      let (top_left, bottom_right) = links.iter().fold(
+         (first.top_left(), first.bottom_right()),
+         |(top_left, bottom_right), (rect, _)| {
+            (
+               point(top_left.x.min(rect.left()), top_left.y.min(rect.top())),
+               point(
+                  bottom_right.x.max(rect.right()),
+                  bottom_right.y.max(rect.bottom()),
+               ),
+            )
+         },
+      );
+      view::layout::absolute(
+         &mut self.log_links_view,
+         Rect::new(top_left, bottom_right - top_left),
+      );
+
+      self.log_links_view.begin(ui, input, Layout::Freeform);
+      let mut jump_to = None;
+      for (rect, target) in links {
+         ui.push(rect.size, Layout::Freeform);
+         ui.set_position(rect.position);
+         if ui.hover(input) {
+            input.set_cursor(CursorIcon::Hand);
+            ui.draw(|ui| {
+               let underline = Rect::new(point(0.0, ui.height() - 1.0), vector(ui.width(), 1.0));
+               let renderer = ui.render();
+               renderer.push();
+               renderer.set_blend_mode(BlendMode::Invert);
+               renderer.fill(underline, Color::WHITE.with_alpha(240), 0.0);
+               renderer.pop();
+            });
+            if input.action(MouseButton::Left) == (true, ButtonState::Pressed) {
+               jump_to = Some(target);
+            }
+         }
+         ui.pop();
+      }
+      self.log_links_view.end(ui);
+
+      if let Some(target) = jump_to {
+         self.viewport.glide_to(target, self.viewport.zoom_level());
+      }
+   }
+
+   /// Pings a location on the canvas for everyone in the room.
+   fn ping(&mut self, position: Point) {
+      self.pings.add(position, self.peer.nickname().to_owned());
+      catch!(self.peer.send_ping((position.x as i32, position.y as i32)));
+      telemetry::feature_used("ping");
+   }
+
+   /// Picks the color of the canvas under the cursor, as seen with all the visible layers blended
+   /// together. Picking a transparent pixel enables the eraser.
+   fn pick_color(&mut self, renderer: &mut Backend) {
+      let Point { x, y } = self.cursor_position;
+      let color = self.paint_canvas.get_pixel(renderer, (x.floor() as i64, y.floor() as i64));
+      let color_picker = &mut self.global_controls.color_picker;
+      if color.a == 0 {
+         color_picker.set_eraser(true);
+      } else {
+         color_picker.set_color(color);
+         color_picker.mark_color_used();
+      }
+      telemetry::feature_used("pick-color");
+   }
+
    fn process_tool_key_shortcuts(&mut self, ui: &mut Ui, input: &mut Input) {
       // If any of the WM's windows are focused, skip keyboard shortcuts.
       if self.wm.has_focus() {
          return;
       }
+      // Neither while the current tool is being typed into.
+      if self.toolbar.with_current_tool(|tool| tool.has_keyboard_focus()) {
+         return;
+      }
 
       match self.toolbar.with_current_tool(|tool| {
          tool.active_key_shortcuts(
@@ -349,6 +784,25 @@ impl State {
          KeyShortcutAction::SwitchToThisTool => (),
       }
 
+      if input.action(config().keymap.edit.undo) == (true, true) {
+         self.undo(ui);
+         return;
+      }
+      if input.action(config().keymap.edit.redo) == (true, true) {
+         self.redo(ui);
+         return;
+      }
+      if self.process_bookmark_shortcuts(input) {
+         return;
+      }
+      let action = self.actions.iter().position(|action| {
+         action.key_shortcut().map_or(false, |shortcut| input.action(shortcut) == (true, true))
+      });
+      if let Some(index) = action {
+         self.perform_action(ui, index);
+         return;
+      }
+
       let mut switch_tool = self
          .toolbar
          .with_each_tool(|tool_id, tool| {
@@ -388,8 +842,14 @@ impl State {
 
       // Panning and zooming
 
+      self.viewport.update();
+
+      // Panning or zooming manually takes the viewport back from whoever we're following.
       match input.action(MouseButton::Middle) {
-         (true, ButtonState::Pressed) if ui.hover(input) => self.panning = true,
+         (true, ButtonState::Pressed) if ui.hover(input) => {
+            self.panning = true;
+            self.stop_following();
+         }
          (_, ButtonState::Released) => self.panning = false,
          _ => (),
       }
@@ -397,20 +857,79 @@ impl State {
       if self.panning {
          let delta_pan = input.previous_mouse_position() - input.mouse_position();
          self.viewport.pan_around(delta_pan);
-         let pan = self.viewport.pan();
-         let position = format!("{}, {}", (pan.x / 256.0).floor(), (pan.y / 256.0).floor());
-         self.show_tip(&position, Duration::from_millis(100));
       }
+      // Zooming keeps the point under the cursor in place, so that it's easy to zoom into
+      // whatever the user is pointing at.
+      let mouse_position = self.viewport.to_viewport_space(ui.mouse_position(input), canvas_size);
       if let (true, Some(scroll)) = input.action(MouseScroll) {
-         self.viewport.zoom_in(scroll.y);
-         self.show_tip(
-            &format!("{:.0}%", self.viewport.zoom() * 100.0),
-            Duration::from_secs(3),
+         if !self.toolbar.with_current_tool(|tool| tool.has_scroll_focus()) {
+            self.viewport.zoom_in_around(scroll.y, mouse_position);
+            self.stop_following();
+         }
+      }
+      let magnify = input.touchpad_magnify();
+      if magnify != 0.0 && ui.hover(input) {
+         self.stop_following();
+         self.viewport.zoom_in_around(
+            Viewport::zoom_level_delta((1.0 + magnify).max(0.1)),
+            mouse_position,
          );
       }
+      let rotate = input.touchpad_rotate();
+      if rotate != 0.0 && ui.hover(input) {
+         // The gesture's delta is counterclockwise, while the viewport's rotation is clockwise.
+         self.viewport.rotate_around(-rotate.to_radians(), mouse_position);
+      }
+      if ui.hover(input) {
+         self.cursor_position =
+            self.viewport.to_viewport_space(ui.mouse_position(input), canvas_size);
+      }
+
+      // Pinging
+
+      if ui.hover(input)
+         && input.action((config().keymap.canvas.ping, MouseButton::Left))
+            == (true, ButtonState::Pressed)
+      {
+         self.ping(self.cursor_position);
+      }
+
+      // Moving the reference image
+
+      if let Some(reference_image) = &mut self.reference_image {
+         match input.action((
+            config().keymap.canvas.move_reference_image,
+            MouseButton::Left,
+         )) {
+            (true, ButtonState::Pressed)
+               if ui.hover(input) && reference_image.contains(self.cursor_position) =>
+            {
+               self.moving_reference_image = true
+            }
+            (_, ButtonState::Released) => self.moving_reference_image = false,
+            _ => (),
+         }
+         if self.moving_reference_image {
+            let previous_position =
+               self.viewport.to_viewport_space(input.previous_mouse_position(), canvas_size);
+            let position = self.viewport.to_viewport_space(input.mouse_position(), canvas_size);
+            reference_image.move_by(position - previous_position);
+         }
+      }
+
+      // Picking colors
+
+      if ui.hover(input)
+         && input.action((config().keymap.canvas.pick_color, MouseButton::Left))
+            == (true, ButtonState::Pressed)
+      {
+         self.pick_color(ui);
+      }
 
       // Drawing & key shortcuts
 
+      self.set_canvas_author(None);
+
       self.toolbar.with_each_tool::<(), _>(|_, tool| {
          tool.process_background_jobs(tool_args!(ui, input, self), &mut self.paint_canvas);
          ControlFlow::Continue
@@ -430,29 +949,85 @@ impl State {
       // Rendering
       //
 
-      while let Ok((chunk_position, image)) = self.decode_channels.rx.try_recv() {
-         self.paint_canvas.set_chunk(ui, chunk_position, image);
+      while let Ok(((layer, chunk_position), image)) = self.decode_channels.rx.try_recv() {
+         self.paint_canvas.set_chunk(ui, layer, chunk_position, image);
       }
-      while let Ok((chunk_position, image)) = self.encode_channels.rx.try_recv() {
-         let _ = self.paint_canvas.ensure_chunk(ui, chunk_position);
-         self.cache_layer.set_chunk(chunk_position, image);
+      while let Ok((chunk, image)) = self.encode_channels.rx.try_recv() {
+         // The layer may have been removed while the chunk was being encoded.
+         if chunk.0 < self.paint_canvas.layers().len() {
+            let revision = self.paint_canvas.ensure_chunk(ui, chunk.0, chunk.1).revision();
+            // The chunk may also have been drawn to in the meantime, which makes the images stale.
+            if image.revision == revision {
+               self.cache_layer.set_chunk(chunk, image);
+            }
+         }
       }
       self.cache_layer.update_timers();
+      if let Some(reference_image) = &mut self.reference_image {
+         reference_image.set_opacity(ui, self.reference_opacity_slider.value() / 100.0);
+      }
+      self.paint_canvas.update_residency(ui, &self.viewport, canvas_size);
+      // Pages that aren't open are never drawn, so their chunks are evicted a few at a time.
+      let mut evictions = PaintCanvas::MAX_EVICTIONS_PER_FRAME;
+      for contents in self.pages.iter_mut().filter_map(|page| page.contents.as_mut()) {
+         evictions -= contents.canvas.evict(ui, evictions);
+      }
+      self.paint_canvas.update_vector_overlay(ui, &self.viewport, canvas_size);
+      let hovering = ui.hover(input);
 
       ui.draw(|ui| {
+         background::draw(
+            ui,
+            self.paint_canvas.background(),
+            &self.viewport,
+            canvas_size,
+            self.paint_canvas.bounds_rect(),
+         );
          ui.render().push();
-         let Vector {
-            x: width,
-            y: height,
-         } = ui.size();
-         ui.render().translate(vector(width / 2.0, height / 2.0));
-         ui.render().scale(vector(self.viewport.zoom(), self.viewport.zoom()));
-         ui.render().translate(-self.viewport.pan());
+         self.viewport.apply_transform(ui.render(), ui.size());
+         // The reference image isn't a part of the canvas, so it's drawn either beneath all the
+         // chunks, or above them, along with the floating images.
+         let reference_image = self.reference_image.as_ref();
+         if let Some(reference_image) = reference_image.filter(|_| self.reference_below_canvas) {
+            reference_image.draw(ui.render());
+         }
          self.paint_canvas.draw_to(ui.render(), &self.viewport, canvas_size);
+         self.paint_canvas.draw_vector_overlay(ui.render(), &self.viewport, canvas_size);
+         self.paint_canvas.draw_floating_images(ui.render());
+         if let Some(reference_image) = reference_image.filter(|_| !self.reference_below_canvas) {
+            reference_image.draw(ui.render());
+         }
+         self.toolbar.with_each_tool::<(), _>(|_, tool| {
+            tool.process_paint_canvas_layers(ui.render(), input, &self.viewport);
+            ControlFlow::Continue
+         });
+         ui.render().pop();
+
+         // These overlays are laid out as rectangles in screen space, so they're rotated along
+         // with the canvas as a whole.
+         ui.render().push();
+         self.viewport.apply_screen_rotation(ui.render(), canvas_size);
+         self.draw_canvas_bounds(ui, canvas_size);
+         self.draw_locked_regions(ui, canvas_size);
+         if self.show_attribution {
+            self.draw_attribution(ui, canvas_size);
+         }
          ui.render().pop();
+         grid::draw(ui, &config().grid, &self.viewport, canvas_size);
+         notes::draw(
+            ui,
+            &self.assets.sans,
+            self.paint_canvas.notes(),
+            &self.viewport,
+            canvas_size,
+         );
+         self.pings.draw(ui, &self.assets.sans, &self.viewport, canvas_size);
 
          ui.render().push();
          for (&address, mate) in self.peer.mates() {
+            if config().block_list.is_blocked(&mate.nickname) || mate.page != self.current_page {
+               continue;
+            }
             if let Some(tool_name) = &mate.tool {
                if let Some(tool_id) = self.toolbar.tool_by_name(tool_name) {
                   self.toolbar.with_tool(tool_id, |tool| {
@@ -470,20 +1045,13 @@ impl State {
          self.toolbar.with_current_tool(|tool| {
             tool.process_paint_canvas_overlays(tool_args!(ui, input, self), &self.viewport);
          });
+
+         if self.show_attribution && hovering {
+            self.draw_attribution_tooltip(ui, canvas_size);
+         }
       });
-      if self.tip.created.elapsed() < self.tip.visible_duration {
-         ui.push(ui.size(), Layout::Freeform);
-         ui.pad((16.0, 16.0));
-         ui.push((72.0, 32.0), Layout::Freeform);
-         ui.fill(Color::BLACK.with_alpha(192));
-         ui.text(
-            &self.assets.sans,
-            &self.tip.text,
-            Color::WHITE,
-            (AlignH::Center, AlignV::Middle),
-         );
-         ui.pop();
-         ui.pop();
+      if self.paint_canvas.is_paused() {
+         self.process_paused_banner(ui);
       }
 
       self.process_log(ui);
@@ -505,45 +1073,255 @@ impl State {
                &self.global_controls
             ))
          });
+         // This must come after the tool's packets, so that the host receives everything that's
+         // part of the operation before it's committed.
+         if self.paint_canvas.take_pending_commit() {
+            catch!(self.peer.send_commit_operation());
+         }
+         self.send_chunk_digests();
+         self.send_viewport();
 
          for chunk_position in self.viewport.visible_tiles(Chunk::SIZE, canvas_size) {
-            if let Some(state) = self.chunk_downloads.get_mut(&chunk_position) {
-               if *state == ChunkDownload::NotDownloaded {
-                  Self::queue_chunk_download(chunk_position);
-                  *state = ChunkDownload::Queued;
+            for layer in 0..self.paint_canvas.layers().len() {
+               if let Some(state) = self.chunk_downloads.get_mut(&(layer, chunk_position)) {
+                  if *state == ChunkDownload::NotDownloaded {
+                     Self::queue_chunk_download((layer, chunk_position));
+                     *state = ChunkDownload::Queued;
+                  }
                }
             }
          }
 
          // Chunk sending
+         let max_bytes_per_packet = self.chunk_encoding.max_batch_size as usize;
          for (&peer_id, EncodeChannels { rx, .. }) in &mut self.encoded_chunks {
-            const KIBIBYTE: usize = 1024;
-            const MAX_BYTES_PER_PACKET: usize = 128 * KIBIBYTE;
-
+            let capabilities = self.peer.capabilities_of(peer_id);
             let mut bytes_in_packet = 0;
             let mut packet = Vec::new();
-            while let Ok((chunk_position, images)) = rx.try_recv() {
-               let image_data = match images {
-                  CachedChunk {
-                     png: _,
-                     webp: Some(webp),
-                  } => webp,
-                  CachedChunk { png, webp: None } => png,
-               };
-               if bytes_in_packet + image_data.len() > MAX_BYTES_PER_PACKET {
-                  catch!(self.peer.send_chunks(peer_id, std::mem::take(&mut packet)));
+            let mut packet_layer = 0;
+            while let Ok(((layer, chunk_position), images)) = rx.try_recv() {
+               let image_data = images.into_data(capabilities);
+               // A packet only carries chunks from a single layer.
+               if !packet.is_empty()
+                  && (layer != packet_layer
+                     || bytes_in_packet + image_data.len() > max_bytes_per_packet)
+               {
+                  catch!(self.peer.send_chunks(peer_id, packet_layer, std::mem::take(&mut packet)));
                   bytes_in_packet = 0;
                }
+               packet_layer = layer;
                bytes_in_packet += image_data.len();
                packet.push((chunk_position, image_data));
             }
             if !packet.is_empty() {
-               catch!(self.peer.send_chunks(peer_id, packet));
+               catch!(self.peer.send_chunks(peer_id, packet_layer, packet));
             }
          }
       }
    }
 
+   /// Shows a banner at the top of the canvas, letting everyone know that the canvas is paused.
+   fn process_paused_banner(&mut self, ui: &mut Ui) {
+      let text = &self.assets.tr.canvas_is_paused;
+      ui.push(ui.size(), Layout::Freeform);
+      ui.pad((16.0, 16.0));
+      ui.push(
+         (self.assets.sans_bold.text_width(text) + 32.0, 32.0),
+         Layout::Freeform,
+      );
+      ui.align((AlignH::Center, AlignV::Top));
+      ui.fill_rounded(Color::BLACK.with_alpha(192), 16.0);
+      ui.text(
+         &self.assets.sans_bold,
+         text,
+         Color::WHITE,
+         (AlignH::Center, AlignV::Middle),
+      );
+      ui.pop();
+      ui.pop();
+   }
+
+   /// Shades the area outside of the canvas's bounds, if it's bounded, and outlines the bounds.
+   ///
+   /// This must be drawn inside of [`Viewport::apply_screen_rotation`].
+   fn draw_canvas_bounds(&self, renderer: &mut Backend, canvas_size: Vector) {
+      let Some(bounds) = self.paint_canvas.bounds_rect() else {
+         return;
+      };
+      let viewport = self.viewport.unrotated();
+      let top_left = viewport.to_screen_space(bounds.top_left(), canvas_size).floor();
+      let bottom_right = viewport.to_screen_space(bounds.bottom_right(), canvas_size).floor();
+      // While the viewport is rotated, the shade has to reach past the edges of the window to
+      // cover its corners.
+      let screen = if self.viewport.rotation() == 0.0 {
+         Rect::new(point(0.0, 0.0), canvas_size)
+      } else {
+         let diagonal = canvas_size.x.hypot(canvas_size.y);
+         Rect::new(
+            (canvas_size - vector(diagonal, diagonal)) / 2.0,
+            vector(diagonal, diagonal),
+         )
+      };
+      let (left, top) = (top_left.x.max(screen.left()), top_left.y.max(screen.top()));
+      let (right, bottom) = (
+         bottom_right.x.min(screen.right()),
+         bottom_right.y.min(screen.bottom()),
+      );
+      let shade = Color::BLACK.with_alpha(48);
+      let outside = [
+         Rect::new(screen.position, vector(screen.width(), top - screen.top())),
+         Rect::new(
+            point(screen.left(), bottom),
+            vector(screen.width(), screen.bottom() - bottom),
+         ),
+         Rect::new(
+            point(screen.left(), top),
+            vector(left - screen.left(), bottom - top),
+         ),
+         Rect::new(
+            point(right, top),
+            vector(screen.right() - right, bottom - top),
+         ),
+      ];
+      for rect in outside {
+         if rect.width() > 0.0 && rect.height() > 0.0 {
+            renderer.fill(rect, shade, 0.0);
+         }
+      }
+      renderer.outline(
+         Rect::new(top_left, bottom_right - top_left),
+         Color::BLACK.with_alpha(128),
+         0.0,
+         1.0,
+      );
+   }
+
+   /// Draws an overlay over the regions of the canvas that are locked.
+   ///
+   /// This must be drawn inside of [`Viewport::apply_screen_rotation`].
+   fn draw_locked_regions(&self, renderer: &mut Backend, canvas_size: Vector) {
+      let viewport = self.viewport.unrotated();
+      for region in self.paint_canvas.locked_regions() {
+         let rect = region.rect();
+         let top_left = viewport.to_screen_space(rect.top_left(), canvas_size).floor();
+         let bottom_right = viewport.to_screen_space(rect.bottom_right(), canvas_size).floor();
+         let rect = Rect::new(top_left, bottom_right - top_left);
+         renderer.fill(rect, Color::BLACK.with_alpha(16), 0.0);
+         renderer.outline(rect, Color::BLACK.with_alpha(64), 0.0, 1.0);
+      }
+   }
+
+   /// Returns the color that the given author's contributions are tinted with.
+   fn author_color(&self, author: Option<PeerId>) -> Color {
+      let PeerId(id) = author.or(self.peer.peer_id()).unwrap_or(PeerId(0));
+      // Scrambling the ID spreads peers that joined one after another across the color wheel.
+      let hue = (id.wrapping_mul(0x9e3779b97f4a7c15) >> 40) as f32 / (1 << 24) as f32 * 6.0;
+      Srgb::from(Hsv {
+         h: hue,
+         s: 0.75,
+         v: 0.9,
+      })
+      .to_color(1.0)
+   }
+
+   /// Returns the nickname of the given author, where `None` stands for ourselves.
+   fn author_nickname(&self, author: Option<PeerId>) -> &str {
+      match author {
+         None => self.peer.nickname(),
+         Some(peer_id) => match self.peer.mates().get(&peer_id) {
+            Some(mate) => &mate.nickname,
+            None => &self.assets.tr.attribution_unknown_author,
+         },
+      }
+   }
+
+   /// Tints the visible chunks with the color of whoever drew to them most recently.
+   ///
+   /// This must be drawn inside of [`Viewport::apply_screen_rotation`].
+   fn draw_attribution(&self, renderer: &mut Backend, canvas_size: Vector) {
+      let attribution = self.paint_canvas.attribution();
+      let viewport = self.viewport.unrotated();
+      for chunk_position in self.viewport.visible_tiles(Chunk::SIZE, canvas_size) {
+         let Some(contribution) = attribution.latest(chunk_position) else {
+            continue;
+         };
+         let (x, y) = chunk_position;
+         let top_left = Chunk::screen_position(chunk_position);
+         let bottom_right = Chunk::screen_position((x + 1, y + 1));
+         let top_left = viewport.to_screen_space(top_left, canvas_size).floor();
+         let bottom_right = viewport.to_screen_space(bottom_right, canvas_size).floor();
+         renderer.fill(
+            Rect::new(top_left, bottom_right - top_left),
+            self.author_color(contribution.author).with_alpha(64),
+            0.0,
+         );
+      }
+   }
+
+   /// Lists who drew to the chunk under the mouse cursor, and when, next to the cursor.
+   fn draw_attribution_tooltip(&self, renderer: &mut Backend, canvas_size: Vector) {
+      const MAX_LINES: usize = 4;
+      const PADDING: f32 = 8.0;
+
+      let chunk_position = PaintCanvas::chunk_position_at(self.cursor_position);
+      let contributions = self.paint_canvas.attribution().contributions(chunk_position);
+      if contributions.is_empty() {
+         return;
+      }
+      let font = &self.assets.sans;
+      let tr = &self.assets.tr;
+      let lines: Vec<_> = contributions
+         .iter()
+         .take(MAX_LINES)
+         .map(|contribution| {
+            let minutes = contribution.last_change.elapsed().as_secs() / 60;
+            let age = if minutes == 0 {
+               tr.audit_log_just_now.clone()
+            } else {
+               tr.audit_log_minutes_ago.format().with("minutes", minutes).done()
+            };
+            let text = format!("{} – {}", self.author_nickname(contribution.author), age);
+            (self.author_color(contribution.author), text)
+         })
+         .collect();
+
+      let line_height = (font.height() * 1.4).ceil();
+      let swatch_size = 8.0;
+      let text_width = lines.iter().map(|(_, text)| font.text_width(text)).fold(0.0, f32::max);
+      let size = vector(
+         PADDING * 2.0 + swatch_size + PADDING + text_width,
+         PADDING * 2.0 + line_height * lines.len() as f32,
+      );
+      let cursor = self.viewport.to_screen_space(self.cursor_position, canvas_size);
+      let position = point(
+         (cursor.x + 16.0).min(canvas_size.x - size.x).max(0.0),
+         (cursor.y + 16.0).min(canvas_size.y - size.y).max(0.0),
+      );
+      renderer.fill(Rect::new(position, size), Color::BLACK.with_alpha(192), 4.0);
+      for (i, (color, text)) in lines.iter().enumerate() {
+         let top = position.y + PADDING + line_height * i as f32;
+         let swatch_top = top + (line_height - swatch_size) / 2.0;
+         renderer.fill(
+            Rect::new(
+               point(position.x + PADDING, swatch_top),
+               vector(swatch_size, swatch_size),
+            ),
+            *color,
+            swatch_size / 2.0,
+         );
+         renderer.text(
+            Rect::new(
+               point(position.x + PADDING * 2.0 + swatch_size, top),
+               vector(0.0, line_height),
+            ),
+            font,
+            text,
+            Color::WHITE,
+            (AlignH::Left, AlignV::Middle),
+         );
+      }
+   }
+
    /// Processes the bottom bar.
    fn process_bar(&mut self, ui: &mut Ui, input: &mut Input) {
       self.bottom_bar_view.begin(ui, input, Layout::Horizontal);
@@ -559,7 +1337,6 @@ impl State {
 
       //
       // Right side
-      // Note that elements in HorizontalRev go from right to left rather than left to right.
       //
 
       ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
@@ -574,119 +1351,1794 @@ impl State {
       {
          self.overflow_menu.toggle();
       }
+      // There's nobody else to see when drawing offline.
+      if !self.peer.is_offline() {
+         ui.space(4.0);
+         if Button::with_icon(
+            ui,
+            input,
+            &ButtonArgs::new(ui, &self.assets.colors.action_button),
+            &self.assets.icons.peer.client,
+         )
+         .clicked()
+         {
+            self.toggle_people_window();
+         }
+         ui.space(8.0);
+         let attribution_toggle = if self.show_attribution {
+            &self.assets.tr.hide_attribution
+         } else {
+            &self.assets.tr.show_attribution
+         };
+         if PeopleWindow::small_button(ui, input, &self.assets, attribution_toggle) {
+            self.show_attribution = !self.show_attribution;
+         }
+      }
+      ui.space(8.0);
+      if PeopleWindow::small_button(ui, input, &self.assets, &self.assets.tr.layers) {
+         self.toggle_layers_window();
+      }
+      ui.space(8.0);
+      if PeopleWindow::small_button(ui, input, &self.assets, &self.assets.tr.history) {
+         self.toggle_history_window();
+      }
+      if self.reference_image.is_some() {
+         ui.space(16.0);
+         if PeopleWindow::small_button(
+            ui,
+            input,
+            &self.assets,
+            &self.assets.tr.remove_reference_image,
+         ) {
+            self.reference_image = None;
+            self.moving_reference_image = false;
+         }
+         ui.space(8.0);
+         let placement_toggle = if self.reference_below_canvas {
+            &self.assets.tr.reference_image_above_canvas
+         } else {
+            &self.assets.tr.reference_image_below_canvas
+         };
+         if PeopleWindow::small_button(ui, input, &self.assets, placement_toggle) {
+            self.reference_below_canvas = !self.reference_below_canvas;
+         }
+         ui.space(8.0);
+         ui.push((96.0, ui.height()), Layout::Freeform);
+         self.reference_opacity_slider.process(
+            ui,
+            input,
+            SliderArgs {
+               width: ui.width(),
+               color: self.assets.colors.slider,
+               focus: self.assets.colors.focus_ring,
+            },
+         );
+         ui.pop();
+         ui.space(8.0);
+         ui.horizontal_label(
+            &self.assets.sans,
+            &self.assets.tr.reference_image_opacity,
+            self.assets.colors.text,
+            None,
+         );
+      }
 
       ui.pop();
 
       self.bottom_bar_view.end(ui);
    }
 
-   /// Processes the overflow menu.
-   fn process_overflow_menu(&mut self, ui: &mut Ui, input: &mut Input) {
-      if self
-         .overflow_menu
-         .begin(
-            ui,
-            input,
-            ContextMenuArgs {
-               colors: &self.assets.colors.context_menu,
-            },
-         )
-         .is_open()
-      {
-         ui.pad(8.0);
+   /// Returns whether the page tabs should be shown. They're hidden while there's just one page,
+   /// unless we're the host, who can add more.
+   fn has_page_bar(&self) -> bool {
+      self.pages.len() > 1 || self.peer.is_host()
+   }
 
-         // Room ID display
+   /// Processes the page tabs above the bottom bar.
+   fn process_page_bar(&mut self, ui: &mut Ui, input: &mut Input) {
+      self.page_bar_view.begin(ui, input, Layout::Horizontal);
 
-         ui.push((ui.width(), 0.0), Layout::Vertical);
-         ui.pad((8.0, 0.0));
-         ui.space(8.0);
+      ui.fill(self.assets.colors.panel);
+      ui.border_top(self.assets.colors.separator, 1.0);
+      ui.pad((8.0, 2.0));
 
-         ui.vertical_label(
+      let mut selected_page = None;
+      let mut add_page = false;
+      for (index, page) in self.pages.iter().enumerate() {
+         if Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(
+               ui,
+               ButtonColors::toggle(
+                  index == self.current_page,
+                  &self.assets.colors.toolbar_button,
+                  &self.assets.colors.selected_toolbar_button,
+               ),
+            )
+            .corner_radius(4.0),
             &self.assets.sans,
-            &self.assets.tr.room_id,
-            self.assets.colors.text,
-            AlignH::Left,
+            &page.name,
+         )
+         .clicked()
+         {
+            selected_page = Some(index);
+         }
+         ui.space(4.0);
+      }
+      if self.peer.is_host()
+         && self.pages.len() < MAX_PAGES
+         && Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(ui, &self.assets.colors.action_button)
+               .corner_radius(4.0)
+               .tooltip(&self.assets.sans, Tooltip::top(&self.assets.tr.add_page)),
+            &self.assets.sans,
+            "+",
+         )
+         .clicked()
+      {
+         add_page = true;
+      }
+
+      self.page_bar_view.end(ui);
+
+      if add_page {
+         catch!(self.add_page());
+      } else if let Some(page) = selected_page {
+         catch!(self.switch_page(page));
+      }
+   }
+
+   /// Processes the status bar.
+   fn process_status_bar(&mut self, ui: &mut Ui, input: &mut Input) {
+      self.status_bar_view.begin(ui, input, Layout::Horizontal);
+
+      ui.fill(self.assets.colors.panel);
+      ui.border_top(self.assets.colors.separator, 1.0);
+      ui.pad((8.0, 0.0));
+
+      let font = &self.assets.sans;
+      let color = self.assets.colors.text;
+      let tr = &self.assets.tr;
+
+      // Where the cursor is
+
+      let Point { x, y } = self.cursor_position.floor();
+      let (x, y) = (x as i32, y as i32);
+      ui.horizontal_label(
+         font,
+         &format!("{}, {}", x, y),
+         color,
+         Some((112.0, AlignH::Left)),
+      );
+      let chunk = (
+         x.div_euclid(Chunk::SIZE.0 as i32),
+         y.div_euclid(Chunk::SIZE.1 as i32),
+      );
+      ui.horizontal_label(
+         font,
+         &tr.status_bar_chunk.format().with("x", chunk.0).with("y", chunk.1).done(),
+         color,
+         Some((112.0, AlignH::Left)),
+      );
+      ui.horizontal_label(
+         font,
+         &format!("{:.0}%", self.viewport.zoom() * 100.0),
+         color,
+         Some((64.0, AlignH::Left)),
+      );
+      if self.viewport.rotation() != 0.0 {
+         ui.horizontal_label(
+            font,
+            &format!("{:.0}°", self.viewport.rotation().to_degrees()),
+            color,
+            Some((48.0, AlignH::Left)),
+         );
+      }
+
+      //
+      // Right side
+      //
+
+      ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+      if self.peer.is_offline() {
+         ui.horizontal_label(font, &tr.status_bar_offline, color, None);
+      } else {
+         let pending_downloads = self
+            .chunk_downloads
+            .values()
+            .filter(|&&state| matches!(state, ChunkDownload::Queued | ChunkDownload::Requested))
+            .count();
+         let sync_status = if pending_downloads > 0 {
+            tr.status_bar_downloading.format().with("chunks", pending_downloads).done()
+         } else {
+            tr.status_bar_up_to_date.clone()
+         };
+         ui.horizontal_label(font, &sync_status, color, None);
+         ui.space(16.0);
+         // Count ourselves in, too.
+         let people = self.peer.mates().len() + 1;
+         ui.horizontal_label(
+            font,
+            &tr.status_bar_people.format().with("people", people).done(),
+            color,
+            None,
+         );
+      }
+      ui.pop();
+
+      self.status_bar_view.end(ui);
+   }
+
+   /// Opens the People window if it's closed, or closes it if it's open.
+   fn toggle_people_window(&mut self) {
+      if let Some(window_id) = self.people_window.take() {
+         self.wm.close_window(window_id);
+      } else {
+         let content = PeopleWindow::new(&self.assets.tr.revert_changes_from_last)
+            .background()
+            .buttons(WindowButtonStyle {
+               padding: Padding::even(12.0),
+            });
+         let window_id = self
+            .wm
+            .open_window(
+               self.people_window_view.clone(),
+               content,
+               PeopleWindowData::new(),
+            )
+            .finish();
+         self.people_window = Some(window_id);
+      }
+   }
+
+   /// Keeps the People window's list up to date, and handles the actions requested through it.
+   fn process_people_window(&mut self, renderer: &mut Backend) {
+      let window_id = match &self.people_window {
+         Some(window_id) => window_id,
+         None => return,
+      };
+      if self.wm.should_close(window_id) {
+         self.toggle_people_window();
+         return;
+      }
+
+      let host_id = self.peer.host_id();
+      let following = self.following;
+      let cursors = self.mate_cursors();
+      let data = self.wm.window_data_mut(window_id);
+      data.is_host = self.peer.is_host();
+      data.paused = self.paint_canvas.is_paused();
+      data.vector_mode = self.paint_canvas.vector_mode();
+      data.canvas_bounds = self.paint_canvas.bounds();
+      data.background = self.paint_canvas.background();
+      data.chunk_encoding = self.chunk_encoding;
+      data.invite = self.invite;
+      data.invite_only = self.invite_only;
+      data.voice_chat = self.voice_chat.is_some();
+      data.has_room_palette = !self.global_controls.color_picker.room_palette_rgb().is_empty();
+      data.sharing_bookmarks = self.sharing_bookmarks;
+      data.people = self
+         .peer
+         .mates()
+         .iter()
+         .map(|(&peer_id, mate)| Person {
+            peer_id,
+            nickname: mate.nickname.clone(),
+            is_host: host_id == Some(peer_id),
+            color: Minimap::peer_color(peer_id),
+            latency: mate.latency,
+            cursor: cursors.iter().find(|&&(id, _)| id == peer_id).map(|&(_, position)| position),
+            followed: following == Some(peer_id),
+            blocked: config().block_list.is_blocked(&mate.nickname),
+            voice: self.voice_chat.as_ref().map(|voice_chat| voice_chat.settings(peer_id)),
+         })
+         .collect();
+      data.people.sort_by(|a, b| a.nickname.cmp(&b.nickname));
+      let requests = std::mem::take(&mut data.requests);
+      let dimensions = PeopleWindow::dimensions(data);
+
+      let pinned = self.wm.pinned(window_id);
+      let view = self.wm.view_mut(window_id);
+      view.dimensions = dimensions;
+      if !pinned {
+         view.position = self.people_window_view.position;
+      }
+
+      for request in requests {
+         match request {
+            PeopleRequest::Revert(peer_id, period) => {
+               self.revert_changes(renderer, peer_id, period)
+            }
+            PeopleRequest::SetPaused(paused) => self.set_paused(paused),
+            PeopleRequest::SetVectorMode(enabled) => self.set_vector_mode(enabled),
+            PeopleRequest::SetCanvasBounds(bounds) => self.set_canvas_bounds(bounds),
+            PeopleRequest::SetBackground(background) => self.set_background(background),
+            PeopleRequest::SetChunkEncoding(encoding) => {
+               self.apply_chunk_encoding(encoding);
+               catch!(self.peer.send_chunk_encoding(PeerId::BROADCAST, encoding));
+            }
+            PeopleRequest::ShareRoomPalette(share) => self.share_room_palette(share),
+            PeopleRequest::ShareBookmarks(share) => self.share_bookmarks(share),
+            PeopleRequest::SetBlocked(nickname, blocked) => self.set_blocked(&nickname, blocked),
+            PeopleRequest::SetVoiceChat(enabled) => self.set_voice_chat(enabled),
+            PeopleRequest::SetVoiceSettings(peer_id, settings) => {
+               if let Some(voice_chat) = &mut self.voice_chat {
+                  voice_chat.set_settings(peer_id, settings);
+               }
+            }
+            PeopleRequest::MakeHost(peer_id) => catch!(self.peer.transfer_host(peer_id)),
+            PeopleRequest::JumpTo(position) => {
+               self.stop_following();
+               self.viewport.glide_to(position, self.viewport.zoom_level())
+            }
+            PeopleRequest::Follow(Some(peer_id)) => self.follow(peer_id),
+            PeopleRequest::Follow(None) => self.stop_following(),
+            PeopleRequest::OpenAuditLog => {
+               if self.audit_log_window.is_none() {
+                  self.toggle_audit_log_window();
+               }
+            }
+            PeopleRequest::CreateInvite {
+               single_use,
+               lifetime,
+            } => catch!(self.peer.create_invite(single_use, lifetime)),
+            PeopleRequest::CopyInvite => {
+               if let Some(invite) = self.invite {
+                  let message = self.assets.tr.invite_copied.clone();
+                  let link = RoomLink {
+                     relay: self.peer.relay_address().to_owned(),
+                     target: LinkTarget::Invite(invite),
+                  };
+                  tokio::task::spawn(async move {
+                     catch!(clipboard::copy_string_async(link.to_string()).await);
+                     bus::push(common::Log(message));
+                  });
+               }
+            }
+            PeopleRequest::RevokeInvites => {
+               catch!(self.peer.revoke_invites());
+               self.invite = None;
+               log!(self.log, "{}", self.assets.tr.invites_revoked);
+            }
+            PeopleRequest::SetInviteOnly(invite_only) => {
+               catch!(self.peer.set_invite_only(invite_only));
+               self.invite_only = invite_only;
+            }
+         }
+      }
+   }
+
+   /// Opens the Layers window if it's closed, or closes it if it's open.
+   fn toggle_layers_window(&mut self) {
+      if let Some(window_id) = self.layers_window.take() {
+         self.wm.close_window(window_id);
+      } else {
+         let content = LayersWindow.background().buttons(WindowButtonStyle {
+            padding: Padding::even(12.0),
+         });
+         let window_id = self
+            .wm
+            .open_window(
+               self.layers_window_view.clone(),
+               content,
+               LayersWindowData::new(),
+            )
+            .finish();
+         self.layers_window = Some(window_id);
+      }
+   }
+
+   /// Keeps the Layers window's list up to date, and handles the actions requested through it.
+   fn process_layers_window(&mut self, renderer: &mut Backend) {
+      let window_id = match &self.layers_window {
+         Some(window_id) => window_id,
+         None => return,
+      };
+      if self.wm.should_close(window_id) {
+         self.toggle_layers_window();
+         return;
+      }
+
+      let data = self.wm.window_data_mut(window_id);
+      data.is_host = self.peer.is_host();
+      data.layers = self.paint_canvas.layers().to_vec();
+      data.current_layer = self.current_layer;
+      let requests = std::mem::take(&mut data.requests);
+      let dimensions = LayersWindow::dimensions(data);
+
+      let pinned = self.wm.pinned(window_id);
+      let view = self.wm.view_mut(window_id);
+      view.dimensions = dimensions;
+      if !pinned {
+         view.position = self.layers_window_view.position;
+      }
+
+      for request in requests {
+         match request {
+            LayersRequest::Select(layer) => self.select_layer(renderer, layer),
+            LayersRequest::Add => self.add_layer(renderer),
+            LayersRequest::SetVisible(index, visible) => self.change_layers(|layers| {
+               if let Some(layer) = layers.get_mut(index) {
+                  layer.visible = visible;
+               }
+            }),
+            LayersRequest::SetOpacity(index, opacity) => self.change_layers(|layers| {
+               if let Some(layer) = layers.get_mut(index) {
+                  layer.opacity = opacity;
+               }
+            }),
+         }
+      }
+   }
+
+   /// Opens the History window if it's closed, or closes it if it's open.
+   fn toggle_history_window(&mut self) {
+      if let Some(window_id) = self.history_window.take() {
+         self.wm.close_window(window_id);
+      } else {
+         let content = HistoryWindow.background().buttons(WindowButtonStyle {
+            padding: Padding::even(12.0),
+         });
+         let window_id = self
+            .wm
+            .open_window(
+               self.history_window_view.clone(),
+               content,
+               HistoryWindowData::new(),
+            )
+            .finish();
+         self.history_window = Some(window_id);
+      }
+   }
+
+   /// Adds the operations we committed since the last frame to the history, along with
+   /// thumbnails of the chunks they changed.
+   fn update_history(&mut self, renderer: &mut Backend) {
+      let operations = self.paint_canvas.take_own_operations();
+      if operations.is_empty() {
+         return;
+      }
+      let tool = self.toolbar.clone_tool_name(self.toolbar.current_tool());
+      for (layer, chunk_positions) in operations {
+         let thumbnail = self.paint_canvas.thumbnail(
+            renderer,
+            layer,
+            &chunk_positions,
+            HistoryWindow::THUMBNAIL_SIZE,
+         );
+         // A new operation can't be redone past, so the ones undone on its layer are gone for good.
+         self.history.retain(|entry| !(entry.undone && entry.layer == layer));
+         self.history.push(HistoryEntry {
+            tool: tool.clone(),
+            layer,
+            thumbnail: Rc::new(thumbnail),
+            undone: false,
+         });
+      }
+      if self.history.len() > HistoryWindow::MAX_ENTRIES {
+         let excess = self.history.len() - HistoryWindow::MAX_ENTRIES;
+         self.history.drain(..excess);
+      }
+   }
+
+   /// Keeps the History window's list up to date, and handles the jumps requested through it.
+   fn process_history_window(&mut self, renderer: &mut Backend) {
+      self.update_history(renderer);
+
+      let window_id = match &self.history_window {
+         Some(window_id) => window_id,
+         None => return,
+      };
+      if self.wm.should_close(window_id) {
+         self.toggle_history_window();
+         return;
+      }
+
+      let data = self.wm.window_data_mut(window_id);
+      data.entries = self.history.clone();
+      let requests = std::mem::take(&mut data.requests);
+      let dimensions = HistoryWindow::dimensions(data);
+
+      let pinned = self.wm.pinned(window_id);
+      let view = self.wm.view_mut(window_id);
+      view.dimensions = dimensions;
+      if !pinned {
+         view.position = self.history_window_view.position;
+      }
+
+      for request in requests {
+         match request {
+            HistoryRequest::JumpTo(index) => self.jump_in_history(renderer, index),
+         }
+      }
+   }
+
+   /// Undoes or redoes our own operations until the history entry with the given index is the
+   /// most recent one that's done. Undo only ever works on the current layer, so entries from other
+   /// layers are switched to first.
+   fn jump_in_history(&mut self, renderer: &mut Backend, index: usize) {
+      let Some(&HistoryEntry { layer, undone, .. }) = self.history.get(index) else {
+         return;
+      };
+      self.select_layer(renderer, layer);
+      if undone {
+         let redos = self.history[..=index]
+            .iter()
+            .filter(|entry| entry.layer == layer && entry.undone)
+            .count();
+         for _ in 0..redos {
+            self.redo(renderer);
+         }
+      } else {
+         let undos = self.history[index + 1..]
+            .iter()
+            .filter(|entry| entry.layer == layer && !entry.undone)
+            .count();
+         for _ in 0..undos {
+            self.undo(renderer);
+         }
+      }
+   }
+
+   /// Marks our most recent operation on the current layer as undone in the history, or the
+   /// earliest undone one as redone.
+   fn mark_history(&mut self, undone: bool) {
+      let layer = self.current_layer;
+      let mut entries = self.history.iter_mut().filter(|entry| entry.layer == layer);
+      let entry = if undone {
+         entries.filter(|entry| !entry.undone).last()
+      } else {
+         entries.find(|entry| entry.undone)
+      };
+      if let Some(entry) = entry {
+         entry.undone = undone;
+      }
+   }
+
+   /// Returns whether the peer with the given ID is on the local block list.
+   fn is_blocked(&self, peer_id: PeerId) -> bool {
+      match self.peer.mates().get(&peer_id) {
+         Some(mate) => config().block_list.is_blocked(&mate.nickname),
+         None => false,
+      }
+   }
+
+   /// Returns whether changes to the canvas made by the peer with the given ID should be dropped,
+   /// because they're blocked.
+   ///
+   /// The host keeps the canvas everyone else downloads when joining, so it always applies
+   /// blocked people's changes, and only hides their cursors and names.
+   fn drops_changes_of(&self, peer_id: PeerId) -> bool {
+      !self.peer.is_host() && self.is_blocked(peer_id)
+   }
+
+   /// Adds or removes the person from the local block list.
+   fn set_blocked(&mut self, nickname: &str, blocked: bool) {
+      config::write(|config| config.block_list.set_blocked(nickname, blocked));
+      let message = if blocked {
+         &self.assets.tr.someone_was_blocked
+      } else {
+         &self.assets.tr.someone_was_unblocked
+      };
+      log!(
+         self.log,
+         "{}",
+         message.format().with("nickname", nickname).done()
+      );
+   }
+
+   /// Pauses or resumes modifications to the canvas, room-wide. Only the host can do this.
+   fn set_paused(&mut self, paused: bool) {
+      self.paint_canvas.set_paused(paused);
+      catch!(self.peer.send_paused(PeerId::BROADCAST, paused));
+      self.audit_log.record(
+         self.peer.peer_id(),
+         self.peer.nickname(),
+         AuditEventKind::Paused(paused),
+      );
+   }
+
+   /// Turns vector strokes on or off, room-wide. Only the host can do this.
+   fn set_vector_mode(&mut self, enabled: bool) {
+      self.paint_canvas.set_vector_mode(enabled);
+      catch!(self.peer.send_vector_mode(PeerId::BROADCAST, enabled));
+   }
+
+   /// Fixes the canvas to the given size, or makes it infinite, room-wide. Only the host can do
+   /// this.
+   fn set_canvas_bounds(&mut self, bounds: Option<(u32, u32)>) {
+      self.paint_canvas.set_bounds(bounds);
+      catch!(self.peer.send_canvas_bounds(PeerId::BROADCAST, bounds));
+   }
+
+   /// Changes the pattern drawn beneath the canvas, room-wide. Only the host can do this.
+   fn set_background(&mut self, background: Background) {
+      self.paint_canvas.set_background(background);
+      catch!(self.peer.send_background(PeerId::BROADCAST, background));
+   }
+
+   /// Switches to the given chunk encoding. Cached chunk images were encoded with the previous
+   /// settings, so they're dropped.
+   fn apply_chunk_encoding(&mut self, encoding: ChunkEncoding) {
+      if encoding == self.chunk_encoding {
+         return;
+      }
+      self.chunk_encoding = encoding;
+      self.cache_layer = CacheLayer::new();
+      for contents in self.pages.iter_mut().filter_map(|page| page.contents.as_mut()) {
+         contents.cache_layer = CacheLayer::new();
+      }
+   }
+
+   /// Joins or leaves voice chat.
+   fn set_voice_chat(&mut self, enabled: bool) {
+      self.voice_chat = None;
+      if enabled {
+         self.voice_chat = Some(catch!(VoiceChat::start()));
+      }
+   }
+
+   /// Returns the default name of the page with the given index.
+   fn page_name(&self, index: usize) -> String {
+      self.assets.tr.page_name.format().with("number", index + 1).done()
+   }
+
+   /// Returns the page the given peer is on, or `None` if we don't know them.
+   fn page_of(&self, peer_id: PeerId) -> Option<usize> {
+      self.peer.mates().get(&peer_id).map(|mate| mate.page)
+   }
+
+   /// Returns whether the given peer is on a page whose contents we don't keep, in which case
+   /// their changes to the canvas can be ignored. The host keeps every page.
+   fn skips_page_of(&self, peer_id: PeerId) -> bool {
+      !self.peer.is_host() && self.page_of(peer_id).is_some_and(|page| page != self.current_page)
+   }
+
+   /// Swaps the contents of the current page with the given ones.
+   fn swap_page_contents(&mut self, contents: &mut PageContents) {
+      self.paint_canvas.swap_page(&mut contents.canvas);
+      std::mem::swap(&mut self.cache_layer, &mut contents.cache_layer);
+      std::mem::swap(&mut self.chunk_downloads, &mut contents.chunk_downloads);
+   }
+
+   /// Temporarily makes the given page the current one while running `f`, such that the host can
+   /// serve and apply changes to pages it isn't on. Returns `None` if there's no such page.
+   fn with_page<R>(&mut self, page: usize, f: impl FnOnce(&mut Self) -> R) -> Option<R> {
+      if page == self.current_page {
+         return Some(f(self));
+      }
+      let mut contents =
+         self.pages.get_mut(page)?.contents.take().unwrap_or_else(PageContents::new);
+      self.swap_page_contents(&mut contents);
+      // Chunks encoded in the meantime must not end up in the current page's cache.
+      let encode_channels = std::mem::replace(&mut self.encode_channels, EncodeChannels::new());
+      let current_page = std::mem::replace(&mut self.current_page, page);
+      let result = f(self);
+      self.send_chunk_digests();
+      self.current_page = current_page;
+      self.encode_channels = encode_channels;
+      self.swap_page_contents(&mut contents);
+      self.pages[page].contents = Some(contents);
+      Some(result)
+   }
+
+   /// Switches over to the page with the given index.
+   fn switch_page(&mut self, page: usize) -> netcanv::Result<()> {
+      if page == self.current_page || page >= self.pages.len() {
+         return Ok(());
+      }
+      let mut contents = self.pages[page].contents.take().unwrap_or_else(PageContents::new);
+      self.swap_page_contents(&mut contents);
+      if self.peer.is_host() {
+         self.pages[self.current_page].contents = Some(contents);
+      }
+      // Anything still being encoded or decoded belongs to the previous page.
+      self.encode_channels = EncodeChannels::new();
+      self.decode_channels = DecodeChannels::new();
+      self.current_page = page;
+      self.peer.send_select_page(PeerId::BROADCAST, page)?;
+      if !self.peer.is_host() {
+         self.page_sync_pending = true;
+      }
+      Ok(())
+   }
+
+   /// Replaces the pages with the ones loaded from a file, the first of which is already on the
+   /// paint canvas. Files without pages leave the current ones alone.
+   fn set_loaded_pages(&mut self, pages: Vec<LoadedPage>) {
+      if pages.is_empty() {
+         return;
+      }
+      self.pages = pages
+         .into_iter()
+         .map(|page| Page {
+            name: page.name,
+            contents: page.canvas.map(|canvas| PageContents {
+               canvas,
+               ..PageContents::new()
+            }),
+         })
+         .collect();
+      self.current_page = 0;
+   }
+
+   /// Adds a new page to the room. Only the host can do this.
+   fn add_page(&mut self) -> netcanv::Result<()> {
+      if self.pages.len() >= MAX_PAGES {
+         return Ok(());
+      }
+      let name = self.page_name(self.pages.len());
+      self.pages.push(Page::new(name));
+      let names = self.pages.iter().map(|page| page.name.clone()).collect();
+      self.peer.send_pages(PeerId::BROADCAST, names)?;
+      self.switch_page(self.pages.len() - 1)
+   }
+
+   /// Sends the positions of the chunks on every layer of the given page to the given peer.
+   fn send_page_chunk_positions(&mut self, peer_id: PeerId, page: usize) -> netcanv::Result<()> {
+      let layer_count = self.paint_canvas.layers().len();
+      let positions: Vec<_> = self
+         .with_page(page, |this| {
+            (0..layer_count).map(|layer| this.paint_canvas.chunk_positions(layer)).collect()
+         })
+         .unwrap_or_default();
+      for (layer, positions) in positions.into_iter().enumerate() {
+         // The bottom layer's positions are always sent, as they let the peer know it's caught up
+         // with the page.
+         if layer == 0 || !positions.is_empty() {
+            self.peer.send_chunk_positions(peer_id, layer, positions)?;
+         }
+      }
+      Ok(())
+   }
+
+   /// Replaces the canvas's layers with the given ones. Anyone drawing on a layer that no longer
+   /// exists ends up on the topmost one.
+   fn set_layers(&mut self, layers: Vec<Layer>) {
+      self.paint_canvas.set_layers(layers);
+      self.current_layer = self.current_layer.min(self.paint_canvas.layers().len() - 1);
+      // Chunks on layers that were removed won't ever be downloaded.
+      let layer_count = self.paint_canvas.layers().len();
+      self.chunk_downloads.retain(|&(layer, _), _| layer < layer_count);
+   }
+
+   /// Changes the canvas's layers, and lets everyone know. Only the host can do this.
+   fn change_layers(&mut self, change: impl FnOnce(&mut Vec<Layer>)) {
+      let mut layers = self.paint_canvas.layers().to_vec();
+      change(&mut layers);
+      self.set_layers(layers);
+      catch!(self.peer.send_layers(PeerId::BROADCAST, self.paint_canvas.layers().to_vec()));
+   }
+
+   /// Adds a new layer on top of all the others, and switches over to it. Only the host can do
+   /// this.
+   fn add_layer(&mut self, renderer: &mut Backend) {
+      if self.paint_canvas.layers().len() >= MAX_LAYERS {
+         return;
+      }
+      self.change_layers(|layers| layers.push(PaintCanvas::default_layer()));
+      self.select_layer(renderer, self.paint_canvas.layers().len() - 1);
+   }
+
+   /// Switches over to drawing on the layer with the given index.
+   fn select_layer(&mut self, renderer: &mut Backend, layer: usize) {
+      if layer == self.current_layer || layer >= self.paint_canvas.layers().len() {
+         return;
+      }
+      // Whatever we were in the middle of belongs to the previous layer, so the host has to hear
+      // about it before we switch.
+      self.set_canvas_author(None);
+      self.paint_canvas.commit_operation(renderer);
+      if self.paint_canvas.take_pending_commit() {
+         catch!(self.peer.send_commit_operation());
+      }
+      self.current_layer = layer;
+      self.set_canvas_author(None);
+      catch!(self.peer.send_select_layer(PeerId::BROADCAST, layer));
+   }
+
+   /// Encodes the given chunks and sends them to everyone on the current page.
+   fn send_chunks_to_page(&mut self, renderer: &mut Backend, chunks: &[LayerChunk]) {
+      let mates = self.peer.mates();
+      if mates.values().all(|mate| mate.page == self.current_page) {
+         self.encode_chunks(renderer, PeerId::BROADCAST, chunks, true);
+      } else {
+         let peer_ids: Vec<_> = mates
+            .iter()
+            .filter(|(_, mate)| mate.page == self.current_page)
+            .map(|(&peer_id, _)| peer_id)
+            .collect();
+         for peer_id in peer_ids {
+            self.encode_chunks(renderer, peer_id, chunks, true);
+         }
+      }
+   }
+
+   /// Sends digests of the chunks changed by operations committed since the last call to everyone
+   /// on the current page, so that they can download whichever chunks they got wrong. Only the
+   /// host keeps track of operations, so this does nothing for anyone else.
+   fn send_chunk_digests(&mut self) {
+      let digests = self.paint_canvas.take_chunk_digests();
+      if digests.is_empty() || self.peer.is_offline() {
+         return;
+      }
+      let mates = self.peer.mates();
+      for (layer, digests) in digests {
+         if mates.values().all(|mate| mate.page == self.current_page) {
+            catch!(self.peer.send_chunk_digests(PeerId::BROADCAST, layer, digests));
+         } else {
+            for (&peer_id, _) in mates.iter().filter(|(_, mate)| mate.page == self.current_page) {
+               catch!(self.peer.send_chunk_digests(peer_id, layer, digests.clone()));
+            }
+         }
+      }
+   }
+
+   /// Compares the host's digests of chunks on a layer with our own, and fixes up the chunks that
+   /// diverged. Chunks that are empty for the host are cleared right away, and the rest are
+   /// downloaded again.
+   fn check_chunk_digests(
+      &mut self,
+      renderer: &mut Backend,
+      layer: usize,
+      digests: Vec<((i32, i32), Vec<u8>)>,
+   ) -> netcanv::Result<()> {
+      if layer >= self.paint_canvas.layers().len() {
+         return Ok(());
+      }
+      let mut diverged = Vec::new();
+      for (chunk_position, digest) in digests {
+         // Chunks that are still being downloaded will arrive up to date.
+         if matches!(
+            self.chunk_downloads.get(&(layer, chunk_position)),
+            Some(state) if *state != ChunkDownload::Downloaded
+         ) {
+            continue;
+         }
+         let ours = match self.paint_canvas.chunk(layer, chunk_position) {
+            Some(chunk) => Chunk::digest(&chunk.download_image(renderer)),
+            None => vec![0; CHUNK_DIGEST_LEN],
+         };
+         if Chunk::digests_match(&ours, &digest) {
+            continue;
+         }
+         if digest.iter().all(|&value| value == 0) {
+            let empty = RgbaImage::new(Chunk::SIZE.0, Chunk::SIZE.1);
+            let _ = self.decode_channels.tx.send(((layer, chunk_position), empty));
+         } else {
+            diverged.push(chunk_position);
+         }
+      }
+      if !diverged.is_empty() {
+         tracing::info!("{} chunks diverged from the host's", diverged.len());
+         for &chunk_position in &diverged {
+            self.chunk_downloads.insert((layer, chunk_position), ChunkDownload::Requested);
+         }
+         self.peer.download_chunks(layer, diverged)?;
+      }
+      Ok(())
+   }
+
+   /// Saves the view as a bookmark or jumps to one, when a number key is pressed along with the
+   /// matching modifier. Returns whether either happened.
+   fn process_bookmark_shortcuts(&mut self, input: &Input) -> bool {
+      const KEYS: [VirtualKeyCode; MAX_BOOKMARKS] = [
+         VirtualKeyCode::Key1,
+         VirtualKeyCode::Key2,
+         VirtualKeyCode::Key3,
+         VirtualKeyCode::Key4,
+         VirtualKeyCode::Key5,
+         VirtualKeyCode::Key6,
+         VirtualKeyCode::Key7,
+         VirtualKeyCode::Key8,
+         VirtualKeyCode::Key9,
+      ];
+
+      let (save, jump) = {
+         let keymap = config().keymap.canvas.clone();
+         (keymap.save_bookmark, keymap.jump_to_bookmark)
+      };
+      for (slot, key) in (1..).zip(KEYS) {
+         if input.action((save, key)) == (true, true) {
+            self.save_bookmark(slot);
+            return true;
+         }
+         if input.action((jump, key)) == (true, true) {
+            self.jump_to_bookmark(slot);
+            return true;
+         }
+      }
+      false
+   }
+
+   /// Glides the zoom in by one step, which doubles the zoom factor, or out by one step when the
+   /// direction is negative. The view stays centered on the same point.
+   fn zoom_by_step(&mut self, direction: f32) {
+      let zoom_level = self.viewport.zoom_level() + direction * Viewport::zoom_level_delta(2.0);
+      self.viewport.glide_to(self.viewport.pan(), zoom_level);
+   }
+
+   /// Saves the current view as a bookmark under the given number key, replacing the bookmark
+   /// that was saved under it before.
+   fn save_bookmark(&mut self, slot: u8) {
+      let pan = self.viewport.pan();
+      let zoom_level = self.viewport.zoom_level().round() as i32;
+      let bookmark = Bookmark {
+         slot,
+         name: self.assets.tr.bookmark_name.format().with("slot", slot).done(),
+         pan: (pan.x.round() as i32, pan.y.round() as i32),
+         zoom_level: zoom_level.clamp(*BOOKMARK_ZOOM_LEVELS.start(), *BOOKMARK_ZOOM_LEVELS.end()),
+      };
+      self.bookmarks.retain(|bookmark| bookmark.slot != slot);
+      self.bookmarks.push(bookmark);
+      self.bookmarks.sort_by_key(|bookmark| bookmark.slot);
+      log!(
+         self.log,
+         "{}",
+         self.assets.tr.bookmark_saved.format().with("slot", slot).done()
+      );
+      if self.sharing_bookmarks {
+         catch!(self.peer.send_bookmarks(PeerId::BROADCAST, self.bookmarks.clone()));
+      }
+   }
+
+   /// Jumps to the bookmark saved under the given number key. Our own bookmarks take precedence
+   /// over the ones shared by the host.
+   fn jump_to_bookmark(&mut self, slot: u8) {
+      let bookmark =
+         self.bookmarks.iter().chain(&self.room_bookmarks).find(|bookmark| bookmark.slot == slot);
+      match bookmark {
+         Some(bookmark) => {
+            self.viewport.glide_to(
+               point(bookmark.pan.0 as f32, bookmark.pan.1 as f32),
+               bookmark.zoom_level as f32,
+            );
+         }
+         None => log!(
+            self.log,
+            "{}",
+            self.assets.tr.no_bookmark.format().with("slot", slot).done()
+         ),
+      }
+   }
+
+   /// Shares our bookmarks with everyone in the room, or stops sharing them if `share` is false.
+   fn share_bookmarks(&mut self, share: bool) {
+      self.sharing_bookmarks = share;
+      let bookmarks = if share {
+         self.bookmarks.clone()
+      } else {
+         Vec::new()
+      };
+      catch!(self.peer.send_bookmarks(PeerId::BROADCAST, bookmarks));
+   }
+
+   /// Shares our own palette with everyone in the room as the room palette, or removes the room
+   /// palette if `share` is false.
+   fn share_room_palette(&mut self, share: bool) {
+      let color_picker = &mut self.global_controls.color_picker;
+      let colors = if share {
+         color_picker.palette_rgb()
+      } else {
+         Vec::new()
+      };
+      color_picker.set_room_palette(&colors);
+      catch!(self.peer.send_room_palette(PeerId::BROADCAST, colors));
+   }
+
+   /// Imports the pages of a document onto the current layer, starting at the middle of the
+   /// viewport, and sends them out to everyone on the current page.
+   fn import_document(&mut self, renderer: &mut Backend, path: &Path) -> netcanv::Result<()> {
+      let pan = self.viewport.pan();
+      let top_left = (
+         (pan.x / Chunk::SIZE.0 as f32).floor() as i32,
+         (pan.y / Chunk::SIZE.1 as f32).floor() as i32,
+      );
+      let positions =
+         ProjectFile::import_document(renderer, path, &mut self.paint_canvas, top_left)?;
+      let chunks: Vec<_> =
+         positions.into_iter().map(|position| (self.current_layer, position)).collect();
+      for &chunk in &chunks {
+         self.cache_layer.invalidate(chunk);
+      }
+      self.send_chunks_to_page(renderer, &chunks);
+      Ok(())
+   }
+
+   /// Returns the positions of the cursors of everyone on the current page, whose tools show
+   /// where they are. Blocked peers are left out.
+   fn mate_cursors(&self) -> Vec<(PeerId, Point)> {
+      let mut cursors = Vec::new();
+      for (&peer_id, mate) in self.peer.mates() {
+         if config().block_list.is_blocked(&mate.nickname) || mate.page != self.current_page {
+            continue;
+         }
+         let Some(tool_id) = mate.tool.as_deref().and_then(|name| self.toolbar.tool_by_name(name))
+         else {
+            continue;
+         };
+         if let Some(position) = self.toolbar.with_tool(tool_id, |tool| tool.peer_position(peer_id))
+         {
+            cursors.push((peer_id, position));
+         }
+      }
+      cursors
+   }
+
+   /// Processes the minimap, and centers the viewport on wherever it's clicked.
+   fn process_minimap(&mut self, ui: &mut Ui, input: &mut Input) {
+      if self.minimap.is_none() {
+         return;
+      }
+
+      // Chunks that weren't downloaded yet are shown too, so that the minimap gives an idea of
+      // where there's something to see.
+      let mut chunks: HashSet<_> =
+         self.chunk_downloads.keys().map(|&(_, position)| position).collect();
+      chunks.extend(self.paint_canvas.all_chunks().map(|(_, position, _)| position));
+      let cursors = self.mate_cursors();
+      let visible_rect = self.viewport.visible_rect(self.canvas_view.size());
+
+      if let Some(minimap) = &mut self.minimap {
+         let args = MinimapArgs {
+            chunks: &chunks,
+            visible_rect,
+            cursors: &cursors,
+         };
+         if let Some(position) = minimap.process(ui, input, &self.assets, args) {
+            self.viewport.center_on(position);
+            self.stop_following();
+         }
+      }
+   }
+
+   /// Lets everyone know where our viewport is, if it moved since it was last sent. This is
+   /// rate-limited, as the viewport only matters to people who follow us.
+   fn send_viewport(&mut self) {
+      if self.peer.mates().is_empty()
+         || self.last_viewport_sent.elapsed() < Self::VIEWPORT_SEND_INTERVAL
+      {
+         return;
+      }
+      let pan = self.viewport.pan();
+      let zoom_level = self.viewport.zoom_level().round() as i32;
+      let viewport = (
+         (pan.x.round() as i32, pan.y.round() as i32),
+         zoom_level.clamp(*BOOKMARK_ZOOM_LEVELS.start(), *BOOKMARK_ZOOM_LEVELS.end()),
+      );
+      if self.sent_viewport == Some(viewport) {
+         return;
+      }
+      self.sent_viewport = Some(viewport);
+      self.last_viewport_sent = Instant::now();
+      catch!(self.peer.send_viewport(viewport.0, viewport.1));
+   }
+
+   /// Makes our viewport follow the given peer's viewport, starting with wherever it is now.
+   fn follow(&mut self, peer_id: PeerId) {
+      let Some(mate) = self.peer.mates().get(&peer_id) else {
+         return;
+      };
+      self.following = Some(peer_id);
+      log!(
+         self.log,
+         "{}",
+         self.assets.tr.following_someone.format().with("nickname", mate.nickname.as_str()).done()
+      );
+      if let Some(((x, y), zoom_level)) = mate.viewport {
+         self.viewport.glide_to(point(x as f32, y as f32), zoom_level as f32);
+      }
+   }
+
+   /// Stops following whoever's viewport we're following, if anyone's.
+   fn stop_following(&mut self) {
+      if self.following.take().is_some() {
+         log!(self.log, "{}", self.assets.tr.stopped_following);
+      }
+   }
+
+   /// Reverts the changes the given peer made within the last `period`, and sends the reverted
+   /// chunks out to everyone else in the room.
+   fn revert_changes(&mut self, renderer: &mut Backend, peer_id: PeerId, period: Duration) {
+      let chunks = self.paint_canvas.revert_author(renderer, peer_id, period);
+      for &chunk in &chunks {
+         self.cache_layer.invalidate(chunk);
+      }
+      self.send_chunks_to_page(renderer, &chunks);
+
+      let nickname = match self.peer.mates().get(&peer_id) {
+         Some(mate) => mate.nickname.as_str(),
+         None => self.assets.tr.unknown_host.as_str(),
+      };
+      self.audit_log.record(
+         self.peer.peer_id(),
+         self.peer.nickname(),
+         AuditEventKind::Reverted {
+            nickname: nickname.to_owned(),
+            chunks: chunks.len(),
+         },
+      );
+      log!(
+         self.log,
+         "{}",
+         self
+            .assets
+            .tr
+            .someones_changes_were_reverted
+            .format()
+            .with("nickname", nickname)
+            .with("count", chunks.len())
+            .done()
+      );
+   }
+
+   /// Undoes our own most recent operation. Only the host keeps the operation log, so anyone else
+   /// asks the host to do it for them.
+   fn undo(&mut self, renderer: &mut Backend) {
+      self.mark_history(true);
+      if self.peer.is_host() {
+         self.undo_operation(renderer, None);
+      } else {
+         // If the host hasn't been told about our last operation yet, it must be before the undo
+         // request arrives, as that's the operation that's supposed to be undone.
+         if self.paint_canvas.take_pending_commit() {
+            catch!(self.peer.send_commit_operation());
+         }
+         catch!(self.peer.send_undo());
+      }
+   }
+
+   /// Undoes the most recent operation of the given author, where `None` stands for ourselves,
+   /// and sends the affected chunks out to everyone else in the room.
+   fn undo_operation(&mut self, renderer: &mut Backend, author: Option<PeerId>) {
+      self.set_canvas_author(author);
+      let positions = self.paint_canvas.undo_operation(renderer);
+      let removed_vector_strokes = self.paint_canvas.take_removed_vector_strokes();
+      if !removed_vector_strokes.is_empty() && !self.peer.is_offline() {
+         catch!(self.peer.send_remove_vector_strokes(removed_vector_strokes));
+      }
+      if positions.is_empty() {
+         if author.is_none() {
+            log!(self.log, "{}", self.assets.tr.nothing_to_undo);
+         }
+         return;
+      }
+      let layer = self.paint_canvas.current_layer();
+      let chunks: Vec<_> = positions.into_iter().map(|position| (layer, position)).collect();
+      for &chunk in &chunks {
+         self.cache_layer.invalidate(chunk);
+      }
+      if !self.peer.is_offline() {
+         self.send_chunks_to_page(renderer, &chunks);
+      }
+   }
+
+   /// Redoes our own most recently undone operation. Like undoing, this is carried out by the host.
+   fn redo(&mut self, renderer: &mut Backend) {
+      self.mark_history(false);
+      if self.peer.is_host() {
+         self.redo_operation(renderer, None);
+      } else {
+         catch!(self.peer.send_redo());
+      }
+   }
+
+   /// Redoes the most recently undone operation of the given author, where `None` stands for
+   /// ourselves, and sends the affected chunks out to everyone else in the room.
+   fn redo_operation(&mut self, renderer: &mut Backend, author: Option<PeerId>) {
+      self.set_canvas_author(author);
+      let positions = self.paint_canvas.redo_operation(renderer);
+      if self.paint_canvas.take_vector_strokes_restored() && !self.peer.is_offline() {
+         // Strokes can only be sent all at once, as that's how newcomers receive them.
+         if let Some(vector_log) = self.paint_canvas.vector_log() {
+            let strokes: Vec<_> = vector_log.strokes().cloned().collect();
+            catch!(self.peer.send_vector_strokes(PeerId::BROADCAST, strokes));
+         }
+      }
+      if positions.is_empty() {
+         if author.is_none() {
+            log!(self.log, "{}", self.assets.tr.nothing_to_redo);
+         }
+         return;
+      }
+      let layer = self.paint_canvas.current_layer();
+      let chunks: Vec<_> = positions.into_iter().map(|position| (layer, position)).collect();
+      for &chunk in &chunks {
+         self.cache_layer.invalidate(chunk);
+      }
+      if !self.peer.is_offline() {
+         self.send_chunks_to_page(renderer, &chunks);
+      }
+   }
+
+   /// Opens the audit log window if it's closed, or closes it if it's open.
+   fn toggle_audit_log_window(&mut self) {
+      if let Some(window_id) = self.audit_log_window.take() {
+         self.wm.close_window(window_id);
+      } else {
+         let content = AuditLogWindow.background().buttons(WindowButtonStyle {
+            padding: Padding::even(12.0),
+         });
+         let window_id = self
+            .wm
+            .open_window(
+               self.audit_log_window_view.clone(),
+               content,
+               AuditLogWindowData::new(),
+            )
+            .finish();
+         self.audit_log_window = Some(window_id);
+      }
+   }
+
+   /// Records an audit event, if we're the host.
+   fn record_audit_event(&mut self, AuditEvent { author, kind }: AuditEvent) {
+      if !self.peer.is_host() {
+         return;
+      }
+      match author {
+         Some(peer_id) => {
+            let nickname = match self.peer.mates().get(&peer_id) {
+               Some(mate) => mate.nickname.as_str(),
+               None => self.assets.tr.unknown_host.as_str(),
+            };
+            self.audit_log.record(Some(peer_id), nickname, kind);
+         }
+         None => self.audit_log.record(self.peer.peer_id(), self.peer.nickname(), kind),
+      }
+   }
+
+   /// Keeps the audit log window's entries up to date, and exports the log when requested.
+   fn process_audit_log_window(&mut self) {
+      let window_id = match &self.audit_log_window {
+         Some(window_id) => window_id,
+         None => return,
+      };
+      // The audit log is only kept by the host, so close the window if we lose that role.
+      if self.wm.should_close(window_id) || !self.peer.is_host() {
+         self.toggle_audit_log_window();
+         return;
+      }
+
+      let data = self.wm.window_data_mut(window_id);
+      data.entries = self.audit_log.latest(AuditLogWindow::VISIBLE_ENTRIES).cloned().collect();
+      let export_requested = std::mem::take(&mut data.export_requested);
+      if !self.wm.pinned(window_id) {
+         self.wm.view_mut(window_id).position = self.audit_log_window_view.position;
+      }
+
+      if export_requested {
+         let dialog = AsyncFileDialog::new().add_filter(&self.assets.tr.fd_csv_file, &["csv"]);
+         file_dialog::save_file(dialog, ExportAuditLog);
+      }
+   }
+
+   /// Performs the overflow menu action with the given index, logging any errors it runs into.
+   fn perform_action(&mut self, renderer: &mut Backend, index: usize) {
+      let action = &mut self.actions[index];
+      telemetry::feature_used(&format!("action-{}", action.name()));
+      if let Err(error) = action.perform(ActionArgs {
+         assets: &self.assets,
+         paint_canvas: &mut self.paint_canvas,
+         project_file: &mut self.project_file,
+         pages: project_pages(&mut self.pages, self.current_page, self.peer.is_host()),
+         renderer,
+      }) {
+         log!(
+            self.log,
+            "{}",
+            self
+               .assets
+               .tr
+               .error_while_performing_action
+               .format()
+               .with("error", error.translate(&self.assets.language))
+               .done()
+         );
+      }
+   }
+
+   /// Opens the Keyboard shortcuts window if it's closed, or closes it if it's open.
+   fn toggle_keyboard_shortcuts_window(&mut self) {
+      if let Some(window_id) = self.keyboard_shortcuts_window.take() {
+         self.wm.close_window(window_id);
+      } else {
+         let content = KeyboardShortcutsWindow.background().buttons(WindowButtonStyle {
+            padding: Padding::even(12.0),
+         });
+         let window_id = self
+            .wm
+            .open_window(
+               self.keyboard_shortcuts_window_view.clone(),
+               content,
+               KeyboardShortcutsWindowData::new(),
+            )
+            .finish();
+         self.keyboard_shortcuts_window = Some(window_id);
+      }
+   }
+
+   /// Resizes the Keyboard shortcuts window to fit the section that's shown.
+   fn process_keyboard_shortcuts_window(&mut self) {
+      let window_id = match &self.keyboard_shortcuts_window {
+         Some(window_id) => window_id,
+         None => return,
+      };
+      if self.wm.should_close(window_id) {
+         self.toggle_keyboard_shortcuts_window();
+         return;
+      }
+
+      let dimensions = KeyboardShortcutsWindow::dimensions(self.wm.window_data_mut(window_id));
+      let pinned = self.wm.pinned(window_id);
+      let view = self.wm.view_mut(window_id);
+      view.dimensions = dimensions;
+      if !pinned {
+         view.position = self.keyboard_shortcuts_window_view.position;
+      }
+   }
+
+   /// Opens the debug console if it's closed, or closes it if it's open.
+   fn toggle_debug_console_window(&mut self) {
+      if let Some(window_id) = self.debug_console_window.take() {
+         self.wm.close_window(window_id);
+      } else {
+         let content = DebugConsoleWindow.background().buttons(WindowButtonStyle {
+            padding: Padding::even(12.0),
+         });
+         let window_id = self
+            .wm
+            .open_window(
+               self.debug_console_window_view.clone(),
+               content,
+               DebugConsoleWindowData::new(),
+            )
+            .finish();
+         self.debug_console_window = Some(window_id);
+      }
+   }
+
+   /// Keeps the debug console's log up to date, and runs the commands entered into it.
+   fn process_debug_console_window(&mut self) {
+      let window_id = match &self.debug_console_window {
+         Some(window_id) => window_id,
+         None => return,
+      };
+      if self.wm.should_close(window_id) {
+         self.toggle_debug_console_window();
+         return;
+      }
+
+      let data = self.wm.window_data_mut(window_id);
+      let commands = std::mem::take(&mut data.commands);
+      if !self.wm.pinned(window_id) {
+         self.wm.view_mut(window_id).position = self.debug_console_window_view.position;
+      }
+      for line in commands {
+         tracing::info!("> {}", line);
+         match ConsoleCommand::parse(&line) {
+            Ok(command) => self.run_console_command(command),
+            Err(error) => tracing::warn!("{}", error),
+         }
+      }
+
+      // The log is refreshed after running the commands, so that their output shows up right away.
+      let window_id = self.debug_console_window.as_ref().unwrap();
+      self.wm.window_data_mut(window_id).records =
+         console_log::latest(DebugConsoleWindow::VISIBLE_RECORDS);
+   }
+
+   /// Runs a command entered into the debug console.
+   fn run_console_command(&mut self, command: ConsoleCommand) {
+      match command {
+         ConsoleCommand::Help => {
+            for usage in ConsoleCommand::USAGE {
+               tracing::info!("{}", usage);
+            }
+         }
+         ConsoleCommand::Clear => console_log::clear(),
+         ConsoleCommand::Dump => self.dump_state(),
+         ConsoleCommand::Resync => {
+            if self.peer.is_host() {
+               tracing::warn!("the host has the canonical canvas, so there is nothing to resync");
+               return;
+            }
+            // Visible chunks that aren't downloaded get queued up again on the next update.
+            for (layer, chunk_position, _) in self.paint_canvas.all_chunks() {
+               self.chunk_downloads.insert((layer, chunk_position), ChunkDownload::NotDownloaded);
+            }
+            for state in self.chunk_downloads.values_mut() {
+               *state = ChunkDownload::NotDownloaded;
+            }
+            tracing::info!(
+               "marked {} chunks for downloading",
+               self.chunk_downloads.len()
+            );
+         }
+         ConsoleCommand::LogLevel(filter) => {
+            catch!(console_log::set_filter(&filter));
+            tracing::info!("log filter set to `{}`", filter);
+         }
+         ConsoleCommand::PacketLoss(fraction) => {
+            self.peer.set_simulated_packet_loss(fraction);
+            tracing::info!("simulating {}% packet loss", fraction * 100.0);
+         }
+         ConsoleCommand::Admin { password, command } => {
+            if self.peer.is_host() {
+               tracing::warn!("admin commands are meant to be sent to the host, not by it");
+               return;
+            }
+            catch!(self.peer.send_admin_command(password, command));
+            tracing::info!("admin command sent, waiting for the host to respond");
+         }
+      }
+   }
+
+   /// Logs the state of the room and the canvas, for the `dump` console command.
+   fn dump_state(&self) {
+      tracing::info!(
+         "we are {:?} ({}), host: {}, offline: {}",
+         self.peer.peer_id(),
+         self.peer.nickname(),
+         self.peer.is_host(),
+         self.peer.is_offline(),
+      );
+      tracing::info!(
+         "room {:?} on {}, host {:?}",
+         self.peer.room_id(),
+         self.peer.relay_address(),
+         self.peer.host_id(),
+      );
+      for (peer_id, mate) in self.peer.mates() {
+         tracing::info!(
+            "mate {:?} ({}), tool: {:?}",
+            peer_id,
+            mate.nickname,
+            mate.tool
          );
-         ui.space(8.0);
+      }
+      let count_downloads = |wanted: ChunkDownload| {
+         self.chunk_downloads.values().filter(|&&state| state == wanted).count()
+      };
+      tracing::info!(
+         "canvas: {} chunks on {} layers, {} locked regions, paused: {}",
+         self.paint_canvas.all_chunks().count(),
+         self.paint_canvas.layers().len(),
+         self.paint_canvas.locked_regions().len(),
+         self.paint_canvas.is_paused(),
+      );
+      tracing::info!(
+         "downloads: {} not downloaded, {} queued, {} requested, {} downloaded",
+         count_downloads(ChunkDownload::NotDownloaded),
+         count_downloads(ChunkDownload::Queued),
+         count_downloads(ChunkDownload::Requested),
+         count_downloads(ChunkDownload::Downloaded),
+      );
+      tracing::info!(
+         "viewport: pan {:?}, zoom {}, tool: {}",
+         self.viewport.pan(),
+         self.viewport.zoom(),
+         self.toolbar.clone_tool_name(self.toolbar.current_tool()),
+      );
+   }
 
-         let id_text = format!("{}", self.peer.room_id().unwrap());
-         ui.push((ui.width(), 32.0), Layout::HorizontalRev);
-         if Button::with_icon(
-            ui,
-            input,
-            &ButtonArgs::new(ui, &self.assets.colors.action_button).corner_radius(4.0),
-            &self.assets.icons.navigation.copy,
+   /// Runs an admin command sent by a peer, and returns the response to send back to them.
+   fn run_admin_command(&mut self, renderer: &mut Backend, command: AdminCommand) -> AdminResponse {
+      let result = match command {
+         AdminCommand::Save => match self.canvas_path() {
+            Some(path) => {
+               self.update_project_metadata();
+               let mut pages =
+                  project_pages(&mut self.pages, self.current_page, self.peer.is_host());
+               self
+                  .project_file
+                  .save(renderer, Some(&path), &mut self.paint_canvas, &mut pages)
+                  .map(|()| AdminResponse::Saved(path.display().to_string()))
+            }
+            None => Ok(AdminResponse::Failed(
+               "the host was not started with a canvas file".to_owned(),
+            )),
+         },
+         AdminCommand::Load(path) => {
+            self.load_canvas(renderer, Path::new(&path)).map(|()| AdminResponse::Loaded(path))
+         }
+         AdminCommand::Status => Ok(AdminResponse::Status(self.admin_status())),
+      };
+      result.unwrap_or_else(|error| {
+         let mut reason = error.translate(&self.assets.language);
+         while reason.len() > MAX_DESCRIPTION_LEN {
+            reason.pop();
+         }
+         AdminResponse::Failed(reason)
+      })
+   }
+
+   /// Returns a number that changes whenever anything is drawn on the canvas, on any page.
+   fn canvas_revision(&self) -> u64 {
+      let stored_pages = self.pages.iter().filter_map(|page| page.contents.as_ref());
+      self
+         .paint_canvas
+         .all_chunks()
+         .map(|(_, _, chunk)| chunk)
+         .chain(stored_pages.flat_map(|contents| contents.canvas.chunks()))
+         .map(Chunk::revision)
+         .fold(0, u64::wrapping_add)
+   }
+
+   /// Saves a snapshot of the canvas to a new autosave, if it's time to and the canvas changed
+   /// since the last one. Only the host's canvas is autosaved, as everyone else's changes are kept
+   /// on the host's canvas.
+   fn autosave(&mut self, renderer: &mut Backend) {
+      let (enabled, interval, keep) = {
+         let config = config();
+         (
+            config.autosave.enabled,
+            config.autosave.interval,
+            config.autosave.keep,
          )
-         .clicked()
-         {
-            {
-               let message = self.assets.tr.room_id_copied.clone();
-               let id_text = id_text.clone();
-               tokio::task::spawn(async move {
-                  catch!(clipboard::copy_string_async(id_text).await);
-                  bus::push(common::Log(message));
-               });
+      };
+      if !enabled
+         || !(self.peer.is_host() || self.peer.is_offline())
+         || self.last_autosave.elapsed() < Duration::from_secs(interval)
+      {
+         return;
+      }
+      self.last_autosave = Instant::now();
+      let revision = self.canvas_revision();
+      if revision == self.autosave_revision {
+         return;
+      }
+
+      tracing::info!("autosaving canvas");
+      self.update_project_metadata();
+      let result = self.autosave_session.new_path().and_then(|path| {
+         let mut pages = project_pages(&mut self.pages, self.current_page, self.peer.is_host());
+         self.project_file.autosave(renderer, &path, &mut self.paint_canvas, &mut pages)?;
+         self.autosave_session.rotate(keep)
+      });
+      match result {
+         Ok(()) => {
+            tracing::info!("autosave complete");
+            self.autosave_revision = revision;
+         }
+         Err(error) => bus::push(Error(error)),
+      }
+   }
+
+   /// Returns whether the canvas has changes that would be lost if NetCanv was closed now.
+   ///
+   /// Only the host's changes count, as everyone else's are kept on the host's canvas. Changes on
+   /// every page count, not just the one that's open.
+   fn has_unsaved_changes(&self) -> bool {
+      let stored_pages = self.pages.iter().filter_map(|page| page.contents.as_ref());
+      (self.peer.is_host() || self.peer.is_offline())
+         && self
+            .paint_canvas
+            .all_chunks()
+            .map(|(_, _, chunk)| chunk)
+            .chain(stored_pages.flat_map(|contents| contents.canvas.chunks()))
+            .any(Chunk::is_dirty)
+   }
+
+   /// Updates the window's title to reflect the room and the canvas, if anything changed.
+   fn update_window_title(&mut self, ui: &mut Ui) {
+      let path = self.canvas_path();
+      let file_name = path.as_deref().and_then(Path::file_name).map(|name| name.to_string_lossy());
+      let title = window_title(Some(&TitleInfo {
+         room_id: self.peer.room_id().map(|room_id| room_id.to_string()),
+         room_name: self.peer.room_name(),
+         file_name: file_name.as_deref(),
+         unsaved: self.has_unsaved_changes(),
+      }));
+      if title != self.window_title {
+         ui.window().set_title(&title);
+         self.window_title = title;
+      }
+   }
+
+   /// Returns the path the canvas is saved to, if any.
+   fn canvas_path(&self) -> Option<PathBuf> {
+      self.project_file.filename().map(Path::to_owned).or_else(|| self.save_path.clone())
+   }
+
+   /// Replaces the canvas and its pages with the ones in the given file, and sends them to
+   /// everyone. If the file can't be loaded, the canvas is left untouched.
+   ///
+   /// Files without pages are loaded onto the current page. Pages that the file doesn't have are
+   /// cleared rather than removed, as people may still be on them.
+   fn load_canvas(&mut self, renderer: &mut Backend, path: &Path) -> netcanv::Result<()> {
+      let mut loaded = PaintCanvas::new();
+      let loaded_pages = self.project_file.load(renderer, path, &mut loaded)?;
+      self.save_path = Some(path.to_owned());
+
+      // Layers beyond the loaded canvas's are removed for everyone. The layers must be known
+      // before any of their chunks arrive.
+      self.set_layers(loaded.layers().to_vec());
+      self.peer.send_layers(PeerId::BROADCAST, self.paint_canvas.layers().to_vec())?;
+      if loaded_pages.is_empty() {
+         self.replace_page_chunks(renderer, &mut loaded);
+      } else {
+         while self.pages.len() < loaded_pages.len() {
+            self.pages.push(Page::new(String::new()));
+         }
+         let mut loaded_pages = loaded_pages.into_iter();
+         for index in 0..self.pages.len() {
+            match loaded_pages.next() {
+               // The first page's chunks are on the loaded canvas already.
+               Some(LoadedPage { name, canvas: None }) => self.pages[index].name = name,
+               Some(LoadedPage {
+                  name,
+                  canvas: Some(mut canvas),
+               }) => {
+                  self.pages[index].name = name;
+                  loaded.swap_page(&mut canvas);
+               }
+               None => loaded.swap_page(&mut CanvasPage::default()),
             }
+            self.with_page(index, |this| {
+               this.replace_page_chunks(renderer, &mut loaded)
+            });
          }
-         ui.horizontal_label(
-            &self.assets.monospace.with_size(24.0),
-            &id_text,
-            self.assets.colors.text,
-            Some((ui.remaining_width(), AlignH::Center)),
+         let names = self.pages.iter().map(|page| page.name.clone()).collect();
+         self.peer.send_pages(PeerId::BROADCAST, names)?;
+      }
+      self.history.clear();
+
+      self.paint_canvas.set_bounds(loaded.bounds());
+      self.paint_canvas.set_background(loaded.background());
+      self.paint_canvas.set_notes(loaded.notes().iter().map(|(&id, note)| (id, note.clone())));
+      self.paint_canvas.set_vector_mode(loaded.vector_mode());
+      if let Some(vector_log) = loaded.vector_log() {
+         self.paint_canvas.set_vector_strokes(vector_log.strokes().cloned().collect());
+      }
+      let notes = self.paint_canvas.notes().iter().map(|(&id, note)| (id, note.clone())).collect();
+      self.peer.send_canvas_bounds(PeerId::BROADCAST, self.paint_canvas.bounds())?;
+      self.peer.send_background(PeerId::BROADCAST, self.paint_canvas.background())?;
+      self.peer.send_notes(PeerId::BROADCAST, notes)?;
+      self.peer.send_vector_mode(PeerId::BROADCAST, self.paint_canvas.vector_mode())?;
+      if let Some(vector_log) = self.paint_canvas.vector_log() {
+         let strokes: Vec<_> = vector_log.strokes().cloned().collect();
+         self.peer.send_vector_strokes(PeerId::BROADCAST, strokes)?;
+      }
+      self.apply_project_metadata();
+      let room_palette = self.global_controls.color_picker.room_palette_rgb();
+      self.peer.send_room_palette(PeerId::BROADCAST, room_palette)?;
+      Ok(())
+   }
+
+   /// Replaces the chunks on the current page with the ones on the loaded canvas, and sends them to
+   /// everyone on the page. Chunks that aren't on the loaded canvas are kept around empty, so that
+   /// everyone else clears them too.
+   fn replace_page_chunks(&mut self, renderer: &mut Backend, loaded: &mut PaintCanvas) {
+      let old_chunks: Vec<_> =
+         self.paint_canvas.all_chunks().map(|(layer, position, _)| (layer, position)).collect();
+      for layer in 0..loaded.layers().len() {
+         std::mem::swap(
+            self.paint_canvas.chunks_mut(layer),
+            loaded.chunks_mut(layer),
          );
-         ui.pop();
+      }
+      for (layer, chunk_position) in old_chunks {
+         let _ = self.paint_canvas.ensure_chunk(renderer, layer, chunk_position);
+      }
+      // Who drew what, and which operations can be undone, no longer applies to the new canvas.
+      self.paint_canvas.set_track_provenance(false);
+      self.paint_canvas.set_track_provenance(true);
+      self.paint_canvas.set_track_operations(false);
+      self.paint_canvas.set_track_operations(true);
+      self.cache_layer = CacheLayer::new();
 
-         ui.fit();
-         ui.pop();
-         ui.space(4.0);
+      let chunks: Vec<_> =
+         self.paint_canvas.all_chunks().map(|(layer, position, _)| (layer, position)).collect();
+      self.send_chunks_to_page(renderer, &chunks);
+   }
 
-         // Room host display
+   /// Updates the metadata saved along with the canvas to match the current state.
+   fn update_project_metadata(&mut self) {
+      let pan = self.viewport.pan();
+      self.project_file.set_metadata(ProjectMetadata {
+         palette: self.global_controls.color_picker.room_palette_rgb(),
+         viewport: Some(SavedViewport {
+            pan: (pan.x, pan.y),
+            zoom_level: self.viewport.zoom_level(),
+         }),
+         room: RoomMetadata {
+            name: self.peer.room_name().to_owned(),
+            description: self.peer.room_description().to_owned(),
+         },
+         bookmarks: self.bookmarks.clone(),
+      });
+   }
 
-         ui.push((ui.width(), 32.0), Layout::Horizontal);
-         ui.icon(
-            if self.peer.is_host() {
-               &self.assets.icons.peer.host
-            } else {
-               &self.assets.icons.peer.client
-            },
-            self.assets.colors.text,
-            Some(vector(ui.height(), ui.height())),
-         );
-         ui.space(4.0);
-         if self.peer.is_host() {
-            ui.horizontal_label(
-               &self.assets.sans,
-               &self.assets.tr.you_are_the_host,
-               self.assets.colors.text,
-               None,
+   /// Restores the viewport, room palette, room info, and bookmarks saved along with the last
+   /// loaded canvas. The room info is only restored if the room wasn't given a name when it was
+   /// created.
+   fn apply_project_metadata(&mut self) {
+      let ProjectMetadata {
+         palette,
+         viewport,
+         room,
+         mut bookmarks,
+      } = self.project_file.metadata().clone();
+      if let Some(SavedViewport { pan, zoom_level }) = viewport {
+         if pan.0.is_finite() && pan.1.is_finite() && zoom_level.is_finite() {
+            self.viewport.center_on(point(pan.0, pan.1));
+            self.viewport.set_zoom_level(zoom_level);
+         }
+      }
+      if !palette.is_empty() {
+         self.global_controls.color_picker.set_room_palette(&palette);
+      }
+      if self.peer.room_name().is_empty() && !room.name.is_empty() {
+         self.peer.set_room_info(&room.name, &room.description);
+      }
+      // The file could've been edited by hand, so bookmarks that couldn't be shared are dropped.
+      bookmarks.retain(|bookmark| bookmark.validate().is_ok());
+      bookmarks.sort_by_key(|bookmark| bookmark.slot);
+      bookmarks.dedup_by_key(|bookmark| bookmark.slot);
+      self.bookmarks = bookmarks;
+      if self.sharing_bookmarks {
+         catch!(self.peer.send_bookmarks(PeerId::BROADCAST, self.bookmarks.clone()));
+      }
+   }
+
+   /// Summarizes the state of the canvas for an admin.
+   fn admin_status(&self) -> AdminStatus {
+      let chunks = self.paint_canvas.all_chunks().count();
+      let unsaved_chunks =
+         self.paint_canvas.all_chunks().filter(|(_, _, chunk)| chunk.is_dirty()).count();
+      AdminStatus {
+         path: self.canvas_path().map(|path| path.display().to_string()),
+         chunks: chunks as u32,
+         unsaved_chunks: unsaved_chunks as u32,
+         people: self.peer.mates().len() as u32,
+         pages: self.pages.len() as u32,
+      }
+   }
+
+   /// Logs the host's response to an admin command we sent.
+   fn log_admin_response(&self, response: AdminResponse) {
+      match response {
+         AdminResponse::Denied => tracing::warn!("the host refused the admin command"),
+         AdminResponse::Failed(reason) => tracing::warn!("admin command failed: {}", reason),
+         AdminResponse::Saved(path) => tracing::info!("the host saved the canvas to {}", path),
+         AdminResponse::Loaded(path) => tracing::info!("the host loaded the canvas from {}", path),
+         AdminResponse::Status(status) => {
+            tracing::info!("canvas file: {}", status.path.as_deref().unwrap_or("none"));
+            tracing::info!(
+               "{} chunks, {} unsaved, {} people, {} pages",
+               status.chunks,
+               status.unsaved_chunks,
+               status.people,
+               status.pages,
             );
+         }
+      }
+   }
+
+   /// Processes the overflow menu.
+   fn process_overflow_menu(&mut self, ui: &mut Ui, input: &mut Input) {
+      if self
+         .overflow_menu
+         .begin(
+            ui,
+            input,
+            ContextMenuArgs {
+               colors: &self.assets.colors.context_menu,
+            },
+         )
+         .is_open()
+      {
+         ui.pad(8.0);
+
+         if self.peer.is_offline() {
+            self.process_offline_info(ui);
          } else {
-            ui.push(
-               (ui.remaining_width(), self.assets.sans.height() * 2.0 + 4.0),
-               Layout::Vertical,
-            );
-            ui.align((AlignH::Right, AlignV::Middle));
-            let name = truncate_text(
-               &self.assets.sans_bold,
-               ui.width(),
-               self.peer.host_name().unwrap_or(&self.assets.tr.unknown_host),
-            );
-            ui.vertical_label(
-               &self.assets.sans_bold,
-               &name,
-               self.assets.colors.text,
-               AlignH::Left,
-            );
-            ui.space(4.0);
-            ui.vertical_label(
-               &self.assets.sans,
-               &self.assets.tr.someone_is_your_host,
-               self.assets.colors.text,
-               AlignH::Left,
-            );
-            ui.pop();
+            self.process_room_info(ui, input);
          }
-         ui.pop();
 
          ui.space(8.0);
          ui.push((ui.width(), 0.0), Layout::Freeform);
@@ -694,55 +3146,275 @@ impl State {
          ui.pop();
          ui.space(8.0);
 
-         for action in &mut self.actions {
-            let action_button = Button::process(
-               ui,
-               input,
-               &ButtonArgs::new(ui, &self.assets.colors.action_button)
-                  .height(32.0)
-                  .corner_radius(4.0),
-               Some(ui.width()),
-               |ui| {
-                  ui.push(ui.size(), Layout::Horizontal);
-                  ui.icon(
-                     action.icon(),
-                     self.assets.colors.text,
-                     Some(vector(ui.height(), ui.height())),
-                  );
-                  ui.space(4.0);
-                  ui.horizontal_label(
-                     &self.assets.sans,
-                     &self.assets.tr.action.get(action.name()),
-                     self.assets.colors.text,
-                     None,
-                  );
-                  ui.pop();
-               },
-            );
-            if action_button.clicked() {
-               if let Err(error) = action.perform(ActionArgs {
-                  assets: &self.assets,
-                  paint_canvas: &mut self.paint_canvas,
-                  project_file: &mut self.project_file,
-                  renderer: ui,
-               }) {
-                  log!(
-                     self.log,
-                     "{}",
-                     self
-                        .assets
-                        .tr
-                        .error_while_performing_action
-                        .format()
-                        .with("error", error.translate(&self.assets.language))
-                        .done()
-                  );
-               }
-            }
-            ui.space(4.0);
-         }
+         let mut performed_action = None;
+         for (index, action) in self.actions.iter().enumerate() {
+            let action_button = Button::process(
+               ui,
+               input,
+               &ButtonArgs::new(ui, &self.assets.colors.action_button)
+                  .height(32.0)
+                  .corner_radius(4.0),
+               Some(ui.width()),
+               |ui| {
+                  ui.push(ui.size(), Layout::Horizontal);
+                  ui.icon(
+                     action.icon(),
+                     self.assets.colors.text,
+                     Some(vector(ui.height(), ui.height())),
+                  );
+                  ui.space(4.0);
+                  ui.horizontal_label(
+                     &self.assets.sans,
+                     &self.assets.tr.action.get(action.name()),
+                     self.assets.colors.text,
+                     None,
+                  );
+                  ui.pop();
+               },
+            );
+            if action_button.clicked() {
+               performed_action = Some(index);
+            }
+            ui.space(4.0);
+         }
+         if let Some(index) = performed_action {
+            self.perform_action(ui, index);
+         }
+
+         self.overflow_menu.end(ui);
+      }
+   }
+
+   /// Processes the room ID and host display at the top of the overflow menu.
+   fn process_room_info(&mut self, ui: &mut Ui, input: &mut Input) {
+      // Room ID display
+
+      ui.push((ui.width(), 0.0), Layout::Vertical);
+      ui.pad((8.0, 0.0));
+      ui.space(8.0);
+
+      // Named rooms show their name in place of the caption.
+      let mut edit_room_info = false;
+      ui.push(
+         (ui.width(), Self::ROOM_CAPTION_HEIGHT),
+         Layout::HorizontalRev,
+      );
+      if self.peer.is_host() {
+         edit_room_info =
+            PeopleWindow::small_button(ui, input, &self.assets, &self.assets.tr.edit_room_info);
+         ui.space(8.0);
+      }
+      let (caption_font, caption) = match self.peer.room_name() {
+         "" => (&self.assets.sans, self.assets.tr.room_id.as_str()),
+         name => (&self.assets.sans_bold, name),
+      };
+      ui.horizontal_label(
+         caption_font,
+         &truncate_text(caption_font, ui.remaining_width(), caption),
+         self.assets.colors.text,
+         Some((ui.remaining_width(), AlignH::Left)),
+      );
+      ui.pop();
+      ui.space(8.0);
+
+      let id_text = format!("{}", self.peer.room_id().unwrap());
+      ui.push((ui.width(), 32.0), Layout::HorizontalRev);
+      if Button::with_icon(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).corner_radius(4.0),
+         &self.assets.icons.navigation.copy,
+      )
+      .clicked()
+      {
+         {
+            let message = self.assets.tr.room_id_copied.clone();
+            let id_text = id_text.clone();
+            tokio::task::spawn(async move {
+               catch!(clipboard::copy_string_async(id_text).await);
+               bus::push(common::Log(message));
+            });
+         }
+      }
+      if Button::with_icon(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).corner_radius(4.0).tooltip(
+            &self.assets.sans,
+            Tooltip::top(&self.assets.tr.copy_room_link),
+         ),
+         &self.assets.icons.navigation.link,
+      )
+      .clicked()
+      {
+         let message = self.assets.tr.room_link_copied.clone();
+         let link = RoomLink::new(self.peer.relay_address(), self.peer.room_id().unwrap());
+         tokio::task::spawn(async move {
+            catch!(clipboard::copy_string_async(link.to_string()).await);
+            bus::push(common::Log(message));
+         });
+      }
+      let id_font = self.assets.monospace.with_size(24.0);
+      let id_color = self.assets.colors.text;
+      if Button::process(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &self.assets.colors.action_button).corner_radius(4.0).tooltip(
+            &self.assets.sans,
+            Tooltip::top(&self.assets.tr.show_qr_code),
+         ),
+         Some(ui.remaining_width()),
+         |ui| {
+            ui.push(ui.size(), Layout::Freeform);
+            ui.text(
+               &id_font,
+               &id_text,
+               id_color,
+               (AlignH::Center, AlignV::Middle),
+            );
+            ui.pop();
+         },
+      )
+      .clicked()
+      {
+         self.toggle_qr_code_overlay();
+      }
+      ui.pop();
+
+      let description = self.peer.room_description();
+      if !description.is_empty() {
+         ui.space(4.0);
+         ui.vertical_label(
+            &self.assets.sans,
+            &truncate_text(&self.assets.sans, ui.width(), description),
+            self.assets.colors.text.with_alpha(160),
+            AlignH::Left,
+         );
+      }
+
+      ui.fit();
+      ui.pop();
+      ui.space(4.0);
+      if edit_room_info {
+         self.toggle_room_info_window();
+      }
+
+      // Room host display
+
+      ui.push((ui.width(), 32.0), Layout::Horizontal);
+      ui.icon(
+         if self.peer.is_host() {
+            &self.assets.icons.peer.host
+         } else {
+            &self.assets.icons.peer.client
+         },
+         self.assets.colors.text,
+         Some(vector(ui.height(), ui.height())),
+      );
+      ui.space(4.0);
+      if self.peer.is_host() {
+         ui.horizontal_label(
+            &self.assets.sans,
+            &self.assets.tr.you_are_the_host,
+            self.assets.colors.text,
+            None,
+         );
+      } else {
+         ui.push(
+            (ui.remaining_width(), self.assets.sans.height() * 2.0 + 4.0),
+            Layout::Vertical,
+         );
+         ui.align((AlignH::Right, AlignV::Middle));
+         let name = truncate_text(
+            &self.assets.sans_bold,
+            ui.width(),
+            self.peer.host_name().unwrap_or(&self.assets.tr.unknown_host),
+         );
+         ui.vertical_label(
+            &self.assets.sans_bold,
+            &name,
+            self.assets.colors.text,
+            AlignH::Left,
+         );
+         ui.space(4.0);
+         ui.vertical_label(
+            &self.assets.sans,
+            &self.assets.tr.someone_is_your_host,
+            self.assets.colors.text,
+            AlignH::Left,
+         );
+         ui.pop();
+      }
+      ui.pop();
+   }
+
+   /// Processes the notice shown at the top of the overflow menu in place of the room ID when
+   /// drawing offline.
+   fn process_offline_info(&mut self, ui: &mut Ui) {
+      ui.push((ui.width(), 0.0), Layout::Vertical);
+      ui.pad((8.0, 0.0));
+      ui.space(8.0);
+      ui.vertical_label(
+         &self.assets.sans_bold,
+         &self.assets.tr.drawing_offline,
+         self.assets.colors.text,
+         AlignH::Left,
+      );
+      ui.space(8.0);
+      ui.paragraph(
+         &self.assets.sans,
+         self.assets.tr.drawing_offline_hint.split('\n'),
+         self.assets.colors.text,
+         AlignH::Left,
+         None,
+      );
+      ui.fit();
+      ui.pop();
+   }
+
+   /// Opens the Room info window if it's closed, or closes it if it's open.
+   fn toggle_room_info_window(&mut self) {
+      if let Some(window_id) = self.room_info_window.take() {
+         self.wm.close_window(window_id);
+      } else {
+         let content = RoomInfoWindow.background().buttons(WindowButtonStyle {
+            padding: Padding::even(12.0),
+         });
+         let data = RoomInfoWindowData::new(self.peer.room_name(), self.peer.room_description());
+         let window_id =
+            self.wm.open_window(self.room_info_window_view.clone(), content, data).finish();
+         self.room_info_window = Some(window_id);
+      }
+   }
+
+   /// Applies the room info saved through the Room info window, and closes the window afterwards.
+   fn process_room_info_window(&mut self) {
+      let window_id = match &self.room_info_window {
+         Some(window_id) => window_id,
+         None => return,
+      };
+      if self.wm.should_close(window_id) {
+         self.toggle_room_info_window();
+         return;
+      }
 
-         self.overflow_menu.end(ui);
+      let saved = self.wm.window_data_mut(window_id).saved.take();
+      if !self.wm.pinned(window_id) {
+         self.wm.view_mut(window_id).position = self.room_info_window_view.position;
+      }
+      if let Some((name, description)) = saved {
+         catch!(self.peer.update_room_info(&name, &description));
+         self.toggle_room_info_window();
+      }
+   }
+
+   /// Shows or hides the QR code of the link to the room.
+   fn toggle_qr_code_overlay(&mut self) {
+      if self.qr_code_overlay.is_some() {
+         self.qr_code_overlay = None;
+      } else if let Some(room_id) = self.peer.room_id() {
+         let link = RoomLink::new(self.peer.relay_address(), room_id);
+         self.qr_code_overlay = QrCodeOverlay::new(&link.to_string());
       }
    }
 
@@ -751,6 +3423,11 @@ impl State {
 
       match message.kind {
          MessageKind::Joined(nickname, peer_id) => {
+            if self.peer.is_host() {
+               self.audit_log.record(Some(peer_id), &nickname, AuditEventKind::Joined);
+            }
+            // Let the newcomer know where our viewport is, in case they want to follow us.
+            self.sent_viewport = None;
             log!(
                self.log,
                "{}",
@@ -763,8 +3440,67 @@ impl State {
                   .done()
             );
             if self.peer.is_host() {
-               let positions = self.paint_canvas.chunk_positions();
-               self.peer.send_chunk_positions(peer_id, positions)?;
+               if self.pages.len() > 1 {
+                  let names = self.pages.iter().map(|page| page.name.clone()).collect();
+                  self.peer.send_pages(peer_id, names)?;
+               }
+               // The layers must be known before any of their chunks arrive.
+               if !self.paint_canvas.has_default_layers() {
+                  self.peer.send_layers(peer_id, self.paint_canvas.layers().to_vec())?;
+               }
+               // Everyone starts out on the first page.
+               self.send_page_chunk_positions(peer_id, 0)?;
+               let locked_regions = self.paint_canvas.locked_regions();
+               if !locked_regions.is_empty() {
+                  let regions = locked_regions.iter().map(|&region| region.into()).collect();
+                  self.peer.send_locked_regions(peer_id, regions)?;
+               }
+               if self.paint_canvas.is_paused() {
+                  self.peer.send_paused(peer_id, true)?;
+               }
+               if let Some(bounds) = self.paint_canvas.bounds() {
+                  self.peer.send_canvas_bounds(peer_id, Some(bounds))?;
+               }
+               let background = self.paint_canvas.background();
+               if !background.is_blank() {
+                  self.peer.send_background(peer_id, background)?;
+               }
+               if self.chunk_encoding != ChunkEncoding::default() {
+                  self.peer.send_chunk_encoding(peer_id, self.chunk_encoding)?;
+               }
+               let room_palette = self.global_controls.color_picker.room_palette_rgb();
+               if !room_palette.is_empty() {
+                  self.peer.send_room_palette(peer_id, room_palette)?;
+               }
+               if self.sharing_bookmarks {
+                  self.peer.send_bookmarks(peer_id, self.bookmarks.clone())?;
+               }
+               let notes = self.paint_canvas.notes();
+               if !notes.is_empty() {
+                  let notes = notes.iter().map(|(&id, note)| (id, note.clone())).collect();
+                  self.peer.send_notes(peer_id, notes)?;
+               }
+               if let Some(vector_log) = self.paint_canvas.vector_log() {
+                  self.peer.send_vector_mode(peer_id, true)?;
+                  let strokes: Vec<_> = vector_log.strokes().cloned().collect();
+                  if !strokes.is_empty() {
+                     self.peer.send_vector_strokes(peer_id, strokes)?;
+                  }
+               }
+               for (&id, image) in self.paint_canvas.floating_images() {
+                  self.peer.send_add_image(
+                     peer_id,
+                     id,
+                     image.placement,
+                     image.data().to_owned(),
+                  )?;
+               }
+            }
+            if self.current_page != 0 {
+               self.peer.send_select_page(peer_id, self.current_page)?;
+            }
+            if self.current_layer != 0 {
+               self.peer.send_select_layer(peer_id, self.current_layer)?;
             }
             // Order matters here! The tool selection packet must arrive before the packets sent
             // from the tool's `network_peer_join` event.
@@ -780,6 +3516,9 @@ impl State {
             nickname,
             last_tool,
          } => {
+            if self.peer.is_host() {
+               self.audit_log.record(Some(peer_id), &nickname, AuditEventKind::Left);
+            }
             log!(
                self.log,
                "{}",
@@ -792,6 +3531,7 @@ impl State {
                   .done()
             );
             // Make sure the tool they were last using is properly deinitialized.
+            self.set_canvas_author(Some(peer_id));
             if let Some(tool) = last_tool {
                if let Some(tool_id) = self.toolbar.tool_by_name(&tool) {
                   self.toolbar.with_tool(tool_id, |tool| {
@@ -804,63 +3544,320 @@ impl State {
                   })?
                }
             }
+            // Whatever they were in the middle of is finished now.
+            self.paint_canvas.commit_operation(ui);
+            if self.following == Some(peer_id) {
+               self.stop_following();
+            }
+            if let Some(voice_chat) = &mut self.voice_chat {
+               voice_chat.remove_peer(peer_id);
+            }
          }
-         MessageKind::NewHost(nickname) => log!(
-            self.log,
-            "{}",
-            self
-               .assets
-               .tr
-               .someone_is_now_hosting_the_room
-               .format()
-               .with("nickname", nickname.as_str())
-               .done()
-         ),
-         MessageKind::NowHosting => {
+         MessageKind::NewHost(nickname) => {
+            log!(
+               self.log,
+               "{}",
+               self
+                  .assets
+                  .tr
+                  .someone_is_now_hosting_the_room
+                  .format()
+                  .with("nickname", nickname.as_str())
+                  .done()
+            );
+            // We might have just handed the host role over to someone else.
+            self.paint_canvas.set_track_provenance(self.peer.is_host());
+            self.paint_canvas.set_track_operations(self.peer.is_host());
+            if !self.peer.is_host() {
+               // We won't be receiving changes to the other pages anymore.
+               for page in &mut self.pages {
+                  page.contents = None;
+               }
+            }
+         }
+         MessageKind::NowHosting { previous_host } => {
             log!(self.log, "{}", self.assets.tr.you_are_now_hosting_the_room);
+            let missing_chunks: Vec<_> = self
+               .chunk_downloads
+               .iter()
+               .filter(|(_, state)| **state != ChunkDownload::Downloaded)
+               .map(|(&chunk, _)| chunk)
+               .collect();
             self.chunk_downloads.clear();
+            self.paint_canvas.set_track_provenance(true);
+            self.paint_canvas.set_track_operations(true);
+            // If the previous host handed the room over to us, they're still around, so we can
+            // fetch the parts of the canvas we haven't seen yet from them.
+            if let Some(previous_host) = previous_host {
+               for (layer, positions) in chunks_by_layer(missing_chunks) {
+                  self.peer.download_chunks_from(previous_host, layer, positions)?;
+               }
+            }
+         }
+         MessageKind::LockedRegions(regions) => {
+            tracing::debug!("received {} locked regions", regions.len());
+            self
+               .paint_canvas
+               .set_locked_regions(regions.into_iter().map(ChunkRegion::from).collect());
+         }
+         MessageKind::Paused(paused) => {
+            tracing::debug!("canvas paused: {}", paused);
+            self.paint_canvas.set_paused(paused);
+         }
+         MessageKind::RoomPalette(colors) => {
+            tracing::debug!("received a room palette of {} colors", colors.len());
+            self.global_controls.color_picker.set_room_palette(&colors);
+         }
+         MessageKind::Bookmarks(bookmarks) => {
+            tracing::debug!("received {} bookmarks", bookmarks.len());
+            self.room_bookmarks = bookmarks;
+         }
+         MessageKind::RoomInfo { name, description } => {
+            // The host sends the room's info once when we join, and again whenever they change it.
+            let message = if self.room_info_received {
+               &self.assets.tr.room_is_now_called
+            } else {
+               &self.assets.tr.welcome_to_room
+            };
+            self.room_info_received = true;
+            if !name.is_empty() {
+               log!(
+                  self.log,
+                  "{}",
+                  message.format().with("name", name.as_str()).done()
+               );
+            }
+            if !description.is_empty() {
+               log!(self.log, "{}", description);
+            }
+         }
+         MessageKind::CommitOperation(peer_id) if self.skips_page_of(peer_id) => (),
+         MessageKind::CommitOperation(peer_id) => {
+            let page = self.page_of(peer_id).unwrap_or(self.current_page);
+            self.with_page(page, |this| {
+               this.set_canvas_author(Some(peer_id));
+               this.paint_canvas.commit_operation(ui);
+            });
+         }
+         MessageKind::Undo(peer_id) if self.drops_changes_of(peer_id) => (),
+         MessageKind::Undo(peer_id) if self.skips_page_of(peer_id) => (),
+         MessageKind::Undo(peer_id) => {
+            let page = self.page_of(peer_id).unwrap_or(self.current_page);
+            self.with_page(page, |this| this.undo_operation(ui, Some(peer_id)));
+         }
+         MessageKind::Redo(peer_id) if self.drops_changes_of(peer_id) => (),
+         MessageKind::Redo(peer_id) if self.skips_page_of(peer_id) => (),
+         MessageKind::Redo(peer_id) => {
+            let page = self.page_of(peer_id).unwrap_or(self.current_page);
+            self.with_page(page, |this| this.redo_operation(ui, Some(peer_id)));
+         }
+         MessageKind::Ping(peer_id, _) if self.is_blocked(peer_id) => (),
+         MessageKind::Ping(peer_id, (x, y)) => {
+            if let Some(mate) = self.peer.mates().get(&peer_id) {
+               let position = point(x as f32, y as f32);
+               let message = self
+                  .assets
+                  .tr
+                  .someone_pinged
+                  .format()
+                  .with("nickname", mate.nickname.as_str())
+                  .done();
+               self.pings.add(position, mate.nickname.clone());
+               self.log.push(LogEntry {
+                  message,
+                  created: Instant::now(),
+                  jump_to: Some(position),
+               });
+            }
+         }
+         MessageKind::SetNote(id, note) => self.paint_canvas.set_note(id, note),
+         MessageKind::RemoveNote(id) => {
+            self.paint_canvas.remove_note(id);
+         }
+         MessageKind::Notes(notes) => self.paint_canvas.set_notes(notes),
+         MessageKind::AddImage(id, placement, data) => {
+            self.paint_canvas.add_floating_image(id, placement, data);
+         }
+         MessageKind::PlaceImage(id, placement) => {
+            self.paint_canvas.place_floating_image(id, placement)
+         }
+         MessageKind::RemoveImage(id) => {
+            self.paint_canvas.remove_floating_image(id);
+         }
+         MessageKind::FlattenImage(peer_id, id) if self.skips_page_of(peer_id) => {
+            // The image is flattened onto a page we don't have, but it's gone either way.
+            self.paint_canvas.remove_floating_image(id);
+         }
+         MessageKind::FlattenImage(peer_id, id) => {
+            let page = self.page_of(peer_id).unwrap_or(self.current_page);
+            self.with_page(page, |this| {
+               this.set_canvas_author(Some(peer_id));
+               catch!(this.paint_canvas.flatten_floating_image(ui, id));
+            });
+         }
+         MessageKind::VectorMode(enabled) => {
+            tracing::debug!("vector strokes turned on: {}", enabled);
+            self.paint_canvas.set_vector_mode(enabled);
+         }
+         MessageKind::VectorStrokes(strokes) => {
+            tracing::debug!("received {} vector strokes", strokes.len());
+            self.paint_canvas.set_vector_strokes(strokes);
+         }
+         MessageKind::RemoveVectorStrokes(ids) => self.paint_canvas.remove_vector_strokes(&ids),
+         MessageKind::CanvasBounds(bounds) => {
+            tracing::debug!("canvas bounds: {:?}", bounds);
+            self.paint_canvas.set_bounds(bounds);
+         }
+         MessageKind::Background(background) => {
+            tracing::debug!("background: {:?}", background);
+            self.paint_canvas.set_background(background);
+         }
+         MessageKind::ChunkEncoding(encoding) => {
+            tracing::debug!("chunk encoding: {:?}", encoding);
+            self.apply_chunk_encoding(encoding);
+         }
+         MessageKind::Pages(names) => {
+            tracing::debug!("received {} pages", names.len());
+            // Pages are never removed, so the list can only ever grow.
+            for (index, name) in names.into_iter().enumerate() {
+               match self.pages.get_mut(index) {
+                  Some(page) => page.name = name,
+                  None => self.pages.push(Page::new(name)),
+               }
+            }
+         }
+         MessageKind::SelectPage { peer_id, page } => {
+            tracing::debug!("{:?} switched to page {}", peer_id, page);
+            if self.peer.is_host() {
+               // Whatever was still being sent to them is from the page they left.
+               self.encoded_chunks.remove(&peer_id);
+               self.send_page_chunk_positions(peer_id, page)?;
+            }
+         }
+         MessageKind::Layers(layers) => {
+            tracing::debug!("received {} layers", layers.len());
+            self.set_layers(layers);
+         }
+         MessageKind::SelectLayer { peer_id, layer } => {
+            tracing::debug!("{:?} switched to layer {}", peer_id, layer);
+         }
+         MessageKind::Viewport {
+            peer_id,
+            pan: (x, y),
+            zoom_level,
+         } => {
+            if self.following == Some(peer_id) {
+               self.viewport.glide_to(point(x as f32, y as f32), zoom_level as f32);
+            }
+         }
+         MessageKind::Voice(peer_id, _) if self.is_blocked(peer_id) => (),
+         MessageKind::Voice(peer_id, frame) => {
+            if let Some(voice_chat) = &mut self.voice_chat {
+               voice_chat.receive(peer_id, &frame)?;
+            }
          }
-         MessageKind::ChunkPositions(positions) => {
-            tracing::debug!("received {} chunk positions", positions.len());
-            for chunk_position in positions {
-               self.chunk_downloads.insert(chunk_position, ChunkDownload::NotDownloaded);
+         MessageKind::AdminCommand(peer_id, command) => {
+            let response = self.run_admin_command(ui, command);
+            self.peer.send_admin_response(peer_id, response)?;
+         }
+         MessageKind::AdminResponse(response) => self.log_admin_response(response),
+         MessageKind::InviteCreated(invite) => {
+            tracing::debug!("created invite {:?}", invite);
+            self.invite = Some(invite);
+         }
+         MessageKind::InviteOnly(invite_only) => self.invite_only = invite_only,
+         MessageKind::ChunkPositions(layer, positions) => {
+            tracing::debug!(
+               "received {} chunk positions on layer {}",
+               positions.len(),
+               layer
+            );
+            self.page_sync_pending = false;
+            if layer < self.paint_canvas.layers().len() {
+               for chunk_position in positions {
+                  self
+                     .chunk_downloads
+                     .insert((layer, chunk_position), ChunkDownload::NotDownloaded);
+               }
             }
             // Make sure we send the tool _after_ adding the requested chunks.
             // This way if something goes wrong here and the function returns Err, at least we
-            // will have queued up some chunk downloads at this point.
-            self
-               .peer
-               .send_select_tool(self.toolbar.clone_tool_name(self.toolbar.current_tool()))?;
+            // will have queued up some chunk downloads at this point. The bottom layer's positions
+            // always come first, so the tool is only sent once.
+            if layer == 0 {
+               self
+                  .peer
+                  .send_select_tool(self.toolbar.clone_tool_name(self.toolbar.current_tool()))?;
+            }
+         }
+         MessageKind::Chunks(_, chunks) if self.page_sync_pending => {
+            tracing::debug!("dropping {} chunks from the previous page", chunks.len());
+         }
+         MessageKind::Chunks(layer, chunks) if layer >= self.paint_canvas.layers().len() => {
+            tracing::debug!(
+               "dropping {} chunks on missing layer {}",
+               chunks.len(),
+               layer
+            );
          }
-         MessageKind::Chunks(chunks) => {
-            tracing::debug!("received {} chunks", chunks.len());
+         MessageKind::Chunks(layer, chunks) => {
+            tracing::debug!("received {} chunks on layer {}", chunks.len(), layer);
             for (chunk_position, image_data) in chunks {
-               self.decode_canvas_data(chunk_position, image_data);
-               self.chunk_downloads.insert(chunk_position, ChunkDownload::Downloaded);
+               // Chunks are only ever supposed to be sent by the host, but if anyone else tries to
+               // overwrite a locked region this way, drop their chunks.
+               if self.peer.is_host() && self.paint_canvas.is_chunk_locked(chunk_position) {
+                  tracing::warn!("dropping chunk {:?} in locked region", chunk_position);
+                  continue;
+               }
+               self.decode_canvas_data((layer, chunk_position), image_data);
+               self.chunk_downloads.insert((layer, chunk_position), ChunkDownload::Downloaded);
             }
          }
-         MessageKind::GetChunks(requester, positions) => {
-            self.encode_chunks(ui, requester, &positions);
+         // Like chunks, digests arriving while switching pages are meant for the previous page.
+         MessageKind::ChunkDigests(..) if self.page_sync_pending => (),
+         MessageKind::ChunkDigests(layer, digests) => {
+            self.check_chunk_digests(ui, layer, digests)?
+         }
+         MessageKind::GetChunks(requester, layer, positions) => {
+            let page = self.page_of(requester).unwrap_or(self.current_page);
+            let chunks: Vec<_> = positions.into_iter().map(|position| (layer, position)).collect();
+            self.with_page(page, |this| {
+               this.encode_chunks(ui, requester, &chunks, false);
+            });
          }
+         // Tool packets carry everything a peer does on the canvas, so dropping them is enough to
+         // ignore blocked people, as well as people on pages we don't keep.
+         MessageKind::Tool(sender, ..) if self.drops_changes_of(sender) => (),
+         MessageKind::Tool(sender, ..) if self.skips_page_of(sender) => (),
          MessageKind::Tool(sender, name, payload) => {
-            if let Some(tool_id) = self.toolbar.tool_by_name(&name) {
-               self.toolbar.with_tool(tool_id, |tool| {
-                  tool.network_receive(
-                     ui,
-                     Net::new(&self.peer),
-                     &mut self.paint_canvas,
-                     sender,
-                     payload.clone(),
-                  )
-               })?;
-            }
+            let page = self.page_of(sender).unwrap_or(self.current_page);
+            self
+               .with_page(page, |this| {
+                  this.set_canvas_author(Some(sender));
+                  if let Some(tool_id) = this.toolbar.tool_by_name(&name) {
+                     this.toolbar.with_tool(tool_id, |tool| {
+                        this.peer.record_received_tool_packet(tool.traffic_kind(&payload));
+                        tool.network_receive(
+                           ui,
+                           Net::new(&this.peer),
+                           &mut this.paint_canvas,
+                           sender,
+                           payload.clone(),
+                        )
+                     })?;
+                  }
+                  Ok::<_, netcanv::Error>(())
+               })
+               .unwrap_or(Ok(()))?;
          }
+         MessageKind::SelectTool { peer_id, .. } if self.drops_changes_of(peer_id) => (),
          MessageKind::SelectTool {
             peer_id: address,
             previous_tool,
             tool,
          } => {
             tracing::debug!("{:?} selected tool {}", address, tool);
+            self.set_canvas_author(Some(address));
             // Deselect the old tool.
             if let Some(tool) = previous_tool {
                if let Some(tool_id) = self.toolbar.tool_by_name(&tool) {
@@ -887,78 +3884,166 @@ impl State {
       Ok(())
    }
 
+   /// Encodes the given chunks and queues them up for sending to the requester.
+   ///
+   /// Empty chunks are skipped unless `include_empty` is set, which is needed when the recipient
+   /// may already have some contents in those chunks that must be cleared.
    fn encode_chunks(
       &mut self,
       renderer: &mut Backend,
       requester: PeerId,
-      positions: &[(i32, i32)],
+      chunks: &[LayerChunk],
+      include_empty: bool,
    ) {
-      let tx = &self
-         .encoded_chunks
-         .entry(requester)
-         .or_insert_with(|| {
-            let (tx, rx) = mpsc::unbounded_channel();
-            EncodeChannels { tx, rx }
-         })
-         .tx;
-      for &chunk_position in positions {
+      let tx = &self.encoded_chunks.entry(requester).or_insert_with(EncodeChannels::new).tx;
+      for &(layer, chunk_position) in chunks {
          tracing::info!(
-            "fetching data for networking transmission of chunk {:?}",
-            chunk_position
+            "fetching data for networking transmission of chunk {:?} on layer {}",
+            chunk_position,
+            layer
          );
-         // If there is a cached image already, there's no point in encoding it all over again.
-         if let Some(chunk) = self.cache_layer.chunk(chunk_position) {
+         let Some(chunk) = self.paint_canvas.chunk(layer, chunk_position) else {
+            continue;
+         };
+         // If there is an up to date cached image already, there's no point in encoding it all over
+         // again. Only the chunks that were drawn to since they were last encoded are re-encoded.
+         let revision = chunk.revision();
+         if let Some(cached) = self.cache_layer.chunk((layer, chunk_position), revision) {
             tracing::debug!("reusing {:?}", chunk_position);
-            let _ = self.encode_channels.tx.send((chunk_position, chunk.to_owned()));
-            let _ = tx.send((chunk_position, chunk.to_owned()));
-         } else if let Some(chunk) = self.paint_canvas.chunk(chunk_position) {
+            let _ = tx.send(((layer, chunk_position), cached.to_owned()));
+         } else {
             // If the chunk's image is empty, there's no point in sending it.
             let image = chunk.download_image(renderer);
-            if Chunk::image_is_empty(&image) {
+            if !include_empty && Chunk::image_is_empty(&image) {
                continue;
             }
-            // Otherwise, we can start encoding the chunk image.
-            let encoded_chunks_tx = self.encode_channels.tx.clone();
-            let tx = tx.clone();
-
-            tokio::spawn(async move {
-               tracing::debug!("encoding image data for chunk {:?}", chunk_position);
-               let image_data = ImageCoder::encode_network_data(image).await;
-               tracing::debug!("encoding done for chunk {:?}", chunk_position);
-               match image_data {
-                  Ok(data) => {
-                     tracing::debug!("sending image data back to main thread");
-                     let _ = encoded_chunks_tx.send((chunk_position, data.clone()));
-                     let _ = tx.send((chunk_position, data));
-                  }
-                  Err(error) => {
-                     tracing::error!(
-                        "error while encoding image for chunk {:?}: {:?}",
-                        chunk_position,
-                        error
-                     );
-                  }
-               }
+            // Otherwise, we can queue the chunk image up for encoding.
+            self.encode_pool.submit(EncodeJob {
+               chunk: (layer, chunk_position),
+               image,
+               revision,
+               encoding: self.chunk_encoding,
+               completion: vec![self.encode_channels.tx.clone(), tx.clone()],
             });
          }
       }
    }
 
    fn reflow_layout(&mut self, root_view: &View) {
-      // The bottom bar and the canvas.
-      view::layout::vertical(
-         root_view,
-         &mut [&mut self.bottom_bar_view, &mut self.canvas_view],
-         DirectionV::BottomToTop,
-      );
+      // The status bar, the bottom bar, the page tabs, and the canvas.
+      if self.has_page_bar() {
+         view::layout::vertical(
+            root_view,
+            &mut [
+               &mut self.status_bar_view,
+               &mut self.bottom_bar_view,
+               &mut self.page_bar_view,
+               &mut self.canvas_view,
+            ],
+            DirectionV::BottomToTop,
+         );
+      } else {
+         view::layout::vertical(
+            root_view,
+            &mut [
+               &mut self.status_bar_view,
+               &mut self.bottom_bar_view,
+               &mut self.canvas_view,
+            ],
+            DirectionV::BottomToTop,
+         );
+      }
       let padded_canvas = view::layout::padded(&self.canvas_view, Self::CANVAS_INNER_PADDING);
 
       // The overflow menu.
+      self.overflow_menu.view.dimensions.vertical =
+         Dimension::Constant(self.overflow_menu_height());
       view::layout::align(
          &padded_canvas,
          &mut self.overflow_menu.view,
          (AlignH::Right, AlignV::Bottom),
       );
+
+      // The People window.
+      view::layout::align(
+         &padded_canvas,
+         &mut self.people_window_view,
+         (AlignH::Right, AlignV::Top),
+      );
+
+      // The Layers window.
+      view::layout::align(
+         &padded_canvas,
+         &mut self.layers_window_view,
+         (AlignH::Left, AlignV::Top),
+      );
+
+      // The History window.
+      view::layout::align(
+         &padded_canvas,
+         &mut self.history_window_view,
+         (AlignH::Left, AlignV::Bottom),
+      );
+
+      // The audit log window.
+      view::layout::align(
+         &padded_canvas,
+         &mut self.audit_log_window_view,
+         (AlignH::Center, AlignV::Middle),
+      );
+
+      // The debug console.
+      view::layout::align(
+         &padded_canvas,
+         &mut self.debug_console_window_view,
+         (AlignH::Center, AlignV::Top),
+      );
+
+      // The Keyboard shortcuts window.
+      view::layout::align(
+         &padded_canvas,
+         &mut self.keyboard_shortcuts_window_view,
+         (AlignH::Center, AlignV::Middle),
+      );
+
+      // The Room info window.
+      view::layout::align(
+         &padded_canvas,
+         &mut self.room_info_window_view,
+         (AlignH::Center, AlignV::Middle),
+      );
+
+      // The network statistics overlay.
+      if let Some(overlay) = &mut self.network_stats_overlay {
+         view::layout::align(
+            &padded_canvas,
+            &mut overlay.view,
+            (AlignH::Right, AlignV::Bottom),
+         );
+      }
+
+      // The performance HUD.
+      if let Some(hud) = &mut self.perf_hud {
+         view::layout::align(&padded_canvas, &mut hud.view, (AlignH::Right, AlignV::Top));
+      }
+
+      // The minimap.
+      if let Some(minimap) = &mut self.minimap {
+         view::layout::align(
+            &padded_canvas,
+            &mut minimap.view,
+            (AlignH::Right, AlignV::Bottom),
+         );
+      }
+
+      // The QR code overlay.
+      if let Some(overlay) = &mut self.qr_code_overlay {
+         view::layout::align(
+            &padded_canvas,
+            &mut overlay.view,
+            (AlignH::Center, AlignV::Middle),
+         );
+      }
    }
 }
 
@@ -975,11 +4060,14 @@ impl AppState for State {
 
       // Autosaving
 
+      let actions_start = Instant::now();
+      self.update_project_metadata();
       for action in &mut self.actions {
          match action.process(ActionArgs {
             assets: &self.assets,
             paint_canvas: &mut self.paint_canvas,
             project_file: &mut self.project_file,
+            pages: project_pages(&mut self.pages, self.current_page, self.peer.is_host()),
             renderer: ui,
          }) {
             Ok(()) => (),
@@ -996,25 +4084,38 @@ impl AppState for State {
             ),
          }
       }
+      self.autosave(ui);
+      perf::record(Metric::Actions, actions_start.elapsed());
 
       // Network
 
+      let network_start = Instant::now();
       catch!(self.peer.communicate(), as Fatal);
       for message in &bus::retrieve_all::<peer::Message>() {
          if message.token == self.peer.token() {
             catch!(self.process_peer_message(ui, message.consume()));
          }
       }
+      if let Some(voice_chat) = &self.voice_chat {
+         while let Some(frame) = voice_chat.next_frame() {
+            catch!(self.peer.send_voice(frame));
+         }
+      }
+      perf::record(Metric::Network, network_start.elapsed());
 
       let needed_chunks: Vec<_> = bus::retrieve_all::<RequestChunkDownload>()
          .into_iter()
          .map(|message| message.consume().0)
          .collect();
-      if !needed_chunks.is_empty() {
-         for &chunk_position in &needed_chunks {
-            self.chunk_downloads.insert(chunk_position, ChunkDownload::Requested);
+      // The host has the whole canvas, so there's nobody to download from. This can happen if we
+      // became the host after the downloads were queued.
+      if !needed_chunks.is_empty() && !self.peer.is_host() {
+         for &chunk in &needed_chunks {
+            self.chunk_downloads.insert(chunk, ChunkDownload::Requested);
+         }
+         for (layer, positions) in chunks_by_layer(needed_chunks) {
+            catch!(self.peer.download_chunks(layer, positions));
          }
-         catch!(self.peer.download_chunks(needed_chunks));
       }
 
       // Error checking
@@ -1040,12 +4141,143 @@ impl AppState for State {
       for _ in &bus::retrieve_all::<Fatal>() {
          self.fatal_error = true;
       }
+      for message in &bus::retrieve_all::<AuditEvent>() {
+         self.record_audit_event(message.consume());
+      }
+      for message in &bus::retrieve_all::<LeaveRoom>() {
+         message.consume();
+         self.leaving = true;
+      }
+      for message in &bus::retrieve_all::<OpenKeyboardShortcuts>() {
+         message.consume();
+         if self.keyboard_shortcuts_window.is_none() {
+            self.toggle_keyboard_shortcuts_window();
+         }
+      }
+      for message in &bus::retrieve_all::<ExportAuditLog>() {
+         let ExportAuditLog(path) = message.consume();
+         if let Err(error) = self.audit_log.export(&path) {
+            bus::push(Error(error));
+         }
+      }
+      for message in &bus::retrieve_all::<ImportDocument>() {
+         let ImportDocument(path) = message.consume();
+         if let Err(error) = self.import_document(ui, &path) {
+            bus::push(Error(error));
+         }
+      }
+      for message in &bus::retrieve_all::<LoadReferenceImage>() {
+         let LoadReferenceImage(path) = message.consume();
+         let opacity = self.reference_opacity_slider.value() / 100.0;
+         match ReferenceImage::load(ui, &path, self.viewport.pan(), opacity) {
+            Ok(image) => self.reference_image = Some(image),
+            Err(error) => log!(
+               self.log,
+               "{}",
+               self
+                  .assets
+                  .tr
+                  .error
+                  .format()
+                  .with("error", error.translate(&self.assets.language).as_ref())
+                  .done()
+            ),
+         }
+      }
+
+      if input.action(config().keymap.debug.console) == (true, true) {
+         self.toggle_debug_console_window();
+      }
+      if input.action(config().keymap.debug.network_stats) == (true, true) {
+         self.network_stats_overlay = match self.network_stats_overlay {
+            Some(_) => None,
+            None => Some(NetworkStatsOverlay::new()),
+         };
+      }
+      if input.action(config().keymap.canvas.toggle_minimap) == (true, true)
+         && !self.wm.has_focus()
+         && !self.toolbar.with_current_tool(|tool| tool.has_keyboard_focus())
+      {
+         self.minimap = match self.minimap {
+            Some(_) => None,
+            None => Some(Minimap::new()),
+         };
+         let show_minimap = self.minimap.is_some();
+         config::write(|config| config.ui.show_minimap = show_minimap);
+      }
+      if !self.wm.has_focus() && !self.toolbar.with_current_tool(|tool| tool.has_keyboard_focus()) {
+         let keymap = config().keymap.canvas.clone();
+         if input.action(keymap.rotate_counterclockwise) == (true, true) {
+            self.viewport.rotate(-Viewport::ROTATION_STEP);
+         }
+         if input.action(keymap.rotate_clockwise) == (true, true) {
+            self.viewport.rotate(Viewport::ROTATION_STEP);
+         }
+         if input.action(keymap.reset_rotation) == (true, true) {
+            self.viewport.set_rotation(0.0);
+         }
+         if input.action(keymap.zoom_in) == (true, true) {
+            self.zoom_by_step(1.0);
+         }
+         if input.action(keymap.zoom_out) == (true, true) {
+            self.zoom_by_step(-1.0);
+         }
+         if input.action(keymap.reset_zoom) == (true, true) {
+            self.viewport.glide_to(self.viewport.pan(), 0.0);
+         }
+         if input.action(keymap.toggle_grid) == (true, true) {
+            config::write(|config| config.grid.enabled = !config.grid.enabled);
+         }
+         if input.action(keymap.toggle_grid_snapping) == (true, true) {
+            let snap = !config().grid.snap;
+            // Snapping only applies while the grid is shown, so the grid is shown along with it.
+            config::write(|config| {
+               config.grid.snap = snap;
+               config.grid.enabled |= snap;
+            });
+            let message = if snap {
+               &self.assets.tr.grid_snapping_enabled
+            } else {
+               &self.assets.tr.grid_snapping_disabled
+            };
+            log!(self.log, "{}", message);
+         }
+      }
+      if !self.wm.has_focus() && !self.toolbar.with_current_tool(|tool| tool.has_keyboard_focus()) {
+         let keymap = config().keymap.windows.clone();
+         if input.action(keymap.people) == (true, true) {
+            self.toggle_people_window();
+         }
+         if input.action(keymap.layers) == (true, true) {
+            self.toggle_layers_window();
+         }
+         if input.action(keymap.history) == (true, true) {
+            self.toggle_history_window();
+         }
+         if input.action(keymap.keyboard_shortcuts) == (true, true) {
+            self.toggle_keyboard_shortcuts_window();
+         }
+      }
+      if input.action(config().keymap.debug.perf_hud) == (true, true) {
+         self.perf_hud = match self.perf_hud {
+            Some(_) => None,
+            None => Some(PerfHud::new()),
+         };
+      }
 
       // Layout
       self.reflow_layout(&root_view);
 
       // Paint canvas
-      self.process_canvas(ui, input);
+      perf::measure(Metric::Canvas, || self.process_canvas(ui, input));
+      self.process_log_links(ui, input);
+      if let Some(overlay) = &mut self.network_stats_overlay {
+         overlay.process(ui, input, &self.assets, &self.peer.stats());
+      }
+      if let Some(hud) = &mut self.perf_hud {
+         hud.process(ui, input, &self.assets);
+      }
+      self.process_minimap(ui, input);
 
       // Bars
       let toolbar_process = self.toolbar.process(
@@ -1060,19 +4292,74 @@ impl AppState for State {
       if let Some((previous_tool, current_tool)) = toolbar_process.switched {
          self.tool_switch_events(ui.render(), previous_tool, current_tool);
       }
+      self.process_people_window(ui);
+      self.process_layers_window(ui);
+      self.process_history_window(ui);
+      self.process_audit_log_window();
+      self.process_debug_console_window();
+      self.process_keyboard_shortcuts_window();
+      self.process_room_info_window();
       // Draw windows over the toolbar, but below the bottom bar.
       self.wm.process(ui, input, &self.assets);
+      if self.has_page_bar() {
+         self.process_page_bar(ui, input);
+      }
       self.process_bar(ui, input);
+      self.process_status_bar(ui, input);
       self.process_overflow_menu(ui, input);
+      if let Some(overlay) = &mut self.qr_code_overlay {
+         if overlay.process(ui, input, &self.assets) {
+            self.qr_code_overlay = None;
+         }
+      }
+
+      self.update_window_title(ui);
    }
 
-   fn next_state(self: Box<Self>, _renderer: &mut Backend) -> Box<dyn AppState> {
+   fn next_state(mut self: Box<Self>, renderer: &mut Backend) -> Box<dyn AppState> {
       if self.fatal_error {
+         renderer.window().set_title(&window_title(None));
+         self.autosave_session.end();
          Box::new(lobby::State::new(self.assets, self.socket_system))
+      } else if self.leaving {
+         renderer.window().set_title(&window_title(None));
+         self.autosave_session.end();
+         let Self {
+            assets,
+            socket_system,
+            mut peer,
+            ..
+         } = *self;
+         // The relay connection is kept around, so that the next room can be entered without
+         // connecting again. A relay on the local network goes away together with our room, so
+         // there's nothing to keep in that case.
+         if peer.is_offline() || peer.is_hosting_on_lan() || peer.leave_room().is_err() {
+            return Box::new(lobby::State::new(assets, socket_system));
+         }
+         Box::new(lobby::State::after_leaving_room(
+            assets,
+            socket_system,
+            peer,
+         ))
       } else {
          self
       }
    }
 
-   fn exit(self: Box<Self>) {}
+   fn confirm_exit(&mut self) -> bool {
+      if !self.has_unsaved_changes() {
+         return true;
+      }
+      let result = MessageDialog::new()
+         .set_title(&self.assets.tr.unsaved_changes_title)
+         .set_description(&self.assets.tr.unsaved_changes_description)
+         .set_level(MessageLevel::Warning)
+         .set_buttons(MessageButtons::YesNo)
+         .show();
+      result == MessageDialogResult::Yes
+   }
+
+   fn exit(mut self: Box<Self>) {
+      self.autosave_session.end();
+   }
 }
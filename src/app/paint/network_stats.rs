@@ -0,0 +1,183 @@
+//! The network statistics overlay. Graphs the traffic of the connection over the last minute,
+//! to help tell whether lag in a room comes from the relay, a peer, or local rendering.
+
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Layout, Rect, Renderer};
+
+use crate::assets::Assets;
+use crate::net::stats::{NetworkStats, Sample, TrafficKind};
+use crate::ui::view::View;
+use crate::ui::{Input, Ui};
+
+pub struct NetworkStatsOverlay {
+   pub view: View,
+}
+
+impl NetworkStatsOverlay {
+   const WIDTH: f32 = 360.0;
+   const PADDING: f32 = 12.0;
+   const LABEL_HEIGHT: f32 = 20.0;
+   const GRAPH_HEIGHT: f32 = 40.0;
+   const SPACING: f32 = 8.0;
+
+   const UPLOAD_COLOR: Color = Color::rgb(0x2196f3);
+   const DOWNLOAD_COLOR: Color = Color::rgb(0x4caf50);
+   const RTT_COLOR: Color = Color::rgb(0xff9800);
+   /// The colors of the packet kinds, in the order of [`TrafficKind::ALL`].
+   const PACKET_COLORS: [Color; 4] = [
+      Color::rgb(0xe91e63),
+      Color::rgb(0x2196f3),
+      Color::rgb(0x4caf50),
+      Color::rgb(0x9e9e9e),
+   ];
+
+   pub fn new() -> Self {
+      let graph = Self::LABEL_HEIGHT + Self::GRAPH_HEIGHT;
+      Self {
+         view: View::new((
+            Self::WIDTH,
+            graph * 3.0 + Self::SPACING * 2.0 + Self::PADDING * 2.0,
+         )),
+      }
+   }
+
+   /// Processes the overlay, showing the given statistics.
+   pub fn process(&mut self, ui: &mut Ui, input: &Input, assets: &Assets, stats: &NetworkStats) {
+      self.view.begin(ui, input, Layout::Vertical);
+      ui.fill_rounded(Color::BLACK.with_alpha(192), 8.0);
+      ui.pad(Self::PADDING);
+
+      let tr = &assets.tr;
+      let history = stats.history();
+      let last = history.back().copied().unwrap_or_default();
+
+      // Throughput.
+      let label = tr
+         .network_stats_throughput
+         .format()
+         .with("up", format_bytes(last.bytes_sent))
+         .with("down", format_bytes(last.bytes_received))
+         .done();
+      Self::label(ui, assets, &label);
+      let max_bytes =
+         history.iter().map(|sample| sample.bytes_sent.max(sample.bytes_received)).max();
+      let max_bytes = max_bytes.unwrap_or(0).max(1) as f32;
+      Self::graph(ui, history, |ui, sample, rect| {
+         let half = rect.width() / 2.0;
+         let up = sample.bytes_sent as f32 / max_bytes;
+         let down = sample.bytes_received as f32 / max_bytes;
+         Self::bar(ui, rect.x(), half, rect, up, Self::UPLOAD_COLOR);
+         Self::bar(ui, rect.x() + half, half, rect, down, Self::DOWNLOAD_COLOR);
+      });
+      ui.space(Self::SPACING);
+
+      // Packets, stacked by kind.
+      let label = tr
+         .network_stats_packets
+         .format()
+         .with("cursor", last.packets(TrafficKind::Cursor))
+         .with("stroke", last.packets(TrafficKind::Stroke))
+         .with("chunk", last.packets(TrafficKind::Chunk))
+         .with("other", last.packets(TrafficKind::Other))
+         .done();
+      Self::label(ui, assets, &label);
+      let max_packets = history.iter().map(|sample| sample.packets.iter().sum::<u32>()).max();
+      let max_packets = max_packets.unwrap_or(0).max(1) as f32;
+      Self::graph(ui, history, |ui, sample, rect| {
+         let mut bottom = rect.bottom();
+         for (&count, &color) in sample.packets.iter().zip(&Self::PACKET_COLORS) {
+            let height = count as f32 / max_packets * rect.height();
+            let bar = Rect::new(
+               point(rect.x(), bottom - height),
+               vector(rect.width(), height),
+            );
+            ui.render().fill(bar, color, 0.0);
+            bottom -= height;
+         }
+      });
+      ui.space(Self::SPACING);
+
+      // Relay round-trip time.
+      let label = match stats.relay_rtt() {
+         Some(rtt) => {
+            tr.network_stats_relay_rtt.format().with("milliseconds", rtt.as_millis() as u64).done()
+         }
+         None => tr.network_stats_relay_rtt_unknown.clone(),
+      };
+      Self::label(ui, assets, &label);
+      let max_rtt = history
+         .iter()
+         .filter_map(|sample| sample.relay_rtt)
+         .max()
+         .map_or(1.0, |rtt| rtt.as_secs_f32().max(0.001));
+      // Samples without a measurement keep showing the previous one, so that the graph doesn't
+      // flicker between pings.
+      let mut previous_rtt = None;
+      Self::graph(ui, history, |ui, sample, rect| {
+         previous_rtt = sample.relay_rtt.or(previous_rtt);
+         if let Some(rtt) = previous_rtt {
+            let height = rtt.as_secs_f32() / max_rtt;
+            Self::bar(ui, rect.x(), rect.width(), rect, height, Self::RTT_COLOR);
+         }
+      });
+
+      self.view.end(ui);
+   }
+
+   /// Draws a line of text above a graph.
+   fn label(ui: &mut Ui, assets: &Assets, text: &str) {
+      ui.push((ui.width(), Self::LABEL_HEIGHT), Layout::Freeform);
+      ui.text(
+         &assets.sans,
+         text,
+         Color::WHITE,
+         (AlignH::Left, AlignV::Middle),
+      );
+      ui.pop();
+   }
+
+   /// Draws a graph with a column for each sample in the history. The newest sample is on the
+   /// right edge.
+   fn graph<'h>(
+      ui: &mut Ui,
+      history: impl IntoIterator<Item = &'h Sample>,
+      mut draw_column: impl FnMut(&mut Ui, &Sample, Rect),
+   ) {
+      ui.push((ui.width(), Self::GRAPH_HEIGHT), Layout::Freeform);
+      ui.fill(Color::WHITE.with_alpha(16));
+      ui.draw(|ui| {
+         let column_width = ui.width() / NetworkStats::HISTORY_LEN as f32;
+         let height = ui.height();
+         let history: Vec<_> = history.into_iter().collect();
+         let first_column = NetworkStats::HISTORY_LEN - history.len();
+         for (i, sample) in history.into_iter().enumerate() {
+            let x = (first_column + i) as f32 * column_width;
+            let column = Rect::new(point(x, 0.0), vector(column_width, height));
+            draw_column(ui, sample, column);
+         }
+      });
+      ui.pop();
+   }
+
+   /// Draws a bar filling the given fraction of a column's height, from the bottom.
+   fn bar(ui: &mut Ui, x: f32, width: f32, column: Rect, fraction: f32, color: Color) {
+      let height = fraction.clamp(0.0, 1.0) * column.height();
+      let rect = Rect::new(point(x, column.bottom() - height), vector(width, height));
+      ui.render().fill(rect, color, 0.0);
+   }
+}
+
+/// Formats a number of bytes with a binary unit prefix.
+fn format_bytes(bytes: u64) -> String {
+   const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+   let mut value = bytes as f64;
+   let mut unit = 0;
+   while value >= 1024.0 && unit < UNITS.len() - 1 {
+      value /= 1024.0;
+      unit += 1;
+   }
+   if unit == 0 {
+      format!("{} {}", bytes, UNITS[0])
+   } else {
+      format!("{:.1} {}", value, UNITS[unit])
+   }
+}
@@ -0,0 +1,107 @@
+//! Sticky notes. Notes are drawn above the canvas at a constant size, no matter how far the
+//! viewport is zoomed in.
+
+use std::collections::BTreeMap;
+
+use netcanv_protocol::client::{NoteId, StickyNote};
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Rect, Renderer, Vector};
+use netcanv_renderer::Font as FontTrait;
+
+use crate::backend::{Backend, Font};
+use crate::viewport::Viewport;
+
+pub const WIDTH: f32 = 160.0;
+pub const HEIGHT: f32 = 112.0;
+const PADDING: f32 = 8.0;
+
+/// The colors a note can have.
+pub const COLORS: [(u8, u8, u8); 4] = [
+   (0xff, 0xf1, 0x76),
+   (0xf4, 0x8f, 0xb1),
+   (0xa5, 0xd6, 0xa7),
+   (0x81, 0xd4, 0xfa),
+];
+
+/// Converts a note's color to a [`Color`].
+pub fn color((r, g, b): (u8, u8, u8)) -> Color {
+   Color::new(r, g, b, 255)
+}
+
+/// Returns the rectangle the note occupies on the screen, relative to the canvas view.
+pub fn rect(note: &StickyNote, viewport: &Viewport, canvas_size: Vector) -> Rect {
+   let (x, y) = note.position;
+   let top_left = viewport.to_screen_space(point(x as f32, y as f32), canvas_size).floor();
+   Rect::new(top_left, vector(WIDTH, HEIGHT))
+}
+
+/// Returns the ID of the topmost note under the given point, if any.
+pub fn note_at(
+   notes: &BTreeMap<NoteId, StickyNote>,
+   position: Vector,
+   viewport: &Viewport,
+   canvas_size: Vector,
+) -> Option<NoteId> {
+   notes.iter().rev().find_map(|(&id, note)| {
+      let rect = rect(note, viewport, canvas_size);
+      let inside = position.x >= rect.left()
+         && position.x < rect.right()
+         && position.y >= rect.top()
+         && position.y < rect.bottom();
+      inside.then_some(id)
+   })
+}
+
+/// Draws all the notes, later ones above earlier ones.
+pub fn draw(
+   renderer: &mut Backend,
+   font: &Font,
+   notes: &BTreeMap<NoteId, StickyNote>,
+   viewport: &Viewport,
+   canvas_size: Vector,
+) {
+   for note in notes.values() {
+      let rect = rect(note, viewport, canvas_size);
+      let shadow = Rect::new(rect.position + vector(2.0, 2.0), rect.size);
+      renderer.fill(shadow, Color::BLACK.with_alpha(32), 2.0);
+      renderer.fill(rect, color(note.color), 2.0);
+
+      renderer.push();
+      renderer.clip(rect);
+      let line_height = (font.height() * 1.2).ceil();
+      let mut y = rect.top() + PADDING;
+      for line in wrap(font, &note.text, WIDTH - PADDING * 2.0) {
+         renderer.text(
+            Rect::new(point(rect.left() + PADDING, y), vector(0.0, line_height)),
+            font,
+            &line,
+            Color::BLACK.with_alpha(220),
+            (AlignH::Left, AlignV::Middle),
+         );
+         y += line_height;
+      }
+      renderer.pop();
+   }
+}
+
+/// Breaks the text up into lines no wider than `width`. Words that don't fit on a line of their
+/// own are left to overflow.
+fn wrap(font: &Font, text: &str, width: f32) -> Vec<String> {
+   let mut lines = Vec::new();
+   let mut line = String::new();
+   for word in text.split_whitespace() {
+      let candidate = if line.is_empty() {
+         word.to_owned()
+      } else {
+         format!("{} {}", line, word)
+      };
+      if font.text_width(&candidate) > width && !line.is_empty() {
+         lines.push(std::mem::replace(&mut line, word.to_owned()));
+      } else {
+         line = candidate;
+      }
+   }
+   if !line.is_empty() {
+      lines.push(line);
+   }
+   lines
+}
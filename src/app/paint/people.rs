@@ -0,0 +1,656 @@
+//! The People window. Lists everyone in the room along with how well they're connected, lets
+//! the user find them on the canvas, and lets the host moderate them.
+
+use netcanv_protocol::client::{Background, ChunkEncoding};
+use netcanv_protocol::relay::{InviteToken, PeerId};
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Layout, Padding, Point};
+use netcanv_renderer::{Font, RenderBackend};
+use web_time::Duration;
+
+use crate::assets::Assets;
+use crate::common::truncate_text;
+use crate::paint_canvas::provenance::Provenance;
+use crate::ui::view::Dimensions;
+use crate::ui::wm::{HitTest, WindowContent, WindowContentArgs};
+use crate::ui::{
+   Button, ButtonArgs, Input, SliderStep, Ui, UiElements, UiInput, ValueSlider, ValueSliderArgs,
+   ValueUnit,
+};
+use crate::voice_chat::VoiceSettings;
+
+/// A single person displayed in the window.
+pub struct Person {
+   pub peer_id: PeerId,
+   pub nickname: String,
+   pub is_host: bool,
+   /// The color the person's cursor is shown with on the minimap.
+   pub color: Color,
+   /// The round-trip time to the person, once it's been measured.
+   pub latency: Option<Duration>,
+   /// Where the person's cursor is on the canvas, if they're on the same page and their tool
+   /// shows it.
+   pub cursor: Option<Point>,
+   /// Whether our viewport follows the person's.
+   pub followed: bool,
+   /// Whether the person is on our local block list.
+   pub blocked: bool,
+   /// How the person's voice is played back, if we're in voice chat.
+   pub voice: Option<VoiceSettings>,
+}
+
+/// An action requested by the user through the window.
+pub enum PeopleRequest {
+   /// Revert all changes the peer made within the given period of time.
+   Revert(PeerId, Duration),
+   /// Open the room's audit log.
+   OpenAuditLog,
+   /// Pause or resume modifications to the canvas.
+   SetPaused(bool),
+   /// Turn vector strokes on or off for the room.
+   SetVectorMode(bool),
+   /// Fix the canvas to the given size, or make it infinite.
+   SetCanvasBounds(Option<(u32, u32)>),
+   /// Change the pattern drawn beneath the canvas.
+   SetBackground(Background),
+   /// Change how chunk images are encoded before they're sent out.
+   SetChunkEncoding(ChunkEncoding),
+   /// Hand the host role over to the peer.
+   MakeHost(PeerId),
+   /// Center the viewport on the given point of the canvas.
+   JumpTo(Point),
+   /// Make our viewport follow the peer's, or stop following if `None`.
+   Follow(Option<PeerId>),
+   /// Add the person with the given nickname to the local block list, or remove them from it.
+   SetBlocked(String, bool),
+   /// Join or leave voice chat.
+   SetVoiceChat(bool),
+   /// Change how the peer's voice is played back.
+   SetVoiceSettings(PeerId, VoiceSettings),
+   /// Create a new invite token. Single-use tokens can only be used to join once; tokens with a
+   /// lifetime stop working once it passes.
+   CreateInvite {
+      single_use: bool,
+      lifetime: Option<Duration>,
+   },
+   /// Copy a link with the most recently created invite token to the clipboard.
+   CopyInvite,
+   /// Revoke all invite tokens created so far.
+   RevokeInvites,
+   /// Set whether the room can only be joined with an invite token.
+   SetInviteOnly(bool),
+   /// Share our own palette with everyone in the room, or stop sharing it if `false`.
+   ShareRoomPalette(bool),
+   /// Share our viewport bookmarks with everyone in the room, or stop sharing them if `false`.
+   ShareBookmarks(bool),
+}
+
+/// Data shared between the People window and the paint state.
+pub struct PeopleWindowData {
+   /// Whether we are the host. Moderation actions are only shown to the host.
+   pub is_host: bool,
+   /// Whether modifications to the canvas are currently paused.
+   pub paused: bool,
+   /// Whether the room keeps brush strokes as vector strokes.
+   pub vector_mode: bool,
+   /// The size of the canvas, if it's bounded.
+   pub canvas_bounds: Option<(u32, u32)>,
+   /// The pattern drawn beneath the canvas.
+   pub background: Background,
+   /// How chunk images are encoded before they're sent out.
+   pub chunk_encoding: ChunkEncoding,
+   /// The most recently created invite token.
+   pub invite: Option<InviteToken>,
+   /// Whether the room can only be joined with an invite token.
+   pub invite_only: bool,
+   /// Whether the room has a shared palette.
+   pub has_room_palette: bool,
+   /// Whether we share our bookmarks with the room.
+   pub sharing_bookmarks: bool,
+   /// Whether we're in voice chat.
+   pub voice_chat: bool,
+   /// Everyone else in the room.
+   pub people: Vec<Person>,
+   /// Actions requested through the window since the last time they were handled.
+   pub requests: Vec<PeopleRequest>,
+}
+
+impl PeopleWindowData {
+   pub fn new() -> Self {
+      Self {
+         is_host: false,
+         paused: false,
+         vector_mode: false,
+         canvas_bounds: None,
+         background: Background::Blank,
+         chunk_encoding: ChunkEncoding::default(),
+         invite: None,
+         invite_only: false,
+         has_room_palette: false,
+         sharing_bookmarks: false,
+         voice_chat: false,
+         people: Vec::new(),
+         requests: Vec::new(),
+      }
+   }
+}
+
+pub struct PeopleWindow {
+   revert_period_slider: ValueSlider,
+}
+
+impl PeopleWindow {
+   const WIDTH: f32 = 520.0;
+   const PADDING: f32 = 12.0;
+   const HEADER_HEIGHT: f32 = 48.0;
+   const ROW_HEIGHT: f32 = 32.0;
+   const SWATCH_RADIUS: f32 = 4.0;
+   /// The sizes the host can pick from when bounding the canvas.
+   const CANVAS_SIZES: [u32; 3] = [1024, 2048, 4096];
+   /// The lossy image qualities the host can pick from.
+   const CHUNK_QUALITIES: [u8; 4] = [40, 60, 80, 95];
+   /// The chunk batch sizes the host can pick from, in kibibytes.
+   const CHUNK_BATCH_SIZES: [u32; 4] = [16, 64, 128, 512];
+   /// How long expiring invites stay valid for.
+   const INVITE_LIFETIME: Duration = Duration::from_secs(60 * 60);
+   /// The width taken up by the pin and close buttons in the top right corner of the window.
+   pub const WINDOW_BUTTONS_WIDTH: f32 = 64.0;
+
+   /// Creates the window content. `revert_label` is the label shown next to the slider that
+   /// controls how far back reverting goes.
+   pub fn new(revert_label: &str) -> Self {
+      Self {
+         revert_period_slider: ValueSlider::new(
+            revert_label,
+            ValueUnit::new(" min", 0),
+            5.0,
+            1.0,
+            Provenance::RETENTION.as_secs_f32() / 60.0,
+            SliderStep::Discrete(1.0),
+         ),
+      }
+   }
+
+   /// Processes a small text button, vertically centered within the current group. Returns whether
+   /// the button was clicked.
+   pub fn small_button(ui: &mut Ui, input: &Input, assets: &Assets, text: &str) -> bool {
+      let height = 24.0;
+      let width = assets.sans.text_width(text) + height;
+      ui.push((width, ui.height()), Layout::Freeform);
+      ui.push((width, height), Layout::Freeform);
+      ui.align((AlignH::Left, AlignV::Middle));
+      let clicked = Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &assets.colors.action_button).height(height).corner_radius(4.0),
+         &assets.sans,
+         text,
+      )
+      .clicked();
+      ui.pop();
+      ui.pop();
+      clicked
+   }
+
+   /// Processes the invite controls shown to the host.
+   fn process_invites(ui: &mut Ui, input: &Input, assets: &Assets, data: &mut PeopleWindowData) {
+      ui.push((ui.width(), Self::ROW_HEIGHT), Layout::HorizontalRev);
+      if let Some(invite) = data.invite {
+         if Self::small_button(ui, input, assets, &assets.tr.copy_invite) {
+            data.requests.push(PeopleRequest::CopyInvite);
+         }
+         ui.space(8.0);
+         ui.horizontal_label(
+            &assets.monospace,
+            &invite.to_string(),
+            assets.colors.text,
+            Some((ui.remaining_width(), AlignH::Left)),
+         );
+      } else {
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.no_invites_yet,
+            assets.colors.text,
+            Some((ui.remaining_width(), AlignH::Left)),
+         );
+      }
+      ui.pop();
+
+      ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+      if Self::small_button(ui, input, assets, &assets.tr.new_single_use_invite) {
+         data.requests.push(PeopleRequest::CreateInvite {
+            single_use: true,
+            lifetime: None,
+         });
+      }
+      ui.space(8.0);
+      if Self::small_button(ui, input, assets, &assets.tr.new_expiring_invite) {
+         data.requests.push(PeopleRequest::CreateInvite {
+            single_use: false,
+            lifetime: Some(Self::INVITE_LIFETIME),
+         });
+      }
+      ui.space(8.0);
+      if Self::small_button(ui, input, assets, &assets.tr.revoke_invites) {
+         data.requests.push(PeopleRequest::RevokeInvites);
+      }
+      ui.pop();
+
+      ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+      let (label, button) = if data.invite_only {
+         (&assets.tr.room_is_invite_only, &assets.tr.allow_room_id)
+      } else {
+         (&assets.tr.room_is_open, &assets.tr.require_invites)
+      };
+      ui.horizontal_label(&assets.sans, label, assets.colors.text, None);
+      ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+      if Self::small_button(ui, input, assets, button) {
+         data.requests.push(PeopleRequest::SetInviteOnly(!data.invite_only));
+      }
+      ui.pop();
+      ui.pop();
+   }
+
+   /// Processes the canvas size controls shown to the host.
+   fn process_canvas_bounds(
+      ui: &mut Ui,
+      input: &Input,
+      assets: &Assets,
+      data: &mut PeopleWindowData,
+   ) {
+      ui.push((ui.width(), Self::ROW_HEIGHT), Layout::HorizontalRev);
+      if data.canvas_bounds.is_some() {
+         if Self::small_button(ui, input, assets, &assets.tr.make_canvas_infinite) {
+            data.requests.push(PeopleRequest::SetCanvasBounds(None));
+         }
+         ui.space(8.0);
+      }
+      for &size in Self::CANVAS_SIZES.iter().rev() {
+         if data.canvas_bounds == Some((size, size)) {
+            continue;
+         }
+         if Self::small_button(ui, input, assets, &format!("{size}²")) {
+            data.requests.push(PeopleRequest::SetCanvasBounds(Some((size, size))));
+         }
+         ui.space(8.0);
+      }
+      let label = match data.canvas_bounds {
+         Some((width, height)) => {
+            assets.tr.canvas_is_bounded.format().with("width", width).with("height", height).done()
+         }
+         None => assets.tr.canvas_is_infinite.clone(),
+      };
+      ui.horizontal_label(
+         &assets.sans,
+         &label,
+         assets.colors.text,
+         Some((ui.remaining_width(), AlignH::Left)),
+      );
+      ui.pop();
+   }
+
+   /// Processes the chunk encoding controls shown to the host.
+   fn process_chunk_encoding(
+      ui: &mut Ui,
+      input: &Input,
+      assets: &Assets,
+      data: &mut PeopleWindowData,
+   ) {
+      let encoding = data.chunk_encoding;
+      let format = assets.tr.chunk_format.get(encoding.format.name());
+      let rows = [
+         (
+            assets.tr.chunk_encoding_format.format().with("format", format).done(),
+            ChunkEncoding {
+               format: encoding.format.next(),
+               ..encoding
+            },
+         ),
+         (
+            assets.tr.chunk_encoding_quality.format().with("quality", encoding.quality).done(),
+            ChunkEncoding {
+               quality: Self::next_preset(&Self::CHUNK_QUALITIES, encoding.quality),
+               ..encoding
+            },
+         ),
+         (
+            assets
+               .tr
+               .chunk_encoding_batch_size
+               .format()
+               .with("size", encoding.max_batch_size / 1024)
+               .done(),
+            ChunkEncoding {
+               max_batch_size: Self::next_preset(
+                  &Self::CHUNK_BATCH_SIZES,
+                  encoding.max_batch_size / 1024,
+               ) * 1024,
+               ..encoding
+            },
+         ),
+      ];
+      for (label, changed) in rows {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         ui.horizontal_label(&assets.sans, &label, assets.colors.text, None);
+         ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+         if Self::small_button(ui, input, assets, &assets.tr.change_chunk_encoding) {
+            data.requests.push(PeopleRequest::SetChunkEncoding(changed));
+         }
+         ui.pop();
+         ui.pop();
+      }
+   }
+
+   /// Returns the smallest preset larger than the value, wrapping around to the first preset.
+   fn next_preset<T: Copy + PartialOrd>(presets: &[T], value: T) -> T {
+      presets.iter().copied().find(|&preset| preset > value).unwrap_or(presets[0])
+   }
+
+   /// Returns the dimensions of the window, given its data.
+   pub fn dimensions(data: &PeopleWindowData) -> Dimensions {
+      // The voice chat controls take up one row.
+      let rows = data.people.len().max(1) as f32 + if data.is_host { 14.0 } else { 1.0 };
+      Dimensions::from((
+         Self::WIDTH,
+         Self::HEADER_HEIGHT + rows * Self::ROW_HEIGHT + Self::PADDING,
+      ))
+   }
+}
+
+impl WindowContent for PeopleWindow {
+   type Data = PeopleWindowData;
+
+   fn process(
+      &mut self,
+      WindowContentArgs {
+         ui,
+         input,
+         assets,
+         hit_test,
+         ..
+      }: &mut WindowContentArgs,
+      data: &mut Self::Data,
+   ) {
+      ui.push(ui.size(), Layout::Vertical);
+
+      // The title bar.
+      ui.push((ui.width(), Self::HEADER_HEIGHT), Layout::HorizontalRev);
+      ui.pad((Self::PADDING, 0.0));
+      // Leave room for the window buttons.
+      ui.space(Self::WINDOW_BUTTONS_WIDTH);
+      if data.is_host {
+         if Self::small_button(ui, input, assets, &assets.tr.audit_log) {
+            data.requests.push(PeopleRequest::OpenAuditLog);
+         }
+         ui.space(8.0);
+      }
+      ui.push((ui.remaining_width(), ui.height()), Layout::Horizontal);
+      if ui.hover(input) {
+         **hit_test = HitTest::Draggable;
+      }
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.people,
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+      ui.pop();
+
+      ui.push(ui.remaining_size(), Layout::Vertical);
+      ui.pad(Padding {
+         top: 0.0,
+         ..Padding::even(Self::PADDING)
+      });
+
+      if data.is_host {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         let (label, button) = if data.paused {
+            (&assets.tr.canvas_is_paused, &assets.tr.resume_canvas)
+         } else {
+            (&assets.tr.pause_canvas_hint, &assets.tr.pause_canvas)
+         };
+         ui.horizontal_label(&assets.sans, label, assets.colors.text, None);
+         ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+         if Self::small_button(ui, input, assets, button) {
+            data.requests.push(PeopleRequest::SetPaused(!data.paused));
+         }
+         ui.pop();
+         ui.pop();
+
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         let (label, button) = if data.vector_mode {
+            (
+               &assets.tr.vector_strokes_are_on,
+               &assets.tr.turn_vector_strokes_off,
+            )
+         } else {
+            (
+               &assets.tr.vector_strokes_hint,
+               &assets.tr.turn_vector_strokes_on,
+            )
+         };
+         ui.horizontal_label(&assets.sans, label, assets.colors.text, None);
+         ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+         if Self::small_button(ui, input, assets, button) {
+            data.requests.push(PeopleRequest::SetVectorMode(!data.vector_mode));
+         }
+         ui.pop();
+         ui.pop();
+
+         Self::process_canvas_bounds(ui, input, assets, data);
+
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         let background = assets.tr.background.get(data.background.name());
+         let label = assets.tr.canvas_background.format().with("background", background).done();
+         ui.horizontal_label(&assets.sans, &label, assets.colors.text, None);
+         ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+         if Self::small_button(ui, input, assets, &assets.tr.change_background) {
+            data.requests.push(PeopleRequest::SetBackground(data.background.next()));
+         }
+         ui.pop();
+         ui.pop();
+
+         Self::process_chunk_encoding(ui, input, assets, data);
+
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Vertical);
+         self.revert_period_slider.process(
+            ui,
+            input,
+            ValueSliderArgs {
+               color: assets.colors.slider,
+               focus: assets.colors.focus_ring,
+               font: &assets.sans,
+               label_width: None,
+               value_width: Some(48.0),
+            },
+         );
+         ui.pop();
+
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::HorizontalRev);
+         if data.has_room_palette {
+            if Self::small_button(ui, input, assets, &assets.tr.stop_sharing_palette) {
+               data.requests.push(PeopleRequest::ShareRoomPalette(false));
+            }
+            ui.space(8.0);
+         }
+         if Self::small_button(ui, input, assets, &assets.tr.share_palette) {
+            data.requests.push(PeopleRequest::ShareRoomPalette(true));
+         }
+         ui.horizontal_label(
+            &assets.sans,
+            if data.has_room_palette {
+               &assets.tr.palette_is_shared
+            } else {
+               &assets.tr.share_palette_hint
+            },
+            assets.colors.text,
+            Some((ui.remaining_width(), AlignH::Left)),
+         );
+         ui.pop();
+
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::HorizontalRev);
+         let (label, button) = if data.sharing_bookmarks {
+            (
+               &assets.tr.bookmarks_are_shared,
+               &assets.tr.stop_sharing_bookmarks,
+            )
+         } else {
+            (&assets.tr.share_bookmarks_hint, &assets.tr.share_bookmarks)
+         };
+         if Self::small_button(ui, input, assets, button) {
+            data.requests.push(PeopleRequest::ShareBookmarks(!data.sharing_bookmarks));
+         }
+         ui.horizontal_label(
+            &assets.sans,
+            label,
+            assets.colors.text,
+            Some((ui.remaining_width(), AlignH::Left)),
+         );
+         ui.pop();
+
+         Self::process_invites(ui, input, assets, data);
+      }
+
+      ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+      let (label, button) = if data.voice_chat {
+         (&assets.tr.in_voice_chat, &assets.tr.leave_voice_chat)
+      } else {
+         (&assets.tr.voice_chat_hint, &assets.tr.join_voice_chat)
+      };
+      ui.horizontal_label(&assets.sans, label, assets.colors.text, None);
+      ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+      if Self::small_button(ui, input, assets, button) {
+         data.requests.push(PeopleRequest::SetVoiceChat(!data.voice_chat));
+      }
+      ui.pop();
+      ui.pop();
+
+      if data.people.is_empty() {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.nobody_else_is_here,
+            assets.colors.text,
+            None,
+         );
+         ui.pop();
+      }
+
+      let revert_period = Duration::from_secs(self.revert_period_slider.value() as u64 * 60);
+      for person in &data.people {
+         ui.push((ui.width(), Self::ROW_HEIGHT), Layout::Horizontal);
+         ui.push((Self::SWATCH_RADIUS * 3.0, ui.height()), Layout::Freeform);
+         ui.draw(|ui| {
+            let center = point(Self::SWATCH_RADIUS, ui.height() / 2.0);
+            ui.render().fill_circle(center, Self::SWATCH_RADIUS, person.color);
+         });
+         ui.pop();
+         ui.icon(
+            if person.is_host {
+               &assets.icons.peer.host
+            } else {
+               &assets.icons.peer.client
+            },
+            assets.colors.text,
+            Some(vector(24.0, ui.height())),
+         );
+         ui.space(4.0);
+
+         ui.push((ui.remaining_width(), ui.height()), Layout::HorizontalRev);
+         let block = if person.blocked {
+            &assets.tr.unblock
+         } else {
+            &assets.tr.block
+         };
+         if Self::small_button(ui, input, assets, block) {
+            data.requests.push(PeopleRequest::SetBlocked(
+               person.nickname.clone(),
+               !person.blocked,
+            ));
+         }
+         ui.space(8.0);
+         if data.is_host {
+            if Self::small_button(ui, input, assets, &assets.tr.revert) {
+               data.requests.push(PeopleRequest::Revert(person.peer_id, revert_period));
+            }
+            ui.space(8.0);
+            if Self::small_button(ui, input, assets, &assets.tr.make_host) {
+               data.requests.push(PeopleRequest::MakeHost(person.peer_id));
+            }
+            ui.space(8.0);
+         }
+         if let Some(cursor) = person.cursor {
+            if Self::small_button(ui, input, assets, &assets.tr.go_to_cursor) {
+               data.requests.push(PeopleRequest::JumpTo(cursor));
+            }
+            ui.space(8.0);
+         }
+         let (follow, following) = if person.followed {
+            (&assets.tr.unfollow, None)
+         } else {
+            (&assets.tr.follow, Some(person.peer_id))
+         };
+         if Self::small_button(ui, input, assets, follow) {
+            data.requests.push(PeopleRequest::Follow(following));
+         }
+         ui.space(8.0);
+         if let Some(voice) = person.voice {
+            let mute = if voice.muted {
+               &assets.tr.unmute
+            } else {
+               &assets.tr.mute
+            };
+            if Self::small_button(ui, input, assets, mute) {
+               let settings = VoiceSettings {
+                  muted: !voice.muted,
+                  ..voice
+               };
+               data.requests.push(PeopleRequest::SetVoiceSettings(person.peer_id, settings));
+            }
+            ui.space(8.0);
+            let volume = format!("{}%", (voice.volume * 100.0).round());
+            if Self::small_button(ui, input, assets, &volume) {
+               let settings = VoiceSettings {
+                  volume: voice.next_volume(),
+                  ..voice
+               };
+               data.requests.push(PeopleRequest::SetVoiceSettings(person.peer_id, settings));
+            }
+            ui.space(8.0);
+         }
+         if let Some(latency) = person.latency {
+            let latency = assets
+               .tr
+               .peer_latency
+               .format()
+               .with("milliseconds", latency.as_millis() as u64)
+               .done();
+            ui.horizontal_label(
+               &assets.sans,
+               &latency,
+               assets.colors.text.with_alpha(128),
+               None,
+            );
+            ui.space(8.0);
+         }
+         let nickname = truncate_text(&assets.sans, ui.remaining_width(), &person.nickname);
+         ui.horizontal_label(
+            &assets.sans,
+            &nickname,
+            if person.blocked {
+               assets.colors.text.with_alpha(128)
+            } else {
+               assets.colors.text
+            },
+            Some((ui.remaining_width(), AlignH::Left)),
+         );
+         ui.pop();
+
+         ui.pop();
+      }
+
+      ui.pop();
+
+      ui.pop();
+   }
+}
@@ -0,0 +1,142 @@
+//! The performance HUD. Shows how long each part of a frame takes, along with a graph of recent
+//! frame times, so that stutters can be pinned down to what caused them.
+
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Layout, Rect, Renderer};
+use web_time::Duration;
+
+use crate::assets::Assets;
+use crate::perf::{self, Metric, FRAME_HISTORY_LEN};
+use crate::ui::view::View;
+use crate::ui::{Input, Ui};
+
+pub struct PerfHud {
+   pub view: View,
+}
+
+impl PerfHud {
+   const WIDTH: f32 = 360.0;
+   const PADDING: f32 = 12.0;
+   const LABEL_HEIGHT: f32 = 20.0;
+   const GRAPH_HEIGHT: f32 = 60.0;
+   const SPACING: f32 = 8.0;
+
+   /// The frame time of a 60 FPS display.
+   const TARGET_FRAME_TIME: Duration = Duration::from_micros(16_667);
+
+   const GOOD_COLOR: Color = Color::rgb(0x4caf50);
+   const SLOW_COLOR: Color = Color::rgb(0xff9800);
+   const STUTTER_COLOR: Color = Color::rgb(0xf44336);
+
+   /// Opens the HUD, and starts collecting measurements for it.
+   pub fn new() -> Self {
+      perf::set_enabled(true);
+      let labels = Metric::ALL.len() as f32 + 1.0;
+      Self {
+         view: View::new((
+            Self::WIDTH,
+            Self::LABEL_HEIGHT * labels + Self::GRAPH_HEIGHT + Self::SPACING + Self::PADDING * 2.0,
+         )),
+      }
+   }
+
+   /// Processes the HUD.
+   pub fn process(&mut self, ui: &mut Ui, input: &Input, assets: &Assets) {
+      let snapshot = perf::snapshot();
+      let tr = &assets.tr;
+
+      self.view.begin(ui, input, Layout::Vertical);
+      ui.fill_rounded(Color::BLACK.with_alpha(192), 8.0);
+      ui.pad(Self::PADDING);
+
+      // Frame times.
+      let frame_count = snapshot.frame_times.len().max(1) as u32;
+      let average = snapshot.frame_times.iter().sum::<Duration>() / frame_count;
+      let max = snapshot.frame_times.iter().max().copied().unwrap_or_default();
+      let label = tr
+         .perf_hud_frame_time
+         .format()
+         .with("average", milliseconds(average))
+         .with("max", milliseconds(max))
+         .with(
+            "fps",
+            (1.0 / average.as_secs_f64().max(0.001)).round() as u32,
+         )
+         .done();
+      Self::label(ui, assets, &label);
+      Self::frame_time_graph(ui, &snapshot.frame_times);
+      ui.space(Self::SPACING);
+
+      // Breakdown of the last second.
+      for metric in Metric::ALL {
+         let summary = snapshot.metric(metric);
+         let label = tr
+            .perf_hud_metric
+            .format()
+            .with("name", tr.perf_metric.get(metric.name()))
+            .with("count", summary.count)
+            .with("average", milliseconds(summary.average()))
+            .with("max", milliseconds(summary.max))
+            .done();
+         Self::label(ui, assets, &label);
+      }
+
+      self.view.end(ui);
+   }
+
+   /// Draws a line of text.
+   fn label(ui: &mut Ui, assets: &Assets, text: &str) {
+      ui.push((ui.width(), Self::LABEL_HEIGHT), Layout::Freeform);
+      ui.text(
+         &assets.sans,
+         text,
+         Color::WHITE,
+         (AlignH::Left, AlignV::Middle),
+      );
+      ui.pop();
+   }
+
+   /// Draws a bar for each recent frame, with the newest frame on the right edge. A line marks
+   /// the frame time of a 60 FPS display, so that dropped frames stand out.
+   fn frame_time_graph(ui: &mut Ui, frame_times: &[Duration]) {
+      ui.push((ui.width(), Self::GRAPH_HEIGHT), Layout::Freeform);
+      ui.fill(Color::WHITE.with_alpha(16));
+      ui.draw(|ui| {
+         let max = frame_times.iter().max().copied().unwrap_or_default();
+         let scale = max.max(Self::TARGET_FRAME_TIME * 2).as_secs_f32();
+         let column_width = ui.width() / FRAME_HISTORY_LEN as f32;
+         let height = ui.height();
+         let first_column = FRAME_HISTORY_LEN - frame_times.len();
+         for (i, &time) in frame_times.iter().enumerate() {
+            let color = if time <= Self::TARGET_FRAME_TIME + Duration::from_millis(1) {
+               Self::GOOD_COLOR
+            } else if time <= Self::TARGET_FRAME_TIME * 2 {
+               Self::SLOW_COLOR
+            } else {
+               Self::STUTTER_COLOR
+            };
+            let bar_height = (time.as_secs_f32() / scale).min(1.0) * height;
+            let x = (first_column + i) as f32 * column_width;
+            let bar = Rect::new(
+               point(x, height - bar_height),
+               vector(column_width, bar_height),
+            );
+            ui.render().fill(bar, color, 0.0);
+         }
+         let target_y = height - Self::TARGET_FRAME_TIME.as_secs_f32() / scale * height;
+         let target_line = Rect::new(point(0.0, target_y), vector(ui.width(), 1.0));
+         ui.render().fill(target_line, Color::WHITE.with_alpha(128), 0.0);
+      });
+      ui.pop();
+   }
+}
+
+impl Drop for PerfHud {
+   fn drop(&mut self) {
+      perf::set_enabled(false);
+   }
+}
+
+/// Formats a duration as milliseconds with a single decimal place.
+fn milliseconds(duration: Duration) -> String {
+   format!("{:.1}", duration.as_secs_f64() * 1000.0)
+}
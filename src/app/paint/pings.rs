@@ -0,0 +1,93 @@
+//! Location pings. A ping is a short-lived marker that points everyone's attention to a spot on
+//! the canvas, labeled with the name of whoever placed it.
+
+use netcanv_renderer::paws::{vector, AlignH, AlignV, Color, Point, Rect, Renderer, Vector};
+use netcanv_renderer::{Font as FontTrait, RenderBackend};
+use web_time::{Duration, Instant};
+
+use crate::backend::{Backend, Font};
+use crate::viewport::Viewport;
+
+struct Ping {
+   /// The pinged point, in canvas coordinates.
+   position: Point,
+   nickname: String,
+   created: Instant,
+}
+
+/// The pings currently visible on the canvas.
+pub struct Pings {
+   pings: Vec<Ping>,
+}
+
+impl Pings {
+   /// How long a ping stays on the canvas.
+   const LIFETIME: Duration = Duration::from_secs(3);
+   /// How long it takes for a single ring to expand fully.
+   const RING_PERIOD: f32 = 1.0;
+   const RING_RADIUS: f32 = 48.0;
+   const DOT_RADIUS: f32 = 5.0;
+   const COLOR: Color = Color::rgb(0xff5722);
+
+   pub fn new() -> Self {
+      Self { pings: Vec::new() }
+   }
+
+   /// Places a new ping at the given point on the canvas.
+   pub fn add(&mut self, position: Point, nickname: String) {
+      self.pings.push(Ping {
+         position,
+         nickname,
+         created: Instant::now(),
+      });
+   }
+
+   /// Draws the pings in screen space, and removes the ones that have expired.
+   pub fn draw(
+      &mut self,
+      renderer: &mut Backend,
+      font: &Font,
+      viewport: &Viewport,
+      canvas_size: Vector,
+   ) {
+      self.pings.retain(|ping| ping.created.elapsed() < Self::LIFETIME);
+      for ping in &self.pings {
+         let age = ping.created.elapsed().as_secs_f32();
+         // Fade the whole marker out during the last second.
+         let fade = (Self::LIFETIME.as_secs_f32() - age).clamp(0.0, 1.0);
+         let alpha = |a: f32| (a * fade * 255.0) as u8;
+         let center = viewport.to_screen_space(ping.position, canvas_size);
+
+         // Two rings, half a period apart, expanding outwards from the pinged point.
+         for offset in [0.0, 0.5] {
+            let t = (age / Self::RING_PERIOD + offset).fract();
+            renderer.outline_circle(
+               center,
+               Self::DOT_RADIUS + t * Self::RING_RADIUS,
+               Self::COLOR.with_alpha(alpha(1.0 - t)),
+               2.0,
+            );
+         }
+         renderer.fill_circle(
+            center,
+            Self::DOT_RADIUS + 2.0,
+            Color::WHITE.with_alpha(alpha(1.0)),
+         );
+         renderer.fill_circle(center, Self::DOT_RADIUS, Self::COLOR.with_alpha(alpha(1.0)));
+
+         let padding = vector(4.0, 4.0);
+         let label = Rect::new(
+            center + vector(Self::DOT_RADIUS, Self::DOT_RADIUS) + padding,
+            vector(font.text_width(&ping.nickname), font.height()) + padding * 2.0,
+         );
+         renderer.fill(label, Self::COLOR.with_alpha(alpha(1.0)), 2.0);
+         renderer.text(
+            label,
+            font,
+            &ping.nickname,
+            Color::WHITE.with_alpha(alpha(1.0)),
+            (AlignH::Center, AlignV::Middle),
+         );
+      }
+   }
+}
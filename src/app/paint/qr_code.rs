@@ -0,0 +1,83 @@
+//! The QR code overlay. Shows a QR code of the link to the room, so that people in the same
+//! physical space can join by scanning it.
+
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Layout, Rect, Renderer};
+use qrcodegen::{QrCode, QrCodeEcc};
+
+use crate::assets::Assets;
+use crate::ui::view::View;
+use crate::ui::{Input, MouseButton, Ui, UiElements, UiInput};
+
+pub struct QrCodeOverlay {
+   pub view: View,
+   code: QrCode,
+}
+
+impl QrCodeOverlay {
+   /// The size of the code itself, including the quiet zone.
+   const CODE_SIZE: f32 = 256.0;
+   const PADDING: f32 = 16.0;
+   const CAPTION_HEIGHT: f32 = 32.0;
+   /// The width of the blank margin around the code, in modules. Scanners need it to tell where
+   /// the code begins.
+   const QUIET_ZONE: i32 = 4;
+
+   /// Creates an overlay showing a QR code of the given link. Returns `None` if the link is too
+   /// long to be encoded.
+   pub fn new(link: &str) -> Option<Self> {
+      let code = QrCode::encode_text(link, QrCodeEcc::Medium).ok()?;
+      Some(Self {
+         view: View::new((
+            Self::CODE_SIZE + Self::PADDING * 2.0,
+            Self::CODE_SIZE + Self::CAPTION_HEIGHT + Self::PADDING * 2.0,
+         )),
+         code,
+      })
+   }
+
+   /// Processes the overlay. Returns `true` if it was clicked, and should be closed.
+   pub fn process(&mut self, ui: &mut Ui, input: &mut Input, assets: &Assets) -> bool {
+      self.view.begin(ui, input, Layout::Vertical);
+      let clicked = ui.clicked(input, MouseButton::Left);
+      // The code is always dark on light, no matter the color scheme, as not all scanners can
+      // read inverted codes.
+      ui.fill_rounded(Color::WHITE, 8.0);
+      ui.outline_rounded(assets.colors.separator, 8.0, 1.0);
+      ui.pad(Self::PADDING);
+
+      ui.push((Self::CODE_SIZE, Self::CODE_SIZE), Layout::Freeform);
+      let code = &self.code;
+      ui.draw(|ui| {
+         let modules = code.size() + Self::QUIET_ZONE * 2;
+         // Modules are snapped to whole pixels, so that there are no seams between them.
+         let module_size = (ui.width() / modules as f32).floor();
+         let offset = ((ui.width() - module_size * modules as f32) / 2.0).floor()
+            + module_size * Self::QUIET_ZONE as f32;
+         for y in 0..code.size() {
+            for x in 0..code.size() {
+               if code.get_module(x, y) {
+                  let position = point(
+                     offset + x as f32 * module_size,
+                     offset + y as f32 * module_size,
+                  );
+                  let rect = Rect::new(position, vector(module_size, module_size));
+                  ui.render().fill(rect, Color::BLACK, 0.0);
+               }
+            }
+         }
+      });
+      ui.pop();
+
+      ui.push((ui.width(), Self::CAPTION_HEIGHT), Layout::Freeform);
+      ui.text(
+         &assets.sans,
+         &assets.tr.scan_to_join,
+         Color::BLACK,
+         (AlignH::Center, AlignV::Middle),
+      );
+      ui.pop();
+
+      self.view.end(ui);
+      clicked
+   }
+}
@@ -0,0 +1,83 @@
+//! Reference images. A reference image is shown above or below the canvas for tracing over or
+//! comparing against, but only to the person who loaded it: it's never sent to anyone else, nor
+//! drawn into the chunks.
+
+use std::path::{Path, PathBuf};
+
+use image::io::Reader as ImageReader;
+use image::RgbaImage;
+use netcanv_renderer::paws::{vector, Point, Rect, Renderer, Vector};
+use netcanv_renderer::RenderBackend;
+
+use crate::backend::{Backend, Image};
+
+/// A bus message requesting that the reference image be loaded from the given file.
+pub struct LoadReferenceImage(pub PathBuf);
+
+/// An image overlaid on the canvas, fixed to canvas coordinates.
+pub struct ReferenceImage {
+   pixels: RgbaImage,
+   image: Image,
+   /// The rectangle the image covers, in pixels.
+   rect: Rect,
+   opacity: f32,
+}
+
+impl ReferenceImage {
+   /// Loads a reference image from a file, and places it at its natural size, centered on the
+   /// given point.
+   pub fn load(
+      renderer: &mut Backend,
+      path: &Path,
+      center: Point,
+      opacity: f32,
+   ) -> netcanv::Result<Self> {
+      let pixels = ImageReader::open(path)?.decode()?.into_rgba8();
+      let size = vector(pixels.width() as f32, pixels.height() as f32);
+      let top_left = (center - size / 2.0).floor();
+      let image = Self::upload(renderer, &pixels, opacity);
+      Ok(Self {
+         pixels,
+         image,
+         rect: Rect::new(top_left, size),
+         opacity,
+      })
+   }
+
+   /// Uploads the image to the graphics card, with its alpha channel scaled by the opacity.
+   fn upload(renderer: &mut Backend, pixels: &RgbaImage, opacity: f32) -> Image {
+      let mut pixels = pixels.clone();
+      for pixel in pixels.pixels_mut() {
+         pixel.0[3] = (pixel.0[3] as f32 * opacity).round() as u8;
+      }
+      renderer.create_image_from_rgba(pixels.width(), pixels.height(), &pixels)
+   }
+
+   /// Sets the opacity of the image, from 0 to 1. Changing the opacity uploads the image anew, so
+   /// this does nothing if the opacity stays the same.
+   pub fn set_opacity(&mut self, renderer: &mut Backend, opacity: f32) {
+      if opacity != self.opacity {
+         self.image = Self::upload(renderer, &self.pixels, opacity);
+         self.opacity = opacity;
+      }
+   }
+
+   /// Returns whether the given point on the canvas lies within the image.
+   pub fn contains(&self, position: Point) -> bool {
+      let rect = self.rect;
+      position.x >= rect.left()
+         && position.x < rect.right()
+         && position.y >= rect.top()
+         && position.y < rect.bottom()
+   }
+
+   /// Moves the image by the given vector, in canvas coordinates.
+   pub fn move_by(&mut self, delta: Vector) {
+      self.rect.position += delta;
+   }
+
+   /// Draws the image. The renderer must be transformed to canvas coordinates.
+   pub fn draw(&self, renderer: &mut Backend) {
+      renderer.image(self.rect, &self.image);
+   }
+}
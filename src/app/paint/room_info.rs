@@ -0,0 +1,150 @@
+//! The Room info window. Lets the host rename the room and change its description after it was
+//! created.
+
+use netcanv_renderer::paws::{Layout, Padding};
+
+use crate::common::truncate_graphemes;
+use crate::net::peer::Peer;
+use crate::ui::view::Dimensions;
+use crate::ui::wm::{HitTest, WindowContent, WindowContentArgs};
+use crate::ui::{Button, ButtonArgs, TextField, TextFieldArgs, UiElements, UiInput};
+
+use super::people::PeopleWindow;
+
+/// Data shared between the Room info window and the paint state.
+pub struct RoomInfoWindowData {
+   name_field: TextField,
+   description_field: TextField,
+   /// The name and description saved through the window since the last time they were applied.
+   pub saved: Option<(String, String)>,
+}
+
+impl RoomInfoWindowData {
+   /// Creates the window's data, with the fields filled in with the room's current info.
+   pub fn new(name: &str, description: &str) -> Self {
+      Self {
+         name_field: TextField::new(Some(name)),
+         description_field: TextField::new(Some(description)),
+         saved: None,
+      }
+   }
+}
+
+pub struct RoomInfoWindow;
+
+impl RoomInfoWindow {
+   const WIDTH: f32 = 320.0;
+   const PADDING: f32 = 12.0;
+   const HEADER_HEIGHT: f32 = 48.0;
+   /// The height of a labelled text field set in the regular, 14 pt font.
+   const FIELD_HEIGHT: f32 = 48.0;
+   const BUTTON_HEIGHT: f32 = 32.0;
+
+   /// Returns the dimensions of the window.
+   pub fn dimensions() -> Dimensions {
+      Dimensions::from((
+         Self::WIDTH,
+         Self::HEADER_HEIGHT
+            + Self::FIELD_HEIGHT * 2.0
+            + 8.0
+            + 12.0
+            + Self::BUTTON_HEIGHT
+            + Self::PADDING,
+      ))
+   }
+}
+
+impl WindowContent for RoomInfoWindow {
+   type Data = RoomInfoWindowData;
+
+   fn process(
+      &mut self,
+      WindowContentArgs {
+         ui,
+         input,
+         assets,
+         hit_test,
+         ..
+      }: &mut WindowContentArgs,
+      data: &mut Self::Data,
+   ) {
+      ui.push(ui.size(), Layout::Vertical);
+
+      // The title bar.
+      ui.push((ui.width(), Self::HEADER_HEIGHT), Layout::Horizontal);
+      ui.pad((Self::PADDING, 0.0));
+      ui.push(
+         (ui.width() - PeopleWindow::WINDOW_BUTTONS_WIDTH, ui.height()),
+         Layout::Horizontal,
+      );
+      if ui.hover(input) {
+         **hit_test = HitTest::Draggable;
+      }
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &assets.tr.room_info,
+         assets.colors.text,
+         None,
+      );
+      ui.pop();
+      ui.pop();
+
+      ui.push(ui.remaining_size(), Layout::Vertical);
+      ui.pad(Padding {
+         top: 0.0,
+         ..Padding::even(Self::PADDING)
+      });
+
+      let name = data.name_field.with_label(
+         ui,
+         input,
+         &assets.sans,
+         &assets.tr.lobby_room_name.label,
+         TextFieldArgs {
+            width: ui.width(),
+            colors: &assets.colors.text_field,
+            hint: Some(&assets.tr.lobby_room_name.hint),
+            font: &assets.sans,
+         },
+      );
+      ui.space(8.0);
+      let description = data.description_field.with_label(
+         ui,
+         input,
+         &assets.sans,
+         &assets.tr.lobby_room_description.label,
+         TextFieldArgs {
+            width: ui.width(),
+            colors: &assets.colors.text_field,
+            hint: Some(&assets.tr.lobby_room_description.hint),
+            font: &assets.sans,
+         },
+      );
+      ui.space(12.0);
+
+      ui.push((ui.width(), Self::BUTTON_HEIGHT), Layout::Horizontal);
+      let save = Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &assets.colors.button).height(Self::BUTTON_HEIGHT).pill(),
+         &assets.sans,
+         &assets.tr.save_room_info,
+      )
+      .clicked();
+      ui.pop();
+
+      // Pressing Enter in either field saves, just like the button does.
+      if save || name.done() || description.done() {
+         // Don't let the room info grow past what everyone else would cut it down to anyway.
+         let name = data.name_field.text().trim();
+         let description = data.description_field.text().trim();
+         data.saved = Some((
+            truncate_graphemes(name, Peer::MAX_ROOM_NAME_LEN).to_owned(),
+            truncate_graphemes(description, Peer::MAX_ROOM_DESCRIPTION_LEN).to_owned(),
+         ));
+      }
+
+      ui.pop();
+      ui.pop();
+   }
+}
@@ -0,0 +1,360 @@
+//! The Airbrush tool. Sprays dots of paint around the cursor while the mouse button is held.
+//!
+//! Spraying happens in bursts, one per network update. Rather than sending every dot, each burst
+//! is sent along with the seed its dots were scattered with, so that peers can scatter the exact
+//! same dots on their end.
+
+use std::f32::consts::TAU;
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Layout, Point, Rect, Renderer};
+use netcanv_renderer::{BlendMode, RenderBackend};
+use serde::{Deserialize, Serialize};
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::app::paint::{self, GlobalControls};
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+use crate::common::deserialize_bincode;
+use crate::config::config;
+use crate::keymap::KeyBinding;
+use crate::net::stats::TrafficKind;
+use crate::net::timer::Timer;
+use crate::paint_canvas::PaintCanvas;
+use crate::ui::{
+   view, ButtonState, ColorPicker, ColorPickerArgs, Slider, SliderArgs, SliderStep, UiElements,
+   UiInput,
+};
+use crate::viewport::Viewport;
+use crate::Error;
+
+use super::{Net, Tool, ToolArgs};
+
+pub struct AirbrushTool {
+   icon: Image,
+   radius_slider: Slider,
+   density_slider: Slider,
+   /// Whether the mouse button is held down over the canvas.
+   spraying: bool,
+   /// Paces the bursts, so that they come out at the same rate as network updates.
+   burst_timer: Timer,
+   /// The state of the xorshift generator picking the seeds of bursts.
+   random_state: u64,
+   /// The position of the mouse, in canvas space.
+   mouse_position: Point,
+   /// The bursts sprayed since the last network update.
+   outgoing: Vec<Burst>,
+}
+
+impl AirbrushTool {
+   const MAX_RADIUS: f32 = 128.0;
+   const DEFAULT_RADIUS: f32 = 24.0;
+   /// The maximum number of dots in a single burst.
+   const MAX_DENSITY: f32 = 128.0;
+   const DEFAULT_DENSITY: f32 = 32.0;
+
+   /// Creates an instance of the airbrush tool.
+   pub fn new(renderer: &mut Backend) -> Self {
+      let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+      Self {
+         icon: Assets::load_svg(
+            renderer,
+            include_bytes!("../../../assets/icons/airbrush.svg"),
+         ),
+         radius_slider: Slider::new(
+            Self::DEFAULT_RADIUS,
+            1.0,
+            Self::MAX_RADIUS,
+            SliderStep::Discrete(1.0),
+         ),
+         density_slider: Slider::new(
+            Self::DEFAULT_DENSITY,
+            1.0,
+            Self::MAX_DENSITY,
+            SliderStep::Discrete(1.0),
+         ),
+         spraying: false,
+         burst_timer: Timer::new(paint::State::TIME_PER_UPDATE),
+         // Xorshift gets stuck on zero, so make sure that the seed is never zero.
+         random_state: seed as u64 | 1,
+         mouse_position: point(0.0, 0.0),
+         outgoing: Vec::new(),
+      }
+   }
+
+   /// Sprays a burst of dots around the mouse, with the current settings.
+   fn spray(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas, color: Color) {
+      let burst = Burst {
+         seed: xorshift(&mut self.random_state),
+         center: (self.mouse_position.x, self.mouse_position.y),
+         radius: self.radius_slider.value() as u8,
+         density: self.density_slider.value() as u8,
+         color: (color.r, color.g, color.b, color.a),
+      };
+      Self::draw(renderer, paint_canvas, &burst);
+      self.outgoing.push(burst);
+   }
+
+   /// Draws a burst onto the paint canvas. Unlike brush strokes, translucent bursts are blended
+   /// onto the canvas right away, so that spraying over the same spot builds the paint up.
+   fn draw(renderer: &mut Backend, paint_canvas: &mut PaintCanvas, burst: &Burst) {
+      let center = point(burst.center.0, burst.center.1);
+      let radius = burst.radius as f32;
+      let coverage = Rect::new(
+         center - vector(radius + 1.0, radius + 1.0),
+         vector(radius + 1.0, radius + 1.0) * 2.0,
+      );
+      let (r, g, b, a) = burst.color;
+      let color = Color::new(r, g, b, a);
+      renderer.push();
+      renderer.set_blend_mode(if a == 0 {
+         BlendMode::Replace
+      } else {
+         BlendMode::Alpha
+      });
+      paint_canvas.draw(renderer, coverage, |renderer| {
+         for dot in burst.dots() {
+            renderer.fill(Rect::new(dot, vector(1.0, 1.0)), color, 0.0);
+         }
+      });
+      renderer.pop();
+   }
+}
+
+impl Tool for AirbrushTool {
+   fn name(&self) -> &'static str {
+      "airbrush"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.airbrush
+   }
+
+   /// Finishes spraying, if the tool is switched away from while the mouse button is held.
+   fn deactivate(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas) {
+      if std::mem::take(&mut self.spraying) {
+         paint_canvas.commit_operation(renderer);
+      }
+   }
+
+   /// Handles spraying onto the paint canvas.
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs {
+         ui,
+         input,
+         global_controls,
+         ..
+      }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      self.mouse_position = viewport.to_viewport_space(ui.mouse_position(input), ui.size());
+      let color = if global_controls.color_picker.eraser {
+         Color::TRANSPARENT
+      } else {
+         global_controls.color_picker.color()
+      };
+      match input.action(MouseButton::Left) {
+         (true, ButtonState::Pressed) if ui.hover(input) => {
+            // The first burst comes out right away, so that clicking without holding still leaves
+            // some paint behind.
+            self.spraying = true;
+            self.burst_timer = Timer::new(paint::State::TIME_PER_UPDATE);
+            self.spray(ui, paint_canvas, color);
+            global_controls.color_picker.mark_color_used();
+         }
+         (_, ButtonState::Down) if self.spraying => {
+            self.burst_timer.tick();
+            while self.burst_timer.update() {
+               self.spray(ui, paint_canvas, color);
+            }
+         }
+         (_, ButtonState::Released) if self.spraying => {
+            self.spraying = false;
+            paint_canvas.commit_operation(ui);
+         }
+         _ => (),
+      }
+   }
+
+   /// Draws the circle the dots are sprayed within.
+   fn process_paint_canvas_overlays(
+      &mut self,
+      ToolArgs { ui, input, .. }: ToolArgs,
+      viewport: &Viewport,
+   ) {
+      if input.mouse_active() {
+         let position = viewport.to_screen_space(self.mouse_position, ui.size());
+         let renderer = ui.render();
+         renderer.push();
+         // Same as the brush's guide circle, inverting makes the circle visible on most
+         // backgrounds.
+         renderer.set_blend_mode(BlendMode::Invert);
+         renderer.outline_circle(
+            position,
+            self.radius_slider.value() * viewport.zoom(),
+            Color::WHITE.with_alpha(240),
+            1.0,
+         );
+         renderer.pop();
+      }
+   }
+
+   /// Processes the color picker, and the radius and density sliders on the bottom bar.
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui,
+         input,
+         assets,
+         wm,
+         canvas_view,
+         global_controls,
+         ..
+      }: ToolArgs,
+   ) {
+      let mut picker_window = ColorPicker::picker_window_view();
+      view::layout::align(
+         &view::layout::padded(canvas_view, 16.0),
+         &mut picker_window,
+         (AlignH::Left, AlignV::Bottom),
+      );
+      global_controls.color_picker.process(
+         ui,
+         input,
+         ColorPickerArgs {
+            assets,
+            wm,
+            window_view: picker_window,
+            show_eraser: true,
+         },
+      );
+      ui.space(16.0);
+
+      for (label, slider) in [
+         (&assets.tr.airbrush_radius, &mut self.radius_slider),
+         (&assets.tr.airbrush_density, &mut self.density_slider),
+      ] {
+         ui.horizontal_label(&assets.sans, label, assets.colors.text, None);
+         ui.space(16.0);
+
+         ui.push((128.0, ui.height()), Layout::Freeform);
+         slider.process(
+            ui,
+            input,
+            SliderArgs {
+               width: ui.width(),
+               color: assets.colors.slider,
+               focus: assets.colors.focus_ring,
+            },
+         );
+         ui.pop();
+         ui.space(8.0);
+
+         ui.horizontal_label(
+            &assets.sans_bold,
+            &slider.value().to_string(),
+            assets.colors.text,
+            Some((ui.height(), AlignH::Center)),
+         );
+         ui.space(16.0);
+      }
+   }
+
+   fn traffic_kind(&self, _payload: &[u8]) -> TrafficKind {
+      TrafficKind::Stroke
+   }
+
+   fn network_send(&mut self, net: Net, _global_controls: &GlobalControls) -> netcanv::Result<()> {
+      if !self.outgoing.is_empty() {
+         let packet = Packet::Bursts(self.outgoing.drain(..).collect());
+         net.send(self, PeerId::BROADCAST, packet)?;
+      }
+      Ok(())
+   }
+
+   fn network_receive(
+      &mut self,
+      renderer: &mut Backend,
+      _net: Net,
+      paint_canvas: &mut PaintCanvas,
+      _sender: PeerId,
+      payload: Vec<u8>,
+   ) -> netcanv::Result<()> {
+      let packet: Packet = deserialize_bincode(&payload)?;
+      match packet {
+         Packet::Bursts(bursts) => {
+            for burst in &bursts {
+               // Verify that the packet is correct.
+               ensure!(
+                  burst.center.0.is_finite() && burst.center.1.is_finite(),
+                  Error::InvalidToolPacket
+               );
+               ensure!(
+                  burst.radius as f32 <= Self::MAX_RADIUS,
+                  Error::InvalidToolPacket
+               );
+               ensure!(
+                  burst.density as f32 <= Self::MAX_DENSITY,
+                  Error::InvalidToolPacket
+               );
+               Self::draw(renderer, paint_canvas, burst);
+            }
+         }
+      }
+      Ok(())
+   }
+}
+
+/// Advances the xorshift generator with the given state, and returns its new state.
+fn xorshift(state: &mut u64) -> u64 {
+   let mut x = *state;
+   x ^= x << 13;
+   x ^= x >> 7;
+   x ^= x << 17;
+   *state = x;
+   x
+}
+
+/// A burst of dots sprayed around a point.
+#[derive(Serialize, Deserialize)]
+struct Burst {
+   /// The seed the dots are scattered with.
+   seed: u64,
+   /// The point the dots are scattered around, in canvas space.
+   center: (f32, f32),
+   radius: u8,
+   /// The number of dots in the burst.
+   density: u8,
+   color: (u8, u8, u8, u8),
+}
+
+impl Burst {
+   /// Returns the top-left corners of the burst's dots, spread evenly across its circle.
+   fn dots(&self) -> impl Iterator<Item = Point> + '_ {
+      // Xorshift gets stuck on zero, so the seed has to be nudged away from it.
+      let mut state = self.seed | 1;
+      let mut random = move || (xorshift(&mut state) >> 40) as f32 / (1u64 << 24) as f32;
+      (0..self.density).map(move |_| {
+         let angle = random() * TAU;
+         // Taking the square root keeps the dots from bunching up in the middle.
+         let distance = random().sqrt() * self.radius as f32;
+         point(
+            (self.center.0 + angle.cos() * distance).floor(),
+            (self.center.1 + angle.sin() * distance).floor(),
+         )
+      })
+   }
+}
+
+/// An airbrush tool packet.
+#[derive(Serialize, Deserialize)]
+enum Packet {
+   Bursts(Vec<Burst>),
+}
@@ -1,7 +1,7 @@
 //! The Brush tool. Allows for painting, as well as erasing pixels from the canvas.
 
 use std::collections::HashMap;
-use web_time::Instant;
+use web_time::{Duration, Instant};
 
 use crate::backend::winit::event::MouseButton;
 use crate::config::config;
@@ -14,17 +14,21 @@ use netcanv_renderer::paws::{
 use netcanv_renderer::{BlendMode, Font, RenderBackend};
 use serde::{Deserialize, Serialize};
 
-use crate::app::paint::{self, GlobalControls};
+use crate::app::paint::GlobalControls;
 use crate::assets::Assets;
 use crate::backend::{Backend, Image};
-use crate::common::{deserialize_bincode, lerp_point, ColorMath};
+use crate::common::{catmull_rom, deserialize_bincode, ColorMath};
+use crate::net::stats::TrafficKind;
+use crate::paint_canvas::stroke_layer::{StrokeBlendMode, StrokeLayer};
+use crate::paint_canvas::vector_log::StrokeStyle;
 use crate::paint_canvas::PaintCanvas;
 use crate::ui::{
-   view, ButtonState, ColorPicker, ColorPickerArgs, Modifier, MouseScroll, Slider, SliderArgs,
-   SliderStep, UiElements, UiInput,
+   view, Button, ButtonArgs, ButtonState, ColorPicker, ColorPickerArgs, Input, MouseScroll, Slider,
+   SliderArgs, SliderStep, UiElements, UiInput,
 };
 use crate::viewport::Viewport;
 
+use super::remote_point::RemotePoint;
 use super::{Net, Tool, ToolArgs};
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
@@ -47,10 +51,26 @@ pub struct BrushTool {
    tool: BrushType,
    brush_thickness_slider: Slider,
    eraser_thickness_slider: Slider,
+   brush_opacity_slider: Slider,
+   eraser_opacity_slider: Slider,
+   brush_hardness_slider: Slider,
+   eraser_hardness_slider: Slider,
+   stabilizer_slider: Slider,
+   blend_mode: StrokeBlendMode,
+   /// When the thickness was last changed with a shortcut.
+   thickness_changed: Option<Instant>,
 
    mouse_position: Point,
    previous_mouse_position: Point,
-   stroke_points: Vec<Stroke>,
+   /// The stabilized end of the stroke being drawn, and when it was last moved, in seconds.
+   stabilized: Option<(Point, f32)>,
+   /// The segments drawn since the last network update, along with the stylus pressure at their
+   /// ends, if a stylus was used to draw them.
+   stroke_points: Vec<(Stroke, Option<u8>)>,
+   /// Translucent strokes are drawn here until the mouse button is released.
+   stroke_layer: StrokeLayer,
+   /// Whether a translucent stroke was finished since the last network update.
+   stroke_ended: bool,
 
    peers: HashMap<PeerId, PeerBrush>,
 }
@@ -58,6 +78,19 @@ pub struct BrushTool {
 impl BrushTool {
    const MAX_THICKNESS: f32 = 64.0;
    const DEFAULT_THICKNESS: f32 = 4.0;
+   const MAX_OPACITY: f32 = 100.0;
+   const MAX_HARDNESS: f32 = 100.0;
+   const MAX_STABILIZER: f32 = 100.0;
+   /// How long it takes the stabilized end of a stroke to cover most of the distance to the mouse
+   /// cursor at full stabilizer strength, in seconds.
+   const STABILIZER_LAG: f32 = 0.1;
+   /// The maximum number of lines a soft brush's falloff is drawn with.
+   const MAX_SOFT_RINGS: usize = 16;
+   /// The length of the pieces that strokes received from peers are subdivided into, in pixels.
+   const INTERPOLATION_STEP: f32 = 4.0;
+   const MAX_INTERPOLATION_STEPS: f32 = 32.0;
+   /// How long the thickness is shown next to the cursor after changing it with a shortcut.
+   const THICKNESS_DISPLAY_DURATION: Duration = Duration::from_secs(1);
 
    /// Creates an instance of the brush tool.
    pub fn new(renderer: &mut Backend) -> Self {
@@ -77,9 +110,39 @@ impl BrushTool {
             Self::MAX_THICKNESS,
             SliderStep::Discrete(1.0),
          ),
+         brush_opacity_slider: Slider::new(
+            Self::MAX_OPACITY,
+            1.0,
+            Self::MAX_OPACITY,
+            SliderStep::Discrete(1.0),
+         ),
+         eraser_opacity_slider: Slider::new(
+            Self::MAX_OPACITY,
+            1.0,
+            Self::MAX_OPACITY,
+            SliderStep::Discrete(1.0),
+         ),
+         brush_hardness_slider: Slider::new(
+            Self::MAX_HARDNESS,
+            0.0,
+            Self::MAX_HARDNESS,
+            SliderStep::Discrete(1.0),
+         ),
+         eraser_hardness_slider: Slider::new(
+            Self::MAX_HARDNESS,
+            0.0,
+            Self::MAX_HARDNESS,
+            SliderStep::Discrete(1.0),
+         ),
+         stabilizer_slider: Slider::new(0.0, 0.0, Self::MAX_STABILIZER, SliderStep::Discrete(1.0)),
+         blend_mode: StrokeBlendMode::Normal,
+         thickness_changed: None,
          mouse_position: point(0.0, 0.0),
          previous_mouse_position: point(0.0, 0.0),
+         stabilized: None,
          stroke_points: Vec::new(),
+         stroke_layer: StrokeLayer::new(),
+         stroke_ended: false,
          peers: HashMap::new(),
       }
    }
@@ -92,6 +155,29 @@ impl BrushTool {
       }
    }
 
+   /// Returns the color, hardness (from 0 to 1), and blend mode that strokes are drawn with.
+   ///
+   /// Erasing fully with a hard eraser replaces pixels with transparency outright. Anything softer
+   /// is drawn to the stroke layer, which erases the canvas once it's merged.
+   fn paint(&self, global_controls: &GlobalControls) -> (Color, f32, StrokeBlendMode) {
+      if self.state == BrushState::Drawing && self.tool == BrushType::Brush {
+         let hardness = self.brush_hardness_slider.value() / Self::MAX_HARDNESS;
+         return (self.color(global_controls), hardness, self.blend_mode);
+      }
+      let opacity = self.eraser_opacity_slider.value() / Self::MAX_OPACITY;
+      let hardness = self.eraser_hardness_slider.value() / Self::MAX_HARDNESS;
+      if opacity >= 1.0 && hardness >= 1.0 {
+         (Color::TRANSPARENT, 1.0, StrokeBlendMode::Normal)
+      } else {
+         let alpha = (opacity * 255.0).round() as u8;
+         (
+            Color::WHITE.with_alpha(alpha),
+            hardness,
+            StrokeBlendMode::Erase,
+         )
+      }
+   }
+
    fn set_thickness(&mut self, thickness: f32) {
       match self.tool {
          BrushType::Brush => self.brush_thickness_slider.set_value(thickness),
@@ -106,6 +192,20 @@ impl BrushTool {
       }
    }
 
+   fn opacity_slider(&mut self) -> &mut Slider {
+      match self.tool {
+         BrushType::Brush => &mut self.brush_opacity_slider,
+         BrushType::Eraser => &mut self.eraser_opacity_slider,
+      }
+   }
+
+   fn hardness_slider(&mut self) -> &mut Slider {
+      match self.tool {
+         BrushType::Brush => &mut self.brush_hardness_slider,
+         BrushType::Eraser => &mut self.eraser_hardness_slider,
+      }
+   }
+
    /// Returns the coverage rectangle for the provided point.
    fn point_coverage(p: Point, thickness: f32) -> Rect {
       let half_thickness = thickness / 2.0;
@@ -126,37 +226,215 @@ impl BrushTool {
       Rect::new(point(left, top), vector(right - left, bottom - top))
    }
 
+   /// Draws a stroke segment. Translucent and soft segments, and segments using a blend mode other
+   /// than normal, are drawn to the stroke layer, to be merged onto the paint canvas once the stroke
+   /// is finished; all others are drawn onto the canvas directly.
+   ///
+   /// `hardness` ranges from 0 to 1, and is the fraction of the radius that is fully opaque.
+   #[allow(clippy::too_many_arguments)]
    fn stroke(
-      &self,
       renderer: &mut Backend,
       paint_canvas: &mut PaintCanvas,
+      stroke_layer: &mut StrokeLayer,
       a: Point,
       b: Point,
       color: Color,
       thickness: f32,
+      hardness: f32,
+      blend_mode: StrokeBlendMode,
    ) {
       let coverage = Self::coverage(a, b, thickness);
+      let soft = hardness < 1.0 && color.a != 0;
       renderer.push();
       renderer.set_blend_mode(BlendMode::Replace);
-      paint_canvas.draw(renderer, coverage, |renderer| {
-         renderer.line(a, b, color, LineCap::Round, thickness);
-      });
+      if color.a == 0 || (color.a == 255 && blend_mode == StrokeBlendMode::Normal && !soft) {
+         paint_canvas.draw(renderer, coverage, |renderer| {
+            renderer.line(a, b, color, LineCap::Round, thickness);
+         });
+      } else if soft {
+         // The falloff is drawn as a series of progressively thinner and more opaque lines.
+         // Taking the maximum, rather than blending them, makes overlapping segments join up
+         // seamlessly.
+         renderer.set_blend_mode(BlendMode::Max);
+         let radius = thickness / 2.0;
+         let core_radius = radius * hardness;
+         let rings = ((radius - core_radius).ceil() as usize).clamp(1, Self::MAX_SOFT_RINGS);
+         if !paint_canvas.is_paused() {
+            stroke_layer.draw(renderer, coverage, blend_mode, |renderer| {
+               for ring in 0..rings {
+                  let t = ring as f32 / rings as f32;
+                  let ring_radius = radius - (radius - core_radius) * t;
+                  // Smoothstep, evaluated halfway through the ring.
+                  let x = t + 0.5 / rings as f32;
+                  let falloff = x * x * (3.0 - 2.0 * x);
+                  let alpha = (color.a as f32 * falloff).round() as u8;
+                  renderer.line(
+                     a,
+                     b,
+                     color.with_alpha(alpha),
+                     LineCap::Round,
+                     ring_radius * 2.0,
+                  );
+               }
+               if core_radius > 0.0 {
+                  renderer.line(a, b, color, LineCap::Round, core_radius * 2.0);
+               }
+            });
+         }
+      } else if !paint_canvas.is_paused() {
+         stroke_layer.draw(renderer, coverage, blend_mode, |renderer| {
+            renderer.line(a, b, color, LineCap::Round, thickness);
+         });
+      }
       renderer.pop();
    }
 
    fn ensure_peer(&mut self, peer_id: PeerId) -> &mut PeerBrush {
       self.peers.entry(peer_id).or_insert(PeerBrush {
-         mouse_position: point(0.0, 0.0),
-         previous_mouse_position: point(0.0, 0.0),
-         last_cursor_packet: Instant::now(),
+         cursor: RemotePoint::new(),
          thickness: 4.0,
          color: Color::BLACK,
+         stroke_layer: StrokeLayer::new(),
+         previous_segment: None,
       })
    }
 
-   /// Returns the color currently selected in the color picker.
-   fn color(global_controls: &GlobalControls) -> Color {
-      global_controls.color_picker.color()
+   /// Returns the color currently selected in the color picker, with the brush opacity applied.
+   fn color(&self, global_controls: &GlobalControls) -> Color {
+      let opacity = self.brush_opacity_slider.value() / Self::MAX_OPACITY;
+      let color = global_controls.color_picker.color();
+      color.with_alpha((color.a as f32 * opacity).round() as u8)
+   }
+
+   /// Scales the thickness and opacity of a segment by the stylus pressure, ranging from 0 to 255.
+   fn apply_pressure(thickness: f32, color: Color, pressure: u8) -> (f32, Color) {
+      let alpha = (color.a as f32 * pressure as f32 / 255.0).round() as u8;
+      let thickness = Self::pressed_thickness(thickness, pressure);
+      (thickness, color.with_alpha(alpha))
+   }
+
+   /// Scales the thickness of a segment by the stylus pressure, ranging from 0 to 255.
+   fn pressed_thickness(thickness: f32, pressure: u8) -> f32 {
+      (thickness * pressure as f32 / 255.0).max(1.0)
+   }
+
+   /// Moves the stabilized end of the stroke towards the given point, and returns where it ends
+   /// up. The stronger the stabilizer, the more slowly it follows; this evens out jittery mouse
+   /// movement, no matter the frame rate.
+   fn stabilize(&mut self, target: Point, time: f32) -> Point {
+      let strength = self.stabilizer_slider.value() / Self::MAX_STABILIZER;
+      let position = match self.stabilized {
+         Some((position, last_time)) if strength > 0.0 => {
+            let delta_time = (time - last_time).max(0.0);
+            let factor = 1.0 - (-delta_time / (strength * Self::STABILIZER_LAG)).exp();
+            position + (target - position) * factor
+         }
+         _ => target,
+      };
+      self.stabilized = Some((position, time));
+      position
+   }
+
+   /// Returns whether a segment starting at `start` continues the one that ended at `end`.
+   fn continues(end: Point, start: Point) -> bool {
+      let delta = start - end;
+      delta.x.abs() < 0.01 && delta.y.abs() < 0.01
+   }
+
+   /// Draws the segments of a stroke received from a peer, along with the stylus pressure at their
+   /// ends, if the peer used a stylus.
+   fn draw_peer_stroke(
+      &mut self,
+      renderer: &mut Backend,
+      paint_canvas: &mut PaintCanvas,
+      sender: PeerId,
+      points: &[(Stroke, Option<u8>)],
+   ) -> netcanv::Result<()> {
+      for (i, (stroke, pressure)) in points.iter().enumerate() {
+         let &Stroke {
+            color,
+            thickness,
+            hardness,
+            a,
+            b,
+            blend_mode,
+         } = stroke;
+         // Verify that the packet is correct.
+         let thickness = thickness as f32;
+         // With thickness being a float, we allow for a little bit of leeway because
+         // computers are dumb.
+         ensure!(
+            thickness <= Self::MAX_THICKNESS + 0.1,
+            Error::InvalidToolPacket
+         );
+         ensure!(
+            hardness as f32 <= Self::MAX_HARDNESS,
+            Error::InvalidToolPacket
+         );
+         stroke.record_vector_segment(paint_canvas, Some(sender), *pressure);
+         let hardness = hardness as f32 / Self::MAX_HARDNESS;
+         // Draw the stroke.
+         let a = {
+            let (ax, ay) = a;
+            point(ax, ay)
+         };
+         let b = {
+            let (bx, by) = b;
+            point(bx, by)
+         };
+         let color = {
+            let (r, g, b, a) = color;
+            Color::new(r, g, b, a)
+         };
+         let (thickness, color) = match *pressure {
+            Some(pressure) => Self::apply_pressure(thickness, color, pressure),
+            None => (thickness, color),
+         };
+         // Smooth the segment out using the segments before and after it. Where there's no
+         // such segment, the stroke is extended in a straight line instead.
+         let peer = self.ensure_peer(sender);
+         let before = match peer.previous_segment {
+            Some((before, end)) if Self::continues(end, a) => before,
+            _ => a - (b - a),
+         };
+         let after = match points.get(i + 1) {
+            Some((next, _)) if Self::continues(b, point(next.a.0, next.a.1)) => {
+               point(next.b.0, next.b.1)
+            }
+            _ => b + (b - a),
+         };
+         peer.previous_segment = Some((a, b));
+         let delta = b - a;
+         let length = delta.x.hypot(delta.y);
+         let steps = (length / Self::INTERPOLATION_STEP)
+            .ceil()
+            .clamp(1.0, Self::MAX_INTERPOLATION_STEPS) as usize;
+         let mut previous = a;
+         for step in 1..=steps {
+            let next = catmull_rom(before, a, b, after, step as f32 / steps as f32);
+            Self::stroke(
+               renderer,
+               paint_canvas,
+               &mut peer.stroke_layer,
+               previous,
+               next,
+               color,
+               thickness,
+               hardness,
+               blend_mode,
+            );
+            previous = next;
+         }
+      }
+      Ok(())
+   }
+
+   /// Merges the stroke that was just finished onto the paint canvas.
+   fn finish_stroke(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas) {
+      if !self.stroke_layer.is_empty() {
+         self.stroke_layer.merge_into(renderer, paint_canvas);
+         self.stroke_ended = true;
+      }
    }
 }
 
@@ -173,6 +451,16 @@ impl Tool for BrushTool {
       config().keymap.tools.brush
    }
 
+   /// Merges the unfinished stroke, if the tool is switched away from in the middle of one.
+   fn deactivate(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas) {
+      let was_drawing = self.state != BrushState::Idle;
+      self.state = BrushState::Idle;
+      self.finish_stroke(renderer, paint_canvas);
+      if was_drawing {
+         paint_canvas.commit_operation(renderer);
+      }
+   }
+
    /// Handles input and drawing to the paint canvas with the brush.
    fn process_paint_canvas_input(
       &mut self,
@@ -180,6 +468,7 @@ impl Tool for BrushTool {
          ui,
          input,
          global_controls,
+         net,
          ..
       }: ToolArgs,
       paint_canvas: &mut PaintCanvas,
@@ -198,8 +487,12 @@ impl Tool for BrushTool {
 
       // Read input.
 
+      let was_drawing = self.state != BrushState::Idle;
       match input.action([MouseButton::Left, MouseButton::Right]) {
-         (true, [ButtonState::Pressed, _]) => self.state = BrushState::Drawing,
+         (true, [ButtonState::Pressed, _]) => {
+            self.state = BrushState::Drawing;
+            global_controls.color_picker.mark_color_used();
+         }
          (true, [_, ButtonState::Pressed]) => self.state = BrushState::Erasing,
          (_, [ButtonState::Released, _]) | (_, [_, ButtonState::Released]) => {
             self.state = BrushState::Idle
@@ -207,22 +500,26 @@ impl Tool for BrushTool {
          _ => (),
       }
 
-      // Shortcuts: Ctrl+Scroll, Ctrl+- and Ctrl+= can be used to alter the brush size.
+      // Shortcuts: Ctrl+Scroll, [ and ] can be used to alter the brush size.
 
       let mut thickness_change = 0.0;
 
-      if let (true, Some(scroll)) = input.action((Modifier::CTRL, MouseScroll)) {
+      let keymap = config().keymap.brush.clone();
+      if let (true, Some(scroll)) = input.action((keymap.thickness_scroll, MouseScroll)) {
          thickness_change += scroll.y * 2.0;
       }
 
-      if input.action(config().keymap.brush.decrease_thickness) == (true, true) {
+      if input.action(keymap.decrease_thickness) == (true, true) {
          thickness_change -= 2.0;
       }
-      if input.action(config().keymap.brush.increase_thickness) == (true, true) {
+      if input.action(keymap.increase_thickness) == (true, true) {
          thickness_change += 2.0;
       }
 
-      self.set_thickness(self.thickness() + thickness_change);
+      if thickness_change != 0.0 {
+         self.set_thickness(self.thickness() + thickness_change);
+         self.thickness_changed = Some(Instant::now());
+      }
 
       // Draw to the paint canvas.
       let a = ui.previous_mouse_position(input);
@@ -232,38 +529,67 @@ impl Tool for BrushTool {
          viewport.to_viewport_space(b, ui.size()),
       );
       if self.state != BrushState::Idle {
-         let color = Self::color(global_controls);
-         self.stroke(
+         // Each segment starts where the previous one ended, which is where the stabilizer left
+         // it rather than where the mouse was.
+         let a = self.stabilized.map_or(a, |(position, _)| position);
+         let b = self.stabilize(b, input.time_in_seconds());
+         let (color, hardness, blend_mode) = self.paint(global_controls);
+         let pressure = input.stylus_pressure().map(|pressure| (pressure * 255.0).round() as u8);
+         let (thickness, pressed_color) = match pressure {
+            Some(pressure) => Self::apply_pressure(self.thickness(), color, pressure),
+            None => (self.thickness(), color),
+         };
+         Self::stroke(
             ui,
             paint_canvas,
+            &mut self.stroke_layer,
             a,
             b,
-            match self.state {
-               BrushState::Drawing => color,
-               BrushState::Erasing => Color::TRANSPARENT,
-               _ => unreachable!(),
-            },
-            self.thickness(),
+            pressed_color,
+            thickness,
+            hardness,
+            blend_mode,
          );
-         self.stroke_points.push(Stroke {
-            color: match self.state {
-               BrushState::Drawing => (color.r, color.g, color.b, color.a),
-               BrushState::Erasing => (0, 0, 0, 0),
-               _ => unreachable!(),
-            },
+         let stroke = Stroke {
+            color: (color.r, color.g, color.b, color.a),
             thickness: self.thickness() as u8,
+            hardness: (hardness * Self::MAX_HARDNESS) as u8,
             a: (a.x, a.y),
             b: (b.x, b.y),
-         });
+            blend_mode,
+         };
+         stroke.record_vector_segment(paint_canvas, net.peer.peer_id(), pressure);
+         self.stroke_points.push((stroke, pressure));
+      } else {
+         self.stabilized = None;
+         self.finish_stroke(ui, paint_canvas);
+         if was_drawing {
+            paint_canvas.commit_operation(ui);
+         }
       }
       self.previous_mouse_position = self.mouse_position;
       self.mouse_position = b;
    }
 
-   /// Draws the guide circle of the brush.
+   /// Draws the translucent strokes that are still in progress, both ours and those of peers.
+   fn process_paint_canvas_layers(
+      &mut self,
+      renderer: &mut Backend,
+      _input: &Input,
+      _viewport: &Viewport,
+   ) {
+      for peer in self.peers.values() {
+         peer.stroke_layer.draw_to(renderer);
+      }
+      self.stroke_layer.draw_to(renderer);
+   }
+
+   /// Draws the guide circle of the brush, and its thickness if it was just changed.
    fn process_paint_canvas_overlays(
       &mut self,
-      ToolArgs { ui, input, .. }: ToolArgs,
+      ToolArgs {
+         ui, input, assets, ..
+      }: ToolArgs,
       viewport: &Viewport,
    ) {
       if input.mouse_active() {
@@ -282,9 +608,39 @@ impl Tool for BrushTool {
             1.0,
          );
          renderer.pop();
+
+         // Draw the thickness below and to the right of the circle, so that it can be seen
+         // without looking away from the canvas.
+         if let Some(changed) = self.thickness_changed {
+            if changed.elapsed() < Self::THICKNESS_DISPLAY_DURATION {
+               let text = self.thickness().to_string();
+               let radius = self.thickness() / 2.0 * viewport.zoom();
+               let size = vector(
+                  assets.sans_bold.text_width(&text),
+                  assets.sans_bold.height(),
+               );
+               let padding = vector(6.0, 4.0);
+               let rect = Rect::new(position + vector(radius, radius), size + padding * 2.0);
+               renderer.fill(rect, assets.colors.panel, 4.0);
+               renderer.outline(rect, assets.colors.separator, 4.0, 1.0);
+               renderer.text(
+                  rect,
+                  &assets.sans_bold,
+                  &text,
+                  assets.colors.text,
+                  (AlignH::Center, AlignV::Middle),
+               );
+            } else {
+               self.thickness_changed = None;
+            }
+         }
       }
    }
 
+   fn peer_position(&self, peer_id: PeerId) -> Option<Point> {
+      self.peers.get(&peer_id)?.cursor.position()
+   }
+
    /// Processes the guide circle of a peer.
    fn process_paint_canvas_peer(
       &mut self,
@@ -294,8 +650,10 @@ impl Tool for BrushTool {
       viewport: &Viewport,
       peer_id: PeerId,
    ) {
-      if let Some(peer) = self.peers.get(&peer_id) {
-         let position = viewport.to_screen_space(peer.lerp_mouse_position(), ui.size());
+      let peer_cursor =
+         self.peers.get(&peer_id).and_then(|peer| Some((peer, peer.cursor.position()?)));
+      if let Some((peer, position)) = peer_cursor {
+         let position = viewport.to_screen_space(position, ui.size());
          let radius = peer.thickness / 2.0 * viewport.zoom();
          let renderer = ui.render();
          // Render their guide circle.
@@ -383,6 +741,7 @@ impl Tool for BrushTool {
          SliderArgs {
             width: ui.width(),
             color: assets.colors.slider,
+            focus: assets.colors.focus_ring,
          },
       );
 
@@ -413,16 +772,154 @@ impl Tool for BrushTool {
          assets.colors.text,
          Some((ui.height(), AlignH::Center)),
       );
+      ui.space(16.0);
+
+      // Draw the stabilizer strength.
+      ui.horizontal_label(
+         &assets.sans,
+         &assets.tr.brush_stabilizer,
+         assets.colors.text,
+         None,
+      );
+      ui.space(16.0);
+
+      ui.push((96.0, ui.height()), Layout::Freeform);
+      self.stabilizer_slider.process(
+         ui,
+         input,
+         SliderArgs {
+            width: ui.width(),
+            color: assets.colors.slider,
+            focus: assets.colors.focus_ring,
+         },
+      );
+      ui.pop();
+      ui.space(8.0);
+
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &format!("{}%", self.stabilizer_slider.value()),
+         assets.colors.text,
+         Some((40.0, AlignH::Center)),
+      );
+
+      // Draw the opacity.
+      ui.space(16.0);
+      ui.horizontal_label(
+         &assets.sans,
+         &assets.tr.brush_opacity,
+         assets.colors.text,
+         None,
+      );
+      ui.space(16.0);
+
+      ui.push((96.0, ui.height()), Layout::Freeform);
+      self.opacity_slider().process(
+         ui,
+         input,
+         SliderArgs {
+            width: ui.width(),
+            color: assets.colors.slider,
+            focus: assets.colors.focus_ring,
+         },
+      );
+      ui.pop();
+      ui.space(8.0);
+
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &format!("{}%", self.opacity_slider().value()),
+         assets.colors.text,
+         Some((40.0, AlignH::Center)),
+      );
+      ui.space(16.0);
+
+      // Draw the hardness.
+      ui.horizontal_label(
+         &assets.sans,
+         &assets.tr.brush_hardness,
+         assets.colors.text,
+         None,
+      );
+      ui.space(16.0);
+
+      ui.push((96.0, ui.height()), Layout::Freeform);
+      self.hardness_slider().process(
+         ui,
+         input,
+         SliderArgs {
+            width: ui.width(),
+            color: assets.colors.slider,
+            focus: assets.colors.focus_ring,
+         },
+      );
+      ui.pop();
+      ui.space(8.0);
+
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &format!("{}%", self.hardness_slider().value()),
+         assets.colors.text,
+         Some((40.0, AlignH::Center)),
+      );
+
+      // Draw the blend mode. Clicking the button cycles through the available modes. Erasing
+      // doesn't blend anything, so it's only shown for the brush.
+      if self.tool == BrushType::Brush {
+         ui.space(16.0);
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.brush_blend_mode,
+            assets.colors.text,
+            None,
+         );
+         ui.space(8.0);
+         let height = ui.height();
+         if Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+            &assets.sans,
+            &assets.tr.blend_mode.get(self.blend_mode.name()),
+         )
+         .clicked()
+         {
+            self.blend_mode = self.blend_mode.next();
+         }
+      }
+   }
+
+   fn traffic_kind(&self, payload: &[u8]) -> TrafficKind {
+      // Bincode encodes an enum's variant index as a leading u32, so there's no need to decode the
+      // whole packet.
+      match deserialize_bincode::<u32>(payload) {
+         Ok(0) => TrafficKind::Cursor,
+         Ok(1 | 2 | 3) => TrafficKind::Stroke,
+         _ => TrafficKind::Other,
+      }
    }
 
    fn network_send(&mut self, net: Net, global_controls: &GlobalControls) -> netcanv::Result<()> {
       if !self.stroke_points.is_empty() {
-         let packet = Packet::Stroke(self.stroke_points.drain(..).collect());
+         // Strokes drawn with a mouse don't carry any pressure, so they're sent without it.
+         let has_pressure = self.stroke_points.iter().any(|(_, pressure)| pressure.is_some());
+         let points = self.stroke_points.drain(..);
+         let packet = if has_pressure {
+            Packet::PressureStroke(
+               points.map(|(stroke, pressure)| (stroke, pressure.unwrap_or(u8::MAX))).collect(),
+            )
+         } else {
+            Packet::Stroke(points.map(|(stroke, _)| stroke).collect())
+         };
          net.send(self, PeerId::BROADCAST, packet)?;
       }
+      if self.stroke_ended {
+         net.send(self, PeerId::BROADCAST, Packet::StrokeEnd)?;
+         self.stroke_ended = false;
+      }
       if self.mouse_position != self.previous_mouse_position {
          let Point { x, y } = self.mouse_position;
-         let Color { r, g, b, a } = Self::color(global_controls);
+         let Color { r, g, b, a } = self.color(global_controls);
          net.send(
             self,
             PeerId::BROADCAST,
@@ -452,43 +949,22 @@ impl Tool for BrushTool {
             color: (r, g, b, a),
          } => {
             let peer = self.ensure_peer(sender);
-            peer.previous_mouse_position = peer.mouse_position;
-            peer.mouse_position = point(x, y);
-            peer.last_cursor_packet = Instant::now();
+            peer.cursor.update(point(x, y));
             peer.thickness = thickness as f32;
             peer.color = Color::new(r, g, b, a);
          }
          Packet::Stroke(points) => {
-            for Stroke {
-               color,
-               thickness,
-               a,
-               b,
-            } in points
-            {
-               // Verify that the packet is correct.
-               let thickness = thickness as f32;
-               // With thickness being a float, we allow for a little bit of leeway because
-               // computers are dumb.
-               ensure!(
-                  thickness <= Self::MAX_THICKNESS + 0.1,
-                  Error::InvalidToolPacket
-               );
-               // Draw the stroke.
-               let a = {
-                  let (ax, ay) = a;
-                  point(ax, ay)
-               };
-               let b = {
-                  let (bx, by) = b;
-                  point(bx, by)
-               };
-               let color = {
-                  let (r, g, b, a) = color;
-                  Color::new(r, g, b, a)
-               };
-               self.stroke(renderer, paint_canvas, a, b, color, thickness);
-            }
+            let points: Vec<_> = points.into_iter().map(|stroke| (stroke, None)).collect();
+            self.draw_peer_stroke(renderer, paint_canvas, sender, &points)?;
+         }
+         Packet::PressureStroke(points) => {
+            let points: Vec<_> =
+               points.into_iter().map(|(stroke, pressure)| (stroke, Some(pressure))).collect();
+            self.draw_peer_stroke(renderer, paint_canvas, sender, &points)?;
+         }
+         Packet::StrokeEnd => {
+            let peer = self.ensure_peer(sender);
+            peer.stroke_layer.merge_into(renderer, paint_canvas);
          }
       }
       Ok(())
@@ -503,7 +979,7 @@ impl Tool for BrushTool {
    ) -> netcanv::Result<()> {
       // Send to newly joined peer where and what color we are.
       let Point { x, y } = self.mouse_position;
-      let Color { r, g, b, a } = Self::color(global_controls);
+      let Color { r, g, b, a } = self.color(global_controls);
       net.send(
          self,
          peer_id,
@@ -520,17 +996,66 @@ impl Tool for BrushTool {
       self.ensure_peer(peer_id);
       Ok(())
    }
+
+   /// Merges the peer's unfinished stroke, in case they switched tools or left in the middle of
+   /// drawing it.
+   fn network_peer_deactivate(
+      &mut self,
+      renderer: &mut Backend,
+      _net: Net,
+      paint_canvas: &mut PaintCanvas,
+      peer_id: PeerId,
+   ) -> netcanv::Result<()> {
+      if let Some(peer) = self.peers.get_mut(&peer_id) {
+         peer.stroke_layer.merge_into(renderer, paint_canvas);
+      }
+      Ok(())
+   }
 }
 
 #[derive(Serialize, Deserialize)]
 struct Stroke {
    color: (u8, u8, u8, u8),
    thickness: u8,
+   /// The hardness, in percent.
+   hardness: u8,
    a: (f32, f32),
    b: (f32, f32),
+   blend_mode: StrokeBlendMode,
+}
+
+impl Stroke {
+   /// Records the segment as part of a vector stroke, if the room keeps them, along with how wide
+   /// the stylus pressure made it. Segments using a blend mode other than normal can't be replayed
+   /// as plain paths, so they're left out.
+   fn record_vector_segment(
+      &self,
+      paint_canvas: &mut PaintCanvas,
+      creator: Option<PeerId>,
+      pressure: Option<u8>,
+   ) {
+      if self.blend_mode != StrokeBlendMode::Normal {
+         return;
+      }
+      let width = match pressure {
+         Some(pressure) => {
+            BrushTool::pressed_thickness(self.thickness as f32, pressure).round() as u8
+         }
+         None => self.thickness,
+      };
+      let style = StrokeStyle {
+         color: self.color,
+         thickness: self.thickness,
+         hardness: self.hardness,
+      };
+      let (a, b) = (point(self.a.0, self.a.1), point(self.b.0, self.b.1));
+      paint_canvas.record_vector_segment(creator, style, a, b, width);
+   }
 }
 
 /// A brush packet.
+///
+/// The order of the variants matters for [`BrushTool::traffic_kind`].
 #[derive(Serialize, Deserialize)]
 enum Packet {
    Cursor {
@@ -539,20 +1064,17 @@ enum Packet {
       color: (u8, u8, u8, u8),
    },
    Stroke(Vec<Stroke>),
+   /// Sent after the last points of a translucent stroke, to merge it onto the canvas.
+   StrokeEnd,
+   /// Like `Stroke`, but each segment also carries the stylus pressure at its end, from 0 to 255.
+   PressureStroke(Vec<(Stroke, u8)>),
 }
 
 struct PeerBrush {
-   mouse_position: Point,
-   previous_mouse_position: Point,
-   last_cursor_packet: Instant,
+   cursor: RemotePoint,
    thickness: f32,
    color: Color,
-}
-
-impl PeerBrush {
-   fn lerp_mouse_position(&self) -> Point {
-      let elapsed_ms = self.last_cursor_packet.elapsed().as_millis() as f32;
-      let t = (elapsed_ms / paint::State::TIME_PER_UPDATE.as_millis() as f32).min(1.0);
-      lerp_point(self.previous_mouse_position, self.mouse_position, t)
-   }
+   stroke_layer: StrokeLayer,
+   /// The last stroke segment received, used for smoothing out the next one.
+   previous_segment: Option<(Point, Point)>,
 }
@@ -65,6 +65,9 @@ impl Tool for EyedropperTool {
                global_controls.color_picker.set_color(self.color);
             }
          }
+         if input.mouse_button_just_released(MouseButton::Left) && self.color.a != 0 {
+            global_controls.color_picker.mark_color_used();
+         }
       }
    }
 
@@ -0,0 +1,624 @@
+//! The Floating images tool. Pastes images above the canvas, where they can be moved around and
+//! resized until they're flattened onto it or removed.
+//!
+//! A pasted image is first shown only to the person pasting it, following their cursor, until they
+//! confirm where it should go. Nobody else sees it before then.
+
+use std::collections::HashSet;
+
+use image::imageops::FilterType;
+use image::RgbaImage;
+use netcanv_protocol::client::{
+   FloatingImageId, ImagePlacement, MAX_FLOATING_IMAGE_DATA_LEN, MAX_FLOATING_IMAGE_SIZE,
+};
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, vector, Color, Point, Rect, Renderer, Vector};
+use netcanv_renderer::RenderBackend;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::app::paint::GlobalControls;
+use crate::assets::Assets;
+use crate::backend::winit::event::MouseButton;
+use crate::backend::winit::window::CursorIcon;
+use crate::backend::{Backend, Image};
+use crate::clipboard;
+use crate::common::VectorMath;
+use crate::config::config;
+use crate::image_coder::ImageCoder;
+use crate::keymap::KeyBinding;
+use crate::paint_canvas::PaintCanvas;
+use crate::ui::{Button, ButtonArgs, ButtonState, Input, MouseScroll, UiElements, UiInput};
+use crate::viewport::Viewport;
+
+use super::{paste_position, KeyShortcutAction, Net, Tool, ToolArgs};
+
+/// What's being done to the selected image with the mouse.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Action {
+   None,
+   /// The image is being dragged around. The offset is from the mouse cursor to the image's
+   /// top-left corner.
+   Moving(Vector),
+   /// The image is being resized by its bottom-right corner.
+   Resizing,
+}
+
+/// A pasted image whose placement hasn't been confirmed yet.
+struct Placing {
+   pixels: RgbaImage,
+   /// The image's PNG data, sent out once the placement is confirmed.
+   data: Vec<u8>,
+   /// The image uploaded with reduced opacity, so that the canvas shows through it.
+   preview: Image,
+   /// The point on the canvas the image follows.
+   cursor: Point,
+   /// How far the image was moved from the cursor with the arrow keys.
+   nudge: Vector,
+   scale: f32,
+}
+
+impl Placing {
+   /// The opacity of the preview.
+   const PREVIEW_OPACITY: f32 = 0.5;
+   /// How much a single step of the mouse wheel scales the image by.
+   const SCALE_STEP: f32 = 1.1;
+
+   /// Returns where the image would be placed if the placement was confirmed now.
+   fn placement(&self) -> ImagePlacement {
+      let (width, height) = self.pixels.dimensions();
+      let width = (width as f32 * self.scale).round().max(1.0);
+      let height = (height as f32 * self.scale).round().max(1.0);
+      let center = self.cursor + self.nudge;
+      ImagePlacement {
+         position: (
+            (center.x - width / 2.0).round() as i32,
+            (center.y - height / 2.0).round() as i32,
+         ),
+         size: (width as u32, height as u32),
+      }
+   }
+
+   /// Returns the rectangle the image would be placed in.
+   fn rect(&self) -> Rect {
+      let ImagePlacement {
+         position: (x, y),
+         size: (width, height),
+      } = self.placement();
+      Rect::new(
+         point(x as f32, y as f32),
+         vector(width as f32, height as f32),
+      )
+   }
+
+   /// Scales the image by the given number of mouse wheel steps, keeping it within the largest
+   /// size a floating image can have.
+   fn scale_by(&mut self, steps: f32) {
+      let (width, height) = self.pixels.dimensions();
+      let max_scale = MAX_FLOATING_IMAGE_SIZE as f32 / width.max(height) as f32;
+      let min_scale = 1.0 / width.min(height) as f32;
+      self.scale = (self.scale * Self::SCALE_STEP.powf(steps)).clamp(min_scale, max_scale);
+   }
+}
+
+pub struct ImageTool {
+   icon: Image,
+   /// The pasted image being placed, if any.
+   placing: Option<Placing>,
+   selected: Option<FloatingImageId>,
+   action: Action,
+   /// The rectangle of the selected image as of the last time the canvas was processed, used for
+   /// drawing its outline.
+   selected_rect: Option<Rect>,
+   /// The placement of the selected image that hasn't been sent out yet.
+   pending_placement: Option<(FloatingImageId, ImagePlacement)>,
+   /// Whether flattening the selected image was requested on the bottom bar.
+   flatten_requested: bool,
+   /// Whether removing the selected image was requested on the bottom bar.
+   remove_requested: bool,
+
+   /// The image being read from the clipboard, along with its PNG data.
+   paste: Option<(Point, oneshot::Receiver<(RgbaImage, Vec<u8>)>)>,
+   decoded_tx: mpsc::UnboundedSender<(FloatingImageId, RgbaImage)>,
+   decoded_rx: mpsc::UnboundedReceiver<(FloatingImageId, RgbaImage)>,
+   /// Images that were sent to the decoding thread. Images that fail to decode stay here, such that
+   /// they aren't decoded over and over again.
+   decoding: HashSet<FloatingImageId>,
+}
+
+impl ImageTool {
+   /// The maximum width and height of a pasted image. Larger images are scaled down to fit.
+   const MAX_PASTE_SIZE: u32 = 2048;
+   const COLOR: Color = Color::rgb(0x0397fb);
+   const HANDLE_RADIUS: f32 = 4.0;
+
+   /// Creates an instance of the image tool.
+   pub fn new(renderer: &mut Backend) -> Self {
+      let (decoded_tx, decoded_rx) = mpsc::unbounded_channel();
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/image.svg")),
+         placing: None,
+         selected: None,
+         action: Action::None,
+         selected_rect: None,
+         pending_placement: None,
+         flatten_requested: false,
+         remove_requested: false,
+         paste: None,
+         decoded_tx,
+         decoded_rx,
+         decoding: HashSet::new(),
+      }
+   }
+
+   /// Reads an image from the clipboard and encodes it on a separate thread.
+   fn enqueue_paste_from_clipboard(&mut self, position: Point) {
+      let (tx, rx) = oneshot::channel();
+      self.paste = Some((position, rx));
+      tokio::task::spawn_blocking(move || {
+         tracing::debug!("reading image from clipboard");
+         let image = catch!(clipboard::paste_image());
+         let image = if image.width().max(image.height()) > Self::MAX_PASTE_SIZE {
+            tracing::debug!("image is too big! scaling down");
+            let scale = Self::MAX_PASTE_SIZE as f32 / image.width().max(image.height()) as f32;
+            let width = ((image.width() as f32 * scale) as u32).max(1);
+            let height = ((image.height() as f32 * scale) as u32).max(1);
+            image::imageops::resize(&image, width, height, FilterType::Triangle)
+         } else {
+            image
+         };
+         let (image, data) = catch!(Self::encode_within_limit(image));
+         tracing::debug!("paste job done; encoded {} bytes", data.len());
+         let _ = tx.send((image, data));
+      });
+   }
+
+   /// Encodes a pasted image to PNG, scaling it down until its data is small enough to be sent out
+   /// to everyone.
+   fn encode_within_limit(mut image: RgbaImage) -> netcanv::Result<(RgbaImage, Vec<u8>)> {
+      /// How many times the image is scaled down before giving up.
+      const MAX_ATTEMPTS: usize = 8;
+
+      for _ in 0..MAX_ATTEMPTS {
+         let data = ImageCoder::encode_png_data_sync(image.clone())?;
+         if data.len() <= MAX_FLOATING_IMAGE_DATA_LEN {
+            return Ok((image, data));
+         }
+         tracing::debug!("image is too big ({} bytes)! scaling down", data.len());
+         // The size of the data is roughly proportional to the area of the image, so the sides
+         // are scaled by the square root, with some leeway.
+         let scale = (MAX_FLOATING_IMAGE_DATA_LEN as f32 / data.len() as f32).sqrt() * 0.9;
+         let width = ((image.width() as f32 * scale) as u32).max(1);
+         let height = ((image.height() as f32 * scale) as u32).max(1);
+         image = image::imageops::resize(&image, width, height, FilterType::Triangle);
+      }
+      Err(netcanv::Error::ImageIsTooLarge)
+   }
+
+   /// Polls whether the pasted image is ready, and if so, starts placing it. Returns `true` when
+   /// the tool should be switched to the image tool.
+   fn poll_paste_from_clipboard(&mut self, renderer: &mut Backend) -> bool {
+      let Some((center, rx)) = self.paste.as_mut() else {
+         return false;
+      };
+      let Ok((pixels, data)) = rx.try_recv() else {
+         return false;
+      };
+      let center = *center;
+      self.paste = None;
+
+      let mut preview_pixels = pixels.clone();
+      for pixel in preview_pixels.pixels_mut() {
+         pixel.0[3] = (pixel.0[3] as f32 * Placing::PREVIEW_OPACITY).round() as u8;
+      }
+      let preview = renderer.create_image_from_rgba(
+         preview_pixels.width(),
+         preview_pixels.height(),
+         &preview_pixels,
+      );
+      self.deselect();
+      self.placing = Some(Placing {
+         pixels,
+         data,
+         preview,
+         cursor: center,
+         nudge: vector(0.0, 0.0),
+         scale: 1.0,
+      });
+      true
+   }
+
+   /// Adds the image being placed to the canvas, and sends it out to everyone.
+   fn confirm_placement(
+      &mut self,
+      renderer: &mut Backend,
+      paint_canvas: &mut PaintCanvas,
+      net: &Net,
+   ) {
+      let Some(placing) = self.placing.take() else {
+         return;
+      };
+      let id = paint_canvas.next_floating_image_id(net.peer.peer_id());
+      let placement = placing.placement();
+      let Some(floating_image) =
+         paint_canvas.add_floating_image(id, placement, placing.data.clone())
+      else {
+         tracing::warn!("there are too many floating images; dropping the pasted one");
+         return;
+      };
+      floating_image.upload(renderer, &placing.pixels);
+      catch!(net.peer.send_add_image(PeerId::BROADCAST, id, placement, placing.data));
+      self.select(id);
+   }
+
+   /// Selects an image.
+   fn select(&mut self, id: FloatingImageId) {
+      self.selected = Some(id);
+      self.action = Action::None;
+   }
+
+   /// Deselects the selected image, if any.
+   fn deselect(&mut self) {
+      self.selected = None;
+      self.selected_rect = None;
+      self.action = Action::None;
+   }
+
+   /// Sends out the selected image's placement, if it changed since it was last sent.
+   fn send_pending_placement(&mut self, net: &Net) -> netcanv::Result<()> {
+      if let Some((id, placement)) = self.pending_placement.take() {
+         net.peer.send_place_image(id, placement)?;
+      }
+      Ok(())
+   }
+
+   /// Flattens or removes the selected image, if that was requested.
+   fn apply_requests(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas, net: &Net) {
+      let (flatten, remove) = (self.flatten_requested, self.remove_requested);
+      self.flatten_requested = false;
+      self.remove_requested = false;
+      let Some(id) = self.selected else {
+         return;
+      };
+      if !paint_canvas.floating_images().contains_key(&id) {
+         // Someone else flattened or removed the image in the meantime.
+         self.deselect();
+         return;
+      }
+      // The image must end up in the same place for everyone, so its latest placement has to go
+      // out first.
+      if flatten || remove {
+         catch!(self.send_pending_placement(net));
+         self.deselect();
+      }
+      if flatten {
+         catch!(paint_canvas.flatten_floating_image(renderer, id));
+         paint_canvas.commit_operation(renderer);
+         catch!(net.peer.send_flatten_image(id));
+      } else if remove {
+         paint_canvas.remove_floating_image(id);
+         catch!(net.peer.send_remove_image(id));
+      }
+   }
+
+   /// Returns the ID of the topmost image under the given point, in canvas coordinates.
+   fn image_at(paint_canvas: &PaintCanvas, position: Point) -> Option<FloatingImageId> {
+      paint_canvas
+         .floating_images()
+         .iter()
+         .rev()
+         .find_map(|(&id, image)| position.is_in_rect(image.rect()).then_some(id))
+   }
+}
+
+impl Tool for ImageTool {
+   fn name(&self) -> &'static str {
+      "image"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.image
+   }
+
+   fn deactivate(&mut self, _renderer: &mut Backend, _paint_canvas: &mut PaintCanvas) {
+      self.placing = None;
+      self.deselect();
+   }
+
+   fn has_scroll_focus(&self) -> bool {
+      self.placing.is_some()
+   }
+
+   fn active_key_shortcuts(
+      &mut self,
+      ToolArgs { ui, input, net, .. }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      _viewport: &Viewport,
+   ) -> KeyShortcutAction {
+      if let Some(placing) = &mut self.placing {
+         let keymap = config().keymap.image.clone();
+         if input.action(keymap.cancel) == (true, true) {
+            self.placing = None;
+            return KeyShortcutAction::Success;
+         }
+         if input.action(keymap.confirm) == (true, true) {
+            self.confirm_placement(ui, paint_canvas, &net);
+            return KeyShortcutAction::Success;
+         }
+         let nudges = [
+            (keymap.nudge_left, vector(-1.0, 0.0)),
+            (keymap.nudge_right, vector(1.0, 0.0)),
+            (keymap.nudge_up, vector(0.0, -1.0)),
+            (keymap.nudge_down, vector(0.0, 1.0)),
+         ];
+         for (binding, nudge) in nudges {
+            if input.action(binding) == (true, true) {
+               placing.nudge += nudge;
+               return KeyShortcutAction::Success;
+            }
+         }
+         return KeyShortcutAction::None;
+      }
+      if self.selected.is_some() && input.action(config().keymap.edit.delete) == (true, true) {
+         self.remove_requested = true;
+         self.apply_requests(ui, paint_canvas, &net);
+         return KeyShortcutAction::Success;
+      }
+      KeyShortcutAction::None
+   }
+
+   /// Pastes images from the clipboard, no matter which tool is selected.
+   fn global_key_shortcuts(
+      &mut self,
+      ToolArgs {
+         ui,
+         input,
+         canvas_view,
+         ..
+      }: ToolArgs,
+      _paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) -> KeyShortcutAction {
+      if input.action(config().keymap.edit.paste) == (true, true) {
+         tracing::info!("pasting floating image from clipboard");
+         self.enqueue_paste_from_clipboard(paste_position(input, canvas_view, viewport));
+      }
+
+      if self.poll_paste_from_clipboard(ui) {
+         return KeyShortcutAction::SwitchToThisTool;
+      }
+
+      KeyShortcutAction::None
+   }
+
+   /// Decodes images added by other people.
+   fn process_background_jobs(
+      &mut self,
+      ToolArgs { ui, .. }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+   ) {
+      for (&id, image) in paint_canvas.floating_images() {
+         if image.is_uploaded() || self.decoding.contains(&id) {
+            continue;
+         }
+         self.decoding.insert(id);
+         let data = image.data().to_owned();
+         let tx = self.decoded_tx.clone();
+         tokio::task::spawn_blocking(move || match ImageCoder::decode_png_data(&data) {
+            Ok(image) => {
+               let _ = tx.send((id, image));
+            }
+            Err(error) => tracing::error!("could not decode floating image {:?}: {:?}", id, error),
+         });
+      }
+
+      while let Ok((id, image)) = self.decoded_rx.try_recv() {
+         self.decoding.remove(&id);
+         if let Some(floating_image) = paint_canvas.floating_image_mut(id) {
+            floating_image.upload(ui, &image);
+         }
+      }
+   }
+
+   /// Handles placing pasted images, and selecting, moving, and resizing images.
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs { ui, input, net, .. }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      self.apply_requests(ui, paint_canvas, &net);
+
+      let mouse_position = viewport.to_viewport_space(ui.mouse_position(input), ui.size());
+
+      if let Some(placing) = &mut self.placing {
+         if ui.hover(input) {
+            placing.cursor = mouse_position;
+            if let (true, Some(scroll)) = input.action(MouseScroll) {
+               placing.scale_by(scroll.y);
+            }
+            input.set_cursor(CursorIcon::AllScroll);
+         }
+         return;
+      }
+      let handle_radius = Self::HANDLE_RADIUS * 3.0 / viewport.zoom();
+      let selected_rect = self
+         .selected
+         .and_then(|id| paint_canvas.floating_images().get(&id))
+         .map(|image| image.rect());
+      let on_handle = selected_rect
+         .map(|rect| mouse_position.is_in_circle(rect.bottom_right(), handle_radius))
+         .unwrap_or(false);
+      let hovered = Self::image_at(paint_canvas, mouse_position);
+
+      match input.action(MouseButton::Left) {
+         (true, ButtonState::Pressed) if ui.hover(input) => {
+            if on_handle {
+               self.action = Action::Resizing;
+            } else if let Some(id) = hovered {
+               self.select(id);
+               if let Some(image) = paint_canvas.floating_images().get(&id) {
+                  self.action = Action::Moving(mouse_position - image.rect().position);
+               }
+            } else {
+               self.deselect();
+            }
+         }
+         (_, ButtonState::Released) => self.action = Action::None,
+         _ => (),
+      }
+
+      input.set_cursor(match self.action {
+         Action::Moving(_) => CursorIcon::AllScroll,
+         Action::Resizing => CursorIcon::NwseResize,
+         Action::None if on_handle => CursorIcon::NwseResize,
+         Action::None if hovered.is_some() => CursorIcon::Hand,
+         Action::None => CursorIcon::Default,
+      });
+
+      if let Some(id) = self.selected {
+         if let Some(image) = paint_canvas.floating_images().get(&id) {
+            let ImagePlacement {
+               position: (x, y),
+               size: (width, height),
+            } = image.placement;
+            let placement = match self.action {
+               Action::None => None,
+               Action::Moving(offset) => {
+                  let top_left = mouse_position - offset;
+                  Some(ImagePlacement {
+                     position: (top_left.x.round() as i32, top_left.y.round() as i32),
+                     ..image.placement
+                  })
+               }
+               Action::Resizing => {
+                  // The aspect ratio is kept, such that references don't get distorted.
+                  let aspect = height as f32 / width as f32;
+                  let max_width = (MAX_FLOATING_IMAGE_SIZE as f32)
+                     .min(MAX_FLOATING_IMAGE_SIZE as f32 / aspect)
+                     .max(1.0);
+                  let new_width = (mouse_position.x - x as f32).clamp(1.0, max_width);
+                  let new_height = (new_width * aspect).max(1.0);
+                  Some(ImagePlacement {
+                     size: (new_width.round() as u32, new_height.round() as u32),
+                     ..image.placement
+                  })
+               }
+            };
+            if let Some(placement) = placement.filter(|&p| p != image.placement) {
+               paint_canvas.place_floating_image(id, placement);
+               self.pending_placement = Some((id, placement));
+            }
+         }
+      }
+      self.selected_rect = self
+         .selected
+         .and_then(|id| paint_canvas.floating_images().get(&id))
+         .map(|image| image.rect());
+   }
+
+   /// Draws the preview of the image being placed.
+   fn process_paint_canvas_layers(
+      &mut self,
+      renderer: &mut Backend,
+      _input: &Input,
+      _viewport: &Viewport,
+   ) {
+      if let Some(placing) = &self.placing {
+         renderer.image(placing.rect(), &placing.preview);
+      }
+   }
+
+   /// Outlines the selected image or the one being placed, and draws the selected image's resize
+   /// handle.
+   fn process_paint_canvas_overlays(&mut self, ToolArgs { ui, .. }: ToolArgs, viewport: &Viewport) {
+      let canvas_size = ui.size();
+      // The outlines are laid out without the viewport's rotation, and then rotated as a whole.
+      ui.render().push();
+      viewport.apply_screen_rotation(ui.render(), canvas_size);
+      let viewport = &viewport.unrotated();
+      if let Some(placing) = &self.placing {
+         let rect = placing.rect();
+         let top_left = viewport.to_screen_space(rect.top_left(), canvas_size);
+         let bottom_right = viewport.to_screen_space(rect.bottom_right(), canvas_size);
+         ui.render().outline(
+            Rect::new(top_left, bottom_right - top_left),
+            Self::COLOR,
+            0.0,
+            1.0,
+         );
+      }
+      if let Some(rect) = self.selected_rect {
+         let top_left = viewport.to_screen_space(rect.top_left(), canvas_size);
+         let bottom_right = viewport.to_screen_space(rect.bottom_right(), canvas_size);
+         let renderer = ui.render();
+         renderer.outline(
+            Rect::new(top_left, bottom_right - top_left),
+            Self::COLOR,
+            0.0,
+            1.0,
+         );
+         renderer.fill_circle(bottom_right, Self::HANDLE_RADIUS + 2.0, Color::WHITE);
+         renderer.fill_circle(bottom_right, Self::HANDLE_RADIUS, Self::COLOR);
+      }
+      ui.render().pop();
+   }
+
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui, input, assets, ..
+      }: ToolArgs,
+   ) {
+      if self.placing.is_some() {
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.image_placement_hint,
+            assets.colors.text,
+            None,
+         );
+         return;
+      }
+      if self.selected.is_none() {
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.floating_images_hint,
+            assets.colors.text,
+            None,
+         );
+         return;
+      }
+
+      let height = ui.height();
+      if Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+         &assets.sans,
+         &assets.tr.flatten_image,
+      )
+      .clicked()
+      {
+         self.flatten_requested = true;
+      }
+      ui.space(8.0);
+      if Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+         &assets.sans,
+         &assets.tr.remove_image,
+      )
+      .clicked()
+      {
+         self.remove_requested = true;
+      }
+   }
+
+   fn network_send(&mut self, net: Net, _global_controls: &GlobalControls) -> netcanv::Result<()> {
+      self.send_pending_placement(&net)
+   }
+}
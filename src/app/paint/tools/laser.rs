@@ -0,0 +1,278 @@
+//! The Laser pointer tool. Draws trails that fade away after a couple of seconds, for pointing
+//! things out without ever drawing onto the canvas.
+
+use std::collections::{HashMap, VecDeque};
+use web_time::{Duration, Instant};
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, Color, LineCap, Point, Renderer, Vector};
+use netcanv_renderer::RenderBackend;
+use serde::{Deserialize, Serialize};
+
+use crate::app::paint::GlobalControls;
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+use crate::common::deserialize_bincode;
+use crate::config::config;
+use crate::keymap::KeyBinding;
+use crate::net::stats::TrafficKind;
+use crate::paint_canvas::PaintCanvas;
+use crate::ui::{ButtonState, UiElements, UiInput};
+use crate::viewport::Viewport;
+use crate::Error;
+
+use super::{Net, Tool, ToolArgs};
+
+pub struct LaserTool {
+   icon: Image,
+   /// Whether the mouse button is held down and our trail is being extended.
+   pointing: bool,
+   trail: Trail,
+   /// Points added to our trail since the last network update.
+   outgoing_points: Vec<TrailPoint>,
+   peers: HashMap<PeerId, Trail>,
+}
+
+impl LaserTool {
+   /// The maximum number of points accepted in a single packet.
+   const MAX_POINTS_PER_PACKET: usize = 256;
+
+   /// Creates an instance of the laser pointer tool.
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/laser.svg")),
+         pointing: false,
+         trail: Trail::default(),
+         outgoing_points: Vec::new(),
+         peers: HashMap::new(),
+      }
+   }
+
+   /// Stops extending our trail, such that the next point starts a new one.
+   fn stop_pointing(&mut self) {
+      self.pointing = false;
+      self.trail.connect_next = false;
+   }
+}
+
+impl Tool for LaserTool {
+   fn name(&self) -> &'static str {
+      "laser"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.laser
+   }
+
+   fn deactivate(&mut self, _renderer: &mut Backend, _paint_canvas: &mut PaintCanvas) {
+      self.stop_pointing();
+   }
+
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs { ui, input, .. }: ToolArgs,
+      _paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      match input.action(MouseButton::Left) {
+         (true, ButtonState::Pressed) if ui.hover(input) => self.pointing = true,
+         (_, ButtonState::Released) => self.stop_pointing(),
+         _ => (),
+      }
+
+      if self.pointing {
+         let position = viewport.to_viewport_space(ui.mouse_position(input), ui.size());
+         if self.trail.last_position() != Some(position) {
+            let trail_point = TrailPoint {
+               position: (position.x, position.y),
+               connected: self.trail.connect_next,
+            };
+            self.trail.push(position);
+            self.outgoing_points.push(trail_point);
+         }
+      }
+   }
+
+   fn process_paint_canvas_overlays(&mut self, ToolArgs { ui, .. }: ToolArgs, viewport: &Viewport) {
+      let canvas_size = ui.size();
+      self.trail.draw(ui.render(), viewport, canvas_size);
+   }
+
+   fn process_paint_canvas_peer(
+      &mut self,
+      ToolArgs { ui, .. }: ToolArgs,
+      viewport: &Viewport,
+      peer_id: PeerId,
+   ) {
+      if let Some(trail) = self.peers.get_mut(&peer_id) {
+         let canvas_size = ui.size();
+         trail.draw(ui.render(), viewport, canvas_size);
+      }
+   }
+
+   fn process_bottom_bar(&mut self, ToolArgs { ui, assets, .. }: ToolArgs) {
+      ui.horizontal_label(
+         &assets.sans,
+         &assets.tr.laser_hint,
+         assets.colors.text,
+         None,
+      );
+   }
+
+   fn traffic_kind(&self, _payload: &[u8]) -> TrafficKind {
+      TrafficKind::Cursor
+   }
+
+   fn network_send(&mut self, net: Net, _global_controls: &GlobalControls) -> netcanv::Result<()> {
+      if !self.outgoing_points.is_empty() {
+         let packet = Packet::Trail(self.outgoing_points.drain(..).collect());
+         net.send(self, PeerId::BROADCAST, packet)?;
+      }
+      Ok(())
+   }
+
+   fn network_receive(
+      &mut self,
+      _renderer: &mut Backend,
+      _net: Net,
+      _paint_canvas: &mut PaintCanvas,
+      sender: PeerId,
+      payload: Vec<u8>,
+   ) -> netcanv::Result<()> {
+      let packet: Packet = deserialize_bincode(&payload)?;
+      match packet {
+         Packet::Trail(points) => {
+            ensure!(
+               points.len() <= Self::MAX_POINTS_PER_PACKET,
+               Error::InvalidToolPacket
+            );
+            let trail = self.peers.entry(sender).or_default();
+            for TrailPoint {
+               position: (x, y),
+               connected,
+            } in points
+            {
+               ensure!(x.is_finite() && y.is_finite(), Error::InvalidToolPacket);
+               if !connected {
+                  trail.connect_next = false;
+               }
+               trail.push(point(x, y));
+            }
+         }
+      }
+      Ok(())
+   }
+
+   /// Forgets the peer's trail, so that it doesn't show up again once they pick the laser back up.
+   fn network_peer_deactivate(
+      &mut self,
+      _renderer: &mut Backend,
+      _net: Net,
+      _paint_canvas: &mut PaintCanvas,
+      peer_id: PeerId,
+   ) -> netcanv::Result<()> {
+      self.peers.remove(&peer_id);
+      Ok(())
+   }
+}
+
+/// A point of a trail, as sent over the network.
+#[derive(Serialize, Deserialize)]
+struct TrailPoint {
+   position: (f32, f32),
+   /// Whether the point continues the trail from the previous point, as opposed to starting a new
+   /// one.
+   connected: bool,
+}
+
+/// A laser pointer packet.
+#[derive(Serialize, Deserialize)]
+enum Packet {
+   Trail(Vec<TrailPoint>),
+}
+
+/// A trail of points that fade away over time.
+#[derive(Default)]
+struct Trail {
+   /// The points of the trail along with the time they were added, oldest first. Each point is
+   /// flagged with whether it's connected to the point before it.
+   points: VecDeque<(Point, Instant, bool)>,
+   /// Whether the next point continues the trail.
+   connect_next: bool,
+}
+
+impl Trail {
+   /// How long it takes for a point of the trail to fade away.
+   const LIFETIME: Duration = Duration::from_secs(2);
+   /// The maximum number of points kept in a trail, so that a peer can't make us draw too much.
+   const MAX_POINTS: usize = 1024;
+   const COLOR: Color = Color::rgb(0xff1744);
+   const THICKNESS: f32 = 3.0;
+   const GLOW_THICKNESS: f32 = 9.0;
+
+   /// Adds a point to the end of the trail.
+   fn push(&mut self, position: Point) {
+      self.points.push_back((position, Instant::now(), self.connect_next));
+      self.connect_next = true;
+      while self.points.len() > Self::MAX_POINTS {
+         self.points.pop_front();
+      }
+   }
+
+   /// Returns the position of the most recently added point.
+   fn last_position(&self) -> Option<Point> {
+      self.points.back().map(|&(position, _, _)| position)
+   }
+
+   /// Draws the trail in screen space, and removes the points that have faded away.
+   fn draw(&mut self, renderer: &mut Backend, viewport: &Viewport, canvas_size: Vector) {
+      while let Some(&(_, created, _)) = self.points.front() {
+         if created.elapsed() < Self::LIFETIME {
+            break;
+         }
+         self.points.pop_front();
+      }
+
+      let opacity = |created: Instant| {
+         let faded = created.elapsed().as_secs_f32() / Self::LIFETIME.as_secs_f32();
+         (1.0 - faded).clamp(0.0, 1.0)
+      };
+      for (&(a, _, _), &(b, created, connected)) in
+         self.points.iter().zip(self.points.iter().skip(1))
+      {
+         if !connected {
+            continue;
+         }
+         let opacity = opacity(created);
+         let a = viewport.to_screen_space(a, canvas_size);
+         let b = viewport.to_screen_space(b, canvas_size);
+         renderer.line(
+            a,
+            b,
+            Self::COLOR.with_alpha((opacity * 64.0) as u8),
+            LineCap::Round,
+            Self::GLOW_THICKNESS,
+         );
+         renderer.line(
+            a,
+            b,
+            Self::COLOR.with_alpha((opacity * 255.0) as u8),
+            LineCap::Round,
+            Self::THICKNESS,
+         );
+      }
+      if let Some(&(position, created, _)) = self.points.back() {
+         let position = viewport.to_screen_space(position, canvas_size);
+         renderer.fill_circle(
+            position,
+            Self::GLOW_THICKNESS / 2.0,
+            Self::COLOR.with_alpha((opacity(created) * 255.0) as u8),
+         );
+      }
+   }
+}
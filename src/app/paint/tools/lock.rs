@@ -0,0 +1,142 @@
+//! The Lock tool. Lets the host lock regions of the canvas, such that nobody else can draw there.
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{Color, Rect, Renderer};
+
+use crate::app::paint::audit_log::{AuditEvent, AuditEventKind};
+use crate::app::paint::GlobalControls;
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+use crate::common::VectorMath;
+use crate::config::config;
+use crate::keymap::KeyBinding;
+use crate::paint_canvas::{ChunkRegion, PaintCanvas};
+use crate::ui::{ButtonState, UiElements, UiInput};
+use crate::viewport::Viewport;
+
+use super::{Net, Tool, ToolArgs};
+
+pub struct LockTool {
+   icon: Image,
+   /// The chunk position at which dragging out a new region started.
+   drag_start: Option<(i32, i32)>,
+   /// The chunk position the mouse is currently hovering over.
+   mouse_chunk: (i32, i32),
+   /// The list of regions that should be sent out to other peers on the next network tick.
+   outgoing_regions: Option<Vec<ChunkRegion>>,
+}
+
+impl LockTool {
+   /// The color of the region that's being dragged out.
+   const COLOR: Color = Color::rgb(0xff7b00);
+
+   /// Creates an instance of the lock tool.
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/lock.svg")),
+         drag_start: None,
+         mouse_chunk: (0, 0),
+         outgoing_regions: None,
+      }
+   }
+
+   /// Returns the region that's currently being dragged out, if any.
+   fn dragged_region(&self) -> Option<ChunkRegion> {
+      self.drag_start.map(|start| ChunkRegion::new(start, self.mouse_chunk))
+   }
+}
+
+impl Tool for LockTool {
+   fn name(&self) -> &'static str {
+      "lock"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.lock
+   }
+
+   /// Handles dragging out new regions and unlocking existing ones.
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs { ui, input, net, .. }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      let mouse_position = viewport.to_viewport_space(ui.mouse_position(input), ui.size());
+      self.mouse_chunk = PaintCanvas::chunk_position_at(mouse_position);
+
+      if !net.peer.is_host() {
+         self.drag_start = None;
+         return;
+      }
+
+      match input.action([MouseButton::Left, MouseButton::Right]) {
+         (true, [ButtonState::Pressed, _]) if ui.hover(input) => {
+            self.drag_start = Some(self.mouse_chunk);
+         }
+         (_, [ButtonState::Released, _]) => {
+            if let Some(region) = self.dragged_region() {
+               paint_canvas.lock_region(region);
+               AuditEvent::push(AuditEventKind::LockedRegion(region));
+               self.outgoing_regions = Some(paint_canvas.locked_regions().to_vec());
+            }
+            self.drag_start = None;
+         }
+         (true, [_, ButtonState::Pressed]) if ui.hover(input) => {
+            if paint_canvas.unlock_regions_at(self.mouse_chunk) {
+               AuditEvent::push(AuditEventKind::UnlockedRegions(self.mouse_chunk));
+               self.outgoing_regions = Some(paint_canvas.locked_regions().to_vec());
+            }
+         }
+         _ => (),
+      }
+   }
+
+   /// Draws the outline of the region that's being dragged out.
+   fn process_paint_canvas_overlays(&mut self, ToolArgs { ui, .. }: ToolArgs, viewport: &Viewport) {
+      if let Some(region) = self.dragged_region() {
+         ui.draw(|ui| {
+            let canvas_size = ui.size();
+            let rect = region.rect();
+            let renderer = ui.render();
+            renderer.push();
+            viewport.apply_screen_rotation(renderer, canvas_size);
+            let viewport = viewport.unrotated();
+            let top_left = viewport.to_screen_space(rect.top_left(), canvas_size).floor();
+            let bottom_right = viewport.to_screen_space(rect.bottom_right(), canvas_size).floor();
+            let rect = Rect::new(top_left, bottom_right - top_left);
+            renderer.outline(rect, Self::COLOR, 0.0, 2.0);
+            renderer.pop();
+         });
+      }
+   }
+
+   /// Shows a hint on how to use the tool.
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui, assets, net, ..
+      }: ToolArgs,
+   ) {
+      let hint = if net.peer.is_host() {
+         &assets.tr.lock_regions_hint
+      } else {
+         &assets.tr.lock_regions_host_only
+      };
+      ui.horizontal_label(&assets.sans, hint, assets.colors.text, None);
+   }
+
+   /// Sends out the new list of locked regions, if it has changed.
+   fn network_send(&mut self, net: Net, _: &GlobalControls) -> netcanv::Result<()> {
+      if let Some(regions) = self.outgoing_regions.take() {
+         let regions = regions.into_iter().map(|region| region.into()).collect();
+         net.peer.send_locked_regions(PeerId::BROADCAST, regions)?;
+      }
+      Ok(())
+   }
+}
@@ -2,24 +2,44 @@
 
 use std::ops::Deref;
 
+use netcanv_renderer::paws::Point;
+
 use crate::assets::Assets;
 use crate::backend::{Backend, Image};
 use crate::common::serialize_bincode;
 use crate::keymap::KeyBinding;
 use crate::net::peer::Peer;
+use crate::net::stats::TrafficKind;
 use crate::paint_canvas::PaintCanvas;
 use crate::ui::view::View;
 use crate::ui::wm::WindowManager;
 use crate::ui::{Input, Ui};
 use crate::viewport::Viewport;
 
+mod airbrush;
 mod brush;
 mod eyedropper;
+mod floating_images;
+mod laser;
+mod lock;
+mod note;
+mod remote_point;
 mod selection;
+mod shapes;
+mod stamp;
+mod text;
 
+pub use airbrush::*;
 pub use brush::*;
 pub use eyedropper::*;
+pub use floating_images::*;
+pub use laser::*;
+pub use lock::*;
+pub use note::*;
 pub use selection::*;
+pub use shapes::*;
+pub use stamp::*;
+pub use text::*;
 
 use netcanv_protocol::relay::PeerId;
 use serde::Serialize;
@@ -48,6 +68,20 @@ pub trait Tool {
    /// deselected.
    fn deactivate(&mut self, _renderer: &mut Backend, _paint_canvas: &mut PaintCanvas) {}
 
+   /// Returns whether the tool is currently capturing keyboard input, eg. into a text field.
+   ///
+   /// No key shortcuts are processed while this is the case.
+   fn has_keyboard_focus(&self) -> bool {
+      false
+   }
+
+   /// Returns whether the tool is currently using the mouse wheel, eg. for scaling something.
+   ///
+   /// The viewport isn't zoomed while this is the case.
+   fn has_scroll_focus(&self) -> bool {
+      false
+   }
+
    /// Called each frame when this tool is active, to poll for keyboard shortcuts.
    ///
    /// The returned value signifies what action should be taken after the function is done running.
@@ -116,6 +150,13 @@ pub trait Tool {
    ) {
    }
 
+   /// Returns where the given peer's cursor is on the canvas, if the tool keeps track of it.
+   ///
+   /// This is used for showing peers on the minimap.
+   fn peer_position(&self, _peer_id: PeerId) -> Option<Point> {
+      None
+   }
+
    /// Called to draw widgets on the bottom bar.
    ///
    /// Each tool can have its own set of widgets for controlling how the tool is used.
@@ -130,6 +171,12 @@ pub trait Tool {
       Ok(())
    }
 
+   /// Classifies one of this tool's packets for the network statistics. The payload is the same
+   /// as the one sent over the network.
+   fn traffic_kind(&self, _payload: &[u8]) -> TrafficKind {
+      TrafficKind::Other
+   }
+
    /// Called for each incoming packet from a specific `sender`.
    fn network_receive(
       &mut self,
@@ -196,7 +243,8 @@ impl<'peer> Net<'peer> {
       T: 'static + Serialize,
    {
       let payload = serialize_bincode(&payload)?;
-      self.peer.send_tool(peer_id, tool.name().to_owned(), payload)?;
+      let kind = tool.traffic_kind(&payload);
+      self.peer.send_tool(peer_id, tool.name().to_owned(), payload, kind)?;
       Ok(())
    }
 
@@ -217,6 +265,18 @@ pub struct ToolArgs<'ui, 'input, 'state> {
    pub net: Net<'state>,
 }
 
+/// Returns the point on the canvas pasted images should be centered on. That's the mouse cursor if
+/// it's over the canvas, or the middle of the viewport otherwise, such as when pasting with the
+/// mouse over a window.
+pub fn paste_position(input: &Input, canvas_view: &View, viewport: &Viewport) -> Point {
+   if canvas_view.has_mouse(input) {
+      let mouse_position = input.mouse_position() - canvas_view.position;
+      viewport.to_viewport_space(mouse_position, canvas_view.size())
+   } else {
+      viewport.pan()
+   }
+}
+
 /// The action that should be taken after [`Tool::global_key_shortcut`] is called.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum KeyShortcutAction {
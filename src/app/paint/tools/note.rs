@@ -0,0 +1,301 @@
+//! The Note tool. Places sticky notes on the canvas, and lets them be edited, moved, and removed.
+
+use std::collections::BTreeMap;
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_protocol::client::{NoteId, StickyNote, MAX_NOTE_TEXT_LEN};
+use netcanv_renderer::paws::{vector, AlignH, AlignV, Layout, Rect, Renderer, Vector};
+
+use crate::app::paint::{notes, GlobalControls};
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+use crate::config::config;
+use crate::keymap::KeyBinding;
+use crate::paint_canvas::PaintCanvas;
+use crate::ui::{
+   Button, ButtonArgs, ButtonState, Focus, TextField, TextFieldArgs, UiElements, UiInput,
+};
+use crate::viewport::Viewport;
+
+use super::{KeyShortcutAction, Net, Tool, ToolArgs};
+
+pub struct NoteTool {
+   icon: Image,
+   /// The note that's currently being edited.
+   selected: Option<NoteId>,
+   /// A copy of the selected note as of the last time the canvas was processed, used for drawing
+   /// its outline.
+   selected_note: Option<StickyNote>,
+   /// The offset from the mouse cursor to the top-left corner of the note being dragged.
+   drag_offset: Option<Vector>,
+   /// The color of newly created notes, as well as the selected one.
+   color: (u8, u8, u8),
+   text_field: TextField,
+   /// Whether the selected note's text or color was changed on the bottom bar.
+   edited: bool,
+   /// Whether removing the selected note was requested on the bottom bar.
+   remove_requested: bool,
+   /// Notes that were changed (`Some`) or removed (`None`) since the last network update.
+   outgoing: BTreeMap<NoteId, Option<StickyNote>>,
+}
+
+impl NoteTool {
+   const TEXT_FIELD_WIDTH: f32 = 320.0;
+
+   /// Creates an instance of the note tool.
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/note.svg")),
+         selected: None,
+         selected_note: None,
+         drag_offset: None,
+         color: notes::COLORS[0],
+         text_field: TextField::new(None),
+         edited: false,
+         remove_requested: false,
+         outgoing: BTreeMap::new(),
+      }
+   }
+
+   /// Selects a note for editing.
+   fn select(&mut self, id: NoteId, note: &StickyNote) {
+      self.selected = Some(id);
+      self.color = note.color;
+      self.text_field.set_text(note.text.clone());
+   }
+
+   /// Deselects the selected note, if any.
+   fn deselect(&mut self) {
+      self.selected = None;
+      self.selected_note = None;
+      self.drag_offset = None;
+      self.text_field.set_focus(false);
+   }
+
+   /// Stores the note in the canvas, and queues it up to be sent to everyone else.
+   fn update_note(&mut self, paint_canvas: &mut PaintCanvas, id: NoteId, note: StickyNote) {
+      paint_canvas.set_note(id, note.clone());
+      self.outgoing.insert(id, Some(note));
+   }
+
+   /// Applies the edits made on the bottom bar to the selected note.
+   fn apply_edits(&mut self, paint_canvas: &mut PaintCanvas) {
+      let Some(id) = self.selected else {
+         return;
+      };
+      let Some(note) = paint_canvas.notes().get(&id) else {
+         // Someone else removed the note in the meantime.
+         self.deselect();
+         return;
+      };
+      if self.remove_requested {
+         paint_canvas.remove_note(id);
+         self.outgoing.insert(id, None);
+         self.deselect();
+      } else if self.edited {
+         let mut text = self.text_field.text().to_owned();
+         while text.len() > MAX_NOTE_TEXT_LEN {
+            text.pop();
+         }
+         let note = StickyNote {
+            color: self.color,
+            text,
+            ..note.clone()
+         };
+         self.update_note(paint_canvas, id, note);
+      }
+      self.edited = false;
+      self.remove_requested = false;
+   }
+}
+
+impl Tool for NoteTool {
+   fn name(&self) -> &'static str {
+      "note"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.note
+   }
+
+   fn deactivate(&mut self, _renderer: &mut Backend, _paint_canvas: &mut PaintCanvas) {
+      self.deselect();
+   }
+
+   fn has_keyboard_focus(&self) -> bool {
+      self.selected.is_some() && self.text_field.focused()
+   }
+
+   fn active_key_shortcuts(
+      &mut self,
+      ToolArgs { input, .. }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      _viewport: &Viewport,
+   ) -> KeyShortcutAction {
+      if self.selected.is_some() && input.action(config().keymap.edit.delete) == (true, true) {
+         self.remove_requested = true;
+         self.apply_edits(paint_canvas);
+         return KeyShortcutAction::Success;
+      }
+      KeyShortcutAction::None
+   }
+
+   /// Handles creating, selecting, and dragging notes around.
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs { ui, input, net, .. }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      self.apply_edits(paint_canvas);
+
+      let mouse_position = ui.mouse_position(input);
+      match input.action(MouseButton::Left) {
+         (true, ButtonState::Pressed) if ui.hover(input) => {
+            let hovered = notes::note_at(paint_canvas.notes(), mouse_position, viewport, ui.size());
+            match hovered.and_then(|id| Some((id, paint_canvas.notes().get(&id)?.clone()))) {
+               Some((id, note)) => {
+                  let rect = notes::rect(&note, viewport, ui.size());
+                  self.select(id, &note);
+                  self.drag_offset = Some(mouse_position - rect.position);
+               }
+               // Clicking outside of the selected note only deselects it, so that it's possible
+               // to stop editing without creating a new note.
+               None if self.selected.is_some() => self.deselect(),
+               None => {
+                  let position = viewport.to_viewport_space(mouse_position, ui.size());
+                  let note = StickyNote {
+                     position: (position.x.round() as i32, position.y.round() as i32),
+                     color: self.color,
+                     text: String::new(),
+                  };
+                  let id = paint_canvas.next_note_id(net.peer.peer_id());
+                  self.update_note(paint_canvas, id, note.clone());
+                  self.select(id, &note);
+                  self.text_field.set_focus(true);
+               }
+            }
+         }
+         (_, ButtonState::Released) => self.drag_offset = None,
+         _ => (),
+      }
+
+      if let (Some(id), Some(offset)) = (self.selected, self.drag_offset) {
+         if let Some(note) = paint_canvas.notes().get(&id) {
+            let top_left = viewport.to_viewport_space(mouse_position - offset, ui.size());
+            let position = (top_left.x.round() as i32, top_left.y.round() as i32);
+            if position != note.position {
+               let note = StickyNote {
+                  position,
+                  ..note.clone()
+               };
+               self.update_note(paint_canvas, id, note);
+            }
+         }
+      }
+      self.selected_note = self.selected.and_then(|id| paint_canvas.notes().get(&id).cloned());
+   }
+
+   /// Outlines the selected note.
+   fn process_paint_canvas_overlays(
+      &mut self,
+      ToolArgs { ui, assets, .. }: ToolArgs,
+      viewport: &Viewport,
+   ) {
+      if let Some(note) = &self.selected_note {
+         let rect = notes::rect(note, viewport, ui.size());
+         let rect = Rect::new(
+            rect.position - vector(2.0, 2.0),
+            rect.size + vector(4.0, 4.0),
+         );
+         ui.render().outline(rect, assets.colors.text, 4.0, 2.0);
+      }
+   }
+
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui, input, assets, ..
+      }: ToolArgs,
+   ) {
+      if self.selected.is_none() {
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.notes_hint,
+            assets.colors.text,
+            None,
+         );
+         return;
+      }
+
+      // Color swatches.
+      for color in notes::COLORS {
+         ui.push((24.0, ui.height()), Layout::Freeform);
+         ui.push((20.0, 20.0), Layout::Freeform);
+         ui.align((AlignH::Center, AlignV::Middle));
+         ui.fill_rounded(notes::color(color), 4.0);
+         if self.color == color {
+            ui.outline_rounded(assets.colors.text, 4.0, 2.0);
+         }
+         if ui.clicked(input, MouseButton::Left) {
+            self.color = color;
+            self.edited = true;
+         }
+         ui.pop();
+         ui.pop();
+      }
+      ui.space(16.0);
+
+      // Text.
+      ui.push((Self::TEXT_FIELD_WIDTH, ui.height()), Layout::Freeform);
+      ui.push(
+         (Self::TEXT_FIELD_WIDTH, TextField::height(&assets.sans)),
+         Layout::Freeform,
+      );
+      ui.align((AlignH::Left, AlignV::Middle));
+      let text_before = self.text_field.text().to_owned();
+      self.text_field.process(
+         ui,
+         input,
+         TextFieldArgs {
+            width: Self::TEXT_FIELD_WIDTH,
+            colors: &assets.colors.text_field,
+            hint: Some(&assets.tr.note_text_hint),
+            font: &assets.sans,
+         },
+      );
+      if self.text_field.text() != text_before {
+         self.edited = true;
+      }
+      ui.pop();
+      ui.pop();
+      ui.space(8.0);
+
+      let height = ui.height();
+      if Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+         &assets.sans,
+         &assets.tr.remove_note,
+      )
+      .clicked()
+      {
+         self.remove_requested = true;
+      }
+   }
+
+   fn network_send(&mut self, net: Net, _global_controls: &GlobalControls) -> netcanv::Result<()> {
+      for (id, note) in std::mem::take(&mut self.outgoing) {
+         match note {
+            Some(note) => net.peer.send_note(id, note)?,
+            None => net.peer.send_remove_note(id)?,
+         }
+      }
+      Ok(())
+   }
+}
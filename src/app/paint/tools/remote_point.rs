@@ -0,0 +1,87 @@
+//! Smoothing for points controlled by other peers, such as their cursors.
+
+use netcanv_renderer::paws::{point, vector, Point, Vector};
+use web_time::Instant;
+
+use crate::app::paint;
+use crate::common::lerp_point;
+
+/// A point controlled by another peer, whose position is only received once per network tick.
+///
+/// Between updates, the point keeps moving at the velocity measured from the previous two, so
+/// that it doesn't stand still until the next update arrives. Once the next update does arrive,
+/// the point glides from where it was shown over to the new prediction, instead of jumping to it.
+/// If no update arrives in time, the peer has most likely stopped moving, so the point returns to
+/// the last position received.
+pub struct RemotePoint {
+   /// The most recently received position.
+   position: Point,
+   /// The velocity measured between the two most recent updates, in units per second.
+   velocity: Vector,
+   /// Where the point was shown when the most recent update was received.
+   shown_position: Point,
+   /// When the most recent update was received, or `None` if nothing was received yet.
+   received: Option<Instant>,
+}
+
+impl RemotePoint {
+   /// How long the point keeps moving on its own after an update, before heading back.
+   const EXTRAPOLATION_TIME: f32 = paint::State::TIME_PER_UPDATE.as_secs_f32();
+
+   /// The time it takes the point to glide over to the new prediction after an update.
+   const BLEND_TIME: f32 = paint::State::TIME_PER_UPDATE.as_secs_f32();
+
+   /// Updates that are further apart than this are treated as the start of a new movement, since
+   /// the point could've stopped anywhere in between.
+   const MAX_UPDATE_INTERVAL: f32 = 4.0 * paint::State::TIME_PER_UPDATE.as_secs_f32();
+
+   pub fn new() -> Self {
+      Self {
+         position: point(0.0, 0.0),
+         velocity: vector(0.0, 0.0),
+         shown_position: point(0.0, 0.0),
+         received: None,
+      }
+   }
+
+   /// Sets the position received in an update.
+   pub fn update(&mut self, position: Point) {
+      let now = Instant::now();
+      match self.received {
+         Some(received) => {
+            let interval = now.duration_since(received).as_secs_f32();
+            self.shown_position = self.position_at(received, now);
+            self.velocity = if interval > 0.0 && interval < Self::MAX_UPDATE_INTERVAL {
+               (position - self.position) * interval.recip()
+            } else {
+               vector(0.0, 0.0)
+            };
+         }
+         // There's nowhere to glide in from on the first update.
+         None => self.shown_position = position,
+      }
+      self.position = position;
+      self.received = Some(now);
+   }
+
+   /// Returns the position the point should be shown at right now, or `None` if no position was
+   /// received yet.
+   pub fn position(&self) -> Option<Point> {
+      self.received.map(|received| self.position_at(received, Instant::now()))
+   }
+
+   fn position_at(&self, received: Instant, now: Instant) -> Point {
+      let elapsed = now.duration_since(received).as_secs_f32();
+      let lead = if elapsed < Self::EXTRAPOLATION_TIME {
+         elapsed
+      } else {
+         (2.0 * Self::EXTRAPOLATION_TIME - elapsed).max(0.0)
+      };
+      let predicted = self.position + self.velocity * lead;
+      lerp_point(
+         self.shown_position,
+         predicted,
+         (elapsed / Self::BLEND_TIME).min(1.0),
+      )
+   }
+}
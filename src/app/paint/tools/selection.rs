@@ -1,8 +1,8 @@
 use image::imageops::FilterType;
 use std::collections::{HashMap, HashSet};
 use std::io::Cursor;
+use std::path::{Path, PathBuf};
 use tokio::sync::{mpsc, oneshot};
-use web_time::Instant;
 
 use crate::backend::winit::event::MouseButton;
 use crate::backend::winit::window::CursorIcon;
@@ -12,22 +12,32 @@ use image::codecs::png::PngEncoder;
 use image::io::Reader;
 use image::{ColorType, ImageEncoder, ImageFormat, RgbaImage};
 use netcanv_protocol::relay::PeerId;
-use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Point, Rect, Renderer, Vector};
+use netcanv_renderer::paws::{
+   point, vector, AlignH, AlignV, Color, Layout, Point, Rect, Renderer, Vector,
+};
 use netcanv_renderer::{
    BlendMode, Font as FontTrait, Framebuffer as FramebufferTrait, RenderBackend,
 };
+use nysa::global as bus;
+use rfd::AsyncFileDialog;
 use serde::{Deserialize, Serialize};
 
-use crate::app::paint::{self, GlobalControls};
+use crate::app::paint::audit_log::{AuditEvent, AuditEventKind};
+use crate::app::paint::GlobalControls;
 use crate::assets::Assets;
 use crate::backend::{Backend, Font, Framebuffer, Image};
 use crate::clipboard;
-use crate::common::{deserialize_bincode, lerp_point, RectMath, VectorMath};
-use crate::paint_canvas::PaintCanvas;
-use crate::ui::{ButtonState, UiElements, UiInput};
+use crate::common::{deserialize_bincode, RectMath, VectorMath};
+use crate::file_dialog;
+use crate::paint_canvas::{ChunkRegion, PaintCanvas};
+use crate::project_file::ProjectFile;
+use crate::ui::{
+   Button, ButtonArgs, ButtonState, Slider, SliderArgs, SliderStep, UiElements, UiInput,
+};
 use crate::viewport::Viewport;
 
-use super::{KeyShortcutAction, Net, Tool, ToolArgs};
+use super::remote_point::RemotePoint;
+use super::{paste_position, KeyShortcutAction, Net, Tool, ToolArgs};
 
 /// The icon set for the selection tool.
 struct Icons {
@@ -69,6 +79,9 @@ impl Action {
    }
 }
 
+/// A bus message requesting that the selected region be exported to the given file.
+struct ExportSelection(PathBuf);
+
 /// The selection tool.
 pub struct SelectionTool {
    icons: Icons,
@@ -87,6 +100,11 @@ pub struct SelectionTool {
    peer_pastes_tx: mpsc::UnboundedSender<(PeerId, Option<RgbaImage>)>,
    peer_pastes_rx: mpsc::UnboundedReceiver<(PeerId, Option<RgbaImage>)>,
    ongoing_paste_jobs: HashSet<PeerId>,
+
+   /// The scale the selected region is exported at, in percent.
+   export_scale_slider: Slider,
+   /// The region that'll be exported once the user picks where to export it.
+   export_region: Option<Rect>,
 }
 
 impl SelectionTool {
@@ -126,6 +144,9 @@ impl SelectionTool {
          peer_pastes_tx,
          peer_pastes_rx,
          ongoing_paste_jobs: HashSet::new(),
+
+         export_scale_slider: Slider::new(100.0, 25.0, 400.0, SliderStep::Discrete(25.0)),
+         export_region: None,
       }
    }
 
@@ -173,8 +194,8 @@ impl SelectionTool {
    fn ensure_peer(&mut self, peer_id: PeerId) -> &mut PeerSelection {
       self.peer_selections.entry(peer_id).or_insert(PeerSelection {
          selection: Selection::new(),
-         previous_normalized_rect: None,
-         last_rect_packet: Instant::now(),
+         position: RemotePoint::new(),
+         size: RemotePoint::new(),
       })
    }
 
@@ -241,7 +262,15 @@ impl SelectionTool {
       if let Some((position, image, bytes)) = self.paste.as_mut() {
          if let Ok(image) = image.try_recv() {
             self.selection.deselect(renderer, paint_canvas);
+            paint_canvas.commit_operation(renderer);
             self.selection.paste(renderer, Some(*position), &image);
+            AuditEvent::push(AuditEventKind::Pasted {
+               size: image.dimensions(),
+               position: self
+                  .selection
+                  .rect
+                  .map(|rect| (rect.position.x as i32, rect.position.y as i32)),
+            });
             return true;
          }
          if let Ok(bytes) = bytes.try_recv() {
@@ -271,6 +300,16 @@ impl SelectionTool {
             // We don't update the rectangle here because a data race could happen.
             // The peer sends a rect packet immediately after the paste packet anyways.
             tracing::debug!("finishing peer paste");
+            AuditEvent::push_from(
+               peer_id,
+               AuditEventKind::Pasted {
+                  size: image.dimensions(),
+                  position: peer
+                     .selection
+                     .rect
+                     .map(|rect| (rect.position.x as i32, rect.position.y as i32)),
+               },
+            );
             peer.selection.paste(renderer, None, &image);
             if deselected_before_decoding_finished {
                tracing::debug!("the peer deselected before decoding had a chance to finish");
@@ -283,6 +322,35 @@ impl SelectionTool {
    }
 
    /// Encodes an image to PNG.
+   /// Exports the region picked for exporting to the given file.
+   ///
+   /// Regions are exported from the canvas's chunks, so a captured selection is put back onto the
+   /// canvas first, just like when the user clicks away from it.
+   fn export(
+      &mut self,
+      renderer: &mut Backend,
+      net: &Net,
+      paint_canvas: &mut PaintCanvas,
+      path: &Path,
+   ) {
+      let Some(region) = self.export_region.take() else {
+         return;
+      };
+      if self.selection.capture.is_some() {
+         self.selection.deselect(renderer, paint_canvas);
+         paint_canvas.commit_operation(renderer);
+         catch!(net.send(self, PeerId::BROADCAST, Packet::Deselect));
+      }
+      let scale = self.export_scale_slider.value() / 100.0;
+      catch!(ProjectFile::export_region(
+         renderer,
+         path,
+         paint_canvas,
+         region,
+         scale
+      ));
+   }
+
    fn encode_image(image: &RgbaImage) -> netcanv::Result<Vec<u8>> {
       let mut bytes = Vec::new();
       PngEncoder::new(Cursor::new(&mut bytes)).write_image(
@@ -316,18 +384,25 @@ impl Tool for SelectionTool {
    /// When the tool is deactivated, the selection should be deselected.
    fn deactivate(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas) {
       self.selection.deselect(renderer, paint_canvas);
+      paint_canvas.commit_operation(renderer);
    }
 
    /// Processes key shortcuts when the selection is active.
    fn active_key_shortcuts(
       &mut self,
       ToolArgs { input, net, ui, .. }: ToolArgs,
-      _paint_canvas: &mut PaintCanvas,
+      paint_canvas: &mut PaintCanvas,
       _viewport: &Viewport,
    ) -> KeyShortcutAction {
+      // Deleting or cutting the selection leaves the captured area erased, which finishes the
+      // operation that started when the selection was captured.
       if input.action(config().keymap.edit.delete) == (true, true) {
          if self.selection.rect.is_some() {
+            if let Some(region) = self.selection.captured_region() {
+               AuditEvent::push(AuditEventKind::Cleared(region));
+            }
             self.selection.cancel();
+            paint_canvas.commit_operation(ui);
             catch!(
                net.send(self, PeerId::BROADCAST, Packet::Cancel),
                return KeyShortcutAction::None
@@ -343,7 +418,11 @@ impl Tool for SelectionTool {
 
       if input.action(config().keymap.edit.cut) == (true, true) {
          self.copy_to_clipboard(ui);
+         if let Some(region) = self.selection.captured_region() {
+            AuditEvent::push(AuditEventKind::Cleared(region));
+         }
          self.selection.cancel();
+         paint_canvas.commit_operation(ui);
          return KeyShortcutAction::Success;
       }
 
@@ -353,13 +432,19 @@ impl Tool for SelectionTool {
    /// Processes the global key shortcuts for the selection.
    fn global_key_shortcuts(
       &mut self,
-      ToolArgs { ui, input, net, .. }: ToolArgs,
+      ToolArgs {
+         ui,
+         input,
+         net,
+         canvas_view,
+         ..
+      }: ToolArgs,
       paint_canvas: &mut PaintCanvas,
       viewport: &Viewport,
    ) -> KeyShortcutAction {
-      if input.action(config().keymap.edit.paste) == (true, true) {
-         tracing::info!("pasting image from clipboard");
-         self.enqueue_paste_from_clipboard(viewport.pan());
+      if input.action(config().keymap.edit.paste_into_selection) == (true, true) {
+         tracing::info!("pasting image from clipboard into selection");
+         self.enqueue_paste_from_clipboard(paste_position(input, canvas_view, viewport));
       }
 
       if self.poll_paste_from_clipboard(ui, paint_canvas, &net) {
@@ -371,10 +456,14 @@ impl Tool for SelectionTool {
 
    fn process_background_jobs(
       &mut self,
-      ToolArgs { ui, .. }: ToolArgs,
+      ToolArgs { ui, net, .. }: ToolArgs,
       paint_canvas: &mut PaintCanvas,
    ) {
       self.poll_peer_pastes(ui.render(), paint_canvas);
+      for message in &bus::retrieve_all::<ExportSelection>() {
+         let ExportSelection(path) = message.consume();
+         self.export(ui, &net, paint_canvas, &path);
+      }
    }
 
    /// Processes mouse input.
@@ -444,6 +533,7 @@ impl Tool for SelectionTool {
             if self.potential_action == Action::Selecting {
                // Before we erase the old data, draw the capture back onto the canvas.
                self.selection.deselect(ui, paint_canvas);
+               paint_canvas.commit_operation(ui);
                catch!(self.send_rect_packet(&net));
                catch!(net.send(self, PeerId::BROADCAST, Packet::Deselect));
                // Anchor the selection to the mouse position.
@@ -536,17 +626,24 @@ impl Tool for SelectionTool {
       if let Some(rect) = self.selection.normalized_rect() {
          if !Self::rect_is_smaller_than_a_pixel(rect) {
             ui.draw(|ui| {
+               // The rectangle is laid out without the viewport's rotation, and then rotated as a
+               // whole.
+               let canvas_size = ui.size();
+               let renderer = ui.render();
+               renderer.push();
+               viewport.apply_screen_rotation(renderer, canvas_size);
+               let viewport = viewport.unrotated();
                // Oh my.
-               let top_left = viewport.to_screen_space(rect.top_left(), ui.size()).floor();
-               let top = viewport.to_screen_space(rect.top_center(), ui.size()).floor();
-               let top_right = viewport.to_screen_space(rect.top_right(), ui.size()).floor();
-               let right = viewport.to_screen_space(rect.right_center(), ui.size()).floor();
-               let bottom_right = viewport.to_screen_space(rect.bottom_right(), ui.size()).floor();
-               let bottom = viewport.to_screen_space(rect.bottom_center(), ui.size()).floor();
-               let bottom_left = viewport.to_screen_space(rect.bottom_left(), ui.size()).floor();
-               let left = viewport.to_screen_space(rect.left_center(), ui.size()).floor();
+               let top_left = viewport.to_screen_space(rect.top_left(), canvas_size).floor();
+               let top = viewport.to_screen_space(rect.top_center(), canvas_size).floor();
+               let top_right = viewport.to_screen_space(rect.top_right(), canvas_size).floor();
+               let right = viewport.to_screen_space(rect.right_center(), canvas_size).floor();
+               let bottom_right =
+                  viewport.to_screen_space(rect.bottom_right(), canvas_size).floor();
+               let bottom = viewport.to_screen_space(rect.bottom_center(), canvas_size).floor();
+               let bottom_left = viewport.to_screen_space(rect.bottom_left(), canvas_size).floor();
+               let left = viewport.to_screen_space(rect.left_center(), canvas_size).floor();
                let rect = Rect::new(top_left, bottom_right - top_left);
-               let renderer = ui.render();
                if let Some(capture) = self.selection.capture.as_ref() {
                   renderer.framebuffer(rect, capture);
                }
@@ -568,6 +665,7 @@ impl Tool for SelectionTool {
                self.draw_handle(renderer, bottom, Handle::Bottom);
                self.draw_handle(renderer, bottom_left, Handle::BottomLeft);
                self.draw_handle(renderer, left, Handle::Left);
+               renderer.pop();
             });
          }
       }
@@ -583,11 +681,15 @@ impl Tool for SelectionTool {
       peer_id: PeerId,
    ) {
       if let Some(peer) = self.peer_selections.get(&peer_id) {
-         if let Some(rect) = peer.lerp_normalized_rect() {
+         if let Some(rect) = peer.smoothed_normalized_rect() {
             if !Self::rect_is_smaller_than_a_pixel(rect) {
                ui.draw(|ui| {
-                  let top_left = viewport.to_screen_space(rect.top_left(), ui.size());
-                  let bottom_right = viewport.to_screen_space(rect.bottom_right(), ui.size());
+                  let canvas_size = ui.size();
+                  ui.render().push();
+                  viewport.apply_screen_rotation(ui.render(), canvas_size);
+                  let viewport = viewport.unrotated();
+                  let top_left = viewport.to_screen_space(rect.top_left(), canvas_size);
+                  let bottom_right = viewport.to_screen_space(rect.bottom_right(), canvas_size);
                   let rect = Rect::new(top_left, bottom_right - top_left);
 
                   let nickname = net.peer_name(peer_id).unwrap();
@@ -613,14 +715,20 @@ impl Tool for SelectionTool {
                         (AlignH::Center, AlignV::Middle),
                      );
                   }
+                  renderer.pop();
                });
             }
          }
       }
    }
 
-   /// Processes the bottom bar stats.
-   fn process_bottom_bar(&mut self, ToolArgs { ui, assets, .. }: ToolArgs) {
+   /// Processes the bottom bar stats, and the controls for exporting the selected region.
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui, input, assets, ..
+      }: ToolArgs,
+   ) {
       let icon_size = vector(ui.height(), ui.height());
 
       // Show the mouse position.
@@ -652,6 +760,52 @@ impl Tool for SelectionTool {
             assets.colors.text,
             Some((label_width(&assets.sans, &size), AlignH::Center)),
          );
+         ui.space(16.0);
+
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.export_scale,
+            assets.colors.text,
+            None,
+         );
+         ui.space(16.0);
+         ui.push((128.0, ui.height()), Layout::Freeform);
+         self.export_scale_slider.process(
+            ui,
+            input,
+            SliderArgs {
+               width: ui.width(),
+               color: assets.colors.slider,
+               focus: assets.colors.focus_ring,
+            },
+         );
+         ui.pop();
+         ui.space(8.0);
+         let scale = format!("{}%", self.export_scale_slider.value());
+         ui.horizontal_label(
+            &assets.sans,
+            &scale,
+            assets.colors.text,
+            Some((label_width(&assets.sans, "400%"), AlignH::Center)),
+         );
+         ui.space(16.0);
+
+         let height = ui.height();
+         if Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+            &assets.sans,
+            &assets.tr.export_selection,
+         )
+         .clicked()
+         {
+            self.export_region = Some(rect);
+            let dialog = AsyncFileDialog::new()
+               .add_filter(&assets.tr.fd_png_file, &["png"])
+               .add_filter(&assets.tr.fd_jpeg_file, &["jpg", "jpeg"]);
+            file_dialog::save_file(dialog, ExportSelection);
+         }
       }
    }
 
@@ -677,7 +831,11 @@ impl Tool for SelectionTool {
             position: (x, y),
             size: (width, height),
          } => {
-            peer.previous_normalized_rect = peer.selection.normalized_rect();
+            // A new selection shouldn't glide in from wherever the previous one was.
+            if peer.selection.rect.is_none() {
+               peer.position = RemotePoint::new();
+               peer.size = RemotePoint::new();
+            }
             peer.selection.rect = Some(Rect::new(
                point(x, y),
                vector(
@@ -685,10 +843,18 @@ impl Tool for SelectionTool {
                   height.min(Selection::MAX_SIZE as f32),
                ),
             ));
-            peer.last_rect_packet = Instant::now();
+            if let Some(rect) = peer.selection.normalized_rect() {
+               peer.position.update(rect.position);
+               peer.size.update(rect.size);
+            }
          }
          Packet::Capture => peer.selection.capture(renderer, paint_canvas),
-         Packet::Cancel => peer.selection.cancel(),
+         Packet::Cancel => {
+            if let Some(region) = peer.selection.captured_region() {
+               AuditEvent::push_from(sender, AuditEventKind::Cleared(region));
+            }
+            peer.selection.cancel();
+         }
          Packet::Deselect => peer.selection.deselect(renderer, paint_canvas),
          Packet::Paste((_x, _y), data) => {
             // ↑ (x, y) is only here for compatibility with 0.7.0 and is no longer used
@@ -799,6 +965,18 @@ impl Selection {
       }
    }
 
+   /// Returns the region of chunks covered by the selection, if it has been captured.
+   ///
+   /// Cancelling a captured selection leaves its area erased, so this is the region cleared by it.
+   fn captured_region(&self) -> Option<ChunkRegion> {
+      self.capture.as_ref()?;
+      let rect = self.normalized_rect()?;
+      Some(ChunkRegion::new(
+         PaintCanvas::chunk_position_at(rect.top_left()),
+         PaintCanvas::chunk_position_at(rect.bottom_right() - vector(1.0, 1.0)),
+      ))
+   }
+
    /// Cancels the selection, without transferring it to a paint canvas.
    fn cancel(&mut self) {
       self.rect = None;
@@ -890,18 +1068,15 @@ impl Selection {
 /// A peer's selection data.
 struct PeerSelection {
    selection: Selection,
-   previous_normalized_rect: Option<Rect>,
-   last_rect_packet: Instant,
+   position: RemotePoint,
+   size: RemotePoint,
 }
 
 impl PeerSelection {
-   fn lerp_normalized_rect(&self) -> Option<Rect> {
-      let elapsed = self.last_rect_packet.elapsed().as_millis() as f32;
-      let t = (elapsed / paint::State::TIME_PER_UPDATE.as_millis() as f32).clamp(0.0, 1.0);
+   fn smoothed_normalized_rect(&self) -> Option<Rect> {
       self.selection.normalized_rect().map(|mut rect| {
-         let previous_rect = self.previous_normalized_rect.unwrap_or(rect);
-         rect.position = lerp_point(previous_rect.position, rect.position, t);
-         rect.size = lerp_point(previous_rect.size, rect.size, t);
+         rect.position = self.position.position().unwrap_or(rect.position);
+         rect.size = self.size.position().unwrap_or(rect.size);
          rect
       })
    }
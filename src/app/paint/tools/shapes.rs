@@ -0,0 +1,478 @@
+//! The Shapes tool. Draws lines, rectangles, and ellipses onto the canvas.
+//!
+//! The shape is previewed while the mouse is being dragged, and drawn onto the canvas once the
+//! mouse button is released. Peers receive the shape itself rather than the chunks it touched, and
+//! draw it onto their own canvases.
+
+use std::f32::consts::TAU;
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{
+   point, vector, AlignH, AlignV, Color, Layout, LineCap, Point, Rect, Renderer,
+};
+use netcanv_renderer::{BlendMode, RenderBackend};
+use serde::{Deserialize, Serialize};
+
+use crate::app::paint::{grid, GlobalControls};
+use crate::assets::Assets;
+use crate::backend::{Backend, Image};
+use crate::common::deserialize_bincode;
+use crate::config::config;
+use crate::keymap::KeyBinding;
+use crate::net::stats::TrafficKind;
+use crate::paint_canvas::stroke_layer::{StrokeBlendMode, StrokeLayer};
+use crate::paint_canvas::PaintCanvas;
+use crate::ui::{
+   view, Button, ButtonArgs, ButtonState, ColorPicker, ColorPickerArgs, Input, Slider, SliderArgs,
+   SliderStep, UiElements, UiInput,
+};
+use crate::viewport::Viewport;
+use crate::Error;
+
+use super::{Net, Tool, ToolArgs};
+
+pub struct ShapesTool {
+   icon: Image,
+   kind: ShapeKind,
+   filled: bool,
+   thickness_slider: Slider,
+   /// Where the mouse button was pressed, in canvas space. `None` if no shape is being dragged out.
+   start: Option<Point>,
+   /// The shape being dragged out.
+   preview: Option<Shape>,
+   /// Translucent shapes are drawn here before being merged onto the canvas.
+   stroke_layer: StrokeLayer,
+   /// The shapes drawn since the last network update.
+   outgoing: Vec<Shape>,
+}
+
+impl ShapesTool {
+   const MAX_THICKNESS: f32 = 64.0;
+   const DEFAULT_THICKNESS: f32 = 4.0;
+   /// The maximum width and height of a shape, in pixels.
+   const MAX_EXTENT: f32 = 4096.0;
+   /// The number of segments an ellipse's outline is made up of is limited to this range.
+   const MIN_ELLIPSE_SEGMENTS: usize = 16;
+   const MAX_ELLIPSE_SEGMENTS: usize = 256;
+
+   /// Creates an instance of the shapes tool.
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/shapes.svg")),
+         kind: ShapeKind::Line,
+         filled: false,
+         thickness_slider: Slider::new(
+            Self::DEFAULT_THICKNESS,
+            1.0,
+            Self::MAX_THICKNESS,
+            SliderStep::Discrete(1.0),
+         ),
+         start: None,
+         preview: None,
+         stroke_layer: StrokeLayer::new(),
+         outgoing: Vec::new(),
+      }
+   }
+
+   /// Returns the shape spanning from `a` to `b`, with the current settings.
+   fn shape(&self, a: Point, b: Point, global_controls: &GlobalControls) -> Shape {
+      let b = point(
+         b.x.clamp(a.x - Self::MAX_EXTENT, a.x + Self::MAX_EXTENT),
+         b.y.clamp(a.y - Self::MAX_EXTENT, a.y + Self::MAX_EXTENT),
+      );
+      let color = if global_controls.color_picker.eraser {
+         Color::TRANSPARENT
+      } else {
+         global_controls.color_picker.color()
+      };
+      Shape {
+         kind: self.kind,
+         a: (a.x, a.y),
+         b: (b.x, b.y),
+         color: (color.r, color.g, color.b, color.a),
+         thickness: self.thickness_slider.value() as u8,
+         filled: self.filled,
+      }
+   }
+
+   /// Draws a shape onto the paint canvas. Like in the brush tool, translucent shapes go through
+   /// the stroke layer, so that the overlapping segments of an outline don't come out darker.
+   fn draw(
+      renderer: &mut Backend,
+      paint_canvas: &mut PaintCanvas,
+      stroke_layer: &mut StrokeLayer,
+      shape: &Shape,
+   ) {
+      let coverage = shape.coverage();
+      let alpha = shape.color.3;
+      renderer.push();
+      if alpha == 0 || alpha == 255 {
+         renderer.set_blend_mode(BlendMode::Replace);
+         paint_canvas.draw(renderer, coverage, |renderer| shape.render(renderer));
+      } else if !paint_canvas.is_paused() {
+         renderer.set_blend_mode(BlendMode::Max);
+         stroke_layer.draw(renderer, coverage, StrokeBlendMode::Normal, |renderer| {
+            shape.render(renderer)
+         });
+      }
+      renderer.pop();
+      if !stroke_layer.is_empty() {
+         stroke_layer.merge_into(renderer, paint_canvas);
+      }
+   }
+}
+
+impl Tool for ShapesTool {
+   fn name(&self) -> &'static str {
+      "shapes"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.shapes
+   }
+
+   /// Drops the shape that's being dragged out, if any.
+   fn deactivate(&mut self, _renderer: &mut Backend, _paint_canvas: &mut PaintCanvas) {
+      self.start = None;
+      self.preview = None;
+   }
+
+   /// Handles dragging out shapes, and drawing them onto the paint canvas once they're done.
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs {
+         ui,
+         input,
+         global_controls,
+         ..
+      }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      let mut position = viewport.to_viewport_space(ui.mouse_position(input), ui.size());
+      if grid::snapping_enabled(&config().grid) {
+         position = grid::snap(&config().grid, position);
+      }
+      match input.action(MouseButton::Left) {
+         (true, ButtonState::Pressed) if ui.hover(input) => self.start = Some(position),
+         (_, ButtonState::Released) => {
+            if let Some(start) = self.start.take() {
+               let shape = self.shape(start, position, global_controls);
+               Self::draw(ui, paint_canvas, &mut self.stroke_layer, &shape);
+               paint_canvas.commit_operation(ui);
+               global_controls.color_picker.mark_color_used();
+               self.outgoing.push(shape);
+            }
+         }
+         _ => (),
+      }
+      self.preview = self.start.map(|start| self.shape(start, position, global_controls));
+   }
+
+   /// Previews the shape that's being dragged out.
+   fn process_paint_canvas_layers(
+      &mut self,
+      renderer: &mut Backend,
+      _input: &Input,
+      _viewport: &Viewport,
+   ) {
+      if let Some(shape) = &self.preview {
+         renderer.push();
+         if shape.color.3 == 0 {
+            // Erasing shapes are previewed the same way as the brush's guide circle.
+            renderer.set_blend_mode(BlendMode::Invert);
+            Shape {
+               color: (255, 255, 255, 240),
+               ..*shape
+            }
+            .render(renderer);
+         } else {
+            shape.render(renderer);
+         }
+         renderer.pop();
+      }
+   }
+
+   /// Processes the color picker, the shape and fill selection, and the thickness slider on the
+   /// bottom bar.
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui,
+         input,
+         assets,
+         wm,
+         canvas_view,
+         global_controls,
+         ..
+      }: ToolArgs,
+   ) {
+      let mut picker_window = ColorPicker::picker_window_view();
+      view::layout::align(
+         &view::layout::padded(canvas_view, 16.0),
+         &mut picker_window,
+         (AlignH::Left, AlignV::Bottom),
+      );
+      global_controls.color_picker.process(
+         ui,
+         input,
+         ColorPickerArgs {
+            assets,
+            wm,
+            window_view: picker_window,
+            show_eraser: true,
+         },
+      );
+      ui.space(16.0);
+
+      // Draw the shape. Clicking the button cycles through the available shapes.
+      let height = ui.height();
+      if Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+         &assets.sans,
+         &assets.tr.shape.get(self.kind.name()),
+      )
+      .clicked()
+      {
+         self.kind = self.kind.next();
+      }
+      ui.space(8.0);
+
+      // Lines can't be filled in, so the fill is only shown for the other shapes.
+      if self.kind != ShapeKind::Line {
+         let fill = if self.filled {
+            &assets.tr.shape_filled
+         } else {
+            &assets.tr.shape_outline
+         };
+         if Button::with_text(
+            ui,
+            input,
+            &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+            &assets.sans,
+            fill,
+         )
+         .clicked()
+         {
+            self.filled = !self.filled;
+         }
+         ui.space(8.0);
+      }
+
+      // Filled shapes don't have an outline, so there's no thickness to pick.
+      if self.kind == ShapeKind::Line || !self.filled {
+         ui.space(8.0);
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.brush_thickness,
+            assets.colors.text,
+            None,
+         );
+         ui.space(16.0);
+
+         ui.push((192.0, ui.height()), Layout::Freeform);
+         self.thickness_slider.process(
+            ui,
+            input,
+            SliderArgs {
+               width: ui.width(),
+               color: assets.colors.slider,
+               focus: assets.colors.focus_ring,
+            },
+         );
+         ui.pop();
+         ui.space(8.0);
+
+         ui.horizontal_label(
+            &assets.sans_bold,
+            &self.thickness_slider.value().to_string(),
+            assets.colors.text,
+            Some((ui.height(), AlignH::Center)),
+         );
+      }
+   }
+
+   fn traffic_kind(&self, _payload: &[u8]) -> TrafficKind {
+      TrafficKind::Stroke
+   }
+
+   fn network_send(&mut self, net: Net, _global_controls: &GlobalControls) -> netcanv::Result<()> {
+      if !self.outgoing.is_empty() {
+         let packet = Packet::Shapes(self.outgoing.drain(..).collect());
+         net.send(self, PeerId::BROADCAST, packet)?;
+      }
+      Ok(())
+   }
+
+   fn network_receive(
+      &mut self,
+      renderer: &mut Backend,
+      _net: Net,
+      paint_canvas: &mut PaintCanvas,
+      _sender: PeerId,
+      payload: Vec<u8>,
+   ) -> netcanv::Result<()> {
+      let packet: Packet = deserialize_bincode(&payload)?;
+      match packet {
+         Packet::Shapes(shapes) => {
+            for shape in &shapes {
+               // Verify that the packet is correct.
+               let (a, b) = (shape.a(), shape.b());
+               ensure!(
+                  [a.x, a.y, b.x, b.y].iter().all(|x| x.is_finite()),
+                  Error::InvalidToolPacket
+               );
+               // Same as with the brush, allow for a little bit of leeway.
+               ensure!(
+                  (b.x - a.x).abs() <= Self::MAX_EXTENT + 0.1
+                     && (b.y - a.y).abs() <= Self::MAX_EXTENT + 0.1,
+                  Error::InvalidToolPacket
+               );
+               ensure!(
+                  shape.thickness as f32 <= Self::MAX_THICKNESS,
+                  Error::InvalidToolPacket
+               );
+               Self::draw(renderer, paint_canvas, &mut self.stroke_layer, shape);
+            }
+         }
+      }
+      Ok(())
+   }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+enum ShapeKind {
+   Line,
+   Rectangle,
+   Ellipse,
+}
+
+impl ShapeKind {
+   /// All the shapes, in the order they're cycled through.
+   const ALL: [Self; 3] = [Self::Line, Self::Rectangle, Self::Ellipse];
+
+   /// Returns the name of the shape, used as its translation key.
+   fn name(self) -> &'static str {
+      match self {
+         Self::Line => "line",
+         Self::Rectangle => "rectangle",
+         Self::Ellipse => "ellipse",
+      }
+   }
+
+   /// Returns the shape following this one, wrapping around to the first one.
+   fn next(self) -> Self {
+      let index = Self::ALL.iter().position(|&kind| kind == self).unwrap();
+      Self::ALL[(index + 1) % Self::ALL.len()]
+   }
+}
+
+/// A shape drawn onto the canvas.
+#[derive(Clone, Copy, Serialize, Deserialize)]
+struct Shape {
+   kind: ShapeKind,
+   /// The point where dragging the shape out started, in canvas space.
+   a: (f32, f32),
+   /// The point where dragging the shape out ended, in canvas space.
+   b: (f32, f32),
+   color: (u8, u8, u8, u8),
+   thickness: u8,
+   /// Whether the shape is filled in rather than outlined. Ignored for lines.
+   filled: bool,
+}
+
+impl Shape {
+   fn a(&self) -> Point {
+      point(self.a.0, self.a.1)
+   }
+
+   fn b(&self) -> Point {
+      point(self.b.0, self.b.1)
+   }
+
+   /// Returns the rectangle the shape is inscribed in.
+   fn bounds(&self) -> Rect {
+      Rect::new(self.a(), self.b() - self.a()).sort()
+   }
+
+   /// Returns the rectangle covered by the shape, including its outline.
+   fn coverage(&self) -> Rect {
+      let padding = self.thickness as f32 / 2.0 + 1.0;
+      let bounds = self.bounds();
+      Rect::new(
+         bounds.position - vector(padding, padding),
+         bounds.size + vector(padding, padding) * 2.0,
+      )
+   }
+
+   /// Renders the shape, in canvas space.
+   fn render(&self, renderer: &mut Backend) {
+      let (r, g, b, a) = self.color;
+      let color = Color::new(r, g, b, a);
+      let thickness = self.thickness as f32;
+      let bounds = self.bounds();
+      match (self.kind, self.filled) {
+         (ShapeKind::Line, _) => {
+            renderer.line(self.a(), self.b(), color, LineCap::Round, thickness);
+         }
+         (ShapeKind::Rectangle, true) => renderer.fill(bounds, color, 0.0),
+         (ShapeKind::Rectangle, false) => renderer.outline(bounds, color, 0.0, thickness),
+         (ShapeKind::Ellipse, true) => {
+            // The ellipse is filled in row by row, which keeps the result the same no matter how
+            // the renderer tessellates circles.
+            let center = bounds.center();
+            let radius = bounds.size / 2.0;
+            for row in 0..bounds.height().ceil() as u32 {
+               let y = bounds.top() + row as f32;
+               let dy = (y + 0.5 - center.y) / radius.y;
+               let half_width = radius.x * (1.0 - dy * dy).max(0.0).sqrt();
+               renderer.fill(
+                  Rect::new(
+                     point(center.x - half_width, y),
+                     vector(half_width * 2.0, 1.0),
+                  ),
+                  color,
+                  0.0,
+               );
+            }
+         }
+         (ShapeKind::Ellipse, false) => {
+            let center = bounds.center();
+            let radius = bounds.size / 2.0;
+            let segments = ((radius.x.max(radius.y) * TAU / 4.0).ceil() as usize).clamp(
+               ShapesTool::MIN_ELLIPSE_SEGMENTS,
+               ShapesTool::MAX_ELLIPSE_SEGMENTS,
+            );
+            let point_at = |segment: usize| {
+               let angle = segment as f32 / segments as f32 * TAU;
+               point(
+                  center.x + radius.x * angle.cos(),
+                  center.y + radius.y * angle.sin(),
+               )
+            };
+            for segment in 0..segments {
+               renderer.line(
+                  point_at(segment),
+                  point_at(segment + 1),
+                  color,
+                  LineCap::Round,
+                  thickness,
+               );
+            }
+         }
+      }
+   }
+}
+
+/// A shapes tool packet.
+#[derive(Serialize, Deserialize)]
+enum Packet {
+   Shapes(Vec<Shape>),
+}
@@ -0,0 +1,451 @@
+//! The Stamp brush. Paints with a small image loaded from a file, rather than a round tip.
+//!
+//! The image is stamped over and over along the path of the mouse. Peers are sent the image only
+//! once, and tell it apart from others by a hash of its PNG data; from then on, strokes are sent
+//! as the points the image was stamped at, so that everyone's canvas ends up looking the same.
+
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+
+use image::imageops::FilterType;
+use image::io::Reader as ImageReader;
+use image::{ImageFormat, RgbaImage};
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, vector, AlignH, Color, Layout, Point, Rect, Renderer};
+use netcanv_renderer::{BlendMode, RenderBackend};
+use nysa::global as bus;
+use rfd::AsyncFileDialog;
+use serde::{Deserialize, Serialize};
+
+use crate::app::paint::GlobalControls;
+use crate::assets::Assets;
+use crate::backend::winit::event::MouseButton;
+use crate::backend::{Backend, Image};
+use crate::common::deserialize_bincode;
+use crate::config::config;
+use crate::file_dialog;
+use crate::image_coder::ImageCoder;
+use crate::keymap::KeyBinding;
+use crate::net::stats::TrafficKind;
+use crate::paint_canvas::PaintCanvas;
+use crate::ui::{
+   Button, ButtonArgs, ButtonState, Slider, SliderArgs, SliderStep, UiElements, UiInput,
+};
+use crate::viewport::Viewport;
+use crate::Error;
+
+use super::{Net, Tool, ToolArgs};
+
+/// A bus message requesting that the stamp be loaded from the given file.
+pub struct LoadStamp(pub PathBuf);
+
+/// An image used as the tip of the stamp brush.
+struct Stamp {
+   /// The image's PNG data, sent to peers that don't have the stamp yet.
+   data: Vec<u8>,
+   dimensions: (u32, u32),
+   image: Image,
+}
+
+impl Stamp {
+   fn new(renderer: &mut Backend, data: Vec<u8>, pixels: &RgbaImage) -> Self {
+      Self {
+         data,
+         dimensions: pixels.dimensions(),
+         image: renderer.create_image_from_rgba(pixels.width(), pixels.height(), pixels),
+      }
+   }
+
+   /// Returns the rectangle the stamp covers when stamped at the given point. The stamp is scaled
+   /// such that its longer side is as long as the given size.
+   fn rect(&self, center: Point, size: f32) -> Rect {
+      let (width, height) = self.dimensions;
+      let scale = size / width.max(height) as f32;
+      let extent = vector(width as f32 * scale, height as f32 * scale);
+      Rect::new(center - extent / 2.0, extent)
+   }
+}
+
+pub struct StampTool {
+   icon: Image,
+   size_slider: Slider,
+   /// All the stamps we know of, be it ours or those of peers, keyed by their hash.
+   stamps: HashMap<u64, Stamp>,
+   /// The hash of the stamp loaded by the user. `None` if no stamp was loaded yet.
+   current: Option<u64>,
+   /// The stamps that were already sent to everyone.
+   sent: HashSet<u64>,
+   /// The stamps that were requested from peers, but haven't arrived yet.
+   requested: HashSet<u64>,
+   /// Strokes received from peers, waiting for the stamps they use to arrive.
+   pending: HashMap<u64, Vec<Dabs>>,
+   /// The point the stamp was last stamped at. `None` if no stroke is being drawn.
+   last_dab: Option<Point>,
+   /// The position of the mouse, in canvas space.
+   mouse_position: Point,
+   /// The strokes drawn since the last network update.
+   outgoing: Vec<Dabs>,
+}
+
+impl StampTool {
+   /// The maximum width and height of a stamp image. Larger images are scaled down to fit.
+   const MAX_IMAGE_SIZE: u32 = 128;
+   /// The maximum length of a stamp's PNG data. PNG can end up larger than the raw pixels if the
+   /// image compresses badly, so this leaves some headroom.
+   const MAX_DATA_LEN: usize = 2 * (Self::MAX_IMAGE_SIZE * Self::MAX_IMAGE_SIZE * 4) as usize;
+   const MIN_SIZE: f32 = 4.0;
+   const MAX_SIZE: f32 = 128.0;
+   const DEFAULT_SIZE: f32 = 32.0;
+   /// The distance between two consecutive stamps, relative to the size.
+   const SPACING: f32 = 0.25;
+
+   /// Creates an instance of the stamp brush.
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/stamp.svg")),
+         size_slider: Slider::new(
+            Self::DEFAULT_SIZE,
+            Self::MIN_SIZE,
+            Self::MAX_SIZE,
+            SliderStep::Discrete(1.0),
+         ),
+         stamps: HashMap::new(),
+         current: None,
+         sent: HashSet::new(),
+         requested: HashSet::new(),
+         pending: HashMap::new(),
+         last_dab: None,
+         mouse_position: point(0.0, 0.0),
+         outgoing: Vec::new(),
+      }
+   }
+
+   /// Loads a stamp from a file, scaling it down if it's too large. Returns the stamp's hash.
+   fn load(&mut self, renderer: &mut Backend, path: &Path) -> netcanv::Result<u64> {
+      let mut pixels = ImageReader::open(path)?.decode()?.into_rgba8();
+      let (width, height) = pixels.dimensions();
+      if width.max(height) > Self::MAX_IMAGE_SIZE {
+         let scale = Self::MAX_IMAGE_SIZE as f32 / width.max(height) as f32;
+         pixels = image::imageops::resize(
+            &pixels,
+            ((width as f32 * scale).round() as u32).max(1),
+            ((height as f32 * scale).round() as u32).max(1),
+            FilterType::Triangle,
+         );
+      }
+      let data = ImageCoder::encode_png_data_sync(pixels.clone())?;
+      let hash = fnv1a(&data);
+      self.stamps.entry(hash).or_insert_with(|| Stamp::new(renderer, data, &pixels));
+      Ok(hash)
+   }
+
+   /// Stamps the current stamp at the given point, and queues it up to be sent to peers.
+   fn dab(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas, at: Point) {
+      let Some(hash) = self.current else { return };
+      let size = self.size_slider.value() as u8;
+      let continues_stroke = matches!(
+         self.outgoing.last(),
+         Some(dabs) if dabs.stamp == hash && dabs.size == size
+      );
+      if !continues_stroke {
+         self.outgoing.push(Dabs {
+            stamp: hash,
+            size,
+            points: Vec::new(),
+         });
+      }
+      self.outgoing.last_mut().unwrap().points.push((at.x, at.y));
+      Self::draw(
+         renderer,
+         paint_canvas,
+         &self.stamps[&hash],
+         size,
+         &[(at.x, at.y)],
+      );
+      self.last_dab = Some(at);
+   }
+
+   /// Draws the stamp onto the paint canvas at each of the given points.
+   fn draw(
+      renderer: &mut Backend,
+      paint_canvas: &mut PaintCanvas,
+      stamp: &Stamp,
+      size: u8,
+      points: &[(f32, f32)],
+   ) {
+      renderer.push();
+      renderer.set_blend_mode(BlendMode::Alpha);
+      for &(x, y) in points {
+         let rect = stamp.rect(point(x, y), size as f32);
+         paint_canvas.draw(renderer, rect, |renderer| {
+            renderer.image(rect, &stamp.image);
+         });
+      }
+      renderer.pop();
+   }
+}
+
+impl Tool for StampTool {
+   fn name(&self) -> &'static str {
+      "stamp"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.stamp
+   }
+
+   /// Finishes the stroke, if the tool is switched away from in the middle of one.
+   fn deactivate(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas) {
+      if self.last_dab.take().is_some() {
+         paint_canvas.commit_operation(renderer);
+      }
+   }
+
+   /// Loads stamps picked in the file dialog.
+   fn process_background_jobs(&mut self, ToolArgs { ui, .. }: ToolArgs, _: &mut PaintCanvas) {
+      for message in &bus::retrieve_all::<LoadStamp>() {
+         let LoadStamp(path) = message.consume();
+         self.current = Some(catch!(self.load(ui, &path)));
+      }
+   }
+
+   /// Handles stamping along the path of the mouse.
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs { ui, input, .. }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      self.mouse_position = viewport.to_viewport_space(ui.mouse_position(input), ui.size());
+      if self.current.is_none() {
+         return;
+      }
+      match input.action(MouseButton::Left) {
+         (true, ButtonState::Pressed) if ui.hover(input) => {
+            self.dab(ui, paint_canvas, self.mouse_position);
+         }
+         (_, ButtonState::Down) => {
+            if let Some(mut last_dab) = self.last_dab {
+               // Walk towards the mouse, stamping at even intervals along the way. Whatever
+               // distance is left over is carried over to the next frame.
+               let spacing = (self.size_slider.value() * Self::SPACING).max(1.0);
+               let delta = self.mouse_position - last_dab;
+               let distance = delta.x.hypot(delta.y);
+               let direction = delta / distance;
+               for _ in 0..(distance / spacing) as usize {
+                  last_dab = last_dab + direction * spacing;
+                  self.dab(ui, paint_canvas, last_dab);
+               }
+            }
+         }
+         (_, ButtonState::Released) if self.last_dab.is_some() => {
+            self.last_dab = None;
+            paint_canvas.commit_operation(ui);
+         }
+         _ => (),
+      }
+   }
+
+   /// Draws the outline of the stamp around the mouse.
+   fn process_paint_canvas_overlays(
+      &mut self,
+      ToolArgs { ui, input, .. }: ToolArgs,
+      viewport: &Viewport,
+   ) {
+      let Some(stamp) = self.current.and_then(|hash| self.stamps.get(&hash)) else {
+         return;
+      };
+      if input.mouse_active() {
+         let position = viewport.to_screen_space(self.mouse_position, ui.size());
+         let rect = stamp.rect(position, self.size_slider.value() * viewport.zoom());
+         let renderer = ui.render();
+         renderer.push();
+         // Same as the brush's guide circle, inverting makes the outline visible on most
+         // backgrounds.
+         renderer.set_blend_mode(BlendMode::Invert);
+         renderer.outline(rect, Color::WHITE.with_alpha(240), 0.0, 1.0);
+         renderer.pop();
+      }
+   }
+
+   /// Processes the button for loading a stamp, and the size slider on the bottom bar.
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui, input, assets, ..
+      }: ToolArgs,
+   ) {
+      let height = ui.height();
+      if Button::with_text(
+         ui,
+         input,
+         &ButtonArgs::new(ui, &assets.colors.button).height(height).corner_radius(4.0),
+         &assets.sans,
+         &assets.tr.load_stamp,
+      )
+      .clicked()
+      {
+         let dialog = AsyncFileDialog::new().add_filter(&assets.tr.fd_png_file, &["png"]);
+         file_dialog::pick_file(dialog, LoadStamp);
+      }
+      ui.space(16.0);
+
+      if self.current.is_none() {
+         ui.horizontal_label(
+            &assets.sans,
+            &assets.tr.stamp_hint,
+            assets.colors.text,
+            None,
+         );
+         return;
+      }
+
+      // Draw the size: its slider and value display.
+      ui.horizontal_label(
+         &assets.sans,
+         &assets.tr.stamp_size,
+         assets.colors.text,
+         None,
+      );
+      ui.space(16.0);
+
+      ui.push((128.0, ui.height()), Layout::Freeform);
+      self.size_slider.process(
+         ui,
+         input,
+         SliderArgs {
+            width: ui.width(),
+            color: assets.colors.slider,
+            focus: assets.colors.focus_ring,
+         },
+      );
+      ui.pop();
+      ui.space(8.0);
+
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &self.size_slider.value().to_string(),
+         assets.colors.text,
+         Some((ui.height(), AlignH::Center)),
+      );
+   }
+
+   fn traffic_kind(&self, payload: &[u8]) -> TrafficKind {
+      // Same as in the brush, only the variant index is needed.
+      match deserialize_bincode::<u32>(payload) {
+         Ok(2) => TrafficKind::Stroke,
+         _ => TrafficKind::Other,
+      }
+   }
+
+   fn network_send(&mut self, net: Net, _global_controls: &GlobalControls) -> netcanv::Result<()> {
+      for dabs in std::mem::take(&mut self.outgoing) {
+         // The stamp has to be sent before the first stroke using it, so that peers can draw the
+         // stroke right away.
+         if self.sent.insert(dabs.stamp) {
+            let data = self.stamps[&dabs.stamp].data.clone();
+            net.send(self, PeerId::BROADCAST, Packet::Stamp(data))?;
+         }
+         net.send(self, PeerId::BROADCAST, Packet::Dabs(dabs))?;
+      }
+      Ok(())
+   }
+
+   fn network_receive(
+      &mut self,
+      renderer: &mut Backend,
+      net: Net,
+      paint_canvas: &mut PaintCanvas,
+      sender: PeerId,
+      payload: Vec<u8>,
+   ) -> netcanv::Result<()> {
+      let packet: Packet = deserialize_bincode(&payload)?;
+      match packet {
+         Packet::Stamp(data) => {
+            ensure!(data.len() <= Self::MAX_DATA_LEN, Error::InvalidToolPacket);
+            let hash = fnv1a(&data);
+            if self.stamps.contains_key(&hash) {
+               return Ok(());
+            }
+            let pixels = image::load_from_memory_with_format(&data, ImageFormat::Png)
+               .map_err(|_| Error::InvalidToolPacket)?
+               .into_rgba8();
+            let (width, height) = pixels.dimensions();
+            ensure!(
+               width.max(height) <= Self::MAX_IMAGE_SIZE,
+               Error::InvalidToolPacket
+            );
+            let stamp = Stamp::new(renderer, data, &pixels);
+            // Catch up on the strokes that were waiting for the stamp.
+            self.requested.remove(&hash);
+            for dabs in self.pending.remove(&hash).unwrap_or_default() {
+               Self::draw(renderer, paint_canvas, &stamp, dabs.size, &dabs.points);
+            }
+            self.stamps.insert(hash, stamp);
+         }
+         Packet::RequestStamp(hash) => {
+            if let Some(stamp) = self.stamps.get(&hash) {
+               net.send(self, sender, Packet::Stamp(stamp.data.clone()))?;
+            }
+         }
+         Packet::Dabs(dabs) => {
+            // Verify that the packet is correct.
+            ensure!(
+               (Self::MIN_SIZE..=Self::MAX_SIZE).contains(&(dabs.size as f32)),
+               Error::InvalidToolPacket
+            );
+            ensure!(
+               dabs.points.iter().all(|(x, y)| x.is_finite() && y.is_finite()),
+               Error::InvalidToolPacket
+            );
+            match self.stamps.get(&dabs.stamp) {
+               Some(stamp) => Self::draw(renderer, paint_canvas, stamp, dabs.size, &dabs.points),
+               None => {
+                  // We joined after the stamp was sent out, so it has to be requested from the
+                  // person drawing with it.
+                  if self.requested.insert(dabs.stamp) {
+                     net.send(self, sender, Packet::RequestStamp(dabs.stamp))?;
+                  }
+                  self.pending.entry(dabs.stamp).or_default().push(dabs);
+               }
+            }
+         }
+      }
+      Ok(())
+   }
+}
+
+/// Computes the 64-bit FNV-1a hash of the given data. Unlike the standard library's hasher, it's
+/// guaranteed to give the same result on everyone's computer.
+fn fnv1a(data: &[u8]) -> u64 {
+   data.iter().fold(0xcbf29ce484222325, |hash, &byte| {
+      (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+   })
+}
+
+/// Points a stamp was stamped at.
+#[derive(Serialize, Deserialize)]
+struct Dabs {
+   /// The hash of the stamp's PNG data.
+   stamp: u64,
+   /// The length of the stamp's longer side, in pixels.
+   size: u8,
+   /// The centers of the stamps, in canvas space.
+   points: Vec<(f32, f32)>,
+}
+
+/// A stamp brush packet.
+///
+/// The order of the variants matters for [`StampTool::traffic_kind`].
+#[derive(Serialize, Deserialize)]
+enum Packet {
+   /// A stamp's PNG data.
+   Stamp(Vec<u8>),
+   /// A request for the stamp with the given hash.
+   RequestStamp(u64),
+   Dabs(Dabs),
+}
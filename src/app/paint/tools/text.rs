@@ -0,0 +1,334 @@
+//! The Text tool. Stamps lines of text onto the canvas.
+//!
+//! Clicking on the canvas places a text box, whose contents are typed in on the bottom bar. The
+//! text is only drawn onto the canvas once Enter is pressed; until then, it's previewed in place.
+
+use crate::backend::winit::event::MouseButton;
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, vector, AlignH, AlignV, Color, Layout, Point, Rect, Renderer};
+use netcanv_renderer::{Font as FontTrait, RenderBackend};
+use serde::{Deserialize, Serialize};
+
+use crate::app::paint::GlobalControls;
+use crate::assets::{self, Assets};
+use crate::backend::{Backend, Font, Image};
+use crate::common::deserialize_bincode;
+use crate::config::config;
+use crate::keymap::KeyBinding;
+use crate::net::stats::TrafficKind;
+use crate::paint_canvas::PaintCanvas;
+use crate::ui::{
+   view, ButtonState, ColorPicker, ColorPickerArgs, Focus, Slider, SliderArgs, SliderStep,
+   TextField, TextFieldArgs, UiElements, UiInput,
+};
+use crate::viewport::Viewport;
+use crate::Error;
+
+use super::{Net, Tool, ToolArgs};
+
+pub struct TextTool {
+   icon: Image,
+   /// The font text is stamped with. Its size is changed for each stamp.
+   font: Font,
+   size_slider: Slider,
+   text_field: TextField,
+   /// The top-left corner of the text box, in canvas space. `None` if no text box is placed.
+   anchor: Option<Point>,
+   /// Whether Enter was pressed in the text field since the canvas was last processed.
+   stamp_requested: bool,
+   /// The text stamped since the last network update.
+   outgoing: Vec<Stamp>,
+}
+
+impl TextTool {
+   const MIN_SIZE: f32 = 8.0;
+   const MAX_SIZE: f32 = 128.0;
+   const DEFAULT_SIZE: f32 = 24.0;
+   /// The maximum length of stamped text, in bytes.
+   const MAX_TEXT_LEN: usize = 256;
+   const TEXT_FIELD_WIDTH: f32 = 320.0;
+
+   /// Creates an instance of the text tool.
+   pub fn new(renderer: &mut Backend) -> Self {
+      Self {
+         icon: Assets::load_svg(renderer, include_bytes!("../../../assets/icons/text.svg")),
+         font: renderer.create_font_from_memory(assets::SANS_TTF, Self::DEFAULT_SIZE),
+         size_slider: Slider::new(
+            Self::DEFAULT_SIZE,
+            Self::MIN_SIZE,
+            Self::MAX_SIZE,
+            SliderStep::Discrete(1.0),
+         ),
+         text_field: TextField::new(None),
+         anchor: None,
+         stamp_requested: false,
+         outgoing: Vec::new(),
+      }
+   }
+
+   /// Returns the rectangle covered by the given text, with its top-left corner at the given
+   /// position.
+   fn coverage(font: &Font, position: Point, text: &str) -> Rect {
+      Rect::new(position, vector(font.text_width(text), font.height()))
+   }
+
+   /// Draws the stamp onto the paint canvas.
+   fn stamp(renderer: &mut Backend, paint_canvas: &mut PaintCanvas, font: &Font, stamp: &Stamp) {
+      let font = font.with_size(stamp.size as f32);
+      let position = point(stamp.position.0, stamp.position.1);
+      let rect = Self::coverage(&font, position, &stamp.text);
+      let (r, g, b, a) = stamp.color;
+      paint_canvas.draw(renderer, rect, |renderer| {
+         renderer.text(
+            rect,
+            &font,
+            &stamp.text,
+            Color::new(r, g, b, a),
+            (AlignH::Left, AlignV::Top),
+         );
+      });
+   }
+
+   /// Removes the text box, along with its contents.
+   fn cancel(&mut self) {
+      self.anchor = None;
+      self.text_field.set_text(String::new());
+      self.text_field.set_focus(false);
+   }
+}
+
+impl Tool for TextTool {
+   fn name(&self) -> &'static str {
+      "text"
+   }
+
+   fn icon(&self) -> &Image {
+      &self.icon
+   }
+
+   fn key_shortcut(&self) -> KeyBinding {
+      config().keymap.tools.text
+   }
+
+   fn deactivate(&mut self, _renderer: &mut Backend, _paint_canvas: &mut PaintCanvas) {
+      self.cancel();
+   }
+
+   fn has_keyboard_focus(&self) -> bool {
+      self.anchor.is_some() && self.text_field.focused()
+   }
+
+   /// Handles placing the text box and stamping its contents onto the canvas.
+   fn process_paint_canvas_input(
+      &mut self,
+      ToolArgs {
+         ui,
+         input,
+         global_controls,
+         ..
+      }: ToolArgs,
+      paint_canvas: &mut PaintCanvas,
+      viewport: &Viewport,
+   ) {
+      if std::mem::take(&mut self.stamp_requested) {
+         if let Some(anchor) = self.anchor {
+            let mut text = self.text_field.text().to_owned();
+            while text.len() > Self::MAX_TEXT_LEN {
+               text.pop();
+            }
+            if !text.is_empty() {
+               let Color { r, g, b, a } = global_controls.color_picker.color();
+               let stamp = Stamp {
+                  position: (anchor.x, anchor.y),
+                  size: self.size_slider.value() as u8,
+                  color: (r, g, b, a),
+                  text,
+               };
+               Self::stamp(ui, paint_canvas, &self.font, &stamp);
+               paint_canvas.commit_operation(ui);
+               global_controls.color_picker.mark_color_used();
+               self.outgoing.push(stamp);
+            }
+            self.cancel();
+         }
+      }
+
+      if input.action(MouseButton::Left) == (true, ButtonState::Pressed) && ui.hover(input) {
+         let position = viewport.to_viewport_space(ui.mouse_position(input), ui.size());
+         self.anchor = Some(point(position.x.round(), position.y.round()));
+         self.text_field.set_focus(true);
+      }
+   }
+
+   /// Previews the text in the text box.
+   fn process_paint_canvas_overlays(
+      &mut self,
+      ToolArgs {
+         ui,
+         assets,
+         global_controls,
+         ..
+      }: ToolArgs,
+      viewport: &Viewport,
+   ) {
+      if let Some(anchor) = self.anchor {
+         let font = self.font.with_size(self.size_slider.value() * viewport.zoom());
+         let text = self.text_field.text();
+         let position = viewport.to_screen_space(anchor, ui.size());
+         let rect = Self::coverage(&font, position, text);
+         let renderer = ui.render();
+         renderer.text(
+            rect,
+            &font,
+            text,
+            global_controls.color_picker.color(),
+            (AlignH::Left, AlignV::Top),
+         );
+         // Leave some room for the caret when the box is empty.
+         let outline = Rect::new(
+            rect.position - vector(4.0, 4.0),
+            vector(rect.width().max(font.height() / 2.0), rect.height()) + vector(8.0, 8.0),
+         );
+         renderer.outline(outline, assets.colors.text, 2.0, 1.0);
+      }
+   }
+
+   /// Processes the color picker, the text size slider, and the text field on the bottom bar.
+   fn process_bottom_bar(
+      &mut self,
+      ToolArgs {
+         ui,
+         input,
+         assets,
+         wm,
+         canvas_view,
+         global_controls,
+         ..
+      }: ToolArgs,
+   ) {
+      let mut picker_window = ColorPicker::picker_window_view();
+      view::layout::align(
+         &view::layout::padded(canvas_view, 16.0),
+         &mut picker_window,
+         (AlignH::Left, AlignV::Bottom),
+      );
+      global_controls.color_picker.process(
+         ui,
+         input,
+         ColorPickerArgs {
+            assets,
+            wm,
+            window_view: picker_window,
+            show_eraser: false,
+         },
+      );
+      ui.space(16.0);
+
+      // Draw the size: its slider and value display.
+      ui.horizontal_label(&assets.sans, &assets.tr.text_size, assets.colors.text, None);
+      ui.space(16.0);
+
+      ui.push((128.0, ui.height()), Layout::Freeform);
+      self.size_slider.process(
+         ui,
+         input,
+         SliderArgs {
+            width: ui.width(),
+            color: assets.colors.slider,
+            focus: assets.colors.focus_ring,
+         },
+      );
+      ui.pop();
+      ui.space(8.0);
+
+      ui.horizontal_label(
+         &assets.sans_bold,
+         &self.size_slider.value().to_string(),
+         assets.colors.text,
+         Some((ui.height(), AlignH::Center)),
+      );
+      ui.space(16.0);
+
+      if self.anchor.is_none() {
+         ui.horizontal_label(&assets.sans, &assets.tr.text_hint, assets.colors.text, None);
+         return;
+      }
+
+      // Draw the text field.
+      ui.push((Self::TEXT_FIELD_WIDTH, ui.height()), Layout::Freeform);
+      ui.push(
+         (Self::TEXT_FIELD_WIDTH, TextField::height(&assets.sans)),
+         Layout::Freeform,
+      );
+      ui.align((AlignH::Left, AlignV::Middle));
+      let result = self.text_field.process(
+         ui,
+         input,
+         TextFieldArgs {
+            width: Self::TEXT_FIELD_WIDTH,
+            colors: &assets.colors.text_field,
+            hint: Some(&assets.tr.text_field_hint),
+            font: &assets.sans,
+         },
+      );
+      if result.done() {
+         self.stamp_requested = true;
+      }
+      ui.pop();
+      ui.pop();
+   }
+
+   fn traffic_kind(&self, _payload: &[u8]) -> TrafficKind {
+      TrafficKind::Stroke
+   }
+
+   fn network_send(&mut self, net: Net, _global_controls: &GlobalControls) -> netcanv::Result<()> {
+      for stamp in self.outgoing.drain(..) {
+         net.send(self, PeerId::BROADCAST, Packet::Stamp(stamp))?;
+      }
+      Ok(())
+   }
+
+   fn network_receive(
+      &mut self,
+      renderer: &mut Backend,
+      _net: Net,
+      paint_canvas: &mut PaintCanvas,
+      _sender: PeerId,
+      payload: Vec<u8>,
+   ) -> netcanv::Result<()> {
+      let packet: Packet = deserialize_bincode(&payload)?;
+      match packet {
+         Packet::Stamp(stamp) => {
+            let size = stamp.size as f32;
+            ensure!(
+               (Self::MIN_SIZE..=Self::MAX_SIZE).contains(&size),
+               Error::InvalidToolPacket
+            );
+            ensure!(
+               stamp.text.len() <= Self::MAX_TEXT_LEN,
+               Error::InvalidToolPacket
+            );
+            Self::stamp(renderer, paint_canvas, &self.font, &stamp);
+         }
+      }
+      Ok(())
+   }
+}
+
+/// Text stamped onto the canvas.
+#[derive(Serialize, Deserialize)]
+struct Stamp {
+   /// The top-left corner of the text, in canvas space.
+   position: (f32, f32),
+   /// The font size, in pixels.
+   size: u8,
+   color: (u8, u8, u8, u8),
+   text: String,
+}
+
+/// A text tool packet.
+#[derive(Serialize, Deserialize)]
+enum Packet {
+   Stamp(Stamp),
+}
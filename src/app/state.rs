@@ -26,6 +26,13 @@ pub trait AppState {
    /// app state may be constructed, boxed, and returned.
    fn next_state(self: Box<Self>, renderer: &mut Backend) -> Box<dyn AppState>;
 
+   /// Called when the user asks to close the window. Returns whether NetCanv should exit.
+   ///
+   /// States holding work that would be lost on exit should ask the user for confirmation here.
+   fn confirm_exit(&mut self) -> bool {
+      true
+   }
+
    /// Dismantles the state before exiting the app.
    fn exit(self: Box<Self>);
 }
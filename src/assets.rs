@@ -23,7 +23,7 @@ use crate::ui::{
 };
 use crate::Error;
 
-const SANS_TTF: &[u8] = include_bytes!("assets/fonts/Barlow-Medium.ttf");
+pub const SANS_TTF: &[u8] = include_bytes!("assets/fonts/Barlow-Medium.ttf");
 const SANS_BOLD_TTF: &[u8] = include_bytes!("assets/fonts/Barlow-Bold.ttf");
 const MONOSPACE_TTF: &[u8] = include_bytes!("assets/fonts/RobotoMono-Medium.ttf");
 
@@ -43,6 +43,7 @@ const CHEVRON_DOWN_SVG: &[u8] = include_bytes!("assets/icons/chevron-down.svg");
 const ERASER_SVG: &[u8] = include_bytes!("assets/icons/eraser.svg");
 const MENU_SVG: &[u8] = include_bytes!("assets/icons/menu.svg");
 const COPY_SVG: &[u8] = include_bytes!("assets/icons/copy.svg");
+const LINK_SVG: &[u8] = include_bytes!("assets/icons/link.svg");
 const DRAG_HORIZONTAL_SVG: &[u8] = include_bytes!("assets/icons/drag-horizontal.svg");
 const INFO_SVG: &[u8] = include_bytes!("assets/icons/info.svg");
 const ERROR_SVG: &[u8] = include_bytes!("assets/icons/error.svg");
@@ -52,6 +53,8 @@ const DARK_MODE_SVG: &[u8] = include_bytes!("assets/icons/dark-mode.svg");
 const LIGHT_MODE_SVG: &[u8] = include_bytes!("assets/icons/light-mode.svg");
 const TRANSLATE_SVG: &[u8] = include_bytes!("assets/icons/translate.svg");
 const LEGAL_SVG: &[u8] = include_bytes!("assets/icons/legal.svg");
+const CHART_BAR_SVG: &[u8] = include_bytes!("assets/icons/chart-bar.svg");
+const BRUSH_SVG: &[u8] = include_bytes!("assets/icons/brush.svg");
 const WINDOW_CLOSE_SVG: &[u8] = include_bytes!("assets/icons/window-close.svg");
 const WINDOW_PIN_SVG: &[u8] = include_bytes!("assets/icons/window-pin.svg");
 const WINDOW_PINNED_SVG: &[u8] = include_bytes!("assets/icons/window-pinned.svg");
@@ -92,6 +95,7 @@ pub fn open_license_page() -> netcanv::Result<()> {
 pub struct NavigationIcons {
    pub menu: Image,
    pub copy: Image,
+   pub link: Image,
    pub drag_horizontal: Image,
 }
 
@@ -113,6 +117,8 @@ pub struct LobbyIcons {
    pub light_mode: Image,
    pub translate: Image,
    pub legal: Image,
+   pub draw_offline: Image,
+   pub usage_statistics: Image,
 }
 
 pub struct WindowIcons {
@@ -240,10 +246,13 @@ impl Assets {
                light_mode: Self::load_svg(renderer, LIGHT_MODE_SVG),
                translate: Self::load_svg(renderer, TRANSLATE_SVG),
                legal: Self::load_svg(renderer, LEGAL_SVG),
+               draw_offline: Self::load_svg(renderer, BRUSH_SVG),
+               usage_statistics: Self::load_svg(renderer, CHART_BAR_SVG),
             },
             navigation: NavigationIcons {
                menu: Self::load_svg(renderer, MENU_SVG),
                copy: Self::load_svg(renderer, COPY_SVG),
+               link: Self::load_svg(renderer, LINK_SVG),
                drag_horizontal: Self::load_svg(renderer, DRAG_HORIZONTAL_SVG),
             },
             status: StatusIcons {
@@ -425,6 +434,7 @@ pub struct ColorScheme {
    pub panel: Color,
    pub separator: Color,
    pub error: Color,
+   pub focus_ring: Color,
 
    pub button: ButtonColors,
    pub action_button: ButtonColors,
@@ -491,12 +501,14 @@ impl ColorScheme {
       let white_pressed = colors.gray_90.with_alpha(16);
 
       let separator = colors.gray_60;
+      let focus_ring = colors.blue_50;
 
       Self {
          text: colors.gray_00,
          panel: colors.gray_80,
          separator,
          error: colors.red_30,
+         focus_ring,
 
          button: ButtonColors {
             fill: Color::TRANSPARENT,
@@ -504,6 +516,7 @@ impl ColorScheme {
             text: colors.gray_00,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          action_button: ButtonColors {
             fill: Color::TRANSPARENT,
@@ -511,6 +524,7 @@ impl ColorScheme {
             text: colors.gray_00,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          toolbar_button: ButtonColors {
             fill: Color::TRANSPARENT,
@@ -518,6 +532,7 @@ impl ColorScheme {
             text: colors.gray_20,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          selected_toolbar_button: ButtonColors {
             fill: colors.gray_20,
@@ -525,6 +540,7 @@ impl ColorScheme {
             text: colors.gray_80,
             hover: white_hover,
             pressed: white_pressed,
+            focus: focus_ring,
          },
          radio_button: RadioButtonColors {
             normal: ButtonColors {
@@ -533,6 +549,7 @@ impl ColorScheme {
                text: colors.gray_00,
                hover: black_hover,
                pressed: black_pressed,
+               focus: focus_ring,
             },
             selected: ButtonColors {
                fill: colors.gray_20,
@@ -540,6 +557,7 @@ impl ColorScheme {
                text: colors.gray_80,
                hover: white_hover,
                pressed: white_pressed,
+               focus: focus_ring,
             },
          },
          slider: colors.gray_00,
@@ -548,6 +566,7 @@ impl ColorScheme {
             text: colors.gray_00,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          text_field: TextFieldColors {
             outline: colors.gray_50,
@@ -594,6 +613,7 @@ impl ColorScheme {
 
          lobby: LobbyColors {
             background: colors.blue_50,
+            relay_reachable: Color::rgb(0x22B00C),
          },
       }
    }
@@ -622,12 +642,14 @@ impl From<CommonColors> for ColorScheme {
       let white_pressed = gray_90.with_alpha(16);
 
       let separator = gray_60;
+      let focus_ring = blue_50;
 
       Self {
          text: gray_00,
          panel: gray_80,
          separator,
          error: red_30,
+         focus_ring,
 
          button: ButtonColors {
             fill: Color::TRANSPARENT,
@@ -635,6 +657,7 @@ impl From<CommonColors> for ColorScheme {
             text: gray_00,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          action_button: ButtonColors {
             fill: Color::TRANSPARENT,
@@ -642,6 +665,7 @@ impl From<CommonColors> for ColorScheme {
             text: gray_00,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          toolbar_button: ButtonColors {
             fill: Color::TRANSPARENT,
@@ -649,6 +673,7 @@ impl From<CommonColors> for ColorScheme {
             text: gray_20,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          selected_toolbar_button: ButtonColors {
             fill: gray_20,
@@ -656,6 +681,7 @@ impl From<CommonColors> for ColorScheme {
             text: gray_80,
             hover: white_hover,
             pressed: white_pressed,
+            focus: focus_ring,
          },
          radio_button: RadioButtonColors {
             normal: ButtonColors {
@@ -664,6 +690,7 @@ impl From<CommonColors> for ColorScheme {
                text: gray_00,
                hover: black_hover,
                pressed: black_pressed,
+               focus: focus_ring,
             },
             selected: ButtonColors {
                fill: gray_20,
@@ -671,6 +698,7 @@ impl From<CommonColors> for ColorScheme {
                text: gray_80,
                hover: white_hover,
                pressed: white_pressed,
+               focus: focus_ring,
             },
          },
          slider: gray_00,
@@ -679,6 +707,7 @@ impl From<CommonColors> for ColorScheme {
             text: gray_00,
             hover: black_hover,
             pressed: black_pressed,
+            focus: focus_ring,
          },
          text_field: TextFieldColors {
             outline: gray_50,
@@ -725,6 +754,7 @@ impl From<CommonColors> for ColorScheme {
 
          lobby: LobbyColors {
             background: blue_50,
+            relay_reachable: Color::rgb(0x22B00C),
          },
       }
    }
@@ -20,57 +20,411 @@ lobby-join-a-room =
       and enter it into the text field below.
 lobby-room-id =
    .label = { room-id }
-   .hint = 6 characters
+   .hint = 6 characters, invite, or link
 lobby-join = Join
 
 lobby-host-a-new-room =
    .title = Host a new room
-   .description =
-      Create a blank canvas, or load an existing one from file,
-      and share the { room-id } with your friends.
+lobby-room-name =
+   .label = Room name
+   .hint = Optional
+lobby-room-description =
+   .label = Description
+   .hint = Optional
+lobby-custom-room-id =
+   .label = { room-id }
+   .hint = Random
 lobby-host = Host
 lobby-host-from-file = from File
+lobby-public = Public
+lobby-lan = LAN
+lobby-direct = Direct
+
+lobby-rooms = Rooms
+lobby-recent-rooms = Recent
+lobby-no-recent-rooms =
+   Rooms you join or host will show up here,
+   so that you can get back to them in one click.
+lobby-rejoin = Rejoin
+lobby-rehost = Rehost
+lobby-recent-room-as = as { $nickname }, { $time }
+lobby-recent-room-from-file = { $file }, { $time }
+lobby-recent-just-now = just now
+lobby-recent-minutes-ago = { $minutes } min ago
+lobby-recent-hours-ago = { $hours } h ago
+lobby-recent-days-ago = { $days } d ago
+lobby-public-rooms = Public
+lobby-fetching-public-rooms = Fetching the list of rooms…
+lobby-no-public-rooms =
+   There are no public rooms on this relay right now.
+   Host one and make it public to see it here.
+lobby-public-room-players =
+   { $players ->
+      [one] 1 person
+      *[other] { $players } people
+   }
+lobby-public-room-invite-only = invite only
+lobby-public-rooms-hint = Click Join or double-click a room to join it
+lobby-refresh = Refresh
+lobby-lan-rooms = LAN
+lobby-no-lan-rooms =
+   There are no rooms on your local network right now.
+   Host one with LAN turned on to see it here.
+
+lobby-field-required = Required
+lobby-field-too-long = At most { $max-length } characters
+lobby-field-room-id-length = Must have { $length } or { $invite-length } characters
+lobby-field-length = Must have { $length } characters
+lobby-field-letters-and-digits = Only letters and digits are allowed
+
+lobby-relay-checking = Checking…
+lobby-relay-latency = { $milliseconds } ms
+lobby-relay-reachable = The relay server is reachable
+lobby-relay-unreachable = Unreachable
+lobby-relay-profiles = Saved relay servers
+lobby-no-relay-profiles = No saved servers
+lobby-relay-profile-name = Name
+lobby-save-relay-profile = Save
+lobby-recover-session = NetCanv didn't close properly last time. Recover the latest autosave?
+lobby-recover = Recover
+lobby-dismiss = Dismiss
 
 switch-to-dark-mode = Switch to dark mode
 switch-to-light-mode = Switch to light mode
 language = Language
 open-source-licenses = Open source licenses
-
+draw-offline = Draw offline
+usage-statistics = Usage statistics
+usage-statistics-description =
+   Help improve NetCanv by sending anonymous counters
+   when you close it. Nicknames, rooms, and drawings
+   are never included.
+usage-statistics-enabled = Send usage statistics
+usage-statistics-endpoint = Endpoint (ws:// or wss://)
+usage-statistics-preview = This is exactly what would be sent:
+
+mapping-port = Opening a port on the router…
+resolving = Looking up the server…
 connecting = Connecting…
+waiting-for-relay = Waiting for the relay…
+cancel = Cancel
 
 ## Paint
 
 paint-welcome-host =
    Welcome to your room!
    To invite friends, send them the { room-id } from the menu in the bottom right corner of your screen.
+paint-welcome-offline =
+   You're drawing offline.
+   To keep your work, save the canvas from the menu in the bottom right corner of your screen.
+paint-welcome-lan-host =
+   This room is hosted on your local network, and nobody can take it over from you.
+   It ends for everyone when you leave, so save the canvas before leaving.
 
 unknown-host = <unknown>
 you-are-the-host = You are the host
+drawing-offline = You're drawing offline
+drawing-offline-hint =
+   Nobody else can join this canvas.
+   Save it to keep your work.
 someone-is-your-host = is your host
 room-id-copied = { room-id } copied to clipboard
+copy-room-link = Copy link to room
+room-info = Room info
+edit-room-info = Edit
+save-room-info = Save
+room-is-now-called = The room is now called { $name }
+room-link-copied = Link to room copied to clipboard
+show-qr-code = Show QR code
+scan-to-join = Scan to join the room
 
 someone-joined-the-room = { $nickname } joined the room
 someone-left-the-room = { $nickname } has left
 someone-is-now-hosting-the-room = { $nickname } is now hosting the room
 you-are-now-hosting-the-room = You are now hosting the room
+someone-pinged = { $nickname } pinged a location (click to jump there)
+bookmark-name = Bookmark { $slot }
+bookmark-saved = Saved the view as bookmark { $slot }
+no-bookmark = No bookmark is saved under { $slot }
+grid-snapping-enabled = Shapes now snap to the grid
+grid-snapping-disabled = Shapes no longer snap to the grid
+
+status-bar-chunk = Chunk { $x }, { $y }
+status-bar-people =
+   { $people ->
+      [one] 1 person in the room
+      *[other] { $people } people in the room
+   }
+status-bar-up-to-date = Up to date
+status-bar-downloading =
+   { $chunks ->
+      [one] Downloading 1 chunk…
+      *[other] Downloading { $chunks } chunks…
+   }
+status-bar-offline = Offline
 
 tool-selection = Selection
 tool-brush = Brush
 tool-eyedropper = Eyedropper
+tool-lock = Lock regions
+tool-laser = Laser pointer
+tool-note = Sticky note
+tool-image = Floating images
+tool-text = Text
+tool-shapes = Shapes
+tool-airbrush = Airbrush
+tool-stamp = Stamp brush
 
 brush-thickness = Thickness
+brush-opacity = Opacity
+brush-hardness = Hardness
+brush-stabilizer = Stabilizer
+brush-blend-mode = Blend
+blend-mode-normal = Normal
+blend-mode-multiply = Multiply
+blend-mode-screen = Screen
+blend-mode-overlay = Overlay
+lock-regions-hint = Drag to lock a region of the canvas. Right-click a locked region to unlock it
+lock-regions-host-only = Only the host can lock regions of the canvas
+laser-hint = Drag to point things out. The trail fades away and is never drawn onto the canvas
+notes-hint = Click to add a note. Drag a note to move it
+note-text-hint = Note text
+remove-note = Remove note
+floating-images-hint = Paste an image to place it above the canvas. Click an image to move or resize it
+image-placement-hint = Arrow keys nudge the image and the mouse wheel scales it. Enter places it, Esc cancels
+flatten-image = Flatten onto canvas
+remove-image = Remove image
+text-size = Size
+text-hint = Click to place text on the canvas
+text-field-hint = Type the text, then press Enter
+shape-line = Line
+shape-rectangle = Rectangle
+shape-ellipse = Ellipse
+shape-filled = Filled
+shape-outline = Outline
+airbrush-radius = Radius
+airbrush-density = Density
+export-scale = Scale
+export-selection = Export selection
+load-stamp = Load stamp
+stamp-size = Size
+stamp-hint = Load a small PNG image to paint with it
+reference-image-opacity = Reference image
+remove-reference-image = Remove
+reference-image-below-canvas = Put below canvas
+reference-image-above-canvas = Put above canvas
+show-attribution = Show who drew what
+hide-attribution = Hide who drew what
+attribution-unknown-author = Someone who left
 
 action-save-to-file = Save to file
+action-reference-image = Load reference image
+action-import-document = Import document
+action-keyboard-shortcuts = Keyboard shortcuts
+action-leave-room = Leave room
+
+people = People
+nobody-else-is-here = Nobody else is here yet
+pause-canvas = Pause
+pause-canvas-hint = Pause drawing for everyone
+resume-canvas = Resume
+canvas-is-paused = The canvas is paused
+vector-strokes-hint = Keep strokes sharp at any zoom level
+vector-strokes-are-on = Strokes are kept as vectors
+turn-vector-strokes-on = Turn on
+turn-vector-strokes-off = Turn off
+canvas-is-infinite = The canvas is infinite
+canvas-is-bounded = The canvas is { $width }×{ $height }
+make-canvas-infinite = Infinite
+canvas-background = Background: { $background }
+background-blank = Blank
+background-graph = Graph paper
+background-dots = Dot grid
+background-lined = Lined paper
+background-isometric = Isometric
+change-background = Change
+chunk-encoding-format = Chunk images: { $format }
+chunk-format-png = PNG
+chunk-format-auto = PNG, or WebP if large
+chunk-format-webp = WebP
+chunk-format-qoi = QOI
+chunk-encoding-quality = WebP quality: { $quality }%
+chunk-encoding-batch-size = Chunks per packet: up to { $size } KiB
+change-chunk-encoding = Change
+page-name = Page { $number }
+add-page = Add a page
+layers = Layers
+add-layer = Add a layer
+layer-name = Layer { $number }
+draw-on-layer = Draw here
+show-layer = Show
+hide-layer = Hide
+layer-opacity = { $opacity }% opaque
+history = History
+share-palette = Share mine
+share-palette-hint = Share your palette with everyone
+stop-sharing-palette = Stop sharing
+palette-is-shared = Everyone sees the room palette
+share-bookmarks = Share mine
+share-bookmarks-hint = Share your bookmarks with everyone
+stop-sharing-bookmarks = Stop sharing
+bookmarks-are-shared = Everyone can jump to your bookmarks
+no-invites-yet = No invites created yet
+copy-invite = Copy
+invite-copied = Invite copied to clipboard
+new-single-use-invite = Single-use
+new-expiring-invite = 1 hour
+revoke-invites = Revoke all
+invites-revoked = All invites were revoked
+room-is-open = Anyone with the { room-id } can join
+room-is-invite-only = Joining requires an invite
+require-invites = Require
+allow-room-id = Allow { room-id }
+revert-changes-from-last = Revert last
+revert = Revert
+someones-changes-were-reverted = Reverted { $count } chunks changed by { $nickname }
+nothing-to-undo = Nothing to undo
+nothing-to-redo = Nothing to redo
+make-host = Make host
+welcome-to-room = Welcome to { $name }!
+block = Block
+unblock = Unblock
+go-to-cursor = Go to
+follow = Follow
+unfollow = Unfollow
+following-someone = Following { $nickname }. Pan the canvas to stop
+stopped-following = Stopped following
+peer-latency = { $milliseconds } ms
+someone-was-blocked = { $nickname } was blocked. Their actions will be ignored in every room
+someone-was-unblocked = { $nickname } was unblocked
+
+voice-chat-hint = Talk with everyone in the room
+in-voice-chat = You're in voice chat
+join-voice-chat = Join voice chat
+leave-voice-chat = Leave voice chat
+mute = Mute
+unmute = Unmute
+
+audit-log = Audit log
+audit-log-export = Export
+audit-log-empty = Nothing has happened yet
+audit-log-just-now = just now
+audit-log-minutes-ago = { $minutes } min ago
+audit-event-joined = joined the room
+audit-event-left = left the room
+audit-event-pasted = pasted a { $width }×{ $height } image
+audit-event-locked-region = locked chunks { $left }, { $top } to { $right }, { $bottom }
+audit-event-unlocked-regions = unlocked regions at chunk { $x }, { $y }
+audit-event-cleared = cleared chunks { $left }, { $top } to { $right }, { $bottom }
+audit-event-paused = paused the canvas
+audit-event-resumed = resumed the canvas
+audit-event-reverted = reverted { $count } chunks changed by { $nickname }
+
+debug-console = Debug console
+debug-console-hint = Type a command, or `help`
+
+network-stats-throughput = Up { $up }/s, down { $down }/s
+network-stats-packets = Packets/s: { $cursor } cursor, { $stroke } stroke, { $chunk } chunk, { $other } other
+network-stats-relay-rtt = Relay round trip: { $milliseconds } ms
+network-stats-relay-rtt-unknown = Relay round trip: not measured yet
+
+perf-hud-frame-time = Frame time: { $average } ms avg, { $max } ms max ({ $fps } FPS)
+perf-hud-metric = { $name }: { $count }× { $average } ms avg, { $max } ms max
+perf-metric-update = Update
+perf-metric-present = Render & present
+perf-metric-canvas = Canvas
+perf-metric-network = Network
+perf-metric-actions = Actions & autosave
+perf-metric-chunk-encode = Chunk encoding
+perf-metric-chunk-decode = Chunk decoding
+
+keyboard-shortcuts = Keyboard shortcuts
+keyboard-shortcuts-press-a-key = Press a key…
+keyboard-shortcuts-reset = Reset all
+keymap-section-edit = Editing
+keymap-section-tools = Tools
+keymap-section-brush = Brush
+keymap-section-image = Floating images
+keymap-section-canvas = Canvas
+keymap-section-windows = Windows
+keymap-section-actions = Actions
+keymap-section-debug = Debugging
+shortcut-edit-copy = Copy
+shortcut-edit-cut = Cut
+shortcut-edit-paste = Paste
+shortcut-edit-paste-into-selection = Paste into selection
+shortcut-edit-delete = Delete
+shortcut-edit-select-all = Select all
+shortcut-edit-undo = Undo
+shortcut-edit-redo = Redo
+shortcut-tools-selection = Selection
+shortcut-tools-brush = Brush
+shortcut-tools-eyedropper = Eyedropper
+shortcut-tools-lock = Lock regions
+shortcut-tools-laser = Laser pointer
+shortcut-tools-note = Sticky note
+shortcut-tools-image = Floating images
+shortcut-tools-text = Text
+shortcut-tools-shapes = Shapes
+shortcut-tools-airbrush = Airbrush
+shortcut-tools-stamp = Stamp brush
+shortcut-brush-decrease-thickness = Thinner brush
+shortcut-brush-increase-thickness = Thicker brush
+shortcut-image-confirm = Place image
+shortcut-image-cancel = Cancel placing
+shortcut-image-nudge-left = Nudge left
+shortcut-image-nudge-right = Nudge right
+shortcut-image-nudge-up = Nudge up
+shortcut-image-nudge-down = Nudge down
+shortcut-canvas-zoom-in = Zoom in
+shortcut-canvas-zoom-out = Zoom out
+shortcut-canvas-reset-zoom = Reset zoom
+shortcut-canvas-rotate-counterclockwise = Rotate left
+shortcut-canvas-rotate-clockwise = Rotate right
+shortcut-canvas-reset-rotation = Reset rotation
+shortcut-canvas-toggle-minimap = Minimap
+shortcut-canvas-toggle-grid = Grid
+shortcut-canvas-toggle-grid-snapping = Snap to grid
+shortcut-windows-people = People and voice chat
+shortcut-windows-layers = Layers
+shortcut-windows-history = History
+shortcut-windows-keyboard-shortcuts = Keyboard shortcuts
+shortcut-actions-save-to-file = Save to file
+shortcut-debug-console = Debug console
+shortcut-debug-network-stats = Network statistics
+shortcut-debug-perf-hud = Performance overlay
+
+crash-report-title = NetCanv crashed
+crash-report-found =
+   NetCanv crashed the last time it was running. A crash report was saved to:
+   { $path }
+
+   Attaching it to a bug report on GitHub helps a lot with fixing the problem. Open it now?
+
+unsaved-changes-title = Unsaved changes
+unsaved-changes-description =
+   The canvas has changes that haven't been saved to a file.
+   Do you want to close NetCanv anyway?
 
 ## File dialogs
 
 fd-supported-image-files = Supported image files
 fd-png-file = PNG file
+fd-supported-documents = Supported documents
+fd-pdf-document = PDF document
+fd-tiff-document = TIFF document
+fd-jpeg-file = JPEG image
+fd-svg-file = SVG image (vector strokes only)
 fd-netcanv-canvas = NetCanv canvas
+fd-csv-file = CSV file
 
 ## Color picker
 
 click-to-edit-color = Click to edit color
+palette-color-hint = Right-click to replace with the selected color, Shift+right-click to remove
+add-color-to-palette = Add the selected color to the palette
+room-palette-color = From the room palette
+recent-color = Recently used
 eraser = Eraser
 rgb-hex-code = RGB hex code
 
@@ -103,6 +457,7 @@ error-invalid-number = Invalid number (please report this)
 
 error-could-not-initialize-backend = Could not initialize backend: { $error }
 error-could-not-initialize-logger = Could not initialize logger: { $error }
+error-invalid-log-filter = Invalid log filter: { $error }
 error-could-not-initialize-clipboard = Could not initialize clipboard: { $error }
 
 error-config-is-already-loaded = User configuration is already loaded. This is a bug, please report this
@@ -128,14 +483,20 @@ error-non-rgba-chunk-image = Received non-RGBA chunk image
 error-invalid-chunk-image-format = Invalid chunk image format (was not PNG nor WebP)
 error-invalid-chunk-image-size = Received chunk image of invalid size
 error-nothing-to-save = There's nothing to save! Draw something on the canvas and try again
+error-no-vector-strokes-to-save = There are no vector strokes to save. Turn vector strokes on in the People window before drawing
 error-invalid-canvas-folder = Please select a valid canvas folder (one whose name ends with .netcanv)
-error-unsupported-save-format = Unsupported save format. Choose either .png or .netcanv
-error-missing-canvas-save-extension = Can't save canvas without an extension. Choose either .png or .netcanv
+error-unsupported-save-format = Unsupported save format. Choose .png, .svg, or .netcanv
+error-missing-canvas-save-extension = Can't save canvas without an extension. Choose .png, .svg, or .netcanv
+error-unsupported-document-format = This document can't be imported. Only PDF documents, and TIFF documents with 8-bit grayscale or color pages are supported
+error-pdf-renderer-not-found = Importing PDF documents requires pdftoppm, which is part of Poppler. Install it and try again
+error-pdf-rendering-failed = Could not render the PDF document: { $error }
 error-invalid-chunk-position-pattern = Chunk position must follow the pattern: x,y
 error-trailing-chunk-coordinates-in-filename = Trailing coordinates found after x,y
 error-canvas-toml-version-mismatch = Version mismatch in canvas.toml. Try downloading a newer version of NetCanv
 
 error-invalid-url = Could not parse URL. Please double-check if it's correct
+error-could-not-resolve-host = Could not find the server { $host }. Please double-check if the address is correct
+error-connection-timed-out = The server did not respond in time
 error-no-version-packet = Did not receive a version packet from the relay
 error-invalid-version-packet = The relay sent an invalid version packet
 error-relay-is-too-old = Relay version is too old. Try connecting to a different relay or download an older version of NetCanv
@@ -145,9 +506,11 @@ error-tried-to-send-packet-that-is-too-big = Cannot send packet that is bigger t
 error-tried-to-send-packet-that-is-way-too-big = Cannot send packet that exceeds the 32-bit integer limit
 error-relay-has-disconnected = The relay server has disconnected
 error-web-socket = WebSocket error: { $error }
+error-local-network = Local network discovery error: { $error }
 
 error-not-connected-to-relay = Cannot send packet: not connected to relay
 error-not-connected-to-host = Cannot send packet: not connected to host
+error-not-host = Only the host of the room can do that
 error-packet-serialization-failed = Bad packet: { $error }
 error-packet-deserialization-failed = Bad packet: { $error }
 error-relay =
@@ -155,14 +518,35 @@ error-relay =
    .no-free-peer-ids = The relay server is full. Try a different server
    .room-does-not-exist = No room with the given ID. Check if you spelled the ID correctly
    .no-such-peer = Internal server error: No such peer
+   .invalid-invite = This invite is not valid. It may have expired, been used up, or been revoked
+   .invite-required = This room can only be joined with an invite. Ask the host for one
+   .not-the-host = Only the host can do that
+   .host-token-required = This relay server only lets people with a token host rooms
+   .invalid-host-token = The relay server did not accept the room creation token
+   .invalid-host-proof = The relay server did not accept the solution to its challenge. Try again
+   .room-id-taken = A room with this ID already exists. Pick a different one
+   .room-id-reserved = This room ID is reserved by the relay server. Pick a different one
 error-unexpected-relay-packet = Bad packet type received from relay; it's probably modified or malicious
 error-client-is-too-old = Your version of NetCanv is too old. Try downloading a newer version
 error-client-is-too-new = Your version of NetCanv is too new. Join a newer room or download an older version
+error-invalid-recording = The file is not a valid session recording
+error-recording-version-mismatch = The recording was made with a different version of NetCanv
 
 error-invalid-tool-packet = Invalid tool packet received
+error-image-is-too-large = The image is too large to share, even after scaling it down
+
+error-voice-chat-unavailable = This build of NetCanv was compiled without voice chat support
+error-no-audio-device = No microphone or speakers were found
+error-unsupported-audio-device = The audio device does not support 48 kHz audio
+error-audio-stream = Audio device error: { $error }
+error-opus = Could not encode or decode voice: { $error }
 
 error-nickname-must-not-be-empty = Nickname must not be empty
 error-nickname-too-long = The maximum length of a nickname is { $max-length } characters
-error-invalid-room-id-length = { room-id } must be a code with { $length } characters
+error-room-name-too-long = The maximum length of a room name is { $max-length } characters
+error-room-description-too-long = The maximum length of a room description is { $max-length } characters
+error-invalid-room-id-length = { room-id } must be a code with { $length } characters, or an invite with { $invite-length } characters
+error-invalid-room-id-characters = { room-id } may only contain letters and digits
+error-invalid-custom-room-id = The { room-id } of a new room must have { $length } letters and digits, or be left empty
 error-while-performing-action = Error while performing action: { $error }
 error-while-processing-action = Error while processing action: { $error }
@@ -20,55 +20,411 @@ lobby-join-a-room =
       i wpisz go poniżej.
 lobby-room-id =
    .label = { room-id }
-   .hint = 6 znaków
+   .hint = 6 znaków, zaproszenie lub link
 lobby-join = Dołącz
 
 lobby-host-a-new-room =
    .title = Utwórz nowy pokój
-   .description =
-      Utwórz czystą kartkę lub załaduj ją z pliku
-      i podziel się kodem pokoju ze znajomymi.
+lobby-room-name =
+   .label = Nazwa pokoju
+   .hint = Opcjonalna
+lobby-room-description =
+   .label = Opis
+   .hint = Opcjonalny
+lobby-custom-room-id =
+   .label = { room-id }
+   .hint = Losowy
 lobby-host = Utwórz
 lobby-host-from-file = z pliku
+lobby-public = Publiczny
+lobby-lan = LAN
+lobby-direct = Bezpośrednio
+
+lobby-rooms = Pokoje
+lobby-recent-rooms = Ostatnie
+lobby-no-recent-rooms =
+   Tutaj pojawią się pokoje, do których dołączysz lub które utworzysz,
+   aby można było do nich wrócić jednym kliknięciem.
+lobby-rejoin = Dołącz
+lobby-rehost = Utwórz
+lobby-recent-room-as = jako { $nickname }, { $time }
+lobby-recent-room-from-file = { $file }, { $time }
+lobby-recent-just-now = przed chwilą
+lobby-recent-minutes-ago = { $minutes } min temu
+lobby-recent-hours-ago = { $hours } godz. temu
+lobby-recent-days-ago = { $days } dni temu
+lobby-public-rooms = Publiczne
+lobby-fetching-public-rooms = Pobieranie listy pokoi…
+lobby-no-public-rooms =
+   Na tym serwerze nie ma teraz żadnych publicznych pokoi.
+   Utwórz pokój publiczny, aby się tu pojawił.
+lobby-public-room-players =
+   { $players ->
+      [one] 1 osoba
+      [few] { $players } osoby
+      *[many] { $players } osób
+   }
+lobby-public-room-invite-only = tylko z zaproszeniem
+lobby-public-rooms-hint = Kliknij Dołącz lub dwukrotnie kliknij pokój, aby dołączyć
+lobby-refresh = Odśwież
+lobby-lan-rooms = LAN
+lobby-no-lan-rooms =
+   W Twojej sieci lokalnej nie ma teraz żadnych pokoi.
+   Utwórz pokój z włączonym LAN, aby się tu pojawił.
+
+lobby-field-required = Wymagane
+lobby-field-too-long = Maksymalnie { $max-length } znaków
+lobby-field-room-id-length = Musi mieć { $length } lub { $invite-length } znaków
+lobby-field-length = Musi mieć { $length } znaków
+lobby-field-letters-and-digits = Dozwolone są tylko litery i cyfry
+
+lobby-relay-checking = Sprawdzanie…
+lobby-relay-latency = { $milliseconds } ms
+lobby-relay-reachable = Serwer jest osiągalny
+lobby-relay-unreachable = Nieosiągalny
+lobby-relay-profiles = Zapisane serwery
+lobby-no-relay-profiles = Brak zapisanych serwerów
+lobby-relay-profile-name = Nazwa
+lobby-save-relay-profile = Zapisz
+lobby-recover-session = NetCanv nie zamknął się poprawnie ostatnim razem. Przywrócić najnowszy autozapis?
+lobby-recover = Przywróć
+lobby-dismiss = Odrzuć
 
 switch-to-dark-mode = Przełącz na tryb ciemny
 switch-to-light-mode = Przełącz na tryb jasny
 language = Język
 open-source-licenses = Licencje open source
+draw-offline = Rysuj offline
+usage-statistics = Statystyki użytkowania
+usage-statistics-description =
+   Pomóż ulepszyć NetCanv, wysyłając anonimowe liczniki
+   przy jego zamykaniu. Pseudonimy, pokoje i rysunki
+   nigdy nie są do nich dołączane.
+usage-statistics-enabled = Wysyłaj statystyki użytkowania
+usage-statistics-endpoint = Adres (ws:// lub wss://)
+usage-statistics-preview = Dokładnie to zostałoby wysłane:
 
 fd-supported-image-files = Obsługiwane formaty obrazów
 fd-png-file = Obrazek PNG
+fd-supported-documents = Obsługiwane dokumenty
+fd-pdf-document = Dokument PDF
+fd-tiff-document = Dokument TIFF
+fd-jpeg-file = Obrazek JPEG
+fd-svg-file = Obrazek SVG (tylko pociągnięcia wektorowe)
 fd-netcanv-canvas = Kartka NetCanv
+fd-csv-file = Plik CSV
 
+mapping-port = Otwieranie portu na routerze…
+resolving = Wyszukiwanie serwera…
 connecting = Łączenie…
+waiting-for-relay = Oczekiwanie na serwer…
+cancel = Anuluj
 
 ## Paint
 
 paint-welcome-host =
    Witaj w swoim pokoju!
    Aby zaprosić znajomych, wyślij im kod pokoju z menu w prawym dolnym rogu ekranu.
+paint-welcome-offline =
+   Rysujesz offline.
+   Aby nie stracić swojej pracy, zapisz kartkę z menu w prawym dolnym rogu ekranu.
+paint-welcome-lan-host =
+   Ten pokój działa w twojej sieci lokalnej i nikt nie może go od ciebie przejąć.
+   Kończy się dla wszystkich, gdy go opuścisz, więc zapisz kartkę przed wyjściem.
 
 unknown-host = <nieznany>
 you-are-the-host = Jesteś gospodarzem
+drawing-offline = Rysujesz offline
+drawing-offline-hint =
+   Nikt inny nie może dołączyć do tej kartki.
+   Zapisz ją, aby nie stracić swojej pracy.
 someone-is-your-host = jest twoim gospodarzem
 room-id-copied = Kod pokoju skopiowany do schowka
+copy-room-link = Kopiuj link do pokoju
+room-info = Informacje o pokoju
+edit-room-info = Edytuj
+save-room-info = Zapisz
+room-is-now-called = Pokój nazywa się teraz { $name }
+room-link-copied = Link do pokoju skopiowany do schowka
+show-qr-code = Pokaż kod QR
+scan-to-join = Zeskanuj, aby dołączyć do pokoju
 
 someone-joined-the-room = { $nickname } dołączył do pokoju
 someone-left-the-room = { $nickname } opuścił pokój
 someone-is-now-hosting-the-room = { $nickname } został gospodarzem pokoju
 you-are-now-hosting-the-room = Zostałeś gospodarzem pokoju
+someone-pinged = { $nickname } wskazał miejsce (kliknij, aby tam przejść)
+bookmark-name = Zakładka { $slot }
+bookmark-saved = Zapisano widok jako zakładkę { $slot }
+no-bookmark = Pod { $slot } nie zapisano żadnej zakładki
+grid-snapping-enabled = Kształty są teraz przyciągane do siatki
+grid-snapping-disabled = Kształty nie są już przyciągane do siatki
+
+status-bar-chunk = Fragment { $x }, { $y }
+status-bar-people =
+   { $people ->
+      [one] 1 osoba w pokoju
+      [few] { $people } osoby w pokoju
+      *[many] { $people } osób w pokoju
+   }
+status-bar-up-to-date = Aktualna
+status-bar-downloading =
+   { $chunks ->
+      [one] Pobieranie 1 fragmentu…
+      *[other] Pobieranie { $chunks } fragmentów…
+   }
+status-bar-offline = Offline
 
 tool-selection = Zaznaczenie
 tool-brush = Pędzel
 tool-eyedropper = Pipeta
+tool-lock = Blokowanie obszarów
+tool-laser = Wskaźnik laserowy
+tool-note = Karteczka
+tool-image = Obrazy pływające
+tool-text = Tekst
+tool-shapes = Kształty
+tool-airbrush = Aerograf
+tool-stamp = Pędzel stemplowy
 
 brush-thickness = Grubość
+brush-opacity = Krycie
+brush-hardness = Twardość
+brush-stabilizer = Wygładzanie
+brush-blend-mode = Mieszanie
+blend-mode-normal = Normalne
+blend-mode-multiply = Mnożenie
+blend-mode-screen = Ekran
+blend-mode-overlay = Nakładka
+lock-regions-hint = Przeciągnij aby zablokować obszar kartki. Kliknij prawym przyciskiem na zablokowany obszar aby go odblokować
+lock-regions-host-only = Tylko gospodarz może blokować obszary kartki
+laser-hint = Przeciągnij aby coś wskazać. Ślad znika po chwili i nigdy nie trafia na kartkę
+notes-hint = Kliknij aby dodać karteczkę. Przeciągnij karteczkę aby ją przesunąć
+note-text-hint = Treść karteczki
+remove-note = Usuń karteczkę
+floating-images-hint = Wklej obraz aby umieścić go nad kartką. Kliknij obraz aby go przesunąć lub zmienić jego rozmiar
+image-placement-hint = Strzałki przesuwają obraz, a kółko myszy zmienia jego rozmiar. Enter umieszcza go, Esc anuluje
+flatten-image = Wtop w kartkę
+remove-image = Usuń obraz
+text-size = Rozmiar
+text-hint = Kliknij aby umieścić tekst na kartce
+text-field-hint = Wpisz tekst, a następnie wciśnij Enter
+shape-line = Linia
+shape-rectangle = Prostokąt
+shape-ellipse = Elipsa
+shape-filled = Wypełniony
+shape-outline = Kontur
+airbrush-radius = Promień
+airbrush-density = Gęstość
+export-scale = Skala
+export-selection = Eksportuj zaznaczenie
+load-stamp = Wczytaj stempel
+stamp-size = Rozmiar
+stamp-hint = Wczytaj mały obraz PNG aby nim malować
+reference-image-opacity = Obraz referencyjny
+remove-reference-image = Usuń
+reference-image-below-canvas = Umieść pod płótnem
+reference-image-above-canvas = Umieść nad płótnem
+show-attribution = Pokaż kto co narysował
+hide-attribution = Ukryj kto co narysował
+attribution-unknown-author = Ktoś, kto wyszedł
 
 action-save-to-file = Zapisz do pliku
+action-reference-image = Wczytaj obraz referencyjny
+action-import-document = Importuj dokument
+action-keyboard-shortcuts = Skróty klawiszowe
+action-leave-room = Opuść pokój
+
+people = Osoby
+nobody-else-is-here = Nikogo tu jeszcze nie ma
+pause-canvas = Wstrzymaj
+pause-canvas-hint = Wstrzymaj rysowanie dla wszystkich
+resume-canvas = Wznów
+canvas-is-paused = Kartka jest wstrzymana
+vector-strokes-hint = Zachowuj ostre pociągnięcia przy każdym powiększeniu
+vector-strokes-are-on = Pociągnięcia są zapisywane jako wektory
+turn-vector-strokes-on = Włącz
+turn-vector-strokes-off = Wyłącz
+canvas-is-infinite = Kartka jest nieskończona
+canvas-is-bounded = Kartka ma wymiary { $width }×{ $height }
+make-canvas-infinite = Nieskończona
+canvas-background = Tło: { $background }
+background-blank = Puste
+background-graph = Papier w kratkę
+background-dots = Kropki
+background-lined = Papier w linie
+background-isometric = Izometryczne
+change-background = Zmień
+chunk-encoding-format = Obrazy fragmentów: { $format }
+chunk-format-png = PNG
+chunk-format-auto = PNG lub WebP, jeśli duże
+chunk-format-webp = WebP
+chunk-format-qoi = QOI
+chunk-encoding-quality = Jakość WebP: { $quality }%
+chunk-encoding-batch-size = Fragmenty w pakiecie: do { $size } KiB
+change-chunk-encoding = Zmień
+page-name = Strona { $number }
+add-page = Dodaj stronę
+layers = Warstwy
+add-layer = Dodaj warstwę
+layer-name = Warstwa { $number }
+draw-on-layer = Rysuj tutaj
+show-layer = Pokaż
+hide-layer = Ukryj
+layer-opacity = Krycie { $opacity }%
+history = Historia
+share-palette = Udostępnij moją
+share-palette-hint = Udostępnij swoją paletę wszystkim
+stop-sharing-palette = Przestań udostępniać
+palette-is-shared = Wszyscy widzą paletę pokoju
+share-bookmarks = Udostępnij moje
+share-bookmarks-hint = Udostępnij swoje zakładki wszystkim
+stop-sharing-bookmarks = Przestań udostępniać
+bookmarks-are-shared = Wszyscy mogą przejść do twoich zakładek
+no-invites-yet = Nie utworzono jeszcze zaproszeń
+copy-invite = Kopiuj
+invite-copied = Skopiowano zaproszenie do schowka
+new-single-use-invite = Jednorazowe
+new-expiring-invite = Na godzinę
+revoke-invites = Unieważnij
+invites-revoked = Wszystkie zaproszenia zostały unieważnione
+room-is-open = Każdy z kodem pokoju może dołączyć
+room-is-invite-only = Dołączenie wymaga zaproszenia
+require-invites = Wymagaj
+allow-room-id = Zezwól na kod
+revert-changes-from-last = Cofnij ostatnie
+revert = Cofnij
+someones-changes-were-reverted = Cofnięto zmiany { $nickname } w { $count } fragmentach kartki
+nothing-to-undo = Nie ma czego cofnąć
+nothing-to-redo = Nie ma czego ponowić
+make-host = Przekaż pokój
+welcome-to-room = Witaj w pokoju { $name }!
+block = Zablokuj
+unblock = Odblokuj
+go-to-cursor = Pokaż
+follow = Obserwuj
+unfollow = Nie obserwuj
+following-someone = Obserwujesz { $nickname }. Przesuń kartkę, aby przestać
+stopped-following = Koniec obserwowania
+peer-latency = { $milliseconds } ms
+someone-was-blocked = Zablokowano { $nickname }. Ich działania będą ignorowane w każdym pokoju
+someone-was-unblocked = Odblokowano { $nickname }
+
+voice-chat-hint = Rozmawiaj z wszystkimi w pokoju
+in-voice-chat = Jesteś na czacie głosowym
+join-voice-chat = Dołącz do czatu głosowego
+leave-voice-chat = Opuść czat głosowy
+mute = Wycisz
+unmute = Wyłącz wyciszenie
+
+audit-log = Dziennik zdarzeń
+audit-log-export = Eksportuj
+audit-log-empty = Jeszcze nic się nie wydarzyło
+audit-log-just-now = przed chwilą
+audit-log-minutes-ago = { $minutes } min temu
+audit-event-joined = dołączył do pokoju
+audit-event-left = opuścił pokój
+audit-event-pasted = wkleił obraz { $width }×{ $height }
+audit-event-locked-region = zablokował fragmenty od { $left }, { $top } do { $right }, { $bottom }
+audit-event-unlocked-regions = odblokował obszary we fragmencie { $x }, { $y }
+audit-event-cleared = wyczyścił fragmenty od { $left }, { $top } do { $right }, { $bottom }
+audit-event-paused = wstrzymał kartkę
+audit-event-resumed = wznowił kartkę
+audit-event-reverted = cofnął zmiany { $nickname } w { $count } fragmentach
+
+debug-console = Konsola debugowania
+debug-console-hint = Wpisz polecenie lub `help`
+
+network-stats-throughput = Wysyłanie { $up }/s, pobieranie { $down }/s
+network-stats-packets = Pakiety/s: { $cursor } kursor, { $stroke } pociągnięcia, { $chunk } fragmenty, { $other } inne
+network-stats-relay-rtt = Opóźnienie serwera: { $milliseconds } ms
+network-stats-relay-rtt-unknown = Opóźnienie serwera: jeszcze nie zmierzono
+
+perf-hud-frame-time = Czas klatki: śr. { $average } ms, maks. { $max } ms ({ $fps } FPS)
+perf-hud-metric = { $name }: { $count }× śr. { $average } ms, maks. { $max } ms
+perf-metric-update = Aktualizacja
+perf-metric-present = Renderowanie i wyświetlanie
+perf-metric-canvas = Kartka
+perf-metric-network = Sieć
+perf-metric-actions = Akcje i autozapis
+perf-metric-chunk-encode = Kodowanie fragmentów
+perf-metric-chunk-decode = Dekodowanie fragmentów
+
+keyboard-shortcuts = Skróty klawiszowe
+keyboard-shortcuts-press-a-key = Naciśnij klawisz…
+keyboard-shortcuts-reset = Przywróć wszystkie
+keymap-section-edit = Edycja
+keymap-section-tools = Narzędzia
+keymap-section-brush = Pędzel
+keymap-section-image = Obrazy pływające
+keymap-section-canvas = Kartka
+keymap-section-windows = Okna
+keymap-section-actions = Akcje
+keymap-section-debug = Debugowanie
+shortcut-edit-copy = Kopiuj
+shortcut-edit-cut = Wytnij
+shortcut-edit-paste = Wklej
+shortcut-edit-paste-into-selection = Wklej do zaznaczenia
+shortcut-edit-delete = Usuń
+shortcut-edit-select-all = Zaznacz wszystko
+shortcut-edit-undo = Cofnij
+shortcut-edit-redo = Ponów
+shortcut-tools-selection = Zaznaczenie
+shortcut-tools-brush = Pędzel
+shortcut-tools-eyedropper = Pipeta
+shortcut-tools-lock = Blokowanie obszarów
+shortcut-tools-laser = Wskaźnik laserowy
+shortcut-tools-note = Karteczka
+shortcut-tools-image = Obrazy pływające
+shortcut-tools-text = Tekst
+shortcut-tools-shapes = Kształty
+shortcut-tools-airbrush = Aerograf
+shortcut-tools-stamp = Pędzel stemplowy
+shortcut-brush-decrease-thickness = Cieńszy pędzel
+shortcut-brush-increase-thickness = Grubszy pędzel
+shortcut-image-confirm = Umieść obraz
+shortcut-image-cancel = Anuluj umieszczanie
+shortcut-image-nudge-left = Przesuń w lewo
+shortcut-image-nudge-right = Przesuń w prawo
+shortcut-image-nudge-up = Przesuń w górę
+shortcut-image-nudge-down = Przesuń w dół
+shortcut-canvas-zoom-in = Przybliż
+shortcut-canvas-zoom-out = Oddal
+shortcut-canvas-reset-zoom = Resetuj przybliżenie
+shortcut-canvas-rotate-counterclockwise = Obróć w lewo
+shortcut-canvas-rotate-clockwise = Obróć w prawo
+shortcut-canvas-reset-rotation = Resetuj obrót
+shortcut-canvas-toggle-minimap = Minimapa
+shortcut-canvas-toggle-grid = Siatka
+shortcut-canvas-toggle-grid-snapping = Przyciąganie do siatki
+shortcut-windows-people = Ludzie i czat głosowy
+shortcut-windows-layers = Warstwy
+shortcut-windows-history = Historia
+shortcut-windows-keyboard-shortcuts = Skróty klawiszowe
+shortcut-actions-save-to-file = Zapisz do pliku
+shortcut-debug-console = Konsola debugowania
+shortcut-debug-network-stats = Statystyki sieci
+shortcut-debug-perf-hud = Nakładka wydajności
+
+crash-report-title = NetCanv uległ awarii
+crash-report-found =
+   NetCanv uległ awarii podczas ostatniego uruchomienia. Raport o awarii został zapisany w:
+   { $path }
+
+   Dołączenie go do zgłoszenia błędu na GitHubie bardzo pomaga w naprawieniu problemu. Otworzyć go teraz?
+
+unsaved-changes-title = Niezapisane zmiany
+unsaved-changes-description =
+   Kartka ma zmiany, które nie zostały zapisane do pliku.
+   Czy mimo to zamknąć NetCanv?
 
 ## Color picker
 
 click-to-edit-color = Kliknij aby edytować kolor
+palette-color-hint = Kliknij prawym przyciskiem, aby zastąpić wybranym kolorem, z Shiftem, aby usunąć
+add-color-to-palette = Dodaj wybrany kolor do palety
+room-palette-color = Z palety pokoju
+recent-color = Ostatnio używany
 eraser = Gumka
 rgb-hex-code = Kod koloru RGB
 
@@ -95,6 +451,7 @@ error-invalid-number = Niepoprawna liczba (prosimy zgłosić to jako bug)
 
 error-could-not-initialize-backend = Nie udało się zinicjalizować renderera: { $error }
 error-could-not-initialize-logger = Nie udało się zinicjalizować loggera: { $error }
+error-invalid-log-filter = Nieprawidłowy filtr logów: { $error }
 error-could-not-initialize-clipboard = Nie udało się zinicjalizować schowka: { $error }
 
 error-config-is-already-loaded = Konfiguracja użytkownika została wcześniej załadowana. Prosimy to zgłośić
@@ -120,14 +477,20 @@ error-non-rgba-chunk-image = Otrzymano obraz chunka w formacie innym niż RGBA
 error-invalid-chunk-image-format = Niepoprawny format obrazu chunka (ani PNG, ani WebP)
 error-invalid-chunk-image-size = Otrzymano obraz chunka o niepoprawnym rozmiarze
 error-nothing-to-save = Nie ma nic do zapisu! Narysuj coś na kartce i spróbuj ponownie.
+error-no-vector-strokes-to-save = Nie ma pociągnięć wektorowych do zapisu. Włącz je w oknie Osoby przed rysowaniem.
 error-invalid-canvas-folder = Wybierz poprawny folder z kartką (o końcówce .netcanv)
-error-unsupported-save-format = Nieobsługiwany format zapisu. Wybierz .png lub .netcanv
-error-missing-canvas-save-extension = Nie można zapisać kartki bez rozszerzenia pliku. Wybierz .png lub .netcanv
+error-unsupported-save-format = Nieobsługiwany format zapisu. Wybierz .png, .svg lub .netcanv
+error-missing-canvas-save-extension = Nie można zapisać kartki bez rozszerzenia pliku. Wybierz .png, .svg lub .netcanv
+error-unsupported-document-format = Nie można zaimportować tego dokumentu. Obsługiwane są tylko dokumenty PDF oraz dokumenty TIFF z 8-bitowymi stronami w skali szarości lub w kolorze
+error-pdf-renderer-not-found = Importowanie dokumentów PDF wymaga programu pdftoppm, który jest częścią Popplera. Zainstaluj go i spróbuj ponownie
+error-pdf-rendering-failed = Nie udało się wyrenderować dokumentu PDF: { $error }
 error-invalid-chunk-position-pattern = Pozycja chunka powinna spełniać wzór: x,y
 error-trailing-chunk-coordinates-in-filename = Dodatkowe współrzędne znalezione po pozycji x,y
 error-canvas-toml-version-mismatch = Niezgodność wersji w canvas.toml. Spróbuj pobrać nowszego NetCanva
 
 error-invalid-url = Niepoprawny URL. Sprawdź czy nie posiada błędów w pisowni
+error-could-not-resolve-host = Nie znaleziono serwera { $host }. Sprawdź czy adres jest poprawny
+error-connection-timed-out = Serwer nie odpowiedział na czas
 error-no-version-packet = Nie otrzymano pakietu wersji od serwera
 error-invalid-version-packet = Serwer wysłał niepoprawny pakiet wersji
 error-relay-is-too-old = Wersja Relaya jest przestarzała. Spróbuj połączyć się z innym serwerem lub pobrać starego NetCanva
@@ -137,9 +500,11 @@ error-tried-to-send-packet-that-is-too-big = Nie można wysłać pakietu większ
 error-tried-to-send-packet-that-is-way-too-big = Nie można wysłać pakietu o rozmiarze większym niż limit 32-bitowych liczb całkowitych
 error-relay-has-disconnected = Serwer Relay rozłączył się
 error-web-socket = Błąd komunikacji WebSocket: { $error }
+error-local-network = Błąd wyszukiwania w sieci lokalnej: { $error }
 
 error-not-connected-to-relay = Nie można wysłać pakietu: brak połączenia z serwerem
 error-not-connected-to-host = Nie można wysłać pakietu: brak połączenia z gospodarzem
+error-not-host = Tylko gospodarz pokoju może to zrobić
 error-packet-serialization-failed = Niepoprawny pakiet: { $error }
 error-packet-deserialization-failed = Niepoprawny pakiet: { $error }
 error-relay =
@@ -147,14 +512,35 @@ error-relay =
    .no-free-peer-ids = Serwer jest pełny. Spróbuj połączyć się z innym serwerem
    .room-does-not-exist = Pokój o podanym kodzie nie istnieje. Sprawdź czy kod nie zawiera literówek
    .no-such-peer = Błąd wewnętrzny serwera: Nie ma takiej osoby
+   .invalid-invite = To zaproszenie jest nieważne. Mogło wygasnąć, zostać już użyte lub unieważnione
+   .invite-required = Do tego pokoju można dołączyć tylko z zaproszeniem. Poproś o nie gospodarza
+   .not-the-host = Tylko gospodarz może to zrobić
+   .host-token-required = Ten serwer pozwala tworzyć pokoje tylko osobom z tokenem
+   .invalid-host-token = Serwer nie przyjął tokenu do tworzenia pokojów
+   .invalid-host-proof = Serwer nie przyjął rozwiązania swojego wyzwania. Spróbuj ponownie
+   .room-id-taken = Pokój z tym kodem już istnieje. Wybierz inny
+   .room-id-reserved = Ten kod pokoju jest zarezerwowany przez serwer. Wybierz inny
 error-unexpected-relay-packet = Serwer wysłał niepoprawny pakiet; prawdopodobnie został zmodyfikowany i jest potencjalnie niebezpieczny
 error-client-is-too-old = Wersja NetCanv jest zbyt stara. Pobierz nowszą wersję aby dołączyć do tego pokoju
 error-client-is-too-new = Wersja NetCanv jest zbyt nowa. Dołącz do innego pokoju lub pobierz starszą wersję
+error-invalid-recording = Plik nie jest poprawnym nagraniem sesji
+error-recording-version-mismatch = Nagranie zostało utworzone w innej wersji NetCanv
 
 error-invalid-tool-packet = Otrzymano niepoprawny pakiet narzędzia
+error-image-is-too-large = Obraz jest zbyt duży, by go udostępnić, nawet po zmniejszeniu
+
+error-voice-chat-unavailable = Ta wersja NetCanv została skompilowana bez obsługi czatu głosowego
+error-no-audio-device = Nie znaleziono mikrofonu ani głośników
+error-unsupported-audio-device = Urządzenie audio nie obsługuje dźwięku 48 kHz
+error-audio-stream = Błąd urządzenia audio: { $error }
+error-opus = Nie można zakodować lub zdekodować głosu: { $error }
 
 error-nickname-must-not-be-empty = Nazwa nie może być pusta
 error-nickname-too-long = Maksymalna długość nazwy to { $max-length } znaków
-error-invalid-room-id-length = { room-id } musi być kodem o { $length } znakach
+error-room-name-too-long = Maksymalna długość nazwy pokoju to { $max-length } znaków
+error-room-description-too-long = Maksymalna długość opisu pokoju to { $max-length } znaków
+error-invalid-room-id-length = { room-id } musi być kodem o { $length } znakach lub zaproszeniem o { $invite-length } znakach
+error-invalid-room-id-characters = { room-id } może zawierać tylko litery i cyfry
+error-invalid-custom-room-id = Kod nowego pokoju musi mieć { $length } liter i cyfr lub pozostać pusty
 error-while-performing-action = Błąd podczas wykonywania akcji: { $error }
 error-while-processing-action = Błąd podczas przetwarzania akcji: { $error }
@@ -0,0 +1,170 @@
+//! Autosaves.
+//!
+//! While a canvas is open, it's periodically saved to a new timestamped `.netcanv` in the config
+//! directory, and only the few most recent autosaves are kept around. Every open canvas is its own
+//! session, with an ID that its autosaves are tagged with.
+//!
+//! Once a session writes its first autosave, it creates a lockfile holding its ID, and keeps the
+//! file locked until the canvas is closed, at which point the lockfile is removed. If a lockfile
+//! is still there, but nobody holds the lock, its session didn't end cleanly, and the lobby offers
+//! to recover that session's latest autosave. Sessions running in other instances of NetCanv hold
+//! their locks, so they're left alone.
+
+use std::fs::File;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::config::UserConfig;
+
+/// Returns the directory autosaves are written to.
+fn autosaves_dir() -> PathBuf {
+   UserConfig::config_dir().join("autosaves")
+}
+
+/// Returns the path of the lockfile marking the session with the given ID as running.
+fn lock_path(session_id: &str) -> PathBuf {
+   autosaves_dir().join(format!("session-{}.lock", session_id))
+}
+
+/// Extracts the timestamp and session ID from an autosave's file name.
+fn parse_name(path: &Path) -> Option<(u64, &str)> {
+   let name = path.file_name()?.to_str()?;
+   let stem = name.strip_prefix("autosave-")?.strip_suffix(".netcanv")?;
+   let (timestamp, session_id) = stem.split_once('-')?;
+   Some((timestamp.parse().ok()?, session_id))
+}
+
+/// Returns all the autosaves along with the IDs of the sessions that wrote them, oldest first.
+fn list() -> Vec<(String, PathBuf)> {
+   let Ok(entries) = std::fs::read_dir(autosaves_dir()) else {
+      return Vec::new();
+   };
+   let mut autosaves: Vec<_> = entries
+      .filter_map(|entry| entry.ok())
+      .map(|entry| entry.path())
+      .filter(|path| path.is_dir())
+      .filter_map(|path| {
+         let (timestamp, session_id) = parse_name(&path)?;
+         Some((timestamp, session_id.to_owned(), path))
+      })
+      .collect();
+   autosaves.sort_by_key(|&(timestamp, ..)| timestamp);
+   autosaves.into_iter().map(|(_, session_id, path)| (session_id, path)).collect()
+}
+
+/// The autosaving session of an open canvas.
+pub struct Session {
+   id: String,
+   /// The locked lockfile, once the session has written its first autosave.
+   lock: Option<File>,
+}
+
+impl Session {
+   /// Starts a new session. Nothing is written to disk until the first autosave.
+   pub fn new() -> Self {
+      let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+      Self {
+         id: format!("{}{}", std::process::id(), time.subsec_nanos()),
+         lock: None,
+      }
+   }
+
+   /// Creates and locks the session's lockfile, if it isn't locked already.
+   fn lock(&mut self) -> netcanv::Result<()> {
+      if self.lock.is_none() {
+         std::fs::create_dir_all(autosaves_dir())?;
+         let mut file = File::create(lock_path(&self.id))?;
+         file.lock()?;
+         file.write_all(self.id.as_bytes())?;
+         self.lock = Some(file);
+      }
+      Ok(())
+   }
+
+   /// Returns the path a new autosave should be written to, marking the session as running if
+   /// this is its first autosave.
+   pub fn new_path(&mut self) -> netcanv::Result<PathBuf> {
+      self.lock()?;
+      let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+      Ok(autosaves_dir().join(format!("autosave-{}-{}.netcanv", time.as_secs(), self.id)))
+   }
+
+   /// Marks the session as having ended cleanly. This should be called once the canvas is closed.
+   pub fn end(&mut self) {
+      // The lock is released before removing the file, as locked files can't be removed on
+      // some platforms.
+      if self.lock.take().is_some() {
+         if let Err(error) = std::fs::remove_file(lock_path(&self.id)) {
+            tracing::error!("could not remove the session lockfile: {}", error);
+         }
+      }
+   }
+
+   /// Deletes all but the `keep` most recent autosaves. Autosaves written by other sessions that
+   /// are still running, or that can still be recovered, are kept.
+   pub fn rotate(&self, keep: usize) -> netcanv::Result<()> {
+      let autosaves: Vec<_> = list()
+         .into_iter()
+         .filter(|(session_id, _)| *session_id == self.id || !lock_path(session_id).exists())
+         .collect();
+      let excess = autosaves.len().saturating_sub(keep);
+      for (_, path) in &autosaves[..excess] {
+         tracing::debug!("removing old autosave {:?}", path);
+         std::fs::remove_dir_all(path)?;
+      }
+      Ok(())
+   }
+}
+
+/// An autosave left behind by a session that didn't end cleanly.
+pub struct Recovery {
+   lock_path: PathBuf,
+   /// The session's latest autosave.
+   pub path: PathBuf,
+}
+
+impl Recovery {
+   /// Forgets about the session, such that it isn't offered for recovery again.
+   pub fn dismiss(self) {
+      if let Err(error) = std::fs::remove_file(&self.lock_path) {
+         tracing::error!("could not remove the session lockfile: {}", error);
+      }
+   }
+}
+
+/// Returns the ID of the session whose lockfile is at the given path, if the session didn't end
+/// cleanly. Sessions that are still running hold the lock, so they're not returned.
+fn crashed_session_id(lock_path: &Path) -> Option<String> {
+   let mut file = File::open(lock_path).ok()?;
+   file.try_lock_shared().ok()?;
+   let mut session_id = String::new();
+   file.read_to_string(&mut session_id).ok()?;
+   Some(session_id)
+}
+
+/// Returns the latest autosave of the most recent session that didn't end cleanly, if there's
+/// one.
+pub fn recoverable() -> Option<Recovery> {
+   let autosaves = list();
+   let entries = std::fs::read_dir(autosaves_dir()).ok()?;
+   entries
+      .filter_map(|entry| entry.ok())
+      .map(|entry| entry.path())
+      .filter(|path| path.extension().is_some_and(|extension| extension == "lock"))
+      .filter_map(|lock_path| {
+         let session_id = crashed_session_id(&lock_path)?;
+         let (index, (_, path)) =
+            autosaves.iter().enumerate().rev().find(|(_, (id, _))| *id == session_id)?;
+         Some((
+            index,
+            Recovery {
+               lock_path,
+               path: path.clone(),
+            },
+         ))
+      })
+      .max_by_key(|&(index, _)| index)
+      .map(|(_, recovery)| recovery)
+}
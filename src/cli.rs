@@ -3,6 +3,8 @@ use std::path::PathBuf;
 use clap::Subcommand;
 use netcanv_protocol::relay::RoomId;
 
+use crate::net::room_link::RoomLink;
+
 #[derive(clap::Parser)]
 pub struct Cli {
    /// Dump a Chromium .json trace to the given file.
@@ -12,6 +14,14 @@ pub struct Cli {
    #[clap(flatten)]
    pub render: crate::backend::cli::RendererCli,
 
+   /// Record all packets sent and received in the room to the given file, for replaying later.
+   #[clap(long)]
+   pub record: Option<PathBuf>,
+
+   /// A netcanv:// link to a room to join when started.
+   #[arg(value_parser = clap::value_parser!(RoomLink))]
+   pub link: Option<RoomLink>,
+
    #[command(subcommand)]
    pub command: Option<Commands>,
 }
@@ -28,6 +38,18 @@ pub enum Commands {
 
       #[clap(long)]
       load_canvas: Option<PathBuf>,
+
+      /// Room ID to host the room under instead of a random one, made of 6 letters and digits
+      #[arg(long, value_parser = parse_custom_room_id)]
+      room_id: Option<RoomId>,
+
+      /// Password admins can use to save, load, and inspect the canvas from their own client
+      #[clap(long)]
+      admin_password: Option<String>,
+
+      /// Token that lets the relay create the room without solving a proof-of-work challenge
+      #[clap(long)]
+      host_token: Option<String>,
    },
    /// Join room when started
    JoinRoom {
@@ -44,4 +66,24 @@ pub enum Commands {
       #[clap(long)]
       save_canvas: Option<PathBuf>,
    },
+   /// Replay a session recorded with --record
+   Replay {
+      /// The recording to replay
+      file: PathBuf,
+
+      /// How many times faster than in the recording to replay the session
+      #[clap(long, default_value_t = 1.0)]
+      speed: f32,
+   },
+}
+
+/// Parses a room ID a host may ask for. Letters are made uppercase, like in room IDs picked by the
+/// relay.
+fn parse_custom_room_id(value: &str) -> Result<RoomId, String> {
+   let room_id: RoomId =
+      value.to_ascii_uppercase().parse().map_err(|error| format!("{}", error))?;
+   if !room_id.is_valid_custom() {
+      return Err("room ID must be made of letters and digits".into());
+   }
+   Ok(room_id)
 }
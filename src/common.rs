@@ -1,9 +1,13 @@
 //! Various assorted utilities.
 
+use bincode::Options;
+use netcanv_protocol::decode::{self, Validate};
+use netcanv_protocol::relay;
 use netcanv_renderer::paws::{point, vector, Color, Point, Rect, Vector};
 use netcanv_renderer::Font as FontTrait;
 use serde::de::DeserializeOwned;
 use serde::Serialize;
+use unicode_segmentation::UnicodeSegmentation;
 
 use crate::backend::Font;
 
@@ -36,6 +40,23 @@ pub fn lerp_point(p0: Point, p1: Point, t: f32) -> Point {
    point(lerp(p0.x, p1.x, t), lerp(p0.y, p1.y, t))
 }
 
+/// Interpolates between `p1` and `p2` along a uniform Catmull-Rom spline. `p0` and `p3` are the
+/// points before and after, and determine the curve's tangents.
+pub fn catmull_rom(p0: Point, p1: Point, p2: Point, p3: Point, t: f32) -> Point {
+   let t2 = t * t;
+   let t3 = t2 * t;
+   let interpolate = |v0: f32, v1: f32, v2: f32, v3: f32| {
+      0.5 * (2.0 * v1
+         + (v2 - v0) * t
+         + (2.0 * v0 - 5.0 * v1 + 4.0 * v2 - v3) * t2
+         + (3.0 * v1 - v0 - 3.0 * v2 + v3) * t3)
+   };
+   point(
+      interpolate(p0.x, p1.x, p2.x, p3.x),
+      interpolate(p0.y, p1.y, p2.y, p3.y),
+   )
+}
+
 pub trait SafeMath {
    /// Clamps a value, automatically computing which bound is the lower one and which is the
    /// higher one.
@@ -304,13 +325,25 @@ pub fn truncate_text(font: &Font, max_width: f32, text: &str) -> String {
       let suffix_width = font.text_width(ELLIPSIS);
       let max_width = max_width - suffix_width;
       while font.text_width(&text) > max_width {
-         text.pop();
+         // Whole graphemes are removed, so that emoji and accented letters aren't cut in half.
+         match text.grapheme_indices(true).next_back() {
+            Some((index, _)) => text.truncate(index),
+            None => break,
+         }
       }
       text.push_str(ELLIPSIS);
    }
    text
 }
 
+/// Returns the first `max` graphemes of the given string.
+pub fn truncate_graphemes(text: &str, max: usize) -> &str {
+   match text.grapheme_indices(true).nth(max) {
+      Some((end, _)) => &text[..end],
+      None => text,
+   }
+}
+
 pub trait StrExt {
    fn strip_whitespace(&self) -> &str;
 }
@@ -346,11 +379,29 @@ impl StrExt for &str {
 // (De)serialization
 //
 
+/// Deserializes a value from bincode. Reading is bounded by the maximum packet size, so this is
+/// safe to use on tool payloads received from other peers.
 pub fn deserialize_bincode<T>(input: &[u8]) -> netcanv::Result<T>
 where
    T: DeserializeOwned,
 {
-   bincode::deserialize(input).map_err(|e| netcanv::Error::PacketDeserializationFailed {
+   // Trailing bytes are allowed, as tools sometimes only peek at the start of their payloads.
+   bincode::DefaultOptions::new()
+      .with_fixint_encoding()
+      .with_limit(relay::MAX_PACKET_SIZE as u64)
+      .allow_trailing_bytes()
+      .deserialize(input)
+      .map_err(|e| netcanv::Error::PacketDeserializationFailed {
+         error: e.to_string(),
+      })
+}
+
+/// Decodes a packet received over the network, checking that it's within the protocol's limits.
+pub fn decode_packet<T>(input: &[u8]) -> netcanv::Result<T>
+where
+   T: DeserializeOwned + Validate,
+{
+   decode::decode(input).map_err(|e| netcanv::Error::PacketDeserializationFailed {
       error: e.to_string(),
    })
 }
@@ -24,6 +24,84 @@ pub struct LobbyConfig {
    pub nickname: String,
    #[serde(alias = "matchmaker")]
    pub relay: String,
+   /// The name given to hosted rooms.
+   #[serde(default)]
+   pub room_name: String,
+   /// The description given to hosted rooms.
+   #[serde(default)]
+   pub room_description: String,
+   /// Whether hosted rooms are listed publicly on the relay.
+   #[serde(default)]
+   pub public_room: bool,
+   /// Rooms that were recently joined or hosted, most recent first.
+   #[serde(default)]
+   pub recent_rooms: Vec<RecentRoom>,
+   /// Saved relay servers, which can be picked instead of typing in their address.
+   #[serde(default = "default_relay_profiles")]
+   pub relay_profiles: Vec<RelayProfile>,
+}
+
+impl LobbyConfig {
+   /// The maximum number of recent rooms remembered.
+   pub const MAX_RECENT_ROOMS: usize = 3;
+
+   /// Puts the room at the top of the recent rooms list, replacing any older entry for the same
+   /// room.
+   pub fn remember_room(&mut self, room: RecentRoom) {
+      self.recent_rooms.retain(|other| !other.is_same_as(&room));
+      self.recent_rooms.insert(0, room);
+      self.recent_rooms.truncate(Self::MAX_RECENT_ROOMS);
+   }
+
+   /// Saves a relay profile, replacing any older profile with the same name.
+   pub fn save_relay_profile(&mut self, profile: RelayProfile) {
+      match self.relay_profiles.iter_mut().find(|other| other.name == profile.name) {
+         Some(other) => *other = profile,
+         None => self.relay_profiles.push(profile),
+      }
+   }
+}
+
+/// A saved relay server, along with the settings used when connecting to it.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RelayProfile {
+   pub name: String,
+   pub address: String,
+   /// The nickname used on the relay. If empty, the nickname is left as is when the profile is
+   /// picked.
+   #[serde(default)]
+   pub nickname: String,
+   /// Whether rooms hosted on the relay are listed publicly.
+   #[serde(default)]
+   pub public_room: bool,
+}
+
+/// A room that was recently joined or hosted, shown in the lobby for quick access.
+#[derive(Clone, Deserialize, Serialize)]
+pub struct RecentRoom {
+   pub room_id: String,
+   pub relay: String,
+   pub nickname: String,
+   /// Whether the room was hosted by us rather than joined.
+   pub hosted: bool,
+   /// The file the canvas was loaded from, if it was hosted from a file.
+   pub file: Option<PathBuf>,
+   /// When the room was last entered, in seconds since the Unix epoch.
+   pub last_seen: u64,
+}
+
+impl RecentRoom {
+   /// Returns whether the two entries refer to the same room. Hosted rooms get a new ID every
+   /// time, so they're told apart by the file they were loaded from.
+   fn is_same_as(&self, other: &RecentRoom) -> bool {
+      self.relay == other.relay
+         && self.hosted == other.hosted
+         && if self.hosted {
+            self.file == other.file
+         } else {
+            self.room_id == other.room_id
+         }
+   }
 }
 
 /// The color scheme variant.
@@ -55,6 +133,9 @@ pub struct UiConfig {
    pub color_scheme: ColorScheme,
    #[serde(default)]
    pub toolbar_position: ToolbarPosition,
+   /// Whether the minimap is shown in the corner of the canvas.
+   #[serde(default)]
+   pub show_minimap: bool,
 }
 
 /// Window position and size.
@@ -68,6 +149,129 @@ pub struct WindowConfig {
    pub maximized: bool,
 }
 
+/// The list of people whose actions are ignored locally, in every room.
+#[derive(Default, Deserialize, Serialize)]
+pub struct BlockListConfig {
+   /// Nicknames of blocked people. Peer IDs are assigned anew by the relay on every connection,
+   /// so the nickname is the only identifier that carries over between sessions.
+   pub nicknames: Vec<String>,
+}
+
+impl BlockListConfig {
+   /// Returns whether the person with the given nickname is blocked.
+   pub fn is_blocked(&self, nickname: &str) -> bool {
+      self.nicknames.iter().any(|blocked| blocked == nickname)
+   }
+
+   /// Adds or removes the nickname from the block list.
+   pub fn set_blocked(&mut self, nickname: &str, blocked: bool) {
+      self.nicknames.retain(|other| other != nickname);
+      if blocked {
+         self.nicknames.push(nickname.to_owned());
+      }
+   }
+}
+
+/// The user's color palette, shown in the bottom bar.
+#[derive(Default, Deserialize, Serialize)]
+pub struct PaletteConfig {
+   /// The colors in the palette, as `#rrggbb` hex codes. If empty, the default palette is used.
+   pub colors: Vec<String>,
+   /// Colors that were recently used, most recent first, as `#rrggbb` hex codes.
+   #[serde(default)]
+   pub recent: Vec<String>,
+}
+
+/// Anonymous usage statistics. These are never sent unless the user opts in.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct TelemetryConfig {
+   /// Whether a report is sent to the endpoint when NetCanv is closed.
+   pub enabled: bool,
+   /// The WebSocket URL reports are sent to.
+   pub endpoint: String,
+}
+
+impl Default for TelemetryConfig {
+   fn default() -> Self {
+      Self {
+         enabled: false,
+         endpoint: default_telemetry_endpoint(),
+      }
+   }
+}
+
+/// Limits on how much memory the canvas can take up.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct MemoryConfig {
+   /// The number of chunks kept on the graphics card at once. Once there are more, the chunks that
+   /// weren't seen for the longest are evicted and kept compressed instead, until they come back
+   /// into view.
+   pub max_resident_chunks: usize,
+   /// Whether evicted chunks are written out to a temporary directory rather than kept in memory.
+   pub spill_to_disk: bool,
+}
+
+impl Default for MemoryConfig {
+   fn default() -> Self {
+      Self {
+         // 256 MiB of chunk images.
+         max_resident_chunks: 1024,
+         spill_to_disk: false,
+      }
+   }
+}
+
+/// Periodic snapshots of the canvas, kept in the config directory.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct AutosaveConfig {
+   pub enabled: bool,
+   /// How often the canvas is autosaved, in seconds.
+   pub interval: u64,
+   /// The number of autosaves kept. Older ones are deleted as new ones are made.
+   pub keep: usize,
+}
+
+impl Default for AutosaveConfig {
+   fn default() -> Self {
+      Self {
+         enabled: true,
+         interval: 60,
+         keep: 5,
+      }
+   }
+}
+
+/// The grid overlay drawn over the canvas.
+#[derive(Deserialize, Serialize)]
+#[serde(default)]
+pub struct GridConfig {
+   /// Whether the grid is shown.
+   pub enabled: bool,
+   /// The distance between the grid's lines, in pixels on the canvas.
+   pub spacing: u32,
+   /// Whether chunk boundaries are highlighted.
+   pub highlight_chunks: bool,
+   /// Whether individual pixels are outlined when zoomed in closely.
+   pub pixel_grid: bool,
+   /// Whether the shapes tool snaps its endpoints to the grid while it's shown.
+   pub snap: bool,
+}
+
+impl Default for GridConfig {
+   fn default() -> Self {
+      Self {
+         enabled: false,
+         spacing: 32,
+         highlight_chunks: true,
+         pixel_grid: true,
+         snap: false,
+      }
+   }
+}
+
 /// A user `config.toml` file.
 #[derive(Deserialize, Serialize)]
 pub struct UserConfig {
@@ -80,6 +284,24 @@ pub struct UserConfig {
 
    #[serde(default)]
    pub keymap: Keymap,
+
+   #[serde(default)]
+   pub block_list: BlockListConfig,
+
+   #[serde(default)]
+   pub palette: PaletteConfig,
+
+   #[serde(default)]
+   pub telemetry: TelemetryConfig,
+
+   #[serde(default)]
+   pub memory: MemoryConfig,
+
+   #[serde(default)]
+   pub autosave: AutosaveConfig,
+
+   #[serde(default)]
+   pub grid: GridConfig,
 }
 
 impl UserConfig {
@@ -140,18 +362,49 @@ impl Default for UserConfig {
          language: default_language(),
          lobby: LobbyConfig {
             nickname: "AnonD".to_owned(),
-            relay: option_env!("NETCANV_DEFAULT_RELAY_URL").unwrap_or("ws://ncanarchy.firstbober.com").to_owned(),
+            relay: default_relay(),
+            room_name: String::new(),
+            room_description: String::new(),
+            public_room: false,
+            recent_rooms: Vec::new(),
+            relay_profiles: default_relay_profiles(),
          },
          ui: UiConfig {
             color_scheme: ColorScheme::Light,
             toolbar_position: ToolbarPosition::Left,
+            show_minimap: false,
          },
          window: None,
          keymap: Default::default(),
+         block_list: Default::default(),
+         palette: Default::default(),
+         telemetry: Default::default(),
+         memory: Default::default(),
+         autosave: Default::default(),
+         grid: Default::default(),
       }
    }
 }
 
+fn default_relay() -> String {
+   option_env!("NETCANV_DEFAULT_RELAY_URL").unwrap_or("ws://ncanarchy.firstbober.com").to_owned()
+}
+
+fn default_telemetry_endpoint() -> String {
+   option_env!("NETCANV_DEFAULT_TELEMETRY_URL")
+      .unwrap_or("ws://ncanarchy.firstbober.com/telemetry")
+      .to_owned()
+}
+
+fn default_relay_profiles() -> Vec<RelayProfile> {
+   vec![RelayProfile {
+      name: "Official".to_owned(),
+      address: default_relay(),
+      nickname: String::new(),
+      public_room: false,
+   }]
+}
+
 fn default_language() -> String {
    fn inner() -> Option<String> {
       tracing::info!("language not yet determined, checking locale");
@@ -206,6 +459,12 @@ pub fn config() -> RwLockReadGuard<'static, UserConfig> {
    CONFIG.get().expect("attempt to read config without loading it").read().unwrap()
 }
 
+/// Reads from the user config, or returns `None` if it isn't loaded or is currently being written
+/// to.
+pub fn try_config() -> Option<RwLockReadGuard<'static, UserConfig>> {
+   CONFIG.get()?.try_read().ok()
+}
+
 /// Reads from the user config.
 pub fn cli_config() -> RwLockReadGuard<'static, Cli> {
    CLI_CONFIG.get().expect("attempt to read cli config without loading it").read().unwrap()
@@ -0,0 +1,110 @@
+//! The log stream shown in the debug console.
+//!
+//! Log events are recorded by a tracing layer into a bounded buffer, from which the console reads
+//! them each frame. The log filters can also be changed at runtime from here.
+
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write};
+use std::sync::Mutex;
+
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::layer::Context;
+use tracing_subscriber::{EnvFilter, Layer};
+
+use crate::Error;
+
+/// A recorded log event.
+#[derive(Clone)]
+pub struct LogRecord {
+   pub level: Level,
+   pub target: String,
+   /// The message, followed by any other fields of the event.
+   pub message: String,
+}
+
+/// The maximum number of records kept. Older records are dropped.
+const MAX_RECORDS: usize = 1024;
+
+static RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+
+/// Functions that replace the log filters with a new one.
+type ReloadFilter = Box<dyn Fn(EnvFilter) -> Result<(), String> + Send + Sync>;
+
+static RELOAD_FILTERS: Mutex<Vec<ReloadFilter>> = Mutex::new(Vec::new());
+
+/// A tracing layer recording events for the debug console.
+pub struct ConsoleLogLayer;
+
+impl<S> Layer<S> for ConsoleLogLayer
+where
+   S: Subscriber,
+{
+   fn on_event(&self, event: &Event<'_>, _context: Context<'_, S>) {
+      let mut visitor = MessageVisitor(String::new());
+      event.record(&mut visitor);
+      let mut records = RECORDS.lock().unwrap();
+      if records.len() >= MAX_RECORDS {
+         records.pop_front();
+      }
+      records.push_back(LogRecord {
+         level: *event.metadata().level(),
+         target: event.metadata().target().to_owned(),
+         message: visitor.0,
+      });
+   }
+}
+
+/// Formats the fields of an event into a single line.
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+   fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+      if !self.0.is_empty() {
+         self.0.push(' ');
+      }
+      if field.name() == "message" {
+         let _ = write!(self.0, "{:?}", value);
+      } else {
+         let _ = write!(self.0, "{}={:?}", field.name(), value);
+      }
+   }
+}
+
+/// Returns the `count` most recent records, oldest first.
+pub fn latest(count: usize) -> Vec<LogRecord> {
+   let records = RECORDS.lock().unwrap();
+   records.iter().skip(records.len().saturating_sub(count)).cloned().collect()
+}
+
+/// Like [`latest`], but returns `None` instead of waiting if the records are locked. This is
+/// meant for panic handling, where the panicking thread may be the one holding the lock.
+pub fn try_latest(count: usize) -> Option<Vec<LogRecord>> {
+   let records = RECORDS.try_lock().ok()?;
+   Some(records.iter().skip(records.len().saturating_sub(count)).cloned().collect())
+}
+
+/// Removes all records.
+pub fn clear() {
+   RECORDS.lock().unwrap().clear();
+}
+
+/// Registers a function used for replacing one of the log filters. This should be called while
+/// initializing logging, once for each filter.
+pub fn add_filter_reloader(
+   reload: impl Fn(EnvFilter) -> Result<(), String> + Send + Sync + 'static,
+) {
+   RELOAD_FILTERS.lock().unwrap().push(Box::new(reload));
+}
+
+/// Replaces the filters of the log and the console with the given directives, in the same format
+/// as the `NETCANV_LOG` environment variable.
+pub fn set_filter(directives: &str) -> netcanv::Result<()> {
+   for reload in RELOAD_FILTERS.lock().unwrap().iter() {
+      let filter = EnvFilter::try_new(directives).map_err(|error| Error::InvalidLogFilter {
+         error: error.to_string(),
+      })?;
+      reload(filter).map_err(|error| Error::InvalidLogFilter { error })?;
+   }
+   Ok(())
+}
@@ -0,0 +1,156 @@
+//! Crash reports.
+//!
+//! When NetCanv panics, a report with everything needed to track the bug down is written to the
+//! config directory. The next time NetCanv starts, it offers to open the report, so that it can be
+//! attached to a bug report.
+
+use std::backtrace::Backtrace;
+use std::fmt::Write as _;
+use std::panic::PanicHookInfo;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+use rfd::{MessageButtons, MessageDialog, MessageDialogResult, MessageLevel};
+use url::Url;
+use web_time::{SystemTime, UNIX_EPOCH};
+
+use crate::assets::Assets;
+use crate::config::{self, UserConfig};
+use crate::console_log;
+
+/// The number of recent log lines included in a report.
+const LOG_LINES: usize = 200;
+
+/// The path the canvas was last saved to or loaded from.
+static CANVAS_PATH: Mutex<Option<PathBuf>> = Mutex::new(None);
+
+/// Returns the directory crash reports are written to.
+fn reports_dir() -> PathBuf {
+   UserConfig::config_dir().join("crash-reports")
+}
+
+/// Returns the path of the file pointing to the report that hasn't been shown to the user yet.
+fn pending_path() -> PathBuf {
+   reports_dir().join("pending")
+}
+
+/// Sets the path the canvas can be recovered from, or `None` if it hasn't been saved to a file.
+pub fn set_canvas_path(path: Option<PathBuf>) {
+   if let Ok(mut canvas_path) = CANVAS_PATH.lock() {
+      *canvas_path = path;
+   }
+}
+
+/// Writes a crash report for the given panic, and marks it as pending so that it's shown the next
+/// time NetCanv starts. Returns the path to the report.
+///
+/// This is called from the panic hook, so no locks are waited on, as the panicking thread may be
+/// holding them.
+pub fn write(panic_info: &PanicHookInfo<'_>) -> std::io::Result<PathBuf> {
+   let time = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+   let mut report = String::new();
+
+   let _ = writeln!(report, "NetCanv {} crash report", env!("CARGO_PKG_VERSION"));
+   let _ = writeln!(
+      report,
+      "OS: {} {}",
+      std::env::consts::OS,
+      std::env::consts::ARCH
+   );
+   let _ = writeln!(
+      report,
+      "Time: {} (seconds since the Unix epoch)",
+      time.as_secs()
+   );
+
+   section(&mut report, "Panic");
+   let _ = writeln!(report, "{}", panic_info);
+
+   section(&mut report, "Backtrace");
+   let _ = writeln!(report, "{}", Backtrace::force_capture());
+
+   section(&mut report, "Configuration");
+   match config::try_config() {
+      Some(config) => {
+         let _ = writeln!(report, "Language: {}", config.language);
+         let _ = writeln!(report, "Color scheme: {:?}", config.ui.color_scheme);
+         let _ = writeln!(report, "Toolbar position: {:?}", config.ui.toolbar_position);
+         let _ = writeln!(report, "Relay: {}", config.lobby.relay);
+      }
+      None => {
+         let _ = writeln!(report, "(not available)");
+      }
+   }
+
+   section(&mut report, "Canvas");
+   match CANVAS_PATH.try_lock().ok().and_then(|path| path.clone()) {
+      Some(path) => {
+         let _ = writeln!(
+            report,
+            "The canvas was last saved to {}. Changes made after that were lost.",
+            path.display()
+         );
+      }
+      None => {
+         let _ = writeln!(report, "The canvas was not saved to a file.");
+      }
+   }
+
+   section(&mut report, "Recent log");
+   match console_log::try_latest(LOG_LINES) {
+      Some(records) => {
+         for record in records {
+            let _ = writeln!(
+               report,
+               "{} {}: {}",
+               record.level, record.target, record.message
+            );
+         }
+      }
+      None => {
+         let _ = writeln!(report, "(not available)");
+      }
+   }
+
+   std::fs::create_dir_all(reports_dir())?;
+   let path = reports_dir().join(format!("crash-{}.txt", time.as_secs()));
+   std::fs::write(&path, report)?;
+   std::fs::write(pending_path(), path.to_string_lossy().as_bytes())?;
+   Ok(path)
+}
+
+fn section(report: &mut String, title: &str) {
+   let _ = write!(report, "\n{}\n{}\n", title, "-".repeat(title.len()));
+}
+
+/// Returns the crash report that hasn't been shown to the user yet, if any. The report stops being
+/// pending once this is called.
+pub fn take_pending() -> Option<PathBuf> {
+   let pending = pending_path();
+   let path = std::fs::read_to_string(&pending).ok()?;
+   if let Err(error) = std::fs::remove_file(&pending) {
+      tracing::error!("could not remove pending crash report marker: {}", error);
+   }
+   let path = PathBuf::from(path);
+   path.is_file().then_some(path)
+}
+
+/// Tells the user that NetCanv crashed the last time it was running, and offers to open the crash
+/// report.
+pub fn offer_to_open(assets: &Assets, path: &Path) {
+   let description =
+      assets.tr.crash_report_found.format().with("path", path.display().to_string()).done();
+   let result = MessageDialog::new()
+      .set_title(&assets.tr.crash_report_title)
+      .set_description(description)
+      .set_level(MessageLevel::Warning)
+      .set_buttons(MessageButtons::YesNo)
+      .show();
+   if result == MessageDialogResult::Yes {
+      let opened =
+         Url::from_file_path(path).ok().and_then(|url| webbrowser::open(url.as_ref()).ok());
+      if opened.is_none() {
+         tracing::error!("could not open crash report {:?}", path);
+      }
+   }
+}
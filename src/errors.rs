@@ -41,6 +41,7 @@ pub enum Error {
    //
    CouldNotInitializeBackend { error: String },
    CouldNotInitializeLogger { error: String },
+   InvalidLogFilter { error: String },
 
    //
    // Clipboard
@@ -79,9 +80,13 @@ pub enum Error {
    InvalidChunkImageFormat,
    InvalidChunkImageSize,
    NothingToSave,
+   NoVectorStrokesToSave,
    InvalidCanvasFolder,
    UnsupportedSaveFormat,
    MissingCanvasSaveExtension,
+   UnsupportedDocumentFormat,
+   PdfRendererNotFound,
+   PdfRenderingFailed { error: String },
    InvalidChunkPositionPattern,
    TrailingChunkCoordinatesInFilename,
    CanvasTomlVersionMismatch,
@@ -90,6 +95,8 @@ pub enum Error {
    // Socket networking
    //
    InvalidUrl,
+   CouldNotResolveHost { host: String },
+   ConnectionTimedOut,
    NoVersionPacket,
    InvalidVersionPacket,
    RelayIsTooOld,
@@ -99,23 +106,37 @@ pub enum Error {
    TriedToSendPacketThatIsWayTooBig,
    RelayHasDisconnected,
    WebSocket { error: String },
+   LocalNetwork { error: String },
 
    //
    // Peer networking
    //
    NotConnectedToRelay,
    NotConnectedToHost,
+   NotHost,
    PacketSerializationFailed { error: String },
    PacketDeserializationFailed { error: String },
    Relay(relay::Error),
    UnexpectedRelayPacket,
    ClientIsTooOld,
    ClientIsTooNew,
+   InvalidRecording,
+   RecordingVersionMismatch,
 
    //
    // Tools
    //
    InvalidToolPacket,
+   ImageIsTooLarge,
+
+   //
+   // Voice chat
+   //
+   VoiceChatUnavailable,
+   NoAudioDevice,
+   UnsupportedAudioDevice,
+   AudioStream { error: String },
+   Opus { error: String },
 }
 
 macro_rules! error_from {
@@ -132,10 +153,20 @@ macro_rules! error_from {
 
 error_from!(std::io::Error, Error::Io);
 error_from!(ImageError, Error::Image);
+error_from!(tiff::TiffError, Error::Image);
 error_from!(JoinError, Error::Join);
 error_from!(toml::de::Error, Error::TomlParse);
 error_from!(toml::ser::Error, Error::TomlSerialization);
 error_from!(tungstenite::Error, Error::WebSocket);
+error_from!(mdns_sd::Error, Error::LocalNetwork);
+#[cfg(feature = "voice-chat")]
+error_from!(cpal::BuildStreamError, Error::AudioStream);
+#[cfg(feature = "voice-chat")]
+error_from!(cpal::PlayStreamError, Error::AudioStream);
+#[cfg(feature = "voice-chat")]
+error_from!(cpal::SupportedStreamConfigsError, Error::AudioStream);
+#[cfg(feature = "voice-chat")]
+error_from!(audiopus::Error, Error::Opus);
 
 impl<T> From<mpsc::error::SendError<T>> for Error {
    fn from(_: mpsc::error::SendError<T>) -> Self {
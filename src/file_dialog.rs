@@ -0,0 +1,41 @@
+//! Native file dialogs that don't block the main loop.
+//!
+//! A native dialog stays open for as long as the user needs to find the file, and while it's open
+//! the frame isn't finished, so nothing gets drawn and no packets get processed. Dialogs are thus
+//! shown in the background, and the chosen file is pushed onto the bus as a message, which the app
+//! state that opened the dialog picks up in a later frame.
+
+use std::path::PathBuf;
+
+use nysa::global as bus;
+use rfd::{AsyncFileDialog, FileHandle};
+
+/// Shows a dialog for choosing an existing file. If the user picks one, the message made from its
+/// path is pushed onto the bus. Nothing is pushed if the dialog is cancelled.
+pub fn pick_file<M>(dialog: AsyncFileDialog, message: impl FnOnce(PathBuf) -> M + Send + 'static)
+where
+   M: Send + 'static,
+{
+   tokio::spawn(async move {
+      if let Some(file) = dialog.pick_file().await {
+         bus::push(message(path(file)));
+      }
+   });
+}
+
+/// Shows a dialog for choosing where to save a file. If the user picks a path, the message made
+/// from it is pushed onto the bus. Nothing is pushed if the dialog is cancelled.
+pub fn save_file<M>(dialog: AsyncFileDialog, message: impl FnOnce(PathBuf) -> M + Send + 'static)
+where
+   M: Send + 'static,
+{
+   tokio::spawn(async move {
+      if let Some(file) = dialog.save_file().await {
+         bus::push(message(path(file)));
+      }
+   });
+}
+
+fn path(file: FileHandle) -> PathBuf {
+   file.path().to_owned()
+}
@@ -1,74 +1,98 @@
 use std::io::Cursor;
 
 use ::image::codecs::png::{PngDecoder, PngEncoder};
+use ::image::codecs::qoi::{QoiDecoder, QoiEncoder};
 use ::image::codecs::webp::{WebPDecoder, WebPEncoder, WebPQuality};
 use ::image::{ColorType, ImageDecoder, Rgba, RgbaImage};
 use image::{DynamicImage, ImageEncoder};
+use netcanv_protocol::client::{ChunkEncoding, ChunkFormat};
 
 use crate::paint_canvas::cache_layer::CachedChunk;
 use crate::paint_canvas::chunk::Chunk;
+use crate::perf::{self, Metric};
 use crate::Error;
 
 pub struct ImageCoder;
 
 impl ImageCoder {
    /// The maximum size threshold for a PNG to get converted to lossy WebP before network
-   /// transmission.
+   /// transmission, when the room's chunk format is [`ChunkFormat::Auto`].
    const MAX_PNG_SIZE: usize = 32 * 1024;
 
-   /// Encodes an image to PNG data asynchronously.
-   pub async fn encode_png_data(image: RgbaImage) -> netcanv::Result<Vec<u8>> {
-      tokio::task::spawn_blocking(move || {
-         let mut bytes: Vec<u8> = Vec::new();
-         match PngEncoder::new(Cursor::new(&mut bytes)).write_image(
-            &image,
-            image.width(),
-            image.height(),
-            ColorType::Rgba8,
-         ) {
-            Ok(()) => (),
-            Err(error) => {
-               tracing::error!("error while encoding: {}", error);
-               return Err(error.into());
-            }
+   /// The magic bytes every QOI image starts with.
+   const QOI_MAGIC: &'static [u8] = b"qoif";
+
+   /// Encodes an image to lossy WebP of the given quality.
+   fn encode_webp_data(image: &RgbaImage, quality: u8) -> netcanv::Result<Vec<u8>> {
+      let mut bytes: Vec<u8> = Vec::new();
+      match WebPEncoder::new_with_quality(Cursor::new(&mut bytes), WebPQuality::lossy(quality))
+         .write_image(image, image.width(), image.height(), ColorType::Rgba8)
+      {
+         Ok(()) => (),
+         Err(error) => {
+            tracing::error!("error while encoding: {}", error);
+            return Err(error.into());
          }
-         Ok(bytes)
-      })
-      .await?
+      }
+      Ok(bytes)
    }
 
-   /// Encodes an image to WebP asynchronously.
-   async fn encode_webp_data(image: RgbaImage) -> netcanv::Result<Vec<u8>> {
-      tokio::task::spawn_blocking(move || {
-         let mut bytes: Vec<u8> = Vec::new();
-         match WebPEncoder::new_with_quality(
-            Cursor::new(&mut bytes),
-            WebPQuality::lossy(WebPQuality::DEFAULT),
-         )
-         .write_image(&image, image.width(), image.height(), ColorType::Rgba8)
-         {
-            Ok(()) => (),
-            Err(error) => {
-               tracing::error!("error while encoding: {}", error);
-               return Err(error.into());
-            }
+   /// Encodes an image to lossless QOI.
+   fn encode_qoi_data(image: &RgbaImage) -> netcanv::Result<Vec<u8>> {
+      let mut bytes: Vec<u8> = Vec::new();
+      match QoiEncoder::new(Cursor::new(&mut bytes)).write_image(
+         image,
+         image.width(),
+         image.height(),
+         ColorType::Rgba8,
+      ) {
+         Ok(()) => (),
+         Err(error) => {
+            tracing::error!("error while encoding: {}", error);
+            return Err(error.into());
          }
-         Ok(bytes)
-      })
-      .await?
+      }
+      Ok(bytes)
    }
 
-   /// Encodes a network image asynchronously. This encodes PNG, as well as WebP if the PNG is too
-   /// large, and returns both images.
-   pub async fn encode_network_data(image: RgbaImage) -> netcanv::Result<CachedChunk> {
-      let png = Self::encode_png_data(image.clone()).await?;
-      let webp = if png.len() > Self::MAX_PNG_SIZE {
-         tracing::debug!("webp");
-         Some(Self::encode_webp_data(image).await?)
-      } else {
-         None
-      };
-      Ok(CachedChunk { png, webp })
+   /// Encodes a network image, following the room's chunk encoding. This encodes PNG, as well as
+   /// WebP or QOI if the format asks for it, and returns all the images, tagged with the revision
+   /// of the chunk the image was downloaded from. The PNG is always kept around for peers that
+   /// can't decode the other formats.
+   ///
+   /// This blocks for a good while, so it should only be called from an
+   /// [`EncodePool`][crate::paint_canvas::encode_pool::EncodePool] worker.
+   pub fn encode_network_data(
+      image: RgbaImage,
+      revision: u64,
+      encoding: ChunkEncoding,
+   ) -> netcanv::Result<CachedChunk> {
+      perf::measure(Metric::ChunkEncode, || {
+         let png = Self::encode_png_data_sync(image.clone())?;
+         let use_webp = match encoding.format {
+            ChunkFormat::Png | ChunkFormat::Qoi => false,
+            ChunkFormat::Auto => png.len() > Self::MAX_PNG_SIZE,
+            ChunkFormat::WebP => true,
+         };
+         let qoi = if encoding.format == ChunkFormat::Qoi {
+            tracing::debug!("qoi");
+            Some(Self::encode_qoi_data(&image)?)
+         } else {
+            None
+         };
+         let webp = if use_webp {
+            tracing::debug!("webp");
+            Some(Self::encode_webp_data(&image, encoding.quality)?)
+         } else {
+            None
+         };
+         Ok(CachedChunk {
+            png,
+            webp,
+            qoi,
+            revision,
+         })
+      })
    }
 
    /// Encodes an image to PNG data synchronously.
@@ -108,20 +132,33 @@ impl ImageCoder {
       Ok(image)
    }
 
-   /// Decodes a PNG or WebP file into the given sub-chunk, depending on what's actually stored in
-   /// `data`.
+   /// Decodes a QOI file into the given sub-chunk.
+   fn decode_qoi_data(data: &[u8]) -> netcanv::Result<RgbaImage> {
+      let decoder = QoiDecoder::new(Cursor::new(data))?;
+      let image = DynamicImage::from_decoder(decoder)?.into_rgba8();
+      Ok(image)
+   }
+
+   /// Decodes a PNG, WebP, or QOI file into the given sub-chunk, depending on what's actually
+   /// stored in `data`.
    pub fn decode_network_data(data: &[u8]) -> netcanv::Result<RgbaImage> {
-      // Try WebP first.
-      let image = Self::decode_webp_data(data).or_else(|_| Self::decode_png_data(data))?;
-      if image.dimensions() != Chunk::SIZE {
-         tracing::error!(
-            "received chunk with invalid size. got: {:?}, expected {:?}",
-            image.dimensions(),
-            Chunk::SIZE
-         );
-         Err(Error::InvalidChunkImageSize)
-      } else {
-         Ok(image)
-      }
+      perf::measure(Metric::ChunkDecode, || {
+         // QOI is easy to tell apart by its magic bytes. Otherwise, try WebP first.
+         let image = if data.starts_with(Self::QOI_MAGIC) {
+            Self::decode_qoi_data(data)?
+         } else {
+            Self::decode_webp_data(data).or_else(|_| Self::decode_png_data(data))?
+         };
+         if image.dimensions() != Chunk::SIZE {
+            tracing::error!(
+               "received chunk with invalid size. got: {:?}, expected {:?}",
+               image.dimensions(),
+               Chunk::SIZE
+            );
+            Err(Error::InvalidChunkImageSize)
+         } else {
+            Ok(image)
+         }
+      })
    }
 }
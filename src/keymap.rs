@@ -15,6 +15,16 @@ pub struct Keymap {
    #[serde(default)]
    pub tools: ToolKeymap,
    pub brush: BrushKeymap,
+   #[serde(default)]
+   pub image: ImageKeymap,
+   #[serde(default)]
+   pub canvas: CanvasKeymap,
+   #[serde(default)]
+   pub windows: WindowKeymap,
+   #[serde(default)]
+   pub actions: ActionKeymap,
+   #[serde(default)]
+   pub debug: DebugKeymap,
 }
 
 /// The key map for common editing actions, such as copying and pasting.
@@ -25,14 +35,44 @@ pub struct EditKeymap {
    pub paste: KeyBinding,
    pub delete: KeyBinding,
    pub select_all: KeyBinding,
+   /// Undoes our most recent operation, room-wide.
+   #[serde(default = "default_undo")]
+   pub undo: KeyBinding,
+   /// Redoes our most recently undone operation, room-wide.
+   #[serde(default = "default_redo")]
+   pub redo: KeyBinding,
+   /// Pastes an image into a new selection, rather than as a floating image.
+   #[serde(default = "default_paste_into_selection")]
+   pub paste_into_selection: KeyBinding,
+}
+
+fn default_undo() -> KeyBinding {
+   (Modifier::CTRL, VirtualKeyCode::Z)
+}
+
+fn default_redo() -> KeyBinding {
+   (Modifier::CTRL, VirtualKeyCode::Y)
+}
+
+fn default_paste_into_selection() -> KeyBinding {
+   (Modifier::CTRL | Modifier::SHIFT, VirtualKeyCode::V)
 }
 
 /// The key map for selecting tools.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct ToolKeymap {
    pub selection: KeyBinding,
    pub brush: KeyBinding,
    pub eyedropper: KeyBinding,
+   pub lock: KeyBinding,
+   pub laser: KeyBinding,
+   pub note: KeyBinding,
+   pub image: KeyBinding,
+   pub text: KeyBinding,
+   pub shapes: KeyBinding,
+   pub airbrush: KeyBinding,
+   pub stamp: KeyBinding,
 }
 
 impl Default for ToolKeymap {
@@ -41,15 +81,166 @@ impl Default for ToolKeymap {
          selection: (Modifier::NONE, VirtualKeyCode::Key1),
          brush: (Modifier::NONE, VirtualKeyCode::Key2),
          eyedropper: (Modifier::NONE, VirtualKeyCode::Key3),
+         lock: (Modifier::NONE, VirtualKeyCode::Key4),
+         laser: (Modifier::NONE, VirtualKeyCode::Key5),
+         note: (Modifier::NONE, VirtualKeyCode::Key6),
+         image: (Modifier::NONE, VirtualKeyCode::Key7),
+         text: (Modifier::NONE, VirtualKeyCode::Key8),
+         shapes: (Modifier::NONE, VirtualKeyCode::Key9),
+         airbrush: (Modifier::NONE, VirtualKeyCode::Key0),
+         stamp: (Modifier::NONE, VirtualKeyCode::Minus),
       }
    }
 }
 
 /// The key mappings for the brush tool.
 #[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
 pub struct BrushKeymap {
    pub decrease_thickness: KeyBinding,
    pub increase_thickness: KeyBinding,
+   /// The modifier that has to be held while scrolling to change the thickness, rather than zoom.
+   pub thickness_scroll: Modifier,
+}
+
+impl Default for BrushKeymap {
+   fn default() -> Self {
+      Self {
+         decrease_thickness: (Modifier::NONE, VirtualKeyCode::LBracket),
+         increase_thickness: (Modifier::NONE, VirtualKeyCode::RBracket),
+         thickness_scroll: Modifier::CTRL,
+      }
+   }
+}
+
+/// The key mappings for placing a pasted image.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ImageKeymap {
+   pub confirm: KeyBinding,
+   pub cancel: KeyBinding,
+   pub nudge_left: KeyBinding,
+   pub nudge_right: KeyBinding,
+   pub nudge_up: KeyBinding,
+   pub nudge_down: KeyBinding,
+}
+
+impl Default for ImageKeymap {
+   fn default() -> Self {
+      Self {
+         confirm: (Modifier::NONE, VirtualKeyCode::Return),
+         cancel: (Modifier::NONE, VirtualKeyCode::Escape),
+         nudge_left: (Modifier::NONE, VirtualKeyCode::Left),
+         nudge_right: (Modifier::NONE, VirtualKeyCode::Right),
+         nudge_up: (Modifier::NONE, VirtualKeyCode::Up),
+         nudge_down: (Modifier::NONE, VirtualKeyCode::Down),
+      }
+   }
+}
+
+/// The key mappings for actions performed on the canvas regardless of the current tool.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct CanvasKeymap {
+   /// The modifier that has to be held while left-clicking to ping a location.
+   pub ping: Modifier,
+   /// The modifier that has to be held while left-clicking to pick the color under the cursor,
+   /// no matter which tool is selected.
+   pub pick_color: Modifier,
+   /// The modifier that has to be held while dragging the reference image with the left mouse
+   /// button to move it around.
+   pub move_reference_image: Modifier,
+   pub toggle_minimap: KeyBinding,
+   pub rotate_counterclockwise: KeyBinding,
+   pub rotate_clockwise: KeyBinding,
+   pub reset_rotation: KeyBinding,
+   pub toggle_grid: KeyBinding,
+   pub toggle_grid_snapping: KeyBinding,
+   pub zoom_in: KeyBinding,
+   pub zoom_out: KeyBinding,
+   pub reset_zoom: KeyBinding,
+   /// The modifier that has to be held while pressing a number key to save the view as a bookmark.
+   pub save_bookmark: Modifier,
+   /// The modifier that has to be held while pressing a number key to jump to a bookmark. The
+   /// number keys alone already switch tools.
+   pub jump_to_bookmark: Modifier,
+}
+
+impl Default for CanvasKeymap {
+   fn default() -> Self {
+      Self {
+         ping: Modifier::ALT,
+         pick_color: Modifier::CTRL,
+         move_reference_image: Modifier::SHIFT,
+         toggle_minimap: (Modifier::NONE, VirtualKeyCode::M),
+         rotate_counterclockwise: (Modifier::NONE, VirtualKeyCode::Comma),
+         rotate_clockwise: (Modifier::NONE, VirtualKeyCode::Period),
+         reset_rotation: (Modifier::NONE, VirtualKeyCode::Slash),
+         toggle_grid: (Modifier::NONE, VirtualKeyCode::G),
+         toggle_grid_snapping: (Modifier::SHIFT, VirtualKeyCode::G),
+         zoom_in: (Modifier::CTRL, VirtualKeyCode::Equals),
+         zoom_out: (Modifier::CTRL, VirtualKeyCode::Minus),
+         reset_zoom: (Modifier::CTRL, VirtualKeyCode::Key0),
+         save_bookmark: Modifier::CTRL,
+         jump_to_bookmark: Modifier::ALT,
+      }
+   }
+}
+
+/// The key mappings for opening and closing windows.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct WindowKeymap {
+   /// The People window, which is also where voice chat is joined.
+   pub people: KeyBinding,
+   pub layers: KeyBinding,
+   pub history: KeyBinding,
+   pub keyboard_shortcuts: KeyBinding,
+}
+
+impl Default for WindowKeymap {
+   fn default() -> Self {
+      Self {
+         people: (Modifier::NONE, VirtualKeyCode::P),
+         layers: (Modifier::NONE, VirtualKeyCode::L),
+         history: (Modifier::NONE, VirtualKeyCode::H),
+         keyboard_shortcuts: (Modifier::CTRL, VirtualKeyCode::Comma),
+      }
+   }
+}
+
+/// The key mappings for the actions in the overflow menu.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct ActionKeymap {
+   pub save_to_file: KeyBinding,
+}
+
+impl Default for ActionKeymap {
+   fn default() -> Self {
+      Self {
+         save_to_file: (Modifier::CTRL, VirtualKeyCode::S),
+      }
+   }
+}
+
+/// The key mappings for debugging tools.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(default)]
+pub struct DebugKeymap {
+   pub console: KeyBinding,
+   pub network_stats: KeyBinding,
+   pub perf_hud: KeyBinding,
+}
+
+impl Default for DebugKeymap {
+   fn default() -> Self {
+      Self {
+         console: (Modifier::NONE, VirtualKeyCode::F12),
+         network_stats: (Modifier::NONE, VirtualKeyCode::F11),
+         perf_hud: (Modifier::NONE, VirtualKeyCode::F10),
+      }
+   }
 }
 
 impl Default for Keymap {
@@ -61,12 +252,193 @@ impl Default for Keymap {
             paste: (Modifier::CTRL, VirtualKeyCode::V),
             delete: (Modifier::NONE, VirtualKeyCode::Delete),
             select_all: (Modifier::CTRL, VirtualKeyCode::A),
+            undo: default_undo(),
+            redo: default_redo(),
+            paste_into_selection: default_paste_into_selection(),
          },
          tools: Default::default(),
-         brush: BrushKeymap {
-            decrease_thickness: (Modifier::NONE, VirtualKeyCode::LBracket),
-            increase_thickness: (Modifier::NONE, VirtualKeyCode::RBracket),
-         },
+         brush: Default::default(),
+         image: Default::default(),
+         canvas: Default::default(),
+         windows: Default::default(),
+         actions: Default::default(),
+         debug: Default::default(),
+      }
+   }
+}
+
+impl Keymap {
+   /// The names of the key map's sections, in the order they're listed in the Keyboard shortcuts
+   /// window.
+   pub const SECTIONS: [&'static str; 8] = [
+      "edit", "tools", "brush", "image", "canvas", "windows", "actions", "debug",
+   ];
+
+   /// Returns the key bindings in the given section, along with their names. Modifiers that are
+   /// held while clicking or scrolling can only be changed in the config file, so they're left out.
+   pub fn section_mut(&mut self, section: &str) -> Vec<(&'static str, &mut KeyBinding)> {
+      // The key maps are destructured fully, so that new bindings can't be forgotten here.
+      match section {
+         "edit" => {
+            let EditKeymap {
+               copy,
+               cut,
+               paste,
+               delete,
+               select_all,
+               undo,
+               redo,
+               paste_into_selection,
+            } = &mut self.edit;
+            vec![
+               ("copy", copy),
+               ("cut", cut),
+               ("paste", paste),
+               ("paste-into-selection", paste_into_selection),
+               ("delete", delete),
+               ("select-all", select_all),
+               ("undo", undo),
+               ("redo", redo),
+            ]
+         }
+         "tools" => {
+            let ToolKeymap {
+               selection,
+               brush,
+               eyedropper,
+               lock,
+               laser,
+               note,
+               image,
+               text,
+               shapes,
+               airbrush,
+               stamp,
+            } = &mut self.tools;
+            vec![
+               ("selection", selection),
+               ("brush", brush),
+               ("eyedropper", eyedropper),
+               ("lock", lock),
+               ("laser", laser),
+               ("note", note),
+               ("image", image),
+               ("text", text),
+               ("shapes", shapes),
+               ("airbrush", airbrush),
+               ("stamp", stamp),
+            ]
+         }
+         "brush" => {
+            let BrushKeymap {
+               decrease_thickness,
+               increase_thickness,
+               thickness_scroll: _,
+            } = &mut self.brush;
+            vec![
+               ("decrease-thickness", decrease_thickness),
+               ("increase-thickness", increase_thickness),
+            ]
+         }
+         "image" => {
+            let ImageKeymap {
+               confirm,
+               cancel,
+               nudge_left,
+               nudge_right,
+               nudge_up,
+               nudge_down,
+            } = &mut self.image;
+            vec![
+               ("confirm", confirm),
+               ("cancel", cancel),
+               ("nudge-left", nudge_left),
+               ("nudge-right", nudge_right),
+               ("nudge-up", nudge_up),
+               ("nudge-down", nudge_down),
+            ]
+         }
+         "canvas" => {
+            let CanvasKeymap {
+               ping: _,
+               pick_color: _,
+               move_reference_image: _,
+               toggle_minimap,
+               rotate_counterclockwise,
+               rotate_clockwise,
+               reset_rotation,
+               toggle_grid,
+               toggle_grid_snapping,
+               zoom_in,
+               zoom_out,
+               reset_zoom,
+               save_bookmark: _,
+               jump_to_bookmark: _,
+            } = &mut self.canvas;
+            vec![
+               ("zoom-in", zoom_in),
+               ("zoom-out", zoom_out),
+               ("reset-zoom", reset_zoom),
+               ("rotate-counterclockwise", rotate_counterclockwise),
+               ("rotate-clockwise", rotate_clockwise),
+               ("reset-rotation", reset_rotation),
+               ("toggle-minimap", toggle_minimap),
+               ("toggle-grid", toggle_grid),
+               ("toggle-grid-snapping", toggle_grid_snapping),
+            ]
+         }
+         "windows" => {
+            let WindowKeymap {
+               people,
+               layers,
+               history,
+               keyboard_shortcuts,
+            } = &mut self.windows;
+            vec![
+               ("people", people),
+               ("layers", layers),
+               ("history", history),
+               ("keyboard-shortcuts", keyboard_shortcuts),
+            ]
+         }
+         "actions" => {
+            let ActionKeymap { save_to_file } = &mut self.actions;
+            vec![("save-to-file", save_to_file)]
+         }
+         "debug" => {
+            let DebugKeymap {
+               console,
+               network_stats,
+               perf_hud,
+            } = &mut self.debug;
+            vec![
+               ("console", console),
+               ("network-stats", network_stats),
+               ("perf-hud", perf_hud),
+            ]
+         }
+         _ => Vec::new(),
       }
    }
 }
+
+/// Returns a human-readable description of the key binding, such as `Ctrl+Shift+V`.
+pub fn describe((modifier, key): KeyBinding) -> String {
+   let mut description = String::new();
+   if modifier.ctrl() {
+      description.push_str("Ctrl+");
+   }
+   if modifier.shift() {
+      description.push_str("Shift+");
+   }
+   if modifier.alt() {
+      description.push_str("Alt+");
+   }
+   let key = format!("{:?}", key);
+   // Number keys are called `Key1`, `Key2`, and so on.
+   match key.strip_prefix("Key").filter(|digit| digit.len() == 1) {
+      Some(digit) => description.push_str(digit),
+      None => description.push_str(&key),
+   }
+   description
+}
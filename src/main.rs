@@ -64,9 +64,8 @@ use nysa::global as bus;
 use tracing::{error, info, warn};
 use tracing_subscriber::filter::LevelFilter;
 use tracing_subscriber::prelude::*;
-use tracing_subscriber::{EnvFilter, Layer};
+use tracing_subscriber::{reload, EnvFilter, Layer};
 use web_time::{Duration, Instant};
-use whd_common::{WALLHACKD_VERSION, WALLHACKD_YEAR};
 
 use crate::backend::UiRenderFrame;
 
@@ -77,20 +76,27 @@ mod errors;
 
 mod app;
 mod assets;
+mod autosave;
 mod backend;
 mod cli;
 mod clipboard;
 mod color;
 mod config;
+mod console_log;
+mod crash_report;
+mod file_dialog;
 mod image_coder;
 mod keymap;
 mod net;
 mod paint_canvas;
+mod perf;
 mod project_file;
 mod strings;
+mod telemetry;
 mod token;
 mod ui;
 mod viewport;
+mod voice_chat;
 
 use app::*;
 use assets::*;
@@ -109,10 +115,14 @@ async fn inner_main(language: &mut Option<Language>) -> errors::Result<()> {
    // Set up logging.
    let mut log_guards = Some(init_logging(&cli)?);
    info!("NetCanv {}", env!("CARGO_PKG_VERSION"));
+   telemetry::init();
 
    // Load user configuration.
    config::load_or_create()?;
 
+   // Make netcanv:// links open NetCanv.
+   net::room_link::register_url_scheme();
+
    // Set up the winit event loop and open the window.
    let (renderer, event_loop) = {
       profiling::scope!("init_renderer");
@@ -121,7 +131,7 @@ async fn inner_main(language: &mut Option<Language>) -> errors::Result<()> {
       let window_builder = {
          let b = WindowBuilder::new()
             .with_inner_size(PhysicalSize::<u32>::new(1024, 600))
-            .with_title(format!("NetCanv WallhackD ({}) ({})", WALLHACKD_VERSION, WALLHACKD_YEAR))
+            .with_title(app::window_title(None))
             .with_resizable(true);
          if let Some(window) = &config().window {
             b.with_inner_size(PhysicalSize::new(window.width, window.height))
@@ -158,6 +168,9 @@ async fn inner_main(language: &mut Option<Language>) -> errors::Result<()> {
    let assets = Box::new(Assets::new(ui.render(), color_scheme)?);
    let socket_system = SocketSystem::new();
    *language = Some(assets.language.clone());
+   if let Some(report) = crash_report::take_pending() {
+      crash_report::offer_to_open(&assets, &report);
+   }
    let mut app: Option<Box<dyn AppState>> = Some(boot::State::new_state(
       cli,
       assets,
@@ -203,7 +216,9 @@ async fn inner_main(language: &mut Option<Language>) -> errors::Result<()> {
                   last_window_position = new_position;
                }
                WindowEvent::CloseRequested => {
-                  *control_flow = ControlFlow::Exit;
+                  if app.as_mut().unwrap().confirm_exit() {
+                     *control_flow = ControlFlow::Exit;
+                  }
                }
                _ => {
                   input.process_event(&event);
@@ -213,7 +228,10 @@ async fn inner_main(language: &mut Option<Language>) -> errors::Result<()> {
 
          Event::MainEventsCleared => {
             let window_size = ui.window().inner_size();
+            let frame_start = Instant::now();
+            let mut update_time = Duration::ZERO;
             if let Err(error) = ui.render_frame(|ui| {
+               let update_start = Instant::now();
                ui.root(
                   vector(window_size.width as f32, window_size.height as f32),
                   Layout::Freeform,
@@ -228,9 +246,13 @@ async fn inner_main(language: &mut Option<Language>) -> errors::Result<()> {
                   root_view,
                });
                app = Some(app.take().unwrap().next_state(ui.render()));
+               update_time = update_start.elapsed();
             }) {
                error!("render error: {}", error)
             }
+            perf::record(perf::Metric::Update, update_time);
+            perf::record(perf::Metric::Present, frame_start.elapsed().saturating_sub(update_time));
+            perf::finish_frame();
             input.finish_frame(ui.window());
          }
 
@@ -255,6 +277,8 @@ async fn inner_main(language: &mut Option<Language>) -> errors::Result<()> {
             let app = app.take().unwrap();
             app.exit();
 
+            telemetry::send_report();
+
             let _ = log_guards.take();
          }
 
@@ -301,12 +325,19 @@ static ALLOCATOR: profiling::tracy_client::ProfiledAllocator<std::alloc::System>
 fn main() {
    let default_panic_hook = std::panic::take_hook();
    std::panic::set_hook(Box::new(move |panic_info| {
+      let report = crash_report::write(panic_info);
+      if let Err(error) = &report {
+         eprintln!("could not write crash report: {}", error);
+      }
       // Pretty panic messages are only enabled in release mode, as they hinder debugging.
       #[cfg(not(debug_assertions))]
       {
          let mut message = heapless::String::<8192>::new();
          let _ = write!(message, "Oh no! A fatal error occured.\n{}", panic_info);
          let _ = write!(message, "\n\nThis is most definitely a bug, so please file an issue on GitHub. https://github.com/netcanv/netcanv");
+         if let Ok(path) = &report {
+            let _ = write!(message, "\n\nA crash report was saved to {}", path.display());
+         }
          let _ = MessageDialog::new()
             .set_title("NetCanv - Fatal Error")
             .set_description(message.to_string())
@@ -352,15 +383,30 @@ fn init_logging(cli: &Cli) -> errors::Result<LogGuards> {
       (Some(chrome_trace), guard)
    });
 
+   // The stderr and console filters can be changed at runtime from the debug console.
+   let log_filter = || {
+      EnvFilter::builder()
+         .with_default_directive(LevelFilter::INFO.into())
+         .with_env_var("NETCANV_LOG")
+         .from_env_lossy()
+   };
+   let (stderr_filter, stderr_filter_handle) = reload::Layer::new(log_filter());
+   let (console_filter, console_filter_handle) = reload::Layer::new(log_filter());
+   console_log::add_filter_reloader(move |filter| {
+      stderr_filter_handle.reload(filter).map_err(|e| e.to_string())
+   });
+   console_log::add_filter_reloader(move |filter| {
+      console_filter_handle.reload(filter).map_err(|e| e.to_string())
+   });
+
    let subscriber = tracing_subscriber::registry()
       .with(
-         tracing_subscriber::fmt::layer().without_time().with_writer(std::io::stderr).with_filter(
-            EnvFilter::builder()
-               .with_default_directive(LevelFilter::INFO.into())
-               .with_env_var("NETCANV_LOG")
-               .from_env_lossy(),
-         ),
+         tracing_subscriber::fmt::layer()
+            .without_time()
+            .with_writer(std::io::stderr)
+            .with_filter(stderr_filter),
       )
+      .with(console_log::ConsoleLogLayer.with_filter(console_filter))
       .with(chrome_trace.as_mut().and_then(|(ct, _)| ct.take()));
 
    tracing::subscriber::set_global_default(subscriber).map_err(|e| {
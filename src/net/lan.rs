@@ -0,0 +1,165 @@
+//! Rooms on the local network, which work without an Internet relay.
+//!
+//! The host runs a relay of its own, and advertises its room over mDNS, so that others on the
+//! same network can find the room and connect to the host directly.
+
+use std::net::{Ipv4Addr, SocketAddr, TcpListener as StdTcpListener};
+use std::sync::Arc;
+
+use mdns_sd::{ServiceDaemon, ServiceEvent, ServiceInfo};
+use netcanv_protocol::relay::RoomId;
+use netcanv_relay::{HostGate, State};
+use tokio::net::TcpListener;
+use tokio::sync::Mutex;
+use tokio::task::JoinHandle;
+
+/// The mDNS service type NetCanv rooms are advertised under.
+const SERVICE_TYPE: &str = "_netcanv._tcp.local.";
+
+/// A relay running inside NetCanv, serving a room on the local network.
+///
+/// The relay stops once this is dropped, which ends the room for everyone in it.
+pub struct LanRelay {
+   port: u16,
+   server: JoinHandle<()>,
+   advertisement: Option<(ServiceDaemon, String)>,
+}
+
+impl LanRelay {
+   /// Starts the relay on a free port, listening on all network interfaces.
+   pub fn start() -> netcanv::Result<Self> {
+      let listener = StdTcpListener::bind((Ipv4Addr::UNSPECIFIED, 0))?;
+      listener.set_nonblocking(true)?;
+      let port = listener.local_addr()?.port();
+      let listener = TcpListener::from_std(listener)?;
+      let state = Arc::new(Mutex::new(State::new(HostGate::default())));
+      let server = tokio::spawn(async move {
+         if let Err(error) = netcanv_relay::serve(listener, state).await {
+            tracing::error!("LAN relay error: {:?}", error);
+         }
+      });
+      tracing::info!("LAN relay listening on port {}", port);
+      Ok(Self {
+         port,
+         server,
+         advertisement: None,
+      })
+   }
+
+   /// Returns the port the relay listens on.
+   pub fn port(&self) -> u16 {
+      self.port
+   }
+
+   /// Returns the address the host connects to the relay at.
+   pub fn address(&self) -> String {
+      format!("ws://127.0.0.1:{}", self.port)
+   }
+
+   /// Advertises the room on the local network under the given name.
+   pub fn advertise(&mut self, room_id: RoomId, name: &str) -> netcanv::Result<()> {
+      let daemon = ServiceDaemon::new()?;
+      let room_id = room_id.to_string();
+      let service = ServiceInfo::new(
+         SERVICE_TYPE,
+         &room_id,
+         &format!("netcanv-{}.local.", room_id),
+         "",
+         self.port,
+         &[("room", room_id.as_str()), ("name", name)][..],
+      )?
+      .enable_addr_auto();
+      let fullname = service.get_fullname().to_owned();
+      daemon.register(service)?;
+      tracing::info!("advertising room {} on the local network", room_id);
+      self.advertisement = Some((daemon, fullname));
+      Ok(())
+   }
+}
+
+impl Drop for LanRelay {
+   fn drop(&mut self) {
+      if let Some((daemon, fullname)) = self.advertisement.take() {
+         let _ = daemon.unregister(&fullname);
+         let _ = daemon.shutdown();
+      }
+      self.server.abort();
+   }
+}
+
+/// A room found on the local network.
+#[derive(Clone)]
+pub struct LanRoom {
+   /// The mDNS name the room is advertised under, which tells rooms apart.
+   fullname: String,
+   pub name: String,
+   pub room_id: RoomId,
+   /// The address of the relay run by the room's host.
+   pub relay_address: String,
+}
+
+/// Looks for rooms advertised on the local network.
+pub struct LanBrowser {
+   daemon: ServiceDaemon,
+   events: mdns_sd::Receiver<ServiceEvent>,
+   rooms: Vec<LanRoom>,
+}
+
+impl LanBrowser {
+   /// Starts looking for rooms.
+   pub fn start() -> netcanv::Result<Self> {
+      let daemon = ServiceDaemon::new()?;
+      let events = daemon.browse(SERVICE_TYPE)?;
+      Ok(Self {
+         daemon,
+         events,
+         rooms: Vec::new(),
+      })
+   }
+
+   /// Updates the list of rooms with the ones that appeared or disappeared since the last poll.
+   pub fn poll(&mut self) {
+      while let Ok(event) = self.events.try_recv() {
+         match event {
+            ServiceEvent::ServiceResolved(service) => {
+               if let Some(room) = Self::room(&service) {
+                  self.rooms.retain(|other| other.fullname != room.fullname);
+                  self.rooms.push(room);
+               }
+            }
+            ServiceEvent::ServiceRemoved(_, fullname) => {
+               self.rooms.retain(|room| room.fullname != fullname);
+            }
+            _ => (),
+         }
+      }
+   }
+
+   /// Returns the rooms found so far.
+   pub fn rooms(&self) -> &[LanRoom] {
+      &self.rooms
+   }
+
+   /// Reads a room out of an mDNS service. Returns `None` if the service doesn't describe a
+   /// NetCanv room.
+   fn room(service: &ServiceInfo) -> Option<LanRoom> {
+      let room_id = service.get_property_val_str("room")?.parse().ok()?;
+      let name = service.get_property_val_str("name").unwrap_or_default().to_owned();
+      let addresses = service.get_addresses();
+      // IPv4 addresses are preferred, as link-local IPv6 addresses need a scope to be reachable.
+      let ip = addresses.iter().find(|ip| ip.is_ipv4()).or_else(|| addresses.iter().next())?;
+      Some(LanRoom {
+         fullname: service.get_fullname().to_owned(),
+         name,
+         room_id,
+         relay_address: format!("ws://{}", SocketAddr::new(*ip, service.get_port())),
+      })
+   }
+}
+
+impl Drop for LanBrowser {
+   fn drop(&mut self) {
+      let _ = self.daemon.stop_browse(SERVICE_TYPE);
+      let _ = self.daemon.shutdown();
+   }
+}
@@ -1,3 +1,10 @@
+pub mod lan;
 pub mod peer;
+pub mod port_mapping;
+pub mod recording;
+pub mod relay_ping;
+pub mod room_link;
+pub mod room_list;
 pub mod socket;
+pub mod stats;
 pub mod timer;
@@ -1,14 +1,22 @@
+use std::cell::{Cell, Ref, RefCell};
 use std::cmp::Ordering;
 use std::collections::HashMap;
+use std::path::Path;
 use std::sync::Arc;
 
-use netcanv_protocol::relay::{PeerId, RoomId};
+use netcanv_protocol::relay::{InviteToken, PeerId, RoomId, RoomListing};
 use netcanv_protocol::{client as cl, relay};
 use nysa::global as bus;
 use tokio::sync::oneshot;
+use web_time::{Duration, SystemTime, UNIX_EPOCH};
 
-use super::socket::{Socket, SocketSystem};
-use crate::common::{deserialize_bincode, serialize_bincode, Fatal};
+use super::lan::LanRelay;
+use super::port_mapping::PortMapping;
+use super::recording::{Entry, Recorder, Replay};
+use super::socket::{ConnectionStage, PendingSocket, Socket, SocketSystem};
+use super::stats::{NetworkStats, TrafficKind};
+use crate::common::{decode_packet, serialize_bincode, truncate_graphemes, Fatal};
+use crate::telemetry;
 use crate::token::Token;
 use crate::Error;
 
@@ -40,13 +48,18 @@ pub enum MessageKind {
    /// The host role has been transferred to another peer in the room.
    NewHost(String),
    /// The host role has been transferred to the peer (you).
-   NowHosting,
-   /// The host sent us the chunk positions for the room.
-   ChunkPositions(Vec<(i32, i32)>),
-   /// Somebody requested chunk positions from the host.
-   GetChunks(PeerId, Vec<(i32, i32)>),
-   /// Somebody sent us chunk image data.
-   Chunks(Vec<((i32, i32), Vec<u8>)>),
+   ///
+   /// If the previous host is still in the room, they handed the role over on purpose, and their
+   /// ID is included so that the rest of the canvas can be downloaded from them.
+   NowHosting { previous_host: Option<PeerId> },
+   /// The host sent us the chunk positions for one of the room's layers.
+   ChunkPositions(usize, Vec<(i32, i32)>),
+   /// Somebody requested chunk positions on a layer from the host.
+   GetChunks(PeerId, usize, Vec<(i32, i32)>),
+   /// Somebody sent us chunk image data for a layer.
+   Chunks(usize, Vec<((i32, i32), Vec<u8>)>),
+   /// The host sent us digests of the chunks on a layer changed by an operation.
+   ChunkDigests(usize, Vec<((i32, i32), Vec<u8>)>),
    /// A tool packet was received from an address.
    Tool(PeerId, String, Vec<u8>),
    /// The client selected a tool.
@@ -55,18 +68,124 @@ pub enum MessageKind {
       previous_tool: Option<String>,
       tool: String,
    },
+   /// The host sent us the list of locked regions.
+   LockedRegions(Vec<((i32, i32), (i32, i32))>),
+   /// The host paused or resumed modifications to the canvas.
+   Paused(bool),
+   /// The host told us the name and description of the room.
+   RoomInfo { name: String, description: String },
+   /// The host shared the room's color palette.
+   RoomPalette(Vec<(u8, u8, u8)>),
+   /// The host shared their viewport bookmarks.
+   Bookmarks(Vec<cl::Bookmark>),
+   /// A peer finished an operation. Only received by the host.
+   CommitOperation(PeerId),
+   /// A peer asked for their most recent operation to be undone. Only received by the host.
+   Undo(PeerId),
+   /// A peer asked for their most recently undone operation to be redone. Only received by the
+   /// host.
+   Redo(PeerId),
+   /// A peer pinged a location on the canvas.
+   Ping(PeerId, (i32, i32)),
+   /// A sticky note was created or changed.
+   SetNote(cl::NoteId, cl::StickyNote),
+   /// A sticky note was removed.
+   RemoveNote(cl::NoteId),
+   /// The host sent us all the sticky notes on the canvas.
+   Notes(Vec<(cl::NoteId, cl::StickyNote)>),
+   /// A floating image was added.
+   AddImage(cl::FloatingImageId, cl::ImagePlacement, Vec<u8>),
+   /// A floating image was moved or resized.
+   PlaceImage(cl::FloatingImageId, cl::ImagePlacement),
+   /// A floating image was removed.
+   RemoveImage(cl::FloatingImageId),
+   /// A peer flattened a floating image onto the canvas.
+   FlattenImage(PeerId, cl::FloatingImageId),
+   /// The host turned vector strokes on or off.
+   VectorMode(bool),
+   /// The host sent us the vector strokes drawn so far.
+   VectorStrokes(Vec<(cl::VectorStrokeId, cl::VectorStroke)>),
+   /// The host removed vector strokes after undoing an operation.
+   RemoveVectorStrokes(Vec<cl::VectorStrokeId>),
+   /// The host changed the canvas's bounds.
+   CanvasBounds(Option<(u32, u32)>),
+   /// The host changed the canvas's background.
+   Background(cl::Background),
+   /// The host changed how chunk images are encoded.
+   ChunkEncoding(cl::ChunkEncoding),
+   /// The host sent us the names of the room's pages.
+   Pages(Vec<String>),
+   /// The client selected a page.
+   SelectPage { peer_id: PeerId, page: usize },
+   /// The host changed the room's layers.
+   Layers(Vec<cl::Layer>),
+   /// The client selected a layer to draw on.
+   SelectLayer { peer_id: PeerId, layer: usize },
+   /// A peer's viewport moved.
+   Viewport {
+      peer_id: PeerId,
+      pan: (i32, i32),
+      zoom_level: i32,
+   },
+   /// The relay created an invite token for our room.
+   InviteCreated(InviteToken),
+   /// The relay let us know whether our room can only be joined with an invite token. Only
+   /// received by the host, after the host role was transferred to us.
+   InviteOnly(bool),
+   /// A peer sent us an encoded voice frame.
+   Voice(PeerId, Vec<u8>),
+   /// An admin with the correct password asked us to run a command. Only received by the host.
+   AdminCommand(PeerId, cl::AdminCommand),
+   /// The host responded to an admin command we sent.
+   AdminResponse(cl::AdminResponse),
 }
 
 /// Another person in the same room.
 pub struct Mate {
    pub nickname: String,
    pub tool: Option<String>,
+   /// The page the peer is on. Everyone starts out on the first page.
+   pub page: usize,
+   /// The layer the peer draws on. Everyone starts out on the bottom layer.
+   pub layer: usize,
+   /// What the peer's client can do. Peers are assumed to be capable of nothing until they say
+   /// otherwise.
+   pub capabilities: cl::Capabilities,
+   /// The round-trip time to the peer, once it's been measured.
+   pub latency: Option<Duration>,
+   /// Where the peer's viewport is and how far it's zoomed in, once they've told us.
+   pub viewport: Option<((i32, i32), i32)>,
 }
 
 enum State {
-   WaitingForRelay(oneshot::Receiver<netcanv::Result<Socket>>),
+   /// Asking the router to open a port for the relay we host the room on, while connecting to
+   /// the matchmaking relay. The room is created on the matchmaking relay once the router answers,
+   /// whether it opened the port or not.
+   MappingPort {
+      port_mapping: oneshot::Receiver<Option<PortMapping>>,
+      matchmaker: PendingSocket,
+   },
+   WaitingForRelay(PendingSocket),
    ConnectedToRelay,
    InRoom,
+   /// Asked the relay to let us out of the room, and waiting for it to confirm. Packets from the
+   /// room that arrive in the meantime are dropped.
+   LeavingRoom,
+   /// Not connected to any relay, and never will be. Packets are silently dropped, as there's
+   /// nobody to receive them.
+   Offline,
+   /// Replaying a recorded session. Like when offline, sent packets are dropped.
+   Replay(Replay),
+}
+
+/// A room hosted on our own relay, which people on the Internet connect to directly.
+struct DirectHosting {
+   /// The port mapped on the router, through which the relay can be reached.
+   port_mapping: PortMapping,
+   /// The connection to the matchmaking relay, which sends people joining the room over to our
+   /// relay. Kept open for as long as the room exists, as the matchmaking relay forgets the room
+   /// once we disconnect.
+   matchmaker: Option<Socket>,
 }
 
 /// A connection to the relay.
@@ -74,33 +193,148 @@ pub struct Peer {
    token: PeerToken,
    state: State,
    relay_socket: Option<Socket>,
+   /// Used for connecting to another relay, when the room turns out to be hosted directly.
+   socket_system: Option<Arc<SocketSystem>>,
 
    is_host: bool,
 
+   relay_address: String,
    nickname: String,
+   room_name: String,
+   room_description: String,
+   public: bool,
    room_id: Option<RoomId>,
+   invite: Option<InviteToken>,
    peer_id: Option<PeerId>,
    host: Option<PeerId>,
    mates: HashMap<PeerId, Mate>,
+   admin_password: Option<String>,
+   host_token: Option<String>,
+   /// The relay serving our room on the local network, if we're hosting one.
+   lan_relay: Option<LanRelay>,
+   /// Set once the router opened a port for the relay, if we're hosting our room directly.
+   direct: Option<DirectHosting>,
+
+   packet_loss: SimulatedPacketLoss,
+   stats: RefCell<NetworkStats>,
+   recorder: RefCell<Option<Recorder>>,
+}
+
+/// Randomly drops client packets, to reproduce issues that happen on bad connections.
+struct SimulatedPacketLoss {
+   /// The fraction of packets that is dropped.
+   fraction: f32,
+   /// The state of the xorshift generator deciding which packets are dropped.
+   random_state: Cell<u64>,
+}
+
+impl SimulatedPacketLoss {
+   fn new() -> Self {
+      let seed = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default().as_nanos();
+      Self {
+         fraction: 0.0,
+         // Xorshift gets stuck on zero, so make sure that the seed is never zero.
+         random_state: Cell::new(seed as u64 | 1),
+      }
+   }
+
+   /// Returns whether the next packet should be dropped.
+   fn should_drop(&self) -> bool {
+      if self.fraction <= 0.0 {
+         return false;
+      }
+      let mut x = self.random_state.get();
+      x ^= x << 13;
+      x ^= x >> 7;
+      x ^= x << 17;
+      self.random_state.set(x);
+      ((x >> 40) as f32 / (1u64 << 24) as f32) < self.fraction
+   }
 }
 
 static PEER_TOKEN: Token = Token::new(0);
 
 impl Peer {
-   /// Host a new room on the given relay server.
-   pub fn host(socket_system: Arc<SocketSystem>, nickname: &str, relay_address: &str) -> Self {
+   /// The maximum length of a room's name, in characters.
+   pub const MAX_ROOM_NAME_LEN: usize = 32;
+   /// The maximum length of a room's description, in characters.
+   pub const MAX_ROOM_DESCRIPTION_LEN: usize = 128;
+   /// The maximum number of colors in a room's palette.
+   pub const MAX_ROOM_PALETTE_LEN: usize = 16;
+
+   /// Host a new room on the given relay server. If a room ID is given, the room is created under
+   /// that ID rather than a random one.
+   pub fn host(
+      socket_system: Arc<SocketSystem>,
+      nickname: &str,
+      relay_address: &str,
+      room_id: Option<RoomId>,
+   ) -> Self {
       let socket_receiver = socket_system.connect(relay_address.to_owned());
       Self {
          token: PeerToken(PEER_TOKEN.next()),
          state: State::WaitingForRelay(socket_receiver),
          relay_socket: None,
+         socket_system: Some(socket_system),
          is_host: true,
+         relay_address: relay_address.into(),
          nickname: nickname.into(),
-         room_id: None,
+         room_name: String::new(),
+         room_description: String::new(),
+         public: false,
+         room_id,
+         invite: None,
          peer_id: None,
          mates: HashMap::new(),
+         packet_loss: SimulatedPacketLoss::new(),
+         stats: RefCell::new(NetworkStats::new()),
+         recorder: RefCell::new(None),
          host: None,
+         admin_password: None,
+         host_token: None,
+         lan_relay: None,
+         direct: None,
+      }
+   }
+
+   /// Host a new room on the local network, without an Internet relay. A relay is started
+   /// inside this peer, and the room is advertised on the network once it's created.
+   ///
+   /// The room ends once the peer leaves it, as nobody else can take over the relay.
+   pub fn host_on_lan(socket_system: Arc<SocketSystem>, nickname: &str) -> netcanv::Result<Self> {
+      let lan_relay = LanRelay::start()?;
+      let mut peer = Self::host(socket_system, nickname, &lan_relay.address(), None);
+      peer.lan_relay = Some(lan_relay);
+      Ok(peer)
+   }
+
+   /// Host a new room on a relay started inside this peer, which people on the Internet connect
+   /// to directly, through a port opened on the router. The given relay is only used for
+   /// matchmaking: it reserves the room's ID, and sends people joining the room over to us.
+   ///
+   /// If the router doesn't open the port, the room is hosted on the given relay as usual.
+   pub fn host_directly(
+      socket_system: Arc<SocketSystem>,
+      nickname: &str,
+      relay_address: &str,
+      room_id: Option<RoomId>,
+   ) -> netcanv::Result<Self> {
+      let lan_relay = LanRelay::start()?;
+      let (mapped, port_mapping) = oneshot::channel();
+      let port = lan_relay.port();
+      tokio::spawn(async move {
+         let _ = mapped.send(PortMapping::request(port).await);
+      });
+      let mut peer = Self::host(socket_system, nickname, relay_address, room_id);
+      if let State::WaitingForRelay(matchmaker) = std::mem::replace(&mut peer.state, State::Offline)
+      {
+         peer.state = State::MappingPort {
+            port_mapping,
+            matchmaker,
+         };
       }
+      peer.lan_relay = Some(lan_relay);
+      Ok(peer)
    }
 
    /// Join an existing room on the given relay server.
@@ -115,22 +349,159 @@ impl Peer {
          token: PeerToken(PEER_TOKEN.next()),
          state: State::WaitingForRelay(socket_receiver),
          relay_socket: None,
+         socket_system: Some(socket_system),
          is_host: false,
+         relay_address: relay_address.into(),
          nickname: nickname.into(),
+         room_name: String::new(),
+         room_description: String::new(),
+         public: false,
          room_id: Some(room_id),
+         invite: None,
+         peer_id: None,
+         mates: HashMap::new(),
+         packet_loss: SimulatedPacketLoss::new(),
+         stats: RefCell::new(NetworkStats::new()),
+         recorder: RefCell::new(None),
+         host: None,
+         admin_password: None,
+         host_token: None,
+         lan_relay: None,
+         direct: None,
+      }
+   }
+
+   /// Join an existing room on the given relay server, using an invite token.
+   pub fn join_with_invite(
+      socket_system: Arc<SocketSystem>,
+      nickname: &str,
+      relay_address: &str,
+      invite: InviteToken,
+   ) -> Self {
+      let socket_receiver = socket_system.connect(relay_address.to_owned());
+      Self {
+         token: PeerToken(PEER_TOKEN.next()),
+         state: State::WaitingForRelay(socket_receiver),
+         relay_socket: None,
+         socket_system: Some(socket_system),
+         is_host: false,
+         relay_address: relay_address.into(),
+         nickname: nickname.into(),
+         room_name: String::new(),
+         room_description: String::new(),
+         public: false,
+         room_id: None,
+         invite: Some(invite),
+         peer_id: None,
+         mates: HashMap::new(),
+         packet_loss: SimulatedPacketLoss::new(),
+         stats: RefCell::new(NetworkStats::new()),
+         recorder: RefCell::new(None),
+         host: None,
+         admin_password: None,
+         host_token: None,
+         lan_relay: None,
+         direct: None,
+      }
+   }
+
+   /// Creates a peer that's not connected to any relay, for drawing alone.
+   ///
+   /// Such a peer is always the host of its own room, which has no ID.
+   pub fn offline(nickname: &str) -> Self {
+      Self {
+         token: PeerToken(PEER_TOKEN.next()),
+         state: State::Offline,
+         relay_socket: None,
+         socket_system: None,
+         is_host: true,
+         relay_address: String::new(),
+         nickname: nickname.into(),
+         room_name: String::new(),
+         room_description: String::new(),
+         public: false,
+         room_id: None,
+         invite: None,
          peer_id: None,
          mates: HashMap::new(),
+         packet_loss: SimulatedPacketLoss::new(),
+         stats: RefCell::new(NetworkStats::new()),
+         recorder: RefCell::new(None),
          host: None,
+         admin_password: None,
+         host_token: None,
+         lan_relay: None,
+         direct: None,
+      }
+   }
+
+   /// Creates a peer that replays a recorded session, without connecting to any relay.
+   ///
+   /// Besides the packets received from others, packets sent by the recording peer which affect
+   /// the canvas are replayed too, as if they were received from that peer.
+   pub fn replay(nickname: &str, replay: Replay) -> Self {
+      Self {
+         token: PeerToken(PEER_TOKEN.next()),
+         state: State::Replay(replay),
+         relay_socket: None,
+         socket_system: None,
+         is_host: false,
+         relay_address: String::new(),
+         nickname: nickname.into(),
+         room_name: String::new(),
+         room_description: String::new(),
+         public: false,
+         room_id: None,
+         invite: None,
+         peer_id: None,
+         mates: HashMap::new(),
+         packet_loss: SimulatedPacketLoss::new(),
+         stats: RefCell::new(NetworkStats::new()),
+         recorder: RefCell::new(None),
+         host: None,
+         admin_password: None,
+         host_token: None,
+         lan_relay: None,
+         direct: None,
+      }
+   }
+
+   /// Starts recording all client packets to the given file. See [`recording`][super::recording].
+   pub fn start_recording(&mut self, path: &Path) -> netcanv::Result<()> {
+      let mut recorder = Recorder::create(path)?;
+      // If we're already in a room, the recording still has to start with the session.
+      if let (Some(room_id), Some(peer_id)) = (self.room_id, self.peer_id) {
+         recorder.write(&Entry::Session {
+            relay_address: self.relay_address.clone(),
+            room_id,
+            peer_id,
+            host_id: self.host,
+         })?;
       }
+      *self.recorder.get_mut() = Some(recorder);
+      Ok(())
+   }
+
+   /// Writes an entry to the recording, if the session is being recorded. The entry is created
+   /// from the time since the recording was started.
+   fn record(&self, entry: impl FnOnce(u64) -> Entry) -> netcanv::Result<()> {
+      if let Some(recorder) = self.recorder.borrow_mut().as_mut() {
+         let time = recorder.time();
+         recorder.write(&entry(time))?;
+      }
+      Ok(())
    }
 
    /// Sends a relay packet to the currently connected relay, or fails if there's no
    /// relay connection.
    fn send_to_relay(&self, packet: relay::Packet) -> netcanv::Result<()> {
       match &self.state {
-         State::ConnectedToRelay | State::InRoom => {
+         State::ConnectedToRelay | State::InRoom | State::LeavingRoom => {
+            let size = bincode::serialized_size(&packet).unwrap_or(0);
+            self.stats.borrow_mut().record_sent_bytes(size as usize);
             self.relay_socket.as_ref().unwrap().send(packet);
          }
+         State::Offline | State::Replay(_) => (),
          _ => return Err(Error::NotConnectedToRelay),
       }
       Ok(())
@@ -138,10 +509,31 @@ impl Peer {
 
    /// Sends a client packet to the peer with the given address.
    fn send_to_client(&self, to: PeerId, packet: cl::Packet) -> netcanv::Result<()> {
+      let kind = Self::traffic_kind(&packet);
+      self.send_to_client_as(to, packet, kind)
+   }
+
+   /// Sends a client packet to the peer with the given address, counting it in the statistics as
+   /// the given kind of traffic.
+   fn send_to_client_as(
+      &self,
+      to: PeerId,
+      packet: cl::Packet,
+      kind: TrafficKind,
+   ) -> netcanv::Result<()> {
       match &self.state {
+         State::InRoom if self.packet_loss.should_drop() => (),
          State::InRoom => {
-            self.send_to_relay(relay::Packet::Relay(to, serialize_bincode(&packet)?))?;
+            self.stats.borrow_mut().record_packet(kind);
+            let payload = serialize_bincode(&packet)?;
+            self.record(|time| Entry::Outgoing {
+               time,
+               to,
+               payload: payload.clone(),
+            })?;
+            self.send_to_relay(relay::Packet::Relay(to, payload))?;
          }
+         State::Offline | State::Replay(_) => (),
          _ => return Err(Error::NotConnectedToHost),
       }
       Ok(())
@@ -157,6 +549,14 @@ impl Peer {
 
    /// Checks the message bus for any established connections.
    fn poll_for_new_connections(&mut self) -> netcanv::Result<()> {
+      if let State::MappingPort { port_mapping, .. } = &mut self.state {
+         match port_mapping.try_recv() {
+            Ok(port_mapping) => self.port_mapped(port_mapping),
+            Err(oneshot::error::TryRecvError::Empty) => (),
+            // The task only goes away without answering if the runtime is shutting down.
+            Err(oneshot::error::TryRecvError::Closed) => self.port_mapped(None),
+         }
+      }
       if let State::WaitingForRelay(socket) = &mut self.state {
          if let Ok(socket) = socket.try_recv() {
             let socket = catch!(socket, as Fatal, return Ok(()));
@@ -166,6 +566,28 @@ impl Peer {
       Ok(())
    }
 
+   /// Handles the state transition from asking the router for a port to connecting to the
+   /// matchmaking relay. Without a port, we fall back to hosting the room on that relay.
+   fn port_mapped(&mut self, port_mapping: Option<PortMapping>) {
+      if let State::MappingPort { matchmaker, .. } =
+         std::mem::replace(&mut self.state, State::Offline)
+      {
+         self.state = State::WaitingForRelay(matchmaker);
+      }
+      match port_mapping {
+         Some(port_mapping) => {
+            self.direct = Some(DirectHosting {
+               port_mapping,
+               matchmaker: None,
+            })
+         }
+         None => {
+            tracing::warn!("the router did not open a port, so the room is hosted on the relay");
+            self.lan_relay = None;
+         }
+      }
+   }
+
    /// Handles the state transition from connecting to the relay to being connected to the
    /// relay.
    ///
@@ -175,64 +597,161 @@ impl Peer {
       self.state = State::ConnectedToRelay;
       tracing::info!("connected to relay");
       self.relay_socket = Some(socket);
+      self.request_room()
+   }
+
+   /// Asks the relay to create a room if we're the host, or to let us into the room otherwise.
+   /// Before the room is created, the host's room ID is the one it asked for, if any.
+   fn request_room(&self) -> netcanv::Result<()> {
       self.send_to_relay(if self.is_host {
-         relay::Packet::Host
+         match (&self.host_token, self.room_id) {
+            (Some(token), room_id) => relay::Packet::HostWithToken {
+               token: token.clone(),
+               room_id,
+            },
+            (None, Some(room_id)) => relay::Packet::HostCustom(room_id),
+            (None, None) => relay::Packet::Host,
+         }
+      } else if let Some(invite) = self.invite {
+         relay::Packet::JoinWithInvite(invite)
       } else {
          relay::Packet::Join(self.room_id.unwrap())
-      })?;
-      Ok(())
+      })
    }
 
    /// Polls for any incoming packets.
    fn poll_for_incoming_packets(&mut self) -> netcanv::Result<()> {
       match &self.state {
-         State::WaitingForRelay(_) => (),
-         State::ConnectedToRelay | State::InRoom => {
-            while let Some(packet) = self.relay_socket.as_mut().unwrap().recv() {
+         State::MappingPort { .. } | State::WaitingForRelay(_) | State::Offline => (),
+         State::Replay(_) => self.replay_entries()?,
+         State::ConnectedToRelay | State::InRoom | State::LeavingRoom => {
+            // The socket goes away when we're sent over to another relay.
+            while let Some(packet) = self.relay_socket.as_mut().and_then(|socket| socket.recv()) {
+               let size = bincode::serialized_size(&packet).unwrap_or(0);
+               self.stats.get_mut().record_received_bytes(size as usize);
                self.relay_packet(packet)?;
             }
          }
       }
+      if let Some(matchmaker) = self.direct.as_mut().and_then(|direct| direct.matchmaker.as_mut()) {
+         // Nothing but errors is expected from the matchmaking relay once the room is reserved.
+         while let Some(packet) = matchmaker.recv() {
+            if let relay::Packet::Error(error) = packet {
+               tracing::warn!("matchmaking relay error: {:?}", error);
+            }
+         }
+      }
       Ok(())
    }
 
    /// Handles a relay packet.
    fn relay_packet(&mut self, packet: relay::Packet) -> netcanv::Result<()> {
+      if let State::LeavingRoom = self.state {
+         // Everything sent before the relay let us out of the room is meant for the room.
+         if packet == relay::Packet::Left {
+            self.state = State::ConnectedToRelay;
+         }
+         return Ok(());
+      }
       match packet {
+         relay::Packet::HostChallenge(challenge) => {
+            tracing::info!(
+               "solving room creation challenge of difficulty {}",
+               challenge.difficulty
+            );
+            self.send_to_relay(relay::Packet::HostWithProof(challenge.solve()))?;
+         }
+         relay::Packet::RoomCreated(room_id, _) if self.is_reserving_room() => {
+            self.move_to_own_relay(room_id)?;
+         }
          relay::Packet::RoomCreated(room_id, peer_id) => {
             tracing::info!("got free room ID: {:?}", room_id);
             self.room_id = Some(room_id);
             self.peer_id = Some(peer_id);
             self.state = State::InRoom;
+            self.record_session()?;
+            let listing = self.listing();
+            // Directly hosted rooms are listed on the matchmaking relay instead.
+            if self.public && self.direct.is_none() {
+               self.send_to_relay(relay::Packet::SetPublic(Some(listing.clone())))?;
+            }
+            if let Some(lan_relay) = &mut self.lan_relay {
+               lan_relay.advertise(room_id, &listing.name)?;
+            }
             bus::push(Connected { peer: self.token });
          }
-         relay::Packet::Joined { peer_id, host_id } => {
+         relay::Packet::Joined {
+            peer_id,
+            host_id,
+            room_id,
+         } => {
             tracing::info!("got host ID: {:?}", host_id);
+            self.room_id = Some(room_id);
             self.peer_id = Some(peer_id);
             self.host = Some(host_id);
             self.state = State::InRoom;
+            self.record_session()?;
             bus::push(Connected { peer: self.token });
             self.say_hello()?;
          }
+         relay::Packet::Redirect(address) if !self.is_host => {
+            tracing::info!("room is hosted directly at {}", address);
+            if let Some(socket_system) = &self.socket_system {
+               self.state = State::WaitingForRelay(socket_system.connect(address.clone()));
+               self.relay_socket = None;
+               self.relay_address = address;
+            }
+         }
          relay::Packet::HostTransfer(host_id) => {
+            self.record(|time| Entry::HostTransfer { time, host_id })?;
             if self.peer_id == Some(host_id) {
-               self.send_message(MessageKind::NowHosting);
+               let previous_host = self.host.filter(|peer_id| self.mates.contains_key(peer_id));
+               self.send_message(MessageKind::NowHosting { previous_host });
                self.host = None;
                self.is_host = true;
             } else {
+               // We may have been the host, and handed the role over to someone else.
+               self.is_host = false;
                if let Some(mate) = self.mates.get(&host_id) {
                   self.send_message(MessageKind::NewHost(mate.nickname.clone()))
                }
                self.host = Some(host_id);
             }
          }
+         relay::Packet::Relayed(_, _) if self.packet_loss.should_drop() => (),
          relay::Packet::Relayed(author, payload) => {
-            let client_packet: cl::Packet = deserialize_bincode(&payload)?;
+            self.record(|time| Entry::Incoming {
+               time,
+               author,
+               payload: payload.clone(),
+            })?;
+            let client_packet: cl::Packet = decode_packet(&payload)?;
+            // Tool packets are only understood by the tools themselves, so they're counted once
+            // they reach the tool.
+            if !matches!(client_packet, cl::Packet::Tool(..)) {
+               self.stats.get_mut().record_packet(Self::traffic_kind(&client_packet));
+            }
             self.client_packet(author, client_packet)?;
          }
+         // Voice frames are not recorded, since they're not needed to replay the canvas.
+         relay::Packet::VoiceRelayed(author, frame) => {
+            self.send_message(MessageKind::Voice(author, frame));
+         }
          relay::Packet::Disconnected(address) => {
+            self.record(|time| Entry::Disconnected {
+               time,
+               peer_id: address,
+            })?;
             self.remove_mate(address);
          }
+         relay::Packet::InviteCreated(invite) => {
+            self.send_message(MessageKind::InviteCreated(invite));
+         }
+         relay::Packet::SetInviteOnly(invite_only) if self.is_host => {
+            self.send_message(MessageKind::InviteOnly(invite_only));
+         }
+         // The room list is only requested for measuring the relay's round-trip time.
+         relay::Packet::RoomList(_) if self.stats.get_mut().finish_ping() => (),
          relay::Packet::Error(error) => match error {
             relay::Error::NoSuchPeer { address } => {
                // Remove the peer when relay tells us that they are no longer
@@ -255,9 +774,135 @@ impl Peer {
       Ok(())
    }
 
+   /// Returns whether we're hosting directly, and waiting for the matchmaking relay to reserve
+   /// the room for us.
+   fn is_reserving_room(&self) -> bool {
+      matches!(&self.direct, Some(direct) if direct.matchmaker.is_none())
+   }
+
+   /// Sends people joining our room over to our own relay, and moves there ourselves, creating
+   /// the room under the ID the matchmaking relay gave us.
+   fn move_to_own_relay(&mut self, room_id: RoomId) -> netcanv::Result<()> {
+      let (direct, lan_relay, socket_system) =
+         match (&mut self.direct, &self.lan_relay, &self.socket_system) {
+            (Some(direct), Some(lan_relay), Some(socket_system)) => {
+               (direct, lan_relay, socket_system)
+            }
+            _ => return Ok(()),
+         };
+      let matchmaker = self.relay_socket.take().unwrap();
+      matchmaker.send(relay::Packet::SetDirectAddress(Some(
+         direct.port_mapping.address(),
+      )));
+      if self.public {
+         matchmaker.send(relay::Packet::SetPublic(Some(self.listing())));
+      }
+      direct.matchmaker = Some(matchmaker);
+      tracing::info!("room {:?} reserved, moving over to our own relay", room_id);
+
+      self.room_id = Some(room_id);
+      self.relay_address = lan_relay.address();
+      self.state = State::WaitingForRelay(socket_system.connect(self.relay_address.clone()));
+      Ok(())
+   }
+
+   /// Writes the session entry to the recording, once we're in a room.
+   fn record_session(&self) -> netcanv::Result<()> {
+      if let (Some(room_id), Some(peer_id)) = (self.room_id, self.peer_id) {
+         self.record(|_| Entry::Session {
+            relay_address: self.relay_address.clone(),
+            room_id,
+            peer_id,
+            host_id: self.host,
+         })?;
+      }
+      Ok(())
+   }
+
+   /// Replays the recorded entries whose time has come.
+   fn replay_entries(&mut self) -> netcanv::Result<()> {
+      let entries = match &mut self.state {
+         State::Replay(replay) if !replay.is_finished() => {
+            let entries = replay.take_due();
+            if replay.is_finished() {
+               tracing::info!("replay finished");
+            }
+            entries
+         }
+         _ => return Ok(()),
+      };
+      for entry in entries {
+         match entry {
+            Entry::Session {
+               relay_address,
+               room_id,
+               peer_id,
+               host_id,
+            } => {
+               tracing::info!("replaying session in room {:?} as {:?}", room_id, peer_id);
+               self.relay_address = relay_address;
+               self.room_id = Some(room_id);
+               self.peer_id = Some(peer_id);
+               // We're never the host while replaying, so that the host's packets are accepted
+               // when the recording was made by the host.
+               self.host = Some(host_id.unwrap_or(peer_id));
+               bus::push(Connected { peer: self.token });
+            }
+            Entry::Incoming {
+               author, payload, ..
+            } => {
+               let packet: cl::Packet = decode_packet(&payload)?;
+               self.client_packet(author, packet)?;
+            }
+            Entry::Outgoing { payload, .. } => {
+               let packet: cl::Packet = decode_packet(&payload)?;
+               if let Some(peer_id) = self.peer_id.filter(|_| Self::affects_canvas(&packet)) {
+                  self.client_packet(peer_id, packet)?;
+               }
+            }
+            Entry::HostTransfer { host_id, .. } => {
+               if let Some(mate) = self.mates.get(&host_id) {
+                  self.send_message(MessageKind::NewHost(mate.nickname.clone()))
+               }
+               self.host = Some(host_id);
+            }
+            Entry::Disconnected { peer_id, .. } => self.remove_mate(peer_id),
+         }
+      }
+      Ok(())
+   }
+
+   /// Returns whether a packet we sent changes what's on the canvas, as opposed to being part of
+   /// the introduction or chunk exchange, which only matter to the peer receiving it.
+   fn affects_canvas(packet: &cl::Packet) -> bool {
+      !matches!(
+         packet,
+         cl::Packet::Hello(_)
+            | cl::Packet::HiThere(_)
+            | cl::Packet::Reserved1
+            | cl::Packet::Version(_)
+            | cl::Packet::ChunkPositions(..)
+            | cl::Packet::GetChunks(..)
+            | cl::Packet::Chunks(..)
+            | cl::Packet::ChunkDigests(..)
+            | cl::Packet::Admin { .. }
+            | cl::Packet::AdminResponse(_)
+            | cl::Packet::ChunkEncoding(_)
+            | cl::Packet::Capabilities(_)
+            | cl::Packet::Bookmarks(_)
+            | cl::Packet::LatencyProbe(_)
+            | cl::Packet::LatencyReply(_)
+            | cl::Packet::Viewport { .. }
+      )
+   }
+
    /// Says hello to other peers in the room.
    fn say_hello(&self) -> netcanv::Result<()> {
-      self.send_to_client(PeerId::BROADCAST, cl::Packet::Hello(self.nickname.clone()))
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::Hello(self.nickname.clone()))?;
+      self.send_to_client(
+         PeerId::BROADCAST,
+         cl::Packet::Capabilities(cl::Capabilities::CURRENT),
+      )
    }
 
    /// Decodes a client packet.
@@ -270,6 +915,16 @@ impl Peer {
             tracing::info!("{} ({:?}) joined", nickname, author);
             self.send_to_client(author, cl::Packet::HiThere(self.nickname.clone()))?;
             self.send_to_client(author, cl::Packet::Version(cl::PROTOCOL_VERSION))?;
+            self.send_to_client(author, cl::Packet::Capabilities(cl::Capabilities::CURRENT))?;
+            if self.is_host {
+               self.send_to_client(
+                  author,
+                  cl::Packet::RoomInfo {
+                     name: self.room_name.clone(),
+                     description: self.room_description.clone(),
+                  },
+               )?;
+            }
             self.add_mate(author, nickname.clone());
             self.send_message(MessageKind::Joined(nickname, author));
          }
@@ -289,13 +944,15 @@ impl Peer {
             }));
          }
          cl::Packet::Version(_) => (),
-         cl::Packet::ChunkPositions(positions) => {
-            self.send_message(MessageKind::ChunkPositions(positions))
+         cl::Packet::ChunkPositions(layer, positions) => {
+            self.send_message(MessageKind::ChunkPositions(layer as usize, positions))
          }
-         cl::Packet::GetChunks(positions) => {
-            self.send_message(MessageKind::GetChunks(author, positions))
+         cl::Packet::GetChunks(layer, positions) => {
+            self.send_message(MessageKind::GetChunks(author, layer as usize, positions))
+         }
+         cl::Packet::Chunks(layer, chunks) => {
+            self.send_message(MessageKind::Chunks(layer as usize, chunks))
          }
-         cl::Packet::Chunks(chunks) => self.send_message(MessageKind::Chunks(chunks)),
          // -----
          // 0.3.0
          // -----
@@ -313,6 +970,242 @@ impl Peer {
                tool,
             });
          }
+         // -----
+         // 0.5.0
+         // -----
+         cl::Packet::LockedRegions(regions) => {
+            // Only the host is allowed to lock regions, so anyone else trying to do that is
+            // ignored.
+            if self.host == Some(author) {
+               self.send_message(MessageKind::LockedRegions(regions));
+            } else {
+               tracing::warn!("{:?} tried to lock regions but is not the host", author);
+            }
+         }
+         cl::Packet::Paused(paused) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::Paused(paused));
+            } else {
+               tracing::warn!("{:?} tried to pause the room but is not the host", author);
+            }
+         }
+         cl::Packet::RoomInfo { name, description } => {
+            if self.host == Some(author) {
+               // Don't trust the host to stick to the limits.
+               self.room_name = truncate_graphemes(&name, Self::MAX_ROOM_NAME_LEN).to_owned();
+               self.room_description =
+                  truncate_graphemes(&description, Self::MAX_ROOM_DESCRIPTION_LEN).to_owned();
+               self.send_message(MessageKind::RoomInfo {
+                  name: self.room_name.clone(),
+                  description: self.room_description.clone(),
+               });
+            } else {
+               tracing::warn!("{:?} tried to set room info but is not the host", author);
+            }
+         }
+         cl::Packet::RoomPalette(mut colors) => {
+            if self.host == Some(author) {
+               colors.truncate(Self::MAX_ROOM_PALETTE_LEN);
+               self.send_message(MessageKind::RoomPalette(colors));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to set the room palette but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::CommitOperation => {
+            // Only the host keeps the operation log, so everyone else can ignore this.
+            if self.is_host {
+               self.send_message(MessageKind::CommitOperation(author));
+            }
+         }
+         cl::Packet::Undo => {
+            if self.is_host {
+               self.send_message(MessageKind::Undo(author));
+            } else {
+               tracing::warn!("{:?} asked us to undo but we're not the host", author);
+            }
+         }
+         cl::Packet::Ping(position) => self.send_message(MessageKind::Ping(author, position)),
+         cl::Packet::SetNote(id, note) => self.send_message(MessageKind::SetNote(id, note)),
+         cl::Packet::RemoveNote(id) => self.send_message(MessageKind::RemoveNote(id)),
+         cl::Packet::Notes(notes) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::Notes(notes));
+            } else {
+               tracing::warn!("{:?} tried to send all notes but is not the host", author);
+            }
+         }
+         cl::Packet::AddImage(id, placement, data) => {
+            self.send_message(MessageKind::AddImage(id, placement, data))
+         }
+         cl::Packet::PlaceImage(id, placement) => {
+            self.send_message(MessageKind::PlaceImage(id, placement))
+         }
+         cl::Packet::RemoveImage(id) => self.send_message(MessageKind::RemoveImage(id)),
+         cl::Packet::FlattenImage(id) => self.send_message(MessageKind::FlattenImage(author, id)),
+         cl::Packet::VectorMode(enabled) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::VectorMode(enabled));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to change the vector mode but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::VectorStrokes(strokes) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::VectorStrokes(strokes));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to send vector strokes but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::RemoveVectorStrokes(ids) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::RemoveVectorStrokes(ids));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to remove vector strokes but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::Pages(names) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::Pages(names));
+            } else {
+               tracing::warn!("{:?} tried to set the pages but is not the host", author);
+            }
+         }
+         cl::Packet::SelectPage(page) => {
+            let page = page as usize;
+            if let Some(mate) = self.mates.get_mut(&author) {
+               mate.page = page;
+            }
+            self.send_message(MessageKind::SelectPage {
+               peer_id: author,
+               page,
+            });
+         }
+         cl::Packet::Background(background) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::Background(background));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to set the background but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::CanvasBounds(bounds) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::CanvasBounds(bounds));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to change the canvas bounds but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::ChunkDigests(layer, digests) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::ChunkDigests(layer as usize, digests));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to send chunk digests but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::Admin { password, command } => {
+            if !self.is_host {
+               tracing::warn!("{:?} sent an admin command but we're not the host", author);
+            } else if self.admin_password.as_ref() == Some(&password) {
+               tracing::info!("{:?} is running admin command {:?}", author, command);
+               self.send_message(MessageKind::AdminCommand(author, command));
+            } else {
+               tracing::warn!("{:?} sent an admin command with a wrong password", author);
+               self.send_admin_response(author, cl::AdminResponse::Denied)?;
+            }
+         }
+         cl::Packet::AdminResponse(response) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::AdminResponse(response));
+            } else {
+               tracing::warn!("{:?} sent an admin response but is not the host", author);
+            }
+         }
+         cl::Packet::ChunkEncoding(encoding) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::ChunkEncoding(encoding));
+            } else {
+               tracing::warn!(
+                  "{:?} tried to change the chunk encoding but is not the host",
+                  author
+               );
+            }
+         }
+         cl::Packet::Redo => {
+            if self.is_host {
+               self.send_message(MessageKind::Redo(author));
+            } else {
+               tracing::warn!("{:?} asked us to redo but we're not the host", author);
+            }
+         }
+         cl::Packet::Layers(layers) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::Layers(layers));
+            } else {
+               tracing::warn!("{:?} tried to set the layers but is not the host", author);
+            }
+         }
+         cl::Packet::SelectLayer(layer) => {
+            let layer = layer as usize;
+            if let Some(mate) = self.mates.get_mut(&author) {
+               mate.layer = layer;
+            }
+            self.send_message(MessageKind::SelectLayer {
+               peer_id: author,
+               layer,
+            });
+         }
+         cl::Packet::Capabilities(capabilities) => {
+            if let Some(mate) = self.mates.get_mut(&author) {
+               mate.capabilities = capabilities;
+            }
+         }
+         cl::Packet::Bookmarks(bookmarks) => {
+            if self.host == Some(author) {
+               self.send_message(MessageKind::Bookmarks(bookmarks));
+            } else {
+               tracing::warn!("{:?} tried to share bookmarks but is not the host", author);
+            }
+         }
+         cl::Packet::LatencyProbe(id) => {
+            self.send_to_client(author, cl::Packet::LatencyReply(id))?;
+         }
+         cl::Packet::LatencyReply(id) => {
+            if let Some(latency) = self.stats.get_mut().finish_probe(id) {
+               if let Some(mate) = self.mates.get_mut(&author) {
+                  mate.latency = Some(latency);
+               }
+            }
+         }
+         cl::Packet::Viewport { pan, zoom_level } => {
+            if let Some(mate) = self.mates.get_mut(&author) {
+               mate.viewport = Some((pan, zoom_level));
+            }
+            self.send_message(MessageKind::Viewport {
+               peer_id: author,
+               pan,
+               zoom_level,
+            });
+         }
       }
 
       Ok(())
@@ -322,6 +1215,40 @@ impl Peer {
    pub fn communicate(&mut self) -> netcanv::Result<()> {
       self.poll_for_new_connections()?;
       self.poll_for_incoming_packets()?;
+      self.sample_stats()?;
+      Ok(())
+   }
+
+   /// Classifies a client packet for the statistics. Tool packets can't be told apart without
+   /// knowing the tool, so they're counted as other traffic.
+   fn traffic_kind(packet: &cl::Packet) -> TrafficKind {
+      match packet {
+         cl::Packet::ChunkPositions(..)
+         | cl::Packet::GetChunks(..)
+         | cl::Packet::Chunks(..)
+         | cl::Packet::ChunkDigests(..) => TrafficKind::Chunk,
+         _ => TrafficKind::Other,
+      }
+   }
+
+   /// Advances the traffic statistics, and measures the round-trip times to the relay and to
+   /// everyone in the room.
+   ///
+   /// The relay doesn't have a dedicated ping packet, so the list of public rooms is requested
+   /// instead, like in [`RelayPing`][super::relay_ping::RelayPing].
+   fn sample_stats(&mut self) -> netcanv::Result<()> {
+      self.stats.get_mut().tick();
+      if !matches!(self.state, State::InRoom) {
+         return Ok(());
+      }
+      if self.stats.get_mut().start_ping() {
+         self.send_to_relay(relay::Packet::ListRooms)?;
+      }
+      if !self.mates.is_empty() {
+         if let Some(id) = self.stats.get_mut().start_probe() {
+            self.send_to_client(PeerId::BROADCAST, cl::Packet::LatencyProbe(id))?;
+         }
+      }
       Ok(())
    }
 
@@ -332,8 +1259,14 @@ impl Peer {
          Mate {
             nickname,
             tool: None,
+            page: 0,
+            layer: 0,
+            capabilities: cl::Capabilities::default(),
+            latency: None,
+            viewport: None,
          },
       );
+      telemetry::peer_count(self.mates.len() + 1);
    }
 
    /// Removes a peer from the list of registered peers
@@ -348,35 +1281,86 @@ impl Peer {
       }
    }
 
-   /// Sends a chunk positions packet.
+   /// Makes the peer drop the given fraction (0 to 1) of client packets, both incoming and
+   /// outgoing.
+   pub fn set_simulated_packet_loss(&mut self, fraction: f32) {
+      self.packet_loss.fraction = fraction;
+   }
+
+   /// Sends a chunk positions packet for the given layer.
    pub fn send_chunk_positions(
       &self,
       to: PeerId,
+      layer: usize,
       positions: Vec<(i32, i32)>,
    ) -> netcanv::Result<()> {
-      self.send_to_client(to, cl::Packet::ChunkPositions(positions))
+      self.send_to_client(to, cl::Packet::ChunkPositions(layer as u32, positions))
    }
 
-   /// Requests chunk data from the host.
-   pub fn download_chunks(&self, positions: Vec<(i32, i32)>) -> netcanv::Result<()> {
-      assert!(self.host.is_some(), "only non-hosts can download chunks");
-      tracing::info!("downloading {} chunks from the host", positions.len());
-      // The host should be available at this point, as the connection has been established.
-      self.send_to_client(self.host.unwrap(), cl::Packet::GetChunks(positions))
+   /// Returns an error if we're not the host of the room.
+   ///
+   /// The host role can move to someone else between the user asking for something only the host
+   /// can do and the request being sent, so this is an error rather than an assertion.
+   fn check_host(&self) -> netcanv::Result<()> {
+      if self.is_host {
+         Ok(())
+      } else {
+         Err(Error::NotHost)
+      }
    }
 
-   /// Sends chunks to the given peer.
+   /// Requests chunk data on the given layer from the host.
+   pub fn download_chunks(&self, layer: usize, positions: Vec<(i32, i32)>) -> netcanv::Result<()> {
+      let host = self.host.ok_or(Error::NotConnectedToHost)?;
+      tracing::info!(
+         "downloading {} chunks on layer {} from the host",
+         positions.len(),
+         layer
+      );
+      self.send_to_client(host, cl::Packet::GetChunks(layer as u32, positions))
+   }
+
+   /// Requests chunk data from the given peer rather than the host. Used to fetch the rest of
+   /// the canvas from the previous host after they hand the role over to us.
+   pub fn download_chunks_from(
+      &self,
+      from: PeerId,
+      layer: usize,
+      positions: Vec<(i32, i32)>,
+   ) -> netcanv::Result<()> {
+      tracing::info!(
+         "downloading {} chunks on layer {} from {:?}",
+         positions.len(),
+         layer,
+         from
+      );
+      self.send_to_client(from, cl::Packet::GetChunks(layer as u32, positions))
+   }
+
+   /// Sends chunks on the given layer to the given peer.
    pub fn send_chunks(
       &self,
       to: PeerId,
+      layer: usize,
       chunks: Vec<((i32, i32), Vec<u8>)>,
    ) -> netcanv::Result<()> {
-      self.send_to_client(to, cl::Packet::Chunks(chunks))
+      self.send_to_client(to, cl::Packet::Chunks(layer as u32, chunks))
+   }
+
+   /// Sends a tool-specific packet, counted in the statistics as the given kind of traffic.
+   pub fn send_tool(
+      &self,
+      peer_id: PeerId,
+      name: String,
+      payload: Vec<u8>,
+      kind: TrafficKind,
+   ) -> netcanv::Result<()> {
+      self.send_to_client_as(peer_id, cl::Packet::Tool(name, payload), kind)
    }
 
-   /// Sends a tool-specific packet.
-   pub fn send_tool(&self, peer_id: PeerId, name: String, payload: Vec<u8>) -> netcanv::Result<()> {
-      self.send_to_client(peer_id, cl::Packet::Tool(name, payload))
+   /// Counts a received tool packet in the statistics.
+   pub fn record_received_tool_packet(&self, kind: TrafficKind) {
+      self.stats.borrow_mut().record_packet(kind);
    }
 
    /// Sends a tool selection packet.
@@ -384,16 +1368,364 @@ impl Peer {
       self.send_to_client(PeerId::BROADCAST, cl::Packet::SelectTool(name))
    }
 
+   /// Sends the list of locked regions to the given peer.
+   pub fn send_locked_regions(
+      &self,
+      to: PeerId,
+      regions: Vec<((i32, i32), (i32, i32))>,
+   ) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::LockedRegions(regions))
+   }
+
+   /// Tells the given peer whether modifications to the canvas are paused.
+   pub fn send_paused(&self, to: PeerId, paused: bool) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::Paused(paused))
+   }
+
+   /// Sends the room's color palette to the given peer.
+   pub fn send_room_palette(&self, to: PeerId, colors: Vec<(u8, u8, u8)>) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::RoomPalette(colors))
+   }
+
+   /// Sends our viewport bookmarks to the given peer.
+   pub fn send_bookmarks(&self, to: PeerId, bookmarks: Vec<cl::Bookmark>) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::Bookmarks(bookmarks))
+   }
+
+   /// Lets the host know that we finished an operation.
+   pub fn send_commit_operation(&self) -> netcanv::Result<()> {
+      match self.host {
+         Some(host) => self.send_to_client(host, cl::Packet::CommitOperation),
+         None => Ok(()),
+      }
+   }
+
+   /// Asks the host to undo our most recent operation.
+   pub fn send_undo(&self) -> netcanv::Result<()> {
+      match self.host {
+         Some(host) => self.send_to_client(host, cl::Packet::Undo),
+         None => Ok(()),
+      }
+   }
+
+   /// Asks the host to redo our most recently undone operation.
+   pub fn send_redo(&self) -> netcanv::Result<()> {
+      match self.host {
+         Some(host) => self.send_to_client(host, cl::Packet::Redo),
+         None => Ok(()),
+      }
+   }
+
+   /// Pings a location on the canvas for everyone in the room.
+   pub fn send_ping(&self, position: (i32, i32)) -> netcanv::Result<()> {
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::Ping(position))
+   }
+
+   /// Lets everyone know where our viewport is.
+   pub fn send_viewport(&self, pan: (i32, i32), zoom_level: i32) -> netcanv::Result<()> {
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::Viewport { pan, zoom_level })
+   }
+
+   /// Lets everyone know that a sticky note was created or changed.
+   pub fn send_note(&self, id: cl::NoteId, note: cl::StickyNote) -> netcanv::Result<()> {
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::SetNote(id, note))
+   }
+
+   /// Lets everyone know that a sticky note was removed.
+   pub fn send_remove_note(&self, id: cl::NoteId) -> netcanv::Result<()> {
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::RemoveNote(id))
+   }
+
+   /// Sends all the sticky notes on the canvas to the given peer.
+   pub fn send_notes(
+      &self,
+      to: PeerId,
+      notes: Vec<(cl::NoteId, cl::StickyNote)>,
+   ) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::Notes(notes))
+   }
+
+   /// Sends a floating image to the given peer, or to everyone.
+   pub fn send_add_image(
+      &self,
+      to: PeerId,
+      id: cl::FloatingImageId,
+      placement: cl::ImagePlacement,
+      data: Vec<u8>,
+   ) -> netcanv::Result<()> {
+      self.send_to_client(to, cl::Packet::AddImage(id, placement, data))
+   }
+
+   /// Lets everyone know that a floating image was moved or resized.
+   pub fn send_place_image(
+      &self,
+      id: cl::FloatingImageId,
+      placement: cl::ImagePlacement,
+   ) -> netcanv::Result<()> {
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::PlaceImage(id, placement))
+   }
+
+   /// Lets everyone know that a floating image was removed.
+   pub fn send_remove_image(&self, id: cl::FloatingImageId) -> netcanv::Result<()> {
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::RemoveImage(id))
+   }
+
+   /// Asks everyone to flatten a floating image onto their canvas.
+   pub fn send_flatten_image(&self, id: cl::FloatingImageId) -> netcanv::Result<()> {
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::FlattenImage(id))
+   }
+
+   /// Turns vector strokes on or off for the given peer, or the whole room.
+   pub fn send_vector_mode(&self, to: PeerId, enabled: bool) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::VectorMode(enabled))
+   }
+
+   /// Sends all the vector strokes drawn so far to the given peer.
+   pub fn send_vector_strokes(
+      &self,
+      to: PeerId,
+      strokes: Vec<(cl::VectorStrokeId, cl::VectorStroke)>,
+   ) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::VectorStrokes(strokes))
+   }
+
+   /// Asks everyone to remove the given vector strokes.
+   pub fn send_remove_vector_strokes(&self, ids: Vec<cl::VectorStrokeId>) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(PeerId::BROADCAST, cl::Packet::RemoveVectorStrokes(ids))
+   }
+
+   /// Sends the canvas's bounds to the given peer, or the whole room.
+   pub fn send_canvas_bounds(&self, to: PeerId, bounds: Option<(u32, u32)>) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::CanvasBounds(bounds))
+   }
+
+   /// Sends the canvas's background to the given peer, or the whole room.
+   pub fn send_background(&self, to: PeerId, background: cl::Background) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::Background(background))
+   }
+
+   /// Sends the room's chunk encoding settings to the given peer, or the whole room.
+   pub fn send_chunk_encoding(
+      &self,
+      to: PeerId,
+      encoding: cl::ChunkEncoding,
+   ) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::ChunkEncoding(encoding))
+   }
+
+   /// Sends the names of the room's pages to the given peer, or the whole room.
+   pub fn send_pages(&self, to: PeerId, names: Vec<String>) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::Pages(names))
+   }
+
+   /// Sends digests of the chunks on a layer changed by an operation to the given peer, or the
+   /// whole room.
+   pub fn send_chunk_digests(
+      &self,
+      to: PeerId,
+      layer: usize,
+      digests: Vec<((i32, i32), Vec<u8>)>,
+   ) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::ChunkDigests(layer as u32, digests))
+   }
+
+   /// Asks the host to run an admin command. The host refuses to run it unless the password
+   /// matches the one it was started with.
+   pub fn send_admin_command(
+      &self,
+      password: String,
+      command: cl::AdminCommand,
+   ) -> netcanv::Result<()> {
+      let host = self.host.ok_or(Error::NotConnectedToHost)?;
+      self.send_to_client(host, cl::Packet::Admin { password, command })
+   }
+
+   /// Responds to an admin command sent by the given peer.
+   pub fn send_admin_response(
+      &self,
+      to: PeerId,
+      response: cl::AdminResponse,
+   ) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::AdminResponse(response))
+   }
+
+   /// Sends the room's layers to the given peer, or the whole room.
+   pub fn send_layers(&self, to: PeerId, layers: Vec<cl::Layer>) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_client(to, cl::Packet::Layers(layers))
+   }
+
+   /// Lets the given peer, or the whole room, know which layer we're drawing on.
+   pub fn send_select_layer(&self, to: PeerId, layer: usize) -> netcanv::Result<()> {
+      self.send_to_client(to, cl::Packet::SelectLayer(layer as u32))
+   }
+
+   /// Lets the given peer, or the whole room, know which page we're on.
+   pub fn send_select_page(&self, to: PeerId, page: usize) -> netcanv::Result<()> {
+      self.send_to_client(to, cl::Packet::SelectPage(page as u32))
+   }
+
+   /// Sends an encoded voice frame to everyone else in the room.
+   pub fn send_voice(&self, frame: Vec<u8>) -> netcanv::Result<()> {
+      match &self.state {
+         State::InRoom => self.send_to_relay(relay::Packet::Voice(frame)),
+         _ => Ok(()),
+      }
+   }
+
+   /// Asks the relay to hand the host role over to the peer with the given ID.
+   pub fn transfer_host(&self, to: PeerId) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_relay(relay::Packet::TransferHost(to))
+   }
+
+   /// Asks the relay to create an invite token for our room. The token is delivered through
+   /// [`MessageKind::InviteCreated`].
+   pub fn create_invite(
+      &self,
+      single_use: bool,
+      lifetime: Option<Duration>,
+   ) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_relay(relay::Packet::CreateInvite {
+         single_use,
+         lifetime_secs: lifetime.map(|lifetime| lifetime.as_secs()),
+      })
+   }
+
+   /// Asks the relay to revoke all invite tokens created for our room.
+   pub fn revoke_invites(&self) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_relay(relay::Packet::RevokeInvites)
+   }
+
+   /// Sets whether the room can only be joined with an invite token.
+   pub fn set_invite_only(&self, invite_only: bool) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.send_to_relay(relay::Packet::SetInviteOnly(invite_only))
+   }
+
+   /// Leaves the room, while staying connected to the relay, so that another room can be hosted
+   /// or joined without reconnecting.
+   ///
+   /// Everything we knew about the room is forgotten, and the peer gets a new token, so that
+   /// messages from the room still waiting on the bus are not mistaken for ones from the next
+   /// room.
+   pub fn leave_room(&mut self) -> netcanv::Result<()> {
+      if let State::ConnectedToRelay | State::InRoom = self.state {
+         self.send_to_relay(relay::Packet::Leave)?;
+         self.state = State::LeavingRoom;
+      }
+      self.token = PeerToken(PEER_TOKEN.next());
+      self.is_host = false;
+      self.room_name.clear();
+      self.room_description.clear();
+      self.public = false;
+      self.room_id = None;
+      self.invite = None;
+      self.peer_id = None;
+      self.host = None;
+      self.mates.clear();
+      self.admin_password = None;
+      *self.stats.get_mut() = NetworkStats::new();
+      // A recording only covers a single session.
+      *self.recorder.get_mut() = None;
+      Ok(())
+   }
+
+   /// Hosts a new room over the connection kept after [leaving][Self::leave_room] the previous
+   /// one, optionally under the given room ID.
+   pub fn host_another_room(
+      &mut self,
+      nickname: &str,
+      room_id: Option<RoomId>,
+   ) -> netcanv::Result<()> {
+      nickname.clone_into(&mut self.nickname);
+      self.is_host = true;
+      self.room_id = room_id;
+      self.request_room()
+   }
+
+   /// Joins an existing room over the connection kept after [leaving][Self::leave_room] the
+   /// previous one.
+   pub fn join_another_room(&mut self, nickname: &str, room_id: RoomId) -> netcanv::Result<()> {
+      nickname.clone_into(&mut self.nickname);
+      self.room_id = Some(room_id);
+      self.request_room()
+   }
+
+   /// Joins an existing room using an invite token, over the connection kept after
+   /// [leaving][Self::leave_room] the previous one.
+   pub fn join_another_room_with_invite(
+      &mut self,
+      nickname: &str,
+      invite: InviteToken,
+   ) -> netcanv::Result<()> {
+      nickname.clone_into(&mut self.nickname);
+      self.invite = Some(invite);
+      self.request_room()
+   }
+
    /// Returns the peer's unique token.
    pub fn token(&self) -> PeerToken {
       self.token
    }
 
+   /// Returns the stage the connection is at, or `None` if we're already in the room.
+   pub fn connection_stage(&self) -> Option<ConnectionStage> {
+      match &self.state {
+         State::MappingPort { .. } => Some(ConnectionStage::MappingPort),
+         State::WaitingForRelay(socket) => Some(socket.stage()),
+         // The relay still has to create the room or let us into it.
+         State::ConnectedToRelay | State::LeavingRoom => Some(ConnectionStage::WaitingForRelay),
+         State::InRoom | State::Offline | State::Replay(_) => None,
+      }
+   }
+
+   /// Returns whether the peer is offline, and not connected to any relay.
+   pub fn is_offline(&self) -> bool {
+      matches!(self.state, State::Offline)
+   }
+
+   /// Returns whether this peer hosts its room on the local network, through its own relay.
+   pub fn is_hosting_on_lan(&self) -> bool {
+      self.lan_relay.is_some()
+   }
+
    /// Returns whether this peer is the host.
    pub fn is_host(&self) -> bool {
       self.is_host
    }
 
+   /// Returns our own peer ID, or `None` if a connection hasn't been established.
+   pub fn peer_id(&self) -> Option<PeerId> {
+      self.peer_id
+   }
+
+   /// Returns our own nickname.
+   pub fn nickname(&self) -> &str {
+      &self.nickname
+   }
+
+   /// Returns the peer ID of the host, or `None` if this peer is the host.
+   pub fn host_id(&self) -> Option<PeerId> {
+      self.host
+   }
+
    /// Returns the name of the host, or `None` if this peer is the host (or if the host's name isn't
    /// yet known).
    pub fn host_name(&self) -> Option<&str> {
@@ -406,6 +1738,83 @@ impl Peer {
       }
    }
 
+   /// Sets the name and description of the room, which are sent to everyone who joins. Only
+   /// meaningful for the host.
+   pub fn set_room_info(&mut self, name: &str, description: &str) {
+      name.clone_into(&mut self.room_name);
+      description.clone_into(&mut self.room_description);
+   }
+
+   /// Changes the name and description of the room we're hosting, and lets everyone in the room
+   /// know. If the room is listed publicly, its listing is updated too.
+   pub fn update_room_info(&mut self, name: &str, description: &str) -> netcanv::Result<()> {
+      self.check_host()?;
+      self.set_room_info(name, description);
+      self.send_to_client(
+         PeerId::BROADCAST,
+         cl::Packet::RoomInfo {
+            name: self.room_name.clone(),
+            description: self.room_description.clone(),
+         },
+      )?;
+      if self.public {
+         let packet = relay::Packet::SetPublic(Some(self.listing()));
+         match self.direct.as_ref().and_then(|direct| direct.matchmaker.as_ref()) {
+            Some(matchmaker) => matchmaker.send(packet),
+            None => self.send_to_relay(packet)?,
+         }
+      }
+      Ok(())
+   }
+
+   /// Returns how the room is listed publicly. Unnamed rooms are listed under the host's
+   /// nickname.
+   fn listing(&self) -> RoomListing {
+      let name = if self.room_name.is_empty() {
+         self.nickname.clone()
+      } else {
+         self.room_name.clone()
+      };
+      RoomListing {
+         name,
+         description: self.room_description.clone(),
+      }
+   }
+
+   /// Sets whether the room should be listed publicly on the relay once it's created. Public rooms
+   /// are listed under their name, or the host's nickname if the room is unnamed. Only meaningful
+   /// for the host.
+   pub fn set_public(&mut self, public: bool) {
+      self.public = public;
+   }
+
+   /// Sets the password admins need to send to run admin commands. Without a password, admin
+   /// commands are always refused. Only meaningful for the host.
+   pub fn set_admin_password(&mut self, password: Option<String>) {
+      self.admin_password = password;
+   }
+
+   /// Sets the token sent to the relay when asking it to create a room. Relays that limit who can
+   /// create rooms let peers with the right token skip the proof-of-work challenge.
+   pub fn set_host_token(&mut self, token: Option<String>) {
+      self.host_token = token;
+   }
+
+   /// Returns the name of the room. This is empty if the room is unnamed.
+   pub fn room_name(&self) -> &str {
+      &self.room_name
+   }
+
+   /// Returns the description of the room. This is empty if the room has no description.
+   pub fn room_description(&self) -> &str {
+      &self.room_description
+   }
+
+   /// Returns the address of the relay the peer is connected to.
+   pub fn relay_address(&self) -> &str {
+      &self.relay_address
+   }
+
    /// Returns the ID of the room, or `None` if a connection hasn't been established.
    pub fn room_id(&self) -> Option<RoomId> {
       self.room_id
@@ -415,4 +1824,21 @@ impl Peer {
    pub fn mates(&self) -> &HashMap<PeerId, Mate> {
       &self.mates
    }
+
+   /// Returns the capabilities of the given peer. For [`PeerId::BROADCAST`], returns the
+   /// capabilities shared by everyone in the room.
+   pub fn capabilities_of(&self, peer_id: PeerId) -> cl::Capabilities {
+      if peer_id == PeerId::BROADCAST {
+         self.mates.values().fold(cl::Capabilities::CURRENT, |shared, mate| {
+            shared.intersection(mate.capabilities)
+         })
+      } else {
+         self.mates.get(&peer_id).map(|mate| mate.capabilities).unwrap_or_default()
+      }
+   }
+
+   /// Returns the traffic statistics of the connection.
+   pub fn stats(&self) -> Ref<'_, NetworkStats> {
+      self.stats.borrow()
+   }
 }
@@ -0,0 +1,200 @@
+//! Port mapping on the router, so that rooms hosted on our own relay can be reached from the
+//! Internet.
+//!
+//! UPnP is tried first, as most home routers support it. NAT-PMP, spoken by Apple's routers and
+//! a few others, is tried if there's no UPnP gateway on the network.
+
+use std::net::{IpAddr, SocketAddr, UdpSocket};
+use std::sync::Arc;
+use std::time::Duration;
+
+use igd_next::aio::tokio::{search_gateway, Tokio};
+use igd_next::aio::Gateway;
+use igd_next::{PortMappingProtocol, SearchOptions};
+use natpmp::{new_tokio_natpmp, Protocol, Response};
+use tokio::task::JoinHandle;
+
+/// How long the router keeps a mapping for. Mappings are renewed halfway through, so that they
+/// don't outlive NetCanv by much if it exits without removing them.
+const LEASE_DURATION: Duration = Duration::from_secs(60 * 60);
+
+/// The description mappings are listed under in the router's settings.
+const DESCRIPTION: &str = "NetCanv room";
+
+/// A way of talking to the router.
+enum Router {
+   Upnp {
+      gateway: Gateway<Tokio>,
+      /// Our address on the local network, which the router forwards connections to.
+      local_address: SocketAddr,
+   },
+   NatPmp,
+}
+
+impl Router {
+   /// Maps the external port to the local one, returning the external port the router picked.
+   async fn add_mapping(&self, local_port: u16, external_port: u16) -> Result<u16, String> {
+      match self {
+         Router::Upnp {
+            gateway,
+            local_address,
+         } => {
+            gateway
+               .add_port(
+                  PortMappingProtocol::TCP,
+                  external_port,
+                  *local_address,
+                  LEASE_DURATION.as_secs() as u32,
+                  DESCRIPTION,
+               )
+               .await
+               .map_err(|error| error.to_string())?;
+            Ok(external_port)
+         }
+         Router::NatPmp => {
+            let mut natpmp = new_tokio_natpmp().await.map_err(|error| format!("{:?}", error))?;
+            natpmp
+               .send_port_mapping_request(
+                  Protocol::TCP,
+                  local_port,
+                  external_port,
+                  LEASE_DURATION.as_secs() as u32,
+               )
+               .await
+               .map_err(|error| format!("{:?}", error))?;
+            match natpmp.read_response_or_retry().await.map_err(|error| format!("{:?}", error))? {
+               Response::TCP(response) => Ok(response.public_port()),
+               _ => Err("unexpected NAT-PMP response".into()),
+            }
+         }
+      }
+   }
+
+   /// Removes the mapping from the router.
+   async fn remove_mapping(&self, local_port: u16, external_port: u16) -> Result<(), String> {
+      match self {
+         Router::Upnp { gateway, .. } => gateway
+            .remove_port(PortMappingProtocol::TCP, external_port)
+            .await
+            .map_err(|error| error.to_string()),
+         Router::NatPmp => {
+            let mut natpmp = new_tokio_natpmp().await.map_err(|error| format!("{:?}", error))?;
+            // A lifetime of zero asks the router to remove the mapping.
+            natpmp
+               .send_port_mapping_request(Protocol::TCP, local_port, 0, 0)
+               .await
+               .map_err(|error| format!("{:?}", error))?;
+            natpmp.read_response_or_retry().await.map_err(|error| format!("{:?}", error))?;
+            Ok(())
+         }
+      }
+   }
+}
+
+/// A port mapped on the router. The mapping is removed once this is dropped.
+pub struct PortMapping {
+   router: Arc<Router>,
+   local_port: u16,
+   external_address: SocketAddr,
+   renewal: JoinHandle<()>,
+}
+
+impl PortMapping {
+   /// Asks the router to forward connections to the local port from the Internet.
+   ///
+   /// Returns `None` if neither UPnP nor NAT-PMP is available, or the router refused. The reasons
+   /// are logged, since there's nothing to do about them other than using the relay instead.
+   pub async fn request(local_port: u16) -> Option<Self> {
+      let (router, external_address) = match Self::map_with_upnp(local_port).await {
+         Ok(mapped) => mapped,
+         Err(error) => {
+            tracing::info!("UPnP port mapping failed: {}", error);
+            match Self::map_with_nat_pmp(local_port).await {
+               Ok(mapped) => mapped,
+               Err(error) => {
+                  tracing::warn!("NAT-PMP port mapping failed: {}", error);
+                  return None;
+               }
+            }
+         }
+      };
+      tracing::info!(
+         "port {} is reachable from the Internet at {}",
+         local_port,
+         external_address
+      );
+
+      let router = Arc::new(router);
+      let renewal = tokio::spawn({
+         let router = Arc::clone(&router);
+         let external_port = external_address.port();
+         async move {
+            loop {
+               tokio::time::sleep(LEASE_DURATION / 2).await;
+               if let Err(error) = router.add_mapping(local_port, external_port).await {
+                  tracing::warn!("could not renew port mapping: {}", error);
+               }
+            }
+         }
+      });
+      Some(Self {
+         router,
+         local_port,
+         external_address,
+         renewal,
+      })
+   }
+
+   async fn map_with_upnp(local_port: u16) -> Result<(Router, SocketAddr), String> {
+      let gateway =
+         search_gateway(SearchOptions::default()).await.map_err(|error| error.to_string())?;
+      let external_ip = gateway.get_external_ip().await.map_err(|error| error.to_string())?;
+      // The router forwards to whichever of our addresses faces it.
+      let local_ip = UdpSocket::bind(("0.0.0.0", 0))
+         .and_then(|socket| {
+            socket.connect(gateway.addr)?;
+            socket.local_addr()
+         })
+         .map_err(|error| error.to_string())?
+         .ip();
+      let router = Router::Upnp {
+         gateway,
+         local_address: SocketAddr::new(local_ip, local_port),
+      };
+      let external_port = router.add_mapping(local_port, local_port).await?;
+      Ok((router, SocketAddr::new(external_ip, external_port)))
+   }
+
+   async fn map_with_nat_pmp(local_port: u16) -> Result<(Router, SocketAddr), String> {
+      let mut natpmp = new_tokio_natpmp().await.map_err(|error| format!("{:?}", error))?;
+      natpmp.send_public_address_request().await.map_err(|error| format!("{:?}", error))?;
+      let external_ip =
+         match natpmp.read_response_or_retry().await.map_err(|error| format!("{:?}", error))? {
+            Response::Gateway(response) => IpAddr::V4(*response.public_address()),
+            _ => return Err("unexpected NAT-PMP response".into()),
+         };
+      let router = Router::NatPmp;
+      let external_port = router.add_mapping(local_port, local_port).await?;
+      Ok((router, SocketAddr::new(external_ip, external_port)))
+   }
+
+   /// Returns the address others can reach the relay at.
+   pub fn address(&self) -> String {
+      format!("ws://{}", self.external_address)
+   }
+}
+
+impl Drop for PortMapping {
+   fn drop(&mut self) {
+      self.renewal.abort();
+      let router = Arc::clone(&self.router);
+      let local_port = self.local_port;
+      let external_port = self.external_address.port();
+      tokio::spawn(async move {
+         match router.remove_mapping(local_port, external_port).await {
+            Ok(()) => tracing::info!("removed port mapping"),
+            Err(error) => tracing::warn!("could not remove port mapping: {}", error),
+         }
+      });
+   }
+}
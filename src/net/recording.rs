@@ -0,0 +1,166 @@
+//! Recording sessions, and replaying them later.
+//!
+//! A recording contains every client packet sent and received by a peer, along with the time it
+//! was sent or received. Replaying the recording feeds the packets back through the peer, without
+//! any network connection, which reproduces everything that happened on the canvas in the same
+//! order and at the same pace.
+
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Cursor, Write};
+use std::path::Path;
+
+use netcanv_protocol::relay::{PeerId, RoomId};
+use serde::{Deserialize, Serialize};
+use web_time::{Duration, Instant};
+
+use crate::Error;
+
+/// The magic bytes at the start of every recording.
+const MAGIC: &[u8; 8] = b"NCRECORD";
+
+/// The version of the recording format. Recordings with a different version cannot be replayed.
+const VERSION: u32 = 1;
+
+/// A single entry in a recording.
+#[derive(Serialize, Deserialize)]
+pub enum Entry {
+   /// The peer has joined or created a room. This is always the first entry in a recording.
+   Session {
+      relay_address: String,
+      room_id: RoomId,
+      peer_id: PeerId,
+      /// The host of the room, or `None` if the peer was the host.
+      host_id: Option<PeerId>,
+   },
+   /// A client packet was received from another peer.
+   Incoming {
+      /// The time since the recording was started, in milliseconds.
+      time: u64,
+      author: PeerId,
+      payload: Vec<u8>,
+   },
+   /// A client packet was sent to another peer, or broadcast to everyone.
+   Outgoing {
+      time: u64,
+      to: PeerId,
+      payload: Vec<u8>,
+   },
+   /// The host role was transferred to another peer.
+   HostTransfer { time: u64, host_id: PeerId },
+   /// A peer has left the room.
+   Disconnected { time: u64, peer_id: PeerId },
+}
+
+impl Entry {
+   /// Returns the time at which the entry was recorded, in milliseconds.
+   fn time(&self) -> u64 {
+      match self {
+         Self::Session { .. } => 0,
+         Self::Incoming { time, .. }
+         | Self::Outgoing { time, .. }
+         | Self::HostTransfer { time, .. }
+         | Self::Disconnected { time, .. } => *time,
+      }
+   }
+}
+
+/// Writes entries to a recording file.
+pub struct Recorder {
+   file: BufWriter<File>,
+   start: Instant,
+}
+
+impl Recorder {
+   /// Creates a new recording file at the given path, overwriting it if it already exists.
+   pub fn create(path: &Path) -> netcanv::Result<Self> {
+      let mut file = BufWriter::new(File::create(path)?);
+      file.write_all(MAGIC)?;
+      file.write_all(&VERSION.to_le_bytes())?;
+      Ok(Self {
+         file,
+         start: Instant::now(),
+      })
+   }
+
+   /// Returns the time since the recording was started, in milliseconds.
+   pub fn time(&self) -> u64 {
+      self.start.elapsed().as_millis() as u64
+   }
+
+   /// Writes an entry to the recording.
+   ///
+   /// The file is flushed after each entry, so that the recording is usable even if NetCanv
+   /// crashes in the middle of a session.
+   pub fn write(&mut self, entry: &Entry) -> netcanv::Result<()> {
+      bincode::serialize_into(&mut self.file, entry).map_err(|error| Error::Io {
+         error: error.to_string(),
+      })?;
+      self.file.flush()?;
+      Ok(())
+   }
+}
+
+/// A recording being replayed.
+pub struct Replay {
+   entries: VecDeque<Entry>,
+   start: Instant,
+   speed: f32,
+}
+
+impl Replay {
+   /// Loads a recording from the given file. The replay starts right away, and plays at the
+   /// given speed, where 1 is the speed at which the session was recorded.
+   pub fn load(path: &Path, speed: f32) -> netcanv::Result<Self> {
+      let data = std::fs::read(path)?;
+      let header_len = MAGIC.len() + 4;
+      if data.len() < header_len || &data[..MAGIC.len()] != MAGIC {
+         return Err(Error::InvalidRecording);
+      }
+      let version = u32::from_le_bytes(data[MAGIC.len()..header_len].try_into().unwrap());
+      if version != VERSION {
+         return Err(Error::RecordingVersionMismatch);
+      }
+
+      let mut reader = Cursor::new(&data[header_len..]);
+      let mut entries = VecDeque::new();
+      while (reader.position() as usize) < reader.get_ref().len() {
+         // A recording cut short by a crash can end in the middle of an entry. Everything up to
+         // that point is still worth replaying.
+         match bincode::deserialize_from(&mut reader) {
+            Ok(entry) => entries.push_back(entry),
+            Err(error) => {
+               tracing::warn!("recording ends with a truncated entry: {}", error);
+               break;
+            }
+         }
+      }
+      if !matches!(entries.front(), Some(Entry::Session { .. })) {
+         return Err(Error::InvalidRecording);
+      }
+
+      Ok(Self {
+         entries,
+         start: Instant::now(),
+         speed: speed.max(0.01),
+      })
+   }
+
+   /// Removes and returns the entries whose time has come.
+   pub fn take_due(&mut self) -> Vec<Entry> {
+      let elapsed = self.start.elapsed().mul_f32(self.speed);
+      let mut due = Vec::new();
+      while let Some(entry) = self.entries.front() {
+         if Duration::from_millis(entry.time()) > elapsed {
+            break;
+         }
+         due.push(self.entries.pop_front().unwrap());
+      }
+      due
+   }
+
+   /// Returns whether all entries have been replayed.
+   pub fn is_finished(&self) -> bool {
+      self.entries.is_empty()
+   }
+}
@@ -0,0 +1,53 @@
+//! Checking whether a relay is reachable, and how quickly it responds.
+
+use std::sync::Arc;
+
+use netcanv_protocol::relay;
+use web_time::{Duration, Instant};
+
+use super::socket::{PendingSocket, Socket, SocketSystem};
+
+enum State {
+   WaitingForRelay(PendingSocket),
+   WaitingForResponse(Socket, Instant),
+   Done,
+}
+
+/// A pending ping of a relay.
+///
+/// The relay doesn't have a dedicated ping packet, so the ping asks for the list of public rooms
+/// instead, which is cheap to answer.
+pub struct RelayPing {
+   state: State,
+}
+
+impl RelayPing {
+   /// Connects to the relay and pings it.
+   pub fn new(socket_system: Arc<SocketSystem>, relay_address: &str) -> Self {
+      Self {
+         state: State::WaitingForRelay(socket_system.connect(relay_address.to_owned())),
+      }
+   }
+
+   /// Polls for the relay's response. Returns the round-trip time once the response arrives;
+   /// after that, the ping is done and further polls return `None`.
+   pub fn poll(&mut self) -> netcanv::Result<Option<Duration>> {
+      if let State::WaitingForRelay(socket) = &mut self.state {
+         if let Ok(socket) = socket.try_recv() {
+            self.state = State::Done;
+            let socket = socket?;
+            socket.send(relay::Packet::ListRooms);
+            self.state = State::WaitingForResponse(socket, Instant::now());
+         }
+      }
+      if let State::WaitingForResponse(socket, sent) = &mut self.state {
+         // Any response will do, even an error, as it means the relay is there.
+         if socket.recv().is_some() {
+            let round_trip = sent.elapsed();
+            self.state = State::Done;
+            return Ok(Some(round_trip));
+         }
+      }
+      Ok(None)
+   }
+}
@@ -0,0 +1,173 @@
+//! `netcanv://` links. A link bundles the relay address together with a room ID or an invite
+//! token, so that joining a room only requires sharing a single string.
+
+use std::fmt::{self, Display, Formatter};
+use std::str::FromStr;
+use std::sync::Arc;
+
+use netcanv_protocol::relay::{InviteToken, RoomId};
+
+use super::peer::Peer;
+use super::socket::SocketSystem;
+
+/// What a link lets its bearer into.
+#[derive(Clone, Copy, Debug)]
+pub enum LinkTarget {
+   Room(RoomId),
+   Invite(InviteToken),
+}
+
+/// A link to a room on a relay, of the form `netcanv://<relay>/<room ID or invite>`.
+///
+/// Relays are assumed to be reachable over `wss://`. Links to relays only reachable over plain
+/// `ws://` end with `?insecure`.
+#[derive(Clone, Debug)]
+pub struct RoomLink {
+   pub relay: String,
+   pub target: LinkTarget,
+}
+
+impl RoomLink {
+   /// The URL scheme of links.
+   pub const SCHEME: &'static str = "netcanv";
+
+   /// The query marking a link to a relay reachable over plain `ws://`.
+   const INSECURE: &'static str = "?insecure";
+
+   /// Creates a link to the room with the given ID.
+   pub fn new(relay: &str, room_id: RoomId) -> Self {
+      Self {
+         relay: relay.to_owned(),
+         target: LinkTarget::Room(room_id),
+      }
+   }
+
+   /// Connects to the relay and joins the room the link leads to.
+   pub fn join(&self, socket_system: Arc<SocketSystem>, nickname: &str) -> Peer {
+      match self.target {
+         LinkTarget::Room(room_id) => Peer::join(socket_system, nickname, &self.relay, room_id),
+         LinkTarget::Invite(invite) => {
+            Peer::join_with_invite(socket_system, nickname, &self.relay, invite)
+         }
+      }
+   }
+
+   /// Returns the room ID or invite token, as it would be typed into the lobby.
+   pub fn target_string(&self) -> String {
+      match self.target {
+         LinkTarget::Room(room_id) => room_id.to_string(),
+         LinkTarget::Invite(invite) => invite.to_string(),
+      }
+   }
+}
+
+impl Display for RoomLink {
+   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+      let (relay, query) = if let Some(relay) = self.relay.strip_prefix("ws://") {
+         (relay, Self::INSECURE)
+      } else {
+         (self.relay.strip_prefix("wss://").unwrap_or(&self.relay), "")
+      };
+      write!(
+         f,
+         "{}://{}/{}{}",
+         Self::SCHEME,
+         relay.trim_end_matches('/'),
+         self.target_string(),
+         query
+      )
+   }
+}
+
+impl FromStr for RoomLink {
+   type Err = RoomLinkError;
+
+   fn from_str(link: &str) -> Result<Self, Self::Err> {
+      let link = link.trim();
+      let rest = link
+         .split_once("://")
+         .filter(|(scheme, _)| scheme.eq_ignore_ascii_case(Self::SCHEME))
+         .map(|(_, rest)| rest)
+         .ok_or(RoomLinkError(()))?;
+      let (rest, insecure) = match rest.strip_suffix(Self::INSECURE) {
+         Some(rest) => (rest, true),
+         None => (rest, false),
+      };
+      // The relay address may contain a path itself, so the room is always the last segment.
+      let (relay, target) = rest.trim_end_matches('/').rsplit_once('/').ok_or(RoomLinkError(()))?;
+      if relay.is_empty() {
+         return Err(RoomLinkError(()));
+      }
+      let target = match target.len() {
+         RoomId::LEN => LinkTarget::Room(target.parse().map_err(|_| RoomLinkError(()))?),
+         InviteToken::LEN => LinkTarget::Invite(target.parse().map_err(|_| RoomLinkError(()))?),
+         _ => return Err(RoomLinkError(())),
+      };
+      let relay = if insecure {
+         format!("ws://{}", relay)
+      } else {
+         format!("wss://{}", relay)
+      };
+      Ok(Self { relay, target })
+   }
+}
+
+/// An error returned in case a link is not a valid `netcanv://` link.
+#[derive(Debug)]
+pub struct RoomLinkError(());
+
+impl std::error::Error for RoomLinkError {}
+
+impl Display for RoomLinkError {
+   fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+      write!(
+         f,
+         "expected a link of the form {}://<relay>/<room ID or invite>",
+         RoomLink::SCHEME
+      )
+   }
+}
+
+/// Registers NetCanv as the handler of `netcanv://` links for the current user.
+///
+/// On Linux this is done by the `.desktop` file shipped with NetCanv, so this only does anything
+/// on Windows.
+pub fn register_url_scheme() {
+   #[cfg(windows)]
+   {
+      use std::os::windows::process::CommandExt;
+      use std::process::Command;
+
+      /// Keeps `reg` from flashing a console window.
+      const CREATE_NO_WINDOW: u32 = 0x08000000;
+
+      let executable = match std::env::current_exe() {
+         Ok(path) => path,
+         Err(error) => {
+            tracing::warn!("cannot register the URL scheme: {:?}", error);
+            return;
+         }
+      };
+      let key = format!(r"HKCU\Software\Classes\{}", RoomLink::SCHEME);
+      let command = format!("\"{}\" \"%1\"", executable.display());
+      let entries = [
+         (key.clone(), None, "URL:NetCanv room link".to_owned()),
+         // The "URL Protocol" value marks the key as a URL scheme. It's left empty.
+         (key.clone(), Some("URL Protocol"), String::new()),
+         (format!(r"{}\shell\open\command", key), None, command),
+      ];
+      for (key, value, data) in entries {
+         let mut reg = Command::new("reg");
+         reg.args(["add", &key]);
+         match value {
+            Some(value) => reg.args(["/v", value]),
+            None => reg.arg("/ve"),
+         };
+         reg.args(["/d", &data, "/f"]).creation_flags(CREATE_NO_WINDOW);
+         if let Err(error) = reg.output() {
+            tracing::warn!("cannot register the URL scheme: {:?}", error);
+            return;
+         }
+      }
+   }
+}
@@ -0,0 +1,58 @@
+//! Fetching the list of public rooms from a relay, without joining any of them.
+
+use std::sync::Arc;
+
+use netcanv_protocol::relay::{self, PublicRoom};
+
+use super::socket::{PendingSocket, Socket, SocketSystem};
+use crate::Error;
+
+enum State {
+   WaitingForRelay(PendingSocket),
+   WaitingForList(Socket),
+   Done,
+}
+
+/// A pending request for the list of public rooms on a relay.
+pub struct RoomListRequest {
+   state: State,
+}
+
+impl RoomListRequest {
+   /// Connects to the relay and asks it for its list of public rooms.
+   pub fn new(socket_system: Arc<SocketSystem>, relay_address: &str) -> Self {
+      Self {
+         state: State::WaitingForRelay(socket_system.connect(relay_address.to_owned())),
+      }
+   }
+
+   /// Polls for the relay's response. Returns the list of rooms once it arrives; after that, the
+   /// request is done and further polls return `None`.
+   pub fn poll(&mut self) -> netcanv::Result<Option<Vec<PublicRoom>>> {
+      if let State::WaitingForRelay(socket) = &mut self.state {
+         if let Ok(socket) = socket.try_recv() {
+            self.state = State::Done;
+            let socket = socket?;
+            socket.send(relay::Packet::ListRooms);
+            self.state = State::WaitingForList(socket);
+         }
+      }
+      if let State::WaitingForList(socket) = &mut self.state {
+         if let Some(packet) = socket.recv() {
+            // The socket is dropped here, as it's no longer needed.
+            self.state = State::Done;
+            return match packet {
+               relay::Packet::RoomList(rooms) => Ok(Some(rooms)),
+               relay::Packet::Error(error) => Err(Error::Relay(error)),
+               _ => Err(Error::UnexpectedRelayPacket),
+            };
+         }
+      }
+      Ok(None)
+   }
+
+   /// Returns whether the request is still waiting for a response.
+   pub fn is_pending(&self) -> bool {
+      !matches!(self.state, State::Done)
+   }
+}
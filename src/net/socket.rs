@@ -1,30 +1,67 @@
 //! An abstraction for sockets, communicating over the global bus.
 
 use std::cmp::Ordering;
+use std::net::SocketAddr;
 use std::sync::Arc;
 
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use netcanv_protocol::relay;
 use nysa::global as bus;
-use tokio::net::TcpStream;
-use tokio::sync::{broadcast, mpsc, oneshot, Mutex};
+use tokio::net::{lookup_host, TcpStream};
+use tokio::sync::{broadcast, mpsc, oneshot, watch, Mutex};
 use tokio::task::JoinHandle;
 use tokio::time::timeout;
 use tokio_tungstenite::tungstenite::Message;
-use tokio_tungstenite::{connect_async, tungstenite, MaybeTlsStream, WebSocketStream};
+use tokio_tungstenite::{client_async_tls, tungstenite, MaybeTlsStream, WebSocketStream};
 use url::Url;
 use web_time::Duration;
 
-use crate::common::{deserialize_bincode, serialize_bincode, Fatal};
+use crate::common::{decode_packet, serialize_bincode, Fatal};
 use crate::Error;
 
+/// The stage a connection to a relay is at.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ConnectionStage {
+   /// Asking the router to open a port, for hosting a room directly.
+   MappingPort,
+   /// Looking up the relay's address.
+   Resolving,
+   /// Establishing the WebSocket connection.
+   Connecting,
+   /// Connected, and waiting for the relay to respond.
+   WaitingForRelay,
+}
+
+/// A connection to a relay that is still being established.
+///
+/// Dropping this aborts the connection.
+pub struct PendingSocket {
+   socket: oneshot::Receiver<netcanv::Result<Socket>>,
+   stage: watch::Receiver<ConnectionStage>,
+}
+
+impl PendingSocket {
+   /// Returns the socket, if the connection has been established (or failed).
+   pub fn try_recv(&mut self) -> Result<netcanv::Result<Socket>, oneshot::error::TryRecvError> {
+      self.socket.try_recv()
+   }
+
+   /// Returns the stage the connection is at.
+   pub fn stage(&self) -> ConnectionStage {
+      *self.stage.borrow()
+   }
+}
+
 /// Runtime for managing active connections.
 pub struct SocketSystem {
    quitters: Mutex<Vec<SocketQuitter>>,
 }
 
 impl SocketSystem {
+   /// How long each stage of establishing a connection may take before giving up.
+   const CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
    /// Starts the socket system.
    pub fn new() -> Arc<Self> {
       Arc::new(Self {
@@ -44,13 +81,50 @@ impl SocketSystem {
       Ok(url)
    }
 
-   async fn connect_inner(self: Arc<Self>, url: String) -> netcanv::Result<Socket> {
+   /// Resolves the address of the host the URL points to.
+   async fn resolve(url: &Url) -> netcanv::Result<Vec<SocketAddr>> {
+      let host = url.host_str().ok_or(Error::InvalidUrl)?;
+      let port = url.port_or_known_default().ok_or(Error::InvalidUrl)?;
+      // IPv6 addresses are enclosed in brackets in URLs, but lookup_host doesn't expect those.
+      let host = host.trim_start_matches('[').trim_end_matches(']');
+      let addresses: Vec<_> = timeout(Self::CONNECT_TIMEOUT, lookup_host((host, port)))
+         .await
+         .map_err(|_| Error::ConnectionTimedOut)?
+         .map_err(|_| Error::CouldNotResolveHost {
+            host: host.to_owned(),
+         })?
+         .collect();
+      if addresses.is_empty() {
+         return Err(Error::CouldNotResolveHost {
+            host: host.to_owned(),
+         });
+      }
+      Ok(addresses)
+   }
+
+   async fn connect_inner(
+      self: Arc<Self>,
+      url: String,
+      stage: watch::Sender<ConnectionStage>,
+   ) -> netcanv::Result<Socket> {
       let address = Self::parse_url(&url)?;
-      let (stream, _) = connect_async(address).await?;
+      let addresses = Self::resolve(&address).await?;
+
+      stage.send_replace(ConnectionStage::Connecting);
+      let (stream, _) = timeout(Self::CONNECT_TIMEOUT, async {
+         let stream = TcpStream::connect(&addresses[..]).await?;
+         Ok::<_, Error>(client_async_tls(address, stream).await?)
+      })
+      .await
+      .map_err(|_| Error::ConnectionTimedOut)??;
       let (sink, mut stream) = stream.split();
       tracing::info!("connection established");
 
-      let version = stream.next().await.ok_or(Error::NoVersionPacket)?;
+      stage.send_replace(ConnectionStage::WaitingForRelay);
+      let version = timeout(Self::CONNECT_TIMEOUT, stream.next())
+         .await
+         .map_err(|_| Error::ConnectionTimedOut)?
+         .ok_or(Error::NoVersionPacket)?;
 
       let version = match version? {
          Message::Binary(version) => {
@@ -83,9 +157,9 @@ impl SocketSystem {
 
       tracing::debug!("starting sender loop");
       let (send_tx, send_rx) = mpsc::unbounded_channel();
-      let send_quit_rx = quit_tx.subscribe();
+      let (send_quit_tx, send_quit_rx) = (quit_tx.clone(), quit_tx.subscribe());
       let send_join_handle = tokio::spawn(async move {
-         if let Err(error) = Socket::sender_loop(sink, send_rx, send_quit_rx).await {
+         if let Err(error) = Socket::sender_loop(sink, send_rx, send_quit_tx, send_quit_rx).await {
             tracing::error!("sender loop error: {:?}", error);
          }
       });
@@ -105,16 +179,27 @@ impl SocketSystem {
    }
 
    /// Initiates a new connection to the relay at the given hostname (IP address or DNS domain).
-   pub fn connect(self: Arc<Self>, hostname: String) -> oneshot::Receiver<netcanv::Result<Socket>> {
+   pub fn connect(self: Arc<Self>, hostname: String) -> PendingSocket {
       tracing::info!("connecting to {}", hostname);
-      let (socket_tx, socket_rx) = oneshot::channel();
+      let (mut socket_tx, socket_rx) = oneshot::channel();
+      let (stage_tx, stage_rx) = watch::channel(ConnectionStage::Resolving);
       let self2 = Arc::clone(&self);
       tokio::spawn(async move {
-         if socket_tx.send(self2.connect_inner(hostname).await).is_err() {
-            panic!("Could not send ready socket to receiver");
+         let socket = tokio::select! {
+            socket = self2.connect_inner(hostname, stage_tx) => socket,
+            _ = socket_tx.closed() => {
+               tracing::info!("connection aborted");
+               return;
+            }
+         };
+         if socket_tx.send(socket).is_err() {
+            tracing::info!("connection aborted after it was established");
          }
       });
-      socket_rx
+      PendingSocket {
+         socket: socket_rx,
+         stage: stage_rx,
+      }
    }
 
    pub fn shutdown(self: Arc<Self>) {
@@ -154,7 +239,7 @@ impl Socket {
             if data.len() > relay::MAX_PACKET_SIZE as usize {
                return Err(Error::ReceivedPacketThatIsTooBig);
             }
-            let packet = deserialize_bincode(&data)?;
+            let packet = decode_packet(&data)?;
             output.send(packet)?;
          }
          Ok(Message::Close(frame)) => {
@@ -247,6 +332,7 @@ impl Socket {
    async fn sender_loop(
       mut sink: Sink,
       mut input: mpsc::UnboundedReceiver<relay::Packet>,
+      signal_tx: broadcast::Sender<Signal>,
       mut signal: broadcast::Receiver<Signal>,
    ) -> netcanv::Result<()> {
       loop {
@@ -267,6 +353,11 @@ impl Socket {
                if let Some(packet) = packet {
                   Self::write_packet(&mut sink, packet).await?;
                } else {
+                  // The socket was dropped, so the connection is closed on our side. The receiver
+                  // is stopped first, so that the relay's closing handshake isn't mistaken for the
+                  // relay disconnecting on its own.
+                  let _ = signal_tx.send(Signal::Quit);
+                  sink.close().await?;
                   break;
                }
             },
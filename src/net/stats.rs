@@ -0,0 +1,177 @@
+//! Network traffic statistics, for telling where lag in a room comes from.
+
+use std::collections::VecDeque;
+
+use web_time::{Duration, Instant};
+
+/// What a client packet carries, for the purpose of statistics.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TrafficKind {
+   /// Cursor positions of other people.
+   Cursor,
+   /// Strokes painted onto the canvas.
+   Stroke,
+   /// Chunk positions, requests and image data.
+   Chunk,
+   /// Everything else.
+   Other,
+}
+
+impl TrafficKind {
+   /// All traffic kinds, in the order they're stored in a [`Sample`].
+   pub const ALL: [Self; 4] = [Self::Cursor, Self::Stroke, Self::Chunk, Self::Other];
+
+   fn index(self) -> usize {
+      self as usize
+   }
+}
+
+/// The traffic over a single sampling period.
+#[derive(Clone, Copy, Default)]
+pub struct Sample {
+   pub bytes_sent: u64,
+   pub bytes_received: u64,
+   /// The number of client packets sent and received, indexed by [`TrafficKind`].
+   pub packets: [u32; TrafficKind::ALL.len()],
+   /// The last round-trip time to the relay measured during this period.
+   pub relay_rtt: Option<Duration>,
+}
+
+impl Sample {
+   /// Returns the number of packets of the given kind.
+   pub fn packets(&self, kind: TrafficKind) -> u32 {
+      self.packets[kind.index()]
+   }
+}
+
+/// Traffic statistics of a peer connection.
+pub struct NetworkStats {
+   current: Sample,
+   period_start: Instant,
+   history: VecDeque<Sample>,
+   /// The time the relay was last pinged, if it hasn't responded yet.
+   pending_ping: Option<Instant>,
+   last_ping: Option<Instant>,
+   last_rtt: Option<Duration>,
+   /// The ID of the last latency probe sent to the room, and the time it was sent at.
+   last_probe: Option<(u32, Instant)>,
+}
+
+impl NetworkStats {
+   /// The length of a single sampling period. This makes the samples per-second rates.
+   pub const PERIOD: Duration = Duration::from_secs(1);
+   /// The number of past samples kept.
+   pub const HISTORY_LEN: usize = 60;
+   /// How often the relay's round-trip time is measured.
+   const PING_INTERVAL: Duration = Duration::from_secs(2);
+
+   pub fn new() -> Self {
+      Self {
+         current: Sample::default(),
+         period_start: Instant::now(),
+         history: VecDeque::with_capacity(Self::HISTORY_LEN),
+         pending_ping: None,
+         last_ping: None,
+         last_rtt: None,
+         last_probe: None,
+      }
+   }
+
+   /// Records bytes sent to the relay.
+   pub fn record_sent_bytes(&mut self, bytes: usize) {
+      self.current.bytes_sent += bytes as u64;
+   }
+
+   /// Records bytes received from the relay.
+   pub fn record_received_bytes(&mut self, bytes: usize) {
+      self.current.bytes_received += bytes as u64;
+   }
+
+   /// Records a client packet, sent or received.
+   pub fn record_packet(&mut self, kind: TrafficKind) {
+      self.current.packets[kind.index()] += 1;
+   }
+
+   /// Returns whether the relay should be pinged now, and if so, starts timing the ping.
+   pub fn start_ping(&mut self) -> bool {
+      let due = self.last_ping.map_or(true, |last| last.elapsed() >= Self::PING_INTERVAL);
+      if self.pending_ping.is_none() && due {
+         let now = Instant::now();
+         self.pending_ping = Some(now);
+         self.last_ping = Some(now);
+         true
+      } else {
+         false
+      }
+   }
+
+   /// Finishes timing a ping, once the relay responds. Returns `false` if no ping was pending,
+   /// in which case the response was not meant for the statistics.
+   pub fn finish_ping(&mut self) -> bool {
+      match self.pending_ping.take() {
+         Some(sent) => {
+            let rtt = sent.elapsed();
+            self.last_rtt = Some(rtt);
+            self.current.relay_rtt = Some(rtt);
+            true
+         }
+         None => false,
+      }
+   }
+
+   /// Returns the ID of a new latency probe, if it's time to send one to the room.
+   ///
+   /// Probes are sent as often as the relay is pinged. Unlike pings, a new probe doesn't wait for
+   /// the previous one to be answered, as some peers may never answer it.
+   pub fn start_probe(&mut self) -> Option<u32> {
+      let due = self.last_probe.map_or(true, |(_, sent)| sent.elapsed() >= Self::PING_INTERVAL);
+      if due {
+         let id = self.last_probe.map_or(0, |(id, _)| id.wrapping_add(1));
+         self.last_probe = Some((id, Instant::now()));
+         Some(id)
+      } else {
+         None
+      }
+   }
+
+   /// Returns the round-trip time of the latency probe with the given ID. Returns `None` if the
+   /// probe is not the most recent one, as replies to older probes are too late to be accurate.
+   pub fn finish_probe(&self, id: u32) -> Option<Duration> {
+      self.last_probe.filter(|&(last_id, _)| last_id == id).map(|(_, sent)| sent.elapsed())
+   }
+
+   /// Moves on to the next sample once the current sampling period is over.
+   pub fn tick(&mut self) {
+      let elapsed = self.period_start.elapsed();
+      if elapsed < Self::PERIOD {
+         return;
+      }
+      // If the app stalled for several periods, the traffic is spread evenly between them, so
+      // that a single frame of lag doesn't show up as a spike.
+      let periods = (elapsed.as_secs_f32() / Self::PERIOD.as_secs_f32()) as u32;
+      let mut sample = self.current;
+      if periods > 1 {
+         sample.bytes_sent /= periods as u64;
+         sample.bytes_received /= periods as u64;
+         sample.packets = sample.packets.map(|count| count / periods);
+      }
+      for _ in 0..periods.min(Self::HISTORY_LEN as u32) {
+         if self.history.len() >= Self::HISTORY_LEN {
+            self.history.pop_front();
+         }
+         self.history.push_back(sample);
+      }
+      self.current = Sample::default();
+      self.period_start += Self::PERIOD * periods;
+   }
+
+   /// Returns the samples from the past periods, oldest first.
+   pub fn history(&self) -> &VecDeque<Sample> {
+      &self.history
+   }
+
+   /// Returns the most recently measured round-trip time to the relay.
+   pub fn relay_rtt(&self) -> Option<Duration> {
+      self.last_rtt
+   }
+}
@@ -0,0 +1,69 @@
+//! Attribution tracking, used to show who drew which parts of the canvas.
+
+use std::collections::HashMap;
+
+use netcanv_protocol::relay::PeerId;
+use web_time::Instant;
+
+/// A single author's changes to a chunk.
+#[derive(Debug, Clone, Copy)]
+pub struct Contribution {
+   /// The author, or `None` if it's ourselves.
+   pub author: Option<PeerId>,
+   /// When the author last drew to the chunk.
+   pub last_change: Instant,
+}
+
+/// Records who drew to which chunks, and when.
+///
+/// Only changes seen since joining the room are known, as chunks downloaded from the host don't
+/// say who drew them.
+pub struct Attribution {
+   /// The contributions to every chunk, most recent first.
+   chunks: HashMap<(i32, i32), Vec<Contribution>>,
+}
+
+impl Attribution {
+   /// The maximum number of authors remembered for a single chunk. The authors who haven't drawn
+   /// to it for the longest time are forgotten first.
+   const MAX_AUTHORS_PER_CHUNK: usize = 8;
+
+   /// Creates a new, empty attribution record.
+   pub fn new() -> Self {
+      Self {
+         chunks: HashMap::new(),
+      }
+   }
+
+   /// Records that the given author just drew to the chunk.
+   pub fn record(&mut self, author: Option<PeerId>, chunk_position: (i32, i32)) {
+      let contributions = self.chunks.entry(chunk_position).or_default();
+      if let Some(index) = contributions.iter().position(|c| c.author == author) {
+         contributions.remove(index);
+      }
+      contributions.insert(
+         0,
+         Contribution {
+            author,
+            last_change: Instant::now(),
+         },
+      );
+      contributions.truncate(Self::MAX_AUTHORS_PER_CHUNK);
+   }
+
+   /// Returns the contributions to the chunk at the given position, most recent first.
+   pub fn contributions(&self, chunk_position: (i32, i32)) -> &[Contribution] {
+      self.chunks.get(&chunk_position).map(|c| c.as_slice()).unwrap_or(&[])
+   }
+
+   /// Returns who drew to the chunk at the given position most recently.
+   pub fn latest(&self, chunk_position: (i32, i32)) -> Option<&Contribution> {
+      self.contributions(chunk_position).first()
+   }
+}
+
+impl Default for Attribution {
+   fn default() -> Self {
+      Self::new()
+   }
+}
@@ -1,16 +1,36 @@
 use std::collections::HashMap;
 
+use netcanv_protocol::client::Capabilities;
 use web_time::{Duration, Instant};
 
+use super::LayerChunk;
+
 #[derive(Clone)]
 pub struct CachedChunk {
    pub png: Vec<u8>,
    pub webp: Option<Vec<u8>>,
+   pub qoi: Option<Vec<u8>>,
+   /// The revision of the chunk the images were encoded from.
+   pub revision: u64,
+}
+
+impl CachedChunk {
+   /// Picks the image to send to a peer with the given capabilities. PNG is the fallback for
+   /// peers that can't decode the room's chunk format.
+   pub fn into_data(self, capabilities: Capabilities) -> Vec<u8> {
+      match self {
+         CachedChunk {
+            webp: Some(webp), ..
+         } if capabilities.webp_chunks => webp,
+         CachedChunk { qoi: Some(qoi), .. } if capabilities.qoi_chunks => qoi,
+         CachedChunk { png, .. } => png,
+      }
+   }
 }
 
 pub struct CacheLayer {
-   chunks: HashMap<(i32, i32), CachedChunk>,
-   chunk_cache_timers: HashMap<(i32, i32), Instant>,
+   chunks: HashMap<LayerChunk, CachedChunk>,
+   chunk_cache_timers: HashMap<LayerChunk, Instant>,
 }
 
 impl CacheLayer {
@@ -25,16 +45,29 @@ impl CacheLayer {
       }
    }
 
-   pub fn chunk(&mut self, position: (i32, i32)) -> Option<&CachedChunk> {
+   /// Returns the cached images of the chunk at the given position and layer, as long as they
+   /// were encoded from the given revision of the chunk. Images of older revisions are dropped.
+   pub fn chunk(&mut self, position: LayerChunk, revision: u64) -> Option<&CachedChunk> {
+      if self.chunks.get(&position).is_some_and(|chunk| chunk.revision != revision) {
+         self.invalidate(position);
+         return None;
+      }
       self.chunk_cache_timers.insert(position, Instant::now());
       self.chunks.get(&position)
    }
 
-   pub fn set_chunk(&mut self, position: (i32, i32), chunk: CachedChunk) {
+   pub fn set_chunk(&mut self, position: LayerChunk, chunk: CachedChunk) {
       self.chunks.insert(position, chunk);
       self.chunk_cache_timers.insert(position, Instant::now());
    }
 
+   /// Drops the cached images of the chunk at the given position and layer, such that it gets
+   /// encoded anew the next time it's requested.
+   pub fn invalidate(&mut self, position: LayerChunk) {
+      self.chunks.remove(&position);
+      self.chunk_cache_timers.remove(&position);
+   }
+
    pub fn update_timers(&mut self) {
       for (position, instant) in &self.chunk_cache_timers {
          if instant.elapsed() > Self::CHUNK_CACHE_DURATION {
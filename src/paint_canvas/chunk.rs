@@ -1,27 +1,205 @@
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+
 use ::image::{ImageBuffer, Rgba, RgbaImage};
+use netcanv_protocol::client::CHUNK_DIGEST_LEN;
 use netcanv_renderer::paws::Point;
 use netcanv_renderer::{Framebuffer as FramebufferTrait, RenderBackend};
+use tokio::sync::oneshot;
+use tokio::sync::oneshot::error::TryRecvError;
 
 use crate::backend::{Backend, Framebuffer};
+use crate::image_coder::ImageCoder;
+
+#[cfg(test)]
+mod tests;
+
+/// Where a chunk's image is kept.
+enum Storage {
+   /// On the graphics card, ready to be drawn.
+   Resident(Framebuffer),
+   /// Evicted from the graphics card, and being compressed on another thread. The image is kept
+   /// until that's done, such that it can still be read in the meantime.
+   Compressing(Arc<RgbaImage>, oneshot::Receiver<EvictedImage>),
+   /// Evicted from the graphics card.
+   Evicted(EvictedImage),
+}
+
+/// The image of a chunk that was evicted from the graphics card.
+enum EvictedImage {
+   /// Compressed to PNG to save memory.
+   Compressed(Vec<u8>),
+   /// Written out to a PNG file in the disk cache.
+   Spilled(SpilledImage),
+   /// Kept as it is, because it couldn't be compressed.
+   Uncompressed(Arc<RgbaImage>),
+}
+
+impl EvictedImage {
+   /// Compresses an image, writing it out to the given disk cache directory if there is one.
+   fn compress(image: Arc<RgbaImage>, disk_cache: Option<&Path>) -> Self {
+      let data = match ImageCoder::encode_png_data_sync((*image).clone()) {
+         Ok(data) => data,
+         Err(error) => {
+            tracing::error!("could not compress chunk for eviction: {:?}", error);
+            return Self::Uncompressed(image);
+         }
+      };
+      match disk_cache {
+         Some(directory) => {
+            let id = NEXT_SPILL_ID.fetch_add(1, Ordering::Relaxed);
+            let path = directory.join(format!("{id}.png"));
+            match std::fs::write(&path, &data) {
+               Ok(()) => Self::Spilled(SpilledImage { path }),
+               Err(error) => {
+                  tracing::warn!("could not spill chunk to {:?}: {:?}", path, error);
+                  Self::Compressed(data)
+               }
+            }
+         }
+         None => Self::Compressed(data),
+      }
+   }
+
+   /// Brings the image back.
+   fn decompress(&self) -> netcanv::Result<RgbaImage> {
+      match self {
+         Self::Compressed(data) => ImageCoder::decode_png_data(data),
+         Self::Spilled(SpilledImage { path }) => std::fs::read(path)
+            .map_err(Into::into)
+            .and_then(|data| ImageCoder::decode_png_data(&data)),
+         Self::Uncompressed(image) => Ok((**image).clone()),
+      }
+   }
+}
+
+/// Counts up for every chunk spilled to the disk cache, such that each one gets a unique file name.
+static NEXT_SPILL_ID: AtomicU64 = AtomicU64::new(0);
+
+/// A PNG file in the disk cache. The file is removed once this is dropped, along with the disk
+/// cache directory if it was the last file in it.
+struct SpilledImage {
+   path: PathBuf,
+}
+
+impl Drop for SpilledImage {
+   fn drop(&mut self) {
+      if let Err(error) = std::fs::remove_file(&self.path) {
+         tracing::warn!("could not remove cached chunk {:?}: {:?}", self.path, error);
+      }
+      if let Some(directory) = self.path.parent() {
+         // This fails if there are other chunks in the directory, which is fine.
+         let _ = std::fs::remove_dir(directory);
+      }
+   }
+}
+
+/// Counts up every time any chunk is changed, such that revisions are never reused, not even by a
+/// chunk created anew where another one used to be. Revision 0 is left for chunks that were never
+/// changed, which are all blank.
+static NEXT_REVISION: AtomicU64 = AtomicU64::new(1);
+
+/// Returns a revision that no chunk had before.
+fn next_revision() -> u64 {
+   NEXT_REVISION.fetch_add(1, Ordering::Relaxed)
+}
 
 /// A chunk on the infinite canvas.
 pub struct Chunk {
-   pub framebuffer: Framebuffer,
+   storage: Storage,
    dirty: bool,
+   /// Renewed every time the chunk is changed, so that encoded images of it can be told apart from
+   /// stale ones.
+   revision: u64,
+   /// The frame the chunk was last seen on, for picking which chunks to evict first.
+   last_seen: u64,
 }
 
 impl Chunk {
    /// The size of a sub-chunk.
    pub const SIZE: (u32, u32) = (256, 256);
 
+   /// The number of cells along each side of the grid a digest is made of.
+   const DIGEST_GRID: u32 = 8;
+
+   /// How far apart the colors of two digests' cells can be while the digests still match.
+   /// Different graphics cards don't draw the exact same pixels, so digests never match exactly.
+   const DIGEST_TOLERANCE: u8 = 4;
+
    /// Creates a new chunk, using the given canvas as a Skia surface allocator.
    pub fn new(renderer: &mut Backend) -> Self {
       Self {
-         framebuffer: renderer.create_framebuffer(Self::SIZE.0, Self::SIZE.1),
+         storage: Storage::Resident(renderer.create_framebuffer(Self::SIZE.0, Self::SIZE.1)),
          dirty: false,
+         revision: 0,
+         last_seen: 0,
       }
    }
 
+   /// Returns the chunk's framebuffer, or `None` if the chunk is evicted from the graphics card.
+   pub fn framebuffer(&self) -> Option<&Framebuffer> {
+      match &self.storage {
+         Storage::Resident(framebuffer) => Some(framebuffer),
+         Storage::Compressing(..) | Storage::Evicted(_) => None,
+      }
+   }
+
+   /// Returns the chunk's framebuffer, uploading the chunk back to the graphics card first if it
+   /// was evicted.
+   pub fn resident_framebuffer(&mut self, renderer: &mut Backend) -> &Framebuffer {
+      if self.framebuffer().is_none() {
+         let image = self.download_image(renderer);
+         let framebuffer = renderer.create_framebuffer(Self::SIZE.0, Self::SIZE.1);
+         renderer.upload_framebuffer(&framebuffer, (0, 0), Self::SIZE, &image);
+         self.storage = Storage::Resident(framebuffer);
+      }
+      match &self.storage {
+         Storage::Resident(framebuffer) => framebuffer,
+         Storage::Compressing(..) | Storage::Evicted(_) => unreachable!(),
+      }
+   }
+
+   /// Evicts the chunk from the graphics card, keeping its image compressed in memory, or in the
+   /// given disk cache directory. The image is compressed on another thread, and
+   /// [`Chunk::poll_eviction`] picks up the result.
+   pub fn evict(&mut self, renderer: &mut Backend, disk_cache: Option<&Path>) {
+      if self.framebuffer().is_none() {
+         return;
+      }
+      let image = Arc::new(self.download_image(renderer));
+      let (tx, rx) = oneshot::channel();
+      let (compressed_image, disk_cache) = (Arc::clone(&image), disk_cache.map(Path::to_owned));
+      tokio::task::spawn_blocking(move || {
+         let evicted = EvictedImage::compress(compressed_image, disk_cache.as_deref());
+         let _ = tx.send(evicted);
+      });
+      self.storage = Storage::Compressing(image, rx);
+   }
+
+   /// Finishes evicting the chunk if its image is done being compressed.
+   pub fn poll_eviction(&mut self) {
+      if let Storage::Compressing(image, rx) = &mut self.storage {
+         let evicted = match rx.try_recv() {
+            Ok(evicted) => evicted,
+            Err(TryRecvError::Empty) => return,
+            // The compressing thread is gone, so the image has to stay as it is.
+            Err(TryRecvError::Closed) => EvictedImage::Uncompressed(Arc::clone(image)),
+         };
+         self.storage = Storage::Evicted(evicted);
+      }
+   }
+
+   /// Returns the frame the chunk was last seen on.
+   pub fn last_seen(&self) -> u64 {
+      self.last_seen
+   }
+
+   /// Remembers that the chunk was seen on the given frame.
+   pub fn mark_seen(&mut self, frame: u64) {
+      self.last_seen = frame;
+   }
+
    /// Returns the on-screen position of the chunk at the given coordinates.
    pub fn screen_position(chunk_position: (i32, i32)) -> Point {
       Point::new(
@@ -30,30 +208,54 @@ impl Chunk {
       )
    }
 
-   /// Downloads the image of the chunk from the graphics card.
+   /// Downloads the image of the chunk from the graphics card. Evicted chunks are decompressed
+   /// instead, without uploading them back.
    pub fn download_image(&self, renderer: &mut Backend) -> RgbaImage {
       let mut image_buffer =
          ImageBuffer::from_pixel(Self::SIZE.0, Self::SIZE.1, Rgba([0, 0, 0, 0]));
-      renderer.download_framebuffer(
-         &self.framebuffer,
-         (0, 0),
-         self.framebuffer.size(),
-         &mut image_buffer,
-      );
-      image_buffer
+      let decoded = match &self.storage {
+         Storage::Resident(framebuffer) => {
+            renderer.download_framebuffer(
+               framebuffer,
+               (0, 0),
+               framebuffer.size(),
+               &mut image_buffer,
+            );
+            return image_buffer;
+         }
+         Storage::Compressing(image, _) => return (**image).clone(),
+         Storage::Evicted(evicted) => evicted.decompress(),
+      };
+      // If the image can't be brought back, the best we can do is to leave the chunk empty.
+      decoded.unwrap_or_else(|error| {
+         tracing::error!("could not restore evicted chunk: {:?}", error);
+         image_buffer
+      })
    }
 
    /// Uploads the image of the chunk to the graphics card, at the given offset in the master
    /// chunk.
    pub fn upload_image(&mut self, renderer: &mut Backend, image: &RgbaImage, offset: (u32, u32)) {
       self.mark_dirty();
-      renderer.upload_framebuffer(&self.framebuffer, offset, Self::SIZE, image);
+      let framebuffer = self.resident_framebuffer(renderer);
+      renderer.upload_framebuffer(framebuffer, offset, Self::SIZE, image);
    }
 
    /// Marks the chunk as dirty - that is, invalidates any cached PNG and WebP data,
    /// and marks it as unsaved.
    pub fn mark_dirty(&mut self) {
       self.dirty = true;
+      self.revision = next_revision();
+   }
+
+   /// Returns the chunk's revision, which changes every time the chunk is marked as dirty.
+   pub fn revision(&self) -> u64 {
+      self.revision
+   }
+
+   /// Returns whether the chunk was changed since it was last saved.
+   pub fn is_dirty(&self) -> bool {
+      self.dirty
    }
 
    /// Marks the given sub-chunk within this master chunk as saved.
@@ -61,6 +263,31 @@ impl Chunk {
       self.dirty = false;
    }
 
+   /// Computes a digest of the chunk's image, for telling whether two peers see the same chunk.
+   pub fn digest(image: &RgbaImage) -> Vec<u8> {
+      let cell_size = Self::SIZE.0 / Self::DIGEST_GRID;
+      let mut sums = [[0u32; 4]; (Self::DIGEST_GRID * Self::DIGEST_GRID) as usize];
+      for (x, y, pixel) in image.enumerate_pixels() {
+         let cell = (y / cell_size * Self::DIGEST_GRID + x / cell_size) as usize;
+         for (sum, &channel) in sums[cell].iter_mut().zip(&pixel.0) {
+            *sum += channel as u32;
+         }
+      }
+      let digest: Vec<u8> = sums
+         .iter()
+         .flatten()
+         // Rounding up means that a cell is only zero if it's entirely empty.
+         .map(|&sum| sum.div_ceil(cell_size * cell_size) as u8)
+         .collect();
+      debug_assert_eq!(digest.len(), CHUNK_DIGEST_LEN);
+      digest
+   }
+
+   /// Returns whether two digests describe chunks that look the same.
+   pub fn digests_match(a: &[u8], b: &[u8]) -> bool {
+      a.len() == b.len() && a.iter().zip(b).all(|(&a, &b)| a.abs_diff(b) <= Self::DIGEST_TOLERANCE)
+   }
+
    /// Iterates through all pixels within the image and checks whether any pixels in the image are
    /// not transparent.
    pub fn image_is_empty(image: &RgbaImage) -> bool {
@@ -0,0 +1,50 @@
+//! Tests for evicting chunks from the graphics card.
+//!
+//! Evicted chunks have to be brought back intact when they're drawn or captured again, no matter
+//! where their images were kept in the meantime.
+
+use std::sync::Arc;
+
+use image::{Rgba, RgbaImage};
+
+use super::{Chunk, EvictedImage, SpilledImage};
+
+/// Returns a chunk-sized image with a different color in every pixel.
+fn pattern() -> RgbaImage {
+   RgbaImage::from_fn(Chunk::SIZE.0, Chunk::SIZE.1, |x, y| {
+      Rgba([x as u8, y as u8, (x ^ y) as u8, 255 - (x / 2) as u8])
+   })
+}
+
+#[test]
+fn evicted_chunks_are_compressed_in_memory() {
+   let image = pattern();
+   let evicted = EvictedImage::compress(Arc::new(image.clone()), None);
+   assert!(matches!(evicted, EvictedImage::Compressed(_)));
+   assert_eq!(evicted.decompress().unwrap(), image);
+}
+
+#[test]
+fn evicted_chunks_are_spilled_to_disk_and_cleaned_up() {
+   let directory = std::env::temp_dir().join(format!("netcanv-chunk-tests-{}", std::process::id()));
+   std::fs::create_dir_all(&directory).unwrap();
+   let image = pattern();
+   let evicted = EvictedImage::compress(Arc::new(image.clone()), Some(&directory));
+   let path = match &evicted {
+      EvictedImage::Spilled(SpilledImage { path }) => path.clone(),
+      _ => panic!("the chunk should have been spilled to disk"),
+   };
+   assert!(path.is_file());
+   assert_eq!(evicted.decompress().unwrap(), image);
+   // Dropping the last spilled chunk removes the disk cache along with it.
+   drop(evicted);
+   assert!(!path.exists());
+   assert!(!directory.exists());
+}
+
+#[test]
+fn uncompressed_chunks_are_kept_as_they_are() {
+   let image = pattern();
+   let evicted = EvictedImage::Uncompressed(Arc::new(image.clone()));
+   assert_eq!(evicted.decompress().unwrap(), image);
+}
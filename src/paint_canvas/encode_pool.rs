@@ -0,0 +1,94 @@
+//! A pool of worker threads encoding chunk images for sending over the network.
+//!
+//! Encoding a chunk takes a lot longer than downloading its image from the graphics card, so
+//! sending a large region at once used to pile up hundreds of blocking tasks at the same time.
+//! The pool keeps the number of encodes in flight down to the number of CPU cores, and queues
+//! up the rest.
+
+use std::sync::{mpsc, Arc, Mutex};
+use std::thread;
+
+use image::RgbaImage;
+use netcanv_protocol::client::ChunkEncoding;
+use tokio::sync::mpsc::UnboundedSender;
+
+use super::cache_layer::CachedChunk;
+use super::LayerChunk;
+use crate::image_coder::ImageCoder;
+
+/// A chunk image waiting to be encoded.
+pub struct EncodeJob {
+   pub chunk: LayerChunk,
+   pub image: RgbaImage,
+   /// The revision of the chunk the image was downloaded from.
+   pub revision: u64,
+   pub encoding: ChunkEncoding,
+   /// The channels the encoded images are sent to once they're ready. These are drained by the
+   /// paint state, on the main thread.
+   pub completion: Vec<UnboundedSender<(LayerChunk, CachedChunk)>>,
+}
+
+pub struct EncodePool {
+   jobs: mpsc::Sender<EncodeJob>,
+}
+
+impl EncodePool {
+   /// Spawns the worker threads. One core is left for the main thread, as long as there's more
+   /// than one.
+   pub fn new() -> Self {
+      let (jobs, rx) = mpsc::channel();
+      let rx = Arc::new(Mutex::new(rx));
+      let worker_count =
+         thread::available_parallelism().map(|count| count.get() - 1).unwrap_or(1).max(1);
+      for index in 0..worker_count {
+         let rx = Arc::clone(&rx);
+         let spawned = thread::Builder::new()
+            .name(format!("chunk encoder {index}"))
+            .spawn(move || Self::work(&rx));
+         if let Err(error) = spawned {
+            tracing::error!("could not spawn chunk encoder thread: {:?}", error);
+         }
+      }
+      Self { jobs }
+   }
+
+   /// Processes jobs until the pool is dropped.
+   fn work(rx: &Mutex<mpsc::Receiver<EncodeJob>>) {
+      loop {
+         // The lock is released as soon as a job is received, so that other workers can pick up
+         // the next one while this one is being encoded.
+         let job = match rx.lock() {
+            Ok(rx) => rx.recv(),
+            Err(_) => return,
+         };
+         let Ok(job) = job else {
+            return;
+         };
+         tracing::debug!("encoding image data for chunk {:?}", job.chunk);
+         match ImageCoder::encode_network_data(job.image, job.revision, job.encoding) {
+            Ok(data) => {
+               // Doesn't matter if the receiving halves are closed.
+               for tx in &job.completion {
+                  let _ = tx.send((job.chunk, data.clone()));
+               }
+            }
+            Err(error) => {
+               tracing::error!(
+                  "error while encoding image for chunk {:?}: {:?}",
+                  job.chunk,
+                  error
+               );
+            }
+         }
+      }
+   }
+
+   /// Queues up a chunk image for encoding.
+   pub fn submit(&self, job: EncodeJob) {
+      // The workers only stop once the pool is dropped, so this can't fail while the pool is
+      // around, unless every worker failed to spawn.
+      if self.jobs.send(job).is_err() {
+         tracing::error!("no chunk encoder threads are running");
+      }
+   }
+}
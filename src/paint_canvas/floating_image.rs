@@ -0,0 +1,75 @@
+//! Floating images. A floating image sits above the canvas's chunks and can be moved around and
+//! resized freely, until it's flattened into the chunks underneath it.
+
+use image::RgbaImage;
+use netcanv_protocol::client::ImagePlacement;
+use netcanv_renderer::paws::{point, vector, Color, Rect, Renderer};
+use netcanv_renderer::RenderBackend;
+
+use crate::backend::{Backend, Image};
+use crate::image_coder::ImageCoder;
+
+/// An image placed above the canvas.
+pub struct FloatingImage {
+   pub placement: ImagePlacement,
+   /// The image encoded as PNG, the same way it's sent over the network.
+   data: Vec<u8>,
+   /// The image on the graphics card. This is `None` until the data is decoded.
+   image: Option<Image>,
+}
+
+impl FloatingImage {
+   /// Creates a floating image out of PNG data. The image is not decoded right away; this has to
+   /// be done separately, and the result passed to [`FloatingImage::upload`].
+   pub fn new(placement: ImagePlacement, data: Vec<u8>) -> Self {
+      Self {
+         placement,
+         data,
+         image: None,
+      }
+   }
+
+   /// Returns the PNG data of the image.
+   pub fn data(&self) -> &[u8] {
+      &self.data
+   }
+
+   /// Returns whether the decoded image has been uploaded to the graphics card already.
+   pub fn is_uploaded(&self) -> bool {
+      self.image.is_some()
+   }
+
+   /// Uploads the decoded image to the graphics card.
+   pub fn upload(&mut self, renderer: &mut Backend, image: &RgbaImage) {
+      self.image = Some(renderer.create_image_from_rgba(image.width(), image.height(), image));
+   }
+
+   /// Decodes the image and uploads it right away, if that hasn't been done yet.
+   pub(super) fn ensure_uploaded(&mut self, renderer: &mut Backend) -> netcanv::Result<()> {
+      if self.image.is_none() {
+         let image = ImageCoder::decode_png_data(&self.data)?;
+         self.upload(renderer, &image);
+      }
+      Ok(())
+   }
+
+   /// Returns the rectangle the image covers, in pixels.
+   pub fn rect(&self) -> Rect {
+      let ImagePlacement {
+         position: (x, y),
+         size: (width, height),
+      } = self.placement;
+      Rect::new(
+         point(x as f32, y as f32),
+         vector(width as f32, height as f32),
+      )
+   }
+
+   /// Draws the image, or a placeholder if it hasn't been decoded yet.
+   pub fn draw(&self, renderer: &mut Backend) {
+      match &self.image {
+         Some(image) => renderer.image(self.rect(), image),
+         None => renderer.fill(self.rect(), Color::BLACK.with_alpha(32), 0.0),
+      }
+   }
+}
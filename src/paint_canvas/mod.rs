@@ -1,35 +1,231 @@
 //! NetCanv's infinite paint canvas.
 
+pub mod attribution;
 pub mod cache_layer;
 pub mod chunk;
+pub mod encode_pool;
+pub mod floating_image;
+pub mod operation_log;
+pub mod provenance;
+pub mod stroke_layer;
+pub mod vector_log;
 
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::path::PathBuf;
 
 use image::RgbaImage;
-use netcanv_renderer::paws::{vector, Color, Rect, Renderer, Vector};
-use netcanv_renderer::{Framebuffer as FramebufferTrait, RenderBackend};
+use netcanv_protocol::client::{
+   Background, FloatingImageId, ImagePlacement, Layer, NoteId, StickyNote, VectorStroke,
+   VectorStrokeId, MAX_FLOATING_IMAGES, MAX_NOTES,
+};
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, vector, Color, Point, Rect, Renderer, Vector};
+use netcanv_renderer::{BlendMode, Framebuffer as FramebufferTrait, RenderBackend};
+use web_time::Duration;
 
 use crate::backend::{Backend, Framebuffer};
+use crate::config::config;
 use crate::viewport::Viewport;
+use attribution::Attribution;
 use chunk::Chunk;
+use floating_image::FloatingImage;
+use operation_log::{ChunkChange, OperationLog};
+use provenance::Provenance;
+use vector_log::{StrokeStyle, VectorLog};
 
-/// A paint canvas built out of [`Chunk`]s.
-pub struct PaintCanvas {
+/// A chunk on one of the canvas's layers, identified by the layer's index and the chunk's position.
+pub type LayerChunk = (usize, (i32, i32));
+
+/// A rectangular region of chunks. Both corners are inclusive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChunkRegion {
+   pub top_left: (i32, i32),
+   pub bottom_right: (i32, i32),
+}
+
+impl ChunkRegion {
+   /// Creates a region spanning between the two chunk positions, given in any order.
+   pub fn new(a: (i32, i32), b: (i32, i32)) -> Self {
+      Self {
+         top_left: (a.0.min(b.0), a.1.min(b.1)),
+         bottom_right: (a.0.max(b.0), a.1.max(b.1)),
+      }
+   }
+
+   /// Returns whether the region contains the chunk at the given position.
+   pub fn contains(&self, chunk_position: (i32, i32)) -> bool {
+      let (x, y) = chunk_position;
+      x >= self.top_left.0
+         && x <= self.bottom_right.0
+         && y >= self.top_left.1
+         && y <= self.bottom_right.1
+   }
+
+   /// Returns the rectangle covered by this region, in pixels.
+   pub fn rect(&self) -> Rect {
+      let top_left = Chunk::screen_position(self.top_left);
+      let bottom_right = Chunk::screen_position((self.bottom_right.0 + 1, self.bottom_right.1 + 1));
+      Rect::new(top_left, bottom_right - top_left)
+   }
+}
+
+impl From<((i32, i32), (i32, i32))> for ChunkRegion {
+   fn from((top_left, bottom_right): ((i32, i32), (i32, i32))) -> Self {
+      Self::new(top_left, bottom_right)
+   }
+}
+
+impl From<ChunkRegion> for ((i32, i32), (i32, i32)) {
+   fn from(region: ChunkRegion) -> Self {
+      (region.top_left, region.bottom_right)
+   }
+}
+
+/// The chunks and history of a single layer.
+#[derive(Default)]
+struct LayerContents {
    chunks: HashMap<(i32, i32), Chunk>,
+   provenance: Option<Provenance>,
+   operations: Option<OperationLog>,
+}
+
+impl LayerContents {
+   /// Starts or stops keeping track of the layer's provenance and operations. History that's
+   /// already kept is left alone.
+   fn set_tracking(&mut self, track_provenance: bool, track_operations: bool) {
+      if track_provenance != self.provenance.is_some() {
+         self.provenance = track_provenance.then(Provenance::new);
+      }
+      if track_operations != self.operations.is_some() {
+         self.operations = track_operations.then(OperationLog::new);
+      }
+   }
+}
+
+/// The chunks and history of a page that isn't currently shown on a [`PaintCanvas`].
+#[derive(Default)]
+pub struct CanvasPage {
+   layers: Vec<LayerContents>,
+   attribution: Attribution,
+}
+
+impl CanvasPage {
+   /// Returns an iterator over the chunks of all of the page's layers.
+   pub fn chunks(&self) -> impl Iterator<Item = &Chunk> {
+      self.layers.iter().flat_map(|contents| contents.chunks.values())
+   }
+
+   /// Evicts up to `count` of the page's chunks from the graphics card, and returns how many were
+   /// evicted. A page that isn't shown is never drawn, so all of its chunks can go.
+   pub fn evict(&mut self, renderer: &mut Backend, count: usize) -> usize {
+      let disk_cache = PaintCanvas::disk_cache_directory();
+      let mut evicted = 0;
+      for chunk in self.layers.iter_mut().flat_map(|contents| contents.chunks.values_mut()) {
+         chunk.poll_eviction();
+         if evicted < count && chunk.framebuffer().is_some() {
+            chunk.evict(renderer, disk_cache.as_deref());
+            evicted += 1;
+         }
+      }
+      evicted
+   }
+}
+
+/// A paint canvas built out of [`Chunk`]s, stacked in layers.
+pub struct PaintCanvas {
+   /// The canvas's layers, bottom first.
+   layers: Vec<Layer>,
+   /// The chunks and history of each layer, in the same order as `layers`.
+   layer_contents: Vec<LayerContents>,
+   /// The layer that subsequent draws go onto.
+   current_layer: usize,
+
+   locked_regions: Vec<ChunkRegion>,
+   enforce_locks: bool,
+   notes: BTreeMap<NoteId, StickyNote>,
+   floating_images: BTreeMap<FloatingImageId, FloatingImage>,
+   paused: bool,
+   /// The size of the canvas in pixels, or `None` if it's infinite.
+   bounds: Option<(u32, u32)>,
+   background: Background,
+
+   /// The peer that subsequent draws are attributed to, or `None` if it's ourselves.
+   author: Option<PeerId>,
+   attribution: Attribution,
+   track_provenance: bool,
+   track_operations: bool,
+   /// The chunks we drew to since our last commit.
+   uncommitted_chunks: Vec<(i32, i32)>,
+   /// The layers and chunks of our operations committed since they were last taken.
+   own_operations: Vec<(usize, Vec<(i32, i32)>)>,
+   /// Whether we committed an operation that the host hasn't been told about yet.
+   commit_pending: bool,
+   /// The room's vector strokes, or `None` if the room doesn't keep them.
+   vector_log: Option<VectorLog>,
+   /// Vector strokes removed by the last undo, which everyone else should remove too.
+   removed_vector_strokes: Vec<VectorStrokeId>,
+   /// Whether vector strokes were put back by a redo, and everyone else should be sent them.
+   vector_strokes_restored: bool,
+   /// The vector strokes rendered at the viewport's resolution, shown above the chunks when
+   /// zoomed in.
+   vector_overlay: Option<Framebuffer>,
+   vector_overlay_visible: bool,
+   /// Counts up every time chunk residency is updated, which chunks use to remember when they were
+   /// last seen.
+   frame: u64,
 }
 
 impl PaintCanvas {
+   /// The maximum number of chunks evicted from the graphics card in a single frame.
+   pub const MAX_EVICTIONS_PER_FRAME: usize = 4;
+
    /// Creates a new, empty paint canvas.
    pub fn new() -> Self {
       Self {
-         chunks: HashMap::new(),
+         layers: vec![Self::default_layer()],
+         layer_contents: vec![LayerContents::default()],
+         current_layer: 0,
+         locked_regions: Vec::new(),
+         enforce_locks: false,
+         notes: BTreeMap::new(),
+         floating_images: BTreeMap::new(),
+         paused: false,
+         bounds: None,
+         background: Background::Blank,
+         author: None,
+         attribution: Attribution::new(),
+         track_provenance: false,
+         track_operations: false,
+         uncommitted_chunks: Vec::new(),
+         own_operations: Vec::new(),
+         commit_pending: false,
+         vector_log: None,
+         removed_vector_strokes: Vec::new(),
+         vector_strokes_restored: false,
+         vector_overlay: None,
+         vector_overlay_visible: false,
+         frame: 0,
       }
    }
 
-   /// Creates the chunk at the given position, if it doesn't already exist.
+   /// Returns the position of the chunk containing the given point (in pixels).
+   pub fn chunk_position_at(point: Point) -> (i32, i32) {
+      (
+         (point.x / Chunk::SIZE.0 as f32).floor() as i32,
+         (point.y / Chunk::SIZE.1 as f32).floor() as i32,
+      )
+   }
+
+   /// Creates the chunk at the given position on the given layer, if it doesn't already exist.
+   /// The layer must exist.
    #[must_use]
-   pub fn ensure_chunk(&mut self, renderer: &mut Backend, position: (i32, i32)) -> &mut Chunk {
-      self.chunks.entry(position).or_insert_with(|| Chunk::new(renderer))
+   pub fn ensure_chunk(
+      &mut self,
+      renderer: &mut Backend,
+      layer: usize,
+      position: (i32, i32),
+   ) -> &mut Chunk {
+      self.layer_contents[layer].chunks.entry(position).or_insert_with(|| Chunk::new(renderer))
    }
 
    /// Returns the left, top, bottom, right sides covered by the rectangle, in chunk
@@ -51,26 +247,55 @@ impl PaintCanvas {
    /// are expressed in _pixels_ rather than _chunks_.
    ///
    /// The callback may be called multiple times, once for each chunk being drawn to.
+   ///
+   /// Everything is drawn onto the current layer. Nothing is drawn while the canvas is paused, and
+   /// anything drawn outside of the canvas's bounds is cleared away.
    pub fn draw(
       &mut self,
       renderer: &mut Backend,
       coverage: Rect,
       mut callback: impl FnMut(&mut Backend),
    ) {
+      if self.paused {
+         return;
+      }
       let (left, top, bottom, right) = Self::chunk_coverage(coverage);
       assert!(left <= right);
       assert!(top <= bottom);
+      let bounds = self.bounds_rect();
       for y in top..=bottom {
          for x in left..=right {
             let chunk_position = (x, y);
-            let chunk = self.ensure_chunk(renderer, chunk_position);
+            if self.enforce_locks && self.is_chunk_locked(chunk_position) {
+               continue;
+            }
+            if !self.is_chunk_in_bounds(chunk_position) {
+               continue;
+            }
+            let contents = &mut self.layer_contents[self.current_layer];
+            let chunk =
+               contents.chunks.entry(chunk_position).or_insert_with(|| Chunk::new(renderer));
+            self.attribution.record(self.author, chunk_position);
+            if let (Some(provenance), Some(author)) = (&mut contents.provenance, self.author) {
+               provenance.record(renderer, author, chunk_position, chunk);
+            }
+            if let Some(operations) = &mut contents.operations {
+               operations.record(renderer, self.author, chunk_position, chunk);
+            }
+            if self.author.is_none() && !self.uncommitted_chunks.contains(&chunk_position) {
+               self.uncommitted_chunks.push(chunk_position);
+            }
+            let framebuffer = chunk.resident_framebuffer(renderer);
             renderer.push();
             renderer.translate(vector(
                -x as f32 * Chunk::SIZE.0 as f32,
                -y as f32 * Chunk::SIZE.0 as f32,
             ));
-            renderer.draw_to(&chunk.framebuffer, |renderer| {
+            renderer.draw_to(framebuffer, |renderer| {
                callback(renderer);
+               if let Some(bounds) = bounds {
+                  Self::clear_outside_bounds(renderer, chunk_position, bounds);
+               }
             });
             renderer.pop();
             chunk.mark_dirty();
@@ -78,73 +303,916 @@ impl PaintCanvas {
       }
    }
 
-   /// Captures a fragment of the paint canvas onto a framebuffer.
-   pub fn capture(&self, renderer: &mut Backend, framebuffer: &Framebuffer, viewport: &Viewport) {
+   /// Clears the parts of the chunk at the given position that lie outside of the bounds. This must
+   /// be called while drawing to the chunk, in the canvas's pixel coordinates.
+   fn clear_outside_bounds(renderer: &mut Backend, chunk_position: (i32, i32), bounds: Rect) {
+      let top_left = Chunk::screen_position(chunk_position);
+      let bottom_right = Chunk::screen_position((chunk_position.0 + 1, chunk_position.1 + 1));
+      let (left, top) = (top_left.x, top_left.y);
+      let (right, bottom) = (bottom_right.x, bottom_right.y);
+      let outside = [
+         (left, top, bounds.left().clamp(left, right), bottom),
+         (bounds.right().clamp(left, right), top, right, bottom),
+         (left, top, right, bounds.top().clamp(top, bottom)),
+         (left, bounds.bottom().clamp(top, bottom), right, bottom),
+      ];
+      renderer.push();
+      renderer.set_blend_mode(BlendMode::Replace);
+      for (left, top, right, bottom) in outside {
+         if right > left && bottom > top {
+            renderer.fill(
+               Rect::new(point(left, top), vector(right - left, bottom - top)),
+               Color::TRANSPARENT,
+               0.0,
+            );
+         }
+      }
+      renderer.pop();
+   }
+
+   /// Captures a fragment of the current layer onto a framebuffer.
+   ///
+   /// Unlike [`PaintCanvas::draw_to`], the other layers are left out, and the layer's visibility
+   /// and opacity are ignored, such that drawing the capture back onto the layer leaves it intact.
+   ///
+   /// The captured fragment may lie off-screen, e.g. when it's captured by someone else, so evicted
+   /// chunks are uploaded back to the graphics card first.
+   pub fn capture(
+      &mut self,
+      renderer: &mut Backend,
+      framebuffer: &Framebuffer,
+      viewport: &Viewport,
+   ) {
+      let size = vector(framebuffer.width() as f32, framebuffer.height() as f32);
+      let chunk_positions: Vec<_> = viewport.visible_tiles(Chunk::SIZE, size).collect();
+      self.make_resident(renderer, self.current_layer, &chunk_positions);
+      let contents = &self.layer_contents[self.current_layer];
       renderer.draw_to(framebuffer, |renderer| {
-         self.draw_to(
-            renderer,
-            viewport,
-            vector(framebuffer.width() as f32, framebuffer.height() as f32),
-         );
+         for chunk_position in chunk_positions {
+            if let Some(framebuffer) =
+               contents.chunks.get(&chunk_position).and_then(Chunk::framebuffer)
+            {
+               let screen_position = Chunk::screen_position(chunk_position);
+               renderer.framebuffer(framebuffer.rect(screen_position), framebuffer);
+            }
+         }
+      });
+   }
+
+   /// Uploads the given chunks on the given layer back to the graphics card, if they were evicted.
+   fn make_resident(
+      &mut self,
+      renderer: &mut Backend,
+      layer: usize,
+      chunk_positions: &[(i32, i32)],
+   ) {
+      let Some(contents) = self.layer_contents.get_mut(layer) else {
+         return;
+      };
+      for chunk_position in chunk_positions {
+         if let Some(chunk) = contents.chunks.get_mut(chunk_position) {
+            let _ = chunk.resident_framebuffer(renderer);
+            chunk.mark_seen(self.frame);
+         }
+      }
+   }
+
+   /// Renders a small preview of the given chunks on the given layer, scaled down to fit a square
+   /// framebuffer of the given size.
+   pub fn thumbnail(
+      &mut self,
+      renderer: &mut Backend,
+      layer: usize,
+      chunk_positions: &[(i32, i32)],
+      size: u32,
+   ) -> Framebuffer {
+      let framebuffer = renderer.create_framebuffer(size, size);
+      let (Some(left), Some(top), Some(right), Some(bottom)) = (
+         chunk_positions.iter().map(|&(x, _)| x).min(),
+         chunk_positions.iter().map(|&(_, y)| y).min(),
+         chunk_positions.iter().map(|&(x, _)| x).max(),
+         chunk_positions.iter().map(|&(_, y)| y).max(),
+      ) else {
+         return framebuffer;
+      };
+      self.make_resident(renderer, layer, chunk_positions);
+      let Some(contents) = self.layer_contents.get(layer) else {
+         return framebuffer;
+      };
+      let top_left = Chunk::screen_position((left, top));
+      let extent = Chunk::screen_position((right + 1, bottom + 1)) - top_left;
+      let scale = size as f32 / extent.x.max(extent.y);
+      // Center the chunks, as they don't necessarily form a square.
+      let offset = (vector(size as f32, size as f32) / scale - extent) / 2.0;
+      renderer.push();
+      renderer.scale(vector(scale, scale));
+      renderer.translate(offset - top_left);
+      renderer.draw_to(&framebuffer, |renderer| {
+         for chunk_position in chunk_positions {
+            if let Some(framebuffer) =
+               contents.chunks.get(chunk_position).and_then(Chunk::framebuffer)
+            {
+               let screen_position = Chunk::screen_position(*chunk_position);
+               renderer.framebuffer(framebuffer.rect(screen_position), framebuffer);
+            }
+         }
       });
+      renderer.pop();
+      framebuffer
    }
 
-   /// Downloads the color of the pixel at the provided position.
+   /// Downloads the color of the pixel at the provided position, as seen with all the visible
+   /// layers blended together.
    pub fn get_pixel(&self, renderer: &mut Backend, position: (i64, i64)) -> Color {
-      if let Some(chunk) = self.chunks.get(&(
+      let chunk_position = (
          (position.0.div_euclid(Chunk::SIZE.0 as i64)) as i32,
          (position.1.div_euclid(Chunk::SIZE.1 as i64)) as i32,
-      )) {
-         let position_in_chunk = (
-            (position.0.rem_euclid(Chunk::SIZE.0 as i64)) as u32,
-            (position.1.rem_euclid(Chunk::SIZE.1 as i64)) as u32,
-         );
-         let mut rgba = [0u8; 4];
-         renderer.download_framebuffer(&chunk.framebuffer, position_in_chunk, (1, 1), &mut rgba);
-         let [r, g, b, a] = rgba;
-         Color { r, g, b, a }
-      } else {
-         Color::TRANSPARENT
+      );
+      let position_in_chunk = (
+         (position.0.rem_euclid(Chunk::SIZE.0 as i64)) as u32,
+         (position.1.rem_euclid(Chunk::SIZE.1 as i64)) as u32,
+      );
+      let mut color = Color::TRANSPARENT;
+      for (layer, contents) in self.layers.iter().zip(&self.layer_contents) {
+         if !layer.visible {
+            continue;
+         }
+         if let Some(chunk) = contents.chunks.get(&chunk_position) {
+            let mut rgba = [0u8; 4];
+            match chunk.framebuffer() {
+               Some(framebuffer) => {
+                  renderer.download_framebuffer(framebuffer, position_in_chunk, (1, 1), &mut rgba)
+               }
+               None => {
+                  let (x, y) = position_in_chunk;
+                  rgba = chunk.download_image(renderer).get_pixel(x, y).0;
+               }
+            }
+            let [r, g, b, a] = rgba;
+            let a = (a as u32 * layer.opacity as u32 / 255) as u8;
+            color = Self::blend_over(color, Color { r, g, b, a });
+         }
+      }
+      color
+   }
+
+   /// Returns the positions of the chunks on all the visible layers.
+   pub fn visible_chunk_positions(&self) -> HashSet<(i32, i32)> {
+      self
+         .layers
+         .iter()
+         .zip(&self.layer_contents)
+         .filter(|(layer, _)| layer.visible)
+         .flat_map(|(_, contents)| contents.chunks.keys().copied())
+         .collect()
+   }
+
+   /// Downloads the image of the chunk at the given position, with all the visible layers blended
+   /// together. Returns `None` if none of the visible layers has a chunk there.
+   pub fn flattened_chunk_image(
+      &self,
+      renderer: &mut Backend,
+      chunk_position: (i32, i32),
+   ) -> Option<RgbaImage> {
+      let mut flattened: Option<RgbaImage> = None;
+      for (layer, contents) in self.layers.iter().zip(&self.layer_contents) {
+         if !layer.visible {
+            continue;
+         }
+         let Some(chunk) = contents.chunks.get(&chunk_position) else {
+            continue;
+         };
+         let mut image = chunk.download_image(renderer);
+         if layer.opacity != 255 {
+            for pixel in image.pixels_mut() {
+               pixel[3] = (pixel[3] as u32 * layer.opacity as u32 / 255) as u8;
+            }
+         }
+         if let Some(below) = &mut flattened {
+            for (below, above) in below.pixels_mut().zip(image.pixels()) {
+               let [r, g, b, a] = below.0;
+               let below_color = Color { r, g, b, a };
+               let [r, g, b, a] = above.0;
+               let color = Self::blend_over(below_color, Color { r, g, b, a });
+               below.0 = [color.r, color.g, color.b, color.a];
+            }
+         } else {
+            flattened = Some(image);
+         }
       }
+      flattened
    }
 
-   /// Draws the paint canvas using the given renderer.
+   /// Blends the `above` color over the `below` color, the same way the renderer blends layers.
+   fn blend_over(below: Color, above: Color) -> Color {
+      let above_alpha = above.a as f32 / 255.0;
+      let below_alpha = below.a as f32 / 255.0 * (1.0 - above_alpha);
+      let alpha = above_alpha + below_alpha;
+      if alpha <= 0.0 {
+         return Color::TRANSPARENT;
+      }
+      let channel = |above: u8, below: u8| {
+         ((above as f32 * above_alpha + below as f32 * below_alpha) / alpha).round() as u8
+      };
+      Color {
+         r: channel(above.r, below.r),
+         g: channel(above.g, below.g),
+         b: channel(above.b, below.b),
+         a: (alpha * 255.0).round() as u8,
+      }
+   }
+
+   /// Draws the paint canvas using the given renderer. Visible layers are drawn bottom first, with
+   /// their opacity applied.
    ///
    /// The provided viewport and window size are used to only render chunks that are visible at a
    /// given moment.
    pub fn draw_to(&self, renderer: &mut Backend, viewport: &Viewport, window_size: Vector) {
-      for chunk_position in viewport.visible_tiles(Chunk::SIZE, window_size) {
-         if let Some(chunk) = self.chunks.get(&chunk_position) {
-            let screen_position = Chunk::screen_position(chunk_position);
-            renderer.framebuffer(chunk.framebuffer.rect(screen_position), &chunk.framebuffer);
+      for (layer, contents) in self.layers.iter().zip(&self.layer_contents) {
+         if !layer.visible || layer.opacity == 0 {
+            continue;
+         }
+         let opacity = layer.opacity as f32 / 255.0;
+         for chunk_position in viewport.visible_tiles(Chunk::SIZE, window_size) {
+            // Visible chunks are made resident in `update_residency`, so this only skips chunks
+            // if the viewport changed since.
+            if let Some(framebuffer) =
+               contents.chunks.get(&chunk_position).and_then(Chunk::framebuffer)
+            {
+               let rect = framebuffer.rect(Chunk::screen_position(chunk_position));
+               renderer.translucent_framebuffer(rect, framebuffer, opacity);
+            }
          }
       }
    }
 
+   /// Uploads an image to the chunk at the given position on the given layer. Nothing happens if
+   /// the layer doesn't exist.
    pub fn set_chunk(
       &mut self,
       renderer: &mut Backend,
+      layer: usize,
       chunk_position: (i32, i32),
       image: RgbaImage,
    ) {
-      let chunk = self.ensure_chunk(renderer, chunk_position);
-      chunk.upload_image(renderer, &image, (0, 0));
+      if layer < self.layer_contents.len() {
+         let chunk = self.ensure_chunk(renderer, layer, chunk_position);
+         chunk.upload_image(renderer, &image, (0, 0));
+      }
+   }
+
+   /// Returns the chunks of the given layer, which must exist.
+   pub fn chunks(&self, layer: usize) -> &HashMap<(i32, i32), Chunk> {
+      &self.layer_contents[layer].chunks
+   }
+
+   /// Returns the chunks of the given layer, which must exist.
+   pub fn chunks_mut(&mut self, layer: usize) -> &mut HashMap<(i32, i32), Chunk> {
+      &mut self.layer_contents[layer].chunks
+   }
+
+   /// Returns an iterator over the chunks of all layers, along with their layers and positions.
+   pub fn all_chunks(&self) -> impl Iterator<Item = (usize, (i32, i32), &Chunk)> {
+      self.layer_contents.iter().enumerate().flat_map(|(layer, contents)| {
+         contents.chunks.iter().map(move |(&position, chunk)| (layer, position, chunk))
+      })
+   }
+
+   /// Returns a mutable iterator over the chunks of all layers, along with their layers and
+   /// positions.
+   pub fn all_chunks_mut(&mut self) -> impl Iterator<Item = (usize, (i32, i32), &mut Chunk)> {
+      self.layer_contents.iter_mut().enumerate().flat_map(|(layer, contents)| {
+         contents.chunks.iter_mut().map(move |(&position, chunk)| (layer, position, chunk))
+      })
+   }
+
+   /// Swaps the canvas's chunks, history and attribution with the given page's. Whether
+   /// provenance and operations are tracked stays the same, and the page gets as many layers as
+   /// the canvas has.
+   pub fn swap_page(&mut self, page: &mut CanvasPage) {
+      std::mem::swap(&mut self.layer_contents, &mut page.layers);
+      std::mem::swap(&mut self.attribution, &mut page.attribution);
+      self.sync_layer_contents();
+   }
+
+   /// Returns a vector containing all the chunk positions on the given layer, which is empty if
+   /// the layer doesn't exist.
+   pub fn chunk_positions(&self, layer: usize) -> Vec<(i32, i32)> {
+      match self.layer_contents.get(layer) {
+         Some(contents) => contents.chunks.keys().copied().collect(),
+         None => Vec::new(),
+      }
+   }
+
+   pub fn chunk(&self, layer: usize, position: (i32, i32)) -> Option<&Chunk> {
+      self.layer_contents.get(layer)?.chunks.get(&position)
+   }
+
+   /// Returns the canvas's layers, bottom first.
+   pub fn layers(&self) -> &[Layer] {
+      &self.layers
+   }
+
+   /// Returns the layer new canvases start out with: unnamed, visible, and fully opaque.
+   pub fn default_layer() -> Layer {
+      Layer {
+         name: String::new(),
+         visible: true,
+         opacity: 255,
+      }
+   }
+
+   /// Returns whether the canvas still only has the layer it started out with.
+   pub fn has_default_layers(&self) -> bool {
+      self.layers == [Self::default_layer()]
+   }
+
+   /// Replaces the canvas's layers. Layers past the end of the new list are removed along with
+   /// their chunks, and new layers start out empty. An empty list is ignored, as there must
+   /// always be at least one layer.
+   pub fn set_layers(&mut self, layers: Vec<Layer>) {
+      if layers.is_empty() {
+         return;
+      }
+      self.layers = layers;
+      self.sync_layer_contents();
+      self.current_layer = self.current_layer.min(self.layers.len() - 1);
+   }
+
+   /// Returns the layer that subsequent draws go onto.
+   pub fn current_layer(&self) -> usize {
+      self.current_layer
+   }
+
+   /// Sets the layer that subsequent calls to [`PaintCanvas::draw`] go onto, as well as whose
+   /// history is undone and redone. Layers that don't exist fall back to the topmost one.
+   pub fn set_current_layer(&mut self, layer: usize) {
+      self.current_layer = layer.min(self.layers.len() - 1);
+   }
+
+   /// Makes sure there are contents for every layer, and that they all keep track of their
+   /// history like the canvas is set to.
+   fn sync_layer_contents(&mut self) {
+      let (track_provenance, track_operations) = (self.track_provenance, self.track_operations);
+      self.layer_contents.resize_with(self.layers.len(), LayerContents::default);
+      for contents in &mut self.layer_contents {
+         contents.set_tracking(track_provenance, track_operations);
+      }
+   }
+
+   /// Returns the regions of the canvas that are currently locked.
+   pub fn locked_regions(&self) -> &[ChunkRegion] {
+      &self.locked_regions
+   }
+
+   /// Replaces the list of locked regions.
+   pub fn set_locked_regions(&mut self, regions: Vec<ChunkRegion>) {
+      self.locked_regions = regions;
+   }
+
+   /// Locks the given region.
+   pub fn lock_region(&mut self, region: ChunkRegion) {
+      self.locked_regions.push(region);
+   }
+
+   /// Unlocks all regions containing the chunk at the given position. Returns whether any
+   /// regions were unlocked.
+   pub fn unlock_regions_at(&mut self, chunk_position: (i32, i32)) -> bool {
+      let count = self.locked_regions.len();
+      self.locked_regions.retain(|region| !region.contains(chunk_position));
+      self.locked_regions.len() != count
+   }
+
+   /// Returns whether the chunk at the given position lies within a locked region.
+   pub fn is_chunk_locked(&self, chunk_position: (i32, i32)) -> bool {
+      self.locked_regions.iter().any(|region| region.contains(chunk_position))
+   }
+
+   /// Returns the size of the canvas in pixels, or `None` if it's infinite.
+   pub fn bounds(&self) -> Option<(u32, u32)> {
+      self.bounds
+   }
+
+   /// Fixes the canvas to the given size in pixels, centered on the origin, or makes it infinite
+   /// again. Anything drawn outside of the new bounds beforehand is kept, but is left out of
+   /// exports.
+   pub fn set_bounds(&mut self, bounds: Option<(u32, u32)>) {
+      self.bounds = bounds;
+   }
+
+   /// Returns the rectangle covered by the canvas, in pixels, or `None` if it's infinite.
+   pub fn bounds_rect(&self) -> Option<Rect> {
+      let (width, height) = self.bounds?;
+      let (width, height) = (width as f32, height as f32);
+      Some(Rect::new(
+         point(-(width / 2.0).floor(), -(height / 2.0).floor()),
+         vector(width, height),
+      ))
+   }
+
+   /// Returns whether any part of the chunk at the given position lies within the canvas's bounds.
+   pub fn is_chunk_in_bounds(&self, chunk_position: (i32, i32)) -> bool {
+      let Some(bounds) = self.bounds_rect() else {
+         return true;
+      };
+      let top_left = Chunk::screen_position(chunk_position);
+      let bottom_right = Chunk::screen_position((chunk_position.0 + 1, chunk_position.1 + 1));
+      top_left.x < bounds.right()
+         && top_left.y < bounds.bottom()
+         && bottom_right.x > bounds.left()
+         && bottom_right.y > bounds.top()
+   }
+
+   /// Returns the pattern drawn beneath the canvas's chunks.
+   pub fn background(&self) -> Background {
+      self.background
+   }
+
+   /// Sets the pattern drawn beneath the canvas's chunks.
+   pub fn set_background(&mut self, background: Background) {
+      self.background = background;
+   }
+
+   /// Returns the sticky notes on the canvas, ordered by their IDs.
+   pub fn notes(&self) -> &BTreeMap<NoteId, StickyNote> {
+      &self.notes
+   }
+
+   /// Adds a sticky note, or replaces the one with the same ID. New notes are dropped once the
+   /// canvas has [`MAX_NOTES`] of them.
+   pub fn set_note(&mut self, id: NoteId, note: StickyNote) {
+      if self.notes.len() < MAX_NOTES || self.notes.contains_key(&id) {
+         self.notes.insert(id, note);
+      }
+   }
+
+   /// Removes a sticky note. Returns whether the note existed.
+   pub fn remove_note(&mut self, id: NoteId) -> bool {
+      self.notes.remove(&id).is_some()
+   }
+
+   /// Replaces all the sticky notes on the canvas.
+   pub fn set_notes(&mut self, notes: impl IntoIterator<Item = (NoteId, StickyNote)>) {
+      self.notes = notes.into_iter().take(MAX_NOTES).collect();
+   }
+
+   /// Returns an unused note ID for a note created by the given peer.
+   pub fn next_note_id(&self, creator: Option<PeerId>) -> NoteId {
+      Self::next_object_id(&self.notes, creator)
+   }
+
+   /// Returns the floating images above the canvas, ordered by their IDs.
+   pub fn floating_images(&self) -> &BTreeMap<FloatingImageId, FloatingImage> {
+      &self.floating_images
+   }
+
+   /// Returns a mutable reference to the floating image with the given ID.
+   pub fn floating_image_mut(&mut self, id: FloatingImageId) -> Option<&mut FloatingImage> {
+      self.floating_images.get_mut(&id)
+   }
+
+   /// Adds a floating image out of PNG data. New images are dropped once the canvas has
+   /// [`MAX_FLOATING_IMAGES`] of them.
+   pub fn add_floating_image(
+      &mut self,
+      id: FloatingImageId,
+      placement: ImagePlacement,
+      data: Vec<u8>,
+   ) -> Option<&mut FloatingImage> {
+      if self.floating_images.len() >= MAX_FLOATING_IMAGES
+         && !self.floating_images.contains_key(&id)
+      {
+         return None;
+      }
+      self.floating_images.insert(id, FloatingImage::new(placement, data));
+      self.floating_images.get_mut(&id)
+   }
+
+   /// Moves or resizes a floating image.
+   pub fn place_floating_image(&mut self, id: FloatingImageId, placement: ImagePlacement) {
+      if let Some(image) = self.floating_images.get_mut(&id) {
+         image.placement = placement;
+      }
+   }
+
+   /// Removes a floating image without drawing it. Returns whether the image existed.
+   pub fn remove_floating_image(&mut self, id: FloatingImageId) -> bool {
+      self.floating_images.remove(&id).is_some()
+   }
+
+   /// Draws a floating image onto the chunks beneath it, and removes it.
+   ///
+   /// Like any other drawing, this is attributed to the current author. The image stays where it
+   /// is while the canvas is paused.
+   pub fn flatten_floating_image(
+      &mut self,
+      renderer: &mut Backend,
+      id: FloatingImageId,
+   ) -> netcanv::Result<()> {
+      if self.paused {
+         return Ok(());
+      }
+      let Some(mut image) = self.floating_images.remove(&id) else {
+         return Ok(());
+      };
+      image.ensure_uploaded(renderer)?;
+      self.draw(renderer, image.rect(), |renderer| image.draw(renderer));
+      Ok(())
+   }
+
+   /// Returns an unused floating image ID for an image added by the given peer.
+   pub fn next_floating_image_id(&self, creator: Option<PeerId>) -> FloatingImageId {
+      Self::next_object_id(&self.floating_images, creator)
+   }
+
+   /// Draws the floating images above the canvas, later ones above earlier ones.
+   pub fn draw_floating_images(&self, renderer: &mut Backend) {
+      for image in self.floating_images.values() {
+         image.draw(renderer);
+      }
+   }
+
+   /// Returns the next ID for an object created by the given peer. Object IDs are made up of the
+   /// creator's peer ID (or 0 when offline) and a serial number counting up from 0.
+   fn next_object_id<T>(objects: &BTreeMap<(u64, u32), T>, creator: Option<PeerId>) -> (u64, u32) {
+      let creator = creator.map(|PeerId(id)| id).unwrap_or(0);
+      let serial = objects
+         .range((creator, 0)..=(creator, u32::MAX))
+         .next_back()
+         .map(|(&(_, serial), _)| serial.wrapping_add(1))
+         .unwrap_or(0);
+      (creator, serial)
+   }
+
+   /// Sets whether [`PaintCanvas::draw`] should skip chunks that lie within locked regions.
+   ///
+   /// This should be enabled whenever someone other than the host is drawing to the canvas.
+   pub fn set_enforce_locks(&mut self, enforce: bool) {
+      self.enforce_locks = enforce;
+   }
+
+   /// Returns whether modifications to the canvas are paused.
+   pub fn is_paused(&self) -> bool {
+      self.paused
+   }
+
+   /// Pauses or resumes modifications to the canvas made through [`PaintCanvas::draw`].
+   pub fn set_paused(&mut self, paused: bool) {
+      self.paused = paused;
+   }
+
+   /// Sets who subsequent calls to [`PaintCanvas::draw`] are attributed to. `None` stands for
+   /// ourselves.
+   pub fn set_author(&mut self, author: Option<PeerId>) {
+      self.author = author;
+   }
+
+   /// Returns the record of who drew to which chunks since we joined the room.
+   pub fn attribution(&self) -> &Attribution {
+      &self.attribution
+   }
+
+   /// Sets whether the canvas should keep track of who drew to which chunks, such that their
+   /// changes can be reverted later. This is only ever enabled on the host.
+   pub fn set_track_provenance(&mut self, track: bool) {
+      self.track_provenance = track;
+      self.sync_layer_contents();
+   }
+
+   /// Reverts all changes made by the given author within the last `since`, on every layer.
+   /// Returns the layers and positions of the chunks that were changed.
+   pub fn revert_author(
+      &mut self,
+      renderer: &mut Backend,
+      author: PeerId,
+      since: Duration,
+   ) -> Vec<(usize, (i32, i32))> {
+      let mut changed = Vec::new();
+      for layer in 0..self.layer_contents.len() {
+         let reverted = match &mut self.layer_contents[layer].provenance {
+            Some(provenance) => provenance.revert(author, since),
+            None => continue,
+         };
+         for (chunk_position, image) in reverted {
+            self.set_chunk(renderer, layer, chunk_position, image);
+            changed.push((layer, chunk_position));
+         }
+      }
+      changed
+   }
+
+   /// Sets whether the canvas should keep a log of everyone's operations, such that they can be
+   /// undone. Like provenance tracking, this is only ever enabled on the host. Each layer keeps
+   /// its own log.
+   pub fn set_track_operations(&mut self, track: bool) {
+      self.track_operations = track;
+      self.sync_layer_contents();
+      if let Some(vector_log) = &mut self.vector_log {
+         vector_log.set_track_commits(track);
+      }
+   }
+
+   /// Marks the end of an operation made by the current author on the current layer, such as a
+   /// brush stroke or a paste.
+   ///
+   /// On the host, the operation is committed to the log right away. Anyone else has to let the
+   /// host know about our own operations, which [`PaintCanvas::take_pending_commit`] is for.
+   pub fn commit_operation(&mut self, renderer: &mut Backend) {
+      let vector_strokes = match &mut self.vector_log {
+         Some(vector_log) => vector_log.commit(self.author),
+         None => Vec::new(),
+      };
+      let contents = &mut self.layer_contents[self.current_layer];
+      match &mut contents.operations {
+         Some(operations) => {
+            operations.commit(renderer, self.author, &contents.chunks, vector_strokes)
+         }
+         None => {
+            if self.author.is_none() && !self.uncommitted_chunks.is_empty() {
+               self.commit_pending = true;
+            }
+         }
+      }
+      if self.author.is_none() && !self.uncommitted_chunks.is_empty() {
+         let chunks = std::mem::take(&mut self.uncommitted_chunks);
+         self.own_operations.push((self.current_layer, chunks));
+      }
+   }
+
+   /// Returns the layers and the positions of the chunks changed by each of our own operations
+   /// committed since the last call, oldest first.
+   pub fn take_own_operations(&mut self) -> Vec<(usize, Vec<(i32, i32)>)> {
+      std::mem::take(&mut self.own_operations)
+   }
+
+   /// Returns digests of the chunks changed by operations committed since the last call, so that
+   /// the host can let everyone check that they see the same thing. Empty unless the canvas keeps
+   /// track of operations. The digests are grouped by layer, leaving out layers without any.
+   pub fn take_chunk_digests(&mut self) -> Vec<(usize, Vec<((i32, i32), Vec<u8>)>)> {
+      self
+         .layer_contents
+         .iter_mut()
+         .enumerate()
+         .filter_map(|(layer, contents)| {
+            let digests = contents.operations.as_mut()?.take_digests();
+            (!digests.is_empty()).then_some((layer, digests))
+         })
+         .collect()
+   }
+
+   /// Returns whether we committed an operation since the last call, and should let the host know.
+   pub fn take_pending_commit(&mut self) -> bool {
+      std::mem::take(&mut self.commit_pending)
+   }
+
+   /// Undoes the most recent committed operation of the current author on the current layer.
+   /// Returns the positions of the chunks that were changed.
+   ///
+   /// Nothing is undone while the canvas is paused, and locked regions are respected just like when
+   /// drawing.
+   pub fn undo_operation(&mut self, renderer: &mut Backend) -> Vec<(i32, i32)> {
+      if self.paused {
+         return Vec::new();
+      }
+      let operations = &mut self.layer_contents[self.current_layer].operations;
+      let Some(mut operation) = operations.as_mut().and_then(|ops| ops.undo(self.author)) else {
+         return Vec::new();
+      };
+      if let Some(vector_log) = &mut self.vector_log {
+         operation.removed_vector_strokes = vector_log.remove(&operation.vector_strokes);
+      }
+      self.removed_vector_strokes.extend_from_slice(&operation.vector_strokes);
+      let changed = self.apply_changes(renderer, &operation.changes, ChunkChange::revert);
+      if let Some(operations) = &mut self.layer_contents[self.current_layer].operations {
+         operations.push_undone(operation);
+      }
+      changed
+   }
+
+   /// Redoes the most recently undone operation of the current author on the current layer.
+   /// Returns the positions of the chunks that were changed.
+   ///
+   /// Like undoing, nothing is redone while the canvas is paused, and locked regions are respected.
+   pub fn redo_operation(&mut self, renderer: &mut Backend) -> Vec<(i32, i32)> {
+      if self.paused {
+         return Vec::new();
+      }
+      let operations = &mut self.layer_contents[self.current_layer].operations;
+      let Some(mut operation) = operations.as_mut().and_then(|ops| ops.redo(self.author)) else {
+         return Vec::new();
+      };
+      let removed_vector_strokes = std::mem::take(&mut operation.removed_vector_strokes);
+      if let (Some(vector_log), false) = (&mut self.vector_log, removed_vector_strokes.is_empty()) {
+         vector_log.restore(removed_vector_strokes);
+         self.vector_strokes_restored = true;
+      }
+      let changed = self.apply_changes(renderer, &operation.changes, ChunkChange::reapply);
+      if let Some(operations) = &mut self.layer_contents[self.current_layer].operations {
+         operations.push_redone(operation);
+      }
+      changed
+   }
+
+   /// Applies `apply` to the image of every chunk changed by an operation, skipping locked chunks
+   /// if locks are enforced. Returns the positions of the chunks that were changed.
+   fn apply_changes(
+      &mut self,
+      renderer: &mut Backend,
+      changes: &[ChunkChange],
+      apply: fn(&ChunkChange, &mut RgbaImage),
+   ) -> Vec<(i32, i32)> {
+      let mut changed = Vec::with_capacity(changes.len());
+      for change in changes {
+         let chunk_position = change.chunk_position;
+         if self.enforce_locks && self.is_chunk_locked(chunk_position) {
+            continue;
+         }
+         let chunk = self.ensure_chunk(renderer, self.current_layer, chunk_position);
+         let mut image = chunk.download_image(renderer);
+         apply(change, &mut image);
+         chunk.upload_image(renderer, &image, (0, 0));
+         changed.push(chunk_position);
+      }
+      changed
+   }
+
+   /// Returns whether the canvas keeps vector strokes.
+   pub fn vector_mode(&self) -> bool {
+      self.vector_log.is_some()
+   }
+
+   /// Sets whether the canvas should keep brush strokes as vector strokes. Turning this off
+   /// forgets all of them.
+   pub fn set_vector_mode(&mut self, enabled: bool) {
+      if enabled != self.vector_log.is_some() {
+         self.vector_log = enabled.then(|| VectorLog::new(self.track_operations));
+         self.vector_overlay = None;
+      }
+   }
+
+   /// Returns the canvas's vector strokes, if it keeps them.
+   pub fn vector_log(&self) -> Option<&VectorLog> {
+      self.vector_log.as_ref()
+   }
+
+   /// Replaces all the vector strokes. This does nothing if the canvas doesn't keep them.
+   pub fn set_vector_strokes(&mut self, strokes: Vec<(VectorStrokeId, VectorStroke)>) {
+      if let Some(vector_log) = &mut self.vector_log {
+         vector_log.set_strokes(strokes);
+      }
+   }
+
+   /// Removes the vector strokes with the given IDs.
+   pub fn remove_vector_strokes(&mut self, ids: &[VectorStrokeId]) {
+      if let Some(vector_log) = &mut self.vector_log {
+         vector_log.remove(ids);
+      }
+   }
+
+   /// Records a brush stroke segment drawn by `creator`, if the canvas keeps vector strokes. The
+   /// segment belongs to the current author's operation, and `width` is how wide it is at `b`.
+   ///
+   /// Like drawing, this does nothing while the canvas is paused.
+   pub fn record_vector_segment(
+      &mut self,
+      creator: Option<PeerId>,
+      style: StrokeStyle,
+      a: Point,
+      b: Point,
+      width: u8,
+   ) {
+      if self.paused {
+         return;
+      }
+      if let Some(vector_log) = &mut self.vector_log {
+         vector_log.record(self.author, creator, style, a, b, width);
+      }
    }
 
-   pub fn chunks(&self) -> &HashMap<(i32, i32), Chunk> {
-      &self.chunks
+   /// Returns the vector strokes removed by undos since the last call.
+   pub fn take_removed_vector_strokes(&mut self) -> Vec<VectorStrokeId> {
+      std::mem::take(&mut self.removed_vector_strokes)
    }
 
-   pub fn chunks_mut(&mut self) -> &mut HashMap<(i32, i32), Chunk> {
-      &mut self.chunks
+   /// Returns whether vector strokes were put back by a redo since the last call.
+   pub fn take_vector_strokes_restored(&mut self) -> bool {
+      std::mem::take(&mut self.vector_strokes_restored)
    }
 
-   /// Returns a vector containing all the chunk positions in the paint canvas.
-   pub fn chunk_positions(&self) -> Vec<(i32, i32)> {
-      self.chunks.keys().copied().collect()
+   /// Uploads the chunks visible in the viewport back to the graphics card if they were evicted,
+   /// and evicts the chunks that weren't seen for the longest once there are more chunks on the
+   /// graphics card than the config allows. This should be called once per frame, before drawing.
+   pub fn update_residency(
+      &mut self,
+      renderer: &mut Backend,
+      viewport: &Viewport,
+      window_size: Vector,
+   ) {
+      self.frame += 1;
+      let frame = self.frame;
+      let visible: Vec<_> = viewport.visible_tiles(Chunk::SIZE, window_size).collect();
+      let mut resident = 0;
+      for contents in &mut self.layer_contents {
+         for chunk in contents.chunks.values_mut() {
+            chunk.poll_eviction();
+         }
+         for chunk_position in &visible {
+            if let Some(chunk) = contents.chunks.get_mut(chunk_position) {
+               let _ = chunk.resident_framebuffer(renderer);
+               chunk.mark_seen(frame);
+            }
+         }
+         resident += contents.chunks.values().filter(|chunk| chunk.framebuffer().is_some()).count();
+      }
+
+      let max_resident_chunks = config().memory.max_resident_chunks;
+      if resident <= max_resident_chunks {
+         return;
+      }
+      let mut candidates: Vec<_> = self
+         .all_chunks()
+         .filter(|(_, _, chunk)| chunk.framebuffer().is_some() && chunk.last_seen() != frame)
+         .map(|(layer, chunk_position, chunk)| (chunk.last_seen(), layer, chunk_position))
+         .collect();
+      candidates.sort_unstable();
+      let disk_cache = Self::disk_cache_directory();
+      // Evicting a chunk means downloading its image from the graphics card, so only a few chunks
+      // are evicted per frame to keep the frame rate smooth.
+      let count = (resident - max_resident_chunks).min(Self::MAX_EVICTIONS_PER_FRAME);
+      for (_, layer, chunk_position) in candidates.into_iter().take(count) {
+         if let Some(chunk) = self.layer_contents[layer].chunks.get_mut(&chunk_position) {
+            chunk.evict(renderer, disk_cache.as_deref());
+         }
+      }
    }
 
-   pub fn chunk(&self, position: (i32, i32)) -> Option<&Chunk> {
-      self.chunks.get(&position)
+   /// Returns the directory evicted chunks are spilled to, creating it if needed. Returns `None`
+   /// if spilling to disk is turned off, or the directory can't be created.
+   ///
+   /// The directory is removed once the last chunk in it is dropped.
+   fn disk_cache_directory() -> Option<PathBuf> {
+      if !config().memory.spill_to_disk {
+         return None;
+      }
+      let directory = std::env::temp_dir().join(format!("netcanv-chunks-{}", std::process::id()));
+      match std::fs::create_dir_all(&directory) {
+         Ok(()) => Some(directory),
+         Err(error) => {
+            tracing::warn!("could not create chunk disk cache: {:?}", error);
+            None
+         }
+      }
+   }
+
+   /// Renders the vector strokes visible in the viewport to the overlay shown by
+   /// [`PaintCanvas::draw_vector_overlay`]. The overlay is only used once the viewport is zoomed
+   /// in, as that's when the chunks' pixels start to show.
+   ///
+   /// This must be called outside of any transforms.
+   pub fn update_vector_overlay(
+      &mut self,
+      renderer: &mut Backend,
+      viewport: &Viewport,
+      window_size: Vector,
+   ) {
+      self.vector_overlay_visible = false;
+      let Some(vector_log) = &self.vector_log else {
+         return;
+      };
+      // While the viewport is rotated, the overlay covers the rectangle enclosing the visible
+      // area, which is larger than the window.
+      let visible = viewport.visible_rect(window_size);
+      let overlay_size = visible.size * viewport.zoom();
+      let (width, height) = (overlay_size.x.ceil() as u32, overlay_size.y.ceil() as u32);
+      if viewport.zoom() <= 1.0 || vector_log.is_empty() || width == 0 || height == 0 {
+         return;
+      }
+      if self.vector_overlay.as_ref().map(|overlay| overlay.size()) != Some((width, height)) {
+         self.vector_overlay = Some(renderer.create_framebuffer(width, height));
+      }
+      let Some(overlay) = &self.vector_overlay else {
+         return;
+      };
+      renderer.push();
+      viewport.unrotated().apply_transform(renderer, overlay_size);
+      renderer.draw_to(overlay, |renderer| {
+         renderer.clear(Color::TRANSPARENT);
+         vector_log.draw(renderer, visible);
+      });
+      renderer.pop();
+      self.vector_overlay_visible = true;
+   }
+
+   /// Draws the overlay rendered by [`PaintCanvas::update_vector_overlay`] over the chunks. Like
+   /// [`PaintCanvas::draw_to`], this is drawn in the viewport's space.
+   pub fn draw_vector_overlay(
+      &self,
+      renderer: &mut Backend,
+      viewport: &Viewport,
+      window_size: Vector,
+   ) {
+      if let (true, Some(overlay)) = (self.vector_overlay_visible, &self.vector_overlay) {
+         renderer.framebuffer(viewport.visible_rect(window_size), overlay);
+      }
    }
 }
@@ -0,0 +1,238 @@
+//! The host's log of committed operations, used for undoing them the same way on every peer.
+//!
+//! An operation is everything a single author draws between two commits, such as one brush stroke
+//! or one paste. Undoing an operation only restores the pixels that still look the way the
+//! operation left them, so that changes other people made on top of it afterwards are kept.
+//! Redoing an operation works the same way in reverse.
+
+use std::collections::{HashMap, VecDeque};
+
+use image::RgbaImage;
+use netcanv_protocol::client::{VectorStroke, VectorStrokeId};
+use netcanv_protocol::relay::PeerId;
+
+use crate::backend::Backend;
+
+use super::chunk::Chunk;
+
+/// The images of a chunk before and after an operation changed it.
+pub struct ChunkChange {
+   pub chunk_position: (i32, i32),
+   before: RgbaImage,
+   after: RgbaImage,
+}
+
+impl ChunkChange {
+   /// Reverts the change in the given image of the chunk. Pixels that were changed again since the
+   /// operation are left alone.
+   pub fn revert(&self, image: &mut RgbaImage) {
+      for ((pixel, after), before) in
+         image.pixels_mut().zip(self.after.pixels()).zip(self.before.pixels())
+      {
+         if pixel == after {
+            *pixel = *before;
+         }
+      }
+   }
+
+   /// Applies the change again to the given image of the chunk, after it was reverted. Pixels that
+   /// were changed since the revert are left alone.
+   pub fn reapply(&self, image: &mut RgbaImage) {
+      for ((pixel, after), before) in
+         image.pixels_mut().zip(self.after.pixels()).zip(self.before.pixels())
+      {
+         if pixel == before {
+            *pixel = *after;
+         }
+      }
+   }
+
+   /// Updates an image of the same chunk taken after this change, such that pixels still showing
+   /// the change show what was there before it instead. Pixels for which `unchanged` is equal to
+   /// `image` are updated in `unchanged` as well.
+   fn rebase(&self, image: &mut RgbaImage, mut unchanged: Option<&mut RgbaImage>) {
+      for (i, ((pixel, after), before)) in
+         image.pixels_mut().zip(self.after.pixels()).zip(self.before.pixels()).enumerate()
+      {
+         if pixel == after {
+            if let Some(unchanged) = unchanged.as_deref_mut() {
+               let (x, y) = (i as u32 % Chunk::SIZE.0, i as u32 / Chunk::SIZE.0);
+               let unchanged = unchanged.get_pixel_mut(x, y);
+               if unchanged == pixel {
+                  *unchanged = *before;
+               }
+            }
+            *pixel = *before;
+         }
+      }
+   }
+}
+
+/// A committed operation.
+pub struct Operation {
+   /// The author of the operation, where `None` stands for the host.
+   author: Option<PeerId>,
+   pub changes: Vec<ChunkChange>,
+   /// The vector strokes started during the operation, if the room keeps them.
+   pub vector_strokes: Vec<VectorStrokeId>,
+   /// The vector strokes removed from the canvas when the operation was undone, which are put back
+   /// if it's redone.
+   pub removed_vector_strokes: Vec<(VectorStrokeId, VectorStroke)>,
+}
+
+/// Records what chunks looked like before and after each operation.
+pub struct OperationLog {
+   /// The images chunks had before each author's current operation first changed them.
+   open: HashMap<Option<PeerId>, Vec<((i32, i32), RgbaImage)>>,
+   /// Committed operations, oldest first.
+   committed: VecDeque<Operation>,
+   /// Undone operations, oldest first. An author's undone operations can be redone until they
+   /// commit a new one.
+   undone: VecDeque<Operation>,
+   /// Digests of the chunks changed by operations committed since they were last taken.
+   digests: Vec<((i32, i32), Vec<u8>)>,
+}
+
+impl OperationLog {
+   /// The maximum number of chunk changes kept in the log, for committed and undone operations
+   /// each. Each change takes up 512 KiB, and once there are more, the oldest operations can no
+   /// longer be undone or redone.
+   const MAX_CHANGES: usize = 256;
+
+   /// Creates a new, empty operation log.
+   pub fn new() -> Self {
+      Self {
+         open: HashMap::new(),
+         committed: VecDeque::new(),
+         undone: VecDeque::new(),
+         digests: Vec::new(),
+      }
+   }
+
+   /// Records that the given author is about to draw to the chunk, taking a snapshot of it if
+   /// it hasn't been changed yet by the author's current operation.
+   pub fn record(
+      &mut self,
+      renderer: &mut Backend,
+      author: Option<PeerId>,
+      chunk_position: (i32, i32),
+      chunk: &Chunk,
+   ) {
+      let open = self.open.entry(author).or_default();
+      if !open.iter().any(|(position, _)| *position == chunk_position) {
+         open.push((chunk_position, chunk.download_image(renderer)));
+      }
+   }
+
+   /// Commits the author's current operation, such that it can be undone.
+   pub fn commit(
+      &mut self,
+      renderer: &mut Backend,
+      author: Option<PeerId>,
+      chunks: &HashMap<(i32, i32), Chunk>,
+      vector_strokes: Vec<VectorStrokeId>,
+   ) {
+      let open = self.open.remove(&author).unwrap_or_default();
+      let changes: Vec<_> = open
+         .into_iter()
+         .filter_map(|(chunk_position, before)| {
+            let after = chunks.get(&chunk_position)?.download_image(renderer);
+            (after != before).then_some(ChunkChange {
+               chunk_position,
+               before,
+               after,
+            })
+         })
+         .collect();
+      for change in &changes {
+         let digest = Chunk::digest(&change.after);
+         match self.digests.iter_mut().find(|(position, _)| *position == change.chunk_position) {
+            Some((_, existing)) => *existing = digest,
+            None => self.digests.push((change.chunk_position, digest)),
+         }
+      }
+      if changes.is_empty() && vector_strokes.is_empty() {
+         return;
+      }
+      tracing::debug!(
+         "committed operation of {:?} changing {} chunks",
+         author,
+         changes.len()
+      );
+      self.undone.retain(|op| op.author != author);
+      self.committed.push_back(Operation {
+         author,
+         changes,
+         vector_strokes,
+         removed_vector_strokes: Vec::new(),
+      });
+      Self::limit_changes(&mut self.committed);
+   }
+
+   /// Drops the oldest operations until there are at most [`OperationLog::MAX_CHANGES`] changes
+   /// left. The most recent operation is always kept.
+   fn limit_changes(operations: &mut VecDeque<Operation>) {
+      let mut change_count: usize = operations.iter().map(|op| op.changes.len()).sum();
+      while change_count > Self::MAX_CHANGES && operations.len() > 1 {
+         if let Some(oldest) = operations.pop_front() {
+            change_count -= oldest.changes.len();
+         }
+      }
+   }
+
+   /// Returns the digests of the chunks changed by operations committed since the last call.
+   pub fn take_digests(&mut self) -> Vec<((i32, i32), Vec<u8>)> {
+      std::mem::take(&mut self.digests)
+   }
+
+   /// Removes the author's most recent committed operation from the log, and returns it. Operations
+   /// that came after it are updated to no longer refer to what it drew.
+   ///
+   /// Once the operation is reverted, it should be handed back with [`OperationLog::push_undone`],
+   /// so that it can be redone.
+   pub fn undo(&mut self, author: Option<PeerId>) -> Option<Operation> {
+      let index = self.committed.iter().rposition(|op| op.author == author)?;
+      let operation = self.committed.remove(index)?;
+      for change in &operation.changes {
+         let later_changes = self
+            .committed
+            .iter_mut()
+            .skip(index)
+            .flat_map(|op| op.changes.iter_mut())
+            .filter(|later| later.chunk_position == change.chunk_position);
+         for later in later_changes {
+            change.rebase(&mut later.before, Some(&mut later.after));
+         }
+         let open_snapshots = self
+            .open
+            .values_mut()
+            .flatten()
+            .filter(|(position, _)| *position == change.chunk_position);
+         for (_, before) in open_snapshots {
+            change.rebase(before, None);
+         }
+      }
+      Some(operation)
+   }
+
+   /// Keeps an operation returned by [`OperationLog::undo`] around, such that it can be redone.
+   pub fn push_undone(&mut self, operation: Operation) {
+      self.undone.push_back(operation);
+      Self::limit_changes(&mut self.undone);
+   }
+
+   /// Removes the author's most recently undone operation from the log, and returns it.
+   ///
+   /// Once the operation is applied again, it should be handed back with
+   /// [`OperationLog::push_redone`], so that it can be undone again.
+   pub fn redo(&mut self, author: Option<PeerId>) -> Option<Operation> {
+      let index = self.undone.iter().rposition(|op| op.author == author)?;
+      self.undone.remove(index)
+   }
+
+   /// Commits an operation returned by [`OperationLog::redo`] again.
+   pub fn push_redone(&mut self, operation: Operation) {
+      self.committed.push_back(operation);
+      Self::limit_changes(&mut self.committed);
+   }
+}
@@ -0,0 +1,103 @@
+//! Chunk provenance tracking, used by the host to roll back a single peer's changes.
+
+use image::RgbaImage;
+use netcanv_protocol::relay::PeerId;
+use web_time::{Duration, Instant};
+
+use crate::backend::Backend;
+
+use super::chunk::Chunk;
+
+/// The state of a chunk just before a peer started drawing to it.
+struct Snapshot {
+   author: PeerId,
+   chunk_position: (i32, i32),
+   taken: Instant,
+   image: RgbaImage,
+}
+
+/// Records who changed which chunks, and what the chunks looked like before they did.
+///
+/// Snapshots are taken at most once per [`Provenance::SNAPSHOT_INTERVAL`] for every author and
+/// chunk, so rollbacks have roughly that granularity.
+pub struct Provenance {
+   snapshots: Vec<Snapshot>,
+}
+
+impl Provenance {
+   /// The minimum amount of time between two snapshots of the same chunk for the same author.
+   const SNAPSHOT_INTERVAL: Duration = Duration::from_secs(30);
+
+   /// How long snapshots are kept around. Changes older than this cannot be rolled back.
+   pub const RETENTION: Duration = Duration::from_secs(30 * 60);
+
+   /// The maximum number of snapshots held in memory. Each snapshot takes up 256 KiB.
+   const MAX_SNAPSHOTS: usize = 512;
+
+   /// Creates a new, empty provenance record.
+   pub fn new() -> Self {
+      Self {
+         snapshots: Vec::new(),
+      }
+   }
+
+   /// Records that the given author is about to draw to the chunk, taking a snapshot of it if
+   /// necessary.
+   pub fn record(
+      &mut self,
+      renderer: &mut Backend,
+      author: PeerId,
+      chunk_position: (i32, i32),
+      chunk: &Chunk,
+   ) {
+      let has_recent_snapshot = self.snapshots.iter().any(|snapshot| {
+         snapshot.author == author
+            && snapshot.chunk_position == chunk_position
+            && snapshot.taken.elapsed() < Self::SNAPSHOT_INTERVAL
+      });
+      if has_recent_snapshot {
+         return;
+      }
+
+      self.prune();
+      if self.snapshots.len() >= Self::MAX_SNAPSHOTS {
+         self.snapshots.remove(0);
+      }
+      self.snapshots.push(Snapshot {
+         author,
+         chunk_position,
+         taken: Instant::now(),
+         image: chunk.download_image(renderer),
+      });
+   }
+
+   /// Drops snapshots older than [`Provenance::RETENTION`].
+   fn prune(&mut self) {
+      self.snapshots.retain(|snapshot| snapshot.taken.elapsed() < Self::RETENTION);
+   }
+
+   /// Removes all snapshots of the given author taken within the last `since`, and returns the
+   /// image each affected chunk had before the author's earliest change within that period.
+   ///
+   /// Note that reverting a chunk also reverts any changes made to it by other people after the
+   /// author started drawing.
+   pub fn revert(&mut self, author: PeerId, since: Duration) -> Vec<((i32, i32), RgbaImage)> {
+      self.prune();
+      let mut reverted: Vec<((i32, i32), RgbaImage)> = Vec::new();
+      let mut index = 0;
+      // Snapshots are stored chronologically, so the first one we encounter for a chunk is the
+      // earliest one.
+      while index < self.snapshots.len() {
+         let snapshot = &self.snapshots[index];
+         if snapshot.author == author && snapshot.taken.elapsed() <= since {
+            let snapshot = self.snapshots.remove(index);
+            if !reverted.iter().any(|(position, _)| *position == snapshot.chunk_position) {
+               reverted.push((snapshot.chunk_position, snapshot.image));
+            }
+         } else {
+            index += 1;
+         }
+      }
+      reverted
+   }
+}
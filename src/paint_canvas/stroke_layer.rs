@@ -0,0 +1,194 @@
+//! Stroke layers, for strokes that have to be composited onto the canvas as a whole.
+//!
+//! A translucent stroke is made up of many overlapping segments. Blending each segment onto the
+//! canvas separately would make the overlaps darker than the rest of the stroke, so instead the
+//! segments are drawn opaquely to a stroke layer, and the layer is blended onto the canvas once
+//! the stroke is finished.
+//!
+//! Merging is also where blend modes other than normal alpha blending are applied. These are
+//! computed on the CPU rather than by the graphics backend, so that the result is exactly the same
+//! for everyone in the room, no matter which renderer they use.
+
+use std::collections::HashMap;
+
+use image::{Rgba, RgbaImage};
+use netcanv_renderer::paws::{vector, Rect, Renderer};
+use netcanv_renderer::{BlendMode, Framebuffer as FramebufferTrait, RenderBackend};
+use serde::{Deserialize, Serialize};
+
+use super::chunk::Chunk;
+use super::PaintCanvas;
+use crate::backend::{Backend, Framebuffer};
+
+/// How a finished stroke is blended onto the canvas.
+///
+/// Blend modes are sent along with brush strokes, so adding one needs an incompatible bump of the
+/// protocol version; older clients would fail to decode strokes using it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum StrokeBlendMode {
+   Normal,
+   Multiply,
+   Screen,
+   Overlay,
+   /// Removes paint from the canvas, in proportion to the stroke's alpha. Used by the eraser, so
+   /// it's not one of the modes that can be cycled through.
+   Erase,
+}
+
+impl StrokeBlendMode {
+   /// All the blend modes, in the order they're cycled through.
+   pub const ALL: [Self; 4] = [Self::Normal, Self::Multiply, Self::Screen, Self::Overlay];
+
+   /// Returns the name of the blend mode, used as its translation key.
+   pub fn name(self) -> &'static str {
+      match self {
+         Self::Normal => "normal",
+         Self::Multiply => "multiply",
+         Self::Screen => "screen",
+         Self::Overlay => "overlay",
+         Self::Erase => "erase",
+      }
+   }
+
+   /// Returns the blend mode following this one, wrapping around to the first one.
+   pub fn next(self) -> Self {
+      let index = Self::ALL.iter().position(|&mode| mode == self).unwrap();
+      Self::ALL[(index + 1) % Self::ALL.len()]
+   }
+
+   /// Blends a single color channel of the source with the backdrop. Both are in the 0–1 range.
+   fn blend_channel(self, backdrop: f32, source: f32) -> f32 {
+      let screen = |a: f32, b: f32| a + b - a * b;
+      match self {
+         Self::Normal => source,
+         Self::Multiply => backdrop * source,
+         Self::Screen => screen(backdrop, source),
+         Self::Overlay => {
+            if backdrop <= 0.5 {
+               2.0 * backdrop * source
+            } else {
+               screen(2.0 * backdrop - 1.0, source)
+            }
+         }
+         Self::Erase => backdrop,
+      }
+   }
+
+   /// Composites a source pixel over a backdrop pixel, as described in the W3C Compositing and
+   /// Blending spec. Neither pixel is premultiplied.
+   fn composite(self, backdrop: Rgba<u8>, source: Rgba<u8>) -> Rgba<u8> {
+      let [br, bg, bb, ba] = backdrop.0.map(|x| x as f32 / 255.0);
+      let [sr, sg, sb, sa] = source.0.map(|x| x as f32 / 255.0);
+      if self == Self::Erase {
+         let [r, g, b, a] = backdrop.0;
+         return Rgba([r, g, b, ((1.0 - sa) * a as f32).round() as u8]);
+      }
+      let alpha = sa + ba * (1.0 - sa);
+      if alpha <= 0.0 {
+         return backdrop;
+      }
+      let channel = |backdrop: f32, source: f32| {
+         let blended = (1.0 - ba) * source + ba * self.blend_channel(backdrop, source);
+         (sa * blended + (1.0 - sa) * ba * backdrop) / alpha
+      };
+      let pixel = [channel(br, sr), channel(bg, sg), channel(bb, sb), alpha];
+      Rgba(pixel.map(|x| (x.clamp(0.0, 1.0) * 255.0).round() as u8))
+   }
+}
+
+/// A layer with an unfinished stroke, split into chunk-sized framebuffers.
+pub struct StrokeLayer {
+   chunks: HashMap<(i32, i32), Framebuffer>,
+   blend_mode: StrokeBlendMode,
+}
+
+impl StrokeLayer {
+   /// Creates a new, empty stroke layer.
+   pub fn new() -> Self {
+      Self {
+         chunks: HashMap::new(),
+         blend_mode: StrokeBlendMode::Normal,
+      }
+   }
+
+   /// Returns whether nothing has been drawn to the layer since it was last merged.
+   pub fn is_empty(&self) -> bool {
+      self.chunks.is_empty()
+   }
+
+   /// Draws to the layer. Works just like [`PaintCanvas::draw`], except that locked regions are
+   /// not taken into account until the layer is merged.
+   ///
+   /// The layer is merged with the blend mode of the last draw.
+   pub fn draw(
+      &mut self,
+      renderer: &mut Backend,
+      coverage: Rect,
+      blend_mode: StrokeBlendMode,
+      mut callback: impl FnMut(&mut Backend),
+   ) {
+      self.blend_mode = blend_mode;
+      let (left, top, bottom, right) = PaintCanvas::chunk_coverage(coverage);
+      for y in top..=bottom {
+         for x in left..=right {
+            let framebuffer = self
+               .chunks
+               .entry((x, y))
+               .or_insert_with(|| renderer.create_framebuffer(Chunk::SIZE.0, Chunk::SIZE.1));
+            renderer.push();
+            renderer.translate(vector(
+               -x as f32 * Chunk::SIZE.0 as f32,
+               -y as f32 * Chunk::SIZE.1 as f32,
+            ));
+            renderer.draw_to(framebuffer, |renderer| {
+               callback(renderer);
+            });
+            renderer.pop();
+         }
+      }
+   }
+
+   /// Draws the layer using the given renderer, so that the stroke can be seen while it's still
+   /// being drawn. The preview is always alpha blended, as the other blend modes are only applied
+   /// when merging.
+   pub fn draw_to(&self, renderer: &mut Backend) {
+      for (&chunk_position, framebuffer) in &self.chunks {
+         let screen_position = Chunk::screen_position(chunk_position);
+         renderer.framebuffer(framebuffer.rect(screen_position), framebuffer);
+      }
+   }
+
+   /// Blends the layer onto the paint canvas, and clears it.
+   pub fn merge_into(&mut self, renderer: &mut Backend, paint_canvas: &mut PaintCanvas) {
+      let blend_mode = self.blend_mode;
+      for (chunk_position, framebuffer) in self.chunks.drain() {
+         let rect = framebuffer.rect(Chunk::screen_position(chunk_position));
+         // The coverage is shrunk by a pixel, as its right and bottom edges would otherwise fall
+         // into the neighboring chunks.
+         let coverage = Rect::new(rect.position, rect.size - vector(1.0, 1.0));
+         renderer.push();
+         if blend_mode == StrokeBlendMode::Normal {
+            renderer.set_blend_mode(BlendMode::Alpha);
+         } else {
+            // The whole chunk is composited into the layer's framebuffer, which then replaces the
+            // chunk's contents.
+            let backdrop = paint_canvas
+               .chunk(paint_canvas.current_layer(), chunk_position)
+               .map(|chunk| chunk.download_image(renderer));
+            let mut image = RgbaImage::new(Chunk::SIZE.0, Chunk::SIZE.1);
+            renderer.download_framebuffer(&framebuffer, (0, 0), Chunk::SIZE, &mut image);
+            for (x, y, pixel) in image.enumerate_pixels_mut() {
+               let backdrop =
+                  backdrop.as_ref().map_or(Rgba([0, 0, 0, 0]), |image| *image.get_pixel(x, y));
+               *pixel = blend_mode.composite(backdrop, *pixel);
+            }
+            renderer.upload_framebuffer(&framebuffer, (0, 0), Chunk::SIZE, &image);
+            renderer.set_blend_mode(BlendMode::Replace);
+         }
+         paint_canvas.draw(renderer, coverage, |renderer| {
+            renderer.framebuffer(rect, &framebuffer);
+         });
+         renderer.pop();
+      }
+   }
+}
@@ -0,0 +1,343 @@
+//! Vector strokes. While a room has them turned on, brush strokes are also kept as paths, which can
+//! be redrawn sharply at any zoom level and exported to SVG.
+//!
+//! Every peer records the segments it draws and receives on its own. Since segments arrive in the
+//! same order everywhere, everyone ends up with the same strokes under the same IDs, and only
+//! joining and undoing have to be coordinated by the host.
+
+use std::collections::{HashMap, VecDeque};
+use std::fmt::Write;
+
+use netcanv_protocol::client::{
+   VectorStroke, VectorStrokeId, MAX_VECTOR_STROKES, MAX_VECTOR_STROKE_POINTS, VECTOR_SUBPIXELS,
+};
+use netcanv_protocol::relay::PeerId;
+use netcanv_renderer::paws::{point, Color, LineCap, Point, Rect, Renderer};
+use netcanv_renderer::{BlendMode, RenderBackend};
+
+use crate::backend::Backend;
+
+/// The look of a stroke, shared by all of its segments.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StrokeStyle {
+   pub color: (u8, u8, u8, u8),
+   pub thickness: u8,
+   /// The hardness of the brush, in percent.
+   pub hardness: u8,
+}
+
+/// The vector strokes drawn on a canvas.
+pub struct VectorLog {
+   /// All strokes, oldest first.
+   strokes: VecDeque<(VectorStrokeId, VectorStroke)>,
+   /// The stroke each creator drew most recently, which their next segment may continue.
+   last_strokes: HashMap<u64, VectorStrokeId>,
+   /// The strokes each author started since their last commit, grouped the same way as the
+   /// canvas's operations. This is only tracked on the host.
+   uncommitted: Option<HashMap<Option<PeerId>, Vec<VectorStrokeId>>>,
+}
+
+impl VectorLog {
+   /// Creates a new, empty vector log.
+   pub fn new(track_commits: bool) -> Self {
+      Self {
+         strokes: VecDeque::new(),
+         last_strokes: HashMap::new(),
+         uncommitted: track_commits.then(HashMap::new),
+      }
+   }
+
+   /// Sets whether new strokes should be grouped by the operations they belong to, such that they
+   /// can be removed when the operation is undone.
+   pub fn set_track_commits(&mut self, track: bool) {
+      if track != self.uncommitted.is_some() {
+         self.uncommitted = track.then(HashMap::new);
+      }
+   }
+
+   /// Returns whether there are no strokes in the log.
+   pub fn is_empty(&self) -> bool {
+      self.strokes.is_empty()
+   }
+
+   /// Returns all the strokes, oldest first.
+   pub fn strokes(&self) -> impl Iterator<Item = &(VectorStrokeId, VectorStroke)> {
+      self.strokes.iter()
+   }
+
+   /// Replaces all the strokes in the log.
+   pub fn set_strokes(&mut self, strokes: Vec<(VectorStrokeId, VectorStroke)>) {
+      self.strokes = strokes.into_iter().take(MAX_VECTOR_STROKES).collect();
+      self.last_strokes = self.strokes.iter().map(|&(id, _)| (id.0, id)).collect();
+      if let Some(uncommitted) = &mut self.uncommitted {
+         uncommitted.clear();
+      }
+   }
+
+   /// Removes the strokes with the given IDs, and returns them.
+   pub fn remove(&mut self, ids: &[VectorStrokeId]) -> Vec<(VectorStrokeId, VectorStroke)> {
+      let (removed, kept): (Vec<_>, Vec<_>) =
+         std::mem::take(&mut self.strokes).into_iter().partition(|(id, _)| ids.contains(id));
+      self.strokes = kept.into();
+      self.last_strokes.retain(|_, id| !ids.contains(id));
+      for uncommitted in self.uncommitted.iter_mut().flat_map(|map| map.values_mut()) {
+         uncommitted.retain(|id| !ids.contains(id));
+      }
+      removed
+   }
+
+   /// Puts strokes returned by [`VectorLog::remove`] back, above all the other strokes.
+   pub fn restore(&mut self, strokes: Vec<(VectorStrokeId, VectorStroke)>) {
+      self.strokes.extend(strokes);
+      while self.strokes.len() > MAX_VECTOR_STROKES {
+         if let Some((oldest, _)) = self.strokes.pop_front() {
+            self.last_strokes.retain(|_, id| *id != oldest);
+         }
+      }
+   }
+
+   /// Records a segment of a stroke, from `a` to `b` (in pixels). `width` is how wide the stroke is
+   /// at `b`, which is less than the style's thickness where the stylus was pressed lightly.
+   ///
+   /// The segment continues the creator's last stroke if it starts where that stroke ended and
+   /// looks the same; otherwise it starts a new stroke, which is attributed to `author` until
+   /// [`VectorLog::commit`] is called.
+   pub fn record(
+      &mut self,
+      author: Option<PeerId>,
+      creator: Option<PeerId>,
+      style: StrokeStyle,
+      a: Point,
+      b: Point,
+      width: u8,
+   ) {
+      let creator = creator.map(|PeerId(id)| id).unwrap_or(0);
+      let (a, b) = (to_subpixels(a), to_subpixels(b));
+
+      if let Some(&id) = self.last_strokes.get(&creator) {
+         if let Some((_, stroke)) = self.strokes.iter_mut().rev().find(|(other, _)| *other == id) {
+            let continues = stroke.points.last() == Some(&a)
+               && style_of(stroke) == style
+               && stroke.points.len() < MAX_VECTOR_STROKE_POINTS;
+            if continues {
+               if stroke.points.last() != Some(&b) {
+                  if stroke.widths.is_empty() && width != stroke.thickness {
+                     stroke.widths = vec![stroke.thickness; stroke.points.len()];
+                  }
+                  stroke.points.push(b);
+                  if !stroke.widths.is_empty() {
+                     stroke.widths.push(width);
+                  }
+               }
+               return;
+            }
+         }
+      }
+
+      let serial = self
+         .strokes
+         .iter()
+         .filter(|((other_creator, _), _)| *other_creator == creator)
+         .map(|&((_, serial), _)| serial.wrapping_add(1))
+         .max()
+         .unwrap_or(0);
+      let id = (creator, serial);
+      let StrokeStyle {
+         color,
+         thickness,
+         hardness,
+      } = style;
+      // Strokes drawn without a stylus are as wide as their thickness everywhere.
+      let widths = if width != thickness {
+         vec![width; 2]
+      } else {
+         Vec::new()
+      };
+      self.strokes.push_back((
+         id,
+         VectorStroke {
+            color,
+            thickness,
+            hardness,
+            points: vec![a, b],
+            widths,
+         },
+      ));
+      self.last_strokes.insert(creator, id);
+      if let Some(uncommitted) = &mut self.uncommitted {
+         uncommitted.entry(author).or_default().push(id);
+      }
+
+      while self.strokes.len() > MAX_VECTOR_STROKES {
+         if let Some((oldest, _)) = self.strokes.pop_front() {
+            self.last_strokes.retain(|_, id| *id != oldest);
+         }
+      }
+   }
+
+   /// Ends the author's current operation, returning the strokes that were started during it.
+   pub fn commit(&mut self, author: Option<PeerId>) -> Vec<VectorStrokeId> {
+      self.uncommitted.as_mut().and_then(|map| map.remove(&author)).unwrap_or_default()
+   }
+
+   /// Draws the strokes that can be drawn exactly: hard, opaque ones, and erasers. Translucent and
+   /// soft strokes are left to the chunks underneath.
+   ///
+   /// Only strokes with a segment inside `visible` are drawn.
+   pub fn draw(&self, renderer: &mut Backend, visible: Rect) {
+      renderer.push();
+      renderer.set_blend_mode(BlendMode::Replace);
+      for (_, stroke) in &self.strokes {
+         let (r, g, b, a) = stroke.color;
+         if a != 0 && (a != 255 || stroke.hardness < 100) {
+            continue;
+         }
+         let color = Color::new(r, g, b, a);
+         let margin = stroke.thickness as f32 / 2.0;
+         for (i, segment) in stroke.points.windows(2).enumerate() {
+            let (a, b) = (from_subpixels(segment[0]), from_subpixels(segment[1]));
+            let thickness = width_at(stroke, i + 1);
+            let outside = a.x.max(b.x) + margin < visible.left()
+               || a.x.min(b.x) - margin > visible.right()
+               || a.y.max(b.y) + margin < visible.top()
+               || a.y.min(b.y) - margin > visible.bottom();
+            if !outside {
+               renderer.line(a, b, color, LineCap::Round, thickness);
+            }
+         }
+      }
+      renderer.pop();
+   }
+
+   /// Renders the strokes to an SVG document, or returns `None` if there aren't any.
+   ///
+   /// The document covers `area` if given, or fits the strokes otherwise. Erasers are turned into
+   /// masks over everything drawn before them.
+   pub fn to_svg(&self, area: Option<Rect>) -> Option<String> {
+      let (mut left, mut top, mut right, mut bottom) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
+      for (_, stroke) in &self.strokes {
+         let margin = stroke.thickness as i32 * VECTOR_SUBPIXELS / 2 + VECTOR_SUBPIXELS;
+         for &(x, y) in &stroke.points {
+            left = left.min(x - margin);
+            top = top.min(y - margin);
+            right = right.max(x + margin);
+            bottom = bottom.max(y + margin);
+         }
+      }
+      if left > right {
+         return None;
+      }
+      let subpixels = VECTOR_SUBPIXELS as f32;
+      let (x, y, width, height) = match area {
+         Some(area) => (area.left(), area.top(), area.width(), area.height()),
+         None => (
+            left as f32 / subpixels,
+            top as f32 / subpixels,
+            (right - left) as f32 / subpixels,
+            (bottom - top) as f32 / subpixels,
+         ),
+      };
+
+      // Each eraser masks everything before it, so the document is built from the inside out:
+      // every eraser wraps the group of strokes drawn so far in a new masked group.
+      let bounds = format!(r#"x="{x}" y="{y}" width="{width}" height="{height}""#);
+      let mut defs = String::new();
+      let mut body = String::new();
+      let mut mask_count = 0;
+      for (_, stroke) in &self.strokes {
+         let (r, g, b, a) = stroke.color;
+         if a == 0 {
+            let _ = write!(
+               defs,
+               r#"<mask id="eraser{mask_count}" maskUnits="userSpaceOnUse" {bounds}>"#
+            );
+            let _ = write!(defs, r#"<rect {bounds} fill="white"/>"#);
+            defs.push_str(&svg_path(stroke, "black", 1.0));
+            defs.push_str("</mask>");
+            body = format!(r#"<g mask="url(#eraser{mask_count})">{body}</g>"#);
+            mask_count += 1;
+         } else {
+            let color = format!("#{r:02x}{g:02x}{b:02x}");
+            body.push_str(&svg_path(stroke, &color, a as f32 / 255.0));
+         }
+      }
+
+      let mut svg = String::new();
+      let _ = writeln!(
+         svg,
+         r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{x} {y} {width} {height}" {}>"#,
+         format_args!(r#"width="{width}" height="{height}""#),
+      );
+      if !defs.is_empty() {
+         let _ = writeln!(svg, "<defs>{defs}</defs>");
+      }
+      let _ = writeln!(svg, "{body}");
+      svg.push_str("</svg>\n");
+      Some(svg)
+   }
+}
+
+/// Returns the style a stroke is drawn with.
+fn style_of(stroke: &VectorStroke) -> StrokeStyle {
+   StrokeStyle {
+      color: stroke.color,
+      thickness: stroke.thickness,
+      hardness: stroke.hardness,
+   }
+}
+
+/// Returns how wide a stroke is at the point with the given index, in pixels. Each segment is as
+/// wide as the stroke is at its end.
+fn width_at(stroke: &VectorStroke, index: usize) -> f32 {
+   stroke.widths.get(index).copied().unwrap_or(stroke.thickness) as f32
+}
+
+/// Converts a point in pixels to vector stroke coordinates.
+fn to_subpixels(p: Point) -> (i32, i32) {
+   let subpixels = VECTOR_SUBPIXELS as f32;
+   (
+      (p.x * subpixels).round() as i32,
+      (p.y * subpixels).round() as i32,
+   )
+}
+
+/// Converts a point in vector stroke coordinates to pixels.
+fn from_subpixels((x, y): (i32, i32)) -> Point {
+   let subpixels = VECTOR_SUBPIXELS as f32;
+   point(x as f32 / subpixels, y as f32 / subpixels)
+}
+
+/// Builds an SVG path element tracing a stroke with the given color and opacity.
+///
+/// A path can only have a single width, so strokes of varying width are built from one path per
+/// segment instead, grouped such that the opacity applies to all of them at once.
+fn svg_path(stroke: &VectorStroke, color: &str, opacity: f32) -> String {
+   if !stroke.widths.is_empty() {
+      let mut segments = String::new();
+      for (i, segment) in stroke.points.windows(2).enumerate() {
+         let (a, b) = (from_subpixels(segment[0]), from_subpixels(segment[1]));
+         let width = width_at(stroke, i + 1);
+         let _ = write!(
+            segments,
+            r#"<path d="M{} {}L{} {}" stroke-width="{width}"/>"#,
+            a.x, a.y, b.x, b.y
+         );
+      }
+      return format!(
+         r#"<g fill="none" stroke="{color}" opacity="{opacity}" {}>{segments}</g>"#,
+         format_args!(r#"stroke-linecap="round" stroke-linejoin="round""#),
+      );
+   }
+
+   let mut data = String::new();
+   for (i, &p) in stroke.points.iter().enumerate() {
+      let p = from_subpixels(p);
+      let command = if i == 0 { 'M' } else { 'L' };
+      let _ = write!(data, "{command}{} {}", p.x, p.y);
+   }
+   let width = stroke.thickness;
+   format!(
+      r#"<path d="{data}" fill="none" stroke="{color}" stroke-opacity="{opacity}" {}/>"#,
+      format_args!(r#"stroke-width="{width}" stroke-linecap="round" stroke-linejoin="round""#),
+   )
+}
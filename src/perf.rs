@@ -0,0 +1,188 @@
+//! Performance measurements shown in the performance HUD.
+//!
+//! Timings are only collected while the HUD is open. They're gathered from both the main thread
+//! and the background threads that encode and decode chunks, and summarized once per second.
+
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+
+use once_cell::sync::Lazy;
+use web_time::{Duration, Instant};
+
+/// Something that's timed.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Metric {
+   /// Processing the app state, which includes building the draw commands for the frame.
+   Update,
+   /// Submitting the frame to the GPU and presenting it.
+   Present,
+   /// Processing the paint canvas and the current tool.
+   Canvas,
+   /// Communicating with the relay and handling peer messages.
+   Network,
+   /// Processing actions, which includes autosaving.
+   Actions,
+   /// Encoding a chunk for sending it over the network.
+   ChunkEncode,
+   /// Decoding a chunk received over the network.
+   ChunkDecode,
+}
+
+impl Metric {
+   /// All metrics, in the order they're stored in a [`Snapshot`].
+   pub const ALL: [Self; 7] = [
+      Self::Update,
+      Self::Present,
+      Self::Canvas,
+      Self::Network,
+      Self::Actions,
+      Self::ChunkEncode,
+      Self::ChunkDecode,
+   ];
+
+   /// Returns the name of the metric, used for looking up its translation.
+   pub fn name(self) -> &'static str {
+      match self {
+         Self::Update => "update",
+         Self::Present => "present",
+         Self::Canvas => "canvas",
+         Self::Network => "network",
+         Self::Actions => "actions",
+         Self::ChunkEncode => "chunk-encode",
+         Self::ChunkDecode => "chunk-decode",
+      }
+   }
+
+   fn index(self) -> usize {
+      self as usize
+   }
+}
+
+/// A summary of a metric's timings over a period.
+#[derive(Clone, Copy, Default)]
+pub struct Summary {
+   /// How many times the metric was measured.
+   pub count: u32,
+   pub total: Duration,
+   pub max: Duration,
+}
+
+impl Summary {
+   /// Returns the average time of a single measurement.
+   pub fn average(&self) -> Duration {
+      self.total.checked_div(self.count).unwrap_or_default()
+   }
+
+   fn add(&mut self, time: Duration) {
+      self.count += 1;
+      self.total += time;
+      self.max = self.max.max(time);
+   }
+}
+
+/// The measurements of the last period, along with the recent frame times.
+#[derive(Clone, Default)]
+pub struct Snapshot {
+   /// Summaries of the last period, indexed by [`Metric`].
+   pub metrics: [Summary; Metric::ALL.len()],
+   /// The times between the most recent frames, oldest first.
+   pub frame_times: Vec<Duration>,
+}
+
+impl Snapshot {
+   /// Returns the summary of the given metric.
+   pub fn metric(&self, metric: Metric) -> &Summary {
+      &self.metrics[metric.index()]
+   }
+}
+
+struct State {
+   current: [Summary; Metric::ALL.len()],
+   last: [Summary; Metric::ALL.len()],
+   period_start: Instant,
+   frame_times: VecDeque<Duration>,
+   last_frame: Option<Instant>,
+}
+
+/// The number of frame times kept for the graph.
+pub const FRAME_HISTORY_LEN: usize = 240;
+
+/// The length of the period metrics are summarized over.
+const PERIOD: Duration = Duration::from_secs(1);
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+static STATE: Lazy<Mutex<State>> = Lazy::new(|| {
+   Mutex::new(State {
+      current: Default::default(),
+      last: Default::default(),
+      period_start: Instant::now(),
+      frame_times: VecDeque::with_capacity(FRAME_HISTORY_LEN),
+      last_frame: None,
+   })
+});
+
+/// Starts or stops collecting measurements. Stopping clears everything measured so far.
+pub fn set_enabled(enabled: bool) {
+   ENABLED.store(enabled, Ordering::Relaxed);
+   if !enabled {
+      let mut state = STATE.lock().unwrap();
+      state.current = Default::default();
+      state.last = Default::default();
+      state.frame_times.clear();
+      state.last_frame = None;
+   }
+}
+
+/// Returns whether measurements are being collected.
+pub fn is_enabled() -> bool {
+   ENABLED.load(Ordering::Relaxed)
+}
+
+/// Records a measurement of the given metric.
+pub fn record(metric: Metric, time: Duration) {
+   if is_enabled() {
+      STATE.lock().unwrap().current[metric.index()].add(time);
+   }
+}
+
+/// Measures how long the function takes to run.
+pub fn measure<R>(metric: Metric, f: impl FnOnce() -> R) -> R {
+   if !is_enabled() {
+      return f();
+   }
+   let start = Instant::now();
+   let result = f();
+   record(metric, start.elapsed());
+   result
+}
+
+/// Marks the end of a frame. This should be called once per frame, after it's presented.
+pub fn finish_frame() {
+   if !is_enabled() {
+      return;
+   }
+   let mut state = STATE.lock().unwrap();
+   let now = Instant::now();
+   if let Some(last_frame) = state.last_frame {
+      if state.frame_times.len() >= FRAME_HISTORY_LEN {
+         state.frame_times.pop_front();
+      }
+      state.frame_times.push_back(now - last_frame);
+   }
+   state.last_frame = Some(now);
+   if state.period_start.elapsed() >= PERIOD {
+      state.last = std::mem::take(&mut state.current);
+      state.period_start = now;
+   }
+}
+
+/// Returns the measurements of the last period.
+pub fn snapshot() -> Snapshot {
+   let state = STATE.lock().unwrap();
+   Snapshot {
+      metrics: state.last,
+      frame_times: state.frame_times.iter().copied().collect(),
+   }
+}
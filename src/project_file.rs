@@ -1,36 +1,161 @@
 use std::ffi::OsStr;
+use std::fs::File;
+use std::io::BufReader;
 use std::path::{Path, PathBuf};
 
-use image::{GenericImage, GenericImageView, Rgba, RgbaImage};
+use image::imageops::{self, FilterType};
+use image::{GenericImage, GenericImageView, Rgb, RgbImage, Rgba, RgbaImage};
+use netcanv_protocol::client::{
+   Background, Bookmark, Layer, StickyNote, VectorStroke, VectorStrokeId, MAX_PAGES,
+};
+use netcanv_renderer::paws::Rect;
 use serde::{Deserialize, Serialize};
 
 use crate::backend::Backend;
+use crate::crash_report;
 use crate::image_coder::ImageCoder;
 use crate::paint_canvas::chunk::Chunk;
-use crate::paint_canvas::PaintCanvas;
+use crate::paint_canvas::{CanvasPage, PaintCanvas};
 use crate::Error;
 
+#[cfg(test)]
+mod tests;
+
 /// The format version in a `.netcanv`'s `canvas.toml` file.
-pub const CANVAS_TOML_VERSION: u32 = 1;
+pub const CANVAS_TOML_VERSION: u32 = 6;
+
+/// The maximum number of pages imported from a single document.
+const MAX_DOCUMENT_PAGES: usize = 64;
+
+/// The resolution PDF pages are rendered at, in dots per inch.
+const PDF_RESOLUTION: u32 = 150;
 
 /// A `canvas.toml` file.
-#[derive(Serialize, Deserialize)]
+#[derive(Default, Serialize, Deserialize)]
 struct CanvasToml {
    /// The format version of the canvas.
    version: u32,
+   /// The sticky notes on the canvas.
+   #[serde(default, rename = "note", skip_serializing_if = "Vec::is_empty")]
+   notes: Vec<StickyNote>,
+   /// The width and height of the canvas, if it's bounded.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   bounds: Option<(u32, u32)>,
+   /// The pattern drawn beneath the canvas.
+   #[serde(default, skip_serializing_if = "Background::is_blank")]
+   background: Background,
+   /// The canvas's layers, bottom first. Canvases saved before layers existed have none, and
+   /// are loaded with a single layer.
+   #[serde(default, rename = "layer", skip_serializing_if = "Vec::is_empty")]
+   layers: Vec<Layer>,
+   /// The room palette, as 8-bit RGB triples.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   palette: Vec<(u8, u8, u8)>,
+   /// Where the viewport was when the canvas was saved.
+   #[serde(default, skip_serializing_if = "Option::is_none")]
+   viewport: Option<SavedViewport>,
+   /// The name and description of the room the canvas was hosted in.
+   #[serde(default, skip_serializing_if = "RoomMetadata::is_empty")]
+   room: RoomMetadata,
+   /// Whether the canvas keeps vector strokes. The strokes themselves are saved separately, in
+   /// `vector-strokes.toml`.
+   #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+   vector_mode: bool,
+   /// The viewport bookmarks saved on the canvas.
+   #[serde(default, rename = "bookmark", skip_serializing_if = "Vec::is_empty")]
+   bookmarks: Vec<Bookmark>,
+   /// The names of the canvas's pages, in order. The first page's chunks are saved right in the
+   /// save's directory, and every other page's in a `page-<index>` directory. Canvases saved
+   /// before pages existed have none, and are loaded as a single page.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   pages: Vec<String>,
+}
+
+/// A `vector-strokes.toml` file.
+#[derive(Serialize, Deserialize)]
+struct VectorStrokesToml {
+   #[serde(default, rename = "stroke")]
+   strokes: Vec<SavedVectorStroke>,
+}
+
+/// A vector stroke, as saved in a `vector-strokes.toml` file.
+#[derive(Serialize, Deserialize)]
+struct SavedVectorStroke {
+   id: VectorStrokeId,
+   color: (u8, u8, u8, u8),
+   thickness: u8,
+   hardness: u8,
+   points: Vec<(i32, i32)>,
+   /// Strokes saved before pressure was recorded don't have widths.
+   #[serde(default, skip_serializing_if = "Vec::is_empty")]
+   widths: Vec<u8>,
+}
+
+/// The position of the viewport, as saved in a `canvas.toml` file.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+pub struct SavedViewport {
+   /// The point in the center of the viewport, in canvas space.
+   pub pan: (f32, f32),
+   /// The zoom level, as opposed to the zoom factor.
+   pub zoom_level: f32,
+}
+
+/// The room a canvas was hosted in.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RoomMetadata {
+   #[serde(default)]
+   pub name: String,
+   #[serde(default)]
+   pub description: String,
+}
+
+impl RoomMetadata {
+   fn is_empty(&self) -> bool {
+      self.name.is_empty() && self.description.is_empty()
+   }
+}
+
+/// Things that aren't part of the canvas itself, but are saved along with it in a `.netcanv`.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectMetadata {
+   pub palette: Vec<(u8, u8, u8)>,
+   pub viewport: Option<SavedViewport>,
+   pub room: RoomMetadata,
+   pub bookmarks: Vec<Bookmark>,
+}
+
+/// A page of the canvas being saved to a `.netcanv`.
+pub struct ProjectPage<'a> {
+   pub name: &'a str,
+   /// The page's chunks, or `None` if it's the page currently shown on the paint canvas.
+   pub canvas: Option<&'a mut CanvasPage>,
+}
+
+/// A page of a canvas loaded from a `.netcanv`.
+pub struct LoadedPage {
+   pub name: String,
+   /// The page's chunks, or `None` for the first page, whose chunks are loaded onto the paint
+   /// canvas itself.
+   pub canvas: Option<CanvasPage>,
 }
 
 pub struct ProjectFile {
    /// The path to the `.netcanv` directory this paint canvas was saved to.
    filename: Option<PathBuf>,
+   /// The metadata saved along with the canvas, and the metadata of the last loaded canvas.
+   metadata: ProjectMetadata,
 }
 
 impl ProjectFile {
    pub fn new() -> Self {
-      ProjectFile { filename: None }
+      crash_report::set_canvas_path(None);
+      ProjectFile {
+         filename: None,
+         metadata: ProjectMetadata::default(),
+      }
    }
 
-   /// Saves the entire paint canvas to a PNG file.
+   /// Saves the entire paint canvas to a PNG file, with all the visible layers blended together.
    fn save_as_png(
       &self,
       renderer: &mut Backend,
@@ -38,8 +163,12 @@ impl ProjectFile {
       canvas: &mut PaintCanvas,
    ) -> netcanv::Result<()> {
       tracing::info!("saving png {:?}", path);
+      if let Some(bounds) = canvas.bounds_rect() {
+         return Self::save_bounded_png(renderer, path, canvas, bounds);
+      }
       let (mut left, mut top, mut right, mut bottom) = (i32::MAX, i32::MAX, i32::MIN, i32::MIN);
-      for chunk_position in canvas.chunks_mut().keys() {
+      let chunk_positions = canvas.visible_chunk_positions();
+      for chunk_position in &chunk_positions {
          left = left.min(chunk_position.0);
          top = top.min(chunk_position.1);
          right = right.max(chunk_position.0);
@@ -59,7 +188,10 @@ impl ProjectFile {
       let height = ((bottom - top + 1) * Chunk::SIZE.1 as i32) as u32;
       tracing::debug!("size: {:?}", (width, height));
       let mut image = RgbaImage::from_pixel(width, height, Rgba([0, 0, 0, 0]));
-      for (chunk_position, chunk) in canvas.chunks() {
+      for chunk_position in chunk_positions {
+         let Some(chunk_image) = canvas.flattened_chunk_image(renderer, chunk_position) else {
+            continue;
+         };
          tracing::debug!("writing chunk {:?}", chunk_position);
          let pixel_position = (
             (Chunk::SIZE.0 as i32 * (chunk_position.0 - left)) as u32,
@@ -67,7 +199,6 @@ impl ProjectFile {
          );
          tracing::debug!("   - pixel position: {:?}", pixel_position);
 
-         let chunk_image = chunk.download_image(renderer);
          let mut sub_image = image.sub_image(
             pixel_position.0,
             pixel_position.1,
@@ -81,6 +212,117 @@ impl ProjectFile {
       Ok(())
    }
 
+   /// Saves the part of a bounded canvas that lies within its bounds to a PNG file, such that the
+   /// image is exactly the size of the bounds.
+   fn save_bounded_png(
+      renderer: &mut Backend,
+      path: &Path,
+      canvas: &PaintCanvas,
+      bounds: Rect,
+   ) -> netcanv::Result<()> {
+      tracing::debug!("bounded size: {:?}", (bounds.width(), bounds.height()));
+      Self::composite_region(renderer, canvas, bounds)?.save(path)?;
+      tracing::debug!("image {:?} saved successfully", path);
+      Ok(())
+   }
+
+   /// Blends the visible layers within the given region of the canvas into an image that's exactly
+   /// the size of the region.
+   fn composite_region(
+      renderer: &mut Backend,
+      canvas: &PaintCanvas,
+      region: Rect,
+   ) -> netcanv::Result<RgbaImage> {
+      let (left, top) = (region.left() as i32, region.top() as i32);
+      let (width, height) = (region.width() as i32, region.height() as i32);
+      if width <= 0 || height <= 0 {
+         return Err(Error::NothingToSave);
+      }
+      let mut image = RgbaImage::from_pixel(width as u32, height as u32, Rgba([0, 0, 0, 0]));
+      for chunk_position in canvas.visible_chunk_positions() {
+         // The chunk's position within the image, which may be partially outside of it.
+         let chunk_x = chunk_position.0 * Chunk::SIZE.0 as i32 - left;
+         let chunk_y = chunk_position.1 * Chunk::SIZE.1 as i32 - top;
+         let (x0, y0) = (chunk_x.max(0), chunk_y.max(0));
+         let x1 = (chunk_x + Chunk::SIZE.0 as i32).min(width);
+         let y1 = (chunk_y + Chunk::SIZE.1 as i32).min(height);
+         if x1 <= x0 || y1 <= y0 {
+            continue;
+         }
+         let Some(chunk_image) = canvas.flattened_chunk_image(renderer, chunk_position) else {
+            continue;
+         };
+         tracing::debug!("writing chunk {:?}", chunk_position);
+
+         let sub_image = chunk_image.view(
+            (x0 - chunk_x) as u32,
+            (y0 - chunk_y) as u32,
+            (x1 - x0) as u32,
+            (y1 - y0) as u32,
+         );
+         image.copy_from(&*sub_image, x0 as u32, y0 as u32)?;
+      }
+      Ok(image)
+   }
+
+   /// Exports a region of the canvas to a PNG or JPEG file, with all the visible layers blended
+   /// together, and the image scaled by the given factor.
+   ///
+   /// Unlike saving, this doesn't mark anything as saved.
+   pub fn export_region(
+      renderer: &mut Backend,
+      path: &Path,
+      canvas: &PaintCanvas,
+      region: Rect,
+      scale: f32,
+   ) -> netcanv::Result<()> {
+      tracing::info!("exporting region {:?} at {}x to {:?}", region, scale, path);
+      let image = Self::composite_region(renderer, canvas, region)?;
+      let width = (image.width() as f32 * scale).round().max(1.0) as u32;
+      let height = (image.height() as f32 * scale).round().max(1.0) as u32;
+      let image = if (width, height) == image.dimensions() {
+         image
+      } else {
+         // Scaling up keeps the pixels crisp, while scaling down averages them out.
+         let filter = if scale > 1.0 {
+            FilterType::Nearest
+         } else {
+            FilterType::Triangle
+         };
+         imageops::resize(&image, width, height, filter)
+      };
+      match path.extension().and_then(OsStr::to_str) {
+         Some("png") => image.save(path)?,
+         Some("jpg" | "jpeg") => {
+            // JPEG doesn't have an alpha channel, so the image is put on a white background, which
+            // is what the canvas looks like when nothing's drawn on it.
+            let mut flattened = RgbImage::from_pixel(width, height, Rgb([255, 255, 255]));
+            for (pixel, &Rgba([r, g, b, a])) in flattened.pixels_mut().zip(image.pixels()) {
+               let blend =
+                  |channel: u8| ((channel as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+               *pixel = Rgb([blend(r), blend(g), blend(b)]);
+            }
+            flattened.save(path)?
+         }
+         Some(_) => return Err(Error::UnsupportedSaveFormat),
+         None => return Err(Error::MissingCanvasSaveExtension),
+      }
+      tracing::debug!("region exported successfully");
+      Ok(())
+   }
+
+   /// Saves the canvas's vector strokes to an SVG file. Unlike the other formats, this doesn't
+   /// include anything drawn while vector strokes were off.
+   fn save_as_svg(&self, path: &Path, canvas: &PaintCanvas) -> netcanv::Result<()> {
+      tracing::info!("saving svg {:?}", path);
+      let svg = canvas
+         .vector_log()
+         .and_then(|vector_log| vector_log.to_svg(canvas.bounds_rect()))
+         .ok_or(Error::NoVectorStrokesToSave)?;
+      std::fs::write(path, svg)?;
+      Ok(())
+   }
+
    /// Validates the `.netcanv` save path. This strips away the `canvas.toml` if present, and makes
    /// sure that the directory name ends with `.netcanv`.
    fn validate_netcanv_save_path(path: &Path) -> netcanv::Result<PathBuf> {
@@ -96,14 +338,66 @@ impl ProjectFile {
       Ok(result)
    }
 
+   /// Returns the directory the given page's chunks are saved to within a `.netcanv` save. The
+   /// first page is saved right in the save's directory, just like before pages existed.
+   fn page_directory(path: &Path, page: usize) -> PathBuf {
+      match page {
+         0 => path.to_owned(),
+         _ => path.join(format!("page-{}", page)),
+      }
+   }
+
+   /// Returns the directory the given layer's chunks are saved to within a page's directory. The
+   /// bottom layer is saved right in the page's directory, just like before layers existed.
+   fn layer_directory(path: &Path, layer: usize) -> PathBuf {
+      match layer {
+         0 => path.to_owned(),
+         _ => path.join(format!("layer-{}", layer)),
+      }
+   }
+
+   /// Returns the file a chunk is saved to within a page's directory.
+   fn chunk_file_path(path: &Path, layer: usize, chunk_position: (i32, i32)) -> PathBuf {
+      let filename = format!("{},{}.png", chunk_position.0, chunk_position.1);
+      Self::layer_directory(path, layer).join(filename)
+   }
+
+   /// Runs `f` with the given page's chunks on the canvas, in place of the current page's.
+   /// If the page is `None`, the canvas is left as it is.
+   fn with_page<R>(
+      canvas: &mut PaintCanvas,
+      page: Option<&mut CanvasPage>,
+      f: impl FnOnce(&mut PaintCanvas) -> R,
+   ) -> R {
+      match page {
+         Some(page) => {
+            canvas.swap_page(page);
+            let result = f(canvas);
+            canvas.swap_page(page);
+            result
+         }
+         None => f(canvas),
+      }
+   }
+
    /// Clears the existing `.netcanv` save at the given path.
    fn clear_netcanv_save(path: &Path) -> netcanv::Result<()> {
       tracing::info!("clearing older netcanv save {:?}", path);
       for entry in std::fs::read_dir(path)? {
          let path = entry?.path();
-         if path.is_file()
+         let is_chunk_directory =
+            path.file_name().and_then(|name| name.to_str()).map_or(false, |name| {
+               name
+                  .strip_prefix("layer-")
+                  .or_else(|| name.strip_prefix("page-"))
+                  .map_or(false, |index| index.parse::<usize>().is_ok())
+            });
+         if path.is_dir() && is_chunk_directory {
+            Self::clear_netcanv_save(&path)?;
+         } else if path.is_file()
             && (path.extension() == Some(OsStr::new("png"))
-               || path.file_name() == Some(OsStr::new("canvas.toml")))
+               || path.file_name() == Some(OsStr::new("canvas.toml"))
+               || path.file_name() == Some(OsStr::new("vector-strokes.toml")))
          {
             std::fs::remove_file(path)?;
          }
@@ -111,53 +405,154 @@ impl ProjectFile {
       Ok(())
    }
 
-   /// Saves the paint canvas as a `.netcanv` canvas.
+   /// Saves the paint canvas along with all of its pages as a `.netcanv` canvas.
    fn save_as_netcanv(
       &mut self,
       renderer: &mut Backend,
       path: &Path,
       canvas: &mut PaintCanvas,
+      pages: &mut [ProjectPage],
+   ) -> netcanv::Result<()> {
+      let path = Self::validate_netcanv_save_path(path)?;
+      let clear = self.filename != Some(path.clone());
+      self.write_netcanv(renderer, &path, canvas, pages, clear)?;
+      let mark_saved = |canvas: &mut PaintCanvas| {
+         for (_, _, chunk) in canvas.all_chunks_mut() {
+            chunk.mark_saved();
+         }
+      };
+      mark_saved(canvas);
+      for page in pages {
+         if let Some(page) = page.canvas.as_deref_mut() {
+            Self::with_page(canvas, Some(page), mark_saved);
+         }
+      }
+      self.set_filename(path);
+      Ok(())
+   }
+
+   /// Saves a snapshot of the paint canvas and its pages as a new `.netcanv` canvas at the given
+   /// path. Unlike saving, this leaves the canvas's own save path alone, and doesn't mark anything
+   /// as saved.
+   pub fn autosave(
+      &self,
+      renderer: &mut Backend,
+      path: &Path,
+      canvas: &mut PaintCanvas,
+      pages: &mut [ProjectPage],
+   ) -> netcanv::Result<()> {
+      let path = Self::validate_netcanv_save_path(path)?;
+      self.write_netcanv(renderer, &path, canvas, pages, false)
+   }
+
+   /// Writes the paint canvas and its pages out to a `.netcanv` directory. If `pages` is empty,
+   /// the canvas is saved as a single page. If `clear` is true, whatever else was saved in the
+   /// directory is removed first.
+   fn write_netcanv(
+      &self,
+      renderer: &mut Backend,
+      path: &Path,
+      canvas: &mut PaintCanvas,
+      pages: &mut [ProjectPage],
+      clear: bool,
    ) -> netcanv::Result<()> {
       // create the directory
       tracing::info!("creating or reusing existing directory ({:?})", path);
-      let path = Self::validate_netcanv_save_path(path)?;
-      std::fs::create_dir_all(path.clone())?; // use create_dir_all to not fail if the dir already exists
-      if self.filename != Some(path.clone()) {
-         Self::clear_netcanv_save(&path)?;
+      std::fs::create_dir_all(path)?; // use create_dir_all to not fail if the dir already exists
+      if clear {
+         Self::clear_netcanv_save(path)?;
       }
       // save the canvas.toml manifest
       tracing::info!("saving canvas.toml");
       let canvas_toml = CanvasToml {
          version: CANVAS_TOML_VERSION,
+         notes: canvas.notes().values().cloned().collect(),
+         bounds: canvas.bounds(),
+         background: canvas.background(),
+         layers: canvas.layers().to_vec(),
+         palette: self.metadata.palette.clone(),
+         viewport: self.metadata.viewport,
+         room: self.metadata.room.clone(),
+         vector_mode: canvas.vector_mode(),
+         bookmarks: self.metadata.bookmarks.clone(),
+         pages: pages.iter().map(|page| page.name.to_owned()).collect(),
       };
       std::fs::write(
          path.join(Path::new("canvas.toml")),
          toml::to_string(&canvas_toml)?,
       )?;
-      // save all the chunks
-      tracing::info!("saving chunks");
-      for (chunk_position, chunk) in canvas.chunks_mut() {
-         tracing::debug!("chunk {:?}", chunk_position);
+      // save the vector strokes, so that they can still be exported to SVG after reopening
+      let vector_strokes_path = path.join(Path::new("vector-strokes.toml"));
+      match canvas.vector_log() {
+         Some(vector_log) if !vector_log.is_empty() => {
+            tracing::info!("saving vector-strokes.toml");
+            let vector_strokes_toml = VectorStrokesToml {
+               strokes: vector_log
+                  .strokes()
+                  .map(|(id, stroke)| SavedVectorStroke {
+                     id: *id,
+                     color: stroke.color,
+                     thickness: stroke.thickness,
+                     hardness: stroke.hardness,
+                     points: stroke.points.clone(),
+                     widths: stroke.widths.clone(),
+                  })
+                  .collect(),
+            };
+            std::fs::write(vector_strokes_path, toml::to_string(&vector_strokes_toml)?)?;
+         }
+         _ => {
+            if vector_strokes_path.is_file() {
+               std::fs::remove_file(vector_strokes_path)?;
+            }
+         }
+      }
+      // save all the chunks, page by page
+      if pages.is_empty() {
+         return Self::write_chunks(renderer, path, canvas);
+      }
+      for (index, page) in pages.iter_mut().enumerate() {
+         tracing::info!("saving chunks of page {}", index);
+         let page_path = Self::page_directory(path, index);
+         Self::with_page(canvas, page.canvas.as_deref_mut(), |canvas| {
+            Self::write_chunks(renderer, &page_path, canvas)
+         })?;
+      }
+      Ok(())
+   }
+
+   /// Writes the chunks of the page currently shown on the paint canvas to the given page
+   /// directory.
+   fn write_chunks(
+      renderer: &mut Backend,
+      path: &Path,
+      canvas: &mut PaintCanvas,
+   ) -> netcanv::Result<()> {
+      for layer in 0..canvas.layers().len() {
+         std::fs::create_dir_all(Self::layer_directory(path, layer))?;
+      }
+      for (layer, chunk_position, chunk) in canvas.all_chunks_mut() {
+         tracing::debug!("chunk {:?} on layer {}", chunk_position, layer);
          let image = chunk.download_image(renderer);
          let image_data = ImageCoder::encode_png_data_sync(image)?;
-         let filename = format!("{},{}.png", chunk_position.0, chunk_position.1);
-         let filepath = path.join(Path::new(&filename));
+         let filepath = Self::chunk_file_path(path, layer, chunk_position);
          tracing::debug!("saving to {:?}", filepath);
          std::fs::write(filepath, image_data)?;
-         chunk.mark_saved();
       }
-      self.filename = Some(path);
       Ok(())
    }
 
-   /// Saves the canvas to a PNG file or a `.netcanv` directory.
+   /// Saves the canvas to a PNG or SVG file, or a `.netcanv` directory. Only a `.netcanv` can hold
+   /// more than one page, so PNG and SVG files only get the page currently shown on the canvas.
    ///
-   /// If `path` is `None`, this performs an autosave of an already saved `.netcanv` directory.
+   /// If `path` is `None`, the canvas is saved again to the `.netcanv` directory it was last saved
+   /// to.
    pub fn save(
       &mut self,
       renderer: &mut Backend,
       path: Option<&Path>,
       canvas: &mut PaintCanvas,
+      pages: &mut [ProjectPage],
    ) -> netcanv::Result<()> {
       let path = path
          .map(|p| p.to_path_buf())
@@ -165,10 +560,18 @@ impl ProjectFile {
          .expect("no save path provided");
       if let Some(ext) = path.extension() {
          match ext.to_str() {
-            Some("png") => self.save_as_png(renderer, &path, canvas),
+            Some("png") => {
+               self.save_as_png(renderer, &path, canvas)?;
+               // The image has everything that's drawn on the canvas, so nothing is left unsaved.
+               for (_, _, chunk) in canvas.all_chunks_mut() {
+                  chunk.mark_saved();
+               }
+               Ok(())
+            }
+            Some("svg") => self.save_as_svg(&path, canvas),
             Some("netcanv") | Some("toml") => {
                // TODO: Saving should be asynchronous.
-               self.save_as_netcanv(renderer, &path, canvas)
+               self.save_as_netcanv(renderer, &path, canvas, pages)
             }
             _ => Err(Error::UnsupportedSaveFormat),
          }
@@ -196,17 +599,31 @@ impl ProjectFile {
       use ::image::io::Reader as ImageReader;
 
       let image = ImageReader::open(path)?.decode()?.into_rgba8();
+      let (origin_x, origin_y) = Self::extract_chunk_origin_from_filename(path).unwrap_or((0, 0));
+      Self::place_image(renderer, canvas, &image, (-origin_x, -origin_y))?;
+      Ok(())
+   }
+
+   /// Splits an image into chunks on the current layer, such that its top-left corner lands in the
+   /// top-left corner of the chunk at `offset`. Returns the positions of the chunks that were drawn
+   /// to.
+   fn place_image(
+      renderer: &mut Backend,
+      canvas: &mut PaintCanvas,
+      image: &RgbaImage,
+      offset: (i32, i32),
+   ) -> netcanv::Result<Vec<(i32, i32)>> {
       tracing::debug!("image size: {:?}", image.dimensions());
-      let chunks_x = (image.width() as f32 / Chunk::SIZE.0 as f32).ceil() as i32;
-      let chunks_y = (image.height() as f32 / Chunk::SIZE.1 as f32).ceil() as i32;
+      let (chunks_x, chunks_y) = Self::size_in_chunks(image);
       tracing::debug!("n. chunks: x={}, y={}", chunks_x, chunks_y);
-      let (origin_x, origin_y) = Self::extract_chunk_origin_from_filename(path).unwrap_or((0, 0));
 
+      let mut placed = Vec::new();
       for y in 0..chunks_y {
          for x in 0..chunks_x {
             let chunk_position = (x, y);
-            let offset_chunk_position = (x - origin_x, y - origin_y);
-            let chunk = canvas.ensure_chunk(renderer, offset_chunk_position);
+            let offset_chunk_position = (x + offset.0, y + offset.1);
+            let chunk =
+               canvas.ensure_chunk(renderer, canvas.current_layer(), offset_chunk_position);
             let pixel_position = (
                Chunk::SIZE.0 * chunk_position.0 as u32,
                Chunk::SIZE.1 * chunk_position.1 as u32,
@@ -229,10 +646,150 @@ impl ProjectFile {
             }
             chunk.mark_dirty();
             chunk.upload_image(renderer, &chunk_image, (0, 0));
+            placed.push(offset_chunk_position);
          }
       }
 
-      Ok(())
+      Ok(placed)
+   }
+
+   /// Returns how many chunks wide and tall an image is.
+   fn size_in_chunks(image: &RgbaImage) -> (i32, i32) {
+      (
+         (image.width() as f32 / Chunk::SIZE.0 as f32).ceil() as i32,
+         (image.height() as f32 / Chunk::SIZE.1 as f32).ceil() as i32,
+      )
+   }
+
+   /// Imports the pages of a multi-page document onto the current layer, laid out in a grid whose
+   /// top-left corner is in the chunk at `top_left`. Returns the positions of the chunks the pages
+   /// were drawn to.
+   ///
+   /// PDF and TIFF documents can be imported. PDF documents are rendered with Poppler's `pdftoppm`,
+   /// which has to be installed separately.
+   pub fn import_document(
+      renderer: &mut Backend,
+      path: &Path,
+      canvas: &mut PaintCanvas,
+      top_left: (i32, i32),
+   ) -> netcanv::Result<Vec<(i32, i32)>> {
+      tracing::info!("importing document {:?}", path);
+      let extension = path.extension().and_then(OsStr::to_str).map(str::to_ascii_lowercase);
+      let pages = match extension.as_deref() {
+         Some("pdf") => Self::render_pdf_pages(path)?,
+         Some("tif" | "tiff") => Self::decode_tiff_pages(path)?,
+         _ => return Err(Error::UnsupportedDocumentFormat),
+      };
+      tracing::debug!("{} pages", pages.len());
+
+      // The pages are laid out in a roughly square grid, with a chunk of space between them so that
+      // there's room to annotate them.
+      let columns = (pages.len() as f32).sqrt().ceil().max(1.0) as usize;
+      let (cell_width, cell_height) = pages.iter().map(Self::size_in_chunks).fold(
+         (0, 0),
+         |(cell_width, cell_height), (width, height)| {
+            (cell_width.max(width + 1), cell_height.max(height + 1))
+         },
+      );
+      let mut placed = Vec::new();
+      for (index, page) in pages.iter().enumerate() {
+         let (column, row) = ((index % columns) as i32, (index / columns) as i32);
+         let offset = (
+            top_left.0 + column * cell_width,
+            top_left.1 + row * cell_height,
+         );
+         placed.extend(Self::place_image(renderer, canvas, page, offset)?);
+      }
+      Ok(placed)
+   }
+
+   /// Renders the pages of a PDF file, using `pdftoppm` to rasterize them into PNG files in a
+   /// temporary directory.
+   fn render_pdf_pages(path: &Path) -> netcanv::Result<Vec<RgbaImage>> {
+      use std::io::ErrorKind;
+      use std::process::Command;
+
+      let directory = std::env::temp_dir().join(format!("netcanv-pdf-{}", std::process::id()));
+      std::fs::create_dir_all(&directory)?;
+      let output = Command::new("pdftoppm")
+         .arg("-png")
+         .args(["-r", &PDF_RESOLUTION.to_string()])
+         .args(["-l", &MAX_DOCUMENT_PAGES.to_string()])
+         .arg(path)
+         .arg(directory.join("page"))
+         .output();
+      let result = match output {
+         Ok(output) if output.status.success() => Self::read_rendered_pdf_pages(&directory),
+         Ok(output) => Err(Error::PdfRenderingFailed {
+            error: String::from_utf8_lossy(&output.stderr).trim().to_owned(),
+         }),
+         Err(error) if error.kind() == ErrorKind::NotFound => Err(Error::PdfRendererNotFound),
+         Err(error) => Err(error.into()),
+      };
+      if let Err(error) = std::fs::remove_dir_all(&directory) {
+         tracing::warn!("could not remove rendered PDF pages: {:?}", error);
+      }
+      let pages = result?;
+      if pages.is_empty() {
+         return Err(Error::UnsupportedDocumentFormat);
+      }
+      Ok(pages)
+   }
+
+   /// Reads the pages rendered by `pdftoppm` into the given directory.
+   fn read_rendered_pdf_pages(directory: &Path) -> netcanv::Result<Vec<RgbaImage>> {
+      use ::image::io::Reader as ImageReader;
+
+      // The pages are named page-1.png, page-2.png, and so on, with the numbers padded to the same
+      // width, so sorting them by name puts them in order.
+      let mut page_paths = Vec::new();
+      for entry in std::fs::read_dir(directory)? {
+         let page_path = entry?.path();
+         if page_path.extension() == Some(OsStr::new("png")) {
+            page_paths.push(page_path);
+         }
+      }
+      page_paths.sort();
+      let mut pages = Vec::new();
+      for page_path in &page_paths {
+         pages.push(ImageReader::open(page_path)?.decode()?.into_rgba8());
+      }
+      Ok(pages)
+   }
+
+   /// Decodes the pages of a TIFF file. Only 8-bit grayscale and color pages are supported.
+   fn decode_tiff_pages(path: &Path) -> netcanv::Result<Vec<RgbaImage>> {
+      use tiff::decoder::{Decoder, DecodingResult};
+      use tiff::ColorType;
+
+      let mut decoder = Decoder::new(BufReader::new(File::open(path)?))?;
+      let mut pages = Vec::new();
+      loop {
+         let (width, height) = decoder.dimensions()?;
+         let color_type = decoder.colortype()?;
+         let DecodingResult::U8(data) = decoder.read_image()? else {
+            return Err(Error::UnsupportedDocumentFormat);
+         };
+         let rgba: Vec<u8> = match color_type {
+            ColorType::RGBA(8) => data,
+            ColorType::RGB(8) => {
+               data.chunks_exact(3).flat_map(|p| [p[0], p[1], p[2], 255]).collect()
+            }
+            ColorType::GrayA(8) => {
+               data.chunks_exact(2).flat_map(|p| [p[0], p[0], p[0], p[1]]).collect()
+            }
+            ColorType::Gray(8) => data.iter().flat_map(|&v| [v, v, v, 255]).collect(),
+            _ => return Err(Error::UnsupportedDocumentFormat),
+         };
+         let page =
+            RgbaImage::from_raw(width, height, rgba).ok_or(Error::UnsupportedDocumentFormat)?;
+         pages.push(page);
+         if pages.len() >= MAX_DOCUMENT_PAGES || !decoder.more_images() {
+            break;
+         }
+         decoder.next_image()?;
+      }
+      Ok(pages)
    }
 
    /// Parses an `x,y` chunk position.
@@ -254,61 +811,172 @@ impl ProjectFile {
       Ok((x, y))
    }
 
-   /// Loads chunks from a `.netcanv` directory.
+   /// Reads the `canvas.toml` of a `.netcanv` directory.
+   fn read_canvas_toml(path: &Path) -> netcanv::Result<CanvasToml> {
+      tracing::debug!("loading canvas.toml");
+      let canvas_toml_path = path.join(Path::new("canvas.toml"));
+      let canvas_toml: CanvasToml = toml::from_str(&std::fs::read_to_string(canvas_toml_path)?)?;
+      if canvas_toml.version > CANVAS_TOML_VERSION {
+         return Err(Error::CanvasTomlVersionMismatch);
+      }
+      Ok(canvas_toml)
+   }
+
+   /// Loads chunks from a `.netcanv` directory. The first page is loaded onto the paint canvas,
+   /// and all the pages are returned, which is none for canvases saved before pages existed.
    fn load_from_netcanv(
       &mut self,
       renderer: &mut Backend,
       path: &Path,
       canvas: &mut PaintCanvas,
-   ) -> netcanv::Result<()> {
+   ) -> netcanv::Result<Vec<LoadedPage>> {
       let path = Self::validate_netcanv_save_path(path)?;
       tracing::info!("loading canvas from {:?}", path);
-      // load canvas.toml
-      tracing::debug!("loading canvas.toml");
-      let canvas_toml_path = path.join(Path::new("canvas.toml"));
-      let canvas_toml: CanvasToml = toml::from_str(&std::fs::read_to_string(canvas_toml_path)?)?;
-      if canvas_toml.version > CANVAS_TOML_VERSION {
-         return Err(Error::CanvasTomlVersionMismatch);
+      let canvas_toml = Self::read_canvas_toml(&path)?;
+      canvas.set_bounds(canvas_toml.bounds);
+      canvas.set_background(canvas_toml.background);
+      canvas.set_notes(
+         canvas_toml.notes.into_iter().enumerate().map(|(i, note)| ((0, i as u32), note)),
+      );
+      canvas.set_layers(canvas_toml.layers);
+      self.metadata = ProjectMetadata {
+         palette: canvas_toml.palette,
+         viewport: canvas_toml.viewport,
+         room: canvas_toml.room,
+         bookmarks: canvas_toml.bookmarks,
+      };
+      canvas.set_vector_mode(canvas_toml.vector_mode);
+      let vector_strokes_path = path.join(Path::new("vector-strokes.toml"));
+      if canvas_toml.vector_mode && vector_strokes_path.is_file() {
+         tracing::debug!("loading vector-strokes.toml");
+         let vector_strokes_toml: VectorStrokesToml =
+            toml::from_str(&std::fs::read_to_string(vector_strokes_path)?)?;
+         canvas.set_vector_strokes(
+            vector_strokes_toml
+               .strokes
+               .into_iter()
+               .map(|stroke| {
+                  let SavedVectorStroke {
+                     id,
+                     color,
+                     thickness,
+                     hardness,
+                     points,
+                     widths,
+                  } = stroke;
+                  let stroke = VectorStroke {
+                     color,
+                     thickness,
+                     hardness,
+                     points,
+                     widths,
+                  };
+                  (id, stroke)
+               })
+               .collect(),
+         );
       }
-      // load chunks
+      // load chunks, page by page
       tracing::debug!("loading chunks");
-      for entry in std::fs::read_dir(path.clone())? {
-         let path = entry?.path();
-         // Please let me have if let chains.
-         if path.is_file() && path.extension() == Some(OsStr::new("png")) {
-            if let Some(position_osstr) = path.file_stem() {
-               if let Some(position_str) = position_osstr.to_str() {
-                  let chunk_position = Self::parse_chunk_position(position_str)?;
-                  tracing::debug!("chunk {:?}", chunk_position);
-                  let chunk = canvas.ensure_chunk(renderer, chunk_position);
-                  let image_data = ImageCoder::decode_png_data(&std::fs::read(path)?)?;
-                  chunk.upload_image(renderer, &image_data, (0, 0));
-                  chunk.mark_saved();
+      Self::read_chunks(renderer, &path, canvas)?;
+      let mut pages = Vec::new();
+      for (index, name) in canvas_toml.pages.into_iter().take(MAX_PAGES).enumerate() {
+         let page = match index {
+            0 => None,
+            _ => {
+               let mut page = CanvasPage::default();
+               let page_path = Self::page_directory(&path, index);
+               Self::with_page(canvas, Some(&mut page), |canvas| {
+                  Self::read_chunks(renderer, &page_path, canvas)
+               })?;
+               Some(page)
+            }
+         };
+         pages.push(LoadedPage { name, canvas: page });
+      }
+      self.set_filename(path);
+      Ok(pages)
+   }
+
+   /// Lists the chunk files in a page directory, along with the layers and positions of their
+   /// chunks. Only the canvas's first `layer_count` layers are looked at.
+   fn chunk_files(
+      path: &Path,
+      layer_count: usize,
+   ) -> netcanv::Result<Vec<(usize, (i32, i32), PathBuf)>> {
+      let mut chunk_files = Vec::new();
+      for layer in 0..layer_count {
+         let layer_path = Self::layer_directory(path, layer);
+         if !layer_path.is_dir() {
+            continue;
+         }
+         for entry in std::fs::read_dir(layer_path)? {
+            let path = entry?.path();
+            // Please let me have if let chains.
+            if path.is_file() && path.extension() == Some(OsStr::new("png")) {
+               if let Some(position_osstr) = path.file_stem() {
+                  if let Some(position_str) = position_osstr.to_str() {
+                     let chunk_position = Self::parse_chunk_position(position_str)?;
+                     chunk_files.push((layer, chunk_position, path));
+                  }
                }
             }
          }
       }
-      self.filename = Some(path);
+      Ok(chunk_files)
+   }
+
+   /// Loads the chunks in a page directory onto the page currently shown on the paint canvas.
+   fn read_chunks(
+      renderer: &mut Backend,
+      path: &Path,
+      canvas: &mut PaintCanvas,
+   ) -> netcanv::Result<()> {
+      for (layer, chunk_position, path) in Self::chunk_files(path, canvas.layers().len())? {
+         tracing::debug!("chunk {:?} on layer {}", chunk_position, layer);
+         let chunk = canvas.ensure_chunk(renderer, layer, chunk_position);
+         let image_data = ImageCoder::decode_png_data(&std::fs::read(path)?)?;
+         chunk.upload_image(renderer, &image_data, (0, 0));
+         chunk.mark_saved();
+      }
       Ok(())
    }
 
-   /// Loads a paint canvas from the given path.
+   /// Loads a paint canvas from the given path, and returns its pages. Canvases that don't have
+   /// any pages are a single page, which is loaded onto the paint canvas.
    pub fn load(
       &mut self,
       renderer: &mut Backend,
       path: &Path,
       canvas: &mut PaintCanvas,
-   ) -> netcanv::Result<()> {
+   ) -> netcanv::Result<Vec<LoadedPage>> {
       if let Some(ext) = path.extension() {
          match ext.to_str() {
             Some("netcanv") | Some("toml") => self.load_from_netcanv(renderer, path, canvas),
-            _ => self.load_from_image_file(renderer, path, canvas),
+            _ => self.load_from_image_file(renderer, path, canvas).map(|()| Vec::new()),
          }
       } else {
-         self.load_from_image_file(renderer, path, canvas)
+         self.load_from_image_file(renderer, path, canvas).map(|()| Vec::new())
       }
    }
 
+   /// Sets the filename the canvas was saved under, which is also where it can be recovered from
+   /// after a crash.
+   fn set_filename(&mut self, path: PathBuf) {
+      crash_report::set_canvas_path(Some(path.clone()));
+      self.filename = Some(path);
+   }
+
+   /// Returns the metadata of the last loaded `.netcanv` canvas.
+   pub fn metadata(&self) -> &ProjectMetadata {
+      &self.metadata
+   }
+
+   /// Sets the metadata to save along with the canvas the next time it's saved as a `.netcanv`.
+   pub fn set_metadata(&mut self, metadata: ProjectMetadata) {
+      self.metadata = metadata;
+   }
+
    /// Returns what filename the canvas was saved under.
    pub fn filename(&self) -> Option<&Path> {
       self.filename.as_deref()
@@ -0,0 +1,71 @@
+//! Tests for the layout of `.netcanv` saves.
+//!
+//! Every page of a canvas has its own directory of chunks, which has to survive being saved and
+//! loaded back without pages swapping their chunks or losing them.
+
+use std::path::{Path, PathBuf};
+
+use super::{CanvasToml, ProjectFile, CANVAS_TOML_VERSION};
+use crate::paint_canvas::PaintCanvas;
+
+/// Returns a fresh `.netcanv` directory for a test to save into.
+fn save_directory(name: &str) -> PathBuf {
+   let directory = std::env::temp_dir().join(format!(
+      "netcanv-project-tests-{}-{}.netcanv",
+      name,
+      std::process::id()
+   ));
+   let _ = std::fs::remove_dir_all(&directory);
+   std::fs::create_dir_all(&directory).unwrap();
+   directory
+}
+
+/// Writes a chunk file the way saving does, and returns its path.
+fn write_chunk(path: &Path, page: usize, layer: usize, chunk_position: (i32, i32)) -> PathBuf {
+   let page_path = ProjectFile::page_directory(path, page);
+   std::fs::create_dir_all(ProjectFile::layer_directory(&page_path, layer)).unwrap();
+   let chunk_path = ProjectFile::chunk_file_path(&page_path, layer, chunk_position);
+   std::fs::write(&chunk_path, format!("page {}", page)).unwrap();
+   chunk_path
+}
+
+#[test]
+fn two_pages_round_trip() {
+   let directory = save_directory("pages");
+   let canvas_toml = CanvasToml {
+      version: CANVAS_TOML_VERSION,
+      layers: vec![PaintCanvas::default_layer(); 2],
+      pages: vec!["Sketches".to_owned(), "Final".to_owned()],
+      ..Default::default()
+   };
+   std::fs::write(
+      directory.join("canvas.toml"),
+      toml::to_string(&canvas_toml).unwrap(),
+   )
+   .unwrap();
+   let first = write_chunk(&directory, 0, 0, (0, 0));
+   let second = write_chunk(&directory, 1, 1, (-1, 2));
+
+   let loaded = ProjectFile::read_canvas_toml(&directory).unwrap();
+   assert_eq!(loaded.pages, ["Sketches", "Final"]);
+   assert_eq!(loaded.layers.len(), 2);
+   let page_chunks =
+      |page| ProjectFile::chunk_files(&ProjectFile::page_directory(&directory, page), 2).unwrap();
+   assert_eq!(page_chunks(0), [(0, (0, 0), first.clone())]);
+   assert_eq!(page_chunks(1), [(1, (-1, 2), second.clone())]);
+
+   // Saving somewhere new clears out every page's chunks, not just the first's.
+   ProjectFile::clear_netcanv_save(&directory).unwrap();
+   assert!(!first.exists() && !second.exists());
+   assert!(page_chunks(0).is_empty() && page_chunks(1).is_empty());
+   std::fs::remove_dir_all(&directory).unwrap();
+}
+
+#[test]
+fn canvases_saved_before_pages_have_none() {
+   let directory = save_directory("unpaged");
+   std::fs::write(directory.join("canvas.toml"), "version = 5\n").unwrap();
+   let loaded = ProjectFile::read_canvas_toml(&directory).unwrap();
+   assert!(loaded.pages.is_empty());
+   std::fs::remove_dir_all(&directory).unwrap();
+}
@@ -21,41 +21,263 @@ pub struct Strings {
    pub lobby_room_id: LabelledTextField,
    pub lobby_join: String,
 
-   pub lobby_host_a_new_room: ExpandWithDescription,
+   pub lobby_host_a_new_room: ExpandWithTitle,
+   pub lobby_room_name: LabelledTextField,
+   pub lobby_room_description: LabelledTextField,
+   pub lobby_custom_room_id: LabelledTextField,
    pub lobby_host: String,
    pub lobby_host_from_file: String,
+   pub lobby_public: String,
+   pub lobby_lan: String,
+   pub lobby_direct: String,
+
+   pub lobby_rooms: String,
+   pub lobby_recent_rooms: String,
+   pub lobby_no_recent_rooms: String,
+   pub lobby_rejoin: String,
+   pub lobby_rehost: String,
+   pub lobby_recent_room_as: Formatted,
+   pub lobby_recent_room_from_file: Formatted,
+   pub lobby_recent_just_now: String,
+   pub lobby_recent_minutes_ago: Formatted,
+   pub lobby_recent_hours_ago: Formatted,
+   pub lobby_recent_days_ago: Formatted,
+   pub lobby_public_rooms: String,
+   pub lobby_fetching_public_rooms: String,
+   pub lobby_no_public_rooms: String,
+   pub lobby_public_room_players: Formatted,
+   pub lobby_public_room_invite_only: String,
+   pub lobby_public_rooms_hint: String,
+   pub lobby_refresh: String,
+   pub lobby_lan_rooms: String,
+   pub lobby_no_lan_rooms: String,
+   pub lobby_field_required: String,
+   pub lobby_field_too_long: Formatted,
+   pub lobby_field_room_id_length: Formatted,
+   pub lobby_field_length: Formatted,
+   pub lobby_field_letters_and_digits: String,
+   pub lobby_relay_checking: String,
+   pub lobby_relay_latency: Formatted,
+   pub lobby_relay_reachable: String,
+   pub lobby_relay_unreachable: String,
+   pub lobby_relay_profiles: String,
+   pub lobby_no_relay_profiles: String,
+   pub lobby_relay_profile_name: String,
+   pub lobby_save_relay_profile: String,
+   pub lobby_recover_session: String,
+   pub lobby_recover: String,
+   pub lobby_dismiss: String,
 
    pub switch_to_dark_mode: String,
    pub switch_to_light_mode: String,
    pub language: String,
    pub open_source_licenses: String,
+   pub draw_offline: String,
+   pub usage_statistics: String,
+   pub usage_statistics_description: String,
+   pub usage_statistics_enabled: String,
+   pub usage_statistics_endpoint: String,
+   pub usage_statistics_preview: String,
 
+   pub mapping_port: String,
+   pub resolving: String,
    pub connecting: String,
+   pub waiting_for_relay: String,
+   pub cancel: String,
 
    //
    // Paint
    //
    pub paint_welcome_host: String,
+   pub paint_welcome_offline: String,
+   pub paint_welcome_lan_host: String,
 
    pub unknown_host: String,
    pub you_are_the_host: String,
+   pub drawing_offline: String,
+   pub drawing_offline_hint: String,
    pub someone_is_your_host: String,
    pub room_id_copied: String,
+   pub copy_room_link: String,
+   pub room_info: String,
+   pub edit_room_info: String,
+   pub save_room_info: String,
+   pub room_is_now_called: Formatted,
+   pub room_link_copied: String,
+   pub show_qr_code: String,
+   pub scan_to_join: String,
 
    pub someone_joined_the_room: Formatted,
    pub someone_left_the_room: Formatted,
    pub someone_is_now_hosting_the_room: Formatted,
    pub you_are_now_hosting_the_room: String,
+   pub someone_pinged: Formatted,
+   pub bookmark_name: Formatted,
+   pub bookmark_saved: Formatted,
+   pub no_bookmark: Formatted,
+   pub grid_snapping_enabled: String,
+   pub grid_snapping_disabled: String,
+
+   pub status_bar_chunk: Formatted,
+   pub status_bar_people: Formatted,
+   pub status_bar_up_to_date: String,
+   pub status_bar_downloading: Formatted,
+   pub status_bar_offline: String,
 
    pub tool: Map<String>,
    pub brush_thickness: String,
+   pub brush_opacity: String,
+   pub brush_hardness: String,
+   pub brush_stabilizer: String,
+   pub brush_blend_mode: String,
+   pub blend_mode: Map<String>,
+   pub lock_regions_hint: String,
+   pub lock_regions_host_only: String,
+   pub laser_hint: String,
+   pub notes_hint: String,
+   pub note_text_hint: String,
+   pub remove_note: String,
+   pub floating_images_hint: String,
+   pub image_placement_hint: String,
+   pub flatten_image: String,
+   pub remove_image: String,
+   pub text_size: String,
+   pub text_hint: String,
+   pub text_field_hint: String,
+   pub shape: Map<String>,
+   pub shape_filled: String,
+   pub shape_outline: String,
+   pub airbrush_radius: String,
+   pub airbrush_density: String,
+   pub export_scale: String,
+   pub export_selection: String,
+   pub load_stamp: String,
+   pub stamp_size: String,
+   pub stamp_hint: String,
+   pub reference_image_opacity: String,
+   pub remove_reference_image: String,
+   pub reference_image_below_canvas: String,
+   pub reference_image_above_canvas: String,
+   pub show_attribution: String,
+   pub hide_attribution: String,
+   pub attribution_unknown_author: String,
 
    pub action: Map<String>,
 
+   pub people: String,
+   pub nobody_else_is_here: String,
+   pub pause_canvas: String,
+   pub pause_canvas_hint: String,
+   pub resume_canvas: String,
+   pub canvas_is_paused: String,
+   pub vector_strokes_hint: String,
+   pub vector_strokes_are_on: String,
+   pub turn_vector_strokes_on: String,
+   pub turn_vector_strokes_off: String,
+   pub canvas_is_infinite: String,
+   pub canvas_is_bounded: Formatted,
+   pub make_canvas_infinite: String,
+   pub canvas_background: Formatted,
+   pub background: Map<String>,
+   pub change_background: String,
+   pub chunk_encoding_format: Formatted,
+   pub chunk_format: Map<String>,
+   pub chunk_encoding_quality: Formatted,
+   pub chunk_encoding_batch_size: Formatted,
+   pub change_chunk_encoding: String,
+   pub page_name: Formatted,
+   pub add_page: String,
+   pub layers: String,
+   pub add_layer: String,
+   pub layer_name: Formatted,
+   pub draw_on_layer: String,
+   pub show_layer: String,
+   pub hide_layer: String,
+   pub layer_opacity: Formatted,
+   pub history: String,
+   pub share_palette: String,
+   pub share_palette_hint: String,
+   pub stop_sharing_palette: String,
+   pub palette_is_shared: String,
+   pub share_bookmarks: String,
+   pub share_bookmarks_hint: String,
+   pub stop_sharing_bookmarks: String,
+   pub bookmarks_are_shared: String,
+   pub no_invites_yet: String,
+   pub copy_invite: String,
+   pub invite_copied: String,
+   pub new_single_use_invite: String,
+   pub new_expiring_invite: String,
+   pub revoke_invites: String,
+   pub invites_revoked: String,
+   pub room_is_open: String,
+   pub room_is_invite_only: String,
+   pub require_invites: String,
+   pub allow_room_id: String,
+   pub revert_changes_from_last: String,
+   pub revert: String,
+   pub someones_changes_were_reverted: Formatted,
+   pub nothing_to_undo: String,
+   pub nothing_to_redo: String,
+   pub welcome_to_room: Formatted,
+   pub make_host: String,
+   pub block: String,
+   pub unblock: String,
+   pub go_to_cursor: String,
+   pub follow: String,
+   pub unfollow: String,
+   pub following_someone: Formatted,
+   pub stopped_following: String,
+   pub peer_latency: Formatted,
+   pub someone_was_blocked: Formatted,
+   pub someone_was_unblocked: Formatted,
+
+   pub voice_chat_hint: String,
+   pub in_voice_chat: String,
+   pub join_voice_chat: String,
+   pub leave_voice_chat: String,
+   pub mute: String,
+   pub unmute: String,
+
+   pub audit_log: String,
+   pub audit_log_export: String,
+   pub audit_log_empty: String,
+   pub audit_log_just_now: String,
+   pub audit_log_minutes_ago: Formatted,
+   pub audit_event: Map<Formatted>,
+
+   pub debug_console: String,
+   pub debug_console_hint: String,
+
+   pub network_stats_throughput: Formatted,
+   pub network_stats_packets: Formatted,
+   pub network_stats_relay_rtt: Formatted,
+   pub network_stats_relay_rtt_unknown: String,
+
+   pub perf_hud_frame_time: Formatted,
+   pub perf_hud_metric: Formatted,
+   pub perf_metric: Map<String>,
+
+   pub keyboard_shortcuts: String,
+   pub keyboard_shortcuts_press_a_key: String,
+   pub keyboard_shortcuts_reset: String,
+   pub keymap_section: Map<String>,
+   pub shortcut: Map<String>,
+
+   pub crash_report_title: String,
+   pub crash_report_found: Formatted,
+
+   pub unsaved_changes_title: String,
+   pub unsaved_changes_description: String,
+
    //
    // Color picker
    //
    pub click_to_edit_color: String,
+   pub palette_color_hint: String,
+   pub add_color_to_palette: String,
+   pub room_palette_color: String,
+   pub recent_color: String,
    pub eraser: String,
    pub rgb_hex_code: String,
 
@@ -64,7 +286,13 @@ pub struct Strings {
    //
    pub fd_supported_image_files: String,
    pub fd_png_file: String,
+   pub fd_supported_documents: String,
+   pub fd_pdf_document: String,
+   pub fd_tiff_document: String,
+   pub fd_jpeg_file: String,
+   pub fd_svg_file: String,
    pub fd_netcanv_canvas: String,
+   pub fd_csv_file: String,
 
    //
    // Errors
@@ -74,6 +302,10 @@ pub struct Strings {
    pub error_nickname_must_not_be_empty: String,
    pub error_nickname_too_long: Formatted,
    pub error_invalid_room_id_length: Formatted,
+   pub error_invalid_room_id_characters: String,
+   pub error_invalid_custom_room_id: Formatted,
+   pub error_room_name_too_long: Formatted,
+   pub error_room_description_too_long: Formatted,
    pub error_while_performing_action: Formatted,
    pub error_while_processing_action: Formatted,
 }
@@ -84,6 +316,11 @@ pub struct ExpandWithDescription {
    pub description: String,
 }
 
+#[derive(FromLanguage)]
+pub struct ExpandWithTitle {
+   pub title: String,
+}
+
 #[derive(FromLanguage)]
 pub struct LabelledTextField {
    pub label: String,
@@ -0,0 +1,144 @@
+//! Opt-in anonymous usage statistics.
+//!
+//! Only coarse counters are collected: how long NetCanv was running, how many rooms were entered,
+//! how many people were in them at most, and how often each tool and action was used. Nicknames,
+//! room IDs, relay addresses, and canvas contents are never part of a report.
+//!
+//! The counters are kept in memory for the current session. A report is only sent when the user
+//! opts in from the lobby, where it can also be previewed before sending anything.
+
+use std::collections::BTreeMap;
+use std::sync::{mpsc, Mutex};
+
+use futures_util::SinkExt;
+use once_cell::sync::Lazy;
+use serde::Serialize;
+use tokio_tungstenite::tungstenite::{self, Message};
+use web_time::{Duration, Instant};
+
+use crate::config::config;
+
+/// How long closing NetCanv may be held up by sending the report.
+const SEND_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// The kind of session started when entering the paint state.
+#[derive(Clone, Copy)]
+pub enum Session {
+   Host,
+   Join,
+   Offline,
+}
+
+#[derive(Default)]
+struct Counters {
+   rooms_hosted: u32,
+   rooms_joined: u32,
+   offline_sessions: u32,
+   max_peers: u32,
+   features: BTreeMap<String, u32>,
+}
+
+static SESSION_START: Lazy<Instant> = Lazy::new(Instant::now);
+
+static COUNTERS: Lazy<Mutex<Counters>> = Lazy::new(Default::default);
+
+/// The report sent to the endpoint.
+#[derive(Serialize)]
+pub struct Report {
+   pub version: &'static str,
+   pub os: &'static str,
+   /// How long NetCanv has been running, rounded down to whole minutes.
+   pub session_minutes: u64,
+   pub rooms_hosted: u32,
+   pub rooms_joined: u32,
+   pub offline_sessions: u32,
+   /// The largest number of people in a room at once, including ourselves.
+   pub max_peers: u32,
+   /// How many times each tool was selected and each action was performed.
+   pub features: BTreeMap<String, u32>,
+}
+
+/// Starts measuring the length of the session. This should be called as early as possible.
+pub fn init() {
+   Lazy::force(&SESSION_START);
+}
+
+/// Counts a started session.
+pub fn session_started(session: Session) {
+   let mut counters = COUNTERS.lock().unwrap();
+   match session {
+      Session::Host => counters.rooms_hosted += 1,
+      Session::Join => counters.rooms_joined += 1,
+      Session::Offline => counters.offline_sessions += 1,
+   }
+   counters.max_peers = counters.max_peers.max(1);
+}
+
+/// Records the number of people currently in the room, including ourselves.
+pub fn peer_count(count: usize) {
+   let mut counters = COUNTERS.lock().unwrap();
+   counters.max_peers = counters.max_peers.max(count.try_into().unwrap_or(u32::MAX));
+}
+
+/// Counts a use of the feature with the given name, such as `tool-brush`.
+pub fn feature_used(name: &str) {
+   let mut counters = COUNTERS.lock().unwrap();
+   *counters.features.entry(name.to_owned()).or_default() += 1;
+}
+
+/// Returns the report for the current session.
+pub fn report() -> Report {
+   let counters = COUNTERS.lock().unwrap();
+   Report {
+      version: env!("CARGO_PKG_VERSION"),
+      os: std::env::consts::OS,
+      session_minutes: SESSION_START.elapsed().as_secs() / 60,
+      rooms_hosted: counters.rooms_hosted,
+      rooms_joined: counters.rooms_joined,
+      offline_sessions: counters.offline_sessions,
+      max_peers: counters.max_peers,
+      features: counters.features.clone(),
+   }
+}
+
+/// Returns the report exactly as it would be sent.
+pub fn preview() -> String {
+   toml::to_string(&report()).unwrap_or_default()
+}
+
+/// Sends the report for the current session, if the user opted in. This blocks for at most
+/// [`SEND_TIMEOUT`], and is meant to be called once when NetCanv is closing.
+pub fn send_report() {
+   let endpoint = {
+      let config = config();
+      if !config.telemetry.enabled || config.telemetry.endpoint.is_empty() {
+         return;
+      }
+      config.telemetry.endpoint.clone()
+   };
+   let report = preview();
+
+   // This is called from within the async runtime, which cannot be blocked on, so the report is
+   // sent from a separate thread with a runtime of its own.
+   let (tx, rx) = mpsc::channel();
+   std::thread::spawn(move || {
+      let result = tokio::runtime::Builder::new_current_thread()
+         .enable_all()
+         .build()
+         .map_err(tungstenite::Error::Io)
+         .and_then(|runtime| runtime.block_on(send(&endpoint, report)));
+      let _ = tx.send(result);
+   });
+   match rx.recv_timeout(SEND_TIMEOUT) {
+      Ok(Ok(())) => tracing::info!("usage statistics sent"),
+      Ok(Err(error)) => tracing::error!("could not send usage statistics: {}", error),
+      Err(_) => tracing::warn!("sending usage statistics timed out"),
+   }
+}
+
+async fn send(endpoint: &str, report: String) -> Result<(), tungstenite::Error> {
+   let (mut socket, _) = tokio_tungstenite::connect_async(endpoint).await?;
+   socket.send(Message::Text(report)).await?;
+   socket.close(None).await?;
+   Ok(())
+}
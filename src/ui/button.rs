@@ -19,6 +19,8 @@ pub struct ButtonColors {
    pub text: Color,
    pub hover: Color,
    pub pressed: Color,
+   /// The color of the ring drawn around the button while it has keyboard focus.
+   pub focus: Color,
 }
 
 impl ButtonColors {
@@ -102,6 +104,8 @@ impl Button {
       width_hint: Option<f32>,
       extra: impl FnOnce(&mut Ui),
    ) -> ButtonProcessResult {
+      let focus = input.focus_slot();
+
       // horizontal because we need to fit() later
       ui.push((width_hint.unwrap_or(0.0), *height), Layout::Horizontal);
       ui.fill_rounded(colors.fill, *corner_radius);
@@ -117,10 +121,13 @@ impl Button {
          };
          ui.fill_rounded(fill_color, *corner_radius);
       }
+      if focus.focused() {
+         ui.outline_rounded(colors.focus, *corner_radius, 2.0);
+      }
       if let Some((font, tooltip)) = tooltip {
          tooltip.process(ui, input, font);
       }
-      let clicked = ui.clicked(input, MouseButton::Left);
+      let clicked = ui.clicked(input, MouseButton::Left) || input.focus_activated(focus);
 
       let group = ui.rect();
 
@@ -12,6 +12,7 @@ use crate::assets::Assets;
 use crate::backend::{Backend, Framebuffer, Image};
 use crate::color::{AnyColor, Hsv, Okhsv, Srgb};
 use crate::common::ColorMath;
+use crate::config::{self, config};
 use crate::ui::ValueSlider;
 
 use super::view::{Dimension, Dimensions, View};
@@ -40,18 +41,28 @@ pub struct ColorPickerIcons {
 
 /// A color picker.
 pub struct ColorPicker {
-   palette: [AnyColor; Self::NUM_COLORS],
+   palette: Vec<AnyColor>,
    index: usize,
+   /// Whether the palette or the recently used colors changed since they were last saved.
+   palette_edited: bool,
+   /// Colors that were recently used, most recent first.
+   recent_colors: Vec<AnyColor>,
+   /// The palette shared by the host of the room.
+   room_palette: Vec<AnyColor>,
    pub eraser: bool,
 
    window_state: Option<PickerWindowState>,
 }
 
 impl ColorPicker {
-   /// The number of colors in a palette.
-   const NUM_COLORS: usize = 10;
-
-   const DEFAULT_PALETTE: [Color; Self::NUM_COLORS] = [
+   /// The number of colors in the default palette.
+   const NUM_DEFAULT_COLORS: usize = 10;
+   /// The maximum number of colors in a palette.
+   const MAX_COLORS: usize = 16;
+   /// The maximum number of recently used colors remembered.
+   const MAX_RECENT_COLORS: usize = 8;
+
+   const DEFAULT_PALETTE: [Color; Self::NUM_DEFAULT_COLORS] = [
       Color::rgb(0x100820), // Black
       Color::rgb(0x665b78), // Gray
       Color::rgb(0xeff5f0), // White
@@ -64,19 +75,65 @@ impl ColorPicker {
       Color::rgb(0xa315d7), // Purple
    ];
 
-   /// Creates a new color picker.
+   /// Creates a new color picker, with the palette and recently used colors saved in the user
+   /// config.
    pub fn new() -> Self {
-      let palette = Self::DEFAULT_PALETTE.map(|color| Srgb::from_color(color).into());
+      let palette = Self::load_palette();
+      let mut recent_colors = Self::parse_hex_codes(&config().palette.recent);
+      recent_colors.truncate(Self::MAX_RECENT_COLORS);
       Self {
-         palette,
-         index: 0,
-         eraser: false,
          window_state: Some(PickerWindowState::Closed(PickerWindow::new_data(
             palette[0],
          ))),
+         palette,
+         index: 0,
+         palette_edited: false,
+         recent_colors,
+         room_palette: Vec::new(),
+         eraser: false,
       }
    }
 
+   /// Loads the palette from the user config, falling back to the default palette if there's none
+   /// saved.
+   fn load_palette() -> Vec<AnyColor> {
+      let mut palette = Self::parse_hex_codes(&config().palette.colors);
+      palette.truncate(Self::MAX_COLORS);
+      if palette.is_empty() {
+         Self::DEFAULT_PALETTE.iter().map(|&color| Srgb::from_color(color).into()).collect()
+      } else {
+         palette
+      }
+   }
+
+   /// Saves the palette and the recently used colors to the user config.
+   fn save_palette(&mut self) {
+      let colors = Self::to_hex_codes(&self.palette);
+      let recent = Self::to_hex_codes(&self.recent_colors);
+      config::write(|config| {
+         config.palette.colors = colors;
+         config.palette.recent = recent;
+      });
+      self.palette_edited = false;
+   }
+
+   /// Parses colors saved in the user config. Invalid hex codes are skipped.
+   fn parse_hex_codes(hex_codes: &[String]) -> Vec<AnyColor> {
+      hex_codes
+         .iter()
+         .filter_map(|hex_code| PickerWindow::parse_hex_code(hex_code))
+         .map(|color| Srgb::from_color(color).into())
+         .collect()
+   }
+
+   /// Converts colors to hex codes, for saving them in the user config.
+   fn to_hex_codes(colors: &[AnyColor]) -> Vec<String> {
+      Self::to_rgb(colors)
+         .into_iter()
+         .map(|(r, g, b)| format!("#{:02x}{:02x}{:02x}", r, g, b))
+         .collect()
+   }
+
    /// Returns a view for the picker window. This view should be laid out and then passed back to
    /// `process` via [`ColorPickerArgs`].
    pub fn picker_window_view() -> View {
@@ -96,6 +153,55 @@ impl ColorPicker {
    pub fn set_color(&mut self, color: Color) {
       self.eraser = false;
       self.palette[self.index] = Srgb::from_color(color).into();
+      self.palette_edited = true;
+   }
+
+   /// Returns the colors in the palette, as 8-bit RGB triples.
+   pub fn palette_rgb(&self) -> Vec<(u8, u8, u8)> {
+      Self::to_rgb(&self.palette)
+   }
+
+   /// Returns the palette shared by the host of the room, as 8-bit RGB triples.
+   pub fn room_palette_rgb(&self) -> Vec<(u8, u8, u8)> {
+      Self::to_rgb(&self.room_palette)
+   }
+
+   /// Sets the palette shared by the host of the room. An empty palette hides it.
+   pub fn set_room_palette(&mut self, colors: &[(u8, u8, u8)]) {
+      self.room_palette = colors
+         .iter()
+         .take(Self::MAX_COLORS)
+         .map(|&(r, g, b)| Srgb::from_color(Color::new(r, g, b, 255)).into())
+         .collect();
+   }
+
+   fn to_rgb(colors: &[AnyColor]) -> Vec<(u8, u8, u8)> {
+      colors
+         .iter()
+         .map(|&color| {
+            let Color { r, g, b, .. } = Srgb::from(color).to_color(1.0);
+            (r, g, b)
+         })
+         .collect()
+   }
+
+   /// Remembers the currently selected color as recently used. Does nothing if the eraser is
+   /// enabled.
+   pub fn mark_color_used(&mut self) {
+      if self.eraser {
+         return;
+      }
+      // The color is normalized, so that the same color picked in different color spaces is only
+      // remembered once.
+      let color: AnyColor = Srgb::from_color(self.color()).into();
+      if self.recent_colors.first() == Some(&color) {
+         return;
+      }
+      self.recent_colors.retain(|&other| other != color);
+      self.recent_colors.insert(0, color);
+      self.recent_colors.truncate(Self::MAX_RECENT_COLORS);
+      // Saved along with the palette, so that the config isn't written after every stroke.
+      self.palette_edited = true;
    }
 
    /// Sets whether the eraser is enabled.
@@ -115,7 +221,10 @@ impl ColorPicker {
          show_eraser,
       }: ColorPickerArgs,
    ) {
-      // The palette.
+      // The palette. Right-clicking a color replaces it with the selected one, and
+      // Shift+right-clicking removes it.
+      let mut replaced = None;
+      let mut removed = None;
       for (index, &color) in self.palette.clone().iter().enumerate() {
          ui.push((16.0, ui.height()), Layout::Freeform);
          let y_offset = ui.height()
@@ -127,8 +236,12 @@ impl ColorPicker {
                0.8
             };
          let y_offset = y_offset.round();
-         if self.index == index && self.window_id().is_none() {
-            Tooltip::top(&assets.tr.click_to_edit_color).process(ui, input, &assets.sans);
+         if self.index == index {
+            if self.window_id().is_none() {
+               Tooltip::top(&assets.tr.click_to_edit_color).process(ui, input, &assets.sans);
+            }
+         } else {
+            Tooltip::top(&assets.tr.palette_color_hint).process(ui, input, &assets.sans);
          }
          if ui.hover(input) && input.mouse_button_just_pressed(MouseButton::Left) {
             self.eraser = false;
@@ -138,6 +251,13 @@ impl ColorPicker {
             self.index = index;
             self.window_data_mut(wm).color = self.palette[self.index];
          }
+         if ui.hover(input) && input.mouse_button_just_pressed(MouseButton::Right) {
+            if input.shift_is_down() {
+               removed = Some(index);
+            } else if !self.eraser && index != self.index {
+               replaced = Some(index);
+            }
+         }
          ui.draw(|ui| {
             let rect = Rect::new(point(0.0, y_offset), ui.size());
             let color = Srgb::from(color).to_color(1.0);
@@ -146,6 +266,65 @@ impl ColorPicker {
          ui.pop();
       }
 
+      // The slot for adding the selected color to the palette.
+      if self.palette.len() < Self::MAX_COLORS && !self.eraser {
+         ui.space(4.0);
+         ui.push((16.0, ui.height()), Layout::Freeform);
+         let y_offset = (ui.height() * if ui.hover(input) { 0.7 } else { 0.8 }).round();
+         Tooltip::top(&assets.tr.add_color_to_palette).process(ui, input, &assets.sans);
+         if ui.clicked(input, MouseButton::Left) {
+            self.palette.push(self.palette[self.index]);
+            self.index = self.palette.len() - 1;
+            self.palette_edited = true;
+         }
+         let outline = assets.colors.text.with_alpha(128);
+         ui.draw(|ui| {
+            let rect = Rect::new(point(0.5, y_offset + 0.5), ui.size() - vector(1.0, 1.0));
+            ui.render().outline(rect, outline, 4.0, 1.0);
+         });
+         ui.pop();
+      }
+
+      // The room's palette, drawn as squares to tell it apart from recently used colors, which
+      // are drawn as circles. Clicking either puts the color in place of the selected one.
+      let mut picked = None;
+      if !self.room_palette.is_empty() {
+         ui.space(12.0);
+         for &color in &self.room_palette {
+            let tooltip = Tooltip::top(&assets.tr.room_palette_color);
+            if Self::process_quick_swatch(ui, input, assets, color, 3.0, tooltip) {
+               picked = Some(color);
+            }
+         }
+      }
+      if !self.recent_colors.is_empty() {
+         ui.space(12.0);
+         for &color in &self.recent_colors {
+            let tooltip = Tooltip::top(&assets.tr.recent_color);
+            if Self::process_quick_swatch(ui, input, assets, color, 6.0, tooltip) {
+               picked = Some(color);
+            }
+         }
+      }
+      if let Some(color) = picked {
+         self.eraser = false;
+         self.palette[self.index] = color;
+         self.palette_edited = true;
+      }
+
+      if let Some(index) = replaced {
+         self.palette[index] = self.palette[self.index];
+         self.palette_edited = true;
+      }
+      if let Some(index) = removed.filter(|_| self.palette.len() > 1) {
+         self.palette.remove(index);
+         if index < self.index || self.index == self.palette.len() {
+            self.index -= 1;
+         }
+         self.window_data_mut(wm).color = self.palette[self.index];
+         self.palette_edited = true;
+      }
+
       if show_eraser {
          ui.space(16.0);
          if Button::with_icon(
@@ -171,6 +350,7 @@ impl ColorPicker {
       // The palette color, saved from what was chosen in the picker window.
       if self.window_data(wm).color_changed {
          self.palette[self.index] = self.window_data(wm).color;
+         self.palette_edited = true;
       } else {
          self.window_data_mut(wm).color = self.palette[self.index];
       }
@@ -185,6 +365,39 @@ impl ColorPicker {
             self.toggle_picker_window(ui, wm, window_view);
          }
       }
+
+      // Save the palette once editing it is done, rather than on every little change.
+      let editing = self.window_id().is_some() || input.mouse_button_is_down(MouseButton::Left);
+      if self.palette_edited && !editing {
+         self.save_palette();
+      }
+   }
+
+   /// Processes a small swatch of a color that can be picked with a single click. Returns whether
+   /// the swatch was clicked.
+   fn process_quick_swatch(
+      ui: &mut Ui,
+      input: &Input,
+      assets: &Assets,
+      color: AnyColor,
+      corner_radius: f32,
+      tooltip: Tooltip,
+   ) -> bool {
+      let size = 12.0;
+      ui.push((size, ui.height()), Layout::Freeform);
+      ui.push((size, size), Layout::Freeform);
+      ui.align((AlignH::Left, AlignV::Middle));
+      ui.fill_rounded(Srgb::from(color).to_color(1.0), corner_radius);
+      ui.outline_rounded(Color::BLACK.with_alpha(32), corner_radius - 0.5, 1.0);
+      if ui.hover(input) {
+         ui.fill_rounded(Color::WHITE.with_alpha(96), corner_radius);
+      }
+      tooltip.process(ui, input, &assets.sans);
+      let clicked = ui.clicked(input, MouseButton::Left);
+      ui.pop();
+      ui.pop();
+      ui.space(4.0);
+      clicked
    }
 
    /// Toggles the picker window on or off, depending on whether it's already open or not.
@@ -193,6 +406,7 @@ impl ColorPicker {
          PickerWindowState::Open(window_id) => {
             let data = wm.close_window(window_id);
             self.window_state = Some(PickerWindowState::Closed(data));
+            self.mark_color_used();
          }
          PickerWindowState::Closed(data) => {
             let content =
@@ -576,6 +790,7 @@ impl PickerWindow {
       // The value sliders below the text field.
       let value_slider = ValueSliderArgs {
          color: assets.colors.slider,
+         focus: assets.colors.focus_ring,
          font: &assets.sans,
          label_width: Some(16.0),
          value_width: Some(40.0),
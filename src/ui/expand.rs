@@ -24,6 +24,8 @@ pub struct ExpandColors {
    pub icon: Color,
    pub hover: Color,
    pub pressed: Color,
+   /// The color of the ring drawn around the label while the Expand has keyboard focus.
+   pub focus: Color,
 }
 
 /// Processing arguments for an Expand.
@@ -72,6 +74,7 @@ impl Expand {
          &icons.expand
       };
       let height = icon.height() as f32;
+      let focus = input.focus_slot();
 
       ui.push((ui.width(), height), Layout::Freeform);
 
@@ -110,13 +113,19 @@ impl Expand {
          });
          // events
          if input.action(MouseButton::Left) == (true, ButtonState::Released) {
-            self.expanded = !self.expanded;
             result.just_clicked = true;
-            if self.expanded {
-               result.just_expanded = true;
-            }
          }
       }
+      if focus.focused() {
+         ui.outline_rounded(colors.focus, 4.0, 2.0);
+      }
+      if input.focus_activated(focus) {
+         result.just_clicked = true;
+      }
+      if result.just_clicked {
+         self.expanded = !self.expanded;
+         result.just_expanded = self.expanded;
+      }
       ui.pop();
 
       ui.pop();
@@ -1,12 +1,13 @@
 //! Simplified input handling facility.
 
 use std::borrow::Cow;
+use std::cell::Cell;
 use std::ops::{BitAnd, BitOr};
 use web_time::Instant;
 
 use crate::backend::winit::dpi::PhysicalPosition;
 pub use crate::backend::winit::event::{ElementState, MouseButton, VirtualKeyCode};
-use crate::backend::winit::event::{KeyboardInput, WindowEvent};
+use crate::backend::winit::event::{Ime, KeyboardInput, Touch, TouchPhase, WindowEvent};
 use crate::backend::winit::window::{CursorIcon, Window};
 use netcanv_renderer::paws::{point, vector, Point, Vector};
 use serde::de::Visitor;
@@ -22,6 +23,10 @@ pub struct Input {
    mouse_position: Point,
    previous_mouse_position: Point,
    mouse_scroll: Vector,
+   /// The touchpad pinch gesture's magnification delta.
+   touchpad_magnify: f32,
+   /// The touchpad rotation gesture's delta, in degrees.
+   touchpad_rotate: f32,
 
    mouse_button_is_down: [bool; MOUSE_BUTTON_COUNT],
    mouse_button_just_pressed: [bool; MOUSE_BUTTON_COUNT],
@@ -34,11 +39,33 @@ pub struct Input {
    previous_cursor: CursorIcon,
    cursor: CursorIcon,
 
+   // stylus input
+   /// The pressure the stylus is pressed against the screen with, from 0 to 1. `None` while it
+   /// isn't touching the screen, or if it doesn't report pressure.
+   stylus_pressure: Option<f32>,
+
    // keyboard input
    char_buffer: Vec<char>,
 
    key_just_typed: [bool; KEY_CODE_COUNT],
    key_is_down: [bool; KEY_CODE_COUNT],
+   /// The last key typed during this frame, used for capturing key bindings.
+   last_key_typed: Option<VirtualKeyCode>,
+
+   // input method
+   ime_preedit: String,
+   ime_preedit_cursor: Option<(usize, usize)>,
+   previous_ime_position: Option<Point>,
+   ime_position: Option<Point>,
+
+   // keyboard focus
+   /// The number of elements that were added to the tab order so far during this frame.
+   focusable_count: Cell<usize>,
+   /// The number of elements that were added to the tab order during the previous frame.
+   previous_focusable_count: usize,
+   /// The position of the element with keyboard focus in the tab order.
+   keyboard_focus: Cell<Option<usize>>,
+   focus_moved: bool,
 
    // time
    time_origin: Instant,
@@ -51,6 +78,8 @@ impl Input {
          mouse_position: point(0.0, 0.0),
          previous_mouse_position: point(0.0, 0.0),
          mouse_scroll: vector(0.0, 0.0),
+         touchpad_magnify: 0.0,
+         touchpad_rotate: 0.0,
 
          mouse_button_is_down: [false; MOUSE_BUTTON_COUNT],
          mouse_button_just_pressed: [false; MOUSE_BUTTON_COUNT],
@@ -63,9 +92,22 @@ impl Input {
          previous_cursor: CursorIcon::Default,
          cursor: CursorIcon::Default,
 
+         stylus_pressure: None,
+
          char_buffer: Vec::new(),
          key_just_typed: [false; KEY_CODE_COUNT],
          key_is_down: [false; KEY_CODE_COUNT],
+         last_key_typed: None,
+
+         ime_preedit: String::new(),
+         ime_preedit_cursor: None,
+         previous_ime_position: None,
+         ime_position: None,
+
+         focusable_count: Cell::new(0),
+         previous_focusable_count: 0,
+         keyboard_focus: Cell::new(None),
+         focus_moved: false,
 
          time_origin: Instant::now(),
       }
@@ -76,6 +118,14 @@ impl Input {
       self.mouse_position
    }
 
+   /// Returns the pressure the stylus is pressed against the screen with, from 0 to 1.
+   ///
+   /// Returns `None` while no stylus is touching the screen, as well as for mice and styluses that
+   /// don't report pressure.
+   pub fn stylus_pressure(&self) -> Option<f32> {
+      self.stylus_pressure
+   }
+
    /// Returns the position of the mouse, as it was on the previous frame.
    pub fn previous_mouse_position(&self) -> Point {
       self.previous_mouse_position
@@ -90,6 +140,26 @@ impl Input {
       }
    }
 
+   /// Returns how much the touchpad was pinched this frame. Positive values mean the fingers moved
+   /// apart, ie. the user wants to zoom in.
+   pub fn touchpad_magnify(&self) -> f32 {
+      if self.mouse_buttons_locked() {
+         0.0
+      } else {
+         self.touchpad_magnify
+      }
+   }
+
+   /// Returns how much the touchpad was rotated this frame, in degrees. Positive values mean the
+   /// fingers rotated counterclockwise.
+   pub fn touchpad_rotate(&self) -> f32 {
+      if self.mouse_buttons_locked() {
+         0.0
+      } else {
+         self.touchpad_rotate
+      }
+   }
+
    /// Returns whether mouse clicks are locked.
    fn mouse_buttons_locked(&self) -> bool {
       self.active_mouse_area != self.frame_mouse_area
@@ -178,6 +248,11 @@ impl Input {
       }
    }
 
+   /// Returns the last key typed during this frame, if any.
+   pub fn last_key_typed(&self) -> Option<VirtualKeyCode> {
+      self.last_key_typed
+   }
+
    /// Returns wheter the provided key is down
    pub fn key_is_down(&self, key: VirtualKeyCode) -> bool {
       if let Some(i) = Self::key_index(key) {
@@ -197,6 +272,78 @@ impl Input {
       self.key_is_down(VirtualKeyCode::LShift) || self.key_is_down(VirtualKeyCode::RShift)
    }
 
+   /// Returns whether the Alt key is being held down.
+   pub fn alt_is_down(&self) -> bool {
+      self.key_is_down(VirtualKeyCode::LAlt) || self.key_is_down(VirtualKeyCode::RAlt)
+   }
+
+   /// Returns the text being composed with an input method, along with the byte range of the
+   /// input method's cursor inside of it. The text is empty if nothing is being composed.
+   ///
+   /// Once composition is done, the composed text is received as [typed
+   /// characters][Self::characters_typed].
+   pub fn ime_preedit(&self) -> (&str, Option<(usize, usize)>) {
+      (&self.ime_preedit, self.ime_preedit_cursor)
+   }
+
+   /// Accepts text from an input method during this frame. The input method's candidate window
+   /// is placed at the given position, which should be right below the text cursor.
+   ///
+   /// Input methods are disabled on frames where this is not called, as they take over the
+   /// keyboard.
+   pub fn request_ime(&mut self, position: Point) {
+      self.ime_position = Some(position);
+   }
+
+   /// Adds an element to the tab order, and returns its slot.
+   ///
+   /// The tab order is the order in which elements are processed during a frame, so this must be
+   /// called every frame the element is shown, and not only when it's interacted with.
+   pub fn focus_slot(&self) -> FocusSlot {
+      let index = self.focusable_count.get();
+      self.focusable_count.set(index + 1);
+      FocusSlot {
+         index,
+         focused: self.keyboard_focus.get() == Some(index),
+      }
+   }
+
+   /// Gives keyboard focus to the element in the given slot, eg. after it was clicked.
+   pub fn take_focus(&self, slot: FocusSlot) {
+      self.keyboard_focus.set(Some(slot.index));
+   }
+
+   /// Returns whether keyboard focus was moved with Tab during this frame.
+   pub fn focus_moved(&self) -> bool {
+      self.focus_moved
+   }
+
+   /// Returns whether the element in the given slot has keyboard focus, and was just activated
+   /// with Enter or Space.
+   pub fn focus_activated(&self, slot: FocusSlot) -> bool {
+      slot.focused
+         && (self.action(VirtualKeyCode::Return) == (true, true)
+            || self.action(VirtualKeyCode::Space) == (true, true))
+   }
+
+   /// Moves keyboard focus to the next element in the tab order, or the previous one if Shift is
+   /// held down. Focus wraps around once either end is reached.
+   fn move_focus(&mut self) {
+      let count = self.previous_focusable_count;
+      if count == 0 {
+         return;
+      }
+      let backwards = self.shift_is_down();
+      let index = match self.keyboard_focus.get() {
+         None if backwards => count - 1,
+         None => 0,
+         Some(index) if backwards => (index.min(count) + count - 1) % count,
+         Some(index) => (index + 1) % count,
+      };
+      self.keyboard_focus.set(Some(index));
+      self.focus_moved = true;
+   }
+
    /// Returns the time elapsed since this `Input` was created, in seconds.
    pub fn time_in_seconds(&self) -> f32 {
       let now = self.time_origin.elapsed();
@@ -235,8 +382,37 @@ impl Input {
             }
          }
 
+         // Multiple gesture events may arrive within a single frame, so they're accumulated.
+         WindowEvent::TouchpadMagnify { delta, .. } => self.touchpad_magnify += *delta as f32,
+         WindowEvent::TouchpadRotate { delta, .. } => self.touchpad_rotate += *delta,
+
+         WindowEvent::Touch(Touch { phase, force, .. }) => {
+            self.stylus_pressure = match phase {
+               TouchPhase::Started | TouchPhase::Moved => {
+                  force.map(|force| (force.normalized() as f32).clamp(0.0, 1.0))
+               }
+               TouchPhase::Ended | TouchPhase::Cancelled => None,
+            };
+         }
+
          WindowEvent::ReceivedCharacter(c) => self.char_buffer.push(*c),
 
+         WindowEvent::Ime(ime) => match ime {
+            Ime::Preedit(text, cursor) => {
+               self.ime_preedit.clone_from(text);
+               self.ime_preedit_cursor = *cursor;
+            }
+            Ime::Commit(text) => {
+               self.ime_preedit.clear();
+               self.ime_preedit_cursor = None;
+               self.char_buffer.extend(text.chars());
+            }
+            Ime::Enabled | Ime::Disabled => {
+               self.ime_preedit.clear();
+               self.ime_preedit_cursor = None;
+            }
+         },
+
          WindowEvent::KeyboardInput {
             input:
                KeyboardInput {
@@ -263,15 +439,33 @@ impl Input {
       }
       self.previous_mouse_position = self.mouse_position;
       self.mouse_scroll = vector(0.0, 0.0);
+      self.touchpad_magnify = 0.0;
+      self.touchpad_rotate = 0.0;
       self.frame_mouse_area = self.processed_mouse_area;
       if self.cursor != self.previous_cursor {
          self.previous_cursor = self.cursor;
          window.set_cursor_icon(self.cursor);
       }
+      if self.ime_position != self.previous_ime_position {
+         if self.ime_position.is_some() != self.previous_ime_position.is_some() {
+            window.set_ime_allowed(self.ime_position.is_some());
+         }
+         if let Some(position) = self.ime_position {
+            window.set_ime_position(PhysicalPosition::new(position.x, position.y));
+         }
+         self.previous_ime_position = self.ime_position;
+      }
+      self.ime_position = None;
       for state in &mut self.key_just_typed {
          *state = false;
       }
+      self.last_key_typed = None;
       self.char_buffer.clear();
+      self.previous_focusable_count = self.focusable_count.replace(0);
+      if self.keyboard_focus.get() >= Some(self.previous_focusable_count) {
+         self.keyboard_focus.set(None);
+      }
+      self.focus_moved = false;
    }
 
    /// Returns the numeric index of the mouse given button, or `None` if the mouse button is not
@@ -299,6 +493,8 @@ impl Input {
                self.mouse_button_is_down[i] = true;
                self.mouse_button_just_pressed[i] = true;
                self.click_positions[i] = self.mouse_position();
+               // Focus rings are only shown while navigating with the keyboard.
+               self.keyboard_focus.set(None);
             }
             ElementState::Released => {
                self.mouse_button_is_down[i] = false;
@@ -324,6 +520,12 @@ impl Input {
          if state == ElementState::Pressed {
             self.key_just_typed[i] = true;
             self.key_is_down[i] = true;
+            self.last_key_typed = Some(key);
+            match key {
+               VirtualKeyCode::Tab => self.move_focus(),
+               VirtualKeyCode::Escape => self.keyboard_focus.set(None),
+               _ => (),
+            }
          }
 
          if state == ElementState::Released {
@@ -333,6 +535,20 @@ impl Input {
    }
 }
 
+/// An element's place in the tab order. See [`Input::focus_slot`].
+#[derive(Debug, Clone, Copy)]
+pub struct FocusSlot {
+   index: usize,
+   focused: bool,
+}
+
+impl FocusSlot {
+   /// Returns whether the element has keyboard focus.
+   pub fn focused(&self) -> bool {
+      self.focused
+   }
+}
+
 //
 // Actions
 //
@@ -450,9 +666,12 @@ impl Modifier {
    pub const SHIFT: Self = Self(0b1);
    /// The Ctrl key.
    pub const CTRL: Self = Self(0b10);
+   /// The Alt key.
+   pub const ALT: Self = Self(0b100);
 
    const SHIFT_STR: &'static str = "Shift";
    const CTRL_STR: &'static str = "Ctrl";
+   const ALT_STR: &'static str = "Alt";
 
    /// Creates modifiers from the given input.
    pub fn from_input(input: &Input) -> Self {
@@ -463,6 +682,9 @@ impl Modifier {
       if input.ctrl_is_down() {
          mods = mods | Self::CTRL;
       }
+      if input.alt_is_down() {
+         mods = mods | Self::ALT;
+      }
       mods
    }
 
@@ -476,9 +698,14 @@ impl Modifier {
       (*self & Self::CTRL) == Self::CTRL
    }
 
+   /// Returns whether the alt key is included in this set.
+   pub fn alt(&self) -> bool {
+      (*self & Self::ALT) == Self::ALT
+   }
+
    /// Returns the cardinality of this set.
    pub fn card(&self) -> usize {
-      self.shift() as usize + self.ctrl() as usize
+      self.shift() as usize + self.ctrl() as usize + self.alt() as usize
    }
 }
 
@@ -512,6 +739,9 @@ impl Serialize for Modifier {
       if self.ctrl() {
          seq.serialize_element(Self::CTRL_STR)?;
       }
+      if self.alt() {
+         seq.serialize_element(Self::ALT_STR)?;
+      }
       seq.end()
    }
 }
@@ -539,6 +769,7 @@ impl<'de> Deserialize<'de> for Modifier {
                match &*element {
                   Modifier::SHIFT_STR => modifier = modifier | Modifier::SHIFT,
                   Modifier::CTRL_STR => modifier = modifier | Modifier::CTRL,
+                  Modifier::ALT_STR => modifier = modifier | Modifier::ALT,
                   _ => return Err(serde::de::Error::custom("invalid modifier")),
                }
             }
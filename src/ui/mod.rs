@@ -27,6 +27,10 @@ pub use slider::*;
 pub use text_field::*;
 pub use tooltip::*;
 
+/// The UI, laid out in groups pushed onto a stack.
+///
+/// Note that elements in a [`Layout::HorizontalRev`] group go from right to left rather than left
+/// to right, so the first element pushed onto it ends up on the far right.
 pub type Ui = paws::Ui<Backend>;
 
 pub trait UiInput {
@@ -170,45 +174,10 @@ impl UiElements for Ui {
 }
 
 /// A trait implemented by elements that can be (un)focused.
+///
+/// Elements that take text input keep their own focus, which they keep in sync with keyboard
+/// focus; see [`Input::focus_slot`] for how the tab order is formed.
 pub trait Focus {
    fn focused(&self) -> bool;
    fn set_focus(&mut self, focused: bool);
 }
-
-/// Creates a _focus chain_, that is, a list of elements that can be `Tab`bed between.
-pub fn chain_focus(input: &Input, fields: &mut [&mut dyn Focus]) {
-   macro_rules! process_focus_change {
-      ($had_focus:expr, $element:expr) => {
-         if $had_focus {
-            $element.set_focus(true);
-            return;
-         }
-         if $element.focused() {
-            $element.set_focus(false);
-            $had_focus = true;
-         }
-      };
-   }
-
-   let mut had_focus = false;
-
-   match input.action((Modifier::SHIFT, VirtualKeyCode::Tab)) {
-      (true, true) => {
-         for element in fields.iter_mut().rev() {
-            process_focus_change!(had_focus, element);
-         }
-         if !fields.is_empty() {
-            fields[fields.len() - 1].set_focus(true);
-         }
-      }
-      (false, true) => {
-         for element in fields.iter_mut() {
-            process_focus_change!(had_focus, element);
-         }
-         if !fields.is_empty() {
-            fields[0].set_focus(true);
-         }
-      }
-      _ => (),
-   }
-}
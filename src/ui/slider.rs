@@ -30,6 +30,8 @@ pub struct Slider {
 pub struct SliderArgs {
    pub width: f32,
    pub color: Color,
+   /// The color of the ring drawn around the knob while the slider has keyboard focus.
+   pub focus: Color,
 }
 
 impl Slider {
@@ -58,9 +60,14 @@ impl Slider {
       &mut self,
       ui: &mut Ui,
       input: &Input,
-      SliderArgs { width, color }: SliderArgs,
+      SliderArgs {
+         width,
+         color,
+         focus: focus_color,
+      }: SliderArgs,
    ) -> SliderProcessResult {
       let previous_value = self.value();
+      let focus = input.focus_slot();
 
       ui.push((width, ui.height()), Layout::Freeform);
 
@@ -74,13 +81,21 @@ impl Slider {
          self.value = ui.mouse_position(input).x / ui.width();
       }
 
+      let step_amount = match self.step {
+         SliderStep::Discrete(increment) => increment / self.step_count() as f32 * 2.0,
+         SliderStep::Smooth => 8.0 / width,
+      };
       if ui.hover(input) {
          if let (true, Some(scroll)) = input.action(MouseScroll) {
-            let scroll_amount = match self.step {
-               SliderStep::Discrete(increment) => increment / self.step_count() as f32 * 2.0,
-               SliderStep::Smooth => 8.0 / width,
-            };
-            self.value += scroll.y * scroll_amount;
+            self.value += scroll.y * step_amount;
+         }
+      }
+      if focus.focused() {
+         if input.action(VirtualKeyCode::Left) == (true, true) {
+            self.value -= step_amount;
+         }
+         if input.action(VirtualKeyCode::Right) == (true, true) {
+            self.value += step_amount;
          }
       }
 
@@ -113,6 +128,9 @@ impl Slider {
          }
 
          ui.render().fill_circle(point(x, y), 5.0, color);
+         if focus.focused() {
+            ui.render().outline_circle(point(x, y), 8.0, focus_color, 2.0);
+         }
       });
 
       ui.pop();
@@ -180,6 +198,7 @@ impl ValueUnit {
 #[derive(Clone, Copy)]
 pub struct ValueSliderArgs<'f> {
    pub color: Color,
+   pub focus: Color,
    pub font: &'f Font,
    pub label_width: Option<f32>,
    pub value_width: Option<f32>,
@@ -216,6 +235,7 @@ impl ValueSlider {
       input: &Input,
       ValueSliderArgs {
          color,
+         focus,
          font,
          label_width,
          value_width,
@@ -244,6 +264,7 @@ impl ValueSlider {
          SliderArgs {
             width: ui.remaining_width() - value_width,
             color,
+            focus,
          },
       );
       ui.horizontal_label(font, &value, color, Some((value_width, AlignH::Right)));
@@ -5,6 +5,7 @@ use std::ops::Range;
 use crate::backend::winit::window::CursorIcon;
 use netcanv_renderer::Font as FontTrait;
 use paws::{point, vector, AlignH, AlignV, Color, Layout, LineCap, Rect, Renderer};
+use unicode_segmentation::{GraphemeCursor, UnicodeSegmentation};
 
 use crate::backend::Font;
 use crate::clipboard;
@@ -84,6 +85,8 @@ impl TextField {
          hint,
       }: TextFieldArgs,
    ) -> TextFieldProcessResult {
+      let focus = input.focus_slot();
+
       ui.push((width, Self::height(font)), Layout::Freeform);
 
       // Rendering: box
@@ -105,9 +108,20 @@ impl TextField {
       ui.clip();
       ui.render().translate(vector(-self.scroll_x, 0.0));
 
+      // Text being composed with an input method is shown at the cursor until it's committed.
+      let (preedit, preedit_cursor) = match input.ime_preedit() {
+         (preedit, cursor) if self.focused => (preedit.to_owned(), cursor),
+         _ => (String::new(), None),
+      };
+      let preedit_x = font.text_width(&self.text[..self.selection.cursor()]);
+      let caret_x = match preedit_cursor {
+         Some((start, _)) => preedit_x + font.text_width(&preedit[..start]),
+         None => preedit_x + font.text_width(&preedit),
+      };
+
       // Rendering: hint
       if let Some(hint) = hint {
-         if self.text.is_empty() {
+         if self.text.is_empty() && preedit.is_empty() {
             ui.text(font, hint, colors.text_hint, (AlignH::Left, AlignV::Middle));
          }
       }
@@ -116,8 +130,7 @@ impl TextField {
          && (input.time_in_seconds() - self.blink_start) % Self::BLINK_PERIOD < Self::HALF_BLINK
       {
          ui.draw(|ui| {
-            let current_text = &self.text[..self.selection.cursor()];
-            let x = font.text_width(current_text);
+            let x = caret_x;
 
             // While we have the caret's horizontal position already calculated,
             // also process scrolling.
@@ -130,7 +143,7 @@ impl TextField {
          });
       }
 
-      if self.selection.cursor != self.selection.anchor {
+      if self.selection.cursor != self.selection.anchor && preedit.is_empty() {
          ui.draw(|ui| {
             // Get all the text starting from the start of the textbox to the first position
             // of the selection.
@@ -153,17 +166,44 @@ impl TextField {
          });
       }
 
-      ui.text(
-         font,
-         &self.text,
-         colors.text,
-         (AlignH::Left, AlignV::Middle),
-      );
+      if preedit.is_empty() {
+         ui.text(
+            font,
+            &self.text,
+            colors.text,
+            (AlignH::Left, AlignV::Middle),
+         );
+      } else {
+         let (before, after) = self.text.split_at(self.selection.cursor());
+         ui.text(
+            font,
+            &format!("{}{}{}", before, preedit, after),
+            colors.text,
+            (AlignH::Left, AlignV::Middle),
+         );
+         // The composed text is underlined, like in other programs.
+         ui.draw(|ui| {
+            let width = font.text_width(&preedit);
+            let y = (Self::height(font) * 0.8).round();
+            ui.line(
+               point(preedit_x, y),
+               point(preedit_x + width, y),
+               colors.text,
+               LineCap::Butt,
+               1.0,
+            );
+         });
+      }
 
       ui.render().pop();
 
       // Process events
-      let process_result = self.process_events(ui, input, font);
+      let mut process_result = self.process_events(ui, input, font);
+      self.sync_keyboard_focus(input, focus, &mut process_result);
+      if self.focused {
+         let caret = vector(caret_x - self.scroll_x, Self::height(font));
+         input.request_ime(ui.position() + caret);
+      }
 
       ui.pop();
       ui.pop();
@@ -231,35 +271,34 @@ impl TextField {
 
    /// Appends a character to the cursor position, or replaces selection if any.
    fn append(&mut self, ch: char) {
-      if self.selection.len() > 0 {
-         let mut bytes = [0; 4];
-         self.text.replace_range(self.selection.normalize(), ch.encode_utf8(&mut bytes));
-         self.selection.move_to(TextPosition(self.selection.start()));
-         self.selection.move_right(&self.text, false);
-      } else {
-         self.text.insert(self.selection.cursor(), ch);
-         self.selection.move_right(&self.text, false);
-      }
+      let start = self.selection.start();
+      let mut bytes = [0; 4];
+      self.text.replace_range(self.selection.normalize(), ch.encode_utf8(&mut bytes));
+      // The cursor is placed right after the character rather than after the grapheme, as
+      // combining characters typed next still belong to the same grapheme.
+      self.selection.move_to(TextPosition(start + ch.len_utf8()));
    }
 
-   /// Removes a character at cursor position, or removes selection if any.
+   /// Removes a grapheme at cursor position, or removes selection if any.
    fn backspace(&mut self) {
       if self.selection.len() != 0 {
          self.delete();
       } else if self.selection.cursor() > 0 {
+         let end = self.selection.cursor();
          self.selection.move_left(&self.text, false);
-         self.text.remove(self.selection.cursor());
+         self.text.drain(self.selection.cursor()..end);
       }
    }
 
-   /// Removes character after cursor position.
+   /// Removes grapheme after cursor position.
    /// Or selection if any.
    fn delete(&mut self) {
       if self.selection.len() != 0 {
          self.text.drain(self.selection.normalize());
          self.selection.move_to(TextPosition(self.selection.start()));
       } else if self.selection.cursor() != self.text.len() {
-         self.text.remove(self.selection.cursor());
+         let end = self.selection.cursor.next(&self.text).0;
+         self.text.drain(self.selection.cursor()..end);
       }
    }
 
@@ -270,7 +309,7 @@ impl TextField {
       if self.selection.cursor() == 0 {
          return;
       }
-      while let Some(c) = self.text.get_char(self.selection.cursor() - 1) {
+      while let Some(c) = self.text[..self.selection.cursor()].chars().next_back() {
          if condition(c) {
             self.selection.move_left(&self.text, is_shift_down);
             if self.selection.cursor() == 0 {
@@ -315,14 +354,12 @@ impl TextField {
       }
    }
 
-   /// Returns the character index clicked based on an X position.
+   /// Returns the grapheme index clicked based on an X position.
    fn get_text_position_from_x(&mut self, font: &Font, x: f32) -> TextPosition {
       let mut x_offset = 0.0;
       let mut last_index = 0;
-      for (index, character) in self.text.char_indices() {
-         let mut text = [0; 4];
-         let text = character.encode_utf8(&mut text);
-         let character_width = font.text_width(text);
+      for (index, grapheme) in self.text.grapheme_indices(true) {
+         let character_width = font.text_width(grapheme);
          if x_offset >= x {
             return TextPosition(if x_offset - x > character_width / 2.0 {
                last_index
@@ -341,6 +378,29 @@ impl TextField {
       self.get_text_position_from_x(font, x)
    }
 
+   /// Keeps the text field's focus in sync with keyboard focus. Tabbing into the field focuses it
+   /// and tabbing out unfocuses it, while focusing it some other way moves keyboard focus onto it,
+   /// so that the tab order continues from there.
+   fn sync_keyboard_focus(
+      &mut self,
+      input: &Input,
+      focus: FocusSlot,
+      process_result: &mut TextFieldProcessResult,
+   ) {
+      if input.focus_moved() {
+         if focus.focused() && !self.focused {
+            self.focused = true;
+            self.reset_blink(input);
+            self.selection.move_to(TextPosition(self.text.len()));
+         } else if !focus.focused() && self.focused {
+            self.focused = false;
+            process_result.unfocused = true;
+         }
+      } else if self.focused && !focus.focused() {
+         input.take_focus(focus);
+      }
+   }
+
    /// Processes input events.
    fn process_events(&mut self, ui: &Ui, input: &Input, font: &Font) -> TextFieldProcessResult {
       let mut process_result = TextFieldProcessResult {
@@ -371,6 +431,11 @@ impl TextField {
             self.reset_blink(input);
          }
 
+         // While text is being composed, keys are meant for the input method rather than us.
+         if !input.ime_preedit().0.is_empty() {
+            return process_result;
+         }
+
          // Most of these keybindings don't use the action system, as it would be quite cumbersome
          // and repetitive to represent all the possible textbox actions using it.
 
@@ -498,43 +563,24 @@ impl TextFieldProcessResult {
 }
 
 /// A position inside of a string. This position can be moved left or right.
+///
+/// The position moves by whole graphemes, so that emoji and letters with combining accents are
+/// never split in half.
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 struct TextPosition(usize);
 
-// This impl groups bits to make UTF-8 decoding more readable.
-#[allow(clippy::unusual_byte_groupings)]
 impl TextPosition {
-   /// Returns the next character position in the UTF-8 string.
-   fn next(mut self, text: &str) -> Self {
-      let bytes = text.as_bytes();
-      if let Some(&byte) = bytes.get(self.0) {
-         self.0 += match byte {
-            0x00..=0x7f => 1,
-            x if x & 0b111_00000 == 0b110_00000 => 2,
-            x if x & 0b1111_0000 == 0b1110_0000 => 3,
-            x if x & 0b11111_000 == 0b11110_000 => 4,
-            _ => 1,
-         };
-      }
-      self
+   /// Returns the next grapheme position in the UTF-8 string.
+   fn next(self, text: &str) -> Self {
+      let mut cursor = GraphemeCursor::new(self.0, text.len(), true);
+      // The cursor is given the whole string, so it never asks for more context.
+      Self(cursor.next_boundary(text, 0).ok().flatten().unwrap_or(text.len()))
    }
 
-   /// Returns the previous character position in the UTF-8 string.
-   fn previous(mut self, text: &str) -> Self {
-      if self.0 == 0 {
-         return self;
-      }
-      let bytes = text.as_bytes();
-      while let Some(&byte) = bytes.get(self.0 - 1) {
-         match byte {
-            x if x & 0b11_000000 == 0b10_000000 => self.0 -= 1,
-            _ => {
-               self.0 -= 1;
-               break;
-            }
-         }
-      }
-      self
+   /// Returns the previous grapheme position in the UTF-8 string.
+   fn previous(self, text: &str) -> Self {
+      let mut cursor = GraphemeCursor::new(self.0, text.len(), true);
+      Self(cursor.prev_boundary(text, 0).ok().flatten().unwrap_or(0))
    }
 }
 
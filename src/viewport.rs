@@ -1,12 +1,33 @@
-//! Panning and zooming.
+//! Panning, zooming, and rotating.
 
-use netcanv_renderer::paws::{point, vector, Point, Rect, Vector};
+use std::f32::consts::TAU;
 
-/// A viewport that can be panned around and zoomed into.
+use netcanv_renderer::paws::{point, vector, Point, Rect, Renderer, Vector};
+use netcanv_renderer::RenderBackend;
+use web_time::{Duration, Instant};
+
+use crate::backend::Backend;
+use crate::common::{lerp, lerp_point};
+
+/// A viewport that can be panned around, zoomed into, and rotated.
 #[derive(Debug, Clone)]
 pub struct Viewport {
    pan: Vector,
    zoom_level: f32,
+   /// The clockwise rotation of the canvas on the screen, in radians.
+   rotation: f32,
+   /// The transition the viewport is gliding through, if any.
+   transition: Option<Transition>,
+}
+
+/// An animated transition of the viewport from one place to another.
+#[derive(Debug, Clone)]
+struct Transition {
+   from_pan: Vector,
+   from_zoom_level: f32,
+   to_pan: Vector,
+   to_zoom_level: f32,
+   started: Instant,
 }
 
 /// A rectangle with integer coordinates.
@@ -26,11 +47,25 @@ pub struct Tiles {
 }
 
 impl Viewport {
+   /// How long it takes the viewport to glide over to a new place.
+   const TRANSITION_DURATION: Duration = Duration::from_millis(300);
+
+   /// How much the zoom factor's exponent changes per zoom level.
+   const ZOOM_PER_LEVEL: f32 = 0.25;
+
+   const MIN_ZOOM_LEVEL: f32 = -8.0;
+   const MAX_ZOOM_LEVEL: f32 = 20.0;
+
+   /// The angle the rotation shortcuts rotate the viewport by.
+   pub const ROTATION_STEP: f32 = TAU / 24.0;
+
    /// Creates a new viewport.
    pub fn new() -> Self {
       Self {
          pan: vector(0.0, 0.0),
          zoom_level: 0.0,
+         rotation: 0.0,
+         transition: None,
       }
    }
 
@@ -40,6 +75,8 @@ impl Viewport {
       Self {
          pan: rect.center(),
          zoom_level: 0.0,
+         rotation: 0.0,
+         transition: None,
       }
    }
 
@@ -50,12 +87,54 @@ impl Viewport {
 
    /// Returns the zoom factor.
    pub fn zoom(&self) -> f32 {
-      f32::powf(2.0, self.zoom_level * 0.25)
+      f32::powf(2.0, self.zoom_level * Self::ZOOM_PER_LEVEL)
    }
 
-   /// Pans the viewport around by the given vector.
+   /// Returns the zoom level delta that multiplies the zoom factor by the given factor.
+   pub fn zoom_level_delta(factor: f32) -> f32 {
+      factor.log2() / Self::ZOOM_PER_LEVEL
+   }
+
+   /// Pans the viewport around by the given vector, in screen space.
    pub fn pan_around(&mut self, by: Vector) {
-      self.pan += by * (1.0 / self.zoom());
+      self.transition = None;
+      self.pan += Self::rotate_vector(by, -self.rotation) * (1.0 / self.zoom());
+   }
+
+   /// Pans the viewport such that the given point is in its center.
+   pub fn center_on(&mut self, point: Point) {
+      self.transition = None;
+      self.pan = point;
+   }
+
+   /// Starts gliding the viewport over to the given point, zooming to the given zoom level along
+   /// the way. The glide slows down towards the end, and is interrupted by any other panning or
+   /// zooming.
+   pub fn glide_to(&mut self, point: Point, zoom_level: f32) {
+      self.transition = Some(Transition {
+         from_pan: self.pan,
+         from_zoom_level: self.zoom_level,
+         to_pan: point,
+         to_zoom_level: zoom_level.clamp(Self::MIN_ZOOM_LEVEL, Self::MAX_ZOOM_LEVEL),
+         started: Instant::now(),
+      });
+   }
+
+   /// Advances the transition the viewport is gliding through. This must be called once per
+   /// frame.
+   pub fn update(&mut self) {
+      let Some(transition) = &self.transition else {
+         return;
+      };
+      let t = transition.started.elapsed().as_secs_f32() / Self::TRANSITION_DURATION.as_secs_f32();
+      let t = t.min(1.0);
+      // Cubic ease-out.
+      let eased = 1.0 - (1.0 - t).powi(3);
+      self.pan = lerp_point(transition.from_pan, transition.to_pan, eased);
+      self.zoom_level = lerp(transition.from_zoom_level, transition.to_zoom_level, eased);
+      if t >= 1.0 {
+         self.transition = None;
+      }
    }
 
    /// Zooms in or out of the viewport by the given delta.
@@ -64,15 +143,70 @@ impl Viewport {
    /// _zoom level_, which is linear, and this zoom level is later converted into the
    /// exponential _zoom factor_.
    pub fn zoom_in(&mut self, delta: f32) {
-      self.zoom_level += delta;
-      self.zoom_level = self.zoom_level.clamp(-8.0, 20.0);
+      self.set_zoom_level(self.zoom_level + delta);
+   }
+
+   /// Zooms in or out of the viewport by the given delta, such that the given point stays in the
+   /// same place on the screen.
+   pub fn zoom_in_around(&mut self, delta: f32, point: Point) {
+      let old_zoom = self.zoom();
+      self.zoom_in(delta);
+      self.pan = point - (point - self.pan) * (old_zoom / self.zoom());
+   }
+
+   /// Returns the zoom level, which the zoom factor is derived from.
+   pub fn zoom_level(&self) -> f32 {
+      self.zoom_level
    }
 
-   /// Returns the rectangle visible from the viewport, given the provided window size.
+   /// Sets the zoom level, clamping it to the range that can be zoomed into.
+   pub fn set_zoom_level(&mut self, zoom_level: f32) {
+      self.transition = None;
+      self.zoom_level = zoom_level.clamp(Self::MIN_ZOOM_LEVEL, Self::MAX_ZOOM_LEVEL);
+   }
+
+   /// Returns the rotation of the viewport, in radians.
+   pub fn rotation(&self) -> f32 {
+      self.rotation
+   }
+
+   /// Rotates the viewport clockwise by the given angle, in radians, around its center.
+   pub fn rotate(&mut self, angle: f32) {
+      self.set_rotation(self.rotation + angle);
+   }
+
+   /// Sets the rotation of the viewport, in radians.
+   pub fn set_rotation(&mut self, rotation: f32) {
+      self.rotation = rotation.rem_euclid(TAU);
+      // Snap back to being upright once the rotation gets close enough, so that rotating back and
+      // forth doesn't leave the canvas ever so slightly tilted.
+      if self.rotation < 0.001 || TAU - self.rotation < 0.001 {
+         self.rotation = 0.0;
+      }
+   }
+
+   /// Rotates the viewport clockwise by the given angle, in radians, such that the given point
+   /// stays in the same place on the screen.
+   pub fn rotate_around(&mut self, angle: f32, point: Point) {
+      let old_rotation = self.rotation;
+      self.rotate(angle);
+      self.pan = point - Self::rotate_vector(point - self.pan, old_rotation - self.rotation);
+   }
+
+   /// Rotates a vector clockwise by the given angle, in radians.
+   fn rotate_vector(v: Vector, angle: f32) -> Vector {
+      let (sin, cos) = angle.sin_cos();
+      vector(v.x * cos - v.y * sin, v.x * sin + v.y * cos)
+   }
+
+   /// Returns the rectangle visible from the viewport, given the provided window size. If the
+   /// viewport is rotated, this is the smallest rectangle enclosing the visible area.
    pub fn visible_rect(&self, window_size: Vector) -> Rect {
-      let inv_zoom = 1.0 / self.zoom();
-      let width = window_size.x * inv_zoom;
-      let height = window_size.y * inv_zoom;
+      let half_size = window_size * (0.5 / self.zoom());
+      let corner_a = Self::rotate_vector(half_size, -self.rotation);
+      let corner_b = Self::rotate_vector(vector(half_size.x, -half_size.y), -self.rotation);
+      let width = corner_a.x.abs().max(corner_b.x.abs()) * 2.0;
+      let height = corner_a.y.abs().max(corner_b.y.abs()) * 2.0;
       Rect::new(
          point(self.pan.x - width / 2.0, self.pan.y - height / 2.0),
          vector(width, height),
@@ -97,14 +231,43 @@ impl Viewport {
    ///
    /// This can be used to pick things on the canvas, given a mouse position.
    pub fn to_viewport_space(&self, point: Point, window_size: Vector) -> Point {
-      (point - window_size / 2.0) * (1.0 / self.zoom()) + self.pan
+      Self::rotate_vector(point - window_size / 2.0, -self.rotation) * (1.0 / self.zoom())
+         + self.pan
    }
 
    /// Converts a point from viewport space to screen space.
    ///
    /// This transformation is the inverse of [`Viewport::to_viewport_space`].
    pub fn to_screen_space(&self, point: Point, window_size: Vector) -> Point {
-      (point - self.pan) * self.zoom() + window_size / 2.0
+      Self::rotate_vector((point - self.pan) * self.zoom(), self.rotation) + window_size / 2.0
+   }
+
+   /// Transforms the renderer such that things drawn in viewport space land where they are on
+   /// the screen.
+   pub fn apply_transform(&self, renderer: &mut Backend, window_size: Vector) {
+      renderer.translate(window_size / 2.0);
+      renderer.scale(vector(self.zoom(), self.zoom()));
+      renderer.rotate(self.rotation);
+      renderer.translate(-self.pan);
+   }
+
+   /// Returns a copy of the viewport with its rotation reset.
+   ///
+   /// Rectangles drawn in screen space are laid out with this, and then drawn inside of
+   /// [`Viewport::apply_screen_rotation`], so that they stay lined up with the rotated canvas.
+   pub fn unrotated(&self) -> Self {
+      Self {
+         rotation: 0.0,
+         transition: None,
+         ..self.clone()
+      }
+   }
+
+   /// Rotates the renderer around the center of the window by the viewport's rotation.
+   pub fn apply_screen_rotation(&self, renderer: &mut Backend, window_size: Vector) {
+      renderer.translate(window_size / 2.0);
+      renderer.rotate(self.rotation);
+      renderer.translate(-window_size / 2.0);
    }
 }
 
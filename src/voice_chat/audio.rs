@@ -0,0 +1,183 @@
+//! Voice capture and playback through the system's default audio devices.
+
+use std::collections::hash_map::Entry;
+use std::collections::{HashMap, VecDeque};
+use std::sync::{mpsc, Arc, Mutex};
+
+use audiopus::coder::{Decoder, Encoder};
+use audiopus::packet::Packet;
+use audiopus::{Application, Channels, MutSignals};
+use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
+use cpal::{SampleFormat, SampleRate, Stream, StreamConfig, SupportedStreamConfigRange};
+use netcanv_protocol::relay::{self, PeerId};
+
+use super::VoiceSettings;
+use crate::Error;
+
+/// The sample rate voice is captured and played back at.
+const SAMPLE_RATE: SampleRate = SampleRate(48000);
+
+/// The number of samples in a single captured frame, 20 ms at 48 kHz.
+const FRAME_LEN: usize = 960;
+
+/// The largest number of samples a received frame can decode to, 120 ms at 48 kHz.
+const MAX_DECODED_FRAME_LEN: usize = 5760;
+
+/// The largest number of samples buffered for a single peer, 200 ms at 48 kHz. Older samples are
+/// dropped once a peer's voice falls this far behind, so that latency doesn't build up.
+const MAX_BUFFERED_LEN: usize = 9600;
+
+/// Samples waiting to be played back, per peer.
+type Playback = Arc<Mutex<HashMap<PeerId, VecDeque<f32>>>>;
+
+/// A running voice chat session.
+pub struct VoiceChat {
+   // The streams stop once they're dropped.
+   _input: Stream,
+   _output: Stream,
+   frames: mpsc::Receiver<Vec<u8>>,
+   decoders: HashMap<PeerId, Decoder>,
+   settings: HashMap<PeerId, VoiceSettings>,
+   playback: Playback,
+}
+
+impl VoiceChat {
+   /// Starts capturing voice from the default microphone, and playing back received voice
+   /// through the default speakers.
+   pub fn start() -> netcanv::Result<Self> {
+      let host = cpal::default_host();
+      let input_device = host.default_input_device().ok_or(Error::NoAudioDevice)?;
+      let output_device = host.default_output_device().ok_or(Error::NoAudioDevice)?;
+      let input_config = Self::config(input_device.supported_input_configs()?)?;
+      let output_config = Self::config(output_device.supported_output_configs()?)?;
+
+      let (frame_tx, frames) = mpsc::channel();
+      let encoder = Encoder::new(
+         audiopus::SampleRate::Hz48000,
+         Channels::Mono,
+         Application::Voip,
+      )?;
+      let channels = input_config.channels as usize;
+      let mut pending = Vec::with_capacity(FRAME_LEN);
+      let mut encoded = [0; relay::MAX_VOICE_FRAME_LEN];
+      let input = input_device.build_input_stream(
+         &input_config,
+         move |data: &[f32], _| {
+            // Voice is sent in mono, so all the channels are mixed down into one.
+            for samples in data.chunks(channels) {
+               pending.push(samples.iter().sum::<f32>() / channels as f32);
+               if pending.len() == FRAME_LEN {
+                  match encoder.encode_float(&pending, &mut encoded) {
+                     Ok(len) => {
+                        let _ = frame_tx.send(encoded[..len].to_vec());
+                     }
+                     Err(error) => tracing::error!("cannot encode voice frame: {}", error),
+                  }
+                  pending.clear();
+               }
+            }
+         },
+         |error| tracing::error!("voice capture error: {}", error),
+         None,
+      )?;
+
+      let playback = Playback::default();
+      let channels = output_config.channels as usize;
+      let output = output_device.build_output_stream(
+         &output_config,
+         {
+            let playback = Arc::clone(&playback);
+            move |data: &mut [f32], _| {
+               let mut playback = playback.lock().unwrap();
+               for samples in data.chunks_mut(channels) {
+                  let mixed: f32 =
+                     playback.values_mut().filter_map(|queue| queue.pop_front()).sum();
+                  samples.fill(mixed.clamp(-1.0, 1.0));
+               }
+            }
+         },
+         |error| tracing::error!("voice playback error: {}", error),
+         None,
+      )?;
+
+      input.play()?;
+      output.play()?;
+      Ok(Self {
+         _input: input,
+         _output: output,
+         frames,
+         decoders: HashMap::new(),
+         settings: HashMap::new(),
+         playback,
+      })
+   }
+
+   /// Picks a stream config with floating-point samples at 48 kHz, preferring ones with fewer
+   /// channels.
+   fn config(
+      ranges: impl Iterator<Item = SupportedStreamConfigRange>,
+   ) -> netcanv::Result<StreamConfig> {
+      ranges
+         .filter(|range| {
+            range.sample_format() == SampleFormat::F32
+               && range.min_sample_rate() <= SAMPLE_RATE
+               && range.max_sample_rate() >= SAMPLE_RATE
+         })
+         .min_by_key(|range| range.channels())
+         .map(|range| range.with_sample_rate(SAMPLE_RATE).config())
+         .ok_or(Error::UnsupportedAudioDevice)
+   }
+
+   /// Returns the next captured frame that's ready to be sent, if there is one.
+   pub fn next_frame(&self) -> Option<Vec<u8>> {
+      self.frames.try_recv().ok()
+   }
+
+   /// Decodes a frame received from a peer, and queues it up for playback.
+   pub fn receive(&mut self, peer_id: PeerId, frame: &[u8]) -> netcanv::Result<()> {
+      let settings = self.settings(peer_id);
+      if settings.muted {
+         return Ok(());
+      }
+      let decoder = match self.decoders.entry(peer_id) {
+         Entry::Occupied(entry) => entry.into_mut(),
+         Entry::Vacant(entry) => {
+            entry.insert(Decoder::new(audiopus::SampleRate::Hz48000, Channels::Mono)?)
+         }
+      };
+      let mut samples = [0.0; MAX_DECODED_FRAME_LEN];
+      let len = decoder.decode_float(
+         Some(Packet::try_from(frame)?),
+         MutSignals::try_from(&mut samples[..])?,
+         false,
+      )?;
+
+      let mut playback = self.playback.lock().unwrap();
+      let queue = playback.entry(peer_id).or_default();
+      queue.extend(samples[..len].iter().map(|sample| sample * settings.volume));
+      let excess = queue.len().saturating_sub(MAX_BUFFERED_LEN);
+      queue.drain(..excess);
+      Ok(())
+   }
+
+   /// Forgets about a peer that left the room.
+   pub fn remove_peer(&mut self, peer_id: PeerId) {
+      self.decoders.remove(&peer_id);
+      self.settings.remove(&peer_id);
+      self.playback.lock().unwrap().remove(&peer_id);
+   }
+
+   /// Returns how the peer's voice is played back.
+   pub fn settings(&self, peer_id: PeerId) -> VoiceSettings {
+      self.settings.get(&peer_id).copied().unwrap_or_default()
+   }
+
+   /// Sets how the peer's voice is played back. Muting a peer also drops whatever of their voice
+   /// is still waiting to be played.
+   pub fn set_settings(&mut self, peer_id: PeerId, settings: VoiceSettings) {
+      if settings.muted {
+         self.playback.lock().unwrap().remove(&peer_id);
+      }
+      self.settings.insert(peer_id, settings);
+   }
+}
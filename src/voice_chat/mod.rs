@@ -0,0 +1,44 @@
+//! Opt-in voice chat. Voice is captured from the default microphone, encoded with Opus, and sent
+//! to everyone else in the room through the relay.
+//!
+//! Audio support pulls in native libraries, so it's only compiled in with the `voice-chat`
+//! feature. Without it, starting voice chat always fails.
+
+#[cfg(feature = "voice-chat")]
+mod audio;
+#[cfg(not(feature = "voice-chat"))]
+mod unavailable;
+
+#[cfg(feature = "voice-chat")]
+pub use audio::VoiceChat;
+#[cfg(not(feature = "voice-chat"))]
+pub use unavailable::VoiceChat;
+
+/// How a single peer's voice is played back.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct VoiceSettings {
+   /// Whether the peer's voice is not played back at all.
+   pub muted: bool,
+   /// The volume the peer's voice is played back at. 1 is the volume it was captured at.
+   pub volume: f32,
+}
+
+impl VoiceSettings {
+   /// The volumes that can be picked for a peer.
+   pub const VOLUMES: [f32; 4] = [0.5, 1.0, 1.5, 2.0];
+
+   /// Returns the volume following the current one in [`VOLUMES`][Self::VOLUMES], wrapping around
+   /// to the first one.
+   pub fn next_volume(&self) -> f32 {
+      Self::VOLUMES.iter().copied().find(|&volume| volume > self.volume).unwrap_or(Self::VOLUMES[0])
+   }
+}
+
+impl Default for VoiceSettings {
+   fn default() -> Self {
+      Self {
+         muted: false,
+         volume: 1.0,
+      }
+   }
+}
@@ -0,0 +1,39 @@
+//! Stand-in for voice chat in builds without audio support.
+
+use std::convert::Infallible;
+
+use netcanv_protocol::relay::PeerId;
+
+use super::VoiceSettings;
+use crate::Error;
+
+/// Voice chat that can never be started.
+pub struct VoiceChat {
+   never: Infallible,
+}
+
+impl VoiceChat {
+   pub fn start() -> netcanv::Result<Self> {
+      Err(Error::VoiceChatUnavailable)
+   }
+
+   pub fn next_frame(&self) -> Option<Vec<u8>> {
+      match self.never {}
+   }
+
+   pub fn receive(&mut self, _peer_id: PeerId, _frame: &[u8]) -> netcanv::Result<()> {
+      match self.never {}
+   }
+
+   pub fn remove_peer(&mut self, _peer_id: PeerId) {
+      match self.never {}
+   }
+
+   pub fn settings(&self, _peer_id: PeerId) -> VoiceSettings {
+      match self.never {}
+   }
+
+   pub fn set_settings(&mut self, _peer_id: PeerId, _settings: VoiceSettings) {
+      match self.never {}
+   }
+}